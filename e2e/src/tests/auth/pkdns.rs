@@ -155,3 +155,189 @@ async fn test_republish_homeserver() {
 
     assert!(ts3 > ts2, "record should be republished when stale");
 }
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn rotated_homeserver_keeps_serving_old_key_and_users_move_to_new_one() {
+    use pubky_testnet::pubky_homeserver::{KeyRotation, MockDataDir};
+
+    let mut testnet = Testnet::new().await.unwrap();
+    let pubky = testnet.sdk().unwrap();
+    let old_key = Keypair::random();
+    let mut data_dir =
+        MockDataDir::new(ConfigToml::default_test_config(), Some(Keypair::random())).unwrap();
+    data_dir.key_rotation = Some(KeyRotation::now(old_key.clone()));
+    let new_key = testnet
+        .create_homeserver_app_with_mock(data_dir)
+        .await
+        .unwrap()
+        .public_key();
+    let published_hosts = |packet: pkarr::SignedPacket| -> Vec<String> {
+        packet
+            .resource_records("_pubky")
+            .filter_map(|record| match &record.rdata {
+                pkarr::dns::rdata::RData::HTTPS(https) => Some(https.0.target.to_string()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    // A client that still knows the old key signs up there: TLS and the
+    // grant's audience use the old key, but the published record names the new one.
+    let signer = pubky.signer(Keypair::random());
+    let user = signer.public_key();
+    signer.signup(&old_key.public_key(), None).await.unwrap();
+    let packet = pubky
+        .client()
+        .pkarr()
+        .resolve(&user, pkarr::ResolvePolicy::NetworkOnly)
+        .await
+        .unwrap();
+    assert_eq!(published_hosts(packet), vec![new_key.z32()]);
+
+    // A record published before the rotation still points at the old key.
+    signer
+        .pkdns()
+        .publish_homeservers(&[old_key.public_key()])
+        .await
+        .unwrap();
+
+    // Lookups follow the successor.
+    assert_eq!(
+        pubky
+            .pkdns()
+            .get_successor_of(&old_key.public_key())
+            .await
+            .unwrap(),
+        Some(new_key.clone())
+    );
+    assert_eq!(
+        pubky.get_homeserver_of(&user).await.unwrap(),
+        Some(new_key.clone())
+    );
+
+    // Signing in republishes the fresh record right away, pointing at the new key.
+    let session = signer
+        .signin_blocking(ClientId::new("rotation.test").unwrap())
+        .await
+        .unwrap();
+    let packet = pubky
+        .client()
+        .pkarr()
+        .resolve(&user, pkarr::ResolvePolicy::NetworkOnly)
+        .await
+        .unwrap();
+    assert_eq!(published_hosts(packet), vec![new_key.z32()]);
+
+    session
+        .storage()
+        .put("/pub/rotated.txt", "still here")
+        .await
+        .unwrap();
+    let bytes = pubky
+        .public_storage()
+        .get_bytes(format!("{user}/pub/rotated.txt"))
+        .await
+        .unwrap();
+    assert_eq!(bytes, b"still here");
+}
+
+// Record updates from two devices of the same user keep each other's records, and
+// never touch the `_pubky` homeserver record.
+#[tokio::test]
+#[pubky_testnet::test]
+async fn record_updates_merge_and_keep_homeserver_record() {
+    use pubky_testnet::pubky::{RecordChanges, RecordData};
+
+    let testnet = build_full_testnet().await;
+    let server = testnet.homeserver_app();
+    let keypair = Keypair::random();
+
+    let signer = testnet.sdk().unwrap().signer(keypair.clone());
+    let pubky = signer.public_key().clone();
+    signer
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+
+    // First device sets a profile record.
+    let pkdns = signer.pkdns();
+    pkdns
+        .update_records(
+            &RecordChanges::new().set("_profile", [RecordData::Txt("name=alice".into())]),
+        )
+        .await
+        .unwrap();
+
+    // Second device, with its own client and cache, adds an apex address.
+    let other = testnet.sdk().unwrap().signer(keypair).pkdns();
+    other
+        .update_records(&RecordChanges::new().add("@", RecordData::A([192, 0, 2, 1].into())))
+        .await
+        .unwrap();
+
+    let records = testnet
+        .sdk()
+        .unwrap()
+        .pkdns()
+        .get_records_of(&pubky)
+        .await
+        .unwrap();
+    let named = |name: &str| -> Vec<RecordData> {
+        records
+            .iter()
+            .filter(|record| record.name == name)
+            .map(|record| record.data.clone())
+            .collect()
+    };
+    assert_eq!(
+        named("_profile"),
+        vec![RecordData::Txt("name=alice".into())]
+    );
+    assert_eq!(named("@"), vec![RecordData::A([192, 0, 2, 1].into())]);
+    assert_eq!(
+        pkdns.get_homeserver_of(&pubky).await.unwrap(),
+        Some(server.public_key())
+    );
+
+    // Changing `_pubky` through the record API is refused.
+    let err = pkdns
+        .update_records(&RecordChanges::new().remove("_pubky"))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::Request(RequestError::Validation { .. })
+    ));
+}
+
+// Users can see how the homeserver's last republish of their record went and
+// republish it on demand, at most once a minute.
+#[tokio::test]
+#[pubky_testnet::test]
+async fn user_queries_republish_status_and_republishes_on_demand() {
+    let testnet = build_full_testnet().await;
+    let server = testnet.homeserver_app();
+    let session = testnet
+        .sdk()
+        .unwrap()
+        .signer(Keypair::random())
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+
+    let status = session.republish_status().await.unwrap();
+    assert_eq!(status.last_outcome, None);
+
+    // Whether enough nodes stored it depends on the test DHT, but it was attempted.
+    let status = session.request_republish().await.unwrap();
+    assert!(status.last_outcome.is_some());
+    assert!(status.last_attempt_at.is_some());
+    assert_eq!(session.republish_status().await.unwrap(), status);
+
+    let err = session.request_republish().await.unwrap_err();
+    assert!(
+        matches!(err, Error::Request(RequestError::Server { status, .. }) if status == StatusCode::TOO_MANY_REQUESTS),
+        "unexpected error: {err:?}"
+    );
+}
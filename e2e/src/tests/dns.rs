@@ -0,0 +1,135 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use pubky_testnet::pubky::pkarr::dns::{
+    rdata::RData, Name, Packet, Question, CLASS, QCLASS, QTYPE, RCODE, TYPE,
+};
+use pubky_testnet::pubky::{Keypair, RecordChanges, RecordData};
+use pubky_testnet::pubky_homeserver::ConfigToml;
+use pubky_testnet::EphemeralTestnet;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+
+fn query(name: &str, qtype: TYPE) -> Vec<u8> {
+    let mut query = Packet::new_query(42);
+    query.questions.push(Question::new(
+        Name::new_unchecked(name),
+        QTYPE::TYPE(qtype),
+        QCLASS::CLASS(CLASS::IN),
+        true,
+    ));
+    query.build_bytes_vec().unwrap()
+}
+
+async fn query_udp(server: SocketAddr, query: &[u8]) -> Vec<u8> {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    socket.send_to(query, server).await.unwrap();
+    let mut buf = vec![0; 4096];
+    let len = socket.recv(&mut buf).await.unwrap();
+    buf.truncate(len);
+    buf
+}
+
+async fn query_tcp(server: SocketAddr, query: &[u8]) -> Vec<u8> {
+    let mut stream = TcpStream::connect(server).await.unwrap();
+    stream.write_u16(query.len() as u16).await.unwrap();
+    stream.write_all(query).await.unwrap();
+    let len = stream.read_u16().await.unwrap();
+    let mut buf = vec![0; len as usize];
+    stream.read_exact(&mut buf).await.unwrap();
+    buf
+}
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn dns_server_answers_pkarr_names() {
+    let mut config = ConfigToml::default_test_config();
+    config.dns.enabled = true;
+    let testnet = EphemeralTestnet::builder()
+        .config(config)
+        .build()
+        .await
+        .unwrap();
+    let server = testnet.homeserver_app();
+    let dns = server
+        .dns_server()
+        .expect("DNS server should be enabled")
+        .listen_socket();
+
+    let signer = testnet.sdk().unwrap().signer(Keypair::random());
+    let user = signer.public_key().z32();
+    signer
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+    signer
+        .pkdns()
+        .update_records(
+            &RecordChanges::new().set("www", [RecordData::A(Ipv4Addr::new(192, 0, 2, 1))]),
+        )
+        .await
+        .unwrap();
+
+    // A record over UDP, from the record the user just published.
+    let response = query_udp(dns, &query(&format!("www.{user}"), TYPE::A)).await;
+    let response = Packet::parse(&response).unwrap();
+    assert_eq!(response.id(), 42);
+    assert_eq!(response.rcode(), RCODE::NoError);
+    assert_eq!(response.answers.len(), 1);
+    assert!(matches!(
+        &response.answers[0].rdata,
+        RData::A(a) if Ipv4Addr::from(a.address) == Ipv4Addr::new(192, 0, 2, 1)
+    ));
+
+    // The homeserver record over TCP.
+    let response = query_tcp(dns, &query(&format!("_pubky.{user}"), TYPE::HTTPS)).await;
+    let response = Packet::parse(&response).unwrap();
+    let targets: Vec<String> = response
+        .answers
+        .iter()
+        .filter_map(|answer| match &answer.rdata {
+            RData::HTTPS(https) => Some(https.0.target.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(targets, vec![server.public_key().z32()]);
+
+    // Unknown names under a key are NXDOMAIN; ICANN names are refused without upstream.
+    let response = query_udp(dns, &query(&format!("missing.{user}"), TYPE::A)).await;
+    assert_eq!(Packet::parse(&response).unwrap().rcode(), RCODE::NameError);
+    let response = query_udp(dns, &query("example.com", TYPE::A)).await;
+    assert_eq!(Packet::parse(&response).unwrap().rcode(), RCODE::Refused);
+}
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn dns_server_rate_limits_udp_per_source() {
+    let mut config = ConfigToml::default_test_config();
+    config.dns.enabled = true;
+    let testnet = EphemeralTestnet::builder()
+        .config(config)
+        .build()
+        .await
+        .unwrap();
+    let dns = testnet
+        .homeserver_app()
+        .dns_server()
+        .expect("DNS server should be enabled")
+        .listen_socket();
+
+    // A burst well above the per-source rate only gets some of its replies.
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let query = query("example.com", TYPE::A);
+    for _ in 0..200 {
+        socket.send_to(&query, dns).await.unwrap();
+    }
+    let mut buf = vec![0; 4096];
+    let mut replies = 0;
+    while let Ok(received) =
+        tokio::time::timeout(std::time::Duration::from_millis(500), socket.recv(&mut buf)).await
+    {
+        received.unwrap();
+        replies += 1;
+    }
+    assert!(replies > 0);
+    assert!(replies < 200, "got {replies} replies");
+}
@@ -1,11 +1,13 @@
 mod admin;
 mod auth;
+mod dns;
 mod events;
 mod http;
 mod metrics;
 mod private_data;
 mod rate_limiting;
 mod storage;
+mod sync;
 
 async fn build_full_testnet() -> pubky_testnet::EphemeralTestnet {
     pubky_testnet::EphemeralTestnet::builder()
@@ -0,0 +1,151 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use super::*;
+use pubky_testnet::pubky::{PubkyHttpClient, PublicKey};
+use pubky_testnet::pubky_homeserver::ConfigToml;
+
+/// Status of `path` of `user` when read from `homeserver` directly.
+async fn status_on(
+    client: &PubkyHttpClient,
+    homeserver: &PublicKey,
+    user: &PublicKey,
+    path: &str,
+) -> StatusCode {
+    client
+        .request(Method::GET, &format!("https://{}{path}", homeserver.z32()))
+        .header("pubky-host", user.z32())
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Trigger a mirror run through the admin API of the mirror.
+async fn run_mirror(client: &PubkyHttpClient, admin_socket: SocketAddr, admin_password: &str) {
+    let response = client
+        .request(Method::POST, &format!("http://{admin_socket}/mirror/run"))
+        .header("X-Admin-Password", admin_password)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+}
+
+/// Wait until reading `path` of `user` from `homeserver` returns `expected`.
+async fn wait_for_status(
+    client: &PubkyHttpClient,
+    homeserver: &PublicKey,
+    user: &PublicKey,
+    path: &str,
+    expected: StatusCode,
+) {
+    for _ in 0..100 {
+        if status_on(client, homeserver, user, path).await == expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{path} never returned {expected} on the mirror");
+}
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn mirror_follows_primary_and_serves_failover_reads() {
+    let mut testnet = build_full_testnet().await;
+    let primary = testnet.homeserver_app().public_key();
+    let mut config = ConfigToml::default_test_config();
+    config.mirror.enabled = true;
+    config.mirror.interval = 0;
+    let admin_password = config.admin.admin_password.clone();
+    let mirror_app = testnet
+        .create_random_homeserver_with_config(Some(config))
+        .await
+        .unwrap();
+    let mirror = mirror_app.public_key();
+    let admin_socket = mirror_app
+        .admin_server()
+        .expect("admin server should be enabled")
+        .listen_socket();
+    let pubky = testnet.sdk().unwrap();
+    let client = testnet.client().unwrap();
+
+    // Opt in: an account on the mirror, listed after the primary.
+    let signer = pubky.signer(Keypair::random());
+    let user = signer.public_key();
+    signer.signup(&mirror, None).await.unwrap();
+    let session = signer.signup_cookie(&primary, None).await.unwrap();
+    signer
+        .pkdns()
+        .publish_homeservers(&[primary.clone(), mirror.clone()])
+        .await
+        .unwrap();
+    assert_eq!(
+        pubky.get_homeservers_of(&user).await.unwrap(),
+        vec![primary.clone(), mirror.clone()]
+    );
+
+    session
+        .storage()
+        .put("/pub/mirrored.txt", "hello mirror")
+        .await
+        .unwrap();
+    session
+        .storage()
+        .put("/pub/deleted.txt", "short-lived")
+        .await
+        .unwrap();
+    run_mirror(&client, admin_socket, &admin_password).await;
+    wait_for_status(&client, &mirror, &user, "/pub/mirrored.txt", StatusCode::OK).await;
+    wait_for_status(&client, &mirror, &user, "/pub/deleted.txt", StatusCode::OK).await;
+
+    session.storage().delete("/pub/deleted.txt").await.unwrap();
+    run_mirror(&client, admin_socket, &admin_password).await;
+    wait_for_status(
+        &client,
+        &mirror,
+        &user,
+        "/pub/deleted.txt",
+        StatusCode::NOT_FOUND,
+    )
+    .await;
+
+    // The primary becomes unreachable: reads fail over to the mirror.
+    let unreachable = Keypair::random().public_key();
+    signer
+        .pkdns()
+        .publish_homeservers(&[unreachable, mirror.clone()])
+        .await
+        .unwrap();
+    let storage = pubky.public_storage();
+    let bytes = storage
+        .get_bytes(format!("{user}/pub/mirrored.txt"))
+        .await
+        .unwrap();
+    assert_eq!(bytes, b"hello mirror");
+    let text = storage
+        .get(format!("{user}/pub/mirrored.txt"))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(text, "hello mirror");
+    assert!(storage
+        .exists(format!("{user}/pub/mirrored.txt"))
+        .await
+        .unwrap());
+    assert!(storage
+        .stats(format!("{user}/pub/mirrored.txt"))
+        .await
+        .unwrap()
+        .is_some());
+    let listed = storage
+        .list(format!("{user}/pub/"))
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].path.as_str(), "/pub/mirrored.txt");
+}
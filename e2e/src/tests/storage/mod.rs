@@ -1,6 +1,7 @@
 mod authorization;
 mod legacy_put_get_delete;
 mod listing;
+mod mirroring;
 mod objects;
 mod quotas;
 
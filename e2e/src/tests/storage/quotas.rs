@@ -136,3 +136,75 @@ async fn put_quota_applied_with_bandwidth_throttling() {
         err
     );
 }
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn storage_usage_breakdown() {
+    let mut testnet = Testnet::new().await.unwrap();
+    let pubky = testnet.sdk().unwrap();
+
+    let mut mock_dir = MockDataDir::test();
+    mock_dir.config_toml.storage.default_quota_mb = Some(1); // 1 MB
+    let server = testnet
+        .create_homeserver_app_with_mock(mock_dir)
+        .await
+        .unwrap();
+
+    let signer = pubky.signer(Keypair::random());
+    let session = signer
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+
+    session
+        .storage()
+        .put("/pub/photos.app/a.jpg", vec![0; 1000])
+        .await
+        .unwrap();
+    session
+        .storage()
+        .put("/pub/notes.app/b.txt", vec![0; 10])
+        .await
+        .unwrap();
+
+    let usage = session.storage_usage().await.unwrap();
+    assert_eq!(usage.limit_bytes, Some(1024 * 1024));
+    assert_eq!(usage.directories.len(), 1);
+    let public = &usage.directories[0];
+    assert_eq!(public.path, "/pub/");
+    assert_eq!(public.file_count, 2);
+    assert_eq!(public.used_bytes, usage.used_bytes);
+    let apps: Vec<&str> = public.apps.iter().map(|app| app.path.as_str()).collect();
+    assert_eq!(apps, vec!["/pub/notes.app/", "/pub/photos.app/"]);
+    assert!(public.apps[1].used_bytes > 1000);
+}
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn cookie_session_reports_transfer_usage() {
+    let mut testnet = Testnet::new().await.unwrap();
+    let pubky = testnet.sdk().unwrap();
+
+    let mut mock_dir = MockDataDir::test();
+    mock_dir.config_toml.default_quotas.ingress_daily_mb = Some(1);
+    let server = testnet
+        .create_homeserver_app_with_mock(mock_dir)
+        .await
+        .unwrap();
+
+    let signer = pubky.signer(Keypair::random());
+    let session = signer
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+    session
+        .storage()
+        .put("/pub/usage.app/a.txt", vec![0; 1000])
+        .await
+        .unwrap();
+
+    let usage = session.as_cookie().unwrap().usage().await.unwrap();
+    assert_eq!(usage.ingress_daily.limit_bytes, Some(1024 * 1024));
+    assert!(usage.ingress_daily.used_bytes >= 1000);
+    assert_eq!(usage.ingress_monthly.limit_bytes, None);
+}
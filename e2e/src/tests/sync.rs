@@ -0,0 +1,338 @@
+use super::build_full_testnet;
+use pubky_testnet::pubky::{
+    errors::SyncError, ConflictResolution, Error, FileReplicaStore, Keypair, Method,
+    PubkyHttpClient, PubkySession, StatusCode, SyncEngine,
+};
+use pubky_testnet::pubky_common::crypto::hash;
+use std::path::PathBuf;
+
+fn replica_dir() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "pubky-sync-{}",
+        Keypair::random().public_key().z32()
+    ))
+}
+
+async fn open_engine(session: &PubkySession, directory: &PathBuf) -> SyncEngine {
+    SyncEngine::builder(session, FileReplicaStore::new(directory).unwrap())
+        .prefix("/pub/app/")
+        .open()
+        .await
+        .unwrap()
+}
+
+async fn remote_text(session: &PubkySession, path: &str) -> String {
+    session
+        .storage()
+        .get(path)
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn pushes_local_writes_and_pulls_remote_changes() {
+    let testnet = build_full_testnet().await;
+    let server = testnet.homeserver_app();
+    let pubky = testnet.sdk().unwrap();
+    let session = pubky
+        .signer(Keypair::random())
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+    let storage = session.storage();
+    storage
+        .put("/pub/app/existing.txt", "remote")
+        .await
+        .unwrap();
+    storage
+        .put("/pub/other/skipped.txt", "other")
+        .await
+        .unwrap();
+
+    let directory = replica_dir();
+    let engine = open_engine(&session, &directory).await;
+
+    // The first pull lists the prefix.
+    let report = engine.pull().await.unwrap();
+    assert_eq!(report.pulled, vec!["/pub/app/existing.txt".to_string()]);
+    assert_eq!(
+        engine.get("/pub/app/existing.txt").await.unwrap().unwrap(),
+        b"remote"
+    );
+    assert!(engine.cursor().await.is_some());
+    assert_eq!(
+        engine.list().await,
+        vec!["/pub/app/existing.txt".to_string()]
+    );
+
+    // Local writes are queued until pushed.
+    engine.put("/pub/app/notes.txt", b"local").await.unwrap();
+    assert!(!storage.exists("/pub/app/notes.txt").await.unwrap());
+    assert_eq!(
+        engine.pending().await,
+        vec!["/pub/app/notes.txt".to_string()]
+    );
+    let error = engine
+        .put("/pub/other/notes.txt", b"local")
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::Sync(SyncError::OutsideReplica { .. })
+    ));
+
+    let report = engine.push().await.unwrap();
+    assert_eq!(report.pushed, vec!["/pub/app/notes.txt".to_string()]);
+    assert!(engine.pending().await.is_empty());
+    assert_eq!(remote_text(&session, "/pub/app/notes.txt").await, "local");
+
+    // Our own pushed write comes back as an event but changes nothing.
+    let report = engine.pull().await.unwrap();
+    assert!(report.pulled.is_empty());
+
+    // Remote updates, creations and deletions are pulled from events.
+    storage
+        .put("/pub/app/existing.txt", "changed")
+        .await
+        .unwrap();
+    storage.put("/pub/app/new.txt", "new").await.unwrap();
+    storage.delete("/pub/app/notes.txt").await.unwrap();
+    let report = engine.pull().await.unwrap();
+    assert_eq!(report.pulled.len(), 3);
+    assert_eq!(
+        engine.get("/pub/app/existing.txt").await.unwrap().unwrap(),
+        b"changed"
+    );
+    assert_eq!(
+        engine.get("/pub/app/new.txt").await.unwrap().unwrap(),
+        b"new"
+    );
+    assert_eq!(engine.get("/pub/app/notes.txt").await.unwrap(), None);
+
+    // Local deletions are pushed too.
+    engine.delete("/pub/app/new.txt").await.unwrap();
+    let report = engine.sync().await.unwrap();
+    assert_eq!(report.pushed, vec!["/pub/app/new.txt".to_string()]);
+    assert!(!storage.exists("/pub/app/new.txt").await.unwrap());
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn concurrent_changes_become_conflicts() {
+    let testnet = build_full_testnet().await;
+    let server = testnet.homeserver_app();
+    let pubky = testnet.sdk().unwrap();
+    let session = pubky
+        .signer(Keypair::random())
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+    let storage = session.storage();
+    let path = "/pub/app/doc.txt";
+
+    let directory = replica_dir();
+    let engine = open_engine(&session, &directory).await;
+    engine.put(path, b"v1").await.unwrap();
+    engine.sync().await.unwrap();
+
+    // Changed on both sides: the push detects it from the content hash.
+    engine.put(path, b"local v2").await.unwrap();
+    storage.put(path, "remote v2").await.unwrap();
+    let report = engine.push().await.unwrap();
+    assert!(report.pushed.is_empty());
+    assert_eq!(report.conflicts, vec![path.to_string()]);
+    assert_eq!(remote_text(&session, path).await, "remote v2");
+    let conflicts = engine.conflicts().await;
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].local, Some(hash(b"local v2")));
+    assert_eq!(conflicts[0].remote, Some(hash(b"remote v2")));
+
+    // Pulling the remote change keeps the conflict and the local version.
+    engine.pull().await.unwrap();
+    assert_eq!(engine.conflicts().await.len(), 1);
+    assert_eq!(engine.get(path).await.unwrap().unwrap(), b"local v2");
+
+    engine
+        .resolve_conflict(path, ConflictResolution::KeepLocal)
+        .await
+        .unwrap();
+    let report = engine.sync().await.unwrap();
+    assert_eq!(report.pushed, vec![path.to_string()]);
+    assert_eq!(remote_text(&session, path).await, "local v2");
+
+    // Changed on both sides again, this time detected while pulling.
+    engine.put(path, b"local v3").await.unwrap();
+    storage.put(path, "remote v3").await.unwrap();
+    let report = engine.pull().await.unwrap();
+    assert_eq!(report.conflicts, vec![path.to_string()]);
+
+    engine
+        .resolve_conflict(path, ConflictResolution::KeepRemote)
+        .await
+        .unwrap();
+    assert!(engine.conflicts().await.is_empty());
+    assert!(engine.pending().await.is_empty());
+    assert_eq!(engine.get(path).await.unwrap().unwrap(), b"remote v3");
+
+    let error = engine
+        .resolve_conflict(path, ConflictResolution::KeepLocal)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Sync(SyncError::NoConflict { .. })));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn queued_writes_survive_reopening() {
+    let testnet = build_full_testnet().await;
+    let server = testnet.homeserver_app();
+    let pubky = testnet.sdk().unwrap();
+    let session = pubky
+        .signer(Keypair::random())
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+
+    let directory = replica_dir();
+    let engine = open_engine(&session, &directory).await;
+    engine.put("/pub/app/offline.txt", b"queued").await.unwrap();
+
+    // Only one engine at a time may open a replica.
+    let error = SyncEngine::builder(&session, FileReplicaStore::new(&directory).unwrap())
+        .prefix("/pub/app/")
+        .open()
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Sync(SyncError::Store { .. })));
+    drop(engine);
+
+    let engine = open_engine(&session, &directory).await;
+    assert_eq!(
+        engine.pending().await,
+        vec!["/pub/app/offline.txt".to_string()]
+    );
+    assert_eq!(
+        engine.get("/pub/app/offline.txt").await.unwrap().unwrap(),
+        b"queued"
+    );
+    engine.sync().await.unwrap();
+    assert_eq!(
+        remote_text(&session, "/pub/app/offline.txt").await,
+        "queued"
+    );
+    drop(engine);
+
+    // A replica belongs to one user.
+    let other = pubky
+        .signer(Keypair::random())
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+    let error = SyncEngine::builder(&other, FileReplicaStore::new(&directory).unwrap())
+        .prefix("/pub/app/")
+        .open()
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Sync(SyncError::Store { .. })));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[tokio::test]
+#[pubky_testnet::test]
+async fn pull_lists_again_after_events_were_pruned() {
+    let mut config = pubky_testnet::pubky_homeserver::ConfigToml::default_test_config();
+    config.events.retention_max_per_user = Some(std::num::NonZeroU64::new(1).unwrap());
+    config.events.retention_interval = 0;
+    let admin_password = config.admin.admin_password.clone();
+    let testnet = pubky_testnet::EphemeralTestnet::builder()
+        .config(config)
+        .build()
+        .await
+        .unwrap();
+    let server = testnet.homeserver_app();
+    let admin = format!(
+        "http://{}",
+        server
+            .admin_server()
+            .expect("admin server should be enabled")
+            .listen_socket()
+    );
+    let prune = || async {
+        let http = PubkyHttpClient::new().unwrap();
+        let ran_before = retention_runs(&http, &admin, &admin_password).await;
+        let response = http
+            .request(Method::POST, &format!("{admin}/events/retention/run"))
+            .header("X-Admin-Password", &admin_password)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        for _ in 0..100 {
+            if retention_runs(&http, &admin, &admin_password).await != ran_before {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        panic!("retention run did not finish");
+    };
+
+    let session = testnet
+        .sdk()
+        .unwrap()
+        .signer(Keypair::random())
+        .signup_cookie(&server.public_key(), None)
+        .await
+        .unwrap();
+    let storage = session.storage();
+    storage.put("/pub/app/a.txt", "a").await.unwrap();
+    let directory = replica_dir();
+    let engine = open_engine(&session, &directory).await;
+    engine.pull().await.unwrap();
+
+    // The event of b.txt is pruned before the replica saw it.
+    storage.put("/pub/app/b.txt", "b").await.unwrap();
+    storage.put("/pub/other/c.txt", "c").await.unwrap();
+    prune().await;
+    engine.put("/pub/app/local.txt", b"local").await.unwrap();
+
+    let report = engine.pull().await.unwrap();
+    assert_eq!(report.pulled, vec!["/pub/app/b.txt".to_string()]);
+    assert_eq!(engine.get("/pub/app/b.txt").await.unwrap().unwrap(), b"b");
+    assert_eq!(
+        engine.pending().await,
+        vec!["/pub/app/local.txt".to_string()]
+    );
+
+    // No event under the prefix is retained anymore, yet later pulls follow the feed.
+    storage.put("/pub/other/d.txt", "d").await.unwrap();
+    prune().await;
+    assert_eq!(engine.pull().await.unwrap(), Default::default());
+    storage.put("/pub/app/e.txt", "e").await.unwrap();
+    let report = engine.pull().await.unwrap();
+    assert_eq!(report.pulled, vec!["/pub/app/e.txt".to_string()]);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+/// When the last retention run finished, from the admin API.
+async fn retention_runs(http: &PubkyHttpClient, admin: &str, password: &str) -> serde_json::Value {
+    let status: serde_json::Value = http
+        .request(Method::GET, &format!("{admin}/events/retention"))
+        .header("X-Admin-Password", password)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    status["job"]["last_finished_at"].clone()
+}
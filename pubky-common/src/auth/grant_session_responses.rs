@@ -67,6 +67,38 @@ pub struct GrantSessionInfo {
     pub grant_expires_at: u64,
     /// When this session was created (Unix seconds).
     pub created_at: u64,
+    /// The user's usage against their request and transfer caps.
+    ///
+    /// Only returned by `GET /auth/grant/session`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<SessionUsage>,
+}
+
+/// A user's usage against the per-user request and transfer caps.
+///
+/// Days and months are UTC. Caps are checked before a request starts, so usage
+/// may slightly exceed a limit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionUsage {
+    /// Maximum number of requests per minute, `None` if unlimited.
+    pub requests_per_minute: Option<u64>,
+    /// Bytes downloaded today.
+    pub egress_daily: TransferUsage,
+    /// Bytes downloaded this month.
+    pub egress_monthly: TransferUsage,
+    /// Bytes uploaded today.
+    pub ingress_daily: TransferUsage,
+    /// Bytes uploaded this month.
+    pub ingress_monthly: TransferUsage,
+}
+
+/// Transferred bytes in a period and the cap that applies to them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferUsage {
+    /// Bytes transferred so far.
+    pub used_bytes: u64,
+    /// Cap in bytes, `None` if unlimited.
+    pub limit_bytes: Option<u64>,
 }
 
 #[cfg(test)]
@@ -91,6 +123,7 @@ mod tests {
                 token_expires_at: 1700003600,
                 grant_expires_at: 1763136000,
                 created_at: 1700000000,
+                usage: None,
             },
         };
 
@@ -18,6 +18,9 @@ pub const GRANT_JWS_TYP: &str = "pubky-grant";
 /// JWS header `typ` for Proof-of-Possession proofs.
 pub const POP_JWS_TYP: &str = "pubky-pop";
 
+/// JWS header `typ` for signed admin API requests.
+pub const ADMIN_JWS_TYP: &str = "pubky-admin";
+
 /// Maximum length for a [`RandomId`] generated from 128-bit random bytes as base64url.
 const RANDOM_ID_MAX_LENGTH: usize = 22;
 
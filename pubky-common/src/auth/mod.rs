@@ -1,5 +1,6 @@
 //! Authentication types shared between homeserver and SDK.
 
+pub mod admin;
 mod auth_token;
 pub mod grant;
 pub mod grant_session_responses;
@@ -7,6 +7,13 @@ pub mod reserved_param_keys {
     pub const HTTP_PORT: u16 = 65280;
 }
 
+/// Names of records in a homeserver's own pkarr packet.
+pub mod homeserver_records {
+    /// TXT record published with a homeserver's previous key after a key rotation.
+    /// Its value is the z32 public key that replaced it.
+    pub const SUCCESSOR: &str = "_successor";
+}
+
 /// Pubky storage namespace roots.
 pub mod storage {
     /// Storage root for private data.
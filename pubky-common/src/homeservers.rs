@@ -0,0 +1,29 @@
+//! Homeservers listed in a user's pkarr packet.
+//!
+//! Shared between homeserver (mirroring, republishing) and SDK (resolution).
+
+use pkarr::{dns::rdata::RData, SignedPacket};
+
+/// Extract all `_pubky` SVCB/HTTPS targets from a signed Pkarr packet, ordered by priority.
+///
+/// Records with the same priority keep their order in the packet; duplicate targets are
+/// only listed once.
+pub fn extract_hosts_from_packet(packet: &SignedPacket) -> Vec<String> {
+    let mut targets: Vec<(u16, String)> = packet
+        .resource_records("_pubky")
+        .filter_map(|rr| match &rr.rdata {
+            RData::SVCB(svcb) => Some((svcb.priority, svcb.target.to_string())),
+            RData::HTTPS(https) => Some((https.0.priority, https.0.target.to_string())),
+            _ => None,
+        })
+        .collect();
+    targets.sort_by_key(|(priority, _)| *priority);
+
+    let mut hosts: Vec<String> = Vec::with_capacity(targets.len());
+    for (_, target) in targets {
+        if !hosts.contains(&target) {
+            hosts.push(target);
+        }
+    }
+    hosts
+}
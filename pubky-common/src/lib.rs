@@ -11,10 +11,13 @@ pub mod capabilities;
 pub mod constants;
 pub mod crypto;
 pub mod events;
+pub mod homeservers;
 mod keys;
 pub mod namespaces;
 pub mod recovery_file;
+pub mod republish_status;
 pub mod session;
+pub mod storage_usage;
 
 pub mod timestamp {
     //! Timestamp used across Pubky crates.
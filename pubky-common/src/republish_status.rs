@@ -0,0 +1,121 @@
+//! Health of a user's pkarr record as republished by their homeserver.
+//!
+//! Returned by the homeserver's `GET /pkdns/republish`. Shared between homeserver
+//! (serializes) and SDK (deserializes).
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Result of the last attempt to republish a user's pkarr record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepublishOutcome {
+    /// The record was republished to enough DHT nodes.
+    Published,
+    /// The record does not list the homeserver, so it was left alone.
+    Skipped,
+    /// No record was found on the DHT.
+    Missing,
+    /// The record found on the DHT is not a valid signed packet.
+    InvalidPacket,
+    /// The record could not be republished, e.g. because too few nodes stored it.
+    Failed,
+}
+
+impl RepublishOutcome {
+    /// All outcomes, in declaration order.
+    pub const ALL: [Self; 5] = [
+        Self::Published,
+        Self::Skipped,
+        Self::Missing,
+        Self::InvalidPacket,
+        Self::Failed,
+    ];
+
+    /// Name of the outcome, as used in JSON.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Published => "published",
+            Self::Skipped => "skipped",
+            Self::Missing => "missing",
+            Self::InvalidPacket => "invalid_packet",
+            Self::Failed => "failed",
+        }
+    }
+
+    /// Whether the outcome needs attention: the record is missing, invalid or failed to publish.
+    pub fn is_issue(&self) -> bool {
+        matches!(self, Self::Missing | Self::InvalidPacket | Self::Failed)
+    }
+}
+
+impl fmt::Display for RepublishOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Error parsing a [`RepublishOutcome`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown republish outcome: {0}")]
+pub struct RepublishOutcomeParseError(String);
+
+impl FromStr for RepublishOutcome {
+    type Err = RepublishOutcomeParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|outcome| outcome.as_str() == s)
+            .ok_or_else(|| RepublishOutcomeParseError(s.to_string()))
+    }
+}
+
+/// Republish health of a user's pkarr record.
+///
+/// All fields are `None` until the homeserver attempted to republish the record.
+/// Timestamps are Unix seconds.
+///
+/// # JSON representation
+/// ```json
+/// {
+///   "last_outcome": "failed",
+///   "last_attempt_at": 1700003600,
+///   "last_success_at": 1700000000,
+///   "last_failure_at": 1700003600,
+///   "last_failure_reason": "packet was published to only 3 nodes",
+///   "record_timestamp": 1699990000
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepublishStatus {
+    /// Result of the last attempt.
+    pub last_outcome: Option<RepublishOutcome>,
+    /// When the record was last attempted to be republished.
+    pub last_attempt_at: Option<u64>,
+    /// When the record was last republished successfully.
+    pub last_success_at: Option<u64>,
+    /// When republishing last failed, or the record was last found missing or invalid.
+    pub last_failure_at: Option<u64>,
+    /// Why republishing last failed. Kept after later successes.
+    pub last_failure_reason: Option<String>,
+    /// When the record last found on the DHT was signed. Together with
+    /// `last_success_at`, tells how old the record on the DHT is.
+    pub record_timestamp: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outcome_names_roundtrip() {
+        for outcome in RepublishOutcome::ALL {
+            let json = serde_json::to_value(outcome).unwrap();
+            assert_eq!(json, outcome.as_str());
+            assert_eq!(outcome.as_str().parse::<RepublishOutcome>(), Ok(outcome));
+        }
+        assert!("unknown".parse::<RepublishOutcome>().is_err());
+    }
+}
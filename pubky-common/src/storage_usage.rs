@@ -0,0 +1,91 @@
+//! Storage usage breakdown of a user.
+//!
+//! Returned by the homeserver's `GET /quota/usage`. Shared between homeserver
+//! (serializes) and SDK (deserializes).
+
+use serde::{Deserialize, Serialize};
+
+/// A user's storage usage, broken down by directory and app.
+///
+/// Byte counts include the homeserver's per-file metadata overhead, so they add
+/// up to what is counted against the quotas.
+///
+/// # JSON representation
+/// ```json
+/// {
+///   "used_bytes": 3072,
+///   "limit_bytes": 104857600,
+///   "directories": [
+///     {
+///       "path": "/pub/",
+///       "used_bytes": 3072,
+///       "file_count": 2,
+///       "last_modified": 1700000000,
+///       "limit_bytes": null,
+///       "apps": [{ "path": "/pub/photos-app/", "used_bytes": 2048, ... }]
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageUsage {
+    /// Total storage used by the user in bytes.
+    pub used_bytes: u64,
+    /// Effective total storage limit in bytes, `None` if unlimited.
+    pub limit_bytes: Option<u64>,
+    /// Usage per top-level directory (e.g. `/pub/`, `/priv/`), ordered by path.
+    pub directories: Vec<PrefixUsage>,
+}
+
+/// Storage used below a path prefix.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefixUsage {
+    /// Directory prefix, ending with `/`.
+    pub path: String,
+    /// Storage used below `path` in bytes.
+    pub used_bytes: u64,
+    /// Number of files below `path`.
+    pub file_count: u64,
+    /// When a file below `path` was last written (Unix seconds).
+    pub last_modified: u64,
+    /// Path quota set for exactly this prefix in bytes, `None` if there is none.
+    pub limit_bytes: Option<u64>,
+    /// Usage per app directory below a top-level directory (e.g. `/pub/photos-app/`),
+    /// ordered by path. Files directly in the top-level directory belong to no app.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub apps: Vec<PrefixUsage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_roundtrip_omits_empty_apps() {
+        let usage = StorageUsage {
+            used_bytes: 3072,
+            limit_bytes: None,
+            directories: vec![PrefixUsage {
+                path: "/pub/".into(),
+                used_bytes: 3072,
+                file_count: 2,
+                last_modified: 1700000000,
+                limit_bytes: None,
+                apps: vec![PrefixUsage {
+                    path: "/pub/photos-app/".into(),
+                    used_bytes: 2048,
+                    file_count: 1,
+                    last_modified: 1700000000,
+                    limit_bytes: Some(1024 * 1024),
+                    apps: vec![],
+                }],
+            }],
+        };
+
+        let json = serde_json::to_value(&usage).unwrap();
+        assert!(json["directories"][0]["apps"][0].get("apps").is_none());
+
+        let decoded: StorageUsage = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, usage);
+    }
+}
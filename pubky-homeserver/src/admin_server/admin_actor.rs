@@ -0,0 +1,46 @@
+//! Axum extractor naming the operator behind an admin request, for audit trails.
+//!
+//! Admins authenticating with a key are named by their public key. Admins
+//! sharing the admin password self-report through the optional
+//! `X-Admin-Actor` header, falling back to [`DEFAULT_ACTOR`].
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use std::convert::Infallible;
+
+use super::admin_auth::AdminIdentity;
+
+const ACTOR_HEADER: &str = "X-Admin-Actor";
+/// Recorded when the request does not name an actor.
+const DEFAULT_ACTOR: &str = "admin";
+/// Longer names are truncated.
+const MAX_ACTOR_LEN: usize = 128;
+
+/// Name of the admin performing a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AdminActor(pub String);
+
+impl<S> FromRequestParts<S> for AdminActor
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let signed_by = parts
+            .extensions
+            .get::<AdminIdentity>()
+            .and_then(|identity| identity.public_key.as_ref());
+        if let Some(public_key) = signed_by {
+            return Ok(Self(public_key.z32()));
+        }
+        let actor = parts
+            .headers
+            .get(ACTOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map_or(DEFAULT_ACTOR, |value| value);
+        Ok(Self(actor.chars().take(MAX_ACTOR_LEN).collect()))
+    }
+}
@@ -0,0 +1,482 @@
+//! Authentication and role checks for the admin API.
+//!
+//! Admins authenticate either with the shared `X-Admin-Password` header, which
+//! grants the [`AdminRole::Admin`] role, or with an `Authorization: Bearer` JWS
+//! of [`AdminRequestClaims`] signed by a key listed under `[[admin.keys]]`.
+//! A signed request is bound to this homeserver, the request method, path and
+//! query, the hash of its body, and a single-use nonce, so a leaked header cannot
+//! be replayed or attached to another body.
+
+use std::collections::HashMap;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{HeaderMap, Method, StatusCode, Uri},
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use pubky_common::{
+    auth::{
+        admin::AdminRequestClaims,
+        jws::{decode_jws_payload, ADMIN_JWS_TYP},
+    },
+    crypto::PublicKey,
+};
+use subtle::ConstantTimeEq;
+
+use crate::client_server::auth::grant::{
+    crypto::{
+        jws_crypto,
+        pop_verifier::{POP_MAX_AGE_SECS, POP_NONCE_GC_THRESHOLD_SECS},
+    },
+    persistence::pop_nonce::{PopNonceError, PopNonceRepository},
+};
+use crate::persistence::sql::SqlDb;
+use crate::shared::HomeserverKeys;
+use crate::{AdminRole, AdminToml};
+
+const PASSWORD_HEADER: &str = "X-Admin-Password";
+
+/// Largest request body the admin API buffers to check its signed hash.
+/// Admin routes only take small JSON bodies.
+pub(crate) const MAX_ADMIN_BODY_BYTES: usize = 1024 * 1024;
+
+/// The authenticated caller of an admin route, stored in the request extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AdminIdentity {
+    pub role: AdminRole,
+    /// The admin key for signed requests, `None` for password auth.
+    pub public_key: Option<PublicKey>,
+}
+
+/// Why an admin request was rejected with `401`.
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum AdminAuthError {
+    #[error("Missing admin credentials")]
+    Missing,
+    #[error("Invalid admin password")]
+    InvalidPassword,
+    #[error("Invalid admin request signature")]
+    InvalidSignature,
+    #[error("Unknown admin key")]
+    UnknownKey,
+    #[error("Admin request was signed for another homeserver")]
+    AudienceMismatch,
+    #[error("Admin request was signed for another method or path")]
+    RequestMismatch,
+    #[error("Admin request was signed for another body")]
+    BodyMismatch,
+    #[error("Admin request body too large")]
+    BodyTooLarge,
+    #[error("Admin request timestamp out of range")]
+    TimestampOutOfRange,
+    #[error("Admin request nonce already used")]
+    NonceReplay,
+    #[error("Failed to track admin request nonce")]
+    Internal(#[source] sqlx::Error),
+}
+
+impl AdminAuthError {
+    fn status(&self) -> StatusCode {
+        match self {
+            AdminAuthError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AdminAuthError::BodyTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    pub fn into_response(self) -> Response {
+        if let AdminAuthError::Internal(e) = &self {
+            tracing::error!("Admin authentication failed: {e}");
+        }
+        let msg = self.to_string();
+        Response::builder()
+            .status(self.status())
+            .body(Body::from(msg.clone()))
+            .unwrap_or_else(|_| Response::new(Body::from(msg)))
+    }
+}
+
+/// Verifies admin credentials against the `[admin]` config.
+pub(crate) struct AdminAuthenticator {
+    /// `None` if password authentication is disabled.
+    password: Option<String>,
+    keys: HashMap<PublicKey, AdminRole>,
+    homeserver: HomeserverKeys,
+    sql_db: SqlDb,
+}
+
+impl AdminAuthenticator {
+    pub fn new(config: &AdminToml, homeserver: HomeserverKeys, sql_db: SqlDb) -> Self {
+        Self {
+            password: Some(config.admin_password.clone()).filter(|p| !p.is_empty()),
+            keys: config
+                .keys
+                .iter()
+                .map(|key| (key.public_key.clone(), key.role))
+                .collect(),
+            homeserver,
+            sql_db,
+        }
+    }
+
+    /// Authenticate a request from its method, path with query, headers and body.
+    ///
+    /// A bearer JWS takes precedence over the password header.
+    pub async fn authenticate(
+        &self,
+        method: &Method,
+        path_and_query: &str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<AdminIdentity, AdminAuthError> {
+        if let Some(token) = bearer_token(headers) {
+            return self
+                .verify_signed(token, method, path_and_query, body)
+                .await;
+        }
+        match headers.get(PASSWORD_HEADER) {
+            Some(value) => self.verify_password(value.to_str().unwrap_or("")),
+            None => Err(AdminAuthError::Missing),
+        }
+    }
+
+    /// The shared admin password, `None` if password authentication is disabled.
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    /// Check the shared admin password, in constant time.
+    fn verify_password(&self, password: &str) -> Result<AdminIdentity, AdminAuthError> {
+        let matches =
+            |expected: &String| bool::from(expected.as_bytes().ct_eq(password.as_bytes()));
+        match &self.password {
+            Some(expected) if matches(expected) => Ok(AdminIdentity {
+                role: AdminRole::Admin,
+                public_key: None,
+            }),
+            _ => Err(AdminAuthError::InvalidPassword),
+        }
+    }
+
+    async fn verify_signed(
+        &self,
+        token: &str,
+        method: &Method,
+        path_and_query: &str,
+        body: &[u8],
+    ) -> Result<AdminIdentity, AdminAuthError> {
+        let (claims, role) = self.verify_claims(token)?;
+        if !self.homeserver.accepted().contains(&claims.aud) {
+            return Err(AdminAuthError::AudienceMismatch);
+        }
+        if claims.htm != method.as_str() || claims.htu != path_and_query {
+            return Err(AdminAuthError::RequestMismatch);
+        }
+        if claims.bh != AdminRequestClaims::body_hash(body) {
+            return Err(AdminAuthError::BodyMismatch);
+        }
+        if (Utc::now().timestamp() as u64).abs_diff(claims.iat) > POP_MAX_AGE_SECS {
+            return Err(AdminAuthError::TimestampOutOfRange);
+        }
+        self.check_nonce_replay(&claims).await?;
+        Ok(AdminIdentity {
+            role,
+            public_key: Some(claims.iss),
+        })
+    }
+
+    /// Check the JWS type and that it is signed by the configured key it names.
+    fn verify_claims(
+        &self,
+        token: &str,
+    ) -> Result<(AdminRequestClaims, AdminRole), AdminAuthError> {
+        let header =
+            jsonwebtoken::decode_header(token).map_err(|_| AdminAuthError::InvalidSignature)?;
+        if header.typ.as_deref() != Some(ADMIN_JWS_TYP) {
+            return Err(AdminAuthError::InvalidSignature);
+        }
+        let unverified: AdminRequestClaims =
+            decode_jws_payload(token).map_err(|_| AdminAuthError::InvalidSignature)?;
+        let role = *self
+            .keys
+            .get(&unverified.iss)
+            .ok_or(AdminAuthError::UnknownKey)?;
+        let decoding_key = jws_crypto::decoding_key(&unverified.iss);
+        let token_data = jsonwebtoken::decode::<AdminRequestClaims>(
+            token,
+            &decoding_key,
+            &jws_crypto::eddsa_validation(),
+        )
+        .map_err(|_| AdminAuthError::InvalidSignature)?;
+        Ok((token_data.claims, role))
+    }
+
+    /// Reject replayed nonces. Shares the PoP nonce table with client grants.
+    async fn check_nonce_replay(&self, claims: &AdminRequestClaims) -> Result<(), AdminAuthError> {
+        if let Err(e) = PopNonceRepository::garbage_collect(
+            POP_NONCE_GC_THRESHOLD_SECS,
+            &mut self.sql_db.pool().into(),
+        )
+        .await
+        {
+            tracing::warn!("PoP nonce garbage collection failed: {e}");
+        }
+        PopNonceRepository::check_and_track(&claims.nonce, &mut self.sql_db.pool().into())
+            .await
+            .map_err(|e| match e {
+                PopNonceError::AlreadyUsed => AdminAuthError::NonceReplay,
+                PopNonceError::Internal(e) => AdminAuthError::Internal(e),
+            })
+    }
+}
+
+/// The path and query of `uri`, as covered by the `htu` claim of signed requests.
+pub(crate) fn path_and_query(uri: &Uri) -> &str {
+    uri.path_and_query()
+        .map_or_else(|| uri.path(), |path_and_query| path_and_query.as_str())
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Route layer rejecting callers below `role` with `403`.
+///
+/// Must run inside [`super::auth_middleware::AdminAuthLayer`].
+pub(crate) async fn require_role(
+    State(role): State<AdminRole>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let allowed = request
+        .extensions()
+        .get::<AdminIdentity>()
+        .is_some_and(|identity| identity.role >= role);
+    if !allowed {
+        let msg = "Admin role not allowed to call this route";
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(Body::from(msg))
+            .unwrap_or_else(|_| Response::new(Body::from(msg)));
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::{header::AUTHORIZATION, StatusCode};
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::admin_server::app_state::AppState;
+    use crate::{AdminKeyToml, AppContext};
+
+    fn signed(
+        context: &AppContext,
+        admin: &Keypair,
+        method: &str,
+        path: &str,
+    ) -> (AdminRequestClaims, String) {
+        signed_with_body(context, admin, method, path, b"")
+    }
+
+    fn signed_with_body(
+        context: &AppContext,
+        admin: &Keypair,
+        method: &str,
+        path: &str,
+        body: &[u8],
+    ) -> (AdminRequestClaims, String) {
+        let claims = AdminRequestClaims::new(
+            &admin.public_key(),
+            &context.keypair.public_key(),
+            method,
+            path,
+            body,
+        );
+        let token = format!("Bearer {}", claims.sign(admin));
+        (claims, token)
+    }
+
+    async fn context_with_keys(keys: &[(&Keypair, AdminRole)]) -> Arc<AppContext> {
+        let keys: Vec<AdminKeyToml> = keys
+            .iter()
+            .map(|(keypair, role)| AdminKeyToml {
+                public_key: keypair.public_key(),
+                role: *role,
+            })
+            .collect();
+        AppContext::test_with_config(|config| {
+            config.admin.admin_password = String::new();
+            config.admin.keys = keys;
+        })
+        .await
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_signed_requests_are_restricted_by_role() {
+        let read_only = Keypair::random();
+        let moderator = Keypair::random();
+        let context = context_with_keys(&[
+            (&read_only, AdminRole::ReadOnly),
+            (&moderator, AdminRole::Moderator),
+        ])
+        .await;
+        let server = AppState::test_server(&context);
+
+        let (_, token) = signed(&context, &read_only, "GET", "/blocklist");
+        server
+            .get("/blocklist")
+            .add_header(AUTHORIZATION, token)
+            .expect_success()
+            .await;
+        let block = serde_json::json!({ "path_glob": "*/pub/spam.app/**" });
+        let body = serde_json::to_vec(&block).unwrap();
+        let (_, token) = signed_with_body(&context, &read_only, "POST", "/blocklist", &body);
+        server
+            .post("/blocklist")
+            .add_header(AUTHORIZATION, token)
+            .json(&block)
+            .expect_failure()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        // The moderator is recorded by key, whatever actor it claims.
+        let (_, token) = signed_with_body(&context, &moderator, "POST", "/blocklist", &body);
+        let block: serde_json::Value = server
+            .post("/blocklist")
+            .add_header(AUTHORIZATION, token)
+            .add_header("X-Admin-Actor", "someone-else")
+            .json(&block)
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(block["created_by"], moderator.public_key().z32());
+        let (_, token) = signed(&context, &moderator, "GET", "/generate_signup_token");
+        server
+            .get("/generate_signup_token")
+            .add_header(AUTHORIZATION, token)
+            .expect_failure()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+
+        // Password authentication is disabled by the empty password.
+        server
+            .get("/blocklist")
+            .add_header(PASSWORD_HEADER, "")
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_signed_requests_are_bound_to_request() {
+        let admin = Keypair::random();
+        let context = context_with_keys(&[(&admin, AdminRole::Admin)]).await;
+        let server = AppState::test_server(&context);
+
+        let (_, token) = signed(&context, &admin, "GET", "/users");
+        server
+            .get("/users")
+            .add_header(AUTHORIZATION, token.clone())
+            .expect_success()
+            .await;
+        // Replayed nonce.
+        server
+            .get("/users")
+            .add_header(AUTHORIZATION, token)
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+
+        let (_, token) = signed(&context, &admin, "GET", "/bans");
+        server
+            .get("/users")
+            .add_header(AUTHORIZATION, token)
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+
+        // The query string is signed too.
+        let (_, token) = signed(&context, &admin, "GET", "/users?limit=1");
+        server
+            .get("/users?limit=1")
+            .add_header(AUTHORIZATION, token)
+            .expect_success()
+            .await;
+        let (_, token) = signed(&context, &admin, "GET", "/users?limit=1");
+        server
+            .get("/users?limit=2")
+            .add_header(AUTHORIZATION, token)
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+
+        // The body is signed too.
+        let users = serde_json::json!({ "users": [Keypair::random().public_key().z32()] });
+        let other_users = serde_json::json!({ "users": [Keypair::random().public_key().z32()] });
+        let body = serde_json::to_vec(&users).unwrap();
+        let (_, token) = signed_with_body(&context, &admin, "POST", "/users/bulk/disable", &body);
+        server
+            .post("/users/bulk/disable")
+            .add_header(AUTHORIZATION, token)
+            .json(&other_users)
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+        let (_, token) = signed_with_body(&context, &admin, "POST", "/users/bulk/disable", &body);
+        server
+            .post("/users/bulk/disable")
+            .add_header(AUTHORIZATION, token)
+            .json(&users)
+            .await
+            .assert_status(StatusCode::ACCEPTED);
+
+        let (mut claims, _) = signed(&context, &admin, "GET", "/users");
+        claims.aud = Keypair::random().public_key();
+        server
+            .get("/users")
+            .add_header(AUTHORIZATION, format!("Bearer {}", claims.sign(&admin)))
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+
+        let (mut claims, _) = signed(&context, &admin, "GET", "/users");
+        claims.iat -= 2 * POP_MAX_AGE_SECS;
+        server
+            .get("/users")
+            .add_header(AUTHORIZATION, format!("Bearer {}", claims.sign(&admin)))
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+
+        // Signed by a key that is not configured, or by another key than it claims.
+        let stranger = Keypair::random();
+        let (_, token) = signed(&context, &stranger, "GET", "/users");
+        server
+            .get("/users")
+            .add_header(AUTHORIZATION, token)
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+        let (claims, _) = signed(&context, &admin, "GET", "/users");
+        server
+            .get("/users")
+            .add_header(AUTHORIZATION, format!("Bearer {}", claims.sign(&stranger)))
+            .expect_failure()
+            .await
+            .assert_status_unauthorized();
+    }
+}
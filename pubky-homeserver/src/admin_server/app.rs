@@ -4,41 +4,115 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use super::routes::{
-    admin_events, dav_handler, delete_entry,
+    admin_events, banned_keys, blocklist, config, dav_handler, delete_entry,
     disable_users::{disable_user, enable_user},
-    generate_signup_token, info, root, signup_tokens, user_quota,
+    events_retention, generate_signup_token, info, mirror, moderation, republish, root, sessions,
+    signup_tokens, storage_scrub, user_quota, users,
 };
 use super::trace::with_trace_layer;
-use super::{app_state::AppState, auth_middleware::AdminAuthLayer};
-use crate::AppContext;
+use super::{
+    admin_auth::{require_role, AdminAuthenticator},
+    app_state::AppState,
+    auth_middleware::AdminAuthLayer,
+};
 #[cfg(any(test, feature = "testing"))]
 use crate::MockDataDir;
+use crate::{AdminRole, AppContext};
 use crate::{AppContextConversionError, PersistentDataDir};
-use axum::routing::{any, delete, post};
-use axum::{routing::get, Router};
+use axum::routing::{any, delete, patch, post, put};
+use axum::{middleware, routing::get, Router};
 use axum_server::Handle;
 use tokio::task::JoinHandle;
 use tower_http::cors::CorsLayer;
 
-/// Admin password protected router.
-fn create_protected_router(password: &str) -> Router<AppState> {
+/// Routes any admin can call, including read-only keys.
+fn create_read_only_router() -> Router<AppState> {
     Router::new()
-        .route(
-            "/generate_signup_token",
-            get(generate_signup_token::generate_signup_token)
-                .post(generate_signup_token::generate_signup_token_with_limits),
-        )
         .route("/info", get(info::info))
         .route("/events-stream", get(admin_events::feed_stream))
+        .route(
+            "/events/retention",
+            get(events_retention::get_events_retention),
+        )
         .route("/signup_tokens", get(signup_tokens::list_signup_tokens))
+        .route("/users", get(users::list_users))
+        .route("/users/{pubkey}", get(users::get_user))
+        .route("/users/{pubkey}/quota", get(user_quota::get_user_quota))
+        .route(
+            "/users/{pubkey}/sessions",
+            get(sessions::list_user_sessions),
+        )
+        .route("/jobs", get(moderation::list_jobs))
+        .route("/jobs/{id}", get(moderation::get_job))
+        .route("/bans", get(banned_keys::list_banned_keys))
+        .route("/blocklist", get(blocklist::list_blocks))
+        .route("/storage/scrub", get(storage_scrub::get_storage_scrub))
+        .route("/mirror", get(mirror::get_mirror))
+        .route("/republish", get(republish::list_republish_status))
+}
+
+/// User and content moderation routes.
+fn create_moderator_router() -> Router<AppState> {
+    Router::new()
         .route("/webdav/{*entry_path}", delete(delete_entry::delete_entry))
+        .route("/users/{pubkey}", delete(moderation::delete_user))
         .route("/users/{pubkey}/disable", post(disable_user))
         .route("/users/{pubkey}/enable", post(enable_user))
         .route(
-            "/users/{pubkey}/quota",
-            get(user_quota::get_user_quota).patch(user_quota::patch_user_quota),
+            "/users/{pubkey}/entries",
+            delete(moderation::delete_user_entries),
+        )
+        .route("/users/bulk/disable", post(moderation::bulk_disable_users))
+        .route("/users/bulk/delete", post(moderation::bulk_delete_users))
+        .route(
+            "/users/{pubkey}/sessions",
+            delete(sessions::revoke_user_sessions),
+        )
+        .route(
+            "/users/{pubkey}/grants/{grant_id}",
+            delete(sessions::revoke_user_grant),
         )
-        .layer(AdminAuthLayer::new(password.to_string()))
+        .route(
+            "/users/{pubkey}/cookie_sessions/{session_id}",
+            delete(sessions::revoke_user_cookie_session),
+        )
+        .route("/grants", delete(sessions::revoke_client_grants))
+        .route(
+            "/bans/{pubkey}",
+            put(banned_keys::ban_key).delete(banned_keys::unban_key),
+        )
+        .route("/blocklist", post(blocklist::add_block))
+        .route("/blocklist/{id}", delete(blocklist::remove_block))
+}
+
+/// Routes changing homeserver-wide settings, reserved to full admins.
+fn create_admin_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/generate_signup_token",
+            get(generate_signup_token::generate_signup_token)
+                .post(generate_signup_token::generate_signup_token_with_limits),
+        )
+        .route(
+            "/events/retention/run",
+            post(events_retention::run_events_retention),
+        )
+        .route("/storage/scrub/run", post(storage_scrub::run_storage_scrub))
+        .route("/mirror/run", post(mirror::run_mirror))
+        .route("/users/{pubkey}/quota", patch(user_quota::patch_user_quota))
+        .route("/config/reload", post(config::reload_config))
+}
+
+/// Authenticated router. Each group of routes requires a minimum [`AdminRole`].
+fn create_protected_router(authenticator: Arc<AdminAuthenticator>) -> Router<AppState> {
+    let with_role = |router: Router<AppState>, role: AdminRole| {
+        router.route_layer(middleware::from_fn_with_state(role, require_role))
+    };
+    Router::new()
+        .merge(with_role(create_read_only_router(), AdminRole::ReadOnly))
+        .merge(with_role(create_moderator_router(), AdminRole::Moderator))
+        .merge(with_role(create_admin_router(), AdminRole::Admin))
+        .layer(AdminAuthLayer::new(authenticator))
 }
 
 /// Public router without any authentication.
@@ -49,7 +123,7 @@ fn create_public_router() -> Router<AppState> {
 
 /// Create the app
 pub(crate) fn create_app(state: AppState) -> axum::routing::IntoMakeService<Router> {
-    let admin_router = create_protected_router(state.admin_password());
+    let admin_router = create_protected_router(Arc::clone(&state.authenticator));
     let public_router = create_public_router();
     let app = Router::new()
         .merge(admin_router)
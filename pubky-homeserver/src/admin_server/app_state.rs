@@ -3,6 +3,7 @@ use std::sync::Arc;
 use dav_server::{fakels::FakeLs, DavHandler};
 use dav_server_opendalfs::OpendalFs;
 
+use super::admin_auth::AdminAuthenticator;
 use crate::AppContext;
 use crate::ConfigToml;
 
@@ -10,6 +11,7 @@ use crate::ConfigToml;
 pub(crate) struct AppState {
     pub(crate) context: Arc<AppContext>,
     pub(crate) inner_dav_handler: DavHandler,
+    pub(crate) authenticator: Arc<AdminAuthenticator>,
 }
 
 impl AppState {
@@ -21,16 +23,18 @@ impl AppState {
             .strip_prefix("/dav")
             .autoindex(true)
             .build_handler();
+        let authenticator = Arc::new(AdminAuthenticator::new(
+            &context.config_toml.admin,
+            context.homeserver_keys(),
+            context.sql_db.clone(),
+        ));
         Self {
             inner_dav_handler,
+            authenticator,
             context,
         }
     }
 
-    pub(crate) fn admin_password(&self) -> &str {
-        &self.context.config_toml.admin.admin_password
-    }
-
     pub(crate) fn public_key(&self) -> String {
         self.context.keypair.public_key().z32()
     }
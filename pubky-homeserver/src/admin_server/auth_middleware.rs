@@ -1,23 +1,28 @@
-// src/core/layers/admin_auth.rs
 use axum::{
-    body::Body,
-    http::{Request, StatusCode},
+    body::{to_bytes, Body},
+    http::Request,
     response::Response,
 };
 use futures_util::future::BoxFuture;
-use std::{convert::Infallible, task::Poll};
+use std::{convert::Infallible, sync::Arc, task::Poll};
 use tower::{Layer, Service};
 
-/// A Tower Layer that checks the “X-Admin-Password” header against a configured password.
+use super::admin_auth::{path_and_query, AdminAuthError, AdminAuthenticator, MAX_ADMIN_BODY_BYTES};
+
+/// A Tower Layer that authenticates admin requests with an [`AdminAuthenticator`]
+/// and stores the resulting [`super::admin_auth::AdminIdentity`] in the request extensions.
+///
+/// The body is buffered (up to [`MAX_ADMIN_BODY_BYTES`]) because signed requests
+/// cover its hash.
 #[derive(Clone)]
 pub struct AdminAuthLayer {
-    password: String,
+    authenticator: Arc<AdminAuthenticator>,
 }
 
 impl AdminAuthLayer {
-    /// Create a new AdminAuthLayer with the given admin password.
-    pub fn new(password: String) -> Self {
-        Self { password }
+    /// Create a new AdminAuthLayer with the given authenticator.
+    pub fn new(authenticator: Arc<AdminAuthenticator>) -> Self {
+        Self { authenticator }
     }
 }
 
@@ -27,23 +32,22 @@ impl<S> Layer<S> for AdminAuthLayer {
     fn layer(&self, inner: S) -> Self::Service {
         AdminAuthMiddleware {
             inner,
-            password: self.password.clone(),
+            authenticator: Arc::clone(&self.authenticator),
         }
     }
 }
 
-/// Middleware that performs the admin password check.
+/// Middleware that performs the admin authentication.
 #[derive(Clone)]
 pub struct AdminAuthMiddleware<S> {
     inner: S,
-    password: String,
+    authenticator: Arc<AdminAuthenticator>,
 }
 
-impl<S, ReqBody> Service<Request<ReqBody>> for AdminAuthMiddleware<S>
+impl<S> Service<Request<Body>> for AdminAuthMiddleware<S>
 where
-    S: Service<Request<ReqBody>, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S: Service<Request<Body>, Response = Response, Error = Infallible> + Clone + Send + 'static,
     S::Future: Send + 'static,
-    ReqBody: Send + 'static,
 {
     type Response = S::Response;
     type Error = S::Error;
@@ -53,34 +57,30 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
-        let password = self.password.clone();
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let authenticator = Arc::clone(&self.authenticator);
         let mut inner = self.inner.clone();
 
         Box::pin(async move {
-            match req.headers().get("X-Admin-Password") {
-                Some(header_value) if header_value.to_str().unwrap_or("") == password => {
-                    // If the header is valid, proceed.
+            let (parts, body) = req.into_parts();
+            let Ok(body) = to_bytes(body, MAX_ADMIN_BODY_BYTES).await else {
+                return Ok(AdminAuthError::BodyTooLarge.into_response());
+            };
+            let identity = authenticator
+                .authenticate(
+                    &parts.method,
+                    path_and_query(&parts.uri),
+                    &parts.headers,
+                    &body,
+                )
+                .await;
+            let mut req = Request::from_parts(parts, Body::from(body));
+            match identity {
+                Ok(identity) => {
+                    req.extensions_mut().insert(identity);
                     inner.call(req).await
                 }
-                Some(_) => {
-                    // If header exists but password is incorrect,
-                    let msg = "Invalid admin password";
-                    let response = Response::builder()
-                        .status(StatusCode::UNAUTHORIZED)
-                        .body(Body::from(msg))
-                        .unwrap_or_else(|_| Response::new(Body::from(msg)));
-                    Ok(response)
-                }
-                None => {
-                    // If header is missing, do the same.
-                    let msg = "Missing admin password";
-                    let response = Response::builder()
-                        .status(StatusCode::UNAUTHORIZED)
-                        .body(Body::from(msg))
-                        .unwrap_or_else(|_| Response::new(Body::from(msg)));
-                    Ok(response)
-                }
+                Err(e) => Ok(e.into_response()),
             }
         })
     }
@@ -2,12 +2,16 @@
 //!
 //! Separate HTTP server for operator-only actions: generating
 //! signup tokens, enabling/disabling users, and a WebDAV interface for file
-//! management. Protected routes require the `X-Admin-Password` header.
+//! management. Protected routes require the `X-Admin-Password` header or a
+//! request signed by an admin key from the config, whose role restricts which
+//! routes it can call.
 
+mod admin_actor;
+mod admin_auth;
 mod app;
 mod app_state;
 mod auth_middleware;
-mod routes;
+pub(crate) mod routes;
 mod trace;
 
 pub use app::{AdminServer, AdminServerBuildError};
@@ -0,0 +1,124 @@
+use super::super::app_state::AppState;
+use crate::{
+    persistence::sql::banned_key::BannedKeyRepository,
+    shared::{HttpError, HttpResult, Z32Pubkey},
+};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::NaiveDateTime;
+
+#[derive(Serialize)]
+pub(crate) struct BannedKeyItem {
+    public_key: String,
+    reason: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Deserialize, Default)]
+pub(crate) struct BanKeyRequest {
+    reason: Option<String>,
+}
+
+/// List the public keys that are not allowed to sign up, most recent first.
+pub async fn list_banned_keys(
+    State(state): State<AppState>,
+) -> HttpResult<Json<Vec<BannedKeyItem>>> {
+    let bans = BannedKeyRepository::list(&mut state.context.sql_db.pool().into()).await?;
+    Ok(Json(
+        bans.into_iter()
+            .map(|ban| BannedKeyItem {
+                public_key: ban.public_key.z32(),
+                reason: ban.reason,
+                created_at: ban.created_at,
+            })
+            .collect(),
+    ))
+}
+
+/// Ban a public key from signing up. Does not affect an existing account;
+/// use `DELETE /users/{pubkey}?ban=true` to remove it as well.
+///
+/// # Errors
+///
+/// - `400` if the pubkey is invalid.
+///
+pub async fn ban_key(
+    State(state): State<AppState>,
+    Path(pubkey): Path<Z32Pubkey>,
+    request: Option<Json<BanKeyRequest>>,
+) -> HttpResult<impl IntoResponse> {
+    let Json(request) = request.unwrap_or_default();
+    BannedKeyRepository::ban(
+        &pubkey.0,
+        request.reason.as_deref(),
+        &mut state.context.sql_db.pool().into(),
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lift the signup ban of a public key.
+///
+/// # Errors
+///
+/// - `400` if the pubkey is invalid.
+/// - `404` if the key is not banned.
+///
+pub async fn unban_key(
+    State(state): State<AppState>,
+    Path(pubkey): Path<Z32Pubkey>,
+) -> HttpResult<impl IntoResponse> {
+    if !BannedKeyRepository::unban(&pubkey.0, &mut state.context.sql_db.pool().into()).await? {
+        return Err(HttpError::not_found());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_ban_list_and_unban() {
+        let context = AppContext::test().await;
+        let server = AppState::test_server(&context);
+        let pubkey = Keypair::random().public_key();
+
+        server
+            .put(&format!("/bans/{}", pubkey.z32()))
+            .admin_auth()
+            .json(&serde_json::json!({ "reason": "abuse" }))
+            .expect_success()
+            .await;
+        let bans: serde_json::Value = server
+            .get("/bans")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(bans[0]["public_key"], pubkey.z32());
+        assert_eq!(bans[0]["reason"], "abuse");
+
+        server
+            .delete(&format!("/bans/{}", pubkey.z32()))
+            .admin_auth()
+            .expect_success()
+            .await;
+        server
+            .delete(&format!("/bans/{}", pubkey.z32()))
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_not_found();
+    }
+}
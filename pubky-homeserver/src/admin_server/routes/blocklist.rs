@@ -0,0 +1,244 @@
+use super::super::{admin_actor::AdminActor, app_state::AppState};
+use crate::{
+    persistence::sql::{
+        content_block::{BlockTarget, ContentBlockEntity, ContentBlockRepository},
+        uexecutor,
+    },
+    quota_config::GlobPattern,
+    shared::{HttpError, HttpResult},
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use pubky_common::crypto::Hash;
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::NaiveDateTime;
+
+#[derive(Serialize)]
+pub(crate) struct ContentBlockItem {
+    id: i32,
+    /// Hex-encoded blake3 hash.
+    content_hash: Option<String>,
+    path_glob: Option<String>,
+    reason: Option<String>,
+    created_by: String,
+    created_at: NaiveDateTime,
+    removed_by: Option<String>,
+    removed_at: Option<NaiveDateTime>,
+}
+
+impl From<ContentBlockEntity> for ContentBlockItem {
+    fn from(block: ContentBlockEntity) -> Self {
+        let (content_hash, path_glob) = match block.target {
+            BlockTarget::Hash(hash) => (Some(hash.to_hex().to_string()), None),
+            BlockTarget::PathGlob(glob) => (None, Some(glob.to_string())),
+        };
+        Self {
+            id: block.id,
+            content_hash,
+            path_glob,
+            reason: block.reason,
+            created_by: block.created_by,
+            created_at: block.created_at,
+            removed_by: block.removed_by,
+            removed_at: block.removed_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ListBlocksQuery {
+    /// Include lifted blocks, for auditing.
+    #[serde(default)]
+    include_removed: bool,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AddBlockRequest {
+    content_hash: Option<String>,
+    path_glob: Option<String>,
+    reason: Option<String>,
+}
+
+impl AddBlockRequest {
+    fn target(&self) -> HttpResult<BlockTarget> {
+        match (&self.content_hash, &self.path_glob) {
+            (Some(hash), None) => Hash::from_hex(hash.trim())
+                .map(BlockTarget::Hash)
+                .map_err(|_| HttpError::bad_request("content_hash must be a hex blake3 hash")),
+            (None, Some(glob)) if !glob.trim().is_empty() => {
+                Ok(BlockTarget::PathGlob(GlobPattern::new(glob.trim())))
+            }
+            (None, Some(_)) => Err(HttpError::bad_request("path_glob must not be empty")),
+            _ => Err(HttpError::bad_request(
+                "exactly one of content_hash and path_glob is required",
+            )),
+        }
+    }
+}
+
+/// List the content blocklist, most recent first.
+pub async fn list_blocks(
+    State(state): State<AppState>,
+    Query(params): Query<ListBlocksQuery>,
+) -> HttpResult<Json<Vec<ContentBlockItem>>> {
+    let blocks = ContentBlockRepository::list(
+        params.include_removed,
+        &mut state.context.sql_db.pool().into(),
+    )
+    .await?;
+    Ok(Json(
+        blocks.into_iter().map(ContentBlockItem::from).collect(),
+    ))
+}
+
+/// Block a content hash or a path glob.
+///
+/// Matching uploads are rejected and existing copies are no longer served,
+/// both with `451`. Files are not deleted; use the user entry routes for that.
+///
+/// # Errors
+///
+/// - `400` if not exactly one of `content_hash` and `path_glob` is valid.
+/// - `409` if the same hash or glob is already blocked.
+///
+pub async fn add_block(
+    State(state): State<AppState>,
+    AdminActor(actor): AdminActor,
+    Json(request): Json<AddBlockRequest>,
+) -> HttpResult<impl IntoResponse> {
+    let target = request.target()?;
+    let mut tx = state.context.sql_db.pool().begin().await?;
+    if ContentBlockRepository::get_active(&target, uexecutor!(tx))
+        .await?
+        .is_some()
+    {
+        return Err(HttpError::new_with_message(
+            StatusCode::CONFLICT,
+            "Already blocked",
+        ));
+    }
+    let block =
+        ContentBlockRepository::add(&target, request.reason.as_deref(), &actor, uexecutor!(tx))
+            .await?;
+    tx.commit().await?;
+    state.context.content_block_service.invalidate();
+    tracing::info!(id = block.id, actor = %actor, "Added content block");
+    Ok((StatusCode::CREATED, Json(ContentBlockItem::from(block))))
+}
+
+/// Lift a block. The entry is kept, with who lifted it, for auditing.
+///
+/// # Errors
+///
+/// - `404` if there is no active block with this id.
+///
+pub async fn remove_block(
+    State(state): State<AppState>,
+    AdminActor(actor): AdminActor,
+    Path(id): Path<i32>,
+) -> HttpResult<impl IntoResponse> {
+    let removed =
+        ContentBlockRepository::remove(id, &actor, &mut state.context.sql_db.pool().into()).await?;
+    if removed.is_none() {
+        return Err(HttpError::not_found());
+    }
+    state.context.content_block_service.invalidate();
+    tracing::info!(id, actor = %actor, "Lifted content block");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::hash;
+
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_add_list_and_remove_blocks() {
+        let context = AppContext::test().await;
+        let server = AppState::test_server(&context);
+        let content_hash = hash(b"spam").to_hex().to_string();
+
+        let response = server
+            .post("/blocklist")
+            .admin_auth()
+            .add_header("X-Admin-Actor", "alice")
+            .json(&serde_json::json!({ "content_hash": content_hash, "reason": "dmca" }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::CREATED);
+        let block: serde_json::Value = response.json();
+        assert_eq!(block["content_hash"], content_hash);
+        assert_eq!(block["created_by"], "alice");
+        server
+            .post("/blocklist")
+            .admin_auth()
+            .json(&serde_json::json!({ "content_hash": content_hash }))
+            .expect_failure()
+            .await
+            .assert_status(StatusCode::CONFLICT);
+        server
+            .post("/blocklist")
+            .admin_auth()
+            .json(&serde_json::json!({ "path_glob": "*/pub/spam.app/**" }))
+            .expect_success()
+            .await;
+
+        server
+            .delete(&format!("/blocklist/{}", block["id"]))
+            .admin_auth()
+            .add_header("X-Admin-Actor", "bob")
+            .expect_success()
+            .await;
+        server
+            .delete(&format!("/blocklist/{}", block["id"]))
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_not_found();
+
+        let active: serde_json::Value = server.get("/blocklist").admin_auth().await.json();
+        assert_eq!(active.as_array().unwrap().len(), 1);
+        assert_eq!(active[0]["path_glob"], "*/pub/spam.app/**");
+        assert_eq!(active[0]["created_by"], "admin");
+
+        let all: serde_json::Value = server
+            .get("/blocklist?include_removed=true")
+            .admin_auth()
+            .await
+            .json();
+        assert_eq!(all[1]["id"], block["id"]);
+        assert_eq!(all[1]["removed_by"], "bob");
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_add_block_rejects_bad_targets() {
+        let context = AppContext::test().await;
+        let server = AppState::test_server(&context);
+
+        for body in [
+            serde_json::json!({}),
+            serde_json::json!({ "content_hash": "not-hex" }),
+            serde_json::json!({ "path_glob": " " }),
+            serde_json::json!({
+                "content_hash": hash(b"spam").to_hex().to_string(),
+                "path_glob": "*/pub/**",
+            }),
+        ] {
+            server
+                .post("/blocklist")
+                .admin_auth()
+                .json(&body)
+                .expect_failure()
+                .await
+                .assert_status_bad_request();
+        }
+    }
+}
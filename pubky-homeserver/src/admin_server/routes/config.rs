@@ -0,0 +1,49 @@
+use super::super::{admin_actor::AdminActor, app_state::AppState};
+use crate::shared::{HttpError, HttpResult};
+use crate::ConfigReloadReport;
+use axum::{extract::State, http::StatusCode, Json};
+
+/// Reload the config file, like `SIGHUP`.
+///
+/// Applies `[general] signup_mode`, `[drive] rate_limits`, `[default_quotas]` and `[logging]`.
+/// Changes to other settings are listed in `requires_restart` and not applied.
+///
+/// # Errors
+///
+/// - `422` if the config file cannot be loaded or is invalid. The running config is kept.
+///
+pub async fn reload_config(
+    State(state): State<AppState>,
+    AdminActor(actor): AdminActor,
+) -> HttpResult<Json<ConfigReloadReport>> {
+    tracing::info!(actor = %actor, "Reloading config");
+    let report = state.context.config_service.reload().map_err(|e| {
+        HttpError::new_with_message(StatusCode::UNPROCESSABLE_ENTITY, e.to_string())
+    })?;
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_reload_unchanged_config() {
+        let context = AppContext::test().await;
+        let server = AppState::test_server(&context);
+
+        let report: serde_json::Value = server
+            .post("/config/reload")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(
+            report,
+            serde_json::json!({ "applied": [], "requires_restart": [] })
+        );
+    }
+}
@@ -1,35 +1,84 @@
 //! This module provides a webdav endpoint that gives full access to all files.
-//! It is protected by a basic auth header with the username "admin" and the password set in the config.toml file.
-//! The password is set in the config.toml file.
-use super::super::app_state::AppState;
+//! It is protected by a basic auth header with the username "admin" and the password set in the config.toml file,
+//! or by a signed request from an admin key with the `admin` role.
+//! Signed requests cover the hash of their body, which is buffered before the request
+//! runs, so their bodies are limited to [`MAX_ADMIN_BODY_BYTES`]. Upload larger
+//! files with basic auth.
+use super::super::{
+    admin_auth::{path_and_query, MAX_ADMIN_BODY_BYTES},
+    app_state::AppState,
+};
 use crate::shared::HttpResult;
+use crate::AdminRole;
 use axum::{
-    body::Body,
+    body::{to_bytes, Body},
     extract::{Request, State},
-    http::Response,
+    http::{HeaderMap, Response, StatusCode},
     response::IntoResponse,
 };
 use base64::Engine;
+use subtle::ConstantTimeEq;
 
 pub async fn dav_handler(
     State(state): State<AppState>,
     req: Request<Body>,
 ) -> HttpResult<impl IntoResponse> {
-    if !is_valid_authorization_header(req.headers(), state.admin_password()) {
-        return Ok(Response::builder()
-            .status(401)
-            .header("WWW-Authenticate", "Basic") // This header will trigger the browser to show the login dialog
-            .body(Body::from("Unauthorized"))
-            .expect("This response should always be valid"));
-    }
+    let req = if is_signed(req.headers()) {
+        let (parts, body) = req.into_parts();
+        let Ok(body) = to_bytes(body, MAX_ADMIN_BODY_BYTES).await else {
+            return Ok(Response::builder()
+                .status(StatusCode::PAYLOAD_TOO_LARGE)
+                .body(Body::from("Admin request body too large"))
+                .expect("This response should always be valid"));
+        };
+        let authorized = state
+            .authenticator
+            .authenticate(
+                &parts.method,
+                path_and_query(&parts.uri),
+                &parts.headers,
+                &body,
+            )
+            .await
+            .is_ok_and(|identity| identity.role == AdminRole::Admin);
+        if !authorized {
+            return Ok(unauthorized());
+        }
+        Request::from_parts(parts, Body::from(body))
+    } else {
+        let authorized = state
+            .authenticator
+            .password()
+            .is_some_and(|password| is_valid_authorization_header(req.headers(), password));
+        if !authorized {
+            return Ok(unauthorized());
+        }
+        req
+    };
 
     let dav_response = state.inner_dav_handler.handle(req).await;
     Ok(dav_response.into_response())
 }
 
+fn unauthorized() -> Response<Body> {
+    Response::builder()
+        .status(401)
+        .header("WWW-Authenticate", "Basic") // This header will trigger the browser to show the login dialog
+        .body(Body::from("Unauthorized"))
+        .expect("This response should always be valid")
+}
+
+/// Whether the request is signed by an admin key rather than using the admin password as basic auth.
+fn is_signed(headers: &HeaderMap) -> bool {
+    headers
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("Bearer "))
+}
+
 /// Validate if the authorization header is correct.
 /// It must be a basic auth header with the username "admin" and the given password
-fn is_valid_authorization_header(headers: &axum::http::HeaderMap, should_password: &str) -> bool {
+fn is_valid_authorization_header(headers: &HeaderMap, should_password: &str) -> bool {
     let auth_header_raw = match headers.get("Authorization") {
         Some(authorization) => authorization,
         None => return false,
@@ -76,8 +125,8 @@ fn is_valid_authorization_header_str(auth_header: &str, should_password: &str) -
         return false;
     }
 
-    // Check if username is "admin" and password matches
-    parts[0] == "admin" && parts[1] == should_password
+    // Check if username is "admin" and password matches, in constant time
+    parts[0] == "admin" && bool::from(parts[1].as_bytes().ct_eq(should_password.as_bytes()))
 }
 
 #[cfg(test)]
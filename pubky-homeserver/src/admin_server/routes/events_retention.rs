@@ -0,0 +1,125 @@
+use super::super::app_state::AppState;
+use crate::persistence::files::events::EventRepository;
+use crate::persistence::sql::EventRetentionStatus;
+use crate::shared::HttpResult;
+use crate::EventsToml;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+use sqlx::types::chrono::NaiveDateTime;
+
+#[derive(Serialize)]
+pub(crate) struct EventsTableResponse {
+    num_events: u64,
+    min_id: Option<u64>,
+    max_id: Option<u64>,
+    oldest_created_at: Option<NaiveDateTime>,
+    total_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub(crate) struct EventsRetentionResponse {
+    policy: EventsToml,
+    table: EventsTableResponse,
+    job: EventRetentionStatus,
+}
+
+/// Return the event retention policy, the size of the events table,
+/// and the progress of the retention job on this instance.
+pub async fn get_events_retention(
+    State(state): State<AppState>,
+) -> HttpResult<(StatusCode, Json<EventsRetentionResponse>)> {
+    let overview = EventRepository::get_overview(&mut state.context.sql_db.pool().into()).await?;
+    let job = &state.context.event_retention_job;
+
+    let body = EventsRetentionResponse {
+        policy: job.config().clone(),
+        table: EventsTableResponse {
+            num_events: overview.num_events,
+            min_id: overview.min_id,
+            max_id: overview.max_id,
+            oldest_created_at: overview.oldest_created_at,
+            total_bytes: overview.total_bytes,
+        },
+        job: job.status(),
+    };
+    Ok((StatusCode::OK, Json(body)))
+}
+
+/// Trigger a retention run on this instance without waiting for the schedule.
+///
+/// The run happens in the background; poll `GET /events/retention` for progress.
+pub async fn run_events_retention(State(state): State<AppState>) -> impl IntoResponse {
+    state.context.event_retention_job.trigger();
+    (StatusCode::ACCEPTED, "Accepted")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::shared::webdav::{EntryPath, StoragePath};
+    use crate::AppContext;
+    use axum::routing::{get, post};
+    use axum::Router;
+    use pubky_common::crypto::{Hash, Keypair};
+    use pubky_common::events::EventType;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_trigger_and_report_retention() {
+        let context = AppContext::test_with_config(|config| {
+            config.events.retention_max_per_user = Some(NonZeroU64::new(1).unwrap());
+            config.events.retention_interval = 0;
+        })
+        .await;
+        let pubkey = Keypair::random().public_key();
+        let user = context.user_service.create(&pubkey).await.unwrap();
+        let path = EntryPath::new(pubkey, StoragePath::new("/pub/a").unwrap());
+        for _ in 0..3 {
+            EventRepository::create(
+                user.id,
+                EventType::Put {
+                    content_hash: Hash::from_bytes([0; 32]),
+                },
+                &path,
+                &mut context.sql_db.pool().into(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let app_state = AppState::new(Arc::clone(&context));
+        let router = Router::new()
+            .route("/events/retention", get(get_events_retention))
+            .route("/events/retention/run", post(run_events_retention))
+            .with_state(app_state);
+        let server = axum_test::TestServer::new(router).unwrap();
+
+        let response = server.get("/events/retention").await;
+        response.assert_status_ok();
+        let body: serde_json::Value = response.json();
+        assert_eq!(body["table"]["num_events"], 3);
+        assert_eq!(body["policy"]["retention_max_per_user"], 1);
+        assert_eq!(body["job"]["total_deleted"], 0);
+
+        let response = server.post("/events/retention/run").await;
+        assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+
+        // The run happens in the background.
+        let mut body = serde_json::Value::Null;
+        for _ in 0..50 {
+            body = server.get("/events/retention").await.json();
+            if body["job"]["last_finished_at"].is_string() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(body["job"]["running"], false);
+        assert_eq!(body["job"]["run"]["over_limit"], 2);
+        assert_eq!(body["job"]["total_deleted"], 2);
+        assert_eq!(body["table"]["num_events"], 1);
+    }
+}
@@ -0,0 +1,92 @@
+use super::super::app_state::AppState;
+use crate::services::mirror_job::MirrorStatus;
+use crate::MirrorToml;
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub(crate) struct MirrorResponse {
+    schedule: MirrorToml,
+    job: MirrorStatus,
+}
+
+/// Return the mirror schedule and the progress or result of the last mirror run on this instance.
+pub async fn get_mirror(State(state): State<AppState>) -> Json<MirrorResponse> {
+    Json(MirrorResponse {
+        schedule: state.context.config_toml.mirror.clone(),
+        job: state.context.mirror_job.status(),
+    })
+}
+
+/// Trigger a mirror run on this instance without waiting for the schedule.
+///
+/// The run happens in the background; poll `GET /mirror` for progress.
+/// Fails with `409 Conflict` if mirroring is disabled.
+pub async fn run_mirror(State(state): State<AppState>) -> impl IntoResponse {
+    if !state.context.config_toml.mirror.enabled {
+        return (StatusCode::CONFLICT, "Mirroring is disabled");
+    }
+    state.context.mirror_job.trigger();
+    (StatusCode::ACCEPTED, "Accepted")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_trigger_and_report_mirror() {
+        let context = AppContext::test_with_config(|config| {
+            config.mirror.enabled = true;
+            config.mirror.interval = 0;
+        })
+        .await;
+        let server = AppState::test_server(&context);
+
+        let body: serde_json::Value = server
+            .get("/mirror")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(body["schedule"]["enabled"], true);
+        assert_eq!(body["schedule"]["interval"], 0);
+        assert!(body["job"]["last_started_at"].is_null());
+
+        let response = server.post("/mirror/run").admin_auth().await;
+        assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+
+        // The run happens in the background.
+        let mut body = serde_json::Value::Null;
+        for _ in 0..50 {
+            body = server
+                .get("/mirror")
+                .admin_auth()
+                .expect_success()
+                .await
+                .json();
+            if body["job"]["last_finished_at"].is_string() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(body["job"]["running"], false);
+        assert_eq!(body["job"]["last_error"], serde_json::Value::Null);
+        assert_eq!(body["job"]["run"]["users"], 0);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_run_mirror_when_disabled() {
+        let context = AppContext::test().await;
+        let server = AppState::test_server(&context);
+
+        let response = server.post("/mirror/run").admin_auth().await;
+        assert_eq!(response.status_code(), StatusCode::CONFLICT);
+    }
+}
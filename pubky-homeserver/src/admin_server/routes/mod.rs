@@ -1,9 +1,19 @@
 pub(crate) mod admin_events;
+pub(crate) mod banned_keys;
+pub(crate) mod blocklist;
+pub(crate) mod config;
 pub(crate) mod dav_handler;
 pub(crate) mod delete_entry;
 pub(crate) mod disable_users;
+pub(crate) mod events_retention;
 pub(crate) mod generate_signup_token;
 pub(crate) mod info;
+pub(crate) mod mirror;
+pub(crate) mod moderation;
+pub(crate) mod republish;
 pub(crate) mod root;
+pub(crate) mod sessions;
 pub(crate) mod signup_tokens;
+pub(crate) mod storage_scrub;
 pub(crate) mod user_quota;
+pub(crate) mod users;
@@ -0,0 +1,351 @@
+use super::super::app_state::AppState;
+use crate::{
+    services::moderation_service::{ModerationAction, ModerationJob},
+    shared::{webdav::StoragePath, HttpError, HttpResult, Z32Pubkey},
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use pubky_common::crypto::PublicKey;
+use serde::Deserialize;
+
+/// Maximum number of users in a single bulk request.
+const MAX_BULK_USERS: usize = 1000;
+
+#[derive(Deserialize)]
+pub(crate) struct DeleteUserQuery {
+    /// Prevent the key from signing up again.
+    #[serde(default)]
+    ban: bool,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct BulkDisableRequest {
+    users: Vec<Z32Pubkey>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct BulkDeleteRequest {
+    users: Vec<Z32Pubkey>,
+    #[serde(default)]
+    ban: bool,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DeleteEntriesQuery {
+    prefix: StoragePath,
+}
+
+fn bulk_users(users: Vec<Z32Pubkey>) -> HttpResult<Vec<PublicKey>> {
+    if users.is_empty() || users.len() > MAX_BULK_USERS {
+        return Err(HttpError::bad_request(format!(
+            "users must contain between 1 and {MAX_BULK_USERS} public keys"
+        )));
+    }
+    let mut users: Vec<PublicKey> = users.into_iter().map(|user| user.0).collect();
+    let mut seen = std::collections::HashSet::new();
+    users.retain(|user| seen.insert(user.clone()));
+    Ok(users)
+}
+
+fn accepted(state: &AppState, action: ModerationAction) -> (StatusCode, Json<ModerationJob>) {
+    let job = state.context.moderation_service.start_job(action);
+    (StatusCode::ACCEPTED, Json(job))
+}
+
+/// Hard-delete a user with all their files, events, sessions and grants.
+///
+/// Runs as a background job; poll `GET /jobs/{id}` for progress.
+///
+/// # Errors
+///
+/// - `400` if the pubkey is invalid.
+/// - `404` if the user does not exist.
+///
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Path(pubkey): Path<Z32Pubkey>,
+    Query(params): Query<DeleteUserQuery>,
+) -> HttpResult<(StatusCode, Json<ModerationJob>)> {
+    state
+        .context
+        .user_service
+        .get_or_http_error(&pubkey.0, false)
+        .await?;
+    Ok(accepted(
+        &state,
+        ModerationAction::DeleteUsers {
+            users: vec![pubkey.0],
+            ban: params.ban,
+            reason: params.reason,
+        },
+    ))
+}
+
+/// Disable a list of users in a background job.
+pub async fn bulk_disable_users(
+    State(state): State<AppState>,
+    Json(request): Json<BulkDisableRequest>,
+) -> HttpResult<(StatusCode, Json<ModerationJob>)> {
+    let users = bulk_users(request.users)?;
+    Ok(accepted(&state, ModerationAction::DisableUsers { users }))
+}
+
+/// Hard-delete a list of users in a background job.
+pub async fn bulk_delete_users(
+    State(state): State<AppState>,
+    Json(request): Json<BulkDeleteRequest>,
+) -> HttpResult<(StatusCode, Json<ModerationJob>)> {
+    let users = bulk_users(request.users)?;
+    Ok(accepted(
+        &state,
+        ModerationAction::DeleteUsers {
+            users,
+            ban: request.ban,
+            reason: request.reason,
+        },
+    ))
+}
+
+/// Delete all files of a user below a directory prefix in a background job.
+///
+/// # Errors
+///
+/// - `400` if the pubkey is invalid or the prefix is not a directory.
+/// - `404` if the user does not exist.
+///
+pub async fn delete_user_entries(
+    State(state): State<AppState>,
+    Path(pubkey): Path<Z32Pubkey>,
+    Query(params): Query<DeleteEntriesQuery>,
+) -> HttpResult<(StatusCode, Json<ModerationJob>)> {
+    if !params.prefix.is_directory() {
+        return Err(HttpError::bad_request("prefix must end with '/'"));
+    }
+    state
+        .context
+        .user_service
+        .get_or_http_error(&pubkey.0, false)
+        .await?;
+    Ok(accepted(
+        &state,
+        ModerationAction::DeleteEntries {
+            user: pubkey.0,
+            prefix: params.prefix,
+        },
+    ))
+}
+
+/// List the moderation jobs of this instance, newest first.
+pub async fn list_jobs(State(state): State<AppState>) -> Json<Vec<ModerationJob>> {
+    Json(state.context.moderation_service.jobs())
+}
+
+/// Get the status of a moderation job.
+///
+/// # Errors
+///
+/// - `404` if the job is unknown to this instance.
+///
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> HttpResult<Json<ModerationJob>> {
+    state
+        .context
+        .moderation_service
+        .job(id)
+        .map(Json)
+        .ok_or_else(HttpError::not_found)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum_test::TestServer;
+    use opendal::Buffer;
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::client_server::auth::{SignupService, SignupServiceError};
+    use crate::persistence::files::events::{EventRepository, EventType, EventVisibility};
+    use crate::persistence::sql::banned_key::BannedKeyRepository;
+    use crate::shared::webdav::EntryPath;
+    use crate::AppContext;
+
+    fn create_test_server(context: &Arc<AppContext>) -> TestServer {
+        AppState::test_server(context)
+    }
+
+    async fn write_file(context: &AppContext, pubkey: &PublicKey, path: &str) {
+        let path = EntryPath::new(pubkey.clone(), StoragePath::new(path).unwrap());
+        context
+            .file_service
+            .write(&path, Buffer::from(vec![0; 10]))
+            .await
+            .unwrap();
+    }
+
+    /// Poll a job until it finished.
+    async fn wait_for_job(server: &TestServer, job: &serde_json::Value) -> serde_json::Value {
+        for _ in 0..100 {
+            let body: serde_json::Value = server
+                .get(&format!("/jobs/{}", job["id"]))
+                .admin_auth()
+                .expect_success()
+                .await
+                .json();
+            if body["state"] != "running" {
+                return body;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("job did not finish");
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_delete_user_with_ban() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+        let pubkey = Keypair::random().public_key();
+        context.user_service.create(&pubkey).await.unwrap();
+        write_file(&context, &pubkey, "/pub/a.txt").await;
+        write_file(&context, &pubkey, "/pub/app/b.txt").await;
+
+        let response = server
+            .delete(&format!("/users/{}?ban=true&reason=spam", pubkey.z32()))
+            .admin_auth()
+            .await;
+        assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+        let job = wait_for_job(&server, &response.json()).await;
+        assert_eq!(job["action"], "delete_users");
+        assert_eq!(job["state"], "succeeded");
+        assert_eq!(job["processed"], 1);
+        assert_eq!(job["entries_deleted"], 2);
+
+        assert!(matches!(
+            context.user_service.get(&pubkey).await,
+            Err(sqlx::Error::RowNotFound)
+        ));
+        assert!(
+            BannedKeyRepository::is_banned(&pubkey, &mut context.sql_db.pool().into())
+                .await
+                .unwrap()
+        );
+        let signup = SignupService::from_context(&context)
+            .create_new_user(&pubkey, None)
+            .await;
+        assert!(matches!(signup, Err(SignupServiceError::KeyBanned)));
+
+        let jobs: serde_json::Value = server.get("/jobs").admin_auth().await.json();
+        assert_eq!(jobs[0]["id"], job["id"]);
+
+        server
+            .delete(&format!("/users/{}", pubkey.z32()))
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_not_found();
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_bulk_disable_reports_unknown_users() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+        let existing = Keypair::random().public_key();
+        let unknown = Keypair::random().public_key();
+        context.user_service.create(&existing).await.unwrap();
+
+        let response = server
+            .post("/users/bulk/disable")
+            .admin_auth()
+            .json(&serde_json::json!({ "users": [existing.z32(), unknown.z32()] }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+        let job = wait_for_job(&server, &response.json()).await;
+        assert_eq!(job["state"], "failed");
+        assert_eq!(job["processed"], 2);
+        assert_eq!(job["failures"][0]["target"], unknown.z32());
+
+        assert!(context.user_service.get(&existing).await.unwrap().disabled);
+
+        server
+            .post("/users/bulk/delete")
+            .admin_auth()
+            .json(&serde_json::json!({ "users": [] }))
+            .expect_failure()
+            .await
+            .assert_status_bad_request();
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_delete_user_entries_below_prefix() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+        let pubkey = Keypair::random().public_key();
+        context.user_service.create(&pubkey).await.unwrap();
+        write_file(&context, &pubkey, "/pub/app/a.txt").await;
+        write_file(&context, &pubkey, "/pub/app/nested/b.txt").await;
+        write_file(&context, &pubkey, "/pub/other.txt").await;
+        let used_before = context.user_service.get(&pubkey).await.unwrap().used_bytes;
+
+        server
+            .delete(&format!("/users/{}/entries?prefix=/pub/app", pubkey.z32()))
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_bad_request();
+
+        let response = server
+            .delete(&format!("/users/{}/entries?prefix=/pub/app/", pubkey.z32()))
+            .admin_auth()
+            .await;
+        assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+        let job = wait_for_job(&server, &response.json()).await;
+        assert_eq!(job["state"], "succeeded");
+        assert_eq!(job["total"], 2);
+        assert_eq!(job["entries_deleted"], 2);
+
+        let body: serde_json::Value = server
+            .get(&format!("/users/{}", pubkey.z32()))
+            .admin_auth()
+            .await
+            .json();
+        assert_eq!(body["storage"]["directories"][0]["file_count"], 1);
+
+        // Deletes are accounted and announced like the user's own deletes.
+        let user = context.user_service.get(&pubkey).await.unwrap();
+        assert!(user.used_bytes < used_before);
+        let events = EventRepository::get_by_cursor(
+            None,
+            None,
+            EventVisibility::All,
+            &mut context.sql_db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let deleted: Vec<&str> = events
+            .iter()
+            .filter(|event| matches!(event.event_type, EventType::Delete))
+            .map(|event| event.path.path().as_str())
+            .collect();
+        assert_eq!(deleted, vec!["/pub/app/a.txt", "/pub/app/nested/b.txt"]);
+
+        server
+            .get("/jobs/999999")
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_not_found();
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::num::NonZeroU16;
+
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use pubky_common::republish_status::{RepublishOutcome, RepublishStatus};
+use serde::{Deserialize, Serialize};
+
+use super::super::app_state::AppState;
+use crate::persistence::sql::republish_status::{
+    RepublishStatusListQuery, RepublishStatusRepository,
+};
+use crate::shared::HttpResult;
+
+#[derive(Deserialize)]
+pub(crate) struct ListRepublishQuery {
+    limit: Option<NonZeroU16>,
+    cursor: Option<i32>,
+    /// Only users whose last attempt had this outcome.
+    outcome: Option<RepublishOutcome>,
+    /// Only users whose record is missing, invalid or failed to publish.
+    #[serde(default)]
+    issues: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RepublishItem {
+    public_key: String,
+    #[serde(flatten)]
+    status: RepublishStatus,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RepublishResponse {
+    /// Seconds between two runs of the user keys republisher, 0 if disabled.
+    interval: u64,
+    /// Number of users per outcome of their last attempt. Every outcome is listed.
+    counts: BTreeMap<&'static str, u64>,
+    items: Vec<RepublishItem>,
+    next_cursor: Option<i32>,
+}
+
+/// Republish health of all users: how many users had which outcome, and the
+/// status of each user, in signup order.
+///
+/// Filter with `?outcome=failed`, or `?issues=true` for every user needing attention.
+pub async fn list_republish_status(
+    State(state): State<AppState>,
+    Query(params): Query<ListRepublishQuery>,
+) -> HttpResult<Json<RepublishResponse>> {
+    let db = &state.context.sql_db;
+    let mut counts: BTreeMap<&'static str, u64> = RepublishOutcome::ALL
+        .iter()
+        .map(|outcome| (outcome.as_str(), 0))
+        .collect();
+    for (outcome, count) in
+        RepublishStatusRepository::count_by_outcome(&mut db.pool().into()).await?
+    {
+        counts.insert(outcome.as_str(), count);
+    }
+
+    let page = RepublishStatusRepository::list(
+        RepublishStatusListQuery {
+            outcome: params.outcome,
+            issues_only: params.issues,
+            limit: params.limit.map(NonZeroU16::get),
+            cursor: params.cursor,
+        },
+        &mut db.pool().into(),
+    )
+    .await?;
+    Ok(Json(RepublishResponse {
+        interval: state
+            .context
+            .config_toml
+            .pkdns
+            .user_keys_republisher_interval,
+        counts,
+        items: page
+            .items
+            .into_iter()
+            .map(|status| RepublishItem {
+                public_key: status.public_key.z32(),
+                status: status.into(),
+            })
+            .collect(),
+        next_cursor: page.next_cursor,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_list_republish_status() {
+        let context = AppContext::test().await;
+        let server = AppState::test_server(&context);
+        let mut pubkeys = Vec::new();
+        for (outcome, reason) in [
+            (RepublishOutcome::Published, None),
+            (RepublishOutcome::Failed, Some("too few nodes")),
+            (RepublishOutcome::Missing, Some("no packet")),
+        ] {
+            let pubkey = Keypair::random().public_key();
+            context.user_service.create(&pubkey).await.unwrap();
+            RepublishStatusRepository::record(
+                &pubkey,
+                outcome,
+                reason,
+                None,
+                &mut context.sql_db.pool().into(),
+            )
+            .await
+            .unwrap();
+            pubkeys.push(pubkey.z32());
+        }
+
+        let body: serde_json::Value = server
+            .get("/republish?issues=true&limit=1")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(body["counts"]["published"], 1);
+        assert_eq!(body["counts"]["failed"], 1);
+        assert_eq!(body["counts"]["missing"], 1);
+        assert_eq!(body["counts"]["skipped"], 0);
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["public_key"], pubkeys[1]);
+        assert_eq!(items[0]["last_outcome"], "failed");
+        assert_eq!(items[0]["last_failure_reason"], "too few nodes");
+        assert!(body["next_cursor"].is_number());
+
+        let body: serde_json::Value = server
+            .get("/republish?outcome=published")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["public_key"], pubkeys[0]);
+        assert!(items[0]["last_success_at"].is_number());
+    }
+}
@@ -0,0 +1,387 @@
+use super::super::app_state::AppState;
+use crate::{
+    client_server::auth::{
+        cookie::persistence::SessionRepository,
+        grant::persistence::{grant::GrantRepository, grant_session::GrantSessionRepository},
+    },
+    persistence::sql::SqlDb,
+    services::moderation_service::RevokedSessions,
+    shared::{HttpError, HttpResult, Z32Pubkey},
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use pubky_common::auth::jws::{ClientId, GrantId};
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::NaiveDateTime;
+
+/// An active grant of a user and its current session, if any.
+#[derive(Serialize)]
+pub(crate) struct GrantItem {
+    grant_id: String,
+    client_id: String,
+    capabilities: String,
+    issued_at: i64,
+    expires_at: i64,
+    /// Expiry of the grant's session (Unix seconds), `None` if no session is active.
+    session_expires_at: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct CookieSessionItem {
+    id: i32,
+    capabilities: String,
+    created_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub(crate) struct UserSessionsResponse {
+    grants: Vec<GrantItem>,
+    cookie_sessions: Vec<CookieSessionItem>,
+}
+
+impl UserSessionsResponse {
+    /// Active grants and cookie sessions of a user.
+    pub(crate) async fn for_user(db: &SqlDb, user_id: i32) -> HttpResult<Self> {
+        let sessions =
+            GrantSessionRepository::list_active_for_user(user_id, &mut db.pool().into()).await?;
+        let grants = GrantRepository::list_active_for_user(user_id, &mut db.pool().into())
+            .await?
+            .into_iter()
+            .map(|grant| GrantItem {
+                session_expires_at: sessions
+                    .iter()
+                    .find(|session| session.grant_id == grant.id)
+                    .map(|session| session.expires_at),
+                grant_id: grant.id.to_string(),
+                client_id: grant.client_id.to_string(),
+                capabilities: grant.capabilities.to_string(),
+                issued_at: grant.issued_at,
+                expires_at: grant.expires_at,
+            })
+            .collect();
+        let cookie_sessions = SessionRepository::list_for_user(user_id, &mut db.pool().into())
+            .await?
+            .into_iter()
+            .map(|session| CookieSessionItem {
+                id: session.id,
+                capabilities: session.capabilities.to_string(),
+                created_at: session.created_at,
+            })
+            .collect();
+        Ok(Self {
+            grants,
+            cookie_sessions,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct RevokeClientQuery {
+    client_id: ClientId,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RevokedGrantsResponse {
+    grants: u64,
+}
+
+/// List the active grants and cookie sessions of a user.
+///
+/// # Errors
+///
+/// - `400` if the pubkey is invalid.
+/// - `404` if the user does not exist.
+///
+pub async fn list_user_sessions(
+    State(state): State<AppState>,
+    Path(pubkey): Path<Z32Pubkey>,
+) -> HttpResult<Json<UserSessionsResponse>> {
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(&pubkey.0, false)
+        .await?;
+    Ok(Json(
+        UserSessionsResponse::for_user(&state.context.sql_db, user.id).await?,
+    ))
+}
+
+/// Revoke all grants and cookie sessions of a user.
+///
+/// # Errors
+///
+/// - `400` if the pubkey is invalid.
+/// - `404` if the user does not exist.
+///
+pub async fn revoke_user_sessions(
+    State(state): State<AppState>,
+    Path(pubkey): Path<Z32Pubkey>,
+) -> HttpResult<Json<RevokedSessions>> {
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(&pubkey.0, false)
+        .await?;
+    let revoked = state
+        .context
+        .moderation_service
+        .revoke_all_sessions(user.id)
+        .await?;
+    Ok(Json(revoked))
+}
+
+/// Revoke a grant of a user and delete its session.
+///
+/// # Errors
+///
+/// - `400` if the pubkey or grant id is invalid.
+/// - `404` if the user or the grant does not exist.
+///
+pub async fn revoke_user_grant(
+    State(state): State<AppState>,
+    Path((pubkey, grant_id)): Path<(Z32Pubkey, GrantId)>,
+) -> HttpResult<impl IntoResponse> {
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(&pubkey.0, false)
+        .await?;
+    if !state
+        .context
+        .moderation_service
+        .revoke_grant(user.id, &grant_id)
+        .await?
+    {
+        return Err(HttpError::not_found());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Delete a cookie session of a user.
+///
+/// # Errors
+///
+/// - `400` if the pubkey or session id is invalid.
+/// - `404` if the user or the session does not exist.
+///
+pub async fn revoke_user_cookie_session(
+    State(state): State<AppState>,
+    Path((pubkey, session_id)): Path<(Z32Pubkey, i32)>,
+) -> HttpResult<impl IntoResponse> {
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(&pubkey.0, false)
+        .await?;
+    if !state
+        .context
+        .moderation_service
+        .revoke_cookie_session(user.id, session_id)
+        .await?
+    {
+        return Err(HttpError::not_found());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revoke the grants of all users issued to a client id, e.g. after its key leaked.
+pub async fn revoke_client_grants(
+    State(state): State<AppState>,
+    Query(params): Query<RevokeClientQuery>,
+) -> HttpResult<Json<RevokedGrantsResponse>> {
+    let grants = state
+        .context
+        .moderation_service
+        .revoke_client_grants(&params.client_id)
+        .await?;
+    Ok(Json(RevokedGrantsResponse { grants }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use axum_test::TestServer;
+    use pubky_common::capabilities::{Capabilities, Capability};
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::client_server::auth::{grant::persistence::grant::NewGrant, AuthRevocation};
+    use crate::AppContext;
+
+    fn create_test_server(context: &Arc<AppContext>) -> TestServer {
+        AppState::test_server(context)
+    }
+
+    async fn create_grant(context: &AppContext, user_id: i32, client_id: &str) -> GrantId {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let grant = NewGrant {
+            id: GrantId::generate(),
+            user_id,
+            client_id: ClientId::new(client_id).unwrap(),
+            client_cnf_key: Keypair::random().public_key().z32(),
+            capabilities: Capabilities::builder().cap(Capability::root()).finish(),
+            issued_at: now,
+            expires_at: now + 3600,
+        };
+        GrantRepository::create(&grant, &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+        grant.id
+    }
+
+    async fn create_cookie_session(context: &AppContext, user_id: i32) {
+        SessionRepository::create(
+            user_id,
+            &Capabilities::builder().cap(Capability::root()).finish(),
+            &mut context.sql_db.pool().into(),
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn list_sessions(server: &TestServer, pubkey: &str) -> serde_json::Value {
+        server
+            .get(&format!("/users/{pubkey}/sessions"))
+            .admin_auth()
+            .expect_success()
+            .await
+            .json()
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_revoke_single_grant_and_cookie_session() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+        let pubkey = Keypair::random().public_key();
+        let user = context.user_service.create(&pubkey).await.unwrap();
+        let grant_id = create_grant(&context, user.id, "app.example").await;
+        create_cookie_session(&context, user.id).await;
+
+        let body = list_sessions(&server, &pubkey.z32()).await;
+        assert_eq!(body["grants"][0]["grant_id"], grant_id.to_string());
+        let session_id = body["cookie_sessions"][0]["id"].as_i64().unwrap();
+
+        server
+            .delete(&format!("/users/{}/grants/{grant_id}", pubkey.z32()))
+            .admin_auth()
+            .expect_success()
+            .await;
+        server
+            .delete(&format!(
+                "/users/{}/cookie_sessions/{session_id}",
+                pubkey.z32()
+            ))
+            .admin_auth()
+            .expect_success()
+            .await;
+        let body = list_sessions(&server, &pubkey.z32()).await;
+        assert_eq!(body["grants"], serde_json::json!([]));
+        assert_eq!(body["cookie_sessions"], serde_json::json!([]));
+
+        // Grants and sessions of other users are not found.
+        let other = context
+            .user_service
+            .create(&Keypair::random().public_key())
+            .await
+            .unwrap();
+        let other_grant = create_grant(&context, other.id, "app.example").await;
+        server
+            .delete(&format!("/users/{}/grants/{other_grant}", pubkey.z32()))
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_not_found();
+        server
+            .delete(&format!(
+                "/users/{}/cookie_sessions/{session_id}",
+                pubkey.z32()
+            ))
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_not_found();
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_revoke_all_user_sessions() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+        let pubkey = Keypair::random().public_key();
+        let user = context.user_service.create(&pubkey).await.unwrap();
+        create_grant(&context, user.id, "a.example").await;
+        create_grant(&context, user.id, "b.example").await;
+        create_cookie_session(&context, user.id).await;
+
+        let body: serde_json::Value = server
+            .delete(&format!("/users/{}/sessions", pubkey.z32()))
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(body["grants"], 2);
+        assert_eq!(body["cookie_sessions"], 1);
+
+        let body = list_sessions(&server, &pubkey.z32()).await;
+        assert_eq!(body["grants"], serde_json::json!([]));
+        assert_eq!(body["cookie_sessions"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_revoke_client_grants_across_users() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+        let mut pubkeys = Vec::new();
+        let mut leaked = Vec::new();
+        for _ in 0..2 {
+            let pubkey = Keypair::random().public_key();
+            let user = context.user_service.create(&pubkey).await.unwrap();
+            leaked.push(create_grant(&context, user.id, "leaked.example").await);
+            create_grant(&context, user.id, "safe.example").await;
+            pubkeys.push(pubkey.z32());
+        }
+        let mut revocations = context.revocation_listener.subscribe().await.unwrap();
+
+        let body: serde_json::Value = server
+            .delete("/grants?client_id=leaked.example")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(body["grants"], 2);
+
+        // Streams on every instance are told about each revoked grant.
+        for _ in 0..2 {
+            let revocation = tokio::time::timeout(Duration::from_secs(5), revocations.recv())
+                .await
+                .expect("revocation notification")
+                .unwrap();
+            assert!(leaked
+                .iter()
+                .any(|id| revocation == AuthRevocation::Grant(id.clone())));
+        }
+
+        for pubkey in &pubkeys {
+            let body = list_sessions(&server, pubkey).await;
+            let grants = body["grants"].as_array().unwrap();
+            assert_eq!(grants.len(), 1);
+            assert_eq!(grants[0]["client_id"], "safe.example");
+        }
+
+        server
+            .delete("/grants?client_id=")
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_bad_request();
+    }
+}
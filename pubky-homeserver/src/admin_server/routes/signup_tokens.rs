@@ -3,7 +3,7 @@ use std::num::NonZeroU16;
 use super::super::app_state::AppState;
 use crate::{
     persistence::sql::signup_code::{
-        SignupCode, SignupCodeEntity, SignupCodeListQuery, SignupCodeListState,
+        SignupCode, SignupCodeEntity, SignupCodeListPage, SignupCodeListQuery, SignupCodeListState,
         SignupCodeRepository,
     },
     shared::HttpResult,
@@ -58,6 +58,15 @@ pub(crate) struct SignupTokensResponse {
     next_cursor: Option<SignupCode>,
 }
 
+impl From<SignupCodeListPage> for SignupTokensResponse {
+    fn from(page: SignupCodeListPage) -> Self {
+        Self {
+            items: page.items.into_iter().map(SignupTokenItem::from).collect(),
+            next_cursor: page.next_cursor,
+        }
+    }
+}
+
 /// List signup tokens with usage information.
 pub async fn list_signup_tokens(
     State(state): State<AppState>,
@@ -66,15 +75,8 @@ pub async fn list_signup_tokens(
     let page =
         SignupCodeRepository::list(params.list_query(), &mut state.context.sql_db.pool().into())
             .await?;
-    let items = page.items.into_iter().map(SignupTokenItem::from).collect();
 
-    Ok((
-        StatusCode::OK,
-        Json(SignupTokensResponse {
-            items,
-            next_cursor: page.next_cursor,
-        }),
-    ))
+    Ok((StatusCode::OK, Json(SignupTokensResponse::from(page))))
 }
 
 #[cfg(test)]
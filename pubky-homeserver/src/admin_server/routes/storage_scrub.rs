@@ -0,0 +1,130 @@
+use super::super::app_state::AppState;
+use crate::services::storage_integrity::ScrubOptions;
+use crate::services::storage_scrub_job::StorageScrubStatus;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+pub(crate) struct ScrubScheduleResponse {
+    /// Seconds between scheduled scrubs, 0 if disabled.
+    interval: u64,
+    #[serde(flatten)]
+    options: ScrubOptions,
+}
+
+#[derive(Serialize)]
+pub(crate) struct StorageScrubResponse {
+    schedule: ScrubScheduleResponse,
+    job: StorageScrubStatus,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct RunScrubQuery {
+    #[serde(default)]
+    rehash: bool,
+    #[serde(default)]
+    repair: bool,
+}
+
+/// Return the scrub schedule and the progress or result of the last scrub on this instance.
+pub async fn get_storage_scrub(State(state): State<AppState>) -> Json<StorageScrubResponse> {
+    let config = &state.context.config_toml.storage;
+    Json(StorageScrubResponse {
+        schedule: ScrubScheduleResponse {
+            interval: config.scrub_interval,
+            options: ScrubOptions {
+                rehash: config.scrub_rehash,
+                repair: config.scrub_repair,
+            },
+        },
+        job: state.context.storage_scrub_job.status(),
+    })
+}
+
+/// Trigger a scrub on this instance without waiting for the schedule.
+///
+/// `rehash` and `repair` default to `false`, whatever the schedule uses.
+/// The scrub happens in the background; poll `GET /storage/scrub` for progress.
+pub async fn run_storage_scrub(
+    State(state): State<AppState>,
+    Query(params): Query<RunScrubQuery>,
+) -> impl IntoResponse {
+    state.context.storage_scrub_job.trigger(ScrubOptions {
+        rehash: params.rehash,
+        repair: params.repair,
+    });
+    (StatusCode::ACCEPTED, "Accepted")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use opendal::Buffer;
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::shared::webdav::{EntryPath, StoragePath};
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_trigger_and_report_scrub() {
+        let context = AppContext::test_with_config(|config| {
+            config.storage.scrub_interval = 0;
+        })
+        .await;
+        let pubkey = Keypair::random().public_key();
+        context.user_service.create(&pubkey).await.unwrap();
+        let path = EntryPath::new(pubkey, StoragePath::new("/pub/a.txt").unwrap());
+        context
+            .file_service
+            .write(&path, Buffer::from(b"hello".to_vec()))
+            .await
+            .unwrap();
+        let server = AppState::test_server(&context);
+
+        let body: serde_json::Value = server
+            .get("/storage/scrub")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(body["schedule"]["interval"], 0);
+        assert_eq!(body["schedule"]["repair"], false);
+        assert!(body["job"]["last_started_at"].is_null());
+
+        let response = server
+            .post("/storage/scrub/run?rehash=true")
+            .admin_auth()
+            .await;
+        assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+
+        // The scrub happens in the background.
+        let mut body = serde_json::Value::Null;
+        for _ in 0..50 {
+            body = server
+                .get("/storage/scrub")
+                .admin_auth()
+                .expect_success()
+                .await
+                .json();
+            if body["job"]["last_finished_at"].is_string() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(body["job"]["running"], false);
+        assert_eq!(body["job"]["last_error"], serde_json::Value::Null);
+        assert_eq!(body["job"]["options"]["rehash"], true);
+        assert_eq!(body["job"]["run"]["checked"], 1);
+        assert_eq!(body["job"]["run"]["files_checked"], 1);
+        assert_eq!(body["job"]["run"]["users_checked"], 1);
+    }
+}
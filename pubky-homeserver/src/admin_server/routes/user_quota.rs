@@ -6,6 +6,7 @@ use axum::{
 };
 use serde::Serialize;
 
+use crate::persistence::sql::{user::UserEntity, user_usage::UsagePeriods};
 use crate::shared::{
     user_quota::{UserQuota, UserQuotaPatch},
     HttpError, HttpResult, Z32Pubkey,
@@ -17,7 +18,8 @@ use super::super::app_state::AppState;
 ///
 /// Contains both the effective quota (overrides merged with system defaults)
 /// and the raw per-user overrides, so callers can see what applies and what
-/// was explicitly customised in a single request.
+/// was explicitly customised in a single request, plus the usage counted
+/// against the request and transfer caps.
 #[derive(Debug, Serialize)]
 pub struct UserQuotaResponse {
     /// The effective quota: overrides merged with system defaults.
@@ -25,6 +27,25 @@ pub struct UserQuotaResponse {
     pub effective: UserQuota,
     /// Only the per-user overrides. Fields using the system default are omitted.
     pub overrides: UserQuota,
+    /// Transfer and request usage in the current UTC day and calendar month.
+    pub usage: UsagePeriods,
+}
+
+impl UserQuotaResponse {
+    /// Resolve the quota of `user` against the system defaults and read its usage.
+    pub(crate) async fn for_user(state: &AppState, user: &UserEntity) -> HttpResult<Self> {
+        let overrides = user.quota();
+        let effective = overrides.resolve_with_defaults(
+            state.context.config_toml.storage.default_quota_mb,
+            &state.context.config_service.live().get().default_quotas,
+        );
+        let usage = state.context.usage_service.get(&user.public_key).await?;
+        Ok(Self {
+            effective,
+            overrides,
+            usage,
+        })
+    }
 }
 
 /// GET /users/{pubkey}/quota — return both effective and override quotas.
@@ -38,16 +59,7 @@ pub async fn get_user_quota(
         .get_or_http_error(&pubkey.0, false)
         .await?;
 
-    let overrides = user.quota();
-    let effective = overrides.resolve_with_defaults(
-        state.context.config_toml.storage.default_quota_mb,
-        &state.context.config_toml.default_quotas,
-    );
-
-    Ok(Json(UserQuotaResponse {
-        effective,
-        overrides,
-    }))
+    Ok(Json(UserQuotaResponse::for_user(&state, &user).await?))
 }
 
 /// PATCH /users/{pubkey}/quota — update per-user custom limits.
@@ -88,6 +100,7 @@ mod tests {
     use super::*;
     use crate::admin_server::AdminAuthExt;
     use crate::data_directory::quota_config::BandwidthQuota;
+    use crate::persistence::sql::user_usage::UsageCounters;
     use crate::AppContext;
 
     fn create_test_server(context: &Arc<AppContext>) -> TestServer {
@@ -373,6 +386,64 @@ mod tests {
         assert_eq!(json["overrides"]["rate_write"], "unlimited");
     }
 
+    /// Request and transfer caps are patched like the other fields and usage is reported.
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_usage_limits_and_usage() {
+        let context = AppContext::test_with_config(|c| {
+            c.default_quotas.egress_monthly_mb = Some(1000);
+        })
+        .await;
+        let server = create_test_server(&context);
+        let pubkey = Keypair::random().public_key();
+
+        context.user_service.create(&pubkey).await.unwrap();
+        context.usage_service.record(
+            &pubkey,
+            UsageCounters {
+                egress_bytes: 2048,
+                ingress_bytes: 0,
+                requests: 3,
+            },
+        );
+
+        let url = format!("/users/{}/quota", pubkey.z32());
+        let body = serde_json::json!({
+            "requests_per_minute": 600,
+            "egress_daily_mb": 100,
+            "ingress_monthly_mb": "unlimited"
+        });
+        server
+            .patch(&url)
+            .admin_auth()
+            .content_type("application/json")
+            .bytes(serde_json::to_vec(&body).unwrap().into())
+            .expect_success()
+            .await;
+
+        let response = server.get(&url).admin_auth().expect_success().await;
+        let json: serde_json::Value = response.json();
+        assert_eq!(json["overrides"]["requests_per_minute"], 600);
+        assert_eq!(json["overrides"]["egress_daily_mb"], 100);
+        assert_eq!(json["overrides"]["ingress_monthly_mb"], "unlimited");
+        assert!(json["overrides"].get("egress_monthly_mb").is_none());
+        assert_eq!(json["effective"]["egress_monthly_mb"], 1000);
+        assert_eq!(json["effective"]["ingress_daily_mb"], "unlimited");
+        assert_eq!(json["usage"]["day"]["egress_bytes"], 2048);
+        assert_eq!(json["usage"]["month"]["requests"], 3);
+
+        // Zero requests per minute would lock the user out; use "unlimited" or a cap instead.
+        let body = serde_json::json!({"requests_per_minute": 0});
+        let response = server
+            .patch(&url)
+            .admin_auth()
+            .content_type("application/json")
+            .bytes(serde_json::to_vec(&body).unwrap().into())
+            .expect_failure()
+            .await;
+        response.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
     /// PATCH with invalid rate string should be rejected with 422.
     #[tokio::test]
     #[pubky_test_utils::test]
@@ -0,0 +1,334 @@
+use std::num::NonZeroU16;
+
+use super::super::app_state::AppState;
+use super::sessions::UserSessionsResponse;
+use super::user_quota::UserQuotaResponse;
+use crate::{
+    persistence::sql::{
+        republish_status::RepublishStatusRepository,
+        signup_code::{SignupCode, SignupCodeRepository},
+        user::{UserEntity, UserListPage, UserListQuery},
+    },
+    services::storage_usage::storage_usage,
+    shared::{HttpError, HttpResult, Z32Pubkey},
+};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use pubky_common::{republish_status::RepublishStatus, storage_usage::StorageUsage};
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::NaiveDateTime;
+
+/// Characters of the z-base-32 alphabet used by public keys.
+const Z32_ALPHABET: &str = "ybndrfg8ejkmcpqxot1uwisza345h769";
+
+#[derive(Deserialize)]
+pub(crate) struct ListUsersQuery {
+    limit: Option<NonZeroU16>,
+    cursor: Option<i32>,
+    /// Public key prefix (z-base-32).
+    search: Option<String>,
+    disabled: Option<bool>,
+    created_after: Option<DateTime<Utc>>,
+    created_before: Option<DateTime<Utc>>,
+    min_used_bytes: Option<u64>,
+    max_used_bytes: Option<u64>,
+    signup_code: Option<SignupCode>,
+}
+
+impl ListUsersQuery {
+    fn list_query(self) -> HttpResult<UserListQuery> {
+        if let Some(search) = &self.search {
+            if !is_pubkey_prefix(search) {
+                return Err(HttpError::bad_request(
+                    "search must be a z-base-32 public key prefix",
+                ));
+            }
+        }
+        Ok(UserListQuery {
+            pubkey_prefix: self.search,
+            disabled: self.disabled,
+            created_after: self.created_after.map(|at| at.naive_utc()),
+            created_before: self.created_before.map(|at| at.naive_utc()),
+            min_used_bytes: self.min_used_bytes,
+            max_used_bytes: self.max_used_bytes,
+            signup_code: self.signup_code,
+            limit: self.limit.map(NonZeroU16::get),
+            cursor: self.cursor,
+        })
+    }
+}
+
+/// Whether `search` can be used as a public key prefix when listing users.
+///
+/// Only z-base-32 characters are allowed, so the prefix never contains SQL `LIKE`
+/// wildcards.
+pub(crate) fn is_pubkey_prefix(search: &str) -> bool {
+    !search.is_empty() && search.chars().all(|c| Z32_ALPHABET.contains(c))
+}
+
+#[derive(Serialize)]
+pub(crate) struct UserItem {
+    public_key: String,
+    created_at: NaiveDateTime,
+    disabled: bool,
+    used_bytes: u64,
+}
+
+impl From<&UserEntity> for UserItem {
+    fn from(user: &UserEntity) -> Self {
+        Self {
+            public_key: user.public_key.z32(),
+            created_at: user.created_at,
+            disabled: user.disabled,
+            used_bytes: user.used_bytes,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct UsersResponse {
+    items: Vec<UserItem>,
+    next_cursor: Option<i32>,
+}
+
+impl From<UserListPage> for UsersResponse {
+    fn from(page: UserListPage) -> Self {
+        Self {
+            items: page.items.iter().map(UserItem::from).collect(),
+            next_cursor: page.next_cursor,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct UserDetailResponse {
+    #[serde(flatten)]
+    user: UserItem,
+    /// Signup code the user redeemed, `None` if signed up without one.
+    signup_code: Option<SignupCode>,
+    quota: UserQuotaResponse,
+    #[serde(flatten)]
+    sessions: UserSessionsResponse,
+    storage: StorageUsage,
+    /// Health of the user's pkarr record, as republished by this homeserver.
+    republish: RepublishStatus,
+}
+
+/// List users with optional filters, in signup order.
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(params): Query<ListUsersQuery>,
+) -> HttpResult<Json<UsersResponse>> {
+    let page = state
+        .context
+        .user_service
+        .list(params.list_query()?)
+        .await?;
+    Ok(Json(UsersResponse::from(page)))
+}
+
+/// Get everything support needs to triage an account.
+///
+/// # Errors
+///
+/// - `400` if the pubkey is invalid.
+/// - `404` if the user does not exist.
+///
+pub async fn get_user(
+    State(state): State<AppState>,
+    Path(pubkey): Path<Z32Pubkey>,
+) -> HttpResult<Json<UserDetailResponse>> {
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(&pubkey.0, false)
+        .await?;
+    let db = &state.context.sql_db;
+
+    let signup_code = SignupCodeRepository::get_used_by(&user.public_key, &mut db.pool().into())
+        .await?
+        .map(|code| code.id);
+    let sessions = UserSessionsResponse::for_user(db, user.id).await?;
+    let storage = storage_usage(
+        db,
+        &user,
+        state.context.config_toml.storage.default_quota_mb,
+    )
+    .await?;
+    let republish = RepublishStatusRepository::get(user.id, &mut db.pool().into())
+        .await?
+        .map(Into::into)
+        .unwrap_or_default();
+
+    Ok(Json(UserDetailResponse {
+        user: UserItem::from(&user),
+        signup_code,
+        quota: UserQuotaResponse::for_user(&state, &user).await?,
+        sessions,
+        storage,
+        republish,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum_test::TestServer;
+    use pubky_common::capabilities::{Capabilities, Capability};
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::admin_server::AdminAuthExt;
+    use crate::client_server::auth::cookie::persistence::SessionRepository;
+    use crate::shared::user_quota::UserQuota;
+    use crate::AppContext;
+
+    fn create_test_server(context: &Arc<AppContext>) -> TestServer {
+        AppState::test_server(context)
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_list_users_filters_and_paginates() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+
+        let mut pubkeys = Vec::new();
+        for _ in 0..3 {
+            let pubkey = Keypair::random().public_key();
+            context.user_service.create(&pubkey).await.unwrap();
+            pubkeys.push(pubkey.z32());
+        }
+        server
+            .post(&format!("/users/{}/disable", pubkeys[1]))
+            .admin_auth()
+            .expect_success()
+            .await;
+
+        let body: serde_json::Value = server
+            .get("/users?limit=2")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["public_key"], pubkeys[0]);
+        let cursor = body["next_cursor"].as_i64().unwrap();
+
+        let body: serde_json::Value = server
+            .get(&format!("/users?limit=2&cursor={cursor}"))
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(body["items"][0]["public_key"], pubkeys[2]);
+        assert_eq!(body["next_cursor"], serde_json::Value::Null);
+
+        let body: serde_json::Value = server
+            .get("/users?disabled=true")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        let items = body["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["public_key"], pubkeys[1]);
+        assert_eq!(items[0]["disabled"], true);
+
+        let body: serde_json::Value = server
+            .get(&format!("/users?search={}", &pubkeys[2][..10]))
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(body["items"][0]["public_key"], pubkeys[2]);
+
+        let body: serde_json::Value = server
+            .get("/users?created_after=2000-01-01T00:00:00Z&min_used_bytes=1")
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert!(body["items"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_list_users_rejects_bad_query() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+
+        for query in [
+            "search=not-z32!",
+            "limit=0",
+            "disabled=maybe",
+            "created_after=yesterday",
+            "signup_code=invalid",
+        ] {
+            server
+                .get(&format!("/users?{query}"))
+                .admin_auth()
+                .expect_failure()
+                .await
+                .assert_status_bad_request();
+        }
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_get_user_detail() {
+        let context = AppContext::test().await;
+        let server = create_test_server(&context);
+        let pubkey = Keypair::random().public_key();
+        let user = context.user_service.create(&pubkey).await.unwrap();
+
+        let code = SignupCode::random();
+        SignupCodeRepository::create(
+            &code,
+            &UserQuota::default(),
+            &mut context.sql_db.pool().into(),
+        )
+        .await
+        .unwrap();
+        SignupCodeRepository::mark_as_used(&code, &pubkey, &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+        SessionRepository::create(
+            user.id,
+            &Capabilities::builder().cap(Capability::root()).finish(),
+            &mut context.sql_db.pool().into(),
+        )
+        .await
+        .unwrap();
+
+        let body: serde_json::Value = server
+            .get(&format!("/users/{}", pubkey.z32()))
+            .admin_auth()
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(body["public_key"], pubkey.z32());
+        assert_eq!(body["disabled"], false);
+        assert_eq!(body["signup_code"], code.to_string());
+        assert!(body["quota"]["effective"].is_object());
+        assert!(body["quota"]["usage"]["day"].is_object());
+        assert_eq!(body["grants"], serde_json::json!([]));
+        assert_eq!(body["cookie_sessions"].as_array().unwrap().len(), 1);
+        assert_eq!(body["cookie_sessions"][0]["capabilities"], "/:rw");
+        assert_eq!(body["storage"]["used_bytes"], 0);
+        assert_eq!(body["storage"]["directories"], serde_json::json!([]));
+        assert!(body["republish"]["last_outcome"].is_null());
+
+        server
+            .get(&format!("/users/{}", Keypair::random().public_key().z32()))
+            .admin_auth()
+            .expect_failure()
+            .await
+            .assert_status_not_found();
+    }
+}
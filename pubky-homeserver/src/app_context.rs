@@ -5,6 +5,13 @@
 //! Create with a `DataDir` instance: `AppContext::try_from(data_dir)`
 //!
 
+use crate::services::config_service::ConfigService;
+use crate::services::content_block_service::ContentBlockService;
+use crate::services::mirror_job::{Mirror, MirrorJob};
+use crate::services::moderation_service::ModerationService;
+use crate::services::storage_integrity::StorageIntegrityService;
+use crate::services::storage_scrub_job::StorageScrubJob;
+use crate::services::usage_service::UsageService;
 use crate::services::user_service::UserService;
 #[cfg(any(test, feature = "testing"))]
 use crate::MockDataDir;
@@ -12,9 +19,10 @@ use crate::{
     client_server::auth::RevocationListener,
     observability::{Metrics, MetricsInitError},
     persistence::{
-        files::{events::EventsService, FileIoError, FileService},
-        sql::{Migrator, PgEventListener, SqlDb},
+        files::{events::EventsService, FileIoError, FileService, ReadCache},
+        sql::{EventRetentionJob, Migrator, PgEventListener, SqlDb},
     },
+    shared::{HomeserverKeys, KeyTransition},
     ConfigToml, DataDir,
 };
 use pubky_common::crypto::Keypair;
@@ -45,6 +53,9 @@ pub enum AppContextConversionError {
     /// Failed to build pkarr client.
     #[error("Failed to build pkarr client: {0}")]
     Pkarr(pkarr::errors::BuildError),
+    /// Failed to build the HTTP client mirroring from primary homeservers.
+    #[error("Failed to build the mirror HTTP client: {0}")]
+    Mirror(reqwest::Error),
     /// Failed to start the Postgres event listener.
     #[error("Failed to start Postgres event listener: {0}")]
     PgEventListener(sqlx::Error),
@@ -69,10 +80,17 @@ pub struct AppContext {
     pub(crate) sql_db: SqlDb,
     /// The storage operator to store files.
     pub(crate) file_service: FileService,
+    /// The config the homeserver was started with.
+    /// Read reloadable settings from `config_service` instead.
     pub(crate) config_toml: ConfigToml,
+    /// Reloads the config file and holds the current reloadable settings.
+    pub(crate) config_service: ConfigService,
     /// Keep data_dir alive. The mock dir will cleanup on drop.
     pub(crate) data_dir: Arc<dyn DataDir>,
     pub(crate) keypair: Keypair,
+    /// The keypair before the last `keypair rotate`, still answered to during
+    /// `[pkdns].key_transition_days`.
+    pub(crate) key_transition: Option<KeyTransition>,
     /// Main pkarr instance. This will automatically turn into a DHT server after 15 minutes after startup.
     /// We need to keep this alive.
     pub(crate) pkarr_client: pkarr::Client,
@@ -87,11 +105,23 @@ pub struct AppContext {
     /// Enables cross-instance event propagation for /events-stream's SSE functionality.
     /// Kept alive for the background task, not for direct access.
     _pg_event_listener: Arc<PgEventListener>,
+    /// Background job that prunes the events table according to `[events]`.
+    pub(crate) event_retention_job: Arc<EventRetentionJob>,
     /// Auth revocations are forwarded to private SSE streams on this instance.
     /// Its Postgres listener stops once the last clone is dropped.
     pub(crate) revocation_listener: RevocationListener,
     /// User service for quota resolution and user creation with defaults.
     pub(crate) user_service: UserService,
+    /// Per-user transfer and request counters for the usage caps.
+    pub(crate) usage_service: UsageService,
+    /// Blocklist checks on file reads and writes.
+    pub(crate) content_block_service: ContentBlockService,
+    /// User purges and bulk moderation jobs started through the admin API.
+    pub(crate) moderation_service: ModerationService,
+    /// Background job that scrubs the storage according to `[storage]`.
+    pub(crate) storage_scrub_job: Arc<StorageScrubJob>,
+    /// Background job that mirrors users according to `[mirror]`.
+    pub(crate) mirror_job: Arc<MirrorJob>,
 }
 
 impl AppContext {
@@ -114,6 +144,18 @@ impl AppContext {
         self
     }
 
+    /// Replace the key transition (e.g. to test a rotated homeserver).
+    #[cfg(test)]
+    pub(crate) fn with_key_transition(mut self, transition: Option<KeyTransition>) -> Self {
+        self.key_transition = transition;
+        self
+    }
+
+    /// The keys this homeserver currently answers to.
+    pub(crate) fn homeserver_keys(&self) -> HomeserverKeys {
+        HomeserverKeys::new(self.keypair.public_key()).with_transition(self.key_transition.clone())
+    }
+
     /// Create a new AppContext for testing, wrapped in Arc for use in AppState.
     #[cfg(any(test, feature = "testing"))]
     pub async fn test() -> Arc<Self> {
@@ -150,6 +192,14 @@ impl AppContext {
         let keypair = dir
             .read_or_create_keypair()
             .map_err(AppContextConversionError::Keypair)?;
+        let key_transition = dir
+            .read_key_rotation()
+            .map_err(AppContextConversionError::Keypair)?
+            .and_then(|rotation| {
+                KeyTransition::from_rotation(rotation, conf.pkdns.key_transition_days)
+            });
+        let data_dir: Arc<dyn DataDir> = Arc::new(dir);
+        let config_service = ConfigService::new(&conf, Arc::clone(&data_dir));
 
         let sql_db = Self::connect_to_sql_db(&conf).await?;
         Migrator::new(&sql_db)
@@ -162,38 +212,78 @@ impl AppContext {
         let pg_event_listener = PgEventListener::start(sql_db.pool(), events_service.clone())
             .await
             .map_err(AppContextConversionError::PgEventListener)?;
+        let event_retention_job = EventRetentionJob::start(sql_db.pool(), conf.events.clone());
         let revocation_listener = RevocationListener::start(sql_db.pool())
             .await
             .map_err(AppContextConversionError::RevocationListener)?;
 
         let user_service = UserService::new(sql_db.clone());
+        let usage_service = UsageService::new(sql_db.clone());
+        let content_block_service = ContentBlockService::new();
 
-        let file_service = FileService::new_from_config(
+        let metrics = Metrics::new().map_err(AppContextConversionError::Metrics)?;
+        let mut file_service = FileService::new_from_config(
             &conf,
-            dir.path(),
+            data_dir.path(),
             sql_db.clone(),
             events_service.clone(),
             user_service.clone(),
+            content_block_service.clone(),
         )
         .map_err(AppContextConversionError::Storage)?;
+        if let Some(cache) = &conf.storage.cache {
+            let read_cache = ReadCache::new(cache, data_dir.path(), metrics.clone())
+                .map_err(AppContextConversionError::Storage)?;
+            file_service = file_service.with_read_cache(read_cache);
+        }
+        let moderation_service =
+            ModerationService::new(sql_db.clone(), file_service.clone(), user_service.clone());
+        let storage_scrub_job = StorageScrubJob::start(
+            sql_db.pool(),
+            StorageIntegrityService::new(
+                sql_db.clone(),
+                file_service.clone(),
+                user_service.clone(),
+            ),
+            &conf.storage,
+            metrics.clone(),
+        );
         let pkarr_builder = Self::build_pkarr_builder_from_config(&conf);
+        let pkarr_client = pkarr_builder
+            .clone()
+            .build()
+            .map_err(AppContextConversionError::Pkarr)?;
+        let mirror = Mirror::new(
+            sql_db.clone(),
+            file_service.clone(),
+            user_service.clone(),
+            keypair.public_key(),
+            pkarr_client.clone(),
+        )
+        .map_err(AppContextConversionError::Mirror)?;
+        let mirror_job = MirrorJob::start(sql_db.pool(), mirror, &conf.mirror);
 
         Ok(Self {
             sql_db,
-            pkarr_client: pkarr_builder
-                .clone()
-                .build()
-                .map_err(AppContextConversionError::Pkarr)?,
+            pkarr_client,
             file_service,
             pkarr_builder,
             config_toml: conf,
+            config_service,
             keypair,
-            data_dir: Arc::new(dir),
+            key_transition,
+            data_dir,
             events_service,
-            metrics: Metrics::new().map_err(AppContextConversionError::Metrics)?,
+            metrics,
             _pg_event_listener: Arc::new(pg_event_listener),
+            event_retention_job: Arc::new(event_retention_job),
             revocation_listener,
             user_service,
+            usage_service,
+            content_block_service,
+            moderation_service,
+            storage_scrub_job: Arc::new(storage_scrub_job),
+            mirror_job: Arc::new(mirror_job),
         })
     }
 }
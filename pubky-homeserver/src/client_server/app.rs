@@ -27,11 +27,14 @@ use tower_http::cors::CorsLayer;
 use super::auth::{self, AuthenticationLayer};
 use super::cache_policy;
 use super::middleware::{
-    rate_limiter::{BandwidthQuotaLimitLayer, RequestRateLimitLayer},
+    rate_limiter::{BandwidthQuotaLimitLayer, RequestRateLimitLayer, UsageLimitLayer},
     request_tenant::RequestTenant,
     trace::with_trace_layer,
 };
-use super::routes::{events, info, root, signup_tokens, tenants};
+use super::routes::{
+    events, info, path_quotas, republish, root, signup_tokens, storage_usage, tenants,
+};
+use super::tls;
 
 /// Errors that can occur when building a `HomeserverCore`.
 #[derive(Debug, thiserror::Error)]
@@ -152,19 +155,33 @@ impl ClientServer {
         https_listener.set_nonblocking(true)?;
         let https_socket = https_listener.local_addr()?;
         let https_handle = Handle::new();
-        let server = axum_server::from_tcp(https_listener)?;
-        tokio::spawn(
-            server
-                .acceptor(RustlsAcceptor::new(RustlsConfig::from_config(Arc::new(
-                    context.keypair.to_rpk_rustls_server_config(),
-                ))))
-                .handle(https_handle.clone())
-                .serve(router.into_make_service_with_connect_info::<SocketAddr>())
-                .map_err(|error| {
-                    tracing::error!(?error, "Homeserver pubky tls server error");
-                    println!("Homeserver pubky tls server error: {:?}", error);
-                }),
-        );
+        let server = axum_server::from_tcp(https_listener)?.handle(https_handle.clone());
+        let make_service = router.into_make_service_with_connect_info::<SocketAddr>();
+        let log_error = |error| {
+            tracing::error!(?error, "Homeserver pubky tls server error");
+            println!("Homeserver pubky tls server error: {:?}", error);
+        };
+        // During a key transition, clients may still expect the previous key.
+        match tls::TransitionAcceptor::from_context(context) {
+            Some(acceptor) => {
+                tokio::spawn(
+                    server
+                        .acceptor(acceptor)
+                        .serve(make_service)
+                        .map_err(log_error),
+                );
+            }
+            None => {
+                tokio::spawn(
+                    server
+                        .acceptor(RustlsAcceptor::new(RustlsConfig::from_config(Arc::new(
+                            context.keypair.to_rpk_rustls_server_config(),
+                        ))))
+                        .serve(make_service)
+                        .map_err(log_error),
+                );
+            }
+        }
 
         Ok((https_handle, https_socket))
     }
@@ -202,6 +219,17 @@ fn base() -> Router<AppState> {
     Router::new()
         .route("/", get(root::handler))
         .route("/signup_tokens/{token}", get(signup_tokens::get))
+        .route(
+            "/quota/paths",
+            get(path_quotas::list)
+                .put(path_quotas::set)
+                .delete(path_quotas::delete),
+        )
+        .route("/quota/usage", get(storage_usage::get))
+        .route(
+            "/pkdns/republish",
+            get(republish::get).post(republish::post),
+        )
         // Events
         .route("/events/", get(events::feed))
         .route(
@@ -217,18 +245,17 @@ fn base() -> Router<AppState> {
 
 pub fn create_app(state: AppState) -> std::result::Result<Router, ClientServerBuildError> {
     let auth_state = state.auth_state.clone();
-    let request_rate_limit_layer = RequestRateLimitLayer::from_path_limits(
-        state.context.config_toml.drive.rate_limits.clone(),
-    )
-    .map_err(ClientServerBuildError::RequestRateLimits)?;
+    let request_rate_limit_layer = RequestRateLimitLayer::from_context(&state.context)
+        .map_err(ClientServerBuildError::RequestRateLimits)?;
 
     let middleware = ServiceBuilder::new()
-        // Request order matters: auth needs CookieManager, and bandwidth limits
-        // need AuthSession from authentication. RequestTenant runs outside this
+        // Request order matters: auth needs CookieManager, and usage and bandwidth
+        // limits need AuthSession from authentication. RequestTenant runs outside this
         // stack so tracing and all of these layers see the resolved target.
         .layer(CookieManagerLayer::new())
         .layer(request_rate_limit_layer)
         .layer(AuthenticationLayer::new(auth_state.clone()))
+        .layer(UsageLimitLayer::from_context(&state.context))
         .layer(BandwidthQuotaLimitLayer::from_context(&state.context));
 
     let app = base()
@@ -254,11 +281,17 @@ mod tests {
 
     use axum::http::{header, Method, StatusCode};
     use axum_test::TestServer;
-    use pubky_common::{auth::AuthToken, capabilities::Capability, crypto::Keypair};
+    use pubky_common::{
+        auth::AuthToken,
+        capabilities::Capability,
+        crypto::Keypair,
+        republish_status::{RepublishOutcome, RepublishStatus},
+    };
 
     use crate::{
         app_context::AppContext,
         client_server::ClientServer,
+        persistence::sql::republish_status::RepublishStatusRepository,
         quota_config::{GlobPattern, HttpMethod, LimitKeyType, PathLimit},
     };
 
@@ -323,6 +356,164 @@ mod tests {
         response.assert_json(&serde_json::json!({ "features": [] }));
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn path_quotas_are_managed_by_the_owner_and_enforced() {
+        let context = AppContext::test().await;
+        let router = ClientServer::create_router(Arc::clone(&context)).unwrap();
+        let server = TestServer::new(router).unwrap();
+        let user = Keypair::random();
+        let host = user.public_key().z32();
+        let cookie = signup_cookie(&server, &user).await;
+
+        server
+            .put("/quota/paths")
+            .add_header("host", host.clone())
+            .add_header(header::COOKIE, cookie.clone())
+            .json(&serde_json::json!({ "path": "/pub/app/", "max_mb": 1 }))
+            .expect_success()
+            .await;
+        server
+            .put("/quota/paths")
+            .add_header("host", host.clone())
+            .add_header(header::COOKIE, cookie.clone())
+            .json(&serde_json::json!({ "path": "/pub/app/file.txt", "max_mb": 1 }))
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        server
+            .put("/pub/app/small.bin")
+            .add_header("host", host.clone())
+            .add_header(header::COOKIE, cookie.clone())
+            .bytes(vec![0u8; 100].into())
+            .expect_success()
+            .await;
+        server
+            .put("/pub/app/big.bin")
+            .add_header("host", host.clone())
+            .add_header(header::COOKIE, cookie.clone())
+            .bytes(vec![0u8; 1024 * 1024].into())
+            .await
+            .assert_status(StatusCode::INSUFFICIENT_STORAGE);
+
+        let response = server
+            .get("/quota/paths")
+            .add_header("host", host.clone())
+            .add_header(header::COOKIE, cookie.clone())
+            .expect_success()
+            .await;
+        let json: serde_json::Value = response.json();
+        assert_eq!(json[0]["path"], "/pub/app/");
+        assert_eq!(json[0]["max_mb"], 1);
+        assert_eq!(json[0]["file_count"], 1);
+
+        server
+            .delete("/quota/paths?path=/pub/app/")
+            .add_header("host", host.clone())
+            .add_header(header::COOKIE, cookie.clone())
+            .await
+            .assert_status(StatusCode::NO_CONTENT);
+        server
+            .put("/pub/app/big.bin")
+            .add_header("host", host)
+            .add_header(header::COOKIE, cookie)
+            .bytes(vec![0u8; 1024 * 1024].into())
+            .expect_success()
+            .await;
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn storage_usage_breaks_down_by_directory_and_app() {
+        let context = AppContext::test().await;
+        let router = ClientServer::create_router(Arc::clone(&context)).unwrap();
+        let server = TestServer::new(router).unwrap();
+        let user = Keypair::random();
+        let host = user.public_key().z32();
+        let cookie = signup_cookie(&server, &user).await;
+
+        for (path, size) in [
+            ("/pub/app/a.bin", 100),
+            ("/pub/app/b.bin", 200),
+            ("/pub/c.bin", 10),
+        ] {
+            server
+                .put(path)
+                .add_header("host", host.clone())
+                .add_header(header::COOKIE, cookie.clone())
+                .bytes(vec![0u8; size].into())
+                .expect_success()
+                .await;
+        }
+
+        let response = server
+            .get("/quota/usage")
+            .add_header("host", host)
+            .add_header(header::COOKIE, cookie)
+            .expect_success()
+            .await;
+        let usage: pubky_common::storage_usage::StorageUsage = response.json();
+        let overhead = crate::services::user_service::FILE_METADATA_SIZE;
+        assert_eq!(usage.used_bytes, 310 + 3 * overhead);
+        assert_eq!(usage.directories.len(), 1);
+        let public = &usage.directories[0];
+        assert_eq!(public.path, "/pub/");
+        assert_eq!(public.used_bytes, usage.used_bytes);
+        assert_eq!(public.file_count, 3);
+        assert_eq!(public.apps.len(), 1);
+        assert_eq!(public.apps[0].path, "/pub/app/");
+        assert_eq!(public.apps[0].used_bytes, 300 + 2 * overhead);
+        assert!(public.apps[0].last_modified > 0);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn republish_status_is_reported_and_republishing_is_rate_limited() {
+        let context = AppContext::test().await;
+        let router = ClientServer::create_router(Arc::clone(&context)).unwrap();
+        let server = TestServer::new(router).unwrap();
+        let user = Keypair::random();
+        let host = user.public_key().z32();
+        let cookie = signup_cookie(&server, &user).await;
+
+        let status: RepublishStatus = server
+            .get("/pkdns/republish")
+            .add_header("host", host.clone())
+            .add_header(header::COOKIE, cookie.clone())
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(status, RepublishStatus::default());
+
+        RepublishStatusRepository::record(
+            &user.public_key(),
+            RepublishOutcome::Failed,
+            Some("too few nodes"),
+            None,
+            &mut context.sql_db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let status: RepublishStatus = server
+            .get("/pkdns/republish")
+            .add_header("host", host.clone())
+            .add_header(header::COOKIE, cookie.clone())
+            .expect_success()
+            .await
+            .json();
+        assert_eq!(status.last_outcome, Some(RepublishOutcome::Failed));
+        assert!(status.last_attempt_at.is_some());
+        assert_eq!(status.last_failure_reason.as_deref(), Some("too few nodes"));
+
+        // The record was just attempted.
+        let response = server
+            .post("/pkdns/republish")
+            .add_header("host", host)
+            .add_header(header::COOKIE, cookie)
+            .await;
+        response.assert_status(StatusCode::TOO_MANY_REQUESTS);
+    }
+
     async fn signup_cookie(server: &TestServer, keypair: &Keypair) -> String {
         let auth_token = AuthToken::sign(keypair, vec![Capability::root()]);
         let body_bytes: axum::body::Bytes = auth_token.serialize().into();
@@ -8,7 +8,7 @@ use pubky_common::crypto::PublicKey;
 use pubky_common::{
     capabilities::Capabilities, crypto::random_bytes, session::CookieSessionRecord,
 };
-use sea_query::{Expr, Iden, PostgresQueryBuilder, Query, SimpleExpr};
+use sea_query::{Expr, Iden, Order, PostgresQueryBuilder, Query, SimpleExpr};
 use sea_query_binder::SqlxBinder;
 use sqlx::{postgres::PgRow, FromRow, Row};
 
@@ -84,6 +84,35 @@ impl SessionRepository {
         Ok(user)
     }
 
+    /// List all sessions of a user, oldest first.
+    /// The executor can either be db.pool() or a transaction.
+    pub async fn list_for_user<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<SessionEntity>, sqlx::Error> {
+        let statement = Query::select()
+            .from(SESSION_TABLE)
+            .columns([
+                (SESSION_TABLE, SessionIden::Id),
+                (SESSION_TABLE, SessionIden::Secret),
+                (SESSION_TABLE, SessionIden::User),
+                (SESSION_TABLE, SessionIden::Capabilities),
+                (SESSION_TABLE, SessionIden::CreatedAt),
+            ])
+            .column((USER_TABLE, UserIden::PublicKey))
+            .left_join(
+                USER_TABLE,
+                Expr::col((SESSION_TABLE, SessionIden::User))
+                    .eq(Expr::col((USER_TABLE, UserIden::Id))),
+            )
+            .and_where(Expr::col((SESSION_TABLE, SessionIden::User)).eq(user_id))
+            .order_by((SESSION_TABLE, SessionIden::Id), Order::Asc)
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values).fetch_all(con).await
+    }
+
     /// Delete a session by its secret.
     /// The executor can either be db.pool() or a transaction.
     pub async fn delete<'a>(
@@ -100,6 +129,45 @@ impl SessionRepository {
         sqlx::query_with(&query, values).execute(con).await?;
         Ok(())
     }
+
+    /// Delete a session of a user by its id. Returns whether the session existed.
+    /// The executor can either be db.pool() or a transaction.
+    pub async fn delete_by_id<'a>(
+        user_id: i32,
+        id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<bool, sqlx::Error> {
+        let statement = Query::delete()
+            .from_table(SESSION_TABLE)
+            .and_where(Expr::col(SessionIden::Id).eq(id))
+            .and_where(Expr::col(SessionIden::User).eq(user_id))
+            .to_owned();
+
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let result = sqlx::query_with(&query, values).execute(con).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete all sessions of a user. Returns the ids of the deleted sessions.
+    /// The executor can either be db.pool() or a transaction.
+    pub async fn delete_all_for_user<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<i32>, sqlx::Error> {
+        let statement = Query::delete()
+            .from_table(SESSION_TABLE)
+            .and_where(Expr::col(SessionIden::User).eq(user_id))
+            .returning_col(SessionIden::Id)
+            .to_owned();
+
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let rows: Vec<PgRow> = sqlx::query_with(&query, values).fetch_all(con).await?;
+        rows.iter()
+            .map(|row| row.try_get(SessionIden::Id.to_string().as_str()))
+            .collect()
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -253,10 +321,46 @@ mod tests {
             Capabilities::builder().cap(Capability::root()).finish()
         );
 
+        // Test list sessions
+        let sessions = SessionRepository::list_for_user(user.id, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(sessions, vec![session.clone()]);
+
         // Test delete session
         SessionRepository::delete(&session.secret, &mut db.pool().into())
             .await
             .unwrap();
+        let sessions = SessionRepository::list_for_user(user.id, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert!(sessions.is_empty());
+
+        // Test delete by id and for user
+        let first =
+            SessionRepository::create(user.id, &session.capabilities, &mut db.pool().into())
+                .await
+                .unwrap();
+        let first = SessionRepository::get_by_secret(&first, &mut db.pool().into())
+            .await
+            .unwrap();
+        SessionRepository::create(user.id, &session.capabilities, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert!(
+            !SessionRepository::delete_by_id(user.id + 1, first.id, &mut db.pool().into())
+                .await
+                .unwrap()
+        );
+        assert!(
+            SessionRepository::delete_by_id(user.id, first.id, &mut db.pool().into())
+                .await
+                .unwrap()
+        );
+        let deleted = SessionRepository::delete_all_for_user(user.id, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(deleted.len(), 1);
 
         // Test get session again
         let result = SessionRepository::get_by_secret(&session.secret, &mut db.pool().into()).await;
@@ -1,6 +1,6 @@
 //! Cookie-based authentication route handlers.
 //!
-//! Contains all cookie-specific handlers: signup, signin, get_session, signout.
+//! Contains all cookie-specific handlers: signup, signin, get_session, get_session_usage, signout.
 //! Each handler is a full axum handler wired directly from `router.rs`.
 
 use crate::persistence::sql::signup_code::SignupCode;
@@ -13,6 +13,7 @@ use axum::{
     http::StatusCode,
     http::{header, HeaderValue},
     response::IntoResponse,
+    Json,
 };
 use axum_extra::extract::Host;
 use bytes::Bytes;
@@ -99,6 +100,19 @@ pub async fn get_session(
     Ok(resp)
 }
 
+/// `GET /session/usage` — returns the user's usage against their request and
+/// transfer caps as JSON. `GET /session` keeps its postcard body for old clients.
+pub async fn get_session_usage(
+    State(state): State<AuthState>,
+    auth: crate::client_server::auth::AuthSession,
+) -> HttpResult<impl IntoResponse> {
+    let crate::client_server::auth::AuthSession::Cookie(cookie_session) = auth else {
+        return Err(HttpError::unauthorized());
+    };
+    let usage = state.cookie_auth_service.usage(&cookie_session).await?;
+    Ok(Json(usage))
+}
+
 /// `DELETE /session` — idempotently deletes the DB session (if any) and sets a removal cookie.
 ///
 /// Takes `Option<AuthSession>` rather than `AuthSession` so a second signout with an
@@ -1,10 +1,15 @@
 //! Cookie auth service — orchestrates deprecated cookie auth use cases.
 
 use pubky_common::{
-    auth::AuthToken, capabilities::Capabilities, crypto::PublicKey, session::CookieSessionRecord,
+    auth::{grant_session_responses::SessionUsage, AuthToken},
+    capabilities::Capabilities,
+    crypto::PublicKey,
+    session::CookieSessionRecord,
 };
 
 use crate::persistence::sql::{signup_code::SignupCode, uexecutor, SqlDb};
+use crate::services::config_service::LiveConfig;
+use crate::services::usage_service::UsageService;
 use crate::services::user_service::UserService;
 use crate::shared::{HttpError, HttpResult};
 
@@ -18,6 +23,9 @@ pub(crate) struct CookieAuthService {
     user_service: UserService,
     verifier: CookieAuthVerifier,
     signup_service: SignupService,
+    usage_service: UsageService,
+    /// Source of the reloadable `[default_quotas]`.
+    config: LiveConfig,
 }
 
 impl CookieAuthService {
@@ -28,6 +36,8 @@ impl CookieAuthService {
             user_service: context.user_service.clone(),
             verifier: CookieAuthVerifier::default(),
             signup_service: SignupService::from_context(context),
+            usage_service: context.usage_service.clone(),
+            config: context.config_service.live().clone(),
         }
     }
 
@@ -97,6 +107,19 @@ impl CookieAuthService {
         Ok(())
     }
 
+    /// The session user's usage against their effective request and transfer caps.
+    pub(crate) async fn usage(&self, session: &SessionEntity) -> HttpResult<SessionUsage> {
+        let pubkey = &session.user_pubkey;
+        let Some(quota) = self.user_service.resolve_quota(pubkey).await? else {
+            return Err(HttpError::not_found());
+        };
+        let config = self.config.get();
+        Ok(self
+            .usage_service
+            .session_usage(pubkey, &quota, &config.default_quotas)
+            .await?)
+    }
+
     /// Recheck that this exact session row still exists before opening a
     /// private long-lived stream.
     pub(crate) async fn validate_active_session(&self, session: &SessionEntity) -> HttpResult<()> {
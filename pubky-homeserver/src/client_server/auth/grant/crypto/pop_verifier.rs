@@ -43,8 +43,9 @@ pub struct PopProof {
 pub struct PopVerificationContext<'a> {
     /// The client's public key from the Grant's `cnf` claim.
     pub cnf_key: &'a PublicKey,
-    /// The homeserver's own public key z32 string.
-    pub expected_audience: &'a str,
+    /// The keys the homeserver answers to: its own key and, during a key
+    /// transition, the previous one.
+    pub expected_audiences: &'a [PublicKey],
     /// The Grant's `jti` — the PoP's `gid` must match.
     pub expected_grant_id: &'a GrantId,
 }
@@ -54,14 +55,14 @@ impl PopProof {
     ///
     /// Checks:
     /// 1. Ed25519 signature is valid against `cnf_key`
-    /// 2. `aud` matches the expected homeserver
+    /// 2. `aud` is one of the expected homeserver keys
     /// 3. `iat` is within the ±3 minute window
     ///
     /// Nonce replay checking is done separately via the database.
     pub fn verify(compact: &JwsCompact, context: &PopVerificationContext) -> Result<Self, Error> {
         let raw = verify_signature(compact.as_str(), context.cnf_key)?;
         check_header_type(compact.as_str())?;
-        check_audience(&raw, context.expected_audience)?;
+        check_audience(&raw, context.expected_audiences)?;
         check_grant_binding(&raw, context.expected_grant_id)?;
         check_timestamp(&raw)?;
         parse_verified_pop(raw)
@@ -85,8 +86,8 @@ fn verify_signature(compact: &str, cnf_key: &PublicKey) -> Result<PopProofClaims
     Ok(token_data.claims)
 }
 
-fn check_audience(raw: &PopProofClaims, expected: &str) -> Result<(), Error> {
-    if raw.aud.z32() != expected {
+fn check_audience(raw: &PopProofClaims, expected: &[PublicKey]) -> Result<(), Error> {
+    if !expected.contains(&raw.aud) {
         return Err(Error::AudienceMismatch);
     }
     Ok(())
@@ -183,10 +184,10 @@ mod tests {
         let compact = JwsCompact::parse(&compact_str).unwrap();
 
         let cnf_key = client_kp.public_key();
-        let aud = hs_kp.public_key().z32();
+        let aud = [hs_kp.public_key()];
         let context = PopVerificationContext {
             cnf_key: &cnf_key,
-            expected_audience: &aud,
+            expected_audiences: &aud,
             expected_grant_id: &raw.gid,
         };
         let pop = PopProof::verify(&compact, &context).unwrap();
@@ -201,10 +202,10 @@ mod tests {
         let compact = sign_pop(&client_kp, &raw);
 
         let cnf_key = client_kp.public_key();
-        let aud = hs_kp.public_key().z32();
+        let aud = [hs_kp.public_key()];
         let context = PopVerificationContext {
             cnf_key: &cnf_key,
-            expected_audience: &aud,
+            expected_audiences: &aud,
             expected_grant_id: &raw.gid,
         };
 
@@ -222,10 +223,10 @@ mod tests {
         let compact = sign_pop(&client_kp, &raw);
 
         let wrong_pk = wrong_kp.public_key();
-        let aud = hs_kp.public_key().z32();
+        let aud = [hs_kp.public_key()];
         let context = PopVerificationContext {
             cnf_key: &wrong_pk,
-            expected_audience: &aud,
+            expected_audiences: &aud,
             expected_grant_id: &raw.gid,
         };
 
@@ -241,9 +242,10 @@ mod tests {
         let compact = sign_pop(&client_kp, &raw);
 
         let cnf_key = client_kp.public_key();
+        let aud = [Keypair::random().public_key()];
         let context = PopVerificationContext {
             cnf_key: &cnf_key,
-            expected_audience: "wrong-audience",
+            expected_audiences: &aud,
             expected_grant_id: &raw.gid,
         };
 
@@ -251,6 +253,25 @@ mod tests {
         assert!(matches!(result, Err(Error::AudienceMismatch)));
     }
 
+    #[test]
+    fn accept_previous_homeserver_key_as_audience() {
+        let client_kp = Keypair::random();
+        let previous_hs_kp = Keypair::random();
+        let raw = make_valid_pop(&previous_hs_kp);
+        let compact = sign_pop(&client_kp, &raw);
+
+        let cnf_key = client_kp.public_key();
+        let aud = [Keypair::random().public_key(), previous_hs_kp.public_key()];
+        let context = PopVerificationContext {
+            cnf_key: &cnf_key,
+            expected_audiences: &aud,
+            expected_grant_id: &raw.gid,
+        };
+
+        let pop = PopProof::verify(&compact, &context).unwrap();
+        assert_eq!(pop.grant_id, raw.gid);
+    }
+
     #[test]
     fn reject_wrong_grant_id() {
         let client_kp = Keypair::random();
@@ -259,11 +280,11 @@ mod tests {
         let compact = sign_pop(&client_kp, &raw);
 
         let cnf_key = client_kp.public_key();
-        let aud = hs_kp.public_key().z32();
+        let aud = [hs_kp.public_key()];
         let wrong_gid = GrantId::generate();
         let context = PopVerificationContext {
             cnf_key: &cnf_key,
-            expected_audience: &aud,
+            expected_audiences: &aud,
             expected_grant_id: &wrong_gid,
         };
 
@@ -284,10 +305,10 @@ mod tests {
             JwsCompact::parse(&jsonwebtoken::encode(&header, &raw, &enc).unwrap()).unwrap();
 
         let cnf_key = client_kp.public_key();
-        let aud = hs_kp.public_key().z32();
+        let aud = [hs_kp.public_key()];
         let context = PopVerificationContext {
             cnf_key: &cnf_key,
-            expected_audience: &aud,
+            expected_audiences: &aud,
             expected_grant_id: &raw.gid,
         };
 
@@ -304,10 +325,10 @@ mod tests {
         let compact = sign_pop(&client_kp, &raw);
 
         let cnf_key = client_kp.public_key();
-        let aud = hs_kp.public_key().z32();
+        let aud = [hs_kp.public_key()];
         let context = PopVerificationContext {
             cnf_key: &cnf_key,
-            expected_audience: &aud,
+            expected_audiences: &aud,
             expected_grant_id: &raw.gid,
         };
 
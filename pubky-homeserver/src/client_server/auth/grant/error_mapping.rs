@@ -33,6 +33,9 @@ impl From<AuthServiceError> for HttpError {
             AuthServiceError::SignupTokenAlreadyUsed => {
                 HttpError::unauthorized_with_message("Token already used")
             }
+            AuthServiceError::KeyBanned => {
+                HttpError::forbidden_with_message("Public key is banned")
+            }
             AuthServiceError::NonceReplay => {
                 HttpError::unauthorized_with_message("PoP nonce already used")
             }
@@ -130,6 +133,7 @@ mod tests {
             AuthServiceError::SignupTokenAlreadyUsed,
             StatusCode::UNAUTHORIZED,
         );
+        assert_status(AuthServiceError::KeyBanned, StatusCode::FORBIDDEN);
     }
 
     #[test]
@@ -108,6 +108,52 @@ impl GrantRepository {
         Ok(())
     }
 
+    /// Revoke all grants of a user that are not revoked yet.
+    /// Returns the ids of the newly revoked grants.
+    pub async fn revoke_all_for_user<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<GrantId>, sqlx::Error> {
+        Self::revoke_where(Expr::col(GrantIden::User).eq(user_id), executor).await
+    }
+
+    /// Revoke the grants of all users issued to `client_id` that are not revoked yet.
+    /// Returns the ids of the newly revoked grants.
+    pub async fn revoke_all_for_client<'a>(
+        client_id: &ClientId,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<GrantId>, sqlx::Error> {
+        Self::revoke_where(
+            Expr::col(GrantIden::ClientId).eq(client_id.to_string()),
+            executor,
+        )
+        .await
+    }
+
+    async fn revoke_where<'a>(
+        condition: SimpleExpr,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<GrantId>, sqlx::Error> {
+        let now = chrono::Utc::now().timestamp();
+        let statement = Query::update()
+            .table(GRANTS_TABLE)
+            .value(GrantIden::RevokedAt, SimpleExpr::Value(now.into()))
+            .and_where(condition)
+            .and_where(Expr::col(GrantIden::RevokedAt).is_null())
+            .returning_col(GrantIden::Id)
+            .to_owned();
+
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let rows: Vec<PgRow> = sqlx::query_with(&query, values).fetch_all(con).await?;
+        rows.iter()
+            .map(|row| {
+                let id: String = row.try_get(GrantIden::Id.to_string().as_str())?;
+                GrantId::parse(&id).map_err(|e| sqlx::Error::Decode(e.into()))
+            })
+            .collect()
+    }
+
     /// Check if a grant has been revoked.
     pub async fn is_revoked<'a>(
         grant_id: &GrantId,
@@ -387,6 +433,51 @@ mod tests {
         assert!(entity.revoked_at.is_some());
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_revoke_all_for_client_and_user() {
+        let db = SqlDb::test().await;
+        let user = UserService::new(db.clone())
+            .create(&Keypair::random().public_key())
+            .await
+            .unwrap();
+        let other_user = UserService::new(db.clone())
+            .create(&Keypair::random().public_key())
+            .await
+            .unwrap();
+
+        let first = make_new_grant(user.id);
+        let second = make_new_grant(other_user.id);
+        let mut other_client = make_new_grant(user.id);
+        other_client.client_id = ClientId::new("other.app").unwrap();
+        for grant in [&first, &second, &other_client] {
+            GrantRepository::create(grant, &mut db.pool().into())
+                .await
+                .unwrap();
+        }
+
+        let client_id = ClientId::new("test.app").unwrap();
+        let mut revoked = GrantRepository::revoke_all_for_client(&client_id, &mut db.pool().into())
+            .await
+            .unwrap();
+        revoked.sort_by_key(|id| id.to_string());
+        let mut expected = vec![first.id.clone(), second.id.clone()];
+        expected.sort_by_key(|id| id.to_string());
+        assert_eq!(revoked, expected);
+
+        // Already revoked grants are not reported again.
+        let revoked = GrantRepository::revoke_all_for_user(user.id, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(revoked, vec![other_client.id.clone()]);
+        assert!(
+            GrantRepository::list_active_for_user(user.id, &mut db.pool().into())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[tokio::test]
     #[pubky_test_utils::test]
     async fn test_list_active_for_user() {
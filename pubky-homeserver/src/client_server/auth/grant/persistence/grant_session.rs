@@ -2,14 +2,17 @@
 
 use pubky_common::auth::jws::GrantId;
 use sea_query::{
-    Alias, CommonTableExpression, Expr, Iden, PostgresQueryBuilder, Query, WithClause, WithQuery,
+    Alias, CommonTableExpression, Expr, Iden, Order, PostgresQueryBuilder, Query, WithClause,
+    WithQuery,
 };
 use sea_query_binder::SqlxBinder;
 use sqlx::{postgres::PgRow, FromRow, Row};
 
 use crate::client_server::auth::grant::crypto::session_token::SessionTokenHash;
 use crate::persistence::sql::{
-    migrations::m20260325_create_grant_sessions::{GrantSessionIden, GRANT_SESSIONS_TABLE},
+    migrations::m20260325_create_grant_sessions::{
+        GrantIden, GrantSessionIden, GRANTS_TABLE, GRANT_SESSIONS_TABLE,
+    },
     UnifiedExecutor,
 };
 
@@ -89,6 +92,60 @@ impl GrantSessionRepository {
         sqlx::query_as_with(&query, values).fetch_one(con).await
     }
 
+    /// List the unexpired sessions of a user's active (non-revoked, non-expired) grants.
+    pub async fn list_active_for_user<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<GrantSessionEntity>, sqlx::Error> {
+        let now = chrono::Utc::now().timestamp();
+        let statement = Query::select()
+            .from(GRANT_SESSIONS_TABLE)
+            .columns([
+                (GRANT_SESSIONS_TABLE, GrantSessionIden::Id),
+                (GRANT_SESSIONS_TABLE, GrantSessionIden::TokenHash),
+                (GRANT_SESSIONS_TABLE, GrantSessionIden::GrantId),
+                (GRANT_SESSIONS_TABLE, GrantSessionIden::ExpiresAt),
+                (GRANT_SESSIONS_TABLE, GrantSessionIden::CreatedAt),
+            ])
+            .inner_join(
+                GRANTS_TABLE,
+                Expr::col((GRANT_SESSIONS_TABLE, GrantSessionIden::GrantId))
+                    .eq(Expr::col((GRANTS_TABLE, GrantIden::Id))),
+            )
+            .and_where(Expr::col((GRANTS_TABLE, GrantIden::User)).eq(user_id))
+            .and_where(Expr::col((GRANTS_TABLE, GrantIden::RevokedAt)).is_null())
+            .and_where(Expr::col((GRANTS_TABLE, GrantIden::ExpiresAt)).gt(now))
+            .and_where(Expr::col((GRANT_SESSIONS_TABLE, GrantSessionIden::ExpiresAt)).gt(now))
+            .order_by((GRANT_SESSIONS_TABLE, GrantSessionIden::Id), Order::Asc)
+            .to_owned();
+
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values).fetch_all(con).await
+    }
+
+    /// Delete all sessions of the given grants (used on bulk revocation).
+    pub async fn delete_all_for_grants<'a>(
+        grant_ids: &[GrantId],
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        if grant_ids.is_empty() {
+            return Ok(());
+        }
+        let statement = Query::delete()
+            .from_table(GRANT_SESSIONS_TABLE)
+            .and_where(
+                Expr::col(GrantSessionIden::GrantId)
+                    .is_in(grant_ids.iter().map(|id| id.to_string())),
+            )
+            .to_owned();
+
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_with(&query, values).execute(con).await?;
+        Ok(())
+    }
+
     /// Delete all sessions for a given grant (used on revocation).
     pub async fn delete_all_for_grant<'a>(
         grant_id: &GrantId,
@@ -115,7 +172,7 @@ pub struct NewGrantSession {
 
 /// A grant session entity as stored in the database.
 #[derive(Debug, Clone)]
-#[allow(dead_code)] // `id`, `token_hash`, `created_at` are decoded from DB rows for completeness but only consumed in tests.
+#[allow(dead_code)] // `id` and `token_hash` are decoded from DB rows for completeness but only consumed in tests.
 pub struct GrantSessionEntity {
     pub id: i32,
     pub token_hash: SessionTokenHash,
@@ -338,6 +395,38 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_list_active_for_user() {
+        let db = SqlDb::test().await;
+        let grant_id = setup_user_and_grant(&db).await;
+        let grant = GrantRepository::get_by_id(&grant_id, &mut db.pool().into())
+            .await
+            .unwrap();
+
+        let (session, hash) = make_new_session(&grant_id);
+        GrantSessionRepository::replace_for_grant(&session, &mut db.pool().into())
+            .await
+            .unwrap();
+
+        let sessions =
+            GrantSessionRepository::list_active_for_user(grant.user_id, &mut db.pool().into())
+                .await
+                .unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].token_hash, hash);
+
+        // Sessions of revoked grants are not active.
+        GrantRepository::revoke(&grant_id, &mut db.pool().into())
+            .await
+            .unwrap();
+        let sessions =
+            GrantSessionRepository::list_active_for_user(grant.user_id, &mut db.pool().into())
+                .await
+                .unwrap();
+        assert!(sessions.is_empty());
+    }
+
     #[tokio::test]
     #[pubky_test_utils::test]
     async fn test_get_nonexistent_session() {
@@ -4,11 +4,14 @@
 //! verification, persistence, and minting steps directly.
 
 use crate::persistence::sql::{signup_code::SignupCode, uexecutor, SqlDb, UnifiedExecutor};
+use crate::services::config_service::LiveConfig;
+use crate::services::usage_service::UsageService;
 use crate::services::user_service::{UserEntity, UserService};
+use crate::shared::HomeserverKeys;
 use chrono::Utc;
 use pubky_common::{
     auth::grant::GrantClaims,
-    auth::grant_session_responses::{GrantSessionInfo, GrantSessionResponse},
+    auth::grant_session_responses::{GrantSessionInfo, GrantSessionResponse, SessionUsage},
     auth::jws::GrantId,
     crypto::PublicKey,
 };
@@ -46,9 +49,13 @@ const MAX_SIGNUP_GRANT_LIFETIME_SECS: u64 = 5 * 60;
 #[derive(Clone, Debug)]
 pub struct GrantAuthService {
     sql_db: SqlDb,
-    homeserver_public_key: PublicKey,
+    /// The keys PoP proofs may be addressed to. Responses name the current one.
+    homeserver_keys: HomeserverKeys,
     signup_service: SignupService,
     user_service: UserService,
+    usage_service: UsageService,
+    /// Source of the reloadable `[default_quotas]`.
+    config: LiveConfig,
 }
 
 impl GrantAuthService {
@@ -56,9 +63,11 @@ impl GrantAuthService {
     pub fn from_context(context: &crate::AppContext) -> Self {
         Self {
             sql_db: context.sql_db.clone(),
-            homeserver_public_key: context.keypair.public_key(),
+            homeserver_keys: context.homeserver_keys(),
             signup_service: SignupService::from_context(context),
             user_service: context.user_service.clone(),
+            usage_service: context.usage_service.clone(),
+            config: context.config_service.live().clone(),
         }
     }
 
@@ -71,17 +80,19 @@ impl GrantAuthService {
         user_service: UserService,
     ) -> Self {
         Self {
+            usage_service: UsageService::new(sql_db.clone()),
             sql_db,
-            homeserver_public_key,
+            homeserver_keys: HomeserverKeys::new(homeserver_public_key),
             signup_service,
             user_service,
+            config: LiveConfig::new(Default::default()),
         }
     }
 
     /// The homeserver's public key (used by tests as the PoP audience).
     #[cfg(test)]
     pub fn homeserver_public_key(&self) -> pubky_common::crypto::PublicKey {
-        self.homeserver_public_key.clone()
+        self.homeserver_keys.current().clone()
     }
 
     /// Full grant-based session creation: verify → find user → store → mint.
@@ -151,9 +162,10 @@ impl GrantAuthService {
         session: &GrantSession,
     ) -> Result<GrantSessionInfo, AuthServiceError> {
         let grant = self.get_grant(&session.grant_id).await?;
+        let usage = self.get_session_usage(&session.user_key).await?;
 
         Ok(GrantSessionInfo {
-            homeserver: self.homeserver_public_key.clone(),
+            homeserver: self.homeserver_keys.current().clone(),
             pubky: session.user_key.clone(),
             client_id: grant.client_id.clone(),
             capabilities: session.capabilities.to_vec(),
@@ -161,9 +173,27 @@ impl GrantAuthService {
             token_expires_at: session.token_expires_at,
             grant_expires_at: grant.expires_at as u64,
             created_at: grant.created_at.and_utc().timestamp() as u64,
+            usage,
         })
     }
 
+    /// The user's usage against their effective request and transfer caps.
+    /// Returns `None` for unknown users.
+    async fn get_session_usage(
+        &self,
+        pubkey: &PublicKey,
+    ) -> Result<Option<SessionUsage>, AuthServiceError> {
+        let Some(quota) = self.user_service.resolve_quota(pubkey).await? else {
+            return Ok(None);
+        };
+        let config = self.config.get();
+        let usage = self
+            .usage_service
+            .session_usage(pubkey, &quota, &config.default_quotas)
+            .await?;
+        Ok(Some(usage))
+    }
+
     /// Sign out: Revoke its grant and delete all sessions.
     pub async fn signout_grant_session(
         &self,
@@ -354,10 +384,10 @@ impl GrantAuthService {
         compact: &JwsCompact,
         grant: &GrantClaims,
     ) -> Result<PopProof, AuthServiceError> {
-        let hs_pubkey_z32 = self.homeserver_public_key.z32();
+        let audiences = self.homeserver_keys.accepted();
         let context = PopVerificationContext {
             cnf_key: &grant.cnf,
-            expected_audience: &hs_pubkey_z32,
+            expected_audiences: &audiences,
             expected_grant_id: &grant.jti,
         };
         Ok(PopProof::verify(compact, &context)?)
@@ -437,7 +467,7 @@ impl GrantAuthService {
         Ok(build_session_response(
             bearer.into_string(),
             grant,
-            self.homeserver_public_key.clone(),
+            self.homeserver_keys.current().clone(),
             expires_at,
             now,
         ))
@@ -473,6 +503,7 @@ fn build_session_response(
             token_expires_at,
             grant_expires_at: grant.exp,
             created_at: now,
+            usage: None,
         },
     }
 }
@@ -1015,6 +1046,9 @@ mod tests {
         let info = service.get_grant_session_info(&session).await.unwrap();
         assert_eq!(info.pubky, user_kp.public_key());
         assert_eq!(info.homeserver, service.homeserver_public_key());
+        let usage = info.usage.expect("usage of a known user");
+        assert_eq!(usage.requests_per_minute, None);
+        assert_eq!(usage.egress_daily.limit_bytes, None);
     }
 
     // ── revoke_user_grant ─────────────────────────────────────────
@@ -42,6 +42,10 @@ pub enum AuthServiceError {
     #[error("Token already used")]
     SignupTokenAlreadyUsed,
 
+    /// The public key was banned by an admin.
+    #[error("Public key is banned")]
+    KeyBanned,
+
     /// PoP nonce was already used (replay attack).
     #[error("PoP nonce already used")]
     NonceReplay,
@@ -95,6 +99,7 @@ impl From<SignupServiceError> for AuthServiceError {
             SignupServiceError::SignupTokenRequired => Self::SignupTokenRequired,
             SignupServiceError::InvalidSignupToken => Self::InvalidSignupToken,
             SignupServiceError::SignupTokenAlreadyUsed => Self::SignupTokenAlreadyUsed,
+            SignupServiceError::KeyBanned => Self::KeyBanned,
             SignupServiceError::Internal(e) => Self::Internal(e),
         }
     }
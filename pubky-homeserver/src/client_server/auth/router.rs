@@ -41,10 +41,12 @@ pub fn base_router(auth_state: AuthState) -> Router<()> {
 /// Global authentication resolves credentials into `AuthSession`; handlers
 /// enforce whether they accept cookie or grant sessions.
 pub fn tenant_router(auth_state: AuthState) -> Router<()> {
-    let cookie_routes = Router::new().route(
-        "/session",
-        get(cookie::routes::get_session).delete(cookie::routes::signout),
-    );
+    let cookie_routes = Router::new()
+        .route(
+            "/session",
+            get(cookie::routes::get_session).delete(cookie::routes::signout),
+        )
+        .route("/session/usage", get(cookie::routes::get_session_usage));
 
     let grant_routes = Router::new()
         .route(
@@ -1,9 +1,11 @@
 //! Signup service — owns signup policy and user creation.
 
 use crate::persistence::sql::{
+    banned_key::BannedKeyRepository,
     signup_code::{SignupCode, SignupCodeRepository},
     uexecutor, SqlDb,
 };
+use crate::services::config_service::LiveConfig;
 use crate::services::user_service::{UserEntity, UserService};
 use crate::shared::user_quota::UserQuota;
 use crate::SignupMode;
@@ -28,6 +30,10 @@ pub enum SignupServiceError {
     #[error("Token already used")]
     SignupTokenAlreadyUsed,
 
+    /// The public key was banned by an admin.
+    #[error("Public key is banned")]
+    KeyBanned,
+
     /// Database or infrastructure error.
     #[error("Internal error: {0}")]
     Internal(#[from] sqlx::Error),
@@ -36,7 +42,8 @@ pub enum SignupServiceError {
 #[derive(Clone, Debug)]
 pub struct SignupService {
     sql_db: SqlDb,
-    signup_mode: SignupMode,
+    /// Source of the reloadable `signup_mode`.
+    config: LiveConfig,
     user_service: UserService,
 }
 
@@ -45,7 +52,7 @@ impl SignupService {
     pub(crate) fn from_context(context: &crate::AppContext) -> Self {
         Self {
             sql_db: context.sql_db.clone(),
-            signup_mode: context.config_toml.general.signup_mode.clone(),
+            config: context.config_service.live().clone(),
             user_service: context.user_service.clone(),
         }
     }
@@ -55,15 +62,18 @@ impl SignupService {
     pub fn new(sql_db: SqlDb, signup_mode: SignupMode, user_service: UserService) -> Self {
         Self {
             sql_db,
-            signup_mode,
+            config: LiveConfig::new(crate::services::config_service::ReloadableConfig {
+                signup_mode,
+                ..Default::default()
+            }),
             user_service,
         }
     }
 
     /// Creates a new user in its own transaction.
     ///
-    /// Rejects existing users and enforces signup-token validation when the
-    /// homeserver is configured with [`SignupMode::TokenRequired`].
+    /// Rejects existing users and banned keys, and enforces signup-token validation
+    /// when the homeserver is configured with [`SignupMode::TokenRequired`].
     pub async fn create_new_user(
         &self,
         public_key: &PublicKey,
@@ -93,7 +103,10 @@ impl SignupService {
         tx: &mut sqlx::Transaction<'static, sqlx::Postgres>,
     ) -> Result<UserEntity, SignupServiceError> {
         self.ensure_user_not_exists(public_key, tx).await?;
-        let quota = if self.signup_mode == SignupMode::TokenRequired {
+        if BannedKeyRepository::is_banned(public_key, uexecutor!(*tx)).await? {
+            return Err(SignupServiceError::KeyBanned);
+        }
+        let quota = if self.config.get().signup_mode == SignupMode::TokenRequired {
             Self::validate_and_consume_signup_token(signup_token, public_key, tx).await?
         } else {
             UserQuota::default()
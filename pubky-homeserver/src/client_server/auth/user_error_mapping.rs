@@ -18,6 +18,9 @@ impl From<SignupServiceError> for HttpError {
             SignupServiceError::SignupTokenAlreadyUsed => {
                 HttpError::unauthorized_with_message("Token already used")
             }
+            SignupServiceError::KeyBanned => {
+                HttpError::forbidden_with_message("Public key is banned")
+            }
             SignupServiceError::Internal(e) => {
                 HttpError::internal_server_and_log(format!("Signup service: {e}"))
             }
@@ -0,0 +1,156 @@
+//! Rate-limiter state backends.
+//!
+//! - [`InMemoryLimiter`]: governor limiters local to this process. Each homeserver
+//!   instance grants the full quota.
+//! - [`PgLimiter`]: GCRA state in Postgres, shared by every instance using the same
+//!   database, so limits hold cluster-wide.
+//!
+//! The backend is selected by `[drive] rate_limiter_backend`.
+
+use std::num::NonZero;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use governor::clock::{Clock, QuantaClock};
+use governor::state::keyed::DashMapStateStore;
+use governor::{InsufficientCapacity, Jitter, Quota, RateLimiter};
+
+use crate::quota_config::LimitKey;
+use crate::RateLimiterBackend;
+
+use super::pg_limiter::{PgLimiter, PgRateLimitStore};
+use super::CLEANUP_INTERVAL_SECS;
+
+type KeyedRateLimiter = RateLimiter<LimitKey, DashMapStateStore<LimitKey>, QuantaClock>;
+
+/// A request was rejected by a limiter.
+#[derive(Debug, thiserror::Error)]
+#[error("retry in {wait:?}")]
+pub(super) struct RateLimited {
+    /// Earliest time after which the same request could succeed.
+    pub wait: Duration,
+}
+
+/// A keyed rate limiter, independent of where its state is stored.
+#[async_trait]
+pub(super) trait KeyedLimiter: Send + Sync + std::fmt::Debug {
+    /// Consume one cell for `key` if available, without waiting.
+    async fn check_key(&self, key: &LimitKey) -> Result<(), RateLimited>;
+
+    /// Wait until `cells` cells have been consumed for `key`.
+    /// Used to throttle body streams, where a cell is one kilobyte.
+    async fn until_key_n_ready(
+        &self,
+        key: &LimitKey,
+        cells: usize,
+    ) -> Result<(), InsufficientCapacity>;
+
+    /// Whether the limiter tracks no keys anymore and can be dropped.
+    fn is_idle(&self) -> bool {
+        false
+    }
+}
+
+/// Creates limiters on the configured backend.
+#[derive(Debug, Clone)]
+pub(super) enum LimiterBackend {
+    InMemory,
+    Postgres(PgRateLimitStore),
+}
+
+impl LimiterBackend {
+    /// Build the backend configured in `[drive] rate_limiter_backend`.
+    pub fn from_context(context: &crate::AppContext) -> Self {
+        match context.config_toml.drive.rate_limiter_backend {
+            RateLimiterBackend::InMemory => Self::InMemory,
+            RateLimiterBackend::Postgres => {
+                Self::Postgres(PgRateLimitStore::new(context.sql_db.pool().clone()))
+            }
+        }
+    }
+
+    /// Create a limiter for `quota`.
+    ///
+    /// `namespace` identifies the limit across instances, so every instance must derive
+    /// it from configuration only. Ignored by the in-memory backend.
+    pub fn create(&self, namespace: String, quota: Quota) -> Arc<dyn KeyedLimiter> {
+        match self {
+            Self::InMemory => Arc::new(InMemoryLimiter::new(quota)),
+            Self::Postgres(store) => Arc::new(PgLimiter::new(store.clone(), namespace, quota)),
+        }
+    }
+}
+
+/// Jitter added when waiting for a limiter, to avoid a thundering herd.
+/// When the rate limit is exceeded, we wait between 25ms and 500ms before retrying.
+/// This is to avoid overwhelming the server with requests when the rate limit is exceeded.
+pub(super) fn retry_jitter() -> Jitter {
+    Jitter::new(Duration::from_millis(25), Duration::from_millis(500))
+}
+
+/// Governor limiter local to this process.
+#[derive(Debug)]
+pub(super) struct InMemoryLimiter {
+    limiter: Arc<KeyedRateLimiter>,
+}
+
+impl InMemoryLimiter {
+    /// Create the limiter and spawn a background task that forgets unused keys.
+    pub fn new(quota: Quota) -> Self {
+        let limiter = Arc::new(RateLimiter::keyed(quota));
+
+        // Forget keys that are not used anymore. This is to prevent memory leaks.
+        // Uses a Weak reference so the task self-terminates when the limiter is dropped.
+        let weak = Arc::downgrade(&limiter);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(limiter) = weak.upgrade() else {
+                    break;
+                };
+                limiter.retain_recent();
+                limiter.shrink_to_fit();
+            }
+        });
+
+        Self { limiter }
+    }
+}
+
+#[async_trait]
+impl KeyedLimiter for InMemoryLimiter {
+    async fn check_key(&self, key: &LimitKey) -> Result<(), RateLimited> {
+        self.limiter
+            .check_key(key)
+            .map_err(|not_until| RateLimited {
+                wait: not_until.wait_time_from(self.limiter.clock().now()),
+            })
+    }
+
+    async fn until_key_n_ready(
+        &self,
+        key: &LimitKey,
+        cells: usize,
+    ) -> Result<(), InsufficientCapacity> {
+        // One kilobyte at a time, see `throttle_body`.
+        for _ in 0..cells {
+            self.limiter
+                .until_key_n_ready_with_jitter(
+                    key,
+                    NonZero::new(1).expect("1 is always non zero"),
+                    retry_jitter(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    fn is_idle(&self) -> bool {
+        self.limiter.retain_recent();
+        self.limiter.shrink_to_fit();
+        self.limiter.is_empty()
+    }
+}
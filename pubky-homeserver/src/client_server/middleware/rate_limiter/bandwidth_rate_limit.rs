@@ -1,7 +1,7 @@
 //! Bandwidth (speed) rate limiting layer.
 //!
-//! Throttles upload and download body streams using governor-based
-//! per-key bandwidth limits. Supports per-user overrides from `UserQuota`
+//! Throttles upload and download body streams using per-key bandwidth
+//! limits on the configured limiter backend. Supports per-user overrides from `UserQuota`
 //! and unauthenticated IP-keyed read limits.
 
 use axum::response::{IntoResponse, Response};
@@ -10,22 +10,21 @@ use axum::{
     http::{Request, StatusCode},
 };
 use futures_util::future::BoxFuture;
-use governor::RateLimiter;
 use std::sync::Arc;
-use std::time::Duration;
 use std::{convert::Infallible, task::Poll};
 use tower::{Layer, Service};
 
 use crate::client_server::middleware::request_tenant::RequestTenant;
 use crate::quota_config::LimitKey;
+use crate::services::config_service::LiveConfig;
 use crate::services::user_service::UserService;
 use crate::shared::HttpError;
 use crate::DefaultQuotasToml;
 
-use super::limiter_pool::{KeyedRateLimiter, LimiterPool};
+use super::backend::{KeyedLimiter, LimiterBackend};
+use super::limiter_pool::LimiterPool;
 use super::request_info::{is_write_method, RequestInfo};
 use super::throttle::{throttle_request, throttle_response};
-use super::CLEANUP_INTERVAL_SECS;
 
 /// A Tower Layer for bandwidth (speed) rate limiting.
 ///
@@ -39,7 +38,8 @@ use super::CLEANUP_INTERVAL_SECS;
 #[derive(Debug, Clone)]
 pub struct BandwidthQuotaLimitLayer {
     user_service: UserService,
-    defaults: DefaultQuotasToml,
+    config: LiveConfig,
+    backend: LimiterBackend,
 }
 
 impl BandwidthQuotaLimitLayer {
@@ -47,16 +47,21 @@ impl BandwidthQuotaLimitLayer {
     pub fn from_context(context: &crate::AppContext) -> Self {
         Self {
             user_service: context.user_service.clone(),
-            defaults: context.config_toml.default_quotas.clone(),
+            config: context.config_service.live().clone(),
+            backend: LimiterBackend::from_context(context),
         }
     }
 
-    /// Creates the layer with explicit services (test-only).
+    /// Creates the layer with explicit services and in-memory limiters (test-only).
     #[cfg(test)]
     pub fn new(user_service: UserService, defaults: DefaultQuotasToml) -> Self {
         Self {
             user_service,
-            defaults,
+            config: LiveConfig::new(crate::services::config_service::ReloadableConfig {
+                default_quotas: defaults,
+                ..Default::default()
+            }),
+            backend: LimiterBackend::InMemory,
         }
     }
 }
@@ -65,7 +70,11 @@ impl<S> Layer<S> for BandwidthQuotaLimitLayer {
     type Service = BandwidthQuotaLimitMiddleware<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        let state = BandwidthState::new(self.user_service.clone(), self.defaults.clone());
+        let state = BandwidthState::new(
+            self.user_service.clone(),
+            self.config.clone(),
+            &self.backend,
+        );
         BandwidthQuotaLimitMiddleware { inner, state }
     }
 }
@@ -74,50 +83,33 @@ impl<S> Layer<S> for BandwidthQuotaLimitLayer {
 #[derive(Debug, Clone)]
 struct BandwidthState {
     user_service: UserService,
-    defaults: DefaultQuotasToml,
+    config: LiveConfig,
     user_read_limiters: LimiterPool,
     user_write_limiters: LimiterPool,
-    unauthenticated_read_limiter: Option<Arc<KeyedRateLimiter>>,
+    /// Keyed by the configured `unauthenticated_ip_rate_read`, so a reload picks up a new rate.
+    unauthenticated_read_limiters: LimiterPool,
 }
 
 impl BandwidthState {
-    fn new(user_service: UserService, defaults: DefaultQuotasToml) -> Self {
-        let unauthenticated_read_limiter =
-            defaults.unauthenticated_ip_rate_read.as_ref().map(|rate| {
-                let quota = rate.to_governor_quota(None);
-                let limiter = Arc::new(RateLimiter::keyed(quota));
-
-                let weak = Arc::downgrade(&limiter);
-                tokio::spawn(async move {
-                    let mut interval =
-                        tokio::time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
-                    interval.tick().await;
-                    loop {
-                        interval.tick().await;
-                        let Some(limiter) = weak.upgrade() else {
-                            break;
-                        };
-                        limiter.retain_recent();
-                        limiter.shrink_to_fit();
-                    }
-                });
-
-                limiter
-            });
-
+    fn new(user_service: UserService, config: LiveConfig, backend: &LimiterBackend) -> Self {
         Self {
             user_service,
-            defaults,
-            user_read_limiters: LimiterPool::new(),
-            user_write_limiters: LimiterPool::new(),
-            unauthenticated_read_limiter,
+            config,
+            user_read_limiters: LimiterPool::new(backend.clone(), "bandwidth_read"),
+            user_write_limiters: LimiterPool::new(backend.clone(), "bandwidth_write"),
+            unauthenticated_read_limiters: LimiterPool::new(backend.clone(), "bandwidth_ip_read"),
         }
     }
 
     /// Returns `true` when there is something to check for this request.
     fn should_limit(&self, req: &Request<Body>) -> bool {
         let has_user = req.extensions().get::<RequestTenant>().is_some();
-        let has_bandwidth = self.unauthenticated_read_limiter.is_some();
+        let has_bandwidth = self
+            .config
+            .get()
+            .default_quotas
+            .unauthenticated_ip_rate_read
+            .is_some();
         has_user || has_bandwidth
     }
 
@@ -129,9 +121,11 @@ impl BandwidthState {
     async fn resolve_bandwidth_throttler(
         &self,
         info: &RequestInfo,
-    ) -> Result<Option<(LimitKey, Arc<KeyedRateLimiter>)>, Response> {
+    ) -> Result<Option<(LimitKey, Arc<dyn KeyedLimiter>)>, Response> {
+        let config = self.config.get();
+        let defaults = &config.default_quotas;
         let Some(pubkey) = info.user_pubkey.as_ref() else {
-            return Ok(self.ip_throttler(&info.client_ip));
+            return Ok(self.ip_throttler(defaults, &info.client_ip));
         };
 
         // Resolve per-user quota from cache/DB.
@@ -146,7 +140,7 @@ impl BandwidthState {
 
         // Unknown user (e.g. spoofed cookie) → fall back to IP throttle.
         let Some(quota) = quota else {
-            return Ok(self.ip_throttler(&info.client_ip));
+            return Ok(self.ip_throttler(defaults, &info.client_ip));
         };
 
         // Pick read vs write fields based on HTTP method.
@@ -154,17 +148,17 @@ impl BandwidthState {
         let (rate_override, default_rate, user_burst, default_burst, pool) = if is_write {
             (
                 &quota.rate_write,
-                self.defaults.rate_write.as_ref(),
+                defaults.rate_write.as_ref(),
                 quota.rate_write_burst,
-                self.defaults.rate_write_burst,
+                defaults.rate_write_burst,
                 &self.user_write_limiters,
             )
         } else {
             (
                 &quota.rate_read,
-                self.defaults.rate_read.as_ref(),
+                defaults.rate_read.as_ref(),
                 quota.rate_read_burst,
-                self.defaults.rate_read_burst,
+                defaults.rate_read_burst,
                 &self.user_read_limiters,
             )
         };
@@ -182,11 +176,13 @@ impl BandwidthState {
     /// Try to resolve the unauthenticated IP throttler for the client IP.
     fn ip_throttler(
         &self,
+        defaults: &DefaultQuotasToml,
         client_ip: &Result<std::net::IpAddr, anyhow::Error>,
-    ) -> Option<(LimitKey, Arc<KeyedRateLimiter>)> {
-        let limiter = self.unauthenticated_read_limiter.as_ref()?;
+    ) -> Option<(LimitKey, Arc<dyn KeyedLimiter>)> {
+        let rate = defaults.unauthenticated_ip_rate_read.as_ref()?;
+        let limiter = self.unauthenticated_read_limiters.get_or_create(rate, None);
         match client_ip {
-            Ok(ip) => Some((LimitKey::Ip(*ip), limiter.clone())),
+            Ok(ip) => Some((LimitKey::Ip(*ip), limiter)),
             Err(e) => {
                 tracing::warn!("Failed to extract IP for unauthenticated rate limiting: {e}");
                 None
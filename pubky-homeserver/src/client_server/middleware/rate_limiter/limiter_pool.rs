@@ -1,45 +1,49 @@
-//! Pooled keyed rate limiters for per-user bandwidth throttling.
+//! Pooled keyed rate limiters for per-user bandwidth and request throttling.
 //!
-//! Users with the same configured (rate, burst) share a single governor
-//! instance, keyed by their public key. This avoids creating one governor
+//! Users with the same configured limit share a single limiter
+//! instance, keyed by their public key. This avoids creating one limiter
 //! per user while still allowing per-user tracking.
 
+use std::hash::Hash;
 use std::num::NonZeroU32;
 use std::sync::Arc;
 use std::time::Duration;
 
 use dashmap::DashMap;
-use governor::clock::QuantaClock;
-use governor::state::keyed::DashMapStateStore;
-use governor::{Quota, RateLimiter};
+use governor::Quota;
 
 use crate::data_directory::quota_config::BandwidthQuota;
 use crate::quota_config::{LimitKey, LimitKeyType, PathLimit};
 
+use super::backend::{KeyedLimiter, LimiterBackend};
 use super::extract_ip::extract_ip;
 use super::CLEANUP_INTERVAL_SECS;
 use crate::client_server::middleware::request_tenant::RequestTenant;
 use axum::body::Body;
 use axum::http::Request;
 
-pub(super) type KeyedRateLimiter = RateLimiter<LimitKey, DashMapStateStore<LimitKey>, QuantaClock>;
-
 /// Pool key for per-user speed limiters: rate + optional burst override.
 /// Users with the same (rate, burst) share a limiter instance.
 type SpeedLimitKey = (BandwidthQuota, Option<NonZeroU32>);
 
-/// Shared pool of keyed rate limiters, grouped by (rate, burst).
+/// Shared pool of keyed rate limiters, grouped by configured limit `K`,
+/// by default (rate, burst).
 ///
-/// Users with the same configured rate and burst share a single governor
+/// Users with the same configured limit share a single limiter
 /// instance, keyed by their public key.
 #[derive(Debug, Clone)]
-pub(super) struct LimiterPool(Arc<DashMap<SpeedLimitKey, Arc<KeyedRateLimiter>>>);
+pub(super) struct LimiterPool<K: Eq + Hash = SpeedLimitKey> {
+    limiters: Arc<DashMap<K, Arc<dyn KeyedLimiter>>>,
+    backend: LimiterBackend,
+    /// Identifies the pool on a shared backend, e.g. `bandwidth_read`.
+    namespace: &'static str,
+}
 
-impl LimiterPool {
+impl<K: Eq + Hash + Send + Sync + 'static> LimiterPool<K> {
     /// Create a new empty pool and spawn a background cleanup task.
     /// The cleanup task self-terminates when the Arc is dropped (Weak::upgrade fails).
-    pub fn new() -> Self {
-        let inner: Arc<DashMap<SpeedLimitKey, Arc<KeyedRateLimiter>>> = Arc::new(DashMap::new());
+    pub fn new(backend: LimiterBackend, namespace: &'static str) -> Self {
+        let inner: Arc<DashMap<K, Arc<dyn KeyedLimiter>>> = Arc::new(DashMap::new());
 
         let weak = Arc::downgrade(&inner);
         tokio::spawn(async move {
@@ -50,60 +54,61 @@ impl LimiterPool {
                 let Some(pool) = weak.upgrade() else {
                     break;
                 };
-                pool.retain(|_, limiter| {
-                    limiter.retain_recent();
-                    limiter.shrink_to_fit();
-                    !limiter.is_empty()
-                });
+                pool.retain(|_, limiter| !limiter.is_idle());
             }
         });
 
-        Self(inner)
+        Self {
+            limiters: inner,
+            backend,
+            namespace,
+        }
     }
 
+    /// Get or create the keyed rate limiter for `limit`.
+    /// `quota` is only called when the limiter doesn't exist yet.
+    pub fn get_or_create_with(
+        &self,
+        limit: K,
+        quota: impl FnOnce() -> Quota,
+    ) -> Arc<dyn KeyedLimiter> {
+        self.limiters
+            .entry(limit)
+            .or_insert_with(|| {
+                // A user has one limit per pool at a time, so the shared bucket is
+                // keyed by the pool only and survives quota changes.
+                self.backend.create(self.namespace.to_string(), quota())
+            })
+            .clone()
+    }
+}
+
+impl LimiterPool<SpeedLimitKey> {
     /// Get or create a keyed rate limiter for a specific bandwidth rate + burst.
     pub fn get_or_create(
         &self,
         rate: &BandwidthQuota,
         burst: Option<NonZeroU32>,
-    ) -> Arc<KeyedRateLimiter> {
-        self.0
-            .entry((rate.clone(), burst))
-            .or_insert_with(|| {
-                let quota: Quota = rate.to_governor_quota(burst);
-                Arc::new(RateLimiter::keyed(quota))
-            })
-            .clone()
+    ) -> Arc<dyn KeyedLimiter> {
+        self.get_or_create_with((rate.clone(), burst), || rate.to_governor_quota(burst))
     }
 }
 
-/// A path limit paired with its governor rate limiter instance.
+/// A path limit paired with its rate limiter instance.
 #[derive(Debug, Clone)]
 pub(super) struct LimitTuple {
     pub limit: PathLimit,
-    pub limiter: Arc<KeyedRateLimiter>,
+    pub limiter: Arc<dyn KeyedLimiter>,
 }
 
 impl LimitTuple {
-    pub fn new(path_limit: PathLimit) -> Result<Self, String> {
+    pub fn new(path_limit: PathLimit, backend: &LimiterBackend) -> Result<Self, String> {
         let quota = Quota::try_from(path_limit.clone())?;
-        let limiter = Arc::new(RateLimiter::keyed(quota));
-
-        // Forget keys that are not used anymore. This is to prevent memory leaks.
-        // Uses a Weak reference so the task self-terminates when the limiter is dropped.
-        let weak = Arc::downgrade(&limiter);
-        tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
-            interval.tick().await;
-            loop {
-                interval.tick().await;
-                let Some(limiter) = weak.upgrade() else {
-                    break;
-                };
-                limiter.retain_recent();
-                limiter.shrink_to_fit();
-            }
-        });
+        let namespace = format!(
+            "path:{} {} by {}",
+            path_limit.method, path_limit.path, path_limit.key
+        );
+        let limiter = backend.create(namespace, quota);
 
         Ok(Self {
             limit: path_limit,
@@ -147,7 +152,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_pool_same_rate_shares_limiter() {
-        let pool = LimiterPool::new();
+        let pool = LimiterPool::new(LimiterBackend::InMemory, "test");
 
         let rate: BandwidthQuota = "5mb/s".parse().unwrap();
         let limiter1 = pool.get_or_create(&rate, None);
@@ -159,7 +164,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_pool_different_rate_different_limiter() {
-        let pool = LimiterPool::new();
+        let pool = LimiterPool::new(LimiterBackend::InMemory, "test");
 
         let rate: BandwidthQuota = "5mb/s".parse().unwrap();
         let limiter1 = pool.get_or_create(&rate, None);
@@ -171,7 +176,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_pool_different_burst_different_limiter() {
-        let pool = LimiterPool::new();
+        let pool = LimiterPool::new(LimiterBackend::InMemory, "test");
 
         let rate: BandwidthQuota = "5mb/s".parse().unwrap();
         let limiter1 = pool.get_or_create(&rate, None);
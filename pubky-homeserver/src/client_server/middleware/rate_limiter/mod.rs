@@ -2,15 +2,19 @@
 /// rate-limiter entries and shrink internal maps.
 const CLEANUP_INTERVAL_SECS: u64 = 60;
 
+mod backend;
 mod bandwidth_rate_limit;
 mod extract_ip;
 mod limiter_pool;
+mod pg_limiter;
 mod request_info;
 mod request_rate_limit;
 mod throttle;
+mod usage_limit;
 
 pub use bandwidth_rate_limit::BandwidthQuotaLimitLayer;
 pub use request_rate_limit::RequestRateLimitLayer;
+pub use usage_limit::UsageLimitLayer;
 
 #[cfg(test)]
 mod tests {
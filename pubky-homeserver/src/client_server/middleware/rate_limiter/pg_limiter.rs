@@ -0,0 +1,337 @@
+//! Postgres-backed rate limiter shared by all homeserver instances.
+//!
+//! Implements the same generic cell rate algorithm (GCRA) as governor. Each bucket
+//! stores a single "theoretical arrival time" (`tat`, in microseconds of the database
+//! clock). Taking `n` cells moves `tat` forward by `n` emission intervals; the request
+//! is allowed if `tat` stays within the burst tolerance of now. The check and the update
+//! happen in one statement, so concurrent instances can't both take the last cell.
+//!
+//! The database clock is used on purpose: instances with skewed clocks still agree on
+//! the bucket state.
+//!
+//! Body throttling asks for cells once per chunk. To keep that from costing a
+//! statement per chunk, a limiter leases a whole burst at once and hands the surplus
+//! to the following chunks of the same key. A lease is dropped once the burst would
+//! have been replenished, so an instance holds back at most one burst per key.
+//!
+//! Database errors fail open: the request is allowed and the error is logged, so an
+//! unavailable limiter table never takes the whole homeserver down.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use governor::{InsufficientCapacity, Quota};
+use sqlx::PgPool;
+
+use crate::persistence::sql::migrations::m20261018_create_rate_limit_buckets::RATE_LIMIT_BUCKETS_TABLE;
+use crate::quota_config::LimitKey;
+
+use super::backend::{retry_jitter, KeyedLimiter, RateLimited};
+use super::CLEANUP_INTERVAL_SECS;
+
+/// Current database time in microseconds since the unix epoch.
+const NOW_US: &str = "(extract(epoch FROM clock_timestamp()) * 1000000)::bigint";
+
+/// Shared handle to the bucket table.
+///
+/// Spawns a background task that deletes fully replenished buckets. A bucket whose
+/// `tat` lies in the past behaves exactly like a missing one.
+#[derive(Debug, Clone)]
+pub(super) struct PgRateLimitStore {
+    pool: Arc<PgPool>,
+}
+
+impl PgRateLimitStore {
+    pub fn new(pool: PgPool) -> Self {
+        let pool = Arc::new(pool);
+        // Uses a Weak reference so the task self-terminates when the store is dropped.
+        let weak: Weak<PgPool> = Arc::downgrade(&pool);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(CLEANUP_INTERVAL_SECS));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(pool) = weak.upgrade() else {
+                    break;
+                };
+                if let Err(e) = Self::delete_replenished(&pool).await {
+                    tracing::warn!("Failed to clean up rate limit buckets: {e}");
+                }
+            }
+        });
+        Self { pool }
+    }
+
+    async fn delete_replenished(pool: &PgPool) -> Result<u64, sqlx::Error> {
+        let query = format!("DELETE FROM {RATE_LIMIT_BUCKETS_TABLE} WHERE tat < {NOW_US}");
+        let result = sqlx::query(&query).execute(pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Take `increment_us` worth of cells from `bucket` if the resulting `tat` stays
+    /// within `tolerance_us` of now. On rejection returns how long to wait.
+    async fn take(
+        &self,
+        bucket: &str,
+        increment_us: i64,
+        tolerance_us: i64,
+    ) -> Result<Result<(), Duration>, sqlx::Error> {
+        let take = format!(
+            r#"WITH now AS (SELECT {NOW_US} AS us)
+            INSERT INTO {RATE_LIMIT_BUCKETS_TABLE} AS bucket (key, tat)
+            SELECT $1, now.us + $2 FROM now
+            ON CONFLICT (key) DO UPDATE
+            SET tat = GREATEST(bucket.tat, EXCLUDED.tat - $2) + $2
+            WHERE GREATEST(bucket.tat, EXCLUDED.tat - $2) + $2 - (EXCLUDED.tat - $2) <= $3
+            RETURNING tat"#
+        );
+        let taken: Option<i64> = sqlx::query_scalar(&take)
+            .bind(bucket)
+            .bind(increment_us)
+            .bind(tolerance_us)
+            .fetch_optional(self.pool.as_ref())
+            .await?;
+        if taken.is_some() {
+            return Ok(Ok(()));
+        }
+
+        // Rejected: the cells become available once `tat + increment - tolerance` is reached.
+        let wait = format!(
+            r#"SELECT GREATEST(tat + $2 - $3 - {NOW_US}, 0)
+            FROM {RATE_LIMIT_BUCKETS_TABLE} WHERE key = $1"#
+        );
+        let wait_us: Option<i64> = sqlx::query_scalar(&wait)
+            .bind(bucket)
+            .bind(increment_us)
+            .bind(tolerance_us)
+            .fetch_optional(self.pool.as_ref())
+            .await?;
+        Ok(Err(Duration::from_micros(
+            wait_us.unwrap_or_default() as u64
+        )))
+    }
+}
+
+/// A keyed limiter whose state lives in Postgres.
+#[derive(Debug)]
+pub(super) struct PgLimiter {
+    store: PgRateLimitStore,
+    namespace: String,
+    /// Time it takes to replenish one cell.
+    emission_interval_us: i64,
+    /// Maximum number of cells that can be taken at once.
+    burst: u32,
+    /// Cells taken from the database but not used yet, per bucket.
+    leases: Mutex<HashMap<String, Lease>>,
+}
+
+#[derive(Debug)]
+struct Lease {
+    cells: usize,
+    expires_at: Instant,
+}
+
+impl PgLimiter {
+    pub fn new(store: PgRateLimitStore, namespace: String, quota: Quota) -> Self {
+        Self {
+            store,
+            namespace,
+            emission_interval_us: quota.replenish_interval().as_micros().max(1) as i64,
+            burst: quota.burst_size().get(),
+            leases: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn bucket(&self, key: &LimitKey) -> String {
+        format!("{}|{key}", self.namespace)
+    }
+
+    async fn take(&self, bucket: &str, cells: u32) -> Result<(), Duration> {
+        let increment_us = self.emission_interval_us.saturating_mul(cells as i64);
+        let tolerance_us = self.emission_interval_us.saturating_mul(self.burst as i64);
+        match self.store.take(bucket, increment_us, tolerance_us).await {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::error!("Rate limit bucket {bucket} unavailable, allowing request: {e}");
+                Ok(())
+            }
+        }
+    }
+
+    /// Use up to `cells` from the lease of `bucket`. Returns the cells still missing.
+    fn draw_lease(&self, bucket: &str, cells: usize) -> usize {
+        let mut leases = self.leases.lock().expect("lease lock poisoned");
+        let Some(lease) = leases.get_mut(bucket) else {
+            return cells;
+        };
+        if lease.expires_at <= Instant::now() {
+            leases.remove(bucket);
+            return cells;
+        }
+        let drawn = lease.cells.min(cells);
+        lease.cells -= drawn;
+        if lease.cells == 0 {
+            leases.remove(bucket);
+        }
+        cells - drawn
+    }
+
+    /// Keep `cells` taken from `bucket` for the following chunks.
+    fn store_lease(&self, bucket: &str, cells: usize) {
+        let now = Instant::now();
+        let lifetime = Duration::from_micros(
+            self.emission_interval_us
+                .saturating_mul(self.burst as i64)
+                .max(0) as u64,
+        );
+        let mut leases = self.leases.lock().expect("lease lock poisoned");
+        leases.retain(|_, lease| lease.expires_at > now);
+        if cells > 0 {
+            leases.insert(
+                bucket.to_string(),
+                Lease {
+                    cells,
+                    expires_at: now + lifetime,
+                },
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl KeyedLimiter for PgLimiter {
+    async fn check_key(&self, key: &LimitKey) -> Result<(), RateLimited> {
+        self.take(&self.bucket(key), 1)
+            .await
+            .map_err(|wait| RateLimited { wait })
+    }
+
+    async fn until_key_n_ready(
+        &self,
+        key: &LimitKey,
+        cells: usize,
+    ) -> Result<(), InsufficientCapacity> {
+        let bucket = self.bucket(key);
+        let mut remaining = self.draw_lease(&bucket, cells);
+        while remaining > 0 {
+            // Lease a whole burst so the next chunks don't hit the database. If the
+            // burst isn't available yet, only wait for the cells this chunk needs.
+            let burst = self.burst as usize;
+            if remaining < burst && self.take(&bucket, self.burst).await.is_ok() {
+                self.store_lease(&bucket, burst - remaining);
+                return Ok(());
+            }
+            let piece = remaining.min(self.burst as usize) as u32;
+            while let Err(wait) = self.take(&bucket, piece).await {
+                tokio::time::sleep(retry_jitter() + wait).await;
+            }
+            remaining -= piece as usize;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::persistence::sql::SqlDb;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_limit_is_shared_between_limiters() {
+        let db = SqlDb::test().await;
+        let quota = Quota::per_minute(NonZeroU32::new(2).unwrap());
+        // Two limiters on separate stores stand in for two homeserver instances.
+        let instance_a = PgLimiter::new(
+            PgRateLimitStore::new(db.pool().clone()),
+            "test".to_string(),
+            quota,
+        );
+        let instance_b = PgLimiter::new(
+            PgRateLimitStore::new(db.pool().clone()),
+            "test".to_string(),
+            quota,
+        );
+        let other_namespace = PgLimiter::new(
+            PgRateLimitStore::new(db.pool().clone()),
+            "other".to_string(),
+            quota,
+        );
+        let key = LimitKey::User(Keypair::random().public_key());
+
+        instance_a.check_key(&key).await.unwrap();
+        instance_b.check_key(&key).await.unwrap();
+        let rejected = instance_a.check_key(&key).await.unwrap_err();
+        assert!(rejected.wait > Duration::from_secs(20));
+        assert!(rejected.wait <= Duration::from_secs(30));
+        assert!(instance_b.check_key(&key).await.is_err());
+
+        // Other keys and other limits have their own buckets.
+        let other_key = LimitKey::Ip("127.0.0.1".parse().unwrap());
+        instance_b.check_key(&other_key).await.unwrap();
+        other_namespace.check_key(&key).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_until_ready_waits_for_replenishment() {
+        let db = SqlDb::test().await;
+        // 10 cells per second, burst of 10.
+        let quota = Quota::per_second(NonZeroU32::new(10).unwrap());
+        let limiter = PgLimiter::new(
+            PgRateLimitStore::new(db.pool().clone()),
+            "bandwidth".to_string(),
+            quota,
+        );
+        let key = LimitKey::Ip("127.0.0.1".parse().unwrap());
+
+        // The first burst is free, the next 10 cells take about a second.
+        let start = tokio::time::Instant::now();
+        limiter.until_key_n_ready(&key, 20).await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(900), "took {elapsed:?}");
+
+        // Replenished buckets are removed by the cleanup.
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let deleted = PgRateLimitStore::delete_replenished(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_small_chunks_draw_from_a_leased_burst() {
+        let db = SqlDb::test().await;
+        let quota = Quota::per_minute(NonZeroU32::new(10).unwrap());
+        let limiter = PgLimiter::new(
+            PgRateLimitStore::new(db.pool().clone()),
+            "bandwidth".to_string(),
+            quota,
+        );
+        let other_instance = PgLimiter::new(
+            PgRateLimitStore::new(db.pool().clone()),
+            "bandwidth".to_string(),
+            quota,
+        );
+        let key = LimitKey::Ip("127.0.0.1".parse().unwrap());
+
+        // The first chunk leases the whole burst, so the bucket is empty for others.
+        limiter.until_key_n_ready(&key, 1).await.unwrap();
+        assert!(other_instance.check_key(&key).await.is_err());
+
+        // The remaining cells are served from the lease without waiting.
+        let start = tokio::time::Instant::now();
+        for _ in 0..9 {
+            limiter.until_key_n_ready(&key, 1).await.unwrap();
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(limiter.draw_lease(&limiter.bucket(&key), 1), 1);
+    }
+}
@@ -1,7 +1,8 @@
 //! Request-count rate limiting layer.
 //!
 //! Enforces per-path request-count quotas (`[[drive.rate_limits]]` in config).
-//! Each path+method pattern has a governor rate limiter keyed by IP or user.
+//! Each path+method pattern has a rate limiter keyed by IP or user, on the
+//! backend selected by `[drive] rate_limiter_backend`.
 
 use axum::response::{IntoResponse, Response};
 use axum::{
@@ -9,12 +10,15 @@ use axum::{
     http::{Request, StatusCode},
 };
 use futures_util::future::BoxFuture;
+use std::sync::{Arc, Mutex};
 use std::{convert::Infallible, task::Poll};
 use tower::{Layer, Service};
 
-use crate::quota_config::PathLimit;
+use crate::quota_config::{LimitKey, PathLimit};
+use crate::services::config_service::{LiveConfig, ReloadableConfig};
 use crate::shared::HttpError;
 
+use super::backend::LimiterBackend;
 use super::limiter_pool::LimitTuple;
 
 /// A Tower Layer for request-count rate limiting.
@@ -24,36 +28,120 @@ use super::limiter_pool::LimitTuple;
 ///
 /// Returns 400 BAD REQUEST if the rate-limit key (IP or request tenant)
 /// cannot be extracted.
+///
+/// Limits follow config reloads. Limits that did not change keep their counters.
 #[derive(Debug, Clone)]
 pub struct RequestRateLimitLayer {
-    limits: Vec<LimitTuple>,
+    limits: Arc<LiveLimits>,
 }
 
 impl RequestRateLimitLayer {
+    /// Creates the layer from the application context.
+    pub fn from_context(context: &crate::AppContext) -> Result<Self, String> {
+        Self::new(
+            context.config_service.live().clone(),
+            LimiterBackend::from_context(context),
+        )
+    }
+
+    /// Creates the layer with in-memory limiters (test-only).
+    #[cfg(test)]
     pub fn from_path_limits(limits: Vec<PathLimit>) -> Result<Self, String> {
-        if limits.is_empty() {
-            tracing::info!("No path-based request-count rate limits configured ([[drive.rate_limits]] is empty).");
-        } else {
-            let limits_str = limits
-                .iter()
-                .map(|limit| format!("\"{limit}\""))
-                .collect::<Vec<_>>()
-                .join(", ");
-            tracing::info!("Path-based rate limits configured: {limits_str}");
+        Self::build(limits, &LimiterBackend::InMemory)
+    }
+
+    /// Creates the layer with fixed limits (test-only).
+    #[cfg(test)]
+    fn build(limits: Vec<PathLimit>, backend: &LimiterBackend) -> Result<Self, String> {
+        let config = LiveConfig::new(ReloadableConfig {
+            rate_limits: limits,
+            ..Default::default()
+        });
+        Self::new(config, backend.clone())
+    }
+
+    fn new(config: LiveConfig, backend: LimiterBackend) -> Result<Self, String> {
+        let source = config.get();
+        let limits = build_limits(&source.rate_limits, &[], &backend)?;
+        Ok(Self {
+            limits: Arc::new(LiveLimits {
+                config,
+                backend,
+                built: Mutex::new(BuiltLimits {
+                    source,
+                    limits: Arc::new(limits),
+                }),
+            }),
+        })
+    }
+}
+
+/// The limiters for the current `[[drive.rate_limits]]`, shared by all middleware clones.
+#[derive(Debug)]
+struct LiveLimits {
+    config: LiveConfig,
+    backend: LimiterBackend,
+    built: Mutex<BuiltLimits>,
+}
+
+#[derive(Debug)]
+struct BuiltLimits {
+    /// The config snapshot `limits` were last checked against.
+    source: Arc<ReloadableConfig>,
+    limits: Arc<Vec<LimitTuple>>,
+}
+
+impl LiveLimits {
+    /// The limiters for the current config, rebuilt if the limits were reloaded.
+    fn current(&self) -> Arc<Vec<LimitTuple>> {
+        let config = self.config.get();
+        let mut built = self.built.lock().expect("rate limits lock poisoned");
+        if !Arc::ptr_eq(&built.source, &config) {
+            if built.source.rate_limits != config.rate_limits {
+                // Reloads are validated up front, so this only fails on a bug.
+                match build_limits(&config.rate_limits, &built.limits, &self.backend) {
+                    Ok(limits) => built.limits = Arc::new(limits),
+                    Err(e) => tracing::error!("Failed to apply reloaded rate limits: {e}"),
+                }
+            }
+            built.source = config;
         }
-        let limits = limits
-            .into_iter()
-            .map(LimitTuple::new)
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(Self { limits })
+        Arc::clone(&built.limits)
     }
 }
 
+/// Build the limiters for `limits`, reusing those in `previous` with an identical limit.
+fn build_limits(
+    limits: &[PathLimit],
+    previous: &[LimitTuple],
+    backend: &LimiterBackend,
+) -> Result<Vec<LimitTuple>, String> {
+    if limits.is_empty() {
+        tracing::info!(
+            "No path-based request-count rate limits configured ([[drive.rate_limits]] is empty)."
+        );
+    } else {
+        let limits_str = limits
+            .iter()
+            .map(|limit| format!("\"{limit}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        tracing::info!("Path-based rate limits configured: {limits_str}");
+    }
+    limits
+        .iter()
+        .map(|limit| match previous.iter().find(|t| t.limit == *limit) {
+            Some(existing) => Ok(existing.clone()),
+            None => LimitTuple::new(limit.clone(), backend),
+        })
+        .collect()
+}
+
 impl<S> Layer<S> for RequestRateLimitLayer {
     type Service = RequestRateLimitMiddleware<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        let limits = self.limits.clone();
+        let limits = Arc::clone(&self.limits);
         RequestRateLimitMiddleware { inner, limits }
     }
 }
@@ -61,7 +149,7 @@ impl<S> Layer<S> for RequestRateLimitLayer {
 #[derive(Debug, Clone)]
 pub struct RequestRateLimitMiddleware<S> {
     inner: S,
-    limits: Vec<LimitTuple>,
+    limits: Arc<LiveLimits>,
 }
 
 impl<S> Service<Request<Body>> for RequestRateLimitMiddleware<S>
@@ -79,15 +167,20 @@ where
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
         let mut inner = self.inner.clone();
+        let limits = self.limits.current();
 
-        if !self.limits.iter().any(|l| l.is_match(&req)) {
+        if !limits.iter().any(|l| l.is_match(&req)) {
             return Box::pin(async move { inner.call(req).await });
         }
 
-        let limits = self.limits.clone();
+        // Resolve the keys up front so the `!Sync` request isn't held across `.await`.
+        let matched = match matching_limits(&limits, &req) {
+            Ok(matched) => matched,
+            Err(resp) => return Box::pin(async move { Ok(resp) }),
+        };
 
         Box::pin(async move {
-            if let Err(resp) = check_request_count_limits(&limits, &req) {
+            if let Err(resp) = check_request_count_limits(&matched).await {
                 return Ok(resp);
             }
             inner.call(req).await
@@ -95,9 +188,14 @@ where
     }
 }
 
-/// Check request-count path limits. Returns an error response if any limit is exceeded.
+/// Collect the limits that apply to this request, paired with the request's key.
+/// Whitelisted keys are skipped. Returns an error response if a key can't be extracted.
 #[allow(clippy::result_large_err)]
-fn check_request_count_limits(limits: &[LimitTuple], req: &Request<Body>) -> Result<(), Response> {
+fn matching_limits(
+    limits: &[LimitTuple],
+    req: &Request<Body>,
+) -> Result<Vec<(LimitTuple, LimitKey)>, Response> {
+    let mut matched = Vec::new();
     for limit in limits {
         if !limit.is_match(req) {
             continue;
@@ -121,7 +219,16 @@ fn check_request_count_limits(limits: &[LimitTuple], req: &Request<Body>) -> Res
         if limit.limit.is_whitelisted(&key) {
             continue;
         }
-        if let Err(e) = limit.limiter.check_key(&key) {
+        matched.push((limit.clone(), key));
+    }
+    Ok(matched)
+}
+
+/// Check request-count path limits. Returns an error response if any limit is exceeded.
+#[allow(clippy::result_large_err)]
+async fn check_request_count_limits(matched: &[(LimitTuple, LimitKey)]) -> Result<(), Response> {
+    for (limit, key) in matched {
+        if let Err(e) = limit.limiter.check_key(key).await {
             tracing::debug!(
                 "Rate limit of {} exceeded for {key}: {}",
                 limit.limit.quota,
@@ -154,9 +261,11 @@ mod tests {
     use tower_cookies::CookieManagerLayer;
 
     use crate::client_server::middleware::request_tenant::RequestTenant;
+    use crate::persistence::sql::SqlDb;
     use crate::quota_config::{GlobPattern, HttpMethod, LimitKeyType};
     use crate::shared::HttpResult;
 
+    use super::super::pg_limiter::PgRateLimitStore;
     use super::*;
     use axum::response::IntoResponse;
 
@@ -169,14 +278,19 @@ mod tests {
     }
 
     async fn start_server(config: Vec<PathLimit>) -> SocketAddr {
+        start_server_with_layer(
+            RequestRateLimitLayer::from_path_limits(config)
+                .expect("valid test request-count rate limit"),
+        )
+        .await
+    }
+
+    async fn start_server_with_layer(layer: RequestRateLimitLayer) -> SocketAddr {
         let app = Router::new()
             .route("/upload", post(upload_handler))
             .route("/download", get(download_handler))
             .route("/storage/{user_z32}/{*path}", get(download_handler))
-            .layer(
-                RequestRateLimitLayer::from_path_limits(config)
-                    .expect("valid test request-count rate limit"),
-            )
+            .layer(layer)
             .layer(CookieManagerLayer::new())
             .layer(middleware::from_fn(RequestTenant::resolve));
 
@@ -311,6 +425,98 @@ mod tests {
         assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_postgres_backend_shares_limit_between_instances() {
+        let db = SqlDb::test().await;
+        let path_limit = PathLimit {
+            path: GlobPattern::new("/upload"),
+            method: HttpMethod(Method::POST),
+            quota: "2r/m".parse().unwrap(),
+            key: LimitKeyType::Ip,
+            burst: None,
+            whitelist: Vec::new(),
+        };
+        // Two servers on the same database stand in for two homeserver instances.
+        let mut sockets = Vec::new();
+        for _ in 0..2 {
+            let backend = LimiterBackend::Postgres(PgRateLimitStore::new(db.pool().clone()));
+            let layer = RequestRateLimitLayer::build(vec![path_limit.clone()], &backend)
+                .expect("valid test request-count rate limit");
+            sockets.push(start_server_with_layer(layer).await);
+        }
+
+        let client = Client::new();
+        let mut statuses = Vec::new();
+        for socket in [sockets[0], sockets[1], sockets[0], sockets[1]] {
+            let res = client
+                .post(format!("http://{socket}/upload"))
+                .send()
+                .await
+                .unwrap();
+            statuses.push(res.status());
+        }
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::CREATED,
+                StatusCode::CREATED,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::TOO_MANY_REQUESTS
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_limits_follow_config_reload() {
+        let path_limit = PathLimit {
+            path: GlobPattern::new("/upload"),
+            method: HttpMethod(Method::POST),
+            quota: "1r/m".parse().unwrap(),
+            key: LimitKeyType::Ip,
+            burst: None,
+            whitelist: Vec::new(),
+        };
+        let mut config = crate::ConfigToml::default_test_config();
+        config.drive.rate_limits = vec![path_limit.clone()];
+        let data_dir = crate::MockDataDir::new(config.clone(), None).unwrap();
+        let service =
+            crate::services::config_service::ConfigService::new(&config, Arc::new(data_dir));
+        let layer = RequestRateLimitLayer::new(service.live().clone(), LimiterBackend::InMemory)
+            .expect("valid test request-count rate limit");
+        let socket = start_server_with_layer(layer).await;
+        let client = Client::new();
+        let upload = || client.post(format!("http://{socket}/upload")).send();
+
+        assert_eq!(upload().await.unwrap().status(), StatusCode::CREATED);
+        assert_eq!(
+            upload().await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        // Unrelated changes keep the exhausted limiter.
+        config.general.signup_mode = crate::SignupMode::TokenRequired;
+        service.apply(&config).unwrap();
+        assert_eq!(
+            upload().await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        config.drive.rate_limits = vec![PathLimit {
+            quota: "5r/m".parse().unwrap(),
+            ..path_limit
+        }];
+        service.apply(&config).unwrap();
+        assert_eq!(upload().await.unwrap().status(), StatusCode::CREATED);
+
+        config.drive.rate_limits.clear();
+        service.apply(&config).unwrap();
+        for _ in 0..10 {
+            assert_eq!(upload().await.unwrap().status(), StatusCode::CREATED);
+        }
+    }
+
     #[test]
     fn test_path_limit_accepts_request_count_quota() {
         let limit = PathLimit {
@@ -1,27 +1,24 @@
 //! Body stream bandwidth throttling.
 //!
 //! Wraps request and response bodies in a throttled stream that uses
-//! governor to enforce per-key bandwidth limits in kilobyte granularity.
+//! a [`KeyedLimiter`] to enforce per-key bandwidth limits in kilobyte granularity.
 
-use std::num::NonZero;
 use std::sync::Arc;
-use std::time::Duration;
 
 use axum::body::Body;
 use axum::http::Request;
 use axum::response::Response;
 use futures_util::StreamExt;
-use governor::Jitter;
 
 use crate::quota_config::LimitKey;
 
-use super::limiter_pool::KeyedRateLimiter;
+use super::backend::KeyedLimiter;
 
 /// Wrap the request body in a bandwidth-throttled stream.
 pub(super) fn throttle_request(
     req: Request<Body>,
     key: &LimitKey,
-    limiter: &Arc<KeyedRateLimiter>,
+    limiter: &Arc<dyn KeyedLimiter>,
 ) -> Request<Body> {
     let (parts, body) = req.into_parts();
     Request::from_parts(parts, throttle_body(body, key, limiter))
@@ -31,7 +28,7 @@ pub(super) fn throttle_request(
 pub(super) fn throttle_response(
     res: Response<Body>,
     key: &LimitKey,
-    limiter: &Arc<KeyedRateLimiter>,
+    limiter: &Arc<dyn KeyedLimiter>,
 ) -> Response<Body> {
     let (parts, body) = res.into_parts();
     Response::from_parts(parts, throttle_body(body, key, limiter))
@@ -41,7 +38,7 @@ pub(super) fn throttle_response(
 ///
 /// Important: The speed quotas are always in kilobytes, not bytes.
 /// Counting bytes is not practical.
-fn throttle_body(body: Body, key: &LimitKey, limiter: &Arc<KeyedRateLimiter>) -> Body {
+fn throttle_body(body: Body, key: &LimitKey, limiter: &Arc<dyn KeyedLimiter>) -> Body {
     let body_stream = body.into_data_stream();
     let limiter = limiter.clone();
     let key = key.clone();
@@ -49,10 +46,6 @@ fn throttle_body(body: Body, key: &LimitKey, limiter: &Arc<KeyedRateLimiter>) ->
         .map(move |chunk| {
             let limiter = limiter.clone();
             let key = key.clone();
-            // When the rate limit is exceeded, we wait between 25ms and 500ms before retrying.
-            // This is to avoid overwhelming the server with requests when the rate limit is exceeded.
-            // Randomization is used to avoid thundering herd problem.
-            let jitter = Jitter::new(Duration::from_millis(25), Duration::from_millis(500));
             async move {
                 let bytes = match chunk {
                     Ok(actual_chunk) => actual_chunk,
@@ -74,26 +67,21 @@ fn throttle_body(body: Body, key: &LimitKey, limiter: &Arc<KeyedRateLimiter>) ->
                 // Asking the limiter for 1KB packets is tradeoff between
                 // - Not calling the limiter too much
                 // - Guaranteeing the call size (1kb) is low enough to not cause race condition issues.
+                // The postgres backend leases a burst of cells at a time, so small chunks
+                // mostly draw from the lease instead of the database.
                 let chunk_kilobytes = bytes.len().div_ceil(1024);
-                for _ in 0..chunk_kilobytes {
-                    // Check each kilobyte
-                    if limiter
-                        .until_key_n_ready_with_jitter(
-                            &key,
-                            NonZero::new(1).expect("1 is always non zero"),
-                            jitter,
-                        )
-                        .await
-                        .is_err()
-                    {
-                        // Requested rate (1 KB) exceeds the configured limit.
-                        // This should not happen in practice since limits are in KB.
-                        tracing::error!(
-                            "Rate limiter rejected a 1 KB cell — limit may be misconfigured"
-                        );
-                        return Err(axum::Error::new("Rate limit exceeded"));
-                    };
-                }
+                if limiter
+                    .until_key_n_ready(&key, chunk_kilobytes)
+                    .await
+                    .is_err()
+                {
+                    // Requested rate (1 KB) exceeds the configured limit.
+                    // This should not happen in practice since limits are in KB.
+                    tracing::error!(
+                        "Rate limiter rejected a 1 KB cell — limit may be misconfigured"
+                    );
+                    return Err(axum::Error::new("Rate limit exceeded"));
+                };
                 Ok(bytes)
             }
         })
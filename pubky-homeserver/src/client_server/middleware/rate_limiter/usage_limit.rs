@@ -0,0 +1,557 @@
+//! Per-user request-count limits and transfer caps.
+//!
+//! For authenticated users, enforces `requests_per_minute` and the daily and
+//! monthly egress/ingress caps from `UserQuota`, falling back to `[default_quotas]`,
+//! and records every request and body byte in the [`UsageService`].
+//!
+//! - Requests over `requests_per_minute` are rejected with `429 Too Many Requests`.
+//! - Once a transfer cap is exhausted, requests in that direction are rejected with
+//!   `507 Insufficient Storage` until the UTC day or month rolls over. Uploads
+//!   (PUT, POST, PATCH) count against the ingress caps, everything else against the
+//!   egress caps. DELETE is never blocked so users can always free up space.
+//!
+//! Caps are checked before a request starts, so the request that crosses a cap
+//! still completes.
+
+use std::convert::Infallible;
+use std::num::NonZeroU32;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use axum::body::{Body, Bytes};
+use axum::http::{Method, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use futures_util::future::BoxFuture;
+use governor::Quota;
+use http_body::{Frame, SizeHint};
+use pubky_common::crypto::PublicKey;
+use tower::{Layer, Service};
+
+use crate::persistence::sql::user_usage::{UsageCounters, UsagePeriods};
+use crate::quota_config::LimitKey;
+use crate::services::config_service::LiveConfig;
+use crate::services::usage_service::UsageService;
+use crate::services::user_service::UserService;
+use crate::shared::user_quota::UserQuota;
+use crate::shared::HttpError;
+#[cfg(test)]
+use crate::DefaultQuotasToml;
+
+use super::backend::LimiterBackend;
+use super::limiter_pool::LimiterPool;
+use super::request_info::RequestInfo;
+
+const MB: u64 = 1024 * 1024;
+
+/// A Tower Layer for per-user request-count limits and transfer caps.
+///
+/// Requires `AuthenticationLayer` to run first. Unauthenticated requests pass through.
+#[derive(Debug, Clone)]
+pub struct UsageLimitLayer {
+    user_service: UserService,
+    usage_service: UsageService,
+    config: LiveConfig,
+    backend: LimiterBackend,
+}
+
+impl UsageLimitLayer {
+    /// Creates the layer from the application context.
+    pub fn from_context(context: &crate::AppContext) -> Self {
+        Self {
+            user_service: context.user_service.clone(),
+            usage_service: context.usage_service.clone(),
+            config: context.config_service.live().clone(),
+            backend: LimiterBackend::from_context(context),
+        }
+    }
+
+    /// Creates the layer with explicit services and in-memory limiters (test-only).
+    #[cfg(test)]
+    pub fn new(
+        user_service: UserService,
+        usage_service: UsageService,
+        defaults: DefaultQuotasToml,
+    ) -> Self {
+        Self {
+            user_service,
+            usage_service,
+            config: LiveConfig::new(crate::services::config_service::ReloadableConfig {
+                default_quotas: defaults,
+                ..Default::default()
+            }),
+            backend: LimiterBackend::InMemory,
+        }
+    }
+}
+
+impl<S> Layer<S> for UsageLimitLayer {
+    type Service = UsageLimitMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        UsageLimitMiddleware {
+            inner,
+            state: UsageState {
+                user_service: self.user_service.clone(),
+                usage_service: self.usage_service.clone(),
+                config: self.config.clone(),
+                request_limiters: LimiterPool::new(self.backend.clone(), "requests_per_minute"),
+            },
+        }
+    }
+}
+
+/// Runtime state, shared between middleware clones.
+#[derive(Debug, Clone)]
+struct UsageState {
+    user_service: UserService,
+    usage_service: UsageService,
+    config: LiveConfig,
+    request_limiters: LimiterPool<NonZeroU32>,
+}
+
+/// Direction of a transfer, from the user's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    /// Downloads: response bodies.
+    Egress,
+    /// Uploads: request bodies.
+    Ingress,
+}
+
+impl UsageState {
+    /// Check the request-count limit and the transfer caps of the direction
+    /// the request would use up. Returns the error response if one is exceeded.
+    #[allow(clippy::result_large_err)]
+    async fn check(
+        &self,
+        pubkey: &PublicKey,
+        quota: &UserQuota,
+        method: &Method,
+    ) -> Result<(), Response> {
+        let config = self.config.get();
+        let defaults = &config.default_quotas;
+        let requests_per_minute = quota
+            .requests_per_minute
+            .resolve_with_default(defaults.requests_per_minute)
+            .and_then(|n| NonZeroU32::new(n.min(u32::MAX as u64) as u32));
+        if let Some(n) = requests_per_minute {
+            let limiter = self
+                .request_limiters
+                .get_or_create_with(n, || Quota::per_minute(n));
+            if let Err(e) = limiter.check_key(&LimitKey::User(pubkey.clone())).await {
+                tracing::debug!("Request limit of {n}/min exceeded for {pubkey}: {e}");
+                return Err(HttpError::new_with_message(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Request limit exceeded",
+                )
+                .into_response());
+            }
+        }
+
+        if *method == Method::DELETE {
+            return Ok(());
+        }
+        let direction = match *method {
+            Method::PUT | Method::POST | Method::PATCH => Direction::Ingress,
+            _ => Direction::Egress,
+        };
+        let (daily, monthly) = match direction {
+            Direction::Egress => (
+                quota
+                    .egress_daily_mb
+                    .resolve_with_default(defaults.egress_daily_mb),
+                quota
+                    .egress_monthly_mb
+                    .resolve_with_default(defaults.egress_monthly_mb),
+            ),
+            Direction::Ingress => (
+                quota
+                    .ingress_daily_mb
+                    .resolve_with_default(defaults.ingress_daily_mb),
+                quota
+                    .ingress_monthly_mb
+                    .resolve_with_default(defaults.ingress_monthly_mb),
+            ),
+        };
+        if daily.is_none() && monthly.is_none() {
+            return Ok(());
+        }
+
+        let usage = self.usage_service.get(pubkey).await.map_err(|e| {
+            tracing::error!("Failed to resolve usage of {}: {e}", pubkey.z32());
+            HttpError::new_with_message(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to resolve user usage",
+            )
+            .into_response()
+        })?;
+        let used = |counters: &UsageCounters| match direction {
+            Direction::Egress => counters.egress_bytes,
+            Direction::Ingress => counters.ingress_bytes,
+        };
+        let UsagePeriods { day, month } = usage;
+        for (period, cap_mb, used) in [
+            ("Daily", daily, used(&day)),
+            ("Monthly", monthly, used(&month)),
+        ] {
+            let Some(cap_mb) = cap_mb else {
+                continue;
+            };
+            if used >= cap_mb.saturating_mul(MB) {
+                let kind = match direction {
+                    Direction::Egress => "download",
+                    Direction::Ingress => "upload",
+                };
+                return Err(HttpError::new_with_message(
+                    StatusCode::INSUFFICIENT_STORAGE,
+                    format!("{period} {kind} cap of {cap_mb} MB exceeded"),
+                )
+                .into_response());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UsageLimitMiddleware<S> {
+    inner: S,
+    state: UsageState,
+}
+
+impl<S> Service<Request<Body>> for UsageLimitMiddleware<S>
+where
+    S: Service<Request<Body>, Response = Response, Error = Infallible> + Send + 'static + Clone,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let mut inner = self.inner.clone();
+        let info = RequestInfo::from_request(&req);
+        let Some(pubkey) = info.user_pubkey else {
+            return Box::pin(async move { inner.call(req).await });
+        };
+        let state = self.state.clone();
+
+        Box::pin(async move {
+            let quota = match state.user_service.resolve_quota(&pubkey).await {
+                Ok(Some(quota)) => quota,
+                // Unknown user (e.g. spoofed cookie), nothing to limit or account.
+                Ok(None) => return inner.call(req).await,
+                Err(e) => {
+                    tracing::error!("Failed to resolve user limits for {}: {e}", pubkey.z32());
+                    return Ok(HttpError::new_with_message(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Failed to resolve user limits",
+                    )
+                    .into_response());
+                }
+            };
+            if let Err(resp) = state.check(&pubkey, &quota, &info.method).await {
+                return Ok(resp);
+            }
+
+            state.usage_service.record(
+                &pubkey,
+                UsageCounters {
+                    requests: 1,
+                    ..Default::default()
+                },
+            );
+            let (parts, body) = req.into_parts();
+            let body = CountingBody::wrap(body, &state.usage_service, &pubkey, Direction::Ingress);
+            let response = inner.call(Request::from_parts(parts, body)).await?;
+            let (parts, body) = response.into_parts();
+            let body = CountingBody::wrap(body, &state.usage_service, &pubkey, Direction::Egress);
+            Ok(Response::from_parts(parts, body))
+        })
+    }
+}
+
+/// Body wrapper that records the transferred bytes once the body is dropped,
+/// whether it was read to the end or not. Keeps the size hint of the inner body.
+struct CountingBody {
+    inner: Body,
+    usage_service: UsageService,
+    pubkey: PublicKey,
+    direction: Direction,
+    bytes: u64,
+}
+
+impl CountingBody {
+    fn wrap(
+        inner: Body,
+        usage_service: &UsageService,
+        pubkey: &PublicKey,
+        direction: Direction,
+    ) -> Body {
+        Body::new(Self {
+            inner,
+            usage_service: usage_service.clone(),
+            pubkey: pubkey.clone(),
+            direction,
+            bytes: 0,
+        })
+    }
+}
+
+impl http_body::Body for CountingBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let polled = Pin::new(&mut self.inner).poll_frame(cx);
+        if let Poll::Ready(Some(Ok(frame))) = &polled {
+            if let Some(data) = frame.data_ref() {
+                self.bytes += data.len() as u64;
+            }
+        }
+        polled
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+impl Drop for CountingBody {
+    fn drop(&mut self) {
+        let counters = match self.direction {
+            Direction::Egress => UsageCounters {
+                egress_bytes: self.bytes,
+                ..Default::default()
+            },
+            Direction::Ingress => UsageCounters {
+                ingress_bytes: self.bytes,
+                ..Default::default()
+            },
+        };
+        self.usage_service.record(&self.pubkey, counters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::middleware::{from_fn, Next};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use pubky_common::auth::jws::GrantId;
+    use pubky_common::capabilities::{Capabilities, Capability};
+    use pubky_common::crypto::Keypair;
+    use tower::ServiceExt;
+
+    use crate::client_server::auth::grant::session::GrantSession;
+    use crate::client_server::auth::AuthSession;
+    use crate::persistence::sql::SqlDb;
+    use crate::shared::user_quota::{QuotaOverride, UserQuotaPatch};
+
+    use super::*;
+
+    async fn upload_handler(body: Body) -> StatusCode {
+        axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        StatusCode::CREATED
+    }
+
+    async fn download_handler() -> Vec<u8> {
+        vec![0u8; 3 * 1024]
+    }
+
+    fn app(layer: UsageLimitLayer, public_key: PublicKey) -> Router {
+        Router::new()
+            .route(
+                "/upload",
+                post(upload_handler).delete(|| async { StatusCode::NO_CONTENT }),
+            )
+            .route("/download", get(download_handler))
+            // Finalize the routes so the layer (and its limiters) is built once.
+            .with_state(())
+            .layer(layer)
+            .layer(from_fn(move |mut req: Request<Body>, next: Next| {
+                req.extensions_mut()
+                    .insert(AuthSession::Grant(GrantSession::test(
+                        public_key.clone(),
+                        Capabilities::builder().cap(Capability::root()).finish(),
+                        GrantId::generate(),
+                        chrono::Utc::now().timestamp() as u64 + 3600,
+                    )));
+                next.run(req)
+            }))
+    }
+
+    async fn send(app: &Router, method: Method, uri: &str, body: Vec<u8>) -> StatusCode {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        status
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_requests_per_minute_override() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let usage_service = UsageService::new(db);
+        let pubkey = Keypair::random().public_key();
+        user_service.create(&pubkey).await.unwrap();
+        user_service
+            .patch_quota(
+                &pubkey,
+                &UserQuotaPatch {
+                    requests_per_minute: Some(QuotaOverride::Value(2)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // The server default would allow more, the override wins.
+        let defaults = DefaultQuotasToml {
+            requests_per_minute: Some(100),
+            ..Default::default()
+        };
+        let app = app(
+            UsageLimitLayer::new(user_service, usage_service, defaults),
+            pubkey,
+        );
+        assert_eq!(
+            send(&app, Method::GET, "/download", vec![]).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&app, Method::GET, "/download", vec![]).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&app, Method::GET, "/download", vec![]).await,
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_transfer_is_counted() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let usage_service = UsageService::new(db);
+        let pubkey = Keypair::random().public_key();
+        user_service.create(&pubkey).await.unwrap();
+
+        let layer = UsageLimitLayer::new(
+            user_service,
+            usage_service.clone(),
+            DefaultQuotasToml::default(),
+        );
+        let app = app(layer, pubkey.clone());
+        assert_eq!(
+            send(&app, Method::GET, "/download", vec![]).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            send(&app, Method::POST, "/upload", vec![0u8; 100]).await,
+            StatusCode::CREATED
+        );
+
+        let usage = usage_service.get(&pubkey).await.unwrap();
+        assert_eq!(
+            usage.day,
+            UsageCounters {
+                egress_bytes: 3 * 1024,
+                ingress_bytes: 100,
+                requests: 2,
+            }
+        );
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_transfer_caps() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let usage_service = UsageService::new(db);
+        let pubkey = Keypair::random().public_key();
+        user_service.create(&pubkey).await.unwrap();
+
+        let defaults = DefaultQuotasToml {
+            egress_daily_mb: Some(1),
+            ingress_monthly_mb: Some(1),
+            ..Default::default()
+        };
+        let layer = UsageLimitLayer::new(user_service.clone(), usage_service.clone(), defaults);
+        let app = app(layer, pubkey.clone());
+        assert_eq!(
+            send(&app, Method::GET, "/download", vec![]).await,
+            StatusCode::OK
+        );
+
+        // Exhaust the daily egress cap: downloads are rejected, uploads still pass.
+        usage_service.record(
+            &pubkey,
+            UsageCounters {
+                egress_bytes: MB,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            send(&app, Method::GET, "/download", vec![]).await,
+            StatusCode::INSUFFICIENT_STORAGE
+        );
+        assert_eq!(
+            send(&app, Method::POST, "/upload", vec![0u8; 100]).await,
+            StatusCode::CREATED
+        );
+
+        // Exhaust the monthly ingress cap: uploads are rejected, deletes still pass.
+        usage_service.record(
+            &pubkey,
+            UsageCounters {
+                ingress_bytes: MB,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            send(&app, Method::POST, "/upload", vec![0u8; 100]).await,
+            StatusCode::INSUFFICIENT_STORAGE
+        );
+        assert_eq!(
+            send(&app, Method::DELETE, "/upload", vec![]).await,
+            StatusCode::NO_CONTENT
+        );
+
+        // An unlimited override lifts the cap.
+        user_service
+            .patch_quota(
+                &pubkey,
+                &UserQuotaPatch {
+                    egress_daily_mb: Some(QuotaOverride::Unlimited),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            send(&app, Method::GET, "/download", vec![]).await,
+            StatusCode::OK
+        );
+    }
+}
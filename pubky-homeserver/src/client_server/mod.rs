@@ -11,6 +11,7 @@ pub(crate) mod cache_policy;
 mod middleware;
 mod query_params;
 pub(crate) mod routes;
+mod tls;
 
 pub use app::create_app;
 pub use app::{ClientServer, ClientServerBuildError};
@@ -52,6 +52,10 @@ pub enum EventStreamError {
     DatabaseError(#[from] sqlx::Error),
     #[error("Invalid public key: {0}")]
     InvalidPublicKey(String),
+    #[error(
+        "Cursor {0} is older than the retained event history of this user, resync from a directory listing"
+    )]
+    CursorTooOld(String),
 }
 
 impl From<EventStreamError> for HttpError {
@@ -59,6 +63,9 @@ impl From<EventStreamError> for HttpError {
         match error {
             EventStreamError::UserNotFound => HttpError::not_found(),
             EventStreamError::DatabaseError(e) => HttpError::from(e),
+            EventStreamError::CursorTooOld(_) => {
+                HttpError::new_with_message(StatusCode::GONE, error.to_string())
+            }
             _ => HttpError::bad_request(error.to_string()),
         }
     }
@@ -353,6 +360,13 @@ pub async fn feed(
 /// data: cursor: 42
 /// data: content_hash: r0NJufX5oaagQE3qNtzJSZvLJcmtwRK3zJqTyuQfMmI=
 /// ```
+///
+/// If retention deletes events a forward replay has not reached yet, the stream ends
+/// with an `error` message carrying the status the request would have failed with:
+/// ```text
+/// event: error
+/// data: 410 Cursor 42 is older than the retained event history of this user, ...
+/// ```
 pub async fn feed_stream(
     State(state): State<AppState>,
     session: Option<AuthSession>,
@@ -401,6 +415,7 @@ pub async fn feed_stream(
         &state.context.events_service,
         &state.context.sql_db,
         &state.context.user_service,
+        params.reverse,
     )
     .await
     .map_err(HttpError::from)?;
@@ -446,6 +461,23 @@ pub async fn feed_stream(
             };
             state.context.metrics.record_event_stream_db_query(query_start.elapsed().as_millis());
 
+            // Retention may have run since the cursors were checked. Checking after the
+            // fetch catches deletions committed before it; later ones can't affect it.
+            if !params.reverse {
+                match first_pruned_cursor(&user_cursor_map, &state.context.events_service, &state.context.sql_db).await {
+                    Ok(None) => {}
+                    Ok(Some(cursor)) => {
+                        let error = EventStreamError::CursorTooOld(cursor.to_string());
+                        yield Ok(stream_error_event(StatusCode::GONE, &error));
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::error!("Database error while checking the event watermark: {}", e);
+                        break;
+                    }
+                }
+            }
+
             let event_count = events.len();
 
             // Stream each historical event
@@ -550,6 +582,7 @@ async fn resolve_user_cursors(
     events_service: &EventsService,
     sql_db: &SqlDb,
     user_service: &crate::services::user_service::UserService,
+    reverse: bool,
 ) -> Result<HashMap<i32, Option<EventCursor>>, EventStreamError> {
     let mut user_cursor_map: HashMap<i32, Option<EventCursor>> = HashMap::new();
 
@@ -578,12 +611,53 @@ async fn resolve_user_cursors(
             None
         };
 
+        // A forward replay from below the retention watermark would silently skip
+        // deleted events. Reverse replays only walk further into the past, where
+        // missing history is expected, and no cursor means "whatever is retained".
+        if let (Some(cursor), false) = (cursor, reverse) {
+            let pruned_through = events_service
+                .pruned_through(user_id, &mut sql_db.pool().into())
+                .await?;
+            if pruned_through.is_some_and(|pruned| cursor < pruned) {
+                return Err(EventStreamError::CursorTooOld(cursor.to_string()));
+            }
+        }
+
         user_cursor_map.insert(user_id, cursor);
     }
 
     Ok(user_cursor_map)
 }
 
+/// The first cursor in `user_cursor_map` that its user's retention watermark has
+/// moved past, meaning events after it may have been deleted unseen.
+async fn first_pruned_cursor(
+    user_cursor_map: &HashMap<i32, Option<EventCursor>>,
+    events_service: &EventsService,
+    sql_db: &SqlDb,
+) -> Result<Option<EventCursor>, sqlx::Error> {
+    for (user_id, cursor) in user_cursor_map {
+        let Some(cursor) = cursor else {
+            continue;
+        };
+        let pruned_through = events_service
+            .pruned_through(*user_id, &mut sql_db.pool().into())
+            .await?;
+        if pruned_through.is_some_and(|pruned| *cursor < pruned) {
+            return Ok(Some(*cursor));
+        }
+    }
+    Ok(None)
+}
+
+/// SSE message ending a stream that already started with an error: `event: error`
+/// and `data: <status code> <message>`.
+fn stream_error_event(status: StatusCode, error: &EventStreamError) -> Event {
+    Event::default()
+        .event("error")
+        .data(format!("{} {error}", status.as_u16()))
+}
+
 fn has_bearer_auth(headers: &HeaderMap) -> bool {
     extract_bearer_token(headers).has_bearer_scheme()
 }
@@ -854,4 +928,69 @@ mod tests {
         let filters = authorize(&[wd("/pub/")], &cursors(&[&a, &b]), None).unwrap();
         assert_eq!(filters, vec![pf("/pub/")]);
     }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn resolve_user_cursors_rejects_pruned_cursor() {
+        use crate::persistence::files::events::{EventRepository, EventType};
+        use crate::shared::webdav::EntryPath;
+        use pubky_common::crypto::Hash;
+
+        let context = crate::AppContext::test().await;
+        let user = pk();
+        let user_id = context.user_service.create(&user).await.unwrap().id;
+        let path = EntryPath::new(user.clone(), wd("/pub/a"));
+        for _ in 0..3 {
+            EventRepository::create(
+                user_id,
+                EventType::Put {
+                    content_hash: Hash::from_bytes([0; 32]),
+                },
+                &path,
+                &mut context.sql_db.pool().into(),
+            )
+            .await
+            .unwrap();
+        }
+        // Prune everything but the newest event, watermark is now 2.
+        EventRepository::delete_exceeding_per_user(1, 100, &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+
+        let resolve = |cursor: Option<&str>, reverse: bool| {
+            let user_cursors = vec![(user.clone(), cursor.map(String::from))];
+            let context = context.clone();
+            async move {
+                resolve_user_cursors(
+                    &user_cursors,
+                    &context.events_service,
+                    &context.sql_db,
+                    &context.user_service,
+                    reverse,
+                )
+                .await
+            }
+        };
+
+        let err = resolve(Some("1"), false).await.unwrap_err();
+        assert!(matches!(err, EventStreamError::CursorTooOld(_)));
+        assert_eq!(
+            HttpError::from(err).into_response().status(),
+            StatusCode::GONE
+        );
+        // At or above the watermark, no history is missing.
+        assert!(resolve(Some("2"), false).await.is_ok());
+        // Reverse replays and fresh streams only see what is retained.
+        assert!(resolve(Some("1"), true).await.is_ok());
+        assert!(resolve(None, false).await.is_ok());
+
+        // A stream already past the check notices the watermark moving.
+        let map = resolve(Some("2"), false).await.unwrap();
+        let pruned = || first_pruned_cursor(&map, &context.events_service, &context.sql_db);
+        assert_eq!(pruned().await.unwrap(), None);
+        EventRepository::delete_exceeding_per_user(0, 100, &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(pruned().await.unwrap(), Some(EventCursor::new(2)));
+    }
 }
@@ -2,14 +2,20 @@
 //!
 //! - [`events`]: Historical event feed and live SSE stream for file change notifications.
 //! - [`info`]: Homeserver feature discovery.
+//! - [`path_quotas`]: Owner-managed storage limits for directory prefixes.
+//! - [`republish`]: Republish health of the user's pkarr record, and republishing on demand.
 //! - [`root`]: Server info endpoint.
 //! - [`signup_tokens`]: Signup token validation.
+//! - [`storage_usage`]: Storage usage breakdown by directory and app.
 //! - [`tenants`]: Per-user data routes (read, write).
 //!
 //! Auth routes (signup, signin, session management) live in [`crate::client_server::auth::routes`].
 
 pub(crate) mod events;
 pub(crate) mod info;
+pub(crate) mod path_quotas;
+pub(crate) mod republish;
 pub(crate) mod root;
 pub(crate) mod signup_tokens;
+pub(crate) mod storage_usage;
 pub(crate) mod tenants;
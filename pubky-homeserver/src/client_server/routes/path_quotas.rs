@@ -0,0 +1,177 @@
+//! Per-path storage quotas of the authenticated user.
+//!
+//! Lets the owner cap how much storage everything below a directory prefix
+//! may use, e.g. `/pub/photos-app/` limited to 1 GB, so a single app can't fill
+//! the account. Limits apply in addition to the user's total storage quota and
+//! are enforced by the write finalization layer. All routes require root capability.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::client_server::{
+    auth::{AuthSession, GrantAuthService},
+    AppState,
+};
+use crate::persistence::{
+    files::write_finalization_layer::entries_used_bytes,
+    sql::{
+        entry::EntryRepository,
+        path_quota::{PathQuotaEntity, PathQuotaRepository},
+        uexecutor,
+    },
+};
+use crate::shared::{webdav::StoragePath, HttpError, HttpResult};
+
+/// Maximum number of path quotas per user. Every write checks the quotas containing its path.
+const MAX_PATH_QUOTAS: u64 = 100;
+
+/// A path quota and the storage currently used below it.
+#[derive(Debug, Serialize)]
+pub struct PathQuotaResponse {
+    /// Directory prefix the limit applies to.
+    pub path: StoragePath,
+    /// Maximum storage below `path` in MB.
+    pub max_mb: u64,
+    /// Storage used below `path` in bytes, including per-file metadata overhead.
+    pub used_bytes: u64,
+    /// Number of files below `path`.
+    pub file_count: u64,
+}
+
+/// JSON body of `PUT /quota/paths`.
+#[derive(Debug, Deserialize)]
+pub struct SetPathQuotaRequest {
+    /// Directory prefix, must end with `/`.
+    pub path: StoragePath,
+    /// Maximum storage below `path` in MB.
+    pub max_mb: u64,
+}
+
+/// Query parameters of `DELETE /quota/paths`.
+#[derive(Debug, Deserialize)]
+pub struct DeletePathQuotaParams {
+    path: StoragePath,
+}
+
+/// Validate that `path` can carry a quota: a directory other than `/`.
+fn validate_path(path: &StoragePath) -> HttpResult<()> {
+    if !path.is_directory() {
+        return Err(HttpError::bad_request(
+            "Path quotas apply to directories; the path must end with '/'",
+        ));
+    }
+    if path.is_root() {
+        return Err(HttpError::bad_request(
+            "Path quota must not be '/'; use the user storage quota instead",
+        ));
+    }
+    Ok(())
+}
+
+async fn with_usage(
+    state: &AppState,
+    user_id: i32,
+    quota: PathQuotaEntity,
+) -> HttpResult<PathQuotaResponse> {
+    let usage = EntryRepository::usage_below(
+        user_id,
+        &quota.path,
+        &mut state.context.sql_db.pool().into(),
+    )
+    .await?;
+    Ok(PathQuotaResponse {
+        path: quota.path,
+        max_mb: quota.max_mb,
+        used_bytes: entries_used_bytes(&usage),
+        file_count: usage.file_count,
+    })
+}
+
+/// `GET /quota/paths` — list the path quotas with their current usage.
+pub async fn list(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> HttpResult<impl IntoResponse> {
+    GrantAuthService::require_root_capability(&auth)?;
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(auth.user_key(), false)
+        .await?;
+
+    let quotas =
+        PathQuotaRepository::list(auth.user_key(), &mut state.context.sql_db.pool().into()).await?;
+    let mut response = Vec::with_capacity(quotas.len());
+    for quota in quotas {
+        response.push(with_usage(&state, user.id, quota).await?);
+    }
+    Ok(Json(response))
+}
+
+/// `PUT /quota/paths` — create or replace the quota of a path.
+pub async fn set(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(request): Json<SetPathQuotaRequest>,
+) -> HttpResult<impl IntoResponse> {
+    GrantAuthService::require_root_capability(&auth)?;
+    validate_path(&request.path)?;
+    if request.max_mb > i32::MAX as u64 {
+        return Err(HttpError::bad_request(format!(
+            "max_mb value {} exceeds maximum ({})",
+            request.max_mb,
+            i32::MAX
+        )));
+    }
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(auth.user_key(), true)
+        .await?;
+
+    let quota = PathQuotaEntity {
+        path: request.path,
+        max_mb: request.max_mb,
+        used_bytes: 0,
+    };
+    let mut tx = state.context.sql_db.pool().begin().await?;
+    let set = PathQuotaRepository::set_within_limit(
+        auth.user_key(),
+        &quota,
+        MAX_PATH_QUOTAS,
+        uexecutor!(tx),
+    )
+    .await?;
+    if !set {
+        return Err(HttpError::bad_request(format!(
+            "A user can have at most {MAX_PATH_QUOTAS} path quotas"
+        )));
+    }
+    tx.commit().await?;
+
+    Ok(Json(with_usage(&state, user.id, quota).await?))
+}
+
+/// `DELETE /quota/paths?path=...` — remove the quota of a path.
+pub async fn delete(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Query(params): Query<DeletePathQuotaParams>,
+) -> HttpResult<impl IntoResponse> {
+    GrantAuthService::require_root_capability(&auth)?;
+    let deleted = PathQuotaRepository::delete(
+        auth.user_key(),
+        &params.path,
+        &mut state.context.sql_db.pool().into(),
+    )
+    .await?;
+    if !deleted {
+        return Err(HttpError::not_found());
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,73 @@
+//! Republish health of the authenticated user's pkarr record.
+//!
+//! The user keys republisher keeps users' records alive on the DHT. These routes
+//! let a user check how the last attempt went, e.g. after moving to this
+//! homeserver, and republish their record without waiting for the next run.
+
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use pubky_common::republish_status::RepublishStatus;
+
+use crate::client_server::{auth::AuthSession, AppState};
+use crate::persistence::sql::{republish_status::RepublishStatusRepository, user::UserEntity};
+use crate::republishers::UserKeysRepublisher;
+use crate::shared::{HttpError, HttpResult};
+
+/// Minimum time between two republishes of the same user requested through
+/// `POST /pkdns/republish`. Keeps users from spamming the DHT.
+const MIN_USER_REPUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+
+async fn status_of(state: &AppState, user: &UserEntity) -> HttpResult<RepublishStatus> {
+    let status =
+        RepublishStatusRepository::get(user.id, &mut state.context.sql_db.pool().into()).await?;
+    Ok(status.map(Into::into).unwrap_or_default())
+}
+
+/// `GET /pkdns/republish` — result of the last attempts to republish the user's record.
+pub async fn get(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> HttpResult<impl IntoResponse> {
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(auth.user_key(), false)
+        .await?;
+    Ok(Json(status_of(&state, &user).await?))
+}
+
+/// `POST /pkdns/republish` — republish the user's record now and return the new status.
+///
+/// Fails with `429 Too Many Requests` if the record was attempted less than a
+/// minute ago, by the republisher or an earlier request. The attempt is claimed
+/// before publishing, so concurrent requests can't both publish. The record is
+/// published with the homeserver's shared pkarr client.
+pub async fn post(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> HttpResult<impl IntoResponse> {
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(auth.user_key(), true)
+        .await?;
+    let claimed = RepublishStatusRepository::claim_attempt(
+        user.id,
+        MIN_USER_REPUBLISH_INTERVAL,
+        &mut state.context.sql_db.pool().into(),
+    )
+    .await?;
+    if !claimed {
+        return Err(HttpError::new_with_message(
+            StatusCode::TOO_MANY_REQUESTS,
+            "The record was republished less than a minute ago",
+        ));
+    }
+
+    UserKeysRepublisher::from_context(&state.context)
+        .republish_user(&state.context.pkarr_client, &user.public_key)
+        .await
+        .map_err(HttpError::internal_server_and_log)?;
+    Ok(Json(status_of(&state, &user).await?))
+}
@@ -35,7 +35,7 @@ pub async fn get(
     State(state): State<AppState>,
     Path(token): Path<String>,
 ) -> HttpResult<impl IntoResponse> {
-    if state.context.config_toml.general.signup_mode != SignupMode::TokenRequired {
+    if state.context.config_service.live().get().signup_mode != SignupMode::TokenRequired {
         return Err(HttpError::new_with_message(
             StatusCode::BAD_REQUEST,
             "Signup tokens not required",
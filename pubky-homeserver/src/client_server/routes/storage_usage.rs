@@ -0,0 +1,35 @@
+//! Storage usage breakdown of the authenticated user.
+//!
+//! Aggregates the user's entries by top-level directory (`/pub/`, `/priv/`) and
+//! app directory (`/pub/photos-app/`) so users can see what consumes their quota.
+//! Requires root capability as the breakdown reveals private directory names.
+
+use axum::{extract::State, response::IntoResponse, Json};
+
+use crate::client_server::{
+    auth::{AuthSession, GrantAuthService},
+    AppState,
+};
+use crate::services::storage_usage::storage_usage;
+use crate::shared::HttpResult;
+
+/// `GET /quota/usage` — storage usage per directory and app, with the applicable limits.
+pub async fn get(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> HttpResult<impl IntoResponse> {
+    GrantAuthService::require_root_capability(&auth)?;
+    let user = state
+        .context
+        .user_service
+        .get_or_http_error(auth.user_key(), false)
+        .await?;
+
+    let usage = storage_usage(
+        &state.context.sql_db,
+        &user,
+        state.context.config_toml.storage.default_quota_mb,
+    )
+    .await?;
+    Ok(Json(usage))
+}
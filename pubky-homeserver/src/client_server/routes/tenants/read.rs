@@ -1,3 +1,4 @@
+use crate::constants::{DEFAULT_LIST_LIMIT, DEFAULT_MAX_LIST_LIMIT};
 use crate::persistence::sql::entry::{EntryEntity, EntryRepository};
 use crate::shared::{HttpError, HttpResult};
 use crate::{
@@ -56,6 +57,7 @@ pub async fn head(
         .file_service
         .get_info(&entry_path, &mut state.context.sql_db.pool().into())
         .await?;
+    ensure_not_blocked(&state, &entry_path, &entry).await?;
     let response = entry.to_response_headers().into_response();
     Ok(response)
 }
@@ -100,6 +102,7 @@ pub async fn get(
         .file_service
         .get_info(&entry_path, &mut state.context.sql_db.pool().into())
         .await?;
+    ensure_not_blocked(&state, &entry_path, &entry).await?;
 
     // Per RFC 7232 §3: If-None-Match has precedence over If-Modified-Since.
     if let Some(request_etag) = headers
@@ -132,13 +135,34 @@ pub async fn get(
         }
     }
 
-    let stream = state.context.file_service.get_stream(&entry_path).await?;
+    let stream = state.context.file_service.get_entry_stream(&entry).await?;
     let body_stream = Body::from_stream(stream);
     let mut response = entry.to_response_headers().into_response();
     *response.body_mut() = body_stream;
     Ok(response)
 }
 
+/// Hide files taken down by the operator, including copies uploaded before the block.
+async fn ensure_not_blocked(
+    state: &AppState,
+    entry_path: &EntryPath,
+    entry: &EntryEntity,
+) -> HttpResult<()> {
+    let block = state
+        .context
+        .content_block_service
+        .find_match(
+            entry_path,
+            Some(&entry.content_hash),
+            &mut state.context.sql_db.pool().into(),
+        )
+        .await?;
+    match block {
+        Some(_) => Err(HttpError::content_blocked()),
+        None => Ok(()),
+    }
+}
+
 async fn list(
     state: AppState,
     entry_path: &EntryPath,
@@ -164,25 +188,47 @@ async fn list(
         }
     };
 
-    let entries = if params.shallow {
-        EntryRepository::list_shallow(
-            entry_path,
-            params.limit,
-            parsed_cursor,
-            params.reverse,
-            &mut state.context.sql_db.pool().into(),
-        )
-        .await?
-    } else {
-        EntryRepository::list_deep(
-            entry_path,
-            params.limit,
-            parsed_cursor,
-            params.reverse,
-            &mut state.context.sql_db.pool().into(),
-        )
-        .await?
-    };
+    // Blocked entries are dropped from each page, so keep reading pages until the
+    // limit is filled or the directory is exhausted.
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_LIST_LIMIT)
+        .min(DEFAULT_MAX_LIST_LIMIT);
+    let mut cursor = parsed_cursor;
+    let mut entries = Vec::new();
+    loop {
+        let page = if params.shallow {
+            EntryRepository::list_shallow(
+                entry_path,
+                Some(limit),
+                cursor,
+                params.reverse,
+                &mut state.context.sql_db.pool().into(),
+            )
+            .await?
+        } else {
+            EntryRepository::list_deep(
+                entry_path,
+                Some(limit),
+                cursor,
+                params.reverse,
+                &mut state.context.sql_db.pool().into(),
+            )
+            .await?
+        };
+        let exhausted = page.len() < limit as usize;
+        cursor = page.last().cloned();
+        let visible = state
+            .context
+            .content_block_service
+            .retain_visible(page, &mut state.context.sql_db.pool().into())
+            .await?;
+        entries.extend(visible);
+        if exhausted || entries.len() >= limit as usize {
+            break;
+        }
+    }
+    entries.truncate(limit as usize);
     let pubky_urls = entries
         .iter()
         .map(|entry| format!("pubky://{}", entry))
@@ -443,6 +489,159 @@ mod tests {
         assert_eq!(response.text(), "original");
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn read_cache_serves_overwritten_files() {
+        use crate::storage_config::{StorageCacheBackend, StorageCacheToml};
+
+        let context = AppContext::test_with_config(|config| {
+            config.storage.cache = Some(StorageCacheToml {
+                backend: StorageCacheBackend::InMemory,
+                size_mb: 1,
+                max_file_size_mb: 1,
+            });
+        })
+        .await;
+        let router = ClientServer::create_router(Arc::clone(&context)).unwrap();
+        let server = axum_test::TestServer::new(router).unwrap();
+        let keypair = Keypair::random();
+        let cookie = create_root_user(&server, &keypair).await.unwrap();
+        let public_key = keypair.public_key();
+
+        for content in ["first", "second"] {
+            server
+                .put("/pub/avatar")
+                .add_header("host", public_key.z32())
+                .add_header(header::COOKIE, cookie.clone())
+                .text(content)
+                .expect_success()
+                .await;
+            for _ in 0..2 {
+                let response = server
+                    .get("/pub/avatar")
+                    .add_header("host", public_key.z32())
+                    .expect_success()
+                    .await;
+                assert_eq!(response.text(), content);
+            }
+        }
+
+        let metrics = context.metrics.render().unwrap();
+        assert!(
+            metrics
+                .contains("storage_cache_hit_count_total{otel_scope_name=\"pubky_homeserver\"} 2"),
+            "{metrics}"
+        );
+        assert!(
+            metrics
+                .contains("storage_cache_miss_count_total{otel_scope_name=\"pubky_homeserver\"} 2"),
+            "{metrics}"
+        );
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn blocked_content_is_hidden_and_cannot_be_reuploaded() {
+        use crate::persistence::sql::content_block::{BlockTarget, ContentBlockRepository};
+
+        let (context, _router, server, public_key, cookie) = create_environment().await.unwrap();
+        let data = b"takedown".to_vec();
+        server
+            .put("/pub/foo")
+            .add_header("host", public_key.z32())
+            .add_header(header::COOKIE, cookie.clone())
+            .bytes(data.clone().into())
+            .expect_success()
+            .await;
+
+        let block = ContentBlockRepository::add(
+            &BlockTarget::Hash(pubky_common::crypto::hash(&data)),
+            Some("dmca"),
+            "admin",
+            &mut context.sql_db.pool().into(),
+        )
+        .await
+        .unwrap();
+
+        for method in [Method::GET, Method::HEAD] {
+            server
+                .method(method, "/pub/foo")
+                .add_header("host", public_key.z32())
+                .expect_failure()
+                .await
+                .assert_status(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+        }
+        server
+            .put("/pub/copy")
+            .add_header("host", public_key.z32())
+            .add_header(header::COOKIE, cookie.clone())
+            .bytes(data.clone().into())
+            .expect_failure()
+            .await
+            .assert_status(StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS);
+
+        ContentBlockRepository::remove(block.id, "admin", &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+        server
+            .get("/pub/foo")
+            .add_header("host", public_key.z32())
+            .expect_success()
+            .await;
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn blocked_entries_are_hidden_from_listings() {
+        use crate::persistence::sql::content_block::{BlockTarget, ContentBlockRepository};
+        use crate::quota_config::GlobPattern;
+
+        let (context, _router, server, public_key, cookie) = create_environment().await.unwrap();
+        for (path, content) in [
+            ("/pub/a/blocked", "takedown"),
+            ("/pub/a/ok", "fine"),
+            ("/pub/spam/post", "spam"),
+        ] {
+            server
+                .put(path)
+                .add_header("host", public_key.z32())
+                .add_header(header::COOKIE, cookie.clone())
+                .text(content)
+                .expect_success()
+                .await;
+        }
+        for target in [
+            BlockTarget::Hash(pubky_common::crypto::hash(b"takedown")),
+            BlockTarget::PathGlob(GlobPattern::new("*/pub/spam/**")),
+        ] {
+            ContentBlockRepository::add(&target, None, "admin", &mut context.sql_db.pool().into())
+                .await
+                .unwrap();
+        }
+        context.content_block_service.invalidate();
+
+        let list = |query: &'static str| {
+            let server = &server;
+            let public_key = public_key.clone();
+            async move {
+                server
+                    .get(&format!("/pub/{query}"))
+                    .add_header("host", public_key.z32())
+                    .expect_success()
+                    .await
+                    .text()
+            }
+        };
+        let ok = format!("pubky://{}/pub/a/ok", public_key.z32());
+        assert_eq!(list("").await, ok);
+        // A page whose entries are all blocked doesn't end the listing early.
+        assert_eq!(list("?limit=1").await, ok);
+        assert_eq!(
+            list("?shallow=true").await,
+            format!("pubky://{}/pub/a/", public_key.z32())
+        );
+    }
+
     #[tokio::test]
     #[pubky_test_utils::test]
     async fn if_last_modified() {
@@ -601,6 +800,110 @@ mod tests {
         r.assert_status(StatusCode::OK);
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn conditional_put_requires_matching_etag() {
+        let (_, _, server, public_key, cookie) = create_environment().await.unwrap();
+        let put = |body: &'static str| {
+            server
+                .put("/pub/foo")
+                .add_header("host", public_key.z32())
+                .add_header(header::COOKIE, cookie.clone())
+                .bytes(Vec::from(body).into())
+        };
+
+        put("v1")
+            .add_header(header::IF_MATCH, "*")
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+        put("v1")
+            .add_header(header::IF_NONE_MATCH, "*")
+            .expect_success()
+            .await;
+        put("v1")
+            .add_header(header::IF_NONE_MATCH, "*")
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+
+        let etag_v1 = server
+            .get("/pub/foo")
+            .add_header("host", public_key.z32())
+            .expect_success()
+            .await
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .clone();
+        put("v2")
+            .add_header(header::IF_MATCH, etag_v1.clone())
+            .expect_success()
+            .await;
+
+        // The file changed since v1.
+        put("v3")
+            .add_header(header::IF_MATCH, etag_v1)
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+        put("v3")
+            .add_header(header::IF_MATCH, "\"not-a-hash\"")
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+
+        let body = server
+            .get("/pub/foo")
+            .add_header("host", public_key.z32())
+            .expect_success()
+            .await;
+        assert_eq!(body.as_bytes().as_ref(), b"v2");
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn conditional_delete_requires_matching_etag() {
+        let (_, _, server, public_key, cookie) = create_environment().await.unwrap();
+        let delete = || {
+            server
+                .delete("/pub/foo")
+                .add_header("host", public_key.z32())
+                .add_header(header::COOKIE, cookie.clone())
+        };
+
+        delete()
+            .add_header(header::IF_MATCH, "*")
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+
+        server
+            .put("/pub/foo")
+            .add_header("host", public_key.z32())
+            .add_header(header::COOKIE, cookie.clone())
+            .bytes(Vec::from("v1").into())
+            .expect_success()
+            .await;
+        let etag = server
+            .get("/pub/foo")
+            .add_header("host", public_key.z32())
+            .expect_success()
+            .await
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .clone();
+
+        delete()
+            .add_header(header::IF_NONE_MATCH, "*")
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+        delete()
+            .add_header(header::IF_MATCH, etag.clone())
+            .expect_success()
+            .await;
+        delete()
+            .add_header(header::IF_MATCH, etag)
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+    }
+
     #[tokio::test]
     #[pubky_test_utils::test]
     async fn pub_get_stays_anonymous_after_dual_root_switch() {
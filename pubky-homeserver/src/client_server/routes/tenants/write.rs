@@ -1,4 +1,4 @@
-use axum::http::HeaderMap;
+use axum::http::{header, HeaderMap};
 use axum::{
     body::Body,
     extract::{Path, State},
@@ -6,6 +6,7 @@ use axum::{
     response::IntoResponse,
 };
 use futures_util::stream::StreamExt;
+use pubky_common::crypto::Hash;
 
 use crate::{
     client_server::{
@@ -15,8 +16,11 @@ use crate::{
     },
     persistence::{
         files::{
-            write_finalization_layer::{resolve_storage_max_bytes, would_exceed_limit},
-            WriteStreamError,
+            write_finalization_layer::{
+                exceeded_path_quota, resolve_storage_max_bytes, would_exceed_limit,
+                WritePrecondition,
+            },
+            FileIoError, WriteStreamError,
         },
         sql::{entry::EntryRepository, user::UserEntity, UnifiedExecutor},
     },
@@ -32,15 +36,17 @@ pub async fn legacy_delete(
     session: AuthSession,
     tenant: RequestTenant,
     Path(path): Path<WebDavFilePathAxum>,
+    headers: HeaderMap,
 ) -> HttpResult<impl IntoResponse> {
     let entry_path = EntryPath::new(tenant.public_key().clone(), path.inner().to_owned());
-    delete(state, session, entry_path).await
+    delete(state, session, entry_path, headers).await
 }
 
 pub async fn delete(
     State(state): State<AppState>,
     session: AuthSession,
     entry_path: EntryPath,
+    headers: HeaderMap,
 ) -> HttpResult<impl IntoResponse> {
     if !entry_path.path().is_file() {
         return Err(HttpError::bad_request("Target path must be a file"));
@@ -53,7 +59,11 @@ pub async fn delete(
         .get_or_http_error(entry_path.pubkey(), false)
         .await?;
 
-    state.context.file_service.delete(&entry_path).await?;
+    let file_service = &state.context.file_service;
+    match precondition_from_headers(&headers)? {
+        Some(precondition) => file_service.delete_if(&entry_path, precondition).await?,
+        None => file_service.delete(&entry_path).await?,
+    }
     Ok((StatusCode::NO_CONTENT, ()))
 }
 
@@ -80,6 +90,7 @@ pub async fn put(
         return Err(HttpError::bad_request("Target path must be a file"));
     }
     has_write_permission(&session, entry_path.pubkey(), entry_path.path())?;
+    let precondition = precondition_from_headers(&headers)?;
 
     let user = state
         .context
@@ -107,14 +118,66 @@ pub async fn put(
     let converted_stream =
         body_stream.map(|chunk_result| chunk_result.map_err(WriteStreamError::Axum));
 
-    state
-        .context
-        .file_service
-        .write_stream(&entry_path, converted_stream)
-        .await?;
+    let file_service = &state.context.file_service;
+    match precondition {
+        Some(precondition) => {
+            file_service
+                .write_stream_if(&entry_path, precondition, converted_stream)
+                .await?
+        }
+        None => {
+            file_service
+                .write_stream(&entry_path, converted_stream)
+                .await?
+        }
+    };
     Ok((StatusCode::CREATED, ()))
 }
 
+/// Parse the `If-Match` and `If-None-Match` headers of a write or delete.
+///
+/// `If-Match` takes `*` or a single strong ETag; any other value can never match the
+/// current version, so it fails the request with 412 up front.
+/// `If-None-Match` only supports `*`.
+fn precondition_from_headers(headers: &HeaderMap) -> HttpResult<Option<WritePrecondition>> {
+    let header_str = |name: header::HeaderName| {
+        headers
+            .get(&name)
+            .map(|value| {
+                value
+                    .to_str()
+                    .map(str::trim)
+                    .map_err(|_| HttpError::bad_request(format!("Invalid {name} header")))
+            })
+            .transpose()
+    };
+    let if_match = header_str(header::IF_MATCH)?;
+    let if_none_match = header_str(header::IF_NONE_MATCH)?;
+
+    match (if_match, if_none_match) {
+        (Some(_), Some(_)) => Err(HttpError::bad_request(
+            "If-Match and If-None-Match can't be combined",
+        )),
+        (Some("*"), None) => Ok(Some(WritePrecondition::Exists)),
+        (Some(etag), None) => match parse_etag(etag) {
+            Some(hash) => Ok(Some(WritePrecondition::Matches(hash))),
+            None => Err(FileIoError::PreconditionFailed.into()),
+        },
+        (None, Some("*")) => Ok(Some(WritePrecondition::Absent)),
+        (None, Some(_)) => Err(HttpError::bad_request(
+            "If-None-Match only supports * on writes",
+        )),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Decode a strong ETag (`"<base64 content hash>"`) into the content hash.
+fn parse_etag(etag: &str) -> Option<Hash> {
+    let encoded = etag.strip_prefix('"')?.strip_suffix('"')?;
+    let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, encoded).ok()?;
+    Some(Hash::from_bytes(bytes.try_into().ok()?))
+}
+
 /// Parse the `Content-Length` header into a `u64`, returning `None` if absent or unparseable.
 fn content_length_from_headers(headers: &HeaderMap) -> Option<u64> {
     headers
@@ -125,7 +188,8 @@ fn content_length_from_headers(headers: &HeaderMap) -> Option<u64> {
         .ok()
 }
 
-/// Check whether the Content-Length size hint would exceed the user's storage quota.
+/// Check whether the Content-Length size hint would exceed the user's storage quota
+/// or one of their path quotas.
 /// Returns Ok if there is no size hint, no quota, or the hint fits within the quota.
 async fn fail_if_size_hint_exceeds_quota<'a>(
     content_size_hint: Option<u64>,
@@ -154,6 +218,12 @@ async fn fail_if_size_hint_exceeds_quota<'a>(
     if would_exceed_limit(user.used_bytes, bytes_delta, max_bytes) {
         return Err(HttpError::insufficient_storage());
     }
+    if exceeded_path_quota(user.id, entry_path.path(), bytes_delta, executor)
+        .await?
+        .is_some()
+    {
+        return Err(HttpError::insufficient_storage());
+    }
 
     Ok(())
 }
@@ -162,6 +232,7 @@ async fn fail_if_size_hint_exceeds_quota<'a>(
 mod tests {
     use pubky_common::crypto::Keypair;
 
+    use crate::persistence::sql::path_quota::{PathQuotaEntity, PathQuotaRepository};
     use crate::persistence::sql::SqlDb;
     use crate::services::user_service::UserService;
     use crate::shared::webdav::StoragePath;
@@ -268,4 +339,30 @@ mod tests {
             .await
             .expect("unlimited quota should accept any size");
     }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_hint_exceeds_path_quota() {
+        let db = SqlDb::test().await;
+        let pk = Keypair::random().public_key();
+        let user = UserService::new(db.clone()).create(&pk).await.unwrap();
+        PathQuotaRepository::set(
+            &pk,
+            &PathQuotaEntity {
+                path: StoragePath::new("/pub/app/").unwrap(),
+                max_mb: 1,
+                used_bytes: 0,
+            },
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+
+        check_hint(&db, &user, None, "/pub/app/big.bin", Some(1024 * 1024))
+            .await
+            .expect_err("content + metadata should exceed the 1 MB path quota");
+        check_hint(&db, &user, None, "/pub/other/big.bin", Some(1024 * 1024))
+            .await
+            .expect("paths outside the prefix are not limited");
+    }
 }
@@ -0,0 +1,132 @@
+//! TLS acceptor of the Pubky TLS server during a key transition.
+//!
+//! The server presents its Ed25519 key as a raw public key (RFC 7250), and
+//! clients check it against the key they resolved. After a rotation, clients
+//! may still resolve the previous key, either directly or through a user's
+//! `_pubky` record that was not republished yet. The acceptor reads the SNI
+//! first and presents the previous key to those clients.
+//!
+//! Only the records of users with an account on this homeserver are resolved, and
+//! only for [`RESOLVE_TIMEOUT`], so a client can't make the handshake wait on the
+//! DHT for arbitrary keys.
+
+use std::{future::Future, io, pin::Pin, sync::Arc, time::Duration};
+
+use pkarr::ResolvePolicy;
+use pubky_common::crypto::PublicKey;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::{
+    rustls::{server::Acceptor, ServerConfig},
+    server::TlsStream,
+    LazyConfigAcceptor,
+};
+
+use crate::{
+    app_context::AppContext,
+    persistence::sql::{user::UserRepository, SqlDb},
+    republishers::listed_homeservers,
+    shared::HomeserverKeys,
+};
+
+/// Same as axum-server's default for its rustls acceptor.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for a user's `_pubky` record before presenting the current key.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Presents the previous key to clients that expect it, the current key to everyone else.
+#[derive(Clone)]
+pub(crate) struct TransitionAcceptor {
+    keys: HomeserverKeys,
+    current: Arc<ServerConfig>,
+    previous: Arc<ServerConfig>,
+    /// Resolves the `_pubky` records of users named in the SNI.
+    pkarr_client: pkarr::Client,
+    /// Looks up whether users named in the SNI are local.
+    sql_db: SqlDb,
+}
+
+impl TransitionAcceptor {
+    /// `None` unless a key transition is active.
+    pub fn from_context(context: &AppContext) -> Option<Self> {
+        let keys = context.homeserver_keys();
+        let previous = Arc::new(keys.previous_keypair()?.to_rpk_rustls_server_config());
+        Some(Self {
+            current: Arc::new(context.keypair.to_rpk_rustls_server_config()),
+            previous,
+            keys,
+            pkarr_client: context.pkarr_client.clone(),
+            sql_db: context.sql_db.clone(),
+        })
+    }
+
+    /// Pick the config for a client asking for `server_name`.
+    async fn config_for(&self, server_name: Option<&str>) -> Arc<ServerConfig> {
+        let Some(previous_key) = self.keys.previous_keypair().map(|kp| kp.public_key()) else {
+            return self.current.clone();
+        };
+        let expects_previous = match server_name {
+            Some(name) if name == previous_key.z32() => true,
+            Some(name) => match name.strip_prefix("_pubky.") {
+                Some(user) => self.user_points_at(user, &previous_key).await,
+                None => false,
+            },
+            None => false,
+        };
+        if expects_previous {
+            self.previous.clone()
+        } else {
+            self.current.clone()
+        }
+    }
+
+    /// Whether `user` has an account here and their `_pubky` record lists
+    /// `previous_key` before the current key.
+    async fn user_points_at(&self, user: &str, previous_key: &PublicKey) -> bool {
+        let Ok(user) = PublicKey::try_from_z32(user) else {
+            return false;
+        };
+        if UserRepository::get(&user, &mut self.sql_db.pool().into())
+            .await
+            .is_err()
+        {
+            return false;
+        }
+        let resolve = self.pkarr_client.resolve(&user, ResolvePolicy::CacheFirst);
+        let Ok(Ok(packet)) = tokio::time::timeout(RESOLVE_TIMEOUT, resolve).await else {
+            return false;
+        };
+        listed_homeservers(&packet)
+            .into_iter()
+            .find(|homeserver| homeserver == previous_key || homeserver == self.keys.current())
+            .is_some_and(|homeserver| &homeserver == previous_key)
+    }
+}
+
+impl<I, S> axum_server::accept::Accept<I, S> for TransitionAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = S;
+    type Future = Pin<Box<dyn Future<Output = io::Result<(Self::Stream, Self::Service)>> + Send>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.clone();
+        Box::pin(async move {
+            let handshake = async {
+                let start = LazyConfigAcceptor::new(Acceptor::default(), stream).await?;
+                let config = acceptor
+                    .config_for(start.client_hello().server_name())
+                    .await;
+                start.into_stream(config).await
+            };
+            let stream = tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake)
+                .await
+                .map_err(|_| {
+                    io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out")
+                })??;
+            Ok((stream, service))
+        })
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// Role of an admin key, restricting which admin routes it can call.
+///
+/// Roles are ordered: each role may call everything the previous one can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminRole {
+    /// Read-only access to users, sessions, jobs, bans and events.
+    ReadOnly,
+    /// Read access plus user moderation: disabling and deleting users and entries,
+    /// revoking sessions, and managing bans and the content blocklist.
+    Moderator,
+    /// Full access, including signup tokens, quotas, retention and WebDAV.
+    Admin,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_role_order_and_serde() {
+        assert!(AdminRole::ReadOnly < AdminRole::Moderator);
+        assert!(AdminRole::Moderator < AdminRole::Admin);
+        let role: AdminRole = serde_json::from_str("\"read_only\"").unwrap();
+        assert_eq!(role, AdminRole::ReadOnly);
+    }
+}
@@ -10,7 +10,7 @@ use super::{
     domain_port::DomainPort,
     quota_config::{BandwidthQuota, PathLimit},
     storage_config::StorageToml,
-    Domain, SignupMode,
+    AdminRole, Domain, SignupMode,
 };
 
 use crate::{
@@ -18,6 +18,7 @@ use crate::{
     persistence::sql::ConnectionString,
     shared::toml_merge,
 };
+use pubky_common::crypto::PublicKey;
 use serde::{Deserialize, Serialize};
 use std::{
     fmt::Debug,
@@ -58,9 +59,23 @@ pub struct PkdnsToml {
     pub public_icann_http_port: Option<u16>,
     pub icann_domain: Option<Domain>,
     pub user_keys_republisher_interval: u64,
+    /// Seconds since a user's record was last refreshed on the DHT, by the
+    /// republisher or by the user, before it is republished again.
+    pub user_keys_republisher_min_age: u64,
+    /// Users with events in the last this many days are republished first.
+    /// 0 disables the prioritization.
+    pub user_keys_republisher_active_days: u64,
+    /// Maximum number of user keys republished per hour, across all instances
+    /// sharing the database. 0 means unlimited.
+    pub user_keys_republisher_budget_per_hour: u64,
+    /// Maximum random delay in seconds added to each republisher run.
+    pub user_keys_republisher_jitter: u64,
     pub dht_bootstrap_nodes: Option<Vec<DomainPort>>,
     pub dht_relay_nodes: Option<Vec<Url>>,
     pub dht_request_timeout_ms: Option<NonZeroU64>,
+    /// Days the homeserver keeps answering to its previous key after
+    /// `keypair rotate`. 0 disables the transition.
+    pub key_transition_days: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -69,6 +84,23 @@ pub struct DriveToml {
     pub icann_listen_socket: SocketAddr,
     /// Per-path request-count rate limits.
     pub rate_limits: Vec<PathLimit>,
+    /// Where rate limiter state is kept.
+    #[serde(default)]
+    pub rate_limiter_backend: RateLimiterBackend,
+}
+
+/// Where the rate limiters keep their state.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimiterBackend {
+    /// Limits are tracked in process memory. Every homeserver instance
+    /// grants the full quota on its own.
+    #[default]
+    InMemory,
+    /// Limits are tracked in the Postgres database and shared by all
+    /// instances using it. Adds a database round trip per limited request,
+    /// and one per burst of a throttled body.
+    Postgres,
 }
 
 /// Default bandwidth limits for the rate limiter.
@@ -81,7 +113,7 @@ pub struct DriveToml {
 /// `unauthenticated_ip_rate_read` is a fixed server-level limit for
 /// anonymous requests (not overridable per-user).
 ///
-/// Consumed by `BandwidthQuotaLimitLayer`.
+/// Consumed by `BandwidthQuotaLimitLayer` and `UsageLimitLayer`.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct DefaultQuotasToml {
     /// Default bandwidth limit for user reads / downloads (e.g. "10mb/s").
@@ -99,6 +131,62 @@ pub struct DefaultQuotasToml {
     /// Server-level bandwidth limit for unauthenticated IP reads (e.g. "1mb/s").
     /// `None` means no read throttling for unauthenticated requests.
     pub unauthenticated_ip_rate_read: Option<BandwidthQuota>,
+    /// Default number of requests a user may make per minute.
+    /// Per-user DB overrides take precedence. `None` means no limit.
+    pub requests_per_minute: Option<u64>,
+    /// Default MB a user may download per UTC day. `None` means no cap.
+    pub egress_daily_mb: Option<u64>,
+    /// Default MB a user may download per UTC calendar month. `None` means no cap.
+    pub egress_monthly_mb: Option<u64>,
+    /// Default MB a user may upload per UTC day. `None` means no cap.
+    pub ingress_daily_mb: Option<u64>,
+    /// Default MB a user may upload per UTC calendar month. `None` means no cap.
+    pub ingress_monthly_mb: Option<u64>,
+}
+
+/// Event log retention and compaction.
+///
+/// Without any policy the events table grows forever. Consumed by
+/// `EventRetentionJob`, which prunes in the background and records a
+/// per-user watermark so `/events-stream` can reject cursors that point
+/// into pruned history.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct EventsToml {
+    /// Delete events older than this many days. `None` keeps events forever.
+    pub retention_max_age_days: Option<NonZeroU64>,
+    /// Keep at most this many of the newest events per user. `None` means no limit.
+    pub retention_max_per_user: Option<NonZeroU64>,
+    /// Keep only the latest event per user and path, dropping superseded ones.
+    pub compaction: bool,
+    /// Interval in seconds between two retention runs. 0 disables the schedule;
+    /// a run can still be triggered through the admin API.
+    pub retention_interval: u64,
+}
+
+impl EventsToml {
+    /// Whether any retention or compaction policy is configured.
+    pub fn has_policy(&self) -> bool {
+        self.retention_max_age_days.is_some()
+            || self.retention_max_per_user.is_some()
+            || self.compaction
+    }
+}
+
+/// Mirroring of users whose primary homeserver is another homeserver.
+///
+/// Users opt in by signing up on this homeserver and listing it after their
+/// primary homeserver in their `_pubky` record. Consumed by `MirrorJob`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MirrorToml {
+    /// Replicate the `/pub/` data of users listing this homeserver as a mirror.
+    pub enabled: bool,
+    /// Interval in seconds between two mirror runs. 0 disables the schedule;
+    /// a run can still be triggered through the admin API.
+    pub interval: u64,
+    /// Interval in seconds between two scheduled runs that resolve every local
+    /// user to find users who newly opted in. Other scheduled runs only resolve
+    /// users that are already mirrored.
+    pub discovery_interval: u64,
 }
 
 /// Admin server configuration
@@ -108,8 +196,21 @@ pub struct AdminToml {
     pub enabled: bool,
     /// Socket address for the admin HTTP server
     pub listen_socket: SocketAddr,
-    /// Password for admin authentication
+    /// Password for admin authentication. Grants the `admin` role.
+    /// An empty password disables password authentication.
     pub admin_password: String,
+    /// Admins authenticating with requests signed by their key.
+    #[serde(default)]
+    pub keys: Vec<AdminKeyToml>,
+}
+
+/// An admin identified by their public key.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AdminKeyToml {
+    /// Key the admin signs requests with.
+    pub public_key: PublicKey,
+    /// Which admin routes the key can call.
+    pub role: AdminRole,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
@@ -145,10 +246,22 @@ pub struct MetricsToml {
     pub listen_socket: SocketAddr,
 }
 
+/// DNS server answering pkarr names, for devices without a pubky SDK.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DnsToml {
+    /// Enable or disable the DNS server
+    pub enabled: bool,
+    /// Socket address the DNS server listens on, for both UDP and TCP.
+    pub listen_socket: SocketAddr,
+    /// Resolver that names not ending in a public key are forwarded to.
+    /// `None` refuses them.
+    pub upstream: Option<SocketAddr>,
+}
+
 /// The overall application configuration, composed of several subsections.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ConfigToml {
-    /// General application settings (signup mode, quotas, backups).
+    /// General application settings (signup mode, database).
     pub general: GeneralToml,
     /// File‐drive API settings (listen sockets for Pubky TLS and HTTP).
     pub drive: DriveToml,
@@ -156,10 +269,16 @@ pub struct ConfigToml {
     pub storage: StorageToml,
     /// Default bandwidth limits for the rate limiter. Overridable per-user via admin API.
     pub default_quotas: DefaultQuotasToml,
+    /// Event log retention and compaction.
+    pub events: EventsToml,
+    /// Mirroring of users hosted on another primary homeserver.
+    pub mirror: MirrorToml,
     /// Administrative API configuration.
     pub admin: AdminToml,
     /// Metrics server configuration.
     pub metrics: MetricsToml,
+    /// DNS server configuration.
+    pub dns: DnsToml,
     /// Peer‐to‐peer DHT / PKDNS settings (public endpoints, bootstrap, relays).
     pub pkdns: PkdnsToml,
     /// Logging configuration. If provided, the homeserver instance attempts to init
@@ -179,12 +298,30 @@ impl Default for DriveToml {
     }
 }
 
+impl Default for EventsToml {
+    fn default() -> Self {
+        ConfigToml::default().events
+    }
+}
+
+impl Default for MirrorToml {
+    fn default() -> Self {
+        ConfigToml::default().mirror
+    }
+}
+
 impl Default for AdminToml {
     fn default() -> Self {
         ConfigToml::default().admin
     }
 }
 
+impl Default for DnsToml {
+    fn default() -> Self {
+        ConfigToml::default().dns
+    }
+}
+
 impl Default for PkdnsToml {
     fn default() -> Self {
         ConfigToml::default().pkdns
@@ -253,6 +390,7 @@ impl ConfigToml {
         config.drive.pubky_listen_socket = SocketAddr::from(([127, 0, 0, 1], 0));
         config.admin.enabled = true; // Enabled for backward compat
         config.admin.listen_socket = SocketAddr::from(([127, 0, 0, 1], 0));
+        config.dns.listen_socket = SocketAddr::from(([127, 0, 0, 1], 0));
         config.pkdns.icann_domain =
             Some(Domain::from_str("localhost").expect("localhost is a valid domain"));
         config.pkdns.dht_relay_nodes = None;
@@ -315,6 +453,7 @@ mod tests {
     use crate::data_directory::log_level::LogLevel;
 
     use super::*;
+    use crate::storage_config::{StorageCacheBackend, StorageCacheToml};
     use std::{
         net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4},
         str::FromStr,
@@ -345,15 +484,31 @@ mod tests {
             SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 6288))
         );
         assert_eq!(c.admin.admin_password, "admin");
+        assert!(c.admin.keys.is_empty());
+        assert!(!c.dns.enabled);
+        assert_eq!(
+            c.dns.listen_socket,
+            SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 5300))
+        );
+        assert_eq!(c.dns.upstream, None);
         assert_eq!(c.pkdns.public_ip, IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
         assert_eq!(c.pkdns.public_pubky_tls_port, None);
         assert_eq!(c.pkdns.public_icann_http_port, None);
         assert_eq!(c.pkdns.user_keys_republisher_interval, 14400);
+        assert_eq!(c.pkdns.user_keys_republisher_min_age, 10800);
+        assert_eq!(c.pkdns.user_keys_republisher_active_days, 7);
+        assert_eq!(c.pkdns.user_keys_republisher_budget_per_hour, 0);
+        assert_eq!(c.pkdns.user_keys_republisher_jitter, 600);
         assert_eq!(c.pkdns.dht_bootstrap_nodes, None);
         assert_eq!(c.pkdns.dht_request_timeout_ms, None);
         assert_eq!(c.drive.rate_limits.len(), 1);
         assert_eq!(c.drive.rate_limits[0].path.0, "/signup_tokens/*");
         assert_eq!(c.default_quotas, DefaultQuotasToml::default());
+        assert_eq!(c.events.retention_max_age_days, None);
+        assert_eq!(c.events.retention_max_per_user, None);
+        assert!(!c.events.compaction);
+        assert_eq!(c.events.retention_interval, 3600);
+        assert!(!c.events.has_policy());
         assert_eq!(c.storage.default_quota_mb, None);
         assert_eq!(c.storage.backend, StorageConfigToml::FileSystem);
         assert_eq!(
@@ -411,6 +566,56 @@ mod tests {
         assert_eq!(merged.logging, expected_logging);
     }
 
+    #[test]
+    fn test_events_retention_config() {
+        let s = "[events]\nretention_max_age_days = 30\ncompaction = true\n";
+        let parsed = ConfigToml::from_str_with_defaults(s).unwrap();
+        assert_eq!(
+            parsed.events.retention_max_age_days,
+            Some(NonZeroU64::new(30).unwrap())
+        );
+        assert_eq!(parsed.events.retention_max_per_user, None);
+        assert!(parsed.events.compaction);
+        // Unset values fall back to the defaults.
+        assert_eq!(parsed.events.retention_interval, 3600);
+        assert!(parsed.events.has_policy());
+    }
+
+    #[test]
+    fn test_admin_keys_config() {
+        let public_key = pubky_common::crypto::Keypair::random().public_key();
+        let s = format!(
+            "[admin]\nadmin_password = \"\"\n[[admin.keys]]\npublic_key = \"{}\"\nrole = \"read_only\"\n",
+            public_key.z32()
+        );
+        let parsed = ConfigToml::from_str_with_defaults(&s).unwrap();
+        assert_eq!(
+            parsed.admin.keys,
+            vec![AdminKeyToml {
+                public_key,
+                role: AdminRole::ReadOnly
+            }]
+        );
+        assert_eq!(parsed.admin.listen_socket.port(), 6288);
+    }
+
+    #[test]
+    fn test_storage_cache_config() {
+        assert_eq!(ConfigToml::default().storage.cache, None);
+        let s = "[storage.cache]\ntype = \"in_memory\"\nsize_mb = 64\nmax_file_size_mb = 2\n";
+        let parsed = ConfigToml::from_str_with_defaults(s).unwrap();
+        assert_eq!(
+            parsed.storage.cache,
+            Some(StorageCacheToml {
+                backend: StorageCacheBackend::InMemory,
+                size_mb: 64,
+                max_file_size_mb: 2,
+            })
+        );
+        // The backend settings next to it are untouched.
+        assert_eq!(parsed.storage.backend, StorageConfigToml::FileSystem);
+    }
+
     #[test]
     fn test_legacy_general_storage_quota_migrated() {
         // general.user_storage_quota_mb should migrate to storage.default_quota_mb
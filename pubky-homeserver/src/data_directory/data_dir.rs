@@ -1,4 +1,4 @@
-use super::ConfigToml;
+use super::{ConfigToml, KeyRotation};
 use dyn_clone::DynClone;
 use std::path::Path;
 
@@ -20,6 +20,9 @@ pub trait DataDir: std::fmt::Debug + DynClone + Send + Sync {
     /// Reads the secret file from the data directory.
     /// Creates a new secret file if it doesn't exist.
     fn read_or_create_keypair(&self) -> anyhow::Result<pubky_common::crypto::Keypair>;
+
+    /// Reads the last key rotation, if the keypair was ever rotated.
+    fn read_key_rotation(&self) -> anyhow::Result<Option<KeyRotation>>;
 }
 
 dyn_clone::clone_trait_object!(DataDir);
@@ -0,0 +1,31 @@
+use std::time::{Duration, SystemTime};
+
+use pubky_common::crypto::Keypair;
+
+/// The keypair a homeserver used before its last key rotation.
+///
+/// For `[pkdns].key_transition_days` after the rotation, the homeserver keeps
+/// answering to the previous key and publishes a `_successor` record with it,
+/// pointing at the new key.
+#[derive(Debug, Clone)]
+pub struct KeyRotation {
+    /// The keypair used before the rotation.
+    pub previous_keypair: Keypair,
+    /// When the keypair was rotated.
+    pub rotated_at: SystemTime,
+}
+
+impl KeyRotation {
+    /// A rotation of `previous_keypair` that happened just now.
+    pub fn now(previous_keypair: Keypair) -> Self {
+        Self {
+            previous_keypair,
+            rotated_at: SystemTime::now(),
+        }
+    }
+
+    /// When a transition of `transition` after the rotation ends.
+    pub fn transition_ends_at(&self, transition: Duration) -> SystemTime {
+        self.rotated_at + transition
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use super::DataDir;
+use super::{DataDir, KeyRotation};
 
 /// Mock data directory for testing.
 ///
@@ -14,6 +14,8 @@ pub struct MockDataDir {
     pub config_toml: super::ConfigToml,
     /// The keypair for the homeserver.
     pub keypair: pubky_common::crypto::Keypair,
+    /// The last key rotation, if any.
+    pub key_rotation: Option<KeyRotation>,
 }
 
 impl MockDataDir {
@@ -29,6 +31,7 @@ impl MockDataDir {
             temp_dir: std::sync::Arc::new(tempfile::TempDir::new()?),
             config_toml,
             keypair,
+            key_rotation: None,
         })
     }
 
@@ -67,4 +70,8 @@ impl DataDir for MockDataDir {
     fn read_or_create_keypair(&self) -> anyhow::Result<pubky_common::crypto::Keypair> {
         Ok(self.keypair.clone())
     }
+
+    fn read_key_rotation(&self) -> anyhow::Result<Option<KeyRotation>> {
+        Ok(self.key_rotation.clone())
+    }
 }
@@ -5,10 +5,12 @@
 //! merging embedded defaults with user overrides and controls all server behavior
 //! (listen addresses, signup mode, storage backend, rate limits, logging, etc.).
 
+mod admin_role;
 mod config_toml;
 mod data_dir;
 mod domain;
 mod domain_port;
+mod key_rotation;
 #[cfg(any(test, feature = "testing"))]
 mod mock_data_dir;
 mod persistent_data_dir;
@@ -19,12 +21,15 @@ mod signup_mode;
 pub mod storage_config;
 
 mod log_level;
+pub use admin_role::AdminRole;
 pub use config_toml::{
-    AdminToml, ConfigReadError, ConfigToml, DefaultQuotasToml, LoggingToml, MetricsToml,
+    AdminKeyToml, AdminToml, ConfigReadError, ConfigToml, DefaultQuotasToml, DnsToml, EventsToml,
+    LoggingToml, MetricsToml, MirrorToml, RateLimiterBackend,
 };
 pub use data_dir::DataDir;
 pub use domain::Domain;
 pub use domain_port::DomainPort;
+pub use key_rotation::KeyRotation;
 #[cfg(any(test, feature = "testing"))]
 pub use mock_data_dir::MockDataDir;
 pub use persistent_data_dir::PersistentDataDir;
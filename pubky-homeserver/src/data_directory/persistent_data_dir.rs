@@ -1,4 +1,4 @@
-use super::{data_dir::DataDir, ConfigToml};
+use super::{data_dir::DataDir, ConfigToml, KeyRotation};
 
 use std::{
     fs::{copy, create_dir_all},
@@ -63,6 +63,12 @@ impl PersistentDataDir {
         self.expanded_path.join("secret")
     }
 
+    /// Get the path to the file naming the backup of the previous secret,
+    /// written by [`Self::rotate_keypair`].
+    pub fn get_key_rotation_file_path(&self) -> PathBuf {
+        self.expanded_path.join("secret.previous")
+    }
+
     /// Copy a config file into this data directory.
     /// Errors if a `config.toml` already exists at the destination.
     pub fn seed_config(&self, source: &Path) -> anyhow::Result<()> {
@@ -86,6 +92,103 @@ impl PersistentDataDir {
         self.read_or_create_keypair()?;
         Ok(())
     }
+
+    /// Reads the keypair from the secret file. Errors if there is none yet.
+    pub fn read_keypair(&self) -> anyhow::Result<pubky_common::crypto::Keypair> {
+        let secret_file_path = self.get_secret_file_path();
+        if !secret_file_path.exists() {
+            anyhow::bail!("No secret file at {}", secret_file_path.display());
+        }
+        Ok(pubky_common::crypto::Keypair::from_secret_key_file(
+            &secret_file_path,
+        )?)
+    }
+
+    /// Replace the keypair with a new random one.
+    ///
+    /// The old secret file is copied to `secret.<unix-timestamp>.bak` next to it,
+    /// and `secret.previous` records it as the key the homeserver rotated away from.
+    /// Returns the new keypair and the path of the backup.
+    ///
+    /// The new secret replaces the old one last, with a rename, so a crash at any
+    /// step leaves either the old key or the new key with its rotation record.
+    ///
+    /// This changes the homeserver's identity: users' `_pubky` records still point
+    /// at the old public key until they republish them. The homeserver keeps
+    /// answering to the old key for `[pkdns].key_transition_days`.
+    pub fn rotate_keypair(&self) -> anyhow::Result<(pubky_common::crypto::Keypair, PathBuf)> {
+        self.read_keypair()?;
+        let backup_path = self.back_up_secret_file()?;
+
+        let keypair = pubky_common::crypto::Keypair::random();
+        let staged_secret = self.stage_secret_file(&keypair)?;
+        let backup_name = backup_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .expect("backup file names are ASCII");
+        let rotation_file_path = self.get_key_rotation_file_path();
+        let staged_rotation = rotation_file_path.with_extension("previous.tmp");
+        std::fs::write(&staged_rotation, backup_name)?;
+        std::fs::rename(&staged_rotation, rotation_file_path)?;
+        std::fs::rename(staged_secret, self.get_secret_file_path())?;
+        tracing::info!(
+            "Secret file rotated, old secret kept at {}",
+            backup_path.display()
+        );
+        Ok((keypair, backup_path))
+    }
+
+    /// Replace the keypair with `keypair`, e.g. when restoring a backup.
+    ///
+    /// A different old secret is kept like [`Self::rotate_keypair`] does, and its
+    /// path returned. Unlike a rotation, the homeserver does not answer to the old
+    /// key anymore.
+    pub fn replace_keypair(
+        &self,
+        keypair: &pubky_common::crypto::Keypair,
+    ) -> anyhow::Result<Option<PathBuf>> {
+        let backup_path = if self.get_secret_file_path().exists() {
+            if self.read_keypair()?.public_key() == keypair.public_key() {
+                return Ok(None);
+            }
+            let backup_path = self.back_up_secret_file()?;
+            let rotation_file_path = self.get_key_rotation_file_path();
+            if rotation_file_path.exists() {
+                std::fs::remove_file(rotation_file_path)?;
+            }
+            Some(backup_path)
+        } else {
+            create_dir_all(self.path())?;
+            None
+        };
+        let staged_secret = self.stage_secret_file(keypair)?;
+        std::fs::rename(staged_secret, self.get_secret_file_path())?;
+        Ok(backup_path)
+    }
+
+    /// Write the secret of `keypair` to `secret.tmp`, to be renamed over the
+    /// secret file once everything else is in place.
+    fn stage_secret_file(
+        &self,
+        keypair: &pubky_common::crypto::Keypair,
+    ) -> anyhow::Result<PathBuf> {
+        let staged = self.get_secret_file_path().with_extension("tmp");
+        keypair.write_secret_key_file(&staged)?;
+        Ok(staged)
+    }
+
+    /// Copy the secret file to `secret.<unix-timestamp>.bak`.
+    fn back_up_secret_file(&self) -> anyhow::Result<PathBuf> {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let backup_path = self.expanded_path.join(format!("secret.{timestamp}.bak"));
+        if backup_path.exists() {
+            anyhow::bail!("Backup {} already exists", backup_path.display());
+        }
+        std::fs::copy(self.get_secret_file_path(), &backup_path)?;
+        Ok(backup_path)
+    }
 }
 
 impl Default for PersistentDataDir {
@@ -139,6 +242,41 @@ impl DataDir for PersistentDataDir {
         let keypair = pubky_common::crypto::Keypair::from_secret_key_file(&secret_file_path)?;
         Ok(keypair)
     }
+
+    /// Reads the backup named by `secret.previous`. Its timestamp is the rotation time.
+    ///
+    /// A backup of the current key is a rotation interrupted before the new secret
+    /// was installed, and ignored.
+    fn read_key_rotation(&self) -> anyhow::Result<Option<KeyRotation>> {
+        let rotation_file_path = self.get_key_rotation_file_path();
+        if !rotation_file_path.exists() {
+            return Ok(None);
+        }
+        let backup_name = std::fs::read_to_string(&rotation_file_path)?;
+        let backup_name = backup_name.trim();
+        let timestamp: u64 = backup_name
+            .strip_prefix("secret.")
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .and_then(|timestamp| timestamp.parse().ok())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{} does not name a secret backup",
+                    rotation_file_path.display()
+                )
+            })?;
+        let previous_keypair = pubky_common::crypto::Keypair::from_secret_key_file(
+            &self.expanded_path.join(backup_name),
+        )?;
+        if self.get_secret_file_path().exists()
+            && self.read_keypair()?.public_key() == previous_keypair.public_key()
+        {
+            return Ok(None);
+        }
+        Ok(Some(KeyRotation {
+            previous_keypair,
+            rotated_at: std::time::UNIX_EPOCH + std::time::Duration::from_secs(timestamp),
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -247,6 +385,52 @@ mod tests {
         assert_eq!(content, "test");
     }
 
+    #[test]
+    pub fn test_rotate_keypair_keeps_backup() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = PersistentDataDir::new(temp_dir.path().join(".pubky"));
+        data_dir.ensure_data_dir_exists_and_is_writable().unwrap();
+        assert!(data_dir.read_keypair().is_err()); // Never created implicitly
+
+        let old = data_dir.read_or_create_keypair().unwrap();
+        let (new, backup_path) = data_dir.rotate_keypair().unwrap();
+        assert_ne!(new.public_key(), old.public_key());
+        assert_eq!(
+            data_dir.read_keypair().unwrap().public_key(),
+            new.public_key()
+        );
+        let backup = pubky_common::crypto::Keypair::from_secret_key_file(&backup_path).unwrap();
+        assert_eq!(backup.public_key(), old.public_key());
+
+        let rotation = data_dir.read_key_rotation().unwrap().unwrap();
+        assert_eq!(rotation.previous_keypair.public_key(), old.public_key());
+        let age = rotation.rotated_at.elapsed().unwrap_or_default();
+        assert!(age < std::time::Duration::from_secs(60));
+
+        // A crash before the new secret is renamed into place keeps the old key
+        // without a rotation.
+        std::fs::copy(&backup_path, data_dir.get_secret_file_path()).unwrap();
+        assert!(data_dir.read_key_rotation().unwrap().is_none());
+    }
+
+    #[test]
+    pub fn test_replace_keypair() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_dir = PersistentDataDir::new(temp_dir.path().join(".pubky"));
+        let restored = pubky_common::crypto::Keypair::random();
+        assert_eq!(data_dir.replace_keypair(&restored).unwrap(), None);
+        assert_eq!(data_dir.replace_keypair(&restored).unwrap(), None);
+
+        let other = pubky_common::crypto::Keypair::random();
+        let backup_path = data_dir.replace_keypair(&other).unwrap().unwrap();
+        let backup = pubky_common::crypto::Keypair::from_secret_key_file(&backup_path).unwrap();
+        assert_eq!(backup.public_key(), restored.public_key());
+        assert_eq!(
+            data_dir.read_keypair().unwrap().public_key(),
+            other.public_key()
+        );
+    }
+
     #[test]
     pub fn test_trim_secret_file_content() {
         let temp_dir = TempDir::new().unwrap();
@@ -0,0 +1,33 @@
+/// Where the read cache keeps its copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCacheBackend {
+    /// In `data/cache` of the data directory. Emptied on start.
+    FileSystem,
+    /// In memory.
+    InMemory,
+}
+
+/// The `[storage.cache]` TOML section: a local read cache in front of the storage backend.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StorageCacheToml {
+    /// Where the cached files are kept (file_system, in_memory).
+    #[serde(rename = "type")]
+    pub backend: StorageCacheBackend,
+    /// Size of the cache in MB. The least recently read files are evicted beyond it.
+    pub size_mb: u64,
+    /// Files larger than this many MB are never cached.
+    pub max_file_size_mb: u64,
+}
+
+impl StorageCacheToml {
+    /// Size of the cache in bytes.
+    pub fn size_bytes(&self) -> u64 {
+        self.size_mb.saturating_mul(1024 * 1024)
+    }
+
+    /// Size of the largest cached file in bytes.
+    pub fn max_file_size_bytes(&self) -> u64 {
+        self.max_file_size_mb.saturating_mul(1024 * 1024)
+    }
+}
@@ -1,8 +1,14 @@
+mod cache_config;
 #[cfg(feature = "storage-gcs")]
 mod google_bucket_config;
+#[cfg(feature = "storage-s3")]
+mod s3_config;
 mod storage_config_toml;
 
 #[cfg(feature = "storage-gcs")]
 pub use google_bucket_config::{GoogleBucketConfig, GoogleServiceAccountKeyConfig};
+#[cfg(feature = "storage-s3")]
+pub use s3_config::S3Config;
 
+pub use cache_config::{StorageCacheBackend, StorageCacheToml};
 pub use storage_config_toml::{StorageConfigToml, StorageToml};
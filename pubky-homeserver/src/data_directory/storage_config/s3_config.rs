@@ -0,0 +1,117 @@
+use serde_valid::Validate;
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// S3-compatible object storage config (AWS S3, MinIO, Garage, Cloudflare R2, ...).
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Validate)]
+pub struct S3Config {
+    /// The name of the bucket to use. The bucket must exist already.
+    #[validate(min_length = 1)]
+    pub bucket: String,
+    /// The endpoint of the S3 API, e.g. `http://localhost:9000`.
+    /// Omit to use AWS S3 of the region.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// The region of the bucket. Stores without regions usually accept the
+    /// default `us-east-1`; R2 expects `auto`.
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Address the bucket in the path (`endpoint/bucket/key`) instead of the
+    /// host name (`bucket.endpoint/key`). Most self-hosted stores need this.
+    #[serde(default)]
+    pub path_style: bool,
+    /// Directory in the bucket the files are stored in. Defaults to the bucket root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// The access key ID. Set together with `secret_access_key`.
+    /// Omit both to load the credentials from the `AWS_*` environment variables,
+    /// the AWS config files or the instance metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_key_id: Option<String>,
+    /// The secret access key. Set together with `access_key_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_access_key: Option<String>,
+}
+
+impl S3Config {
+    /// Returns the builder.
+    pub fn to_builder(&self) -> Result<opendal::services::S3, std::io::Error> {
+        let mut builder = opendal::services::S3::default()
+            .bucket(&self.bucket)
+            .region(&self.region);
+        if let Some(endpoint) = &self.endpoint {
+            builder = builder.endpoint(endpoint);
+        }
+        if !self.path_style {
+            builder = builder.enable_virtual_host_style();
+        }
+        if let Some(root) = &self.root {
+            builder = builder.root(root);
+        }
+        match (&self.access_key_id, &self.secret_access_key) {
+            (Some(access_key_id), Some(secret_access_key)) => {
+                // Explicit keys must not be overridden by the environment.
+                builder = builder
+                    .access_key_id(access_key_id)
+                    .secret_access_key(secret_access_key)
+                    .disable_config_load()
+                    .disable_ec2_metadata();
+            }
+            (None, None) => {}
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "S3 access_key_id and secret_access_key must be set together",
+                ))
+            }
+        }
+        Ok(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage_config::StorageToml;
+
+    #[test]
+    fn test_parse_s3_config() {
+        let toml = r#"
+            type = "s3"
+            bucket = "pubky"
+            endpoint = "http://localhost:9000"
+            path_style = true
+            access_key_id = "minioadmin"
+            secret_access_key = "minioadmin"
+            scrub_interval = 0
+            scrub_rehash = false
+            scrub_repair = false
+        "#;
+        let storage: StorageToml = toml::from_str(toml).unwrap();
+        let crate::storage_config::StorageConfigToml::S3(config) = storage.backend else {
+            panic!("Expected the S3 backend");
+        };
+        assert_eq!(config.bucket, "pubky");
+        assert_eq!(config.endpoint.as_deref(), Some("http://localhost:9000"));
+        assert_eq!(config.region, "us-east-1");
+        assert!(config.path_style);
+        assert_eq!(config.root, None);
+        assert!(config.to_builder().is_ok());
+    }
+
+    #[test]
+    fn test_s3_credentials_must_be_set_together() {
+        let config = S3Config {
+            bucket: "pubky".to_string(),
+            endpoint: None,
+            region: default_region(),
+            path_style: false,
+            root: None,
+            access_key_id: Some("key".to_string()),
+            secret_access_key: None,
+        };
+        assert!(config.to_builder().is_err());
+    }
+}
@@ -1,7 +1,11 @@
+use super::cache_config::StorageCacheToml;
 #[cfg(feature = "storage-gcs")]
 use super::google_bucket_config::GoogleBucketConfig;
+#[cfg(feature = "storage-s3")]
+use super::s3_config::S3Config;
 
-/// The storage config. Files can be either stored in a file system, in memory, or in a Google bucket
+/// The storage config. Files can be either stored in a file system, in memory, in a Google bucket
+/// or in an S3-compatible bucket
 /// depending on the configuration.
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -9,6 +13,9 @@ pub enum StorageConfigToml {
     /// Files are stored in a Google bucket.
     #[cfg(feature = "storage-gcs")]
     GoogleBucket(GoogleBucketConfig),
+    /// Files are stored in an S3-compatible bucket.
+    #[cfg(feature = "storage-s3")]
+    S3(S3Config),
     /// Files are stored in memory.
     #[cfg(any(feature = "storage-memory", test))]
     InMemory,
@@ -16,13 +23,23 @@ pub enum StorageConfigToml {
     FileSystem,
 }
 
-/// The `[storage]` TOML section: backend selection and storage quota.
+/// The `[storage]` TOML section: backend selection, storage quota, scrubbing and read cache.
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct StorageToml {
-    /// Which backend to use (file_system, google_bucket, in_memory).
+    /// Which backend to use (file_system, google_bucket, s3, in_memory).
     #[serde(flatten)]
     pub backend: StorageConfigToml,
     /// Default per-user storage quota in MB.
     /// Omit for unlimited. `0` means zero storage (not unlimited).
     pub default_quota_mb: Option<u64>,
+    /// Interval in seconds between two storage scrubs. 0 disables the schedule;
+    /// a scrub can still be triggered through the admin API or the CLI.
+    pub scrub_interval: u64,
+    /// Scheduled scrubs read every file and compare its hash, not only its size.
+    pub scrub_rehash: bool,
+    /// Scheduled scrubs fix the database to match the files.
+    pub scrub_repair: bool,
+    /// Local read cache in front of the backend. Omit to read every file from the backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<StorageCacheToml>,
 }
@@ -0,0 +1,10 @@
+//! Optional DNS server.
+//!
+//! Lets devices resolve pubky domains with a plain DNS resolver: names ending
+//! in a public key are answered from its pkarr records, all other names are
+//! forwarded to an upstream ICANN resolver.
+
+mod resolver;
+mod server;
+
+pub use server::{DnsServer, DnsServerBuildError};
@@ -0,0 +1,407 @@
+//! Answers a single DNS query, from pkarr or the upstream resolver.
+
+use std::{io, net::SocketAddr, time::Duration};
+
+use pkarr::{
+    dns::{Packet, PacketFlag, ResourceRecord, RCODE},
+    errors::ResolveError,
+    ResolvePolicy, SignedPacket,
+};
+use pubky_common::crypto::PublicKey;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, UdpSocket},
+};
+
+/// How long to wait for the upstream resolver.
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest UDP response the upstream resolver may send.
+const MAX_UPSTREAM_RESPONSE: usize = 4096;
+
+/// The transport a query arrived over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transport {
+    Udp,
+    Tcp,
+}
+
+/// Resolves `*.<z32>` names through pkarr and forwards every other name upstream.
+#[derive(Clone)]
+pub(crate) struct DnsResolver {
+    /// Caches resolved packets, so repeated queries stay off the DHT.
+    pkarr_client: pkarr::Client,
+    upstream: Option<SocketAddr>,
+}
+
+impl DnsResolver {
+    pub fn new(pkarr_client: pkarr::Client, upstream: Option<SocketAddr>) -> Self {
+        Self {
+            pkarr_client,
+            upstream,
+        }
+    }
+
+    /// Answer a raw DNS query. `None` when the query cannot be parsed at all.
+    pub async fn answer(&self, query_bytes: &[u8], transport: Transport) -> Option<Vec<u8>> {
+        let query = Packet::parse(query_bytes).ok()?;
+        let Some(public_key) = query.questions.first().and_then(|q| pkarr_key_of(&q.qname)) else {
+            return Some(self.forward(&query, query_bytes, transport).await);
+        };
+
+        let resolved = match self
+            .pkarr_client
+            .resolve(&public_key, ResolvePolicy::CacheFirst)
+            .await
+        {
+            Ok(packet) => Some(packet),
+            Err(ResolveError::NotFound) => None,
+            Err(e) => {
+                tracing::debug!("DNS: failed to resolve {}: {}", public_key, e);
+                return error_reply(&query, RCODE::ServerFailure);
+            }
+        };
+        answer_from_packet(&query, resolved.as_ref())
+    }
+
+    /// Forward a query for an ICANN name to the upstream resolver.
+    ///
+    /// Queries that came in over TCP go upstream over TCP. UDP queries go upstream
+    /// over UDP and are retried over TCP when the upstream reply is truncated.
+    async fn forward(
+        &self,
+        query: &Packet<'_>,
+        query_bytes: &[u8],
+        transport: Transport,
+    ) -> Vec<u8> {
+        let Some(upstream) = self.upstream else {
+            return error_reply(query, RCODE::Refused).unwrap_or_default();
+        };
+        let response = match transport {
+            Transport::Tcp => forward_to_tcp(upstream, query_bytes).await,
+            Transport::Udp => match forward_to(upstream, query_bytes).await {
+                Ok(response) if is_truncated(&response) => {
+                    forward_to_tcp(upstream, query_bytes).await
+                }
+                result => result,
+            },
+        };
+        match response {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::debug!("DNS: upstream {} failed: {}", upstream, e);
+                error_reply(query, RCODE::ServerFailure).unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// The public key a name belongs to, if its last label is one.
+fn pkarr_key_of(name: &pkarr::dns::Name<'_>) -> Option<PublicKey> {
+    let name = name.to_string().to_lowercase();
+    let tld = name.trim_end_matches('.').rsplit('.').next()?;
+    PublicKey::try_from_z32(tld).ok()
+}
+
+/// Answer `query` from the signed packet of the queried key.
+///
+/// A name without any record is answered `NXDOMAIN`. CNAME records are returned for
+/// every query type, so clients can follow them.
+fn answer_from_packet(query: &Packet<'_>, packet: Option<&SignedPacket>) -> Option<Vec<u8>> {
+    let question = query.questions.first()?;
+    let name = question.qname.to_string().to_lowercase();
+    let records: Vec<&ResourceRecord<'_>> = packet
+        .map(|packet| packet.resource_records(&name).collect())
+        .unwrap_or_default();
+    if records.is_empty() {
+        return error_reply(query, RCODE::NameError);
+    }
+
+    let mut reply = Packet::new_reply(query.id());
+    reply.set_flags(PacketFlag::AUTHORITATIVE_ANSWER);
+    reply.questions.push(question.clone());
+    for record in records {
+        let matches = record.match_qtype(question.qtype)
+            || matches!(record.rdata, pkarr::dns::rdata::RData::CNAME(_));
+        if matches {
+            reply.answers.push(ResourceRecord::new(
+                question.qname.clone(),
+                record.class,
+                record.ttl,
+                record.rdata.clone(),
+            ));
+        }
+    }
+    reply.build_bytes_vec_compressed().ok()
+}
+
+/// Reply to `query` with `rcode` and no answers.
+fn error_reply(query: &Packet<'_>, rcode: RCODE) -> Option<Vec<u8>> {
+    let mut reply = Packet::new_reply(query.id());
+    reply.questions = query.questions.clone();
+    *reply.rcode_mut() = rcode;
+    reply.build_bytes_vec().ok()
+}
+
+/// Send `query_bytes` to `upstream` over UDP and wait for the matching response.
+async fn forward_to(upstream: SocketAddr, query_bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let bind: SocketAddr = if upstream.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(upstream).await?;
+    socket.send(query_bytes).await?;
+
+    let mut buf = vec![0; MAX_UPSTREAM_RESPONSE];
+    tokio::time::timeout(UPSTREAM_TIMEOUT, async {
+        loop {
+            let len = socket.recv(&mut buf).await?;
+            // Skip stray datagrams that do not answer this query.
+            if len >= 2 && buf[..2] == query_bytes[..2] {
+                return Ok(buf[..len].to_vec());
+            }
+        }
+    })
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "upstream resolver timed out"))?
+}
+
+/// Send `query_bytes` to `upstream` over TCP and read the response.
+async fn forward_to_tcp(upstream: SocketAddr, query_bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let query_len = u16::try_from(query_bytes.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "DNS query too large"))?;
+    tokio::time::timeout(UPSTREAM_TIMEOUT, async {
+        let mut stream = TcpStream::connect(upstream).await?;
+        stream.write_u16(query_len).await?;
+        stream.write_all(query_bytes).await?;
+        let len = stream.read_u16().await?;
+        let mut response = vec![0; len as usize];
+        stream.read_exact(&mut response).await?;
+        Ok(response)
+    })
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "upstream resolver timed out"))?
+}
+
+/// Whether `response` has the TC flag set.
+fn is_truncated(response: &[u8]) -> bool {
+    Packet::parse(response).is_ok_and(|packet| packet.has_flags(PacketFlag::TRUNCATION))
+}
+
+/// Shrink a response that does not fit in `max_len` bytes to a truncated reply,
+/// so the client retries over TCP.
+pub(crate) fn truncate_for_udp(response: Vec<u8>, max_len: usize) -> Vec<u8> {
+    if response.len() <= max_len {
+        return response;
+    }
+    let Ok(packet) = Packet::parse(&response) else {
+        return response;
+    };
+    let mut reply = Packet::new_reply(packet.id());
+    reply.questions = packet.questions.clone();
+    *reply.rcode_mut() = packet.rcode();
+    reply.set_flags(PacketFlag::TRUNCATION);
+    reply.build_bytes_vec().unwrap_or(response)
+}
+
+/// Largest UDP response the client accepts: its EDNS buffer size, or 512 bytes.
+pub(crate) fn max_udp_response(query_bytes: &[u8]) -> usize {
+    Packet::parse(query_bytes)
+        .ok()
+        .and_then(|query| query.opt().map(|opt| opt.udp_packet_size as usize))
+        .unwrap_or(512)
+        .max(512)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pkarr::dns::{rdata::RData, Name, Question, CLASS, QCLASS, QTYPE, TYPE};
+    use pubky_common::crypto::Keypair;
+    use std::net::Ipv4Addr;
+
+    fn query(name: &str, qtype: TYPE) -> Vec<u8> {
+        let mut query = Packet::new_query(7);
+        query.questions.push(Question::new(
+            Name::new_unchecked(name),
+            QTYPE::TYPE(qtype),
+            QCLASS::CLASS(CLASS::IN),
+            false,
+        ));
+        query.build_bytes_vec().unwrap()
+    }
+
+    fn signed_packet(keypair: &Keypair) -> SignedPacket {
+        SignedPacket::builder()
+            .a("www".try_into().unwrap(), Ipv4Addr::new(192, 0, 2, 1), 300)
+            .txt("www".try_into().unwrap(), "hello".try_into().unwrap(), 300)
+            .sign(keypair)
+            .unwrap()
+    }
+
+    #[test]
+    fn answers_matching_records_with_the_query_name() {
+        let keypair = Keypair::random();
+        let packet = signed_packet(&keypair);
+        let name = format!("WWW.{}", keypair.public_key().z32());
+        let bytes = query(&name, TYPE::A);
+        let query = Packet::parse(&bytes).unwrap();
+
+        let reply = answer_from_packet(&query, Some(&packet)).unwrap();
+        let reply = Packet::parse(&reply).unwrap();
+
+        assert_eq!(reply.id(), 7);
+        assert_eq!(reply.rcode(), RCODE::NoError);
+        assert_eq!(reply.answers.len(), 1);
+        assert_eq!(reply.answers[0].name.to_string(), name);
+        assert!(matches!(
+            &reply.answers[0].rdata,
+            RData::A(a) if Ipv4Addr::from(a.address) == Ipv4Addr::new(192, 0, 2, 1)
+        ));
+    }
+
+    #[test]
+    fn unknown_name_is_nxdomain() {
+        let keypair = Keypair::random();
+        let packet = signed_packet(&keypair);
+        let bytes = query(&format!("missing.{}", keypair.public_key().z32()), TYPE::A);
+        let query = Packet::parse(&bytes).unwrap();
+
+        let reply = answer_from_packet(&query, Some(&packet)).unwrap();
+        assert_eq!(Packet::parse(&reply).unwrap().rcode(), RCODE::NameError);
+        let reply = answer_from_packet(&query, None).unwrap();
+        assert_eq!(Packet::parse(&reply).unwrap().rcode(), RCODE::NameError);
+    }
+
+    #[test]
+    fn only_names_ending_in_a_public_key_go_to_pkarr() {
+        let keypair = Keypair::random();
+        let z32 = keypair.public_key().z32();
+        let key_of = |name: &str| pkarr_key_of(&Name::new_unchecked(name));
+
+        assert_eq!(key_of(&format!("_pubky.{z32}")), Some(keypair.public_key()));
+        assert_eq!(key_of(&z32), Some(keypair.public_key()));
+        assert_eq!(key_of("example.com"), None);
+        assert_eq!(key_of(&format!("{z32}.example.com")), None);
+    }
+
+    #[tokio::test]
+    async fn forwards_icann_names_upstream() {
+        let upstream = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            let (len, from) = upstream.recv_from(&mut buf).await.unwrap();
+            let query = Packet::parse(&buf[..len]).unwrap();
+            let mut reply = Packet::new_reply(query.id());
+            reply.questions = query.questions.clone();
+            reply.answers.push(ResourceRecord::new(
+                query.questions[0].qname.clone(),
+                CLASS::IN,
+                60,
+                RData::A(Ipv4Addr::new(198, 51, 100, 7).into()),
+            ));
+            upstream
+                .send_to(&reply.build_bytes_vec().unwrap(), from)
+                .await
+                .unwrap();
+        });
+
+        let dht = mainline::Testnet::builder(1).build().unwrap();
+        let client = pkarr::Client::builder()
+            .no_default_network()
+            .bootstrap(&dht.bootstrap)
+            .build()
+            .unwrap();
+        let resolver = DnsResolver::new(client.clone(), Some(upstream_addr));
+        let reply = resolver
+            .answer(&query("example.com", TYPE::A), Transport::Udp)
+            .await
+            .unwrap();
+        let reply = Packet::parse(&reply).unwrap();
+        assert_eq!(reply.answers.len(), 1);
+
+        let refusing = DnsResolver::new(client, None);
+        let reply = refusing
+            .answer(&query("example.com", TYPE::A), Transport::Udp)
+            .await
+            .unwrap();
+        assert_eq!(Packet::parse(&reply).unwrap().rcode(), RCODE::Refused);
+    }
+
+    #[tokio::test]
+    async fn retries_truncated_upstream_replies_over_tcp() {
+        let upstream = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let upstream_addr = upstream.local_addr().unwrap();
+        let tcp = tokio::net::TcpListener::bind(upstream_addr).await.unwrap();
+        // UDP only ever answers with a truncated reply.
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            loop {
+                let (len, from) = upstream.recv_from(&mut buf).await.unwrap();
+                let query = Packet::parse(&buf[..len]).unwrap();
+                let mut reply = Packet::new_reply(query.id());
+                reply.questions = query.questions.clone();
+                reply.set_flags(PacketFlag::TRUNCATION);
+                upstream
+                    .send_to(&reply.build_bytes_vec().unwrap(), from)
+                    .await
+                    .unwrap();
+            }
+        });
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = tcp.accept().await.unwrap();
+                let len = stream.read_u16().await.unwrap();
+                let mut buf = vec![0; len as usize];
+                stream.read_exact(&mut buf).await.unwrap();
+                let query = Packet::parse(&buf).unwrap();
+                let mut reply = Packet::new_reply(query.id());
+                reply.questions = query.questions.clone();
+                reply.answers.push(ResourceRecord::new(
+                    query.questions[0].qname.clone(),
+                    CLASS::IN,
+                    60,
+                    RData::A(Ipv4Addr::new(198, 51, 100, 7).into()),
+                ));
+                let reply = reply.build_bytes_vec().unwrap();
+                stream.write_u16(reply.len() as u16).await.unwrap();
+                stream.write_all(&reply).await.unwrap();
+            }
+        });
+
+        let dht = mainline::Testnet::builder(1).build().unwrap();
+        let client = pkarr::Client::builder()
+            .no_default_network()
+            .bootstrap(&dht.bootstrap)
+            .build()
+            .unwrap();
+        let resolver = DnsResolver::new(client, Some(upstream_addr));
+        for transport in [Transport::Udp, Transport::Tcp] {
+            let reply = resolver
+                .answer(&query("example.com", TYPE::A), transport)
+                .await
+                .unwrap();
+            let reply = Packet::parse(&reply).unwrap();
+            assert!(!reply.has_flags(PacketFlag::TRUNCATION));
+            assert_eq!(reply.answers.len(), 1);
+        }
+    }
+
+    #[test]
+    fn oversized_udp_response_is_truncated() {
+        let keypair = Keypair::random();
+        let packet = signed_packet(&keypair);
+        let bytes = query(&format!("www.{}", keypair.public_key().z32()), TYPE::TXT);
+        let query = Packet::parse(&bytes).unwrap();
+        let reply = answer_from_packet(&query, Some(&packet)).unwrap();
+
+        let truncated = truncate_for_udp(reply.clone(), 20);
+        let truncated = Packet::parse(&truncated).unwrap();
+        assert!(truncated.has_flags(PacketFlag::TRUNCATION));
+        assert!(truncated.answers.is_empty());
+        assert_eq!(truncate_for_udp(reply.clone(), 512), reply);
+    }
+}
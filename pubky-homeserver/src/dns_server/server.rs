@@ -0,0 +1,196 @@
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    num::NonZeroU32,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UdpSocket},
+    sync::Semaphore,
+    task::JoinHandle,
+};
+
+use super::resolver::{max_udp_response, truncate_for_udp, DnsResolver, Transport};
+use crate::AppContext;
+
+/// Largest DNS message accepted over UDP.
+const MAX_UDP_QUERY: usize = 4096;
+
+/// Most UDP queries answered at once. Datagrams beyond this are dropped.
+const MAX_IN_FLIGHT_UDP: usize = 1024;
+
+/// UDP queries accepted per second from one source IP. Others are dropped.
+const UDP_QUERIES_PER_IP: NonZeroU32 = NonZeroU32::new(50).expect("50 is non zero");
+
+/// How often idle source IPs are forgotten by the UDP rate limiter.
+const RATE_LIMIT_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time a TCP connection gets to send a complete query before it is closed.
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most TCP connections served at once. Connections beyond this are closed.
+const MAX_TCP_CONNECTIONS: usize = 256;
+
+/// Errors that can occur when building a `DnsServer`.
+#[derive(thiserror::Error, Debug)]
+pub enum DnsServerBuildError {
+    /// Failed to bind the listen socket.
+    #[error("Failed to bind DNS socket: {0}")]
+    Bind(io::Error),
+}
+
+/// DNS server
+///
+/// Answers queries for `*.<public key>` names from the pkarr records of the key,
+/// and forwards every other name to the configured upstream resolver.
+/// Listens on UDP and TCP on the same port.
+///
+/// When dropped, the server will stop.
+pub struct DnsServer {
+    udp_handle: JoinHandle<()>,
+    tcp_handle: JoinHandle<()>,
+    socket: SocketAddr,
+}
+
+impl DnsServer {
+    /// Run the DNS server.
+    pub async fn start(context: &AppContext) -> Result<Self, DnsServerBuildError> {
+        let config = &context.config_toml.dns;
+        let resolver = DnsResolver::new(context.pkarr_client.clone(), config.upstream);
+
+        let udp = UdpSocket::bind(config.listen_socket)
+            .await
+            .map_err(DnsServerBuildError::Bind)?;
+        // Bind TCP to the port UDP got, in case the configured port is 0.
+        let socket = udp.local_addr().map_err(DnsServerBuildError::Bind)?;
+        let tcp = TcpListener::bind(socket)
+            .await
+            .map_err(DnsServerBuildError::Bind)?;
+
+        let udp_handle = tokio::spawn(serve_udp(Arc::new(udp), resolver.clone()));
+        let tcp_handle = tokio::spawn(serve_tcp(tcp, resolver));
+        tracing::info!("DNS server listening on {socket} (UDP and TCP)");
+
+        Ok(Self {
+            udp_handle,
+            tcp_handle,
+            socket,
+        })
+    }
+
+    /// Get the socket address the DNS server is listening on, for both UDP and TCP.
+    pub fn listen_socket(&self) -> SocketAddr {
+        self.socket
+    }
+}
+
+impl Drop for DnsServer {
+    fn drop(&mut self) {
+        self.udp_handle.abort();
+        self.tcp_handle.abort();
+    }
+}
+
+/// Answer UDP queries, each in its own task.
+///
+/// Source addresses are trivially spoofed over UDP, so queries are dropped without
+/// a reply once a source IP exceeds its rate or too many queries are in flight.
+async fn serve_udp(socket: Arc<UdpSocket>, resolver: DnsResolver) {
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT_UDP));
+    let limiter: DefaultKeyedRateLimiter<IpAddr> =
+        RateLimiter::keyed(Quota::per_second(UDP_QUERIES_PER_IP));
+    let mut last_cleanup = Instant::now();
+    let mut buf = vec![0; MAX_UDP_QUERY];
+    loop {
+        let (len, from) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                tracing::debug!("DNS: UDP receive failed: {}", e);
+                continue;
+            }
+        };
+        if last_cleanup.elapsed() >= RATE_LIMIT_CLEANUP_INTERVAL {
+            limiter.retain_recent();
+            limiter.shrink_to_fit();
+            last_cleanup = Instant::now();
+        }
+        if limiter.check_key(&from.ip()).is_err() {
+            tracing::trace!("DNS: dropped UDP query from rate limited {}", from);
+            continue;
+        }
+        let Ok(permit) = in_flight.clone().try_acquire_owned() else {
+            tracing::debug!("DNS: dropped UDP query from {}, too many in flight", from);
+            continue;
+        };
+        let query = buf[..len].to_vec();
+        let socket = socket.clone();
+        let resolver = resolver.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let Some(response) = resolver.answer(&query, Transport::Udp).await else {
+                return;
+            };
+            let response = truncate_for_udp(response, max_udp_response(&query));
+            if let Err(e) = socket.send_to(&response, from).await {
+                tracing::debug!("DNS: UDP reply to {} failed: {}", from, e);
+            }
+        });
+    }
+}
+
+async fn serve_tcp(listener: TcpListener, resolver: DnsResolver) {
+    let connections = Arc::new(Semaphore::new(MAX_TCP_CONNECTIONS));
+    loop {
+        let (stream, from) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                tracing::debug!("DNS: TCP accept failed: {}", e);
+                continue;
+            }
+        };
+        let Ok(permit) = connections.clone().try_acquire_owned() else {
+            tracing::debug!("DNS: closed TCP connection from {}, too many open", from);
+            continue;
+        };
+        let resolver = resolver.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Err(e) = serve_tcp_connection(stream, &resolver).await {
+                tracing::debug!("DNS: TCP connection from {} closed: {}", from, e);
+            }
+        });
+    }
+}
+
+/// Answer the length-prefixed queries of one TCP connection until it closes or idles.
+///
+/// Each query, length prefix included, has to arrive within [`TCP_IDLE_TIMEOUT`],
+/// so a client sending a partial query can't hold the connection open.
+async fn serve_tcp_connection(mut stream: TcpStream, resolver: &DnsResolver) -> io::Result<()> {
+    loop {
+        let read_query = async {
+            let len = stream.read_u16().await?;
+            let mut query = vec![0; len as usize];
+            stream.read_exact(&mut query).await?;
+            Ok::<_, io::Error>(query)
+        };
+        let query = match tokio::time::timeout(TCP_IDLE_TIMEOUT, read_query).await {
+            Ok(Ok(query)) => query,
+            Ok(Err(e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Ok(()),
+        };
+
+        let Some(response) = resolver.answer(&query, Transport::Tcp).await else {
+            return Ok(());
+        };
+        let response_len = u16::try_from(response.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "DNS response too large"))?;
+        stream.write_u16(response_len).await?;
+        stream.write_all(&response).await?;
+    }
+}
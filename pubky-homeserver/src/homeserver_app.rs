@@ -1,25 +1,26 @@
 //! Top-level application orchestrator.
 //!
 //! [`HomeserverApp`] owns and coordinates the three HTTP servers
-//! ([`ClientServer`], [`AdminServer`], [`MetricsServer`]) and the background
-//! DHT republishers. It handles startup (config loading, database connection,
-//! migration) and graceful shutdown.
+//! ([`ClientServer`], [`AdminServer`], [`MetricsServer`]), the optional
+//! [`DnsServer`] and the background DHT republishers. It handles startup
+//! (config loading, database connection, migration) and graceful shutdown.
 
 use crate::admin_server::{AdminServer, AdminServerBuildError};
 use crate::client_server::{ClientServer, ClientServerBuildError};
+use crate::dns_server::{DnsServer, DnsServerBuildError};
 use crate::metrics_server::{MetricsServer, MetricsServerBuildError};
 use crate::republishers::{
-    HomeserverKeyRepublisher, KeyRepublisherBuildError, UserKeysRepublisherJob,
+    HomeserverKeyRepublisher, KeyRepublisherBuildError, UserKeysRepublisher, UserKeysRepublisherJob,
 };
 use crate::tracing::init_tracing_logs_with_config_if_set;
 #[cfg(any(test, feature = "testing"))]
 use crate::MockDataDir;
 use crate::{app_context::AppContext, data_directory::PersistentDataDir};
+use crate::{ConfigReloadError, ConfigReloadReport};
 use anyhow::Result;
 use pubky_common::crypto::PublicKey;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
 
 /// Errors that can occur when building a `HomeserverApp`.
 #[derive(thiserror::Error, Debug)]
@@ -33,6 +34,9 @@ pub enum HomeserverAppBuildError {
     /// Failed to build the metrics server.
     #[error("Failed to build metrics server: {0}")]
     Metrics(MetricsServerBuildError),
+    /// Failed to build the DNS server.
+    #[error("Failed to build DNS server: {0}")]
+    Dns(DnsServerBuildError),
 }
 
 /// Homeserver with all bells and whistles.
@@ -56,6 +60,9 @@ pub struct HomeserverApp {
 
     #[allow(dead_code)] // Keep this alive. When dropped, the metrics server will stop.
     metrics_server: Option<MetricsServer>,
+
+    #[allow(dead_code)] // Keep this alive. When dropped, the DNS server will stop.
+    dns_server: Option<DnsServer>,
 }
 
 impl HomeserverApp {
@@ -87,16 +94,8 @@ impl HomeserverApp {
 
         tracing::debug!("Homeserver data dir: {}", context.data_dir.path().display());
 
-        let mut pkarr_builder = context.pkarr_builder.clone();
-        pkarr_builder.no_relays(); // Disable relays to avoid their rate limiting.
-        let republish_interval =
-            Duration::from_secs(context.config_toml.pkdns.user_keys_republisher_interval);
-        let user_keys_republisher_job = UserKeysRepublisherJob::start(
-            context.user_service.clone(),
-            pkarr_builder,
-            context.keypair.public_key(),
-            republish_interval,
-        );
+        let user_keys_republisher_job =
+            UserKeysRepublisherJob::start(UserKeysRepublisher::from_context(&context));
 
         let admin_server = if context.config_toml.admin.enabled {
             Some(AdminServer::start(Arc::clone(&context)).await?)
@@ -108,6 +107,11 @@ impl HomeserverApp {
         } else {
             None
         };
+        let dns_server = if context.config_toml.dns.enabled {
+            Some(DnsServer::start(&context).await?)
+        } else {
+            None
+        };
         let client_server = ClientServer::start(Arc::clone(&context)).await?;
 
         let key_republisher = HomeserverKeyRepublisher::start(
@@ -123,11 +127,20 @@ impl HomeserverApp {
             client_server,
             admin_server,
             metrics_server,
+            dns_server,
             _user_keys_republisher_job: user_keys_republisher_job,
             _key_republisher: key_republisher,
         })
     }
 
+    /// Re-read the config file and apply the settings that can change without a restart:
+    /// `[general] signup_mode`, `[drive] rate_limits`, `[default_quotas]` and `[logging]`.
+    ///
+    /// The binary calls this on `SIGHUP`.
+    pub fn reload_config(&self) -> Result<ConfigReloadReport, ConfigReloadError> {
+        self.context.config_service.reload()
+    }
+
     /// Get the core of the homeserver app.
     pub fn client_server(&self) -> &ClientServer {
         &self.client_server
@@ -143,6 +156,11 @@ impl HomeserverApp {
         self.metrics_server.as_ref()
     }
 
+    /// Get the DNS server of the homeserver app.
+    pub fn dns_server(&self) -> Option<&DnsServer> {
+        self.dns_server.as_ref()
+    }
+
     /// Returns the public_key of this server.
     pub fn public_key(&self) -> PublicKey {
         self.context.keypair.public_key()
@@ -16,9 +16,11 @@ mod app_context;
 mod client_server;
 mod constants;
 mod data_directory;
+mod dns_server;
 mod homeserver_app;
 mod metrics_server;
 mod observability;
+mod ops;
 mod persistence;
 mod republishers;
 mod services;
@@ -29,6 +31,13 @@ pub use admin_server::{AdminServer, AdminServerBuildError};
 pub use app_context::{AppContext, AppContextConversionError};
 pub use client_server::{ClientServer, ClientServerBuildError};
 pub use data_directory::*;
+pub use dns_server::{DnsServer, DnsServerBuildError};
 pub use homeserver_app::{HomeserverApp, HomeserverAppBuildError};
 pub use metrics_server::{MetricsServer, MetricsServerBuildError};
+pub use ops::HomeserverOps;
 pub use persistence::sql::ConnectionString;
+pub use services::backup::{BackupError, BackupManifest, BackupTable};
+pub use services::config_service::{ConfigReloadError, ConfigReloadReport};
+pub use services::storage_integrity::{
+    HashMismatch, ScrubOptions, SizeMismatch, StorageScrubReport, UsedBytesMismatch,
+};
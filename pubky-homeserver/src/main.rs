@@ -2,8 +2,10 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use pubky_common::crypto::PublicKey;
 use pubky_homeserver::{
-    tracing::init_tracing_logs_if_set, DataDir, HomeserverApp, PersistentDataDir,
+    tracing::init_tracing_logs_if_set, DataDir, HomeserverApp, HomeserverOps, PersistentDataDir,
+    ScrubOptions,
 };
 
 fn default_config_dir_path() -> PathBuf {
@@ -35,6 +37,273 @@ struct Cli {
 enum Command {
     /// Initialize the data directory (config and keypair) without starting the server.
     Init,
+    /// Run pending database migrations without starting the server.
+    Migrate,
+    /// Check the config file.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Manage users.
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Manage signup tokens.
+    #[command(subcommand)]
+    SignupToken(SignupTokenCommand),
+    /// Manage the homeserver keypair.
+    #[command(subcommand)]
+    Keypair(KeypairCommand),
+    /// Check the file storage.
+    #[command(subcommand)]
+    Storage(StorageCommand),
+    /// Back up or restore the database, files and keypair.
+    #[command(subcommand)]
+    Backup(BackupCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Check that config.toml loads and its settings are valid.
+    Validate,
+    /// Print the config with all defaults applied.
+    PrintEffective,
+}
+
+#[derive(Subcommand, Debug)]
+enum UserCommand {
+    /// List users as JSON.
+    List {
+        /// Only users whose public key starts with this z-base-32 prefix.
+        #[arg(long)]
+        search: Option<String>,
+        /// Only disabled (`true`) or enabled (`false`) users.
+        #[arg(long)]
+        disabled: Option<bool>,
+        /// Maximum number of users to list.
+        #[arg(long)]
+        limit: Option<u16>,
+        /// `next_cursor` of the previous page.
+        #[arg(long)]
+        cursor: Option<i32>,
+    },
+    /// Disable a user.
+    Disable { pubkey: PublicKey },
+    /// Re-enable a disabled user.
+    Enable { pubkey: PublicKey },
+    /// Show the quota of a user as JSON, or update it with `--set`.
+    Quota {
+        pubkey: PublicKey,
+        /// Overrides to apply, as the JSON body of the admin `PATCH /users/{pubkey}/quota`.
+        /// e.g. `{"storage_quota_mb": 500, "rate_read": "unlimited"}`
+        #[arg(long)]
+        set: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SignupTokenCommand {
+    /// Create a signup token and print it.
+    Create {
+        /// Quota of the user signing up with the token, as the JSON body of the
+        /// admin `POST /generate_signup_token`. Defaults to the system defaults.
+        #[arg(long)]
+        limits: Option<String>,
+    },
+    /// List signup tokens as JSON.
+    List {
+        /// Only used (`true`) or unused (`false`) tokens.
+        #[arg(long)]
+        used: Option<bool>,
+        /// Maximum number of tokens to list.
+        #[arg(long)]
+        limit: Option<u16>,
+        /// `next_cursor` of the previous page.
+        #[arg(long)]
+        cursor: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeypairCommand {
+    /// Print the homeserver public key.
+    Show,
+    /// Replace the keypair with a new one. The old secret is kept as a backup.
+    ///
+    /// This changes the homeserver's identity: users' `_pubky` records keep
+    /// pointing at the old key until they republish them. For
+    /// `[pkdns].key_transition_days`, the homeserver still answers to the old
+    /// key and publishes a `_successor` record with it.
+    Rotate {
+        /// Confirm the rotation.
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum StorageCommand {
+    /// Check that every entry has a file of the recorded size in the storage backend,
+    /// find files without an entry and check each user's used bytes.
+    Verify {
+        /// Read every file and compare its hash too.
+        #[arg(long)]
+        rehash: bool,
+        /// Fix the database to match the files.
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Copy every file to another storage backend, checking their hashes.
+    ///
+    /// Homeservers can keep running: writes made during the copy are replayed
+    /// from the events. An interrupted migration resumes where it stopped.
+    Migrate {
+        /// TOML file with the `[storage]` backend settings to migrate to,
+        /// e.g. `type = "google_bucket"` and `bucket_name = "my_bucket"`.
+        #[arg(long, required_unless_present = "finish")]
+        target: Option<PathBuf>,
+        /// Point config.toml at the target once every file is copied.
+        #[arg(long, conflicts_with = "finish")]
+        switch: bool,
+        /// After switching and restarting the homeservers, copy the writes they
+        /// made to the old backend until then and end the migration.
+        #[arg(long, conflicts_with = "target")]
+        finish: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupCommand {
+    /// Write a consistent snapshot of the homeserver into a new directory.
+    /// The homeserver can keep running.
+    Create { dir: PathBuf },
+    /// Restore a backup into this homeserver, whose database must be empty.
+    ///
+    /// Runs the pending migrations first, and replaces the keypair with the
+    /// backup's. Stop the homeserver before restoring.
+    Restore { dir: PathBuf },
+}
+
+/// Run a subcommand that needs the database.
+async fn run_ops_command(ops: &HomeserverOps, command: Command) -> Result<()> {
+    match command {
+        Command::Migrate => {
+            ops.migrate().await?;
+            println!("Migrations are up to date.");
+        }
+        Command::User(UserCommand::List {
+            search,
+            disabled,
+            limit,
+            cursor,
+        }) => print_json(&ops.list_users(search, disabled, limit, cursor).await?)?,
+        Command::User(UserCommand::Disable { pubkey }) => {
+            ops.set_user_disabled(&pubkey, true).await?;
+            println!("User {} disabled.", pubkey.z32());
+        }
+        Command::User(UserCommand::Enable { pubkey }) => {
+            ops.set_user_disabled(&pubkey, false).await?;
+            println!("User {} enabled.", pubkey.z32());
+        }
+        Command::User(UserCommand::Quota { pubkey, set }) => {
+            if let Some(patch) = set {
+                ops.patch_user_quota(&pubkey, &patch).await?;
+            }
+            print_json(&ops.user_quota(&pubkey).await?)?;
+        }
+        Command::SignupToken(SignupTokenCommand::Create { limits }) => {
+            println!("{}", ops.create_signup_token(limits.as_deref()).await?);
+        }
+        Command::SignupToken(SignupTokenCommand::List {
+            used,
+            limit,
+            cursor,
+        }) => print_json(&ops.list_signup_tokens(used, limit, cursor).await?)?,
+        Command::Storage(StorageCommand::Verify { rehash, repair }) => {
+            let report = ops.scrub_storage(ScrubOptions { rehash, repair }).await?;
+            print_json(&report)?;
+            if !report.is_ok() && !repair {
+                anyhow::bail!(
+                    "Found {} problems. Run with --repair to fix the database.",
+                    report.problem_count()
+                );
+            }
+        }
+        Command::Storage(StorageCommand::Migrate {
+            target: Some(target),
+            switch,
+            ..
+        }) => {
+            let target = std::fs::read_to_string(&target)?;
+            let target = toml::from_str(&target)
+                .map_err(|e| anyhow::anyhow!("Invalid storage backend settings: {e}"))?;
+            let state = ops.migrate_storage(target, switch).await?;
+            print_json(&state)?;
+            if state.switched {
+                println!(
+                    "config.toml now uses the new storage backend. Restart the homeservers, \
+                     then run `storage migrate --finish`."
+                );
+            }
+        }
+        Command::Storage(StorageCommand::Migrate { .. }) => {
+            print_json(&ops.finish_storage_migration().await?)?;
+            println!("Storage migration finished.");
+        }
+        Command::Backup(BackupCommand::Create { dir }) => {
+            let manifest = ops.create_backup(&dir).await?;
+            println!(
+                "Backed up {} files ({} bytes) up to event cursor {} into {}.",
+                manifest.files,
+                manifest.file_bytes,
+                manifest.event_cursor,
+                dir.display()
+            );
+        }
+        Command::Backup(BackupCommand::Restore { dir }) => {
+            ops.migrate().await?;
+            let (manifest, old_secret) = ops.restore_backup(&dir).await?;
+            println!(
+                "Restored {} files up to event cursor {} from {}.",
+                manifest.files,
+                manifest.event_cursor,
+                dir.display()
+            );
+            if let Some(old_secret) = old_secret {
+                println!("Previous secret kept at {}.", old_secret.display());
+            }
+        }
+        Command::Init | Command::Config(_) | Command::Keypair(_) => {
+            unreachable!("handled without the database")
+        }
+    }
+    Ok(())
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Wait for Ctrl+C. On unix, reload the config on every `SIGHUP` in the meantime.
+async fn wait_for_shutdown(server: &HomeserverApp) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+        loop {
+            tokio::select! {
+                result = tokio::signal::ctrl_c() => return Ok(result?),
+                _ = hangup.recv() => {
+                    tracing::info!("Received SIGHUP, reloading config");
+                    if let Err(e) = server.reload_config() {
+                        tracing::error!("Config reload failed, keeping the running config: {e}");
+                    }
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = server;
+        Ok(tokio::signal::ctrl_c().await?)
+    }
 }
 
 #[tokio::main]
@@ -50,6 +319,48 @@ async fn main() -> Result<()> {
                 data_dir.path().display()
             );
         }
+        Some(Command::Config(command)) => {
+            let data_dir = PersistentDataDir::new(args.data_dir);
+            match command {
+                ConfigCommand::Validate => {
+                    HomeserverOps::validate_config(&data_dir)?;
+                    println!("{} is valid.", data_dir.get_config_file_path().display());
+                }
+                ConfigCommand::PrintEffective => {
+                    print!("{}", HomeserverOps::effective_config(&data_dir)?);
+                }
+            }
+        }
+        Some(Command::Keypair(command)) => {
+            let data_dir = PersistentDataDir::new(args.data_dir);
+            match command {
+                KeypairCommand::Show => println!("{}", data_dir.read_keypair()?.public_key().z32()),
+                KeypairCommand::Rotate { yes } => {
+                    if !yes {
+                        anyhow::bail!(
+                            "Rotating the keypair changes the homeserver's public key. \
+                             Stop the homeserver and pass --yes to continue."
+                        );
+                    }
+                    let old_public_key = data_dir.read_keypair()?.public_key();
+                    let (keypair, backup_path) = data_dir.rotate_keypair()?;
+                    println!("Old public key: {}", old_public_key.z32());
+                    println!("New public key: {}", keypair.public_key().z32());
+                    println!("Old secret kept at {}.", backup_path.display());
+                    let config = data_dir.read_or_create_config_file()?;
+                    match config.pkdns.key_transition_days {
+                        0 => println!("The old key is no longer answered to."),
+                        days => println!(
+                            "The old key points at the new one and stays accepted for {days} days."
+                        ),
+                    }
+                }
+            }
+        }
+        Some(command) => {
+            let ops = HomeserverOps::open(PersistentDataDir::new(args.data_dir)).await?;
+            run_ops_command(&ops, command).await?;
+        }
         None => {
             init_tracing_logs_if_set(&args.data_dir)?;
 
@@ -83,7 +394,7 @@ async fn main() -> Result<()> {
             }
 
             tracing::info!("Press Ctrl+C to stop the Homeserver");
-            tokio::signal::ctrl_c().await?;
+            wait_for_shutdown(&server).await?;
 
             tracing::info!("Shutting down Homeserver");
         }
@@ -6,6 +6,7 @@
 //! `client_server`, …) only *record* into it.
 
 use opentelemetry::metrics::{Counter, Histogram, Meter, MeterProvider, UpDownCounter};
+use opentelemetry::KeyValue;
 use opentelemetry_sdk::metrics::SdkMeterProvider;
 use prometheus::{Encoder, Registry, TextEncoder};
 use std::sync::Arc;
@@ -25,6 +26,13 @@ pub const EVENT_STREAM_BROADCAST_HALF_FULL_COUNT: &str = "event_stream_broadcast
 pub const EVENT_STREAM_ACTIVE_CONNECTIONS: &str = "event_stream_active_connections";
 pub const EVENT_STREAM_CONNECTION_DURATION: &str = "event_stream_connection_duration_ms";
 pub const SIGNUP_COUNT: &str = "signup_count";
+pub const STORAGE_SCRUB_CHECKED_COUNT: &str = "storage_scrub_checked_count";
+pub const STORAGE_SCRUB_PROBLEM_COUNT: &str = "storage_scrub_problem_count";
+pub const STORAGE_SCRUB_REPAIRED_COUNT: &str = "storage_scrub_repaired_count";
+pub const STORAGE_SCRUB_RUN_DURATION: &str = "storage_scrub_run_duration_ms";
+pub const STORAGE_CACHE_HIT_COUNT: &str = "storage_cache_hit_count";
+pub const STORAGE_CACHE_MISS_COUNT: &str = "storage_cache_miss_count";
+pub const USER_REPUBLISH_COUNT: &str = "user_republish_count";
 
 #[derive(Clone, Debug)]
 pub struct Metrics {
@@ -37,6 +45,13 @@ pub struct Metrics {
     event_stream_active_connections: UpDownCounter<i64>,
     event_stream_connection_duration: Histogram<f64>,
     signup_count: Counter<u64>,
+    storage_scrub_checked_count: Counter<u64>,
+    storage_scrub_problem_count: Counter<u64>,
+    storage_scrub_repaired_count: Counter<u64>,
+    storage_scrub_run_duration: Histogram<f64>,
+    storage_cache_hit_count: Counter<u64>,
+    storage_cache_miss_count: Counter<u64>,
+    user_republish_count: Counter<u64>,
 }
 
 impl Metrics {
@@ -81,6 +96,50 @@ impl Metrics {
             .with_description("Total number of successful signups")
             .build();
 
+        let storage_scrub_checked_count = meter
+            .u64_counter(STORAGE_SCRUB_CHECKED_COUNT)
+            .with_description("Number of entries, files and users checked by storage scrubs")
+            .build();
+
+        let storage_scrub_problem_count = meter
+            .u64_counter(STORAGE_SCRUB_PROBLEM_COUNT)
+            .with_description("Number of problems found by storage scrubs, by kind")
+            .build();
+
+        let storage_scrub_repaired_count = meter
+            .u64_counter(STORAGE_SCRUB_REPAIRED_COUNT)
+            .with_description("Number of entries and users repaired by storage scrubs")
+            .build();
+
+        let storage_scrub_run_duration = meter
+            .f64_histogram(STORAGE_SCRUB_RUN_DURATION)
+            .with_description("Duration of storage scrubs in milliseconds")
+            .with_boundaries(vec![
+                1_000.0,
+                60_000.0,
+                600_000.0,
+                3_600_000.0,
+                36_000_000.0,
+            ])
+            .build();
+
+        let storage_cache_hit_count = meter
+            .u64_counter(STORAGE_CACHE_HIT_COUNT)
+            .with_description("Number of file reads served from the storage read cache")
+            .build();
+
+        let storage_cache_miss_count = meter
+            .u64_counter(STORAGE_CACHE_MISS_COUNT)
+            .with_description("Number of cacheable file reads that went to the storage backend")
+            .build();
+
+        let user_republish_count = meter
+            .u64_counter(USER_REPUBLISH_COUNT)
+            .with_description(
+                "Number of user pkarr records processed by the republisher, by outcome",
+            )
+            .build();
+
         Ok(Self {
             registry: Arc::new(registry),
             _provider: Arc::new(provider),
@@ -91,6 +150,13 @@ impl Metrics {
             event_stream_active_connections,
             event_stream_connection_duration,
             signup_count,
+            storage_scrub_checked_count,
+            storage_scrub_problem_count,
+            storage_scrub_repaired_count,
+            storage_scrub_run_duration,
+            storage_cache_hit_count,
+            storage_cache_miss_count,
+            user_republish_count,
         })
     }
 
@@ -135,6 +201,47 @@ impl Metrics {
         self.signup_count.add(1, &[]);
     }
 
+    // === storage scrub metrics ===
+
+    /// Record scrub progress: `checked` more entries, files or users were checked.
+    pub fn record_storage_scrub_checked(&self, checked: u64) {
+        self.storage_scrub_checked_count.add(checked, &[]);
+    }
+
+    /// Record problems found by a scrub. `kind` names the report list they come from.
+    pub fn record_storage_scrub_problems(&self, kind: &'static str, count: u64) {
+        self.storage_scrub_problem_count
+            .add(count, &[KeyValue::new("kind", kind)]);
+    }
+
+    pub fn record_storage_scrub_repaired(&self, repaired: u64) {
+        self.storage_scrub_repaired_count.add(repaired, &[]);
+    }
+
+    pub fn record_storage_scrub_run(&self, duration_ms: u128) {
+        self.storage_scrub_run_duration
+            .record(duration_ms as f64, &[]);
+    }
+
+    // === storage read cache metrics ===
+
+    pub fn record_storage_cache_hit(&self) {
+        self.storage_cache_hit_count.add(1, &[]);
+    }
+
+    pub fn record_storage_cache_miss(&self) {
+        self.storage_cache_miss_count.add(1, &[]);
+    }
+
+    // === user keys republisher metrics ===
+
+    /// Record that a user's pkarr record was processed with `outcome`
+    /// (`published`, `skipped`, `missing`, `invalid_packet` or `failed`).
+    pub fn record_user_republish(&self, outcome: &'static str) {
+        self.user_republish_count
+            .add(1, &[KeyValue::new("outcome", outcome)]);
+    }
+
     /// Render Prometheus metrics in text format
     pub fn render(&self) -> Result<String, String> {
         let metric_families = self.registry.gather();
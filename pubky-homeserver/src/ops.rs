@@ -0,0 +1,661 @@
+//! Offline operations behind the `pubky-homeserver` CLI subcommands.
+//!
+//! [`HomeserverOps`] works directly against a data directory and its database
+//! without starting any server. It goes through the same services and
+//! repositories as the admin API and prints the same JSON shapes, so operators
+//! don't need the admin server running to manage users and signup tokens.
+//!
+//! Running homeservers cache user quotas for a short while, so quota changes
+//! made here can take a moment to apply there.
+//!
+//! Backups made here also hold the homeserver keypair as `secret`, next to the
+//! database and files of [`BackupService`].
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use pubky_common::crypto::{Keypair, PublicKey};
+use serde_json::Value;
+
+use crate::admin_server::routes::{
+    signup_tokens::SignupTokensResponse,
+    user_quota::UserQuotaResponse,
+    users::{is_pubkey_prefix, UsersResponse},
+};
+use crate::persistence::files::{
+    build_backend_operator,
+    events::{EventRepository, EventsService},
+    FileService,
+};
+use crate::persistence::sql::{
+    signup_code::{SignupCode, SignupCodeListQuery, SignupCodeListState, SignupCodeRepository},
+    user::{UserEntity, UserListQuery},
+    Migrator, SqlDb,
+};
+use crate::services::backup::{BackupManifest, BackupService};
+use crate::services::config_service::ReloadableConfig;
+use crate::services::content_block_service::ContentBlockService;
+use crate::services::storage_integrity::{
+    ScrubOptions, StorageIntegrityService, StorageScrubReport,
+};
+use crate::services::storage_migration::{
+    StorageMigrationError, StorageMigrationService, StorageMigrationState,
+};
+use crate::services::usage_service::UsageService;
+use crate::services::user_service::UserService;
+use crate::shared::user_quota::{UserQuota, UserQuotaPatch};
+use crate::storage_config::StorageConfigToml;
+use crate::{ConfigToml, DataDir, PersistentDataDir};
+
+/// Name of the homeserver secret file in a backup.
+const BACKUP_SECRET_FILE: &str = "secret";
+/// State of an ongoing storage migration, in the data directory.
+const STORAGE_MIGRATION_FILE: &str = "storage_migration.json";
+
+/// Offline access to a homeserver's data directory and database.
+#[derive(Debug)]
+pub struct HomeserverOps {
+    data_dir: PersistentDataDir,
+    config: ConfigToml,
+    sql_db: SqlDb,
+    user_service: UserService,
+}
+
+impl HomeserverOps {
+    /// Read the config of `data_dir` and connect to its database.
+    ///
+    /// Does not run migrations; see [`HomeserverOps::migrate`].
+    pub async fn open(data_dir: PersistentDataDir) -> anyhow::Result<Self> {
+        let config = Self::validate_config(&data_dir)?;
+        let sql_db = SqlDb::connect(&config.general.database_url)
+            .await
+            .context("Failed to connect to the database")?;
+        Ok(Self {
+            data_dir,
+            config,
+            user_service: UserService::new(sql_db.clone()),
+            sql_db,
+        })
+    }
+
+    /// Load the config file like the homeserver does at startup and check the
+    /// settings that are only validated when they are used.
+    ///
+    /// Unlike starting the homeserver, a missing config file is an error.
+    pub fn validate_config(data_dir: &PersistentDataDir) -> anyhow::Result<ConfigToml> {
+        let path = data_dir.get_config_file_path();
+        if !path.exists() {
+            bail!(
+                "No config file at {}. Run `pubky-homeserver init` to create one.",
+                path.display()
+            );
+        }
+        let config = ConfigToml::from_file(&path)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        ReloadableConfig::from(&config).validate()?;
+        Ok(config)
+    }
+
+    /// The config with all defaults applied, as TOML.
+    pub fn effective_config(data_dir: &PersistentDataDir) -> anyhow::Result<String> {
+        let config = Self::validate_config(data_dir)?;
+        Ok(toml::to_string_pretty(&config)?)
+    }
+
+    /// Run the pending database migrations.
+    pub async fn migrate(&self) -> anyhow::Result<()> {
+        Migrator::new(&self.sql_db).run().await
+    }
+
+    /// List users like `GET /users` on the admin server.
+    ///
+    /// `search` is a z-base-32 public key prefix.
+    pub async fn list_users(
+        &self,
+        search: Option<String>,
+        disabled: Option<bool>,
+        limit: Option<u16>,
+        cursor: Option<i32>,
+    ) -> anyhow::Result<Value> {
+        if search
+            .as_deref()
+            .is_some_and(|search| !is_pubkey_prefix(search))
+        {
+            bail!("--search must be a z-base-32 public key prefix");
+        }
+        let page = self
+            .user_service
+            .list(UserListQuery {
+                pubkey_prefix: search,
+                disabled,
+                created_after: None,
+                created_before: None,
+                min_used_bytes: None,
+                max_used_bytes: None,
+                signup_code: None,
+                limit,
+                cursor,
+            })
+            .await?;
+        Ok(serde_json::to_value(UsersResponse::from(page))?)
+    }
+
+    /// Disable or re-enable a user.
+    pub async fn set_user_disabled(
+        &self,
+        pubkey: &PublicKey,
+        disabled: bool,
+    ) -> anyhow::Result<()> {
+        self.get_user(pubkey).await?;
+        if disabled {
+            self.user_service.admin_disable(pubkey).await?;
+        } else {
+            self.user_service.admin_enable(pubkey).await?;
+        }
+        Ok(())
+    }
+
+    /// The effective quota, overrides and usage of a user, like `GET /users/{pubkey}/quota`.
+    pub async fn user_quota(&self, pubkey: &PublicKey) -> anyhow::Result<Value> {
+        let user = self.get_user(pubkey).await?;
+        let overrides = user.quota();
+        let effective = overrides.resolve_with_defaults(
+            self.config.storage.default_quota_mb,
+            &self.config.default_quotas,
+        );
+        let usage = UsageService::new(self.sql_db.clone()).get(pubkey).await?;
+        Ok(serde_json::to_value(UserQuotaResponse {
+            effective,
+            overrides,
+            usage,
+        })?)
+    }
+
+    /// Update the quota overrides of a user.
+    ///
+    /// `patch` is the JSON body of `PATCH /users/{pubkey}/quota`.
+    pub async fn patch_user_quota(&self, pubkey: &PublicKey, patch: &str) -> anyhow::Result<()> {
+        let patch: UserQuotaPatch = serde_json::from_str(patch).context("Invalid quota patch")?;
+        patch.validate().map_err(anyhow::Error::msg)?;
+        self.get_user(pubkey).await?;
+        self.user_service.patch_quota(pubkey, &patch).await?;
+        Ok(())
+    }
+
+    /// Create a signup token. `limits` is the JSON body of `POST /generate_signup_token`.
+    pub async fn create_signup_token(&self, limits: Option<&str>) -> anyhow::Result<String> {
+        let limits: UserQuota = match limits {
+            Some(limits) => serde_json::from_str(limits).context("Invalid signup token limits")?,
+            None => UserQuota::default(),
+        };
+        limits.validate().map_err(anyhow::Error::msg)?;
+        let code = SignupCodeRepository::create(
+            &SignupCode::random(),
+            &limits,
+            &mut self.sql_db.pool().into(),
+        )
+        .await?;
+        Ok(code.id.0)
+    }
+
+    /// List signup tokens like `GET /signup_tokens`. `used` filters by usage.
+    pub async fn list_signup_tokens(
+        &self,
+        used: Option<bool>,
+        limit: Option<u16>,
+        cursor: Option<String>,
+    ) -> anyhow::Result<Value> {
+        let state = match used {
+            None => SignupCodeListState::All,
+            Some(true) => SignupCodeListState::Used,
+            Some(false) => SignupCodeListState::Unused,
+        };
+        let cursor = cursor.map(SignupCode::new).transpose()?;
+        let page = SignupCodeRepository::list(
+            SignupCodeListQuery {
+                state,
+                limit,
+                cursor,
+            },
+            &mut self.sql_db.pool().into(),
+        )
+        .await?;
+        Ok(serde_json::to_value(SignupTokensResponse::from(page))?)
+    }
+
+    /// Scrub the storage like the background scrub job does.
+    ///
+    /// With `repair` this fixes the database even while homeservers are running;
+    /// every repair re-checks its entry first.
+    pub async fn scrub_storage(&self, options: ScrubOptions) -> anyhow::Result<StorageScrubReport> {
+        let service = StorageIntegrityService::new(
+            self.sql_db.clone(),
+            self.file_service()?,
+            self.user_service.clone(),
+        );
+        Ok(service
+            .scrub(options, |report| {
+                tracing::debug!(
+                    "Scrubbed {} entries, {} files and {} users",
+                    report.checked,
+                    report.files_checked,
+                    report.users_checked
+                )
+            })
+            .await?)
+    }
+
+    /// Back up the database, the files and the keypair into `dir`.
+    ///
+    /// Homeservers can keep running; the backup is a consistent snapshot.
+    pub async fn create_backup(&self, dir: &Path) -> anyhow::Result<BackupManifest> {
+        let manifest = BackupService::new(self.sql_db.clone(), self.file_service()?)
+            .create(dir)
+            .await?;
+        if self.data_dir.get_secret_file_path().exists() {
+            self.data_dir
+                .read_keypair()?
+                .write_secret_key_file(&dir.join(BACKUP_SECRET_FILE))?;
+        }
+        Ok(manifest)
+    }
+
+    /// Restore a backup of [`Self::create_backup`] into the empty, migrated database.
+    ///
+    /// The keypair of the backup replaces the current one. Returns where a
+    /// different previous secret was kept.
+    pub async fn restore_backup(
+        &self,
+        dir: &Path,
+    ) -> anyhow::Result<(BackupManifest, Option<PathBuf>)> {
+        let manifest = BackupService::new(self.sql_db.clone(), self.file_service()?)
+            .restore(dir)
+            .await?;
+        let secret_path = dir.join(BACKUP_SECRET_FILE);
+        let old_secret = if secret_path.exists() {
+            let keypair = Keypair::from_secret_key_file(&secret_path)
+                .context("Invalid secret file in the backup")?;
+            self.data_dir.replace_keypair(&keypair)?
+        } else {
+            None
+        };
+        Ok((manifest, old_secret))
+    }
+
+    /// Copy every file to the storage backend `target`, then replay the writes
+    /// made in the meantime. Homeservers can keep running.
+    ///
+    /// Progress is saved in the data directory after every batch, so running this
+    /// again resumes an interrupted migration. With `switch`, `config.toml` is
+    /// pointed at the target once every file is copied.
+    pub async fn migrate_storage(
+        &self,
+        target: StorageConfigToml,
+        switch: bool,
+    ) -> anyhow::Result<StorageMigrationState> {
+        let mut state = match self.read_storage_migration()? {
+            Some(state) if state.target != target => bail!(
+                "A migration to another storage backend is in progress. Finish it, or delete {} to start over.",
+                self.storage_migration_path().display()
+            ),
+            Some(state) if state.switched && state.copied_all => bail!(
+                "The config already uses the target. Restart the homeservers, then run `storage migrate --finish`."
+            ),
+            Some(state) => state,
+            None => {
+                let source = self.config.storage.backend.clone();
+                if source == target {
+                    bail!("The homeserver already uses this storage backend");
+                }
+                let event_cursor =
+                    EventRepository::get_max_id(&mut self.sql_db.pool().into()).await?;
+                StorageMigrationState::new(source, target, event_cursor)
+            }
+        };
+        let service = self.storage_migration_service(&state)?;
+        while service.copy_next_batch(&mut state).await? {
+            self.write_storage_migration(&state)?;
+            tracing::debug!("Copied {} files", state.files_copied);
+        }
+        self.replay_storage_migration(&service, &mut state).await?;
+
+        if switch && !state.switched {
+            ensure_all_copied(&state)?;
+            let config_path = self.data_dir.get_config_file_path();
+            let config = std::fs::read_to_string(&config_path)?;
+            let config = set_storage_backend(&config, &state.source, &state.target)?;
+            ConfigToml::from_str_with_defaults(&config)
+                .context("The switched config is invalid")?;
+            std::fs::write(&config_path, config)?;
+            state.switched = true;
+            self.write_storage_migration(&state)?;
+        }
+        Ok(state)
+    }
+
+    /// End a switched storage migration once every homeserver was restarted on the
+    /// target: copy the writes they made to the source until then.
+    pub async fn finish_storage_migration(&self) -> anyhow::Result<StorageMigrationState> {
+        let mut state = match self.read_storage_migration()? {
+            Some(state) if state.switched && !state.copied_all => {
+                bail!("Run `storage migrate` to copy every file again before finishing")
+            }
+            Some(state) if state.switched => state,
+            Some(_) => bail!("Run `storage migrate --switch` before finishing the migration"),
+            None => bail!("No storage migration in progress"),
+        };
+        let service = self.storage_migration_service(&state)?;
+        self.replay_storage_migration(&service, &mut state).await?;
+        ensure_all_copied(&state)?;
+        std::fs::remove_file(self.storage_migration_path())?;
+        Ok(state)
+    }
+
+    /// Replay the writes made since the migration's event cursor, then retry the
+    /// files that did not match.
+    ///
+    /// If event retention pruned events that were not replayed yet, the copy is
+    /// reset so the next `storage migrate` copies every file again.
+    async fn replay_storage_migration(
+        &self,
+        service: &StorageMigrationService,
+        state: &mut StorageMigrationState,
+    ) -> anyhow::Result<()> {
+        loop {
+            match service.replay_next_batch(state).await {
+                Ok(true) => self.write_storage_migration(state)?,
+                Ok(false) => break,
+                Err(e @ StorageMigrationError::EventsPruned { .. }) => {
+                    let event_cursor =
+                        EventRepository::get_max_id(&mut self.sql_db.pool().into()).await?;
+                    state.restart_copy(event_cursor);
+                    self.write_storage_migration(state)?;
+                    bail!("{e}. Run `storage migrate` again to copy every file.");
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        service.retry(state).await?;
+        self.write_storage_migration(state)
+    }
+
+    fn storage_migration_service(
+        &self,
+        state: &StorageMigrationState,
+    ) -> anyhow::Result<StorageMigrationService> {
+        Ok(StorageMigrationService::new(
+            self.sql_db.clone(),
+            build_backend_operator(&state.source, self.data_dir.path())?,
+            build_backend_operator(&state.target, self.data_dir.path())?,
+        ))
+    }
+
+    fn storage_migration_path(&self) -> PathBuf {
+        self.data_dir.path().join(STORAGE_MIGRATION_FILE)
+    }
+
+    fn read_storage_migration(&self) -> anyhow::Result<Option<StorageMigrationState>> {
+        match std::fs::read(self.storage_migration_path()) {
+            Ok(json) => Ok(Some(
+                serde_json::from_slice(&json).context("Invalid storage migration state")?,
+            )),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn write_storage_migration(&self, state: &StorageMigrationState) -> anyhow::Result<()> {
+        std::fs::write(
+            self.storage_migration_path(),
+            serde_json::to_vec_pretty(state)?,
+        )?;
+        Ok(())
+    }
+
+    fn file_service(&self) -> anyhow::Result<FileService> {
+        Ok(FileService::new_from_config(
+            &self.config,
+            self.data_dir.path(),
+            self.sql_db.clone(),
+            EventsService::new(1),
+            self.user_service.clone(),
+            ContentBlockService::new(),
+        )?)
+    }
+
+    async fn get_user(&self, pubkey: &PublicKey) -> anyhow::Result<UserEntity> {
+        match self.user_service.get(pubkey).await {
+            Ok(user) => Ok(user),
+            Err(sqlx::Error::RowNotFound) => bail!("User {} not found", pubkey.z32()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn ensure_all_copied(state: &StorageMigrationState) -> anyhow::Result<()> {
+    if !state.retry.is_empty() {
+        bail!(
+            "{} files do not match their entries, run `storage verify --rehash`: {}",
+            state.retry.len(),
+            state.retry.iter().cloned().collect::<Vec<_>>().join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Replace the `from` backend settings in the `[storage]` table of `config` with
+/// the `to` ones, keeping everything else including comments.
+fn set_storage_backend(
+    config: &str,
+    from: &StorageConfigToml,
+    to: &StorageConfigToml,
+) -> anyhow::Result<String> {
+    let mut config: toml_edit::DocumentMut = config.parse()?;
+    let storage = config
+        .entry("storage")
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .context("[storage] is not a table")?;
+    let to: toml_edit::DocumentMut = toml::to_string(to)?.parse()?;
+    for key in toml::Table::try_from(from)?.keys() {
+        if !to.contains_key(key) {
+            storage.remove(key);
+        }
+    }
+    for (key, item) in to.iter() {
+        match storage.get_mut(key) {
+            // Keeps the comments above the key.
+            Some(existing) => *existing = item.clone(),
+            None => {
+                storage.insert(key, item.clone());
+            }
+        }
+    }
+    Ok(config.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+
+    /// A data directory whose config points at an ephemeral test database.
+    fn test_data_dir() -> (tempfile::TempDir, PersistentDataDir) {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = PersistentDataDir::new(temp_dir.path().to_path_buf());
+        let mut database_url: url::Url = SqlDb::derive_connection_string(None)
+            .to_string()
+            .parse()
+            .unwrap();
+        database_url
+            .query_pairs_mut()
+            .append_pair("pubky-test", "true");
+        std::fs::write(
+            data_dir.get_config_file_path(),
+            format!("[general]\ndatabase_url = \"{database_url}\"\n"),
+        )
+        .unwrap();
+        (temp_dir, data_dir)
+    }
+
+    #[test]
+    fn test_validate_and_print_config() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = PersistentDataDir::new(temp_dir.path().to_path_buf());
+        assert!(HomeserverOps::validate_config(&data_dir).is_err());
+        assert!(!data_dir.get_config_file_path().exists());
+
+        std::fs::write(
+            data_dir.get_config_file_path(),
+            "[general]\nsignup_mode = \"open\"\n",
+        )
+        .unwrap();
+        let effective = HomeserverOps::effective_config(&data_dir).unwrap();
+        let reparsed = ConfigToml::from_str_with_defaults(&effective).unwrap();
+        assert_eq!(reparsed, HomeserverOps::validate_config(&data_dir).unwrap());
+        assert_eq!(reparsed.general.signup_mode, crate::SignupMode::Open);
+
+        std::fs::write(
+            data_dir.get_config_file_path(),
+            "[[drive.rate_limits]]\npath = \"/session\"\nmethod = \"POST\"\nquota = \"10r/m\"\nkey = \"user\"\nwhitelist = [\"127.0.0.1\"]\n",
+        )
+        .unwrap();
+        assert!(HomeserverOps::validate_config(&data_dir).is_err());
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_manage_users_and_signup_tokens() {
+        let (_temp_dir, data_dir) = test_data_dir();
+        let ops = HomeserverOps::open(data_dir).await.unwrap();
+        ops.migrate().await.unwrap();
+
+        let token = ops
+            .create_signup_token(Some(r#"{"storage_quota_mb": 10}"#))
+            .await
+            .unwrap();
+        assert!(ops.create_signup_token(Some("{")).await.is_err());
+        let tokens = ops
+            .list_signup_tokens(Some(false), None, None)
+            .await
+            .unwrap();
+        assert_eq!(tokens["items"][0]["token"], token);
+        assert_eq!(tokens["items"][0]["used_by"], Value::Null);
+
+        let pubkey = Keypair::random().public_key();
+        assert!(ops.set_user_disabled(&pubkey, true).await.is_err());
+        ops.user_service.create(&pubkey).await.unwrap();
+        ops.set_user_disabled(&pubkey, true).await.unwrap();
+        let users = ops.list_users(None, Some(true), None, None).await.unwrap();
+        assert_eq!(users["items"][0]["public_key"], pubkey.z32());
+        assert_eq!(users["items"].as_array().unwrap().len(), 1);
+        assert!(ops
+            .list_users(Some("%".to_string()), None, None, None)
+            .await
+            .is_err());
+
+        ops.patch_user_quota(&pubkey, r#"{"storage_quota_mb": 500}"#)
+            .await
+            .unwrap();
+        assert!(ops
+            .patch_user_quota(&pubkey, r#"{"storage_quota_mb": "lots"}"#)
+            .await
+            .is_err());
+        let quota = ops.user_quota(&pubkey).await.unwrap();
+        assert_eq!(quota["overrides"]["storage_quota_mb"], 500);
+        assert_eq!(quota["effective"]["rate_read"], "unlimited");
+
+        let report = ops.scrub_storage(ScrubOptions::default()).await.unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.checked, 0);
+        assert_eq!(report.users_checked, 1);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_backup_and_restore_keypair() {
+        let (_source_dir, source_data_dir) = test_data_dir();
+        source_data_dir.init().unwrap();
+        let source = HomeserverOps::open(source_data_dir.clone()).await.unwrap();
+        source.migrate().await.unwrap();
+        let pubkey = Keypair::random().public_key();
+        source.user_service.create(&pubkey).await.unwrap();
+        let backup_dir = tempfile::TempDir::new().unwrap();
+        source.create_backup(backup_dir.path()).await.unwrap();
+
+        let (_target_dir, target_data_dir) = test_data_dir();
+        target_data_dir.init().unwrap();
+        let target = HomeserverOps::open(target_data_dir.clone()).await.unwrap();
+        assert!(target.restore_backup(backup_dir.path()).await.is_err()); // Not migrated
+        target.migrate().await.unwrap();
+        let (manifest, old_secret) = target.restore_backup(backup_dir.path()).await.unwrap();
+        assert_eq!(manifest.files, 0);
+        assert!(old_secret.unwrap().exists());
+        assert_eq!(
+            target_data_dir.read_keypair().unwrap().public_key(),
+            source_data_dir.read_keypair().unwrap().public_key()
+        );
+        let users = target.list_users(None, None, None, None).await.unwrap();
+        assert_eq!(users["items"][0]["public_key"], pubkey.z32());
+    }
+
+    #[test]
+    fn test_set_storage_backend_keeps_other_settings() {
+        let config = "[general]\nsignup_mode = \"open\"\n\n[storage]\n# Where files go\ntype = \"file_system\"\ndefault_quota_mb = 10\n";
+        let switched = set_storage_backend(
+            config,
+            &StorageConfigToml::FileSystem,
+            &StorageConfigToml::InMemory,
+        )
+        .unwrap();
+        assert!(switched.contains("# Where files go"), "{switched}");
+        let parsed = ConfigToml::from_str_with_defaults(&switched).unwrap();
+        assert_eq!(parsed.storage.backend, StorageConfigToml::InMemory);
+        assert_eq!(parsed.storage.default_quota_mb, Some(10));
+        assert_eq!(parsed.general.signup_mode, crate::SignupMode::Open);
+
+        // Also works without a `[storage]` table.
+        let switched = set_storage_backend(
+            "",
+            &StorageConfigToml::FileSystem,
+            &StorageConfigToml::InMemory,
+        )
+        .unwrap();
+        let parsed = ConfigToml::from_str_with_defaults(&switched).unwrap();
+        assert_eq!(parsed.storage.backend, StorageConfigToml::InMemory);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_migrate_storage_and_switch() {
+        let (_temp_dir, data_dir) = test_data_dir();
+        let ops = HomeserverOps::open(data_dir.clone()).await.unwrap();
+        ops.migrate().await.unwrap();
+        assert!(ops.finish_storage_migration().await.is_err());
+        assert!(ops
+            .migrate_storage(StorageConfigToml::FileSystem, false)
+            .await
+            .is_err());
+
+        let state = ops
+            .migrate_storage(StorageConfigToml::InMemory, false)
+            .await
+            .unwrap();
+        assert!(state.copied_all && !state.switched);
+        let state = ops
+            .migrate_storage(StorageConfigToml::InMemory, true)
+            .await
+            .unwrap();
+        assert!(state.switched);
+        let config = HomeserverOps::validate_config(&data_dir).unwrap();
+        assert_eq!(config.storage.backend, StorageConfigToml::InMemory);
+        assert!(ops
+            .migrate_storage(StorageConfigToml::InMemory, true)
+            .await
+            .is_err());
+
+        ops.finish_storage_migration().await.unwrap();
+        assert!(!data_dir.path().join(STORAGE_MIGRATION_FILE).exists());
+    }
+}
@@ -4,7 +4,7 @@ use sea_query::{Condition, Expr, Iden, Order, PostgresQueryBuilder, Query, Simpl
 use sea_query_binder::SqlxBinder;
 use sqlx::{
     postgres::PgRow,
-    types::chrono::{DateTime, Utc},
+    types::chrono::{DateTime, NaiveDateTime, Utc},
     Row,
 };
 
@@ -13,6 +13,9 @@ use crate::{
     persistence::{
         files::events::EventEntity,
         sql::{
+            migrations::m20261018_create_event_watermarks::{
+                EventWatermarkIden, EVENT_WATERMARKS_TABLE,
+            },
             user::{UserIden, USER_TABLE},
             UnifiedExecutor,
         },
@@ -384,6 +387,187 @@ impl EventRepository {
         let events: Vec<EventEntity> = sqlx::query_as_with(&query, values).fetch_all(con).await?;
         Ok(events)
     }
+
+    /// Delete up to `batch` events created before `cutoff`, oldest first.
+    /// Advances the retention watermark of every affected user.
+    /// Returns the number of deleted events.
+    pub async fn delete_older_than<'a>(
+        cutoff: &DateTime<Utc>,
+        batch: u32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<u64, sqlx::Error> {
+        let doomed =
+            format!(r#"SELECT id FROM {EVENT_TABLE} WHERE created_at < $1 ORDER BY id LIMIT $2"#);
+        let query = Self::prune_with_watermark_query(&doomed);
+        let con = executor.get_con().await?;
+        let deleted: i64 = sqlx::query_scalar(&query)
+            .bind(cutoff.naive_utc())
+            .bind(batch as i64)
+            .fetch_one(con)
+            .await?;
+        Ok(deleted as u64)
+    }
+
+    /// Delete up to `batch` events of users that have more than `max_per_user` events,
+    /// oldest first, so that each user keeps their `max_per_user` most recent events.
+    /// Advances the retention watermark of every affected user.
+    /// Returns the number of deleted events.
+    pub async fn delete_exceeding_per_user<'a>(
+        max_per_user: u64,
+        batch: u32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<u64, sqlx::Error> {
+        let doomed = format!(
+            r#"SELECT id FROM (
+                SELECT id, row_number() OVER (PARTITION BY "user" ORDER BY id DESC) AS rank
+                FROM {EVENT_TABLE}
+            ) ranked
+            WHERE rank > $1
+            ORDER BY id
+            LIMIT $2"#
+        );
+        let query = Self::prune_with_watermark_query(&doomed);
+        let con = executor.get_con().await?;
+        let deleted: i64 = sqlx::query_scalar(&query)
+            .bind(i64::try_from(max_per_user).unwrap_or(i64::MAX))
+            .bind(batch as i64)
+            .fetch_one(con)
+            .await?;
+        Ok(deleted as u64)
+    }
+
+    /// Builds a statement that deletes the events selected by `doomed` and raises the
+    /// per-user watermark to the highest deleted id, all in a single statement so a
+    /// crash can never delete events without recording it.
+    fn prune_with_watermark_query(doomed: &str) -> String {
+        format!(
+            r#"WITH doomed AS ({doomed}),
+            deleted AS (
+                DELETE FROM {EVENT_TABLE} WHERE id IN (SELECT id FROM doomed)
+                RETURNING "user", id
+            ),
+            watermark AS (
+                INSERT INTO {EVENT_WATERMARKS_TABLE} ("user", pruned_through)
+                SELECT "user", MAX(id) FROM deleted GROUP BY "user"
+                ON CONFLICT ("user") DO UPDATE
+                SET pruned_through = GREATEST({EVENT_WATERMARKS_TABLE}.pruned_through, EXCLUDED.pruned_through)
+            )
+            SELECT COUNT(*) FROM deleted"#
+        )
+    }
+
+    /// Delete up to `batch` events that are superseded by a newer event for the same
+    /// user and path. The latest event per path (PUT or DEL) is always kept.
+    ///
+    /// The retention watermark is deliberately left untouched: a compacted event is only
+    /// removed when a newer event for the same path exists, so a reader at any cursor
+    /// still receives the final event of every path it has not caught up on yet. It
+    /// skips intermediate states (e.g. a DEL that was followed by a new PUT) but always
+    /// converges to the current state, which is all a `410 Gone` resync would give it.
+    ///
+    /// Superseded events are found with a window over the `(user, path, id)` index
+    /// instead of a per-row subquery, and without a global order so a batch can stop
+    /// scanning as soon as it found `batch` candidates.
+    /// Returns the number of deleted events.
+    pub async fn compact<'a>(
+        batch: u32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<u64, sqlx::Error> {
+        let query = format!(
+            r#"WITH doomed AS (
+                SELECT id FROM (
+                    SELECT id, row_number() OVER (PARTITION BY "user", path ORDER BY id DESC) AS rank
+                    FROM {EVENT_TABLE}
+                ) ranked
+                WHERE rank > 1
+                LIMIT $1
+            ),
+            deleted AS (
+                DELETE FROM {EVENT_TABLE} WHERE id IN (SELECT id FROM doomed)
+                RETURNING id
+            )
+            SELECT COUNT(*) FROM deleted"#
+        );
+        let con = executor.get_con().await?;
+        let deleted: i64 = sqlx::query_scalar(&query)
+            .bind(batch as i64)
+            .fetch_one(con)
+            .await?;
+        Ok(deleted as u64)
+    }
+
+    /// Get the highest event id deleted by retention for this user, if any.
+    /// Cursors below this id point into history that no longer exists.
+    pub async fn get_pruned_through<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<EventCursor>, sqlx::Error> {
+        let statement = Query::select()
+            .column(EventWatermarkIden::PrunedThrough)
+            .from(EVENT_WATERMARKS_TABLE)
+            .and_where(Expr::col(EventWatermarkIden::User).eq(user_id))
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let pruned_through: Option<i64> = sqlx::query_scalar_with(&query, values)
+            .fetch_optional(con)
+            .await?;
+        Ok(pruned_through.map(|id| EventCursor::new(id as u64)))
+    }
+
+    /// Get the highest event id deleted by retention for any user, if any.
+    /// A replay of all users from below this id would miss events.
+    pub async fn get_max_pruned_through<'a>(
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<EventCursor>, sqlx::Error> {
+        let statement = Query::select()
+            .expr(Expr::col(EventWatermarkIden::PrunedThrough).max())
+            .from(EVENT_WATERMARKS_TABLE)
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let pruned_through: Option<i64> = sqlx::query_scalar_with(&query, values)
+            .fetch_one(con)
+            .await?;
+        Ok(pruned_through.map(|id| EventCursor::new(id as u64)))
+    }
+
+    /// Get size statistics of the events table.
+    pub async fn get_overview<'a>(
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<EventTableOverview, sqlx::Error> {
+        let query = format!(
+            r#"SELECT COUNT(*) AS num_events, MIN(id) AS min_id, MAX(id) AS max_id,
+                MIN(created_at) AS oldest_created_at,
+                pg_total_relation_size('{EVENT_TABLE}') AS total_bytes
+            FROM {EVENT_TABLE}"#
+        );
+        let con = executor.get_con().await?;
+        let row: PgRow = sqlx::query(&query).fetch_one(con).await?;
+        let num_events: i64 = row.try_get("num_events")?;
+        let min_id: Option<i64> = row.try_get("min_id")?;
+        let max_id: Option<i64> = row.try_get("max_id")?;
+        let oldest_created_at: Option<NaiveDateTime> = row.try_get("oldest_created_at")?;
+        let total_bytes: i64 = row.try_get("total_bytes")?;
+        Ok(EventTableOverview {
+            num_events: num_events as u64,
+            min_id: min_id.map(|id| id as u64),
+            max_id: max_id.map(|id| id as u64),
+            oldest_created_at,
+            total_bytes: total_bytes as u64,
+        })
+    }
+}
+
+/// Size statistics of the events table.
+#[derive(Debug, Clone)]
+pub struct EventTableOverview {
+    pub num_events: u64,
+    pub min_id: Option<u64>,
+    pub max_id: Option<u64>,
+    pub oldest_created_at: Option<NaiveDateTime>,
+    /// Size of the table including its indexes and TOAST data.
+    pub total_bytes: u64,
 }
 
 #[derive(Iden)]
@@ -822,4 +1006,168 @@ mod tests {
             .iter()
             .all(|e| !e.path.path().as_str().starts_with("/priv/")));
     }
+
+    async fn create_put(user_id: i32, path: &EntryPath, db: &SqlDb) -> EventEntity {
+        EventRepository::create(
+            user_id,
+            EventType::Put {
+                content_hash: Hash::from_bytes([0; 32]),
+            },
+            path,
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_delete_older_than_advances_watermark() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let user_pubkey = Keypair::random().public_key();
+        let user = user_service.create(&user_pubkey).await.unwrap();
+        let path = EntryPath::new(user_pubkey, StoragePath::new("/pub/a").unwrap());
+
+        // 3 old events, 2 recent events
+        let now = Utc::now();
+        for _ in 0..3 {
+            let old = now - chrono::Duration::days(10);
+            EventRepository::create_with_timestamp(
+                user.id,
+                EventType::Delete,
+                &path,
+                &old,
+                &mut db.pool().into(),
+            )
+            .await
+            .unwrap();
+        }
+        create_put(user.id, &path, &db).await;
+        create_put(user.id, &path, &db).await;
+
+        assert_eq!(
+            EventRepository::get_pruned_through(user.id, &mut db.pool().into())
+                .await
+                .unwrap(),
+            None
+        );
+
+        let cutoff = now - chrono::Duration::days(1);
+        // Batches are respected
+        let deleted = EventRepository::delete_older_than(&cutoff, 2, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+        let deleted = EventRepository::delete_older_than(&cutoff, 2, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        let deleted = EventRepository::delete_older_than(&cutoff, 2, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(deleted, 0);
+
+        let pruned_through = EventRepository::get_pruned_through(user.id, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(pruned_through, Some(EventCursor::new(3)));
+
+        let overview = EventRepository::get_overview(&mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(overview.num_events, 2);
+        assert_eq!(overview.min_id, Some(4));
+        assert_eq!(overview.max_id, Some(5));
+        assert!(overview.total_bytes > 0);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_delete_exceeding_per_user() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let alice_pubkey = Keypair::random().public_key();
+        let bob_pubkey = Keypair::random().public_key();
+        let alice = user_service.create(&alice_pubkey).await.unwrap();
+        let bob = user_service.create(&bob_pubkey).await.unwrap();
+        let alice_path = EntryPath::new(alice_pubkey, StoragePath::new("/pub/a").unwrap());
+        let bob_path = EntryPath::new(bob_pubkey, StoragePath::new("/pub/b").unwrap());
+
+        for _ in 0..5 {
+            create_put(alice.id, &alice_path, &db).await;
+        }
+        create_put(bob.id, &bob_path, &db).await;
+
+        let deleted = EventRepository::delete_exceeding_per_user(2, 100, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(deleted, 3);
+
+        let remaining = EventRepository::get_by_user_cursors(
+            vec![(alice.id, None), (bob.id, None)],
+            false,
+            &[],
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            remaining.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![4, 5, 6]
+        );
+        assert_eq!(
+            EventRepository::get_pruned_through(alice.id, &mut db.pool().into())
+                .await
+                .unwrap(),
+            Some(EventCursor::new(3))
+        );
+        assert_eq!(
+            EventRepository::get_pruned_through(bob.id, &mut db.pool().into())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_compact_keeps_latest_event_per_path() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let user_pubkey = Keypair::random().public_key();
+        let user = user_service.create(&user_pubkey).await.unwrap();
+        let a = EntryPath::new(user_pubkey.clone(), StoragePath::new("/pub/a").unwrap());
+        let b = EntryPath::new(user_pubkey, StoragePath::new("/pub/b").unwrap());
+
+        create_put(user.id, &a, &db).await; // 1: superseded
+        create_put(user.id, &b, &db).await; // 2: latest for b
+        create_put(user.id, &a, &db).await; // 3: superseded
+        EventRepository::create(user.id, EventType::Delete, &a, &mut db.pool().into())
+            .await
+            .unwrap(); // 4: latest for a
+
+        let deleted = EventRepository::compact(100, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+
+        let remaining =
+            EventRepository::get_by_cursor(None, None, EventVisibility::All, &mut db.pool().into())
+                .await
+                .unwrap();
+        assert_eq!(
+            remaining.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        assert!(matches!(remaining[1].event_type, EventType::Delete));
+
+        // Compaction keeps replay convergent, so it never moves the watermark.
+        assert_eq!(
+            EventRepository::get_pruned_through(user.id, &mut db.pool().into())
+                .await
+                .unwrap(),
+            None
+        );
+    }
 }
@@ -152,6 +152,16 @@ impl EventsService {
         EventRepository::parse_cursor(cursor, executor).await
     }
 
+    /// Get the highest event id deleted by retention for this user, if any.
+    /// A cursor below it can no longer be replayed; the client must resync.
+    pub async fn pruned_through<'a>(
+        &self,
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<EventCursor>, sqlx::Error> {
+        EventRepository::get_pruned_through(user_id, executor).await
+    }
+
     /// Get a list of public (`/pub/...`) events starting from a cursor position.
     /// Private events are served only through the authenticated event stream.
     ///
@@ -19,6 +19,10 @@ pub enum FileIoError {
     WritePathForbidden,
     #[error("File/folder path collision")]
     PathCollision,
+    #[error("Content is blocked")]
+    ContentBlocked,
+    #[error("The file does not match the precondition")]
+    PreconditionFailed,
 }
 
 impl From<opendal::Error> for FileIoError {
@@ -33,6 +37,8 @@ impl From<opendal::Error> for FileIoError {
                 LayerDomainError::WritePathForbidden => FileIoError::WritePathForbidden,
                 LayerDomainError::DiskSpaceQuotaExceeded => FileIoError::DiskSpaceQuotaExceeded,
                 LayerDomainError::PathCollision => FileIoError::PathCollision,
+                LayerDomainError::ContentBlocked => FileIoError::ContentBlocked,
+                LayerDomainError::PreconditionFailed => FileIoError::PreconditionFailed,
             };
         }
         match e.kind() {
@@ -19,7 +19,10 @@ use futures_util::StreamExt;
 use opendal::Buffer;
 use std::path::Path;
 
-use super::super::{FileIoError, FileStream, OpendalService, WriteStreamError};
+use super::super::{
+    write_finalization_layer::WritePrecondition, FileIoError, FileStream, OpendalService,
+    ReadCache, WriteStreamError,
+};
 
 /// The file service creates an abstraction layer over the SqlDb and OpenDAL services.
 /// This way, files can be managed in a unified way.
@@ -43,6 +46,7 @@ impl FileService {
         db: SqlDb,
         events_service: EventsService,
         user_service: crate::services::user_service::UserService,
+        content_block_service: crate::services::content_block_service::ContentBlockService,
     ) -> Result<Self, FileIoError> {
         let opendal_service = OpendalService::new_from_config(
             &config.storage,
@@ -50,6 +54,7 @@ impl FileService {
             db.clone(),
             events_service,
             user_service,
+            content_block_service,
         )?;
         Ok(Self::new(opendal_service, db))
     }
@@ -67,12 +72,18 @@ impl FileService {
         }
     }
 
-    /// Get the content of a file as a stream of bytes.
-    /// The stream is chunked.
-    /// Errors if the file does not exist.
-    pub async fn get_stream(&self, path: &EntryPath) -> Result<FileStream, FileIoError> {
-        let stream: FileStream = self.opendal.get_stream(path).await?;
-        Ok(stream)
+    /// Get the content of the file of `entry` as a stream of bytes, through the
+    /// read cache if there is one. Errors if the file does not exist.
+    pub async fn get_entry_stream(&self, entry: &EntryEntity) -> Result<FileStream, FileIoError> {
+        self.opendal
+            .get_cached_stream(&entry.path, &entry.content_hash, entry.content_length)
+            .await
+    }
+
+    /// Serve file reads through `read_cache`.
+    pub fn with_read_cache(mut self, read_cache: ReadCache) -> Self {
+        self.opendal = self.opendal.with_read_cache(read_cache);
+        self
     }
 
     /// Write a file to the database and storage depending on the selected target location.
@@ -89,6 +100,29 @@ impl FileService {
         }
     }
 
+    /// Write a file like [`Self::write_stream`] if its current version meets
+    /// `precondition`, checked atomically with the write.
+    /// Fails with [`FileIoError::PreconditionFailed`] otherwise.
+    ///
+    /// The precondition is checked once before the body is read too, so a stale
+    /// upload is rejected without streaming it.
+    pub async fn write_stream_if(
+        &self,
+        path: &EntryPath,
+        precondition: WritePrecondition,
+        stream: impl Stream<Item = Result<Bytes, WriteStreamError>> + Unpin + Send,
+    ) -> Result<EntryEntity, FileIoError> {
+        let current = match EntryRepository::get_by_path(path, &mut self.db.pool().into()).await {
+            Ok(entry) => Some(entry.content_hash),
+            Err(sqlx::Error::RowNotFound) => None,
+            Err(e) => return Err(e.into()),
+        };
+        if !precondition.is_met(current.as_ref()) {
+            return Err(FileIoError::PreconditionFailed);
+        }
+        precondition.scope(self.write_stream(path, stream)).await
+    }
+
     /// Delete a file.
     pub async fn delete(&self, path: &EntryPath) -> Result<(), FileIoError> {
         if !self.opendal.exists(path).await? {
@@ -98,6 +132,22 @@ impl FileService {
         Ok(())
     }
 
+    /// Delete a file like [`Self::delete`] if its current version meets
+    /// `precondition`, checked atomically with the delete.
+    /// Fails with [`FileIoError::PreconditionFailed`] otherwise.
+    pub async fn delete_if(
+        &self,
+        path: &EntryPath,
+        precondition: WritePrecondition,
+    ) -> Result<(), FileIoError> {
+        match precondition.scope(self.delete(path)).await {
+            Err(FileIoError::NotFound) if !precondition.is_met(None) => {
+                Err(FileIoError::PreconditionFailed)
+            }
+            result => result,
+        }
+    }
+
     /// Delete a file bypassing write-path restrictions.
     /// Used by the admin `/webdav` REST delete route; the `/dav` WebDAV handler
     /// already uses `admin_operator` directly and does not need this.
@@ -117,6 +167,14 @@ impl FileService {
         Ok(Self::new(opendal_service, context.sql_db.clone()))
     }
 
+    /// Get the content of a file as a stream of bytes.
+    /// The stream is chunked.
+    /// Errors if the file does not exist.
+    pub async fn get_stream(&self, path: &EntryPath) -> Result<FileStream, FileIoError> {
+        let stream: FileStream = self.opendal.get_stream(path).await?;
+        Ok(stream)
+    }
+
     /// Get the content of a file as bytes.
     /// Errors if the file does not exist.
     pub async fn get(&self, path: &EntryPath) -> Result<Bytes, FileIoError> {
@@ -275,6 +333,47 @@ mod tests {
         assert_eq!(content.as_ref(), test_data);
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_failed_precondition_keeps_file_system_content() {
+        let context = AppContext::test_with_config(|c| {
+            c.storage.backend = crate::storage_config::StorageConfigToml::FileSystem
+        })
+        .await;
+        let file_service = FileService::new_from_context(&context).unwrap();
+        let pubkey = pubky_common::crypto::Keypair::random().public_key();
+        context.user_service.create(&pubkey).await.unwrap();
+        let path = EntryPath::new(pubkey, StoragePath::new("/pub/app/file.txt").unwrap());
+        let stream = |data: &'static [u8]| futures_util::stream::iter(vec![Ok(Bytes::from(data))]);
+
+        let v1 = file_service
+            .write_stream(&path, stream(b"v1"))
+            .await
+            .unwrap();
+
+        // Rejected before the body is streamed.
+        let stale = WritePrecondition::Matches(pubky_common::crypto::hash(b"v0"));
+        let result = file_service
+            .write_stream_if(&path, stale, stream(b"v2"))
+            .await;
+        assert!(matches!(result, Err(FileIoError::PreconditionFailed)));
+
+        // Rejected at finalization, after the body was streamed.
+        let result = WritePrecondition::Absent
+            .scope(file_service.write_stream(&path, stream(b"v2")))
+            .await;
+        assert!(matches!(result, Err(FileIoError::PreconditionFailed)));
+
+        assert_eq!(file_service.get(&path).await.unwrap().as_ref(), b"v1");
+        let entry = file_service
+            .get_info(&path, &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(entry.content_hash, v1.content_hash);
+        let staged = std::fs::read_dir(context.data_dir.path().join("data/files.tmp")).unwrap();
+        assert_eq!(staged.count(), 0, "the rejected upload is not left behind");
+    }
+
     #[tokio::test]
     #[pubky_test_utils::test]
     async fn test_data_usage_update_basic() {
@@ -12,4 +12,8 @@ pub enum LayerDomainError {
     DiskSpaceQuotaExceeded,
     #[error("path_collision")]
     PathCollision,
+    #[error("content_blocked")]
+    ContentBlocked,
+    #[error("precondition_failed")]
+    PreconditionFailed,
 }
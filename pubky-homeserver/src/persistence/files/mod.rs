@@ -1,6 +1,6 @@
 //! File storage and associated middleware.
 //!
-//! Blob I/O is handled by [`opendal`] (supporting filesystem, in-memory, and GCS
+//! Blob I/O is handled by [`opendal`] (supporting filesystem, in-memory, GCS and S3
 //! backends). Operations pass through a layered middleware stack (outermost first):
 //!
 //! 1. **[`write_path_layer`]** — enforces per-user allowed write paths (outermost, runs first).
@@ -8,6 +8,9 @@
 //!    entry metadata, events, and quota accounting around backend writes.
 //! 3. **OpenDAL base** — physical storage I/O.
 //!
+//! Public file reads can be served from a local read cache in front of the stack,
+//! keyed by content hash.
+//!
 //! [`file`] provides the high-level [`FileService`](file::file_service::FileService)
 //! used by route handlers.
 
@@ -24,3 +27,5 @@ pub(crate) use file::file_metadata::{FileMetadata, FileMetadataBuilder};
 pub use file::file_service::FileService;
 pub use file::file_stream_type::FileStream;
 pub use opendal::opendal_service::OpendalService;
+pub(crate) use opendal::opendal_service::{build_backend_operator, CHUNK_SIZE};
+pub use opendal::read_cache::ReadCache;
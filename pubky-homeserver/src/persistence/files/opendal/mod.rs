@@ -1,9 +1,12 @@
 //! OpenDAL-based storage backend.
 //!
 //! [`OpendalService`](opendal_service::OpendalService) configures the appropriate
-//! storage operator (filesystem, in-memory, or GCS) based on the server's
+//! storage operator (filesystem, in-memory, GCS or S3) based on the server's
 //! [`StorageConfig`](crate::data_directory::storage_config::StorageConfig).
+//! An optional [`ReadCache`](read_cache::ReadCache) keeps recently read files
+//! on the local disk or in memory.
 
 pub mod opendal_service;
 #[cfg(test)]
 pub(crate) mod opendal_test_operators;
+pub mod read_cache;
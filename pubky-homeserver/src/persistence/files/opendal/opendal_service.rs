@@ -10,7 +10,7 @@ use crate::{
         },
         sql::SqlDb,
     },
-    services::user_service::UserService,
+    services::{content_block_service::ContentBlockService, user_service::UserService},
     shared::webdav::EntryPath,
     storage_config::{StorageConfigToml, StorageToml},
 };
@@ -19,34 +19,34 @@ use futures_util::{stream::StreamExt, Stream};
 #[cfg(test)]
 use opendal::Buffer;
 use opendal::Operator;
+use pubky_common::crypto::Hash;
 
 use super::super::{FileIoError, FileMetadata, FileMetadataBuilder, FileStream, WriteStreamError};
+use super::read_cache::ReadCache;
 
-/// Build storage operators with one transactional finalization layer and an
-/// app-facing operator that additionally enforces write paths and collisions.
+/// Build a bare operator for a storage backend, without any layer.
 ///
-/// Both operators share the same underlying storage backend, which is
-/// important for backends like `InMemory` where separate instances would
-/// have independent data.
-pub fn build_storage_operators(
-    storage_config: &StorageToml,
+/// Writes through it don't touch the database.
+pub(crate) fn build_backend_operator(
+    backend: &StorageConfigToml,
     data_directory: &Path,
-    sql_db: SqlDb,
-    events_service: EventsService,
-    user_service: UserService,
-) -> Result<(Operator, Operator), FileIoError> {
-    let backend_operator = match &storage_config.backend {
+) -> Result<Operator, FileIoError> {
+    Ok(match backend {
         StorageConfigToml::FileSystem => {
-            let files_dir = match data_directory.join("data/files").to_str() {
-                Some(path) => path.to_string(),
-                None => {
-                    return Err(FileIoError::OpenDAL(opendal::Error::new(
-                        opendal::ErrorKind::Unexpected,
-                        "Invalid path",
-                    )))
-                }
+            let files_dir = data_directory.join("data/files");
+            let staging_dir = data_directory.join("data/files.tmp");
+            let (Some(files_dir), Some(staging_dir)) = (files_dir.to_str(), staging_dir.to_str())
+            else {
+                return Err(FileIoError::OpenDAL(opendal::Error::new(
+                    opendal::ErrorKind::Unexpected,
+                    "Invalid path",
+                )));
             };
-            let builder = opendal::services::Fs::default().root(files_dir.as_str());
+            // Write to a temporary file and rename it on close, so a write rejected
+            // at finalization never replaces the stored file.
+            let builder = opendal::services::Fs::default()
+                .root(files_dir)
+                .atomic_write_dir(staging_dir);
             opendal::Operator::new(builder)?.finish()
         }
         #[cfg(feature = "storage-gcs")]
@@ -58,46 +58,73 @@ pub fn build_storage_operators(
             let builder = config.to_builder()?;
             opendal::Operator::new(builder)?.finish()
         }
+        #[cfg(feature = "storage-s3")]
+        StorageConfigToml::S3(config) => {
+            tracing::info!("Store files in an S3 bucket: {}", config.bucket);
+            let builder = config.to_builder()?;
+            opendal::Operator::new(builder)?.finish()
+        }
         #[cfg(any(feature = "storage-memory", test))]
         StorageConfigToml::InMemory => {
             tracing::info!("Store files in memory");
             let builder = opendal::services::Memory::default();
             opendal::Operator::new(builder)?.finish()
         }
-    };
+    })
+}
+
+/// Build storage operators with one transactional finalization layer and an
+/// app-facing operator that additionally enforces write paths and collisions.
+///
+/// Both operators share the same underlying storage backend, which is
+/// important for backends like `InMemory` where separate instances would
+/// have independent data. The bare backend operator is returned as well.
+pub fn build_storage_operators(
+    storage_config: &StorageToml,
+    data_directory: &Path,
+    sql_db: SqlDb,
+    events_service: EventsService,
+    user_service: UserService,
+    content_block_service: ContentBlockService,
+) -> Result<(Operator, Operator, Operator), FileIoError> {
+    let backend_operator = build_backend_operator(&storage_config.backend, data_directory)?;
 
     // Collision checks apply only to app-facing mutations, so each operator
     // needs its own finalization layer.
     let admin_operator = backend_operator.clone().layer(WriteFinalizationLayer::new(
         user_service.clone(),
+        content_block_service.clone(),
         sql_db.clone(),
         events_service.clone(),
         storage_config.default_quota_mb,
         false,
     ));
     let operator = backend_operator
+        .clone()
         .layer(WriteFinalizationLayer::new(
             user_service.clone(),
+            content_block_service,
             sql_db,
             events_service,
             storage_config.default_quota_mb,
             true,
         ))
         .layer(WritePathLayer::new(user_service));
-    Ok((operator, admin_operator))
+    Ok((operator, admin_operator, backend_operator))
 }
 
 /// Build the storage operators from an `AppContext` (test-only convenience).
 #[cfg(test)]
 pub fn build_storage_operators_from_context(
     context: &AppContext,
-) -> Result<(Operator, Operator), FileIoError> {
+) -> Result<(Operator, Operator, Operator), FileIoError> {
     build_storage_operators(
         &context.config_toml.storage,
         context.data_dir.path(),
         context.sql_db.clone(),
         context.events_service.clone(),
         context.user_service.clone(),
+        context.content_block_service.clone(),
     )
 }
 
@@ -106,7 +133,7 @@ pub fn build_storage_operators_from_context(
 /// Important: Not all opendal providers will respect this chunk size.
 /// For example, Google Cloud Buckets will deliver chunks anything from
 /// 200B to 16KB but max CHUNK_SIZE.
-const CHUNK_SIZE: usize = 16 * 1024;
+pub(crate) const CHUNK_SIZE: usize = 16 * 1024;
 
 /// The service to write and read files to and from the configured opendal storage.
 #[derive(Debug, Clone)]
@@ -115,6 +142,10 @@ pub struct OpendalService {
     pub(crate) operator: Operator,
     /// Operator without `WritePathLayer` (for admin operations that bypass write-path restrictions).
     pub(crate) admin_operator: Operator,
+    /// Operator without any layer. Writes don't touch the database (for restoring backups).
+    pub(crate) backend_operator: Operator,
+    /// Local copies of recently read files, for remote backends.
+    pub(crate) read_cache: Option<ReadCache>,
 }
 
 impl OpendalService {
@@ -124,20 +155,30 @@ impl OpendalService {
         sql_db: SqlDb,
         events_service: EventsService,
         user_service: UserService,
+        content_block_service: ContentBlockService,
     ) -> Result<Self, FileIoError> {
-        let (operator, admin_operator) = build_storage_operators(
+        let (operator, admin_operator, backend_operator) = build_storage_operators(
             storage_config,
             data_directory,
             sql_db,
             events_service,
             user_service,
+            content_block_service,
         )?;
         Ok(Self {
             operator,
             admin_operator,
+            backend_operator,
+            read_cache: None,
         })
     }
 
+    /// Serve reads of complete files through `read_cache`.
+    pub fn with_read_cache(mut self, read_cache: ReadCache) -> Self {
+        self.read_cache = Some(read_cache);
+        self
+    }
+
     /// Delete a file.
     /// Deleting a non-existing file will NOT return an error.
     pub async fn delete(&self, path: &EntryPath) -> Result<(), FileIoError> {
@@ -154,9 +195,28 @@ impl OpendalService {
     pub async fn write_stream(
         &self,
         path: &EntryPath,
+        stream: impl Stream<Item = Result<Bytes, WriteStreamError>> + Unpin + Send,
+    ) -> Result<FileMetadata, FileIoError> {
+        Self::write_stream_with(&self.operator, path, stream).await
+    }
+
+    /// Write a stream straight to the storage backend, without creating an entry,
+    /// an event or touching the used bytes.
+    /// Only for restoring backups, which restore the database separately.
+    pub(crate) async fn write_stream_untracked(
+        &self,
+        path: &EntryPath,
+        stream: impl Stream<Item = Result<Bytes, WriteStreamError>> + Unpin + Send,
+    ) -> Result<FileMetadata, FileIoError> {
+        Self::write_stream_with(&self.backend_operator, path, stream).await
+    }
+
+    async fn write_stream_with(
+        operator: &Operator,
+        path: &EntryPath,
         mut stream: impl Stream<Item = Result<Bytes, WriteStreamError>> + Unpin + Send,
     ) -> Result<FileMetadata, FileIoError> {
-        let mut writer = self.operator.writer(path.as_str()).await?;
+        let mut writer = operator.writer(path.as_str()).await?;
         let mut metadata_builder = FileMetadataBuilder::default();
         metadata_builder.guess_mime_type_from_path(path.path().as_str());
 
@@ -201,19 +261,69 @@ impl OpendalService {
         Ok(self.get_stream_inner(path).await?)
     }
 
+    /// Get the content of a file with the given hash and length as a stream of bytes.
+    /// Served from the read cache if there is one and it has the content.
+    pub async fn get_cached_stream(
+        &self,
+        path: &EntryPath,
+        content_hash: &Hash,
+        content_length: u64,
+    ) -> Result<FileStream, FileIoError> {
+        let Some(cache) = self
+            .read_cache
+            .as_ref()
+            .filter(|cache| cache.accepts(content_length))
+        else {
+            return self.get_stream(path).await;
+        };
+        if let Some(stream) = cache.get(content_hash).await? {
+            return Ok(stream);
+        }
+        let stream = self.get_stream(path).await?;
+        Ok(cache.fill(*content_hash, content_length, stream))
+    }
+
     /// Check if a file exists.
     pub async fn exists(&self, path: &EntryPath) -> Result<bool, opendal::Error> {
         self.operator.exists(path.as_str()).await
     }
+
+    /// Size of a file in the storage, `None` if it does not exist.
+    pub async fn content_length(&self, path: &EntryPath) -> Result<Option<u64>, opendal::Error> {
+        match self.operator.stat(path.as_str()).await {
+            Ok(metadata) => Ok(Some(metadata.content_length())),
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Read a stored file and compute its metadata like [`Self::write_stream`] does.
+    /// `None` if the file does not exist.
+    pub async fn rehash(&self, path: &EntryPath) -> Result<Option<FileMetadata>, FileIoError> {
+        let mut stream = match self.get_stream(path).await {
+            Ok(stream) => stream,
+            Err(FileIoError::NotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut metadata_builder = FileMetadataBuilder::default();
+        metadata_builder.guess_mime_type_from_path(path.path().as_str());
+        while let Some(chunk) = stream.next().await {
+            metadata_builder.update(&chunk?);
+        }
+        Ok(Some(metadata_builder.finalize()))
+    }
 }
 
 #[cfg(test)]
 impl OpendalService {
     pub fn new(context: &AppContext) -> Result<Self, FileIoError> {
-        let (operator, admin_operator) = build_storage_operators_from_context(context)?;
+        let (operator, admin_operator, backend_operator) =
+            build_storage_operators_from_context(context)?;
         Ok(Self {
             operator,
             admin_operator,
+            backend_operator,
+            read_cache: None,
         })
     }
 
@@ -222,7 +332,9 @@ impl OpendalService {
     pub fn new_from_operator(operator: Operator) -> Self {
         Self {
             admin_operator: operator.clone(),
+            backend_operator: operator.clone(),
             operator,
+            read_cache: None,
         }
     }
 
@@ -279,6 +391,40 @@ mod tests {
         assert!(!service.exists(&path).await.unwrap());
     }
 
+    /// The homeserver stores its files in an S3 bucket. Needs an S3 stand-in,
+    /// see `get_s3_config`.
+    #[cfg(feature = "storage-s3")]
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_build_storage_operator_from_config_s3() {
+        use crate::persistence::files::opendal::opendal_test_operators::{
+            get_s3_config, get_s3_operator,
+        };
+
+        let Some(s3_config) = get_s3_config(true) else {
+            // S3 not configured. Skip test.
+            return;
+        };
+        let root = s3_config.root.clone().unwrap();
+        let context = AppContext::test_with_config(|c| {
+            c.storage.backend = StorageConfigToml::S3(s3_config);
+        })
+        .await;
+
+        let service =
+            OpendalService::new(&context).expect("Failed to create OpenDAL service for testing");
+        let pubky = pubky_common::crypto::Keypair::random().public_key();
+        context.user_service.create(&pubky).await.unwrap();
+        let path = EntryPath::new(pubky, StoragePath::new("/test.txt").unwrap());
+        service.write(&path, b"hello".to_vec()).await.unwrap();
+        assert_eq!(service.get(&path).await.unwrap().as_ref(), b"hello");
+        service.delete(&path).await.unwrap();
+        assert!(!service.exists(&path).await.unwrap());
+
+        let base_operator = get_s3_operator(false).unwrap().unwrap();
+        base_operator.remove_all(&root).await.unwrap();
+    }
+
     /// Make sure that the OpendalService returns a DiskSpaceQuotaExceeded error if the user has exceeded the quota.
     /// This is important because write finalization returns a RateLimited error if the user has exceeded the quota.
     #[tokio::test]
@@ -299,7 +445,7 @@ mod tests {
     }
 
     /// Test the chunked reading of a file.
-    #[tokio::test]
+    #[tokio::test(flavor = "multi_thread")]
     #[pubky_test_utils::test]
     async fn test_get_content_chunked() {
         let operators = OpendalTestOperators::new();
@@ -361,7 +507,7 @@ mod tests {
         }
     }
 
-    #[tokio::test]
+    #[tokio::test(flavor = "multi_thread")]
     #[pubky_test_utils::test]
     async fn test_write_content_stream() {
         let operators = OpendalTestOperators::new();
@@ -11,21 +11,28 @@ use opendal::Operator;
 use tempfile::TempDir;
 use uuid::Uuid;
 
+#[cfg(feature = "storage-s3")]
+use crate::storage_config::S3Config;
+
 /// A provider of operators for testing.
 ///
 /// Provides:
 /// - A filesystem operator
 /// - A memory operator
 /// - A GCS operator (if the environment variables are set)
+/// - An S3 operator (if the environment variables are set)
 ///
 /// The GCS operator is only available if the required environment variables are set.
 /// GCS environment variables:
 /// - GOOGLE_APPLICATION_CREDENTIALS: The path to the GCS credentials file.
 /// - GCS_BUCKET: The name of the GCS bucket.
 ///
+/// The S3 operator is only available if the required environment variables are set.
+/// See [get_s3_operator] for the S3 environment variables.
+///
 /// Example:
 /// ```ignore
-/// #[tokio::test]
+/// #[tokio::test(flavor = "multi_thread")]
 /// async fn test_ensure_valid_path() {
 ///     // Iterate over all operators.
 ///     for (_scheme, operator) in OpendalTestOperators::new().operators() {
@@ -43,10 +50,14 @@ pub struct OpendalTestOperators {
     #[allow(dead_code)]
     fs_tmp_dir: Arc<TempDir>,
     pub gcs_operator: Option<Operator>,
+    pub s3_operator: Option<Operator>,
     pub memory_operator: Operator,
     #[allow(dead_code)]
     /// Cleaner will remove the gcp bucket when dropped
-    gcp_cleaner: Option<Arc<AsyncDropper<OpendalGcpCleaner>>>,
+    gcp_cleaner: Option<Arc<AsyncDropper<OpendalBucketCleaner>>>,
+    #[allow(dead_code)]
+    /// Cleaner will remove the s3 test directory when dropped
+    s3_cleaner: Option<Arc<AsyncDropper<OpendalBucketCleaner>>>,
 }
 
 impl OpendalTestOperators {
@@ -55,16 +66,26 @@ impl OpendalTestOperators {
         let (fs_operator, fs_tmp_dir) = get_fs_operator();
         let gcs_operator = get_gcs_operator(true).expect("GCS operator should be available");
         let gcp_cleaner = gcs_operator.as_ref().map(|operator| {
-            Arc::new(AsyncDropper::new(OpendalGcpCleaner::new(Some(
-                operator.clone(),
-            ))))
+            Arc::new(AsyncDropper::new(OpendalBucketCleaner::new(
+                Some(operator.clone()),
+                get_gcs_operator,
+            )))
+        });
+        let s3_operator = get_s3_operator(true).expect("S3 operator should be available");
+        let s3_cleaner = s3_operator.as_ref().map(|operator| {
+            Arc::new(AsyncDropper::new(OpendalBucketCleaner::new(
+                Some(operator.clone()),
+                get_s3_operator,
+            )))
         });
         Self {
             fs_operator,
             fs_tmp_dir: Arc::new(fs_tmp_dir),
             gcs_operator,
+            s3_operator,
             memory_operator: get_memory_operator(),
             gcp_cleaner,
+            s3_cleaner,
         }
     }
 
@@ -80,6 +101,9 @@ impl OpendalTestOperators {
         if let Some(gcs_operator) = &self.gcs_operator {
             operators.push((gcs_operator.info().scheme(), gcs_operator.clone()));
         }
+        if let Some(s3_operator) = &self.s3_operator {
+            operators.push((s3_operator.info().scheme(), s3_operator.clone()));
+        }
         operators
     }
 
@@ -88,49 +112,63 @@ impl OpendalTestOperators {
     pub fn is_gcs_available(&self) -> bool {
         self.gcs_operator.is_some()
     }
+
+    /// Check if the S3 operator is available.
+    /// This depends on the environment variables being set. See [get_s3_operator] for more details.
+    pub fn is_s3_available(&self) -> bool {
+        self.s3_operator.is_some()
+    }
 }
 
-/// Helper struct to clean up the GCS operator after the test.
+/// Builds a bucket operator. `true` puts it in a random test directory.
+type BucketOperatorFn = fn(bool) -> anyhow::Result<Option<Operator>>;
+
+/// Helper struct to clean up a bucket operator (GCS, S3) after the test.
 /// Important: This requires the tokio::test(flavor = "multi_thread") attribute,
-/// Otherwise the test will panic when the gcp cleaner is dropped
+/// Otherwise the test will panic when the cleaner is dropped
 #[derive(Default)]
-struct OpendalGcpCleaner {
-    pub gcs_operator: Option<Operator>,
+struct OpendalBucketCleaner {
+    pub operator: Option<Operator>,
+    /// Builds the operator of the bucket root.
+    pub base_operator: Option<BucketOperatorFn>,
 }
 
-impl OpendalGcpCleaner {
-    pub fn new(gcs_operator: Option<Operator>) -> Self {
-        Self { gcs_operator }
+impl OpendalBucketCleaner {
+    pub fn new(operator: Option<Operator>, base_operator: BucketOperatorFn) -> Self {
+        Self {
+            operator,
+            base_operator: Some(base_operator),
+        }
     }
 }
 
 #[async_trait]
-impl AsyncDrop for OpendalGcpCleaner {
+impl AsyncDrop for OpendalBucketCleaner {
     async fn async_drop(&mut self) {
-        let gcs_operator = match &self.gcs_operator {
-            Some(operator) => operator,
-            None => return,
+        let (operator, base_operator) = match (&self.operator, self.base_operator) {
+            (Some(operator), Some(base_operator)) => (operator, base_operator),
+            _ => return,
         };
-        // Delete all files in the GCS root directory that are related to the test.
-        let test_root_dir = gcs_operator.info().root();
-        let base_gcs_operator = match get_gcs_operator(false) {
+        // Delete all files in the bucket root directory that are related to the test.
+        let test_root_dir = operator.info().root();
+        let base_bucket_operator = match base_operator(false) {
             Ok(Some(operator)) => operator,
             Ok(None) => {
                 return;
             }
             Err(e) => {
                 println!(
-                    "Failed to cleanup the GCP test bucket. Directory: {}, Error: {}",
+                    "Failed to cleanup the test bucket. Directory: {}, Error: {}",
                     test_root_dir, e
                 );
                 return;
             }
         };
-        match base_gcs_operator.remove_all(&test_root_dir).await {
+        match base_bucket_operator.remove_all(&test_root_dir).await {
             Ok(_) => {}
             Err(e) => {
                 println!(
-                    "Failed to cleanup the GCP test bucket. Directory: {}, Error: {}",
+                    "Failed to cleanup the test bucket. Directory: {}, Error: {}",
                     test_root_dir, e
                 );
             }
@@ -175,6 +213,50 @@ pub(crate) fn get_gcs_operator(test_root_dir: bool) -> anyhow::Result<Option<Ope
     Ok(Some(operator))
 }
 
+/// The S3 test config if the required environment variables are set.
+/// Works with any S3-compatible stand-in, e.g. MinIO or `moto_server`.
+/// S3 environment variables:
+/// - S3_ENDPOINT: The endpoint of the S3 API, e.g. `http://localhost:9000`.
+/// - S3_BUCKET: The name of the S3 bucket. The bucket must exist already.
+/// - S3_REGION: The region of the bucket. Optional, defaults to `us-east-1`.
+/// - AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY: The credentials.
+///
+/// The bucket is addressed path-style, like most stand-ins need.
+///
+/// Set `test_root_dir` to true to create a random directory that the operator
+/// lives in. This is useful to avoid conflicts with other tests.
+#[cfg(feature = "storage-s3")]
+pub(crate) fn get_s3_config(test_root_dir: bool) -> Option<S3Config> {
+    let endpoint = std::env::var("S3_ENDPOINT").ok()?;
+    let bucket = std::env::var("S3_BUCKET").ok()?;
+    Some(S3Config {
+        bucket,
+        endpoint: Some(endpoint),
+        region: std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+        path_style: true,
+        root: test_root_dir.then(|| format!("test_{}", Uuid::new_v4())),
+        access_key_id: None,
+        secret_access_key: None,
+    })
+}
+
+/// Creates an S3 operator if the required environment variables are set.
+/// See [get_s3_config] for the environment variables.
+#[cfg(feature = "storage-s3")]
+pub(crate) fn get_s3_operator(test_root_dir: bool) -> anyhow::Result<Option<Operator>> {
+    let Some(config) = get_s3_config(test_root_dir) else {
+        return Ok(None);
+    };
+    let operator = opendal::Operator::new(config.to_builder()?)?.finish();
+    Ok(Some(operator))
+}
+
+/// Without the `storage-s3` feature, there is no S3 operator.
+#[cfg(not(feature = "storage-s3"))]
+pub(crate) fn get_s3_operator(_test_root_dir: bool) -> anyhow::Result<Option<Operator>> {
+    Ok(None)
+}
+
 pub(crate) fn get_memory_operator() -> Operator {
     let builder = opendal::services::Memory::default();
 
@@ -187,29 +269,33 @@ mod tests {
 
     use super::*;
 
-    #[tokio::test]
+    #[tokio::test(flavor = "multi_thread")]
     #[pubky_test_utils::test]
     async fn test_operator_test_providers() {
         let providers = OpendalTestOperators::new();
         let operators = providers.operators();
+        let mut expected = 2;
         if providers.is_gcs_available() {
-            assert!(
-                operators.len() == 3,
-                "Expected 3 operators, got {}",
-                operators.len()
-            );
+            expected += 1;
             println!("GCS operator is available"); // Log to make it clear that GCS is included in the tests.
         } else {
-            assert!(
-                operators.len() == 2,
-                "Expected 2 operators, got {}",
-                operators.len()
-            );
             println!("GCS operator is NOT available"); // Log to make it clear that GCS is NOT included in the tests.
         }
+        if providers.is_s3_available() {
+            expected += 1;
+            println!("S3 operator is available"); // Log to make it clear that S3 is included in the tests.
+        } else {
+            println!("S3 operator is NOT available"); // Log to make it clear that S3 is NOT included in the tests.
+        }
+        assert!(
+            operators.len() == expected,
+            "Expected {} operators, got {}",
+            expected,
+            operators.len()
+        );
     }
 
-    #[tokio::test]
+    #[tokio::test(flavor = "multi_thread")]
     #[pubky_test_utils::test]
     async fn test_gcs_cleanup() {
         let test_root_dir = {
@@ -230,4 +316,26 @@ mod tests {
         let exists = base_gcs_operator.exists(&test_root_dir).await.unwrap();
         assert!(!exists, "Test root directory should not exist anymore as it should have been deleted by the Drop impl");
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[pubky_test_utils::test]
+    async fn test_s3_cleanup() {
+        let test_root_dir = {
+            let operators = OpendalTestOperators::new();
+            if !operators.is_s3_available() {
+                // S3 not configured. Skip test.
+                return;
+            }
+            let s3_operator = operators.s3_operator.as_ref().unwrap().clone();
+            s3_operator
+                .write("test.txt", Buffer::from("test"))
+                .await
+                .unwrap();
+            s3_operator.info().root()
+        };
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await; // Sleep to ensure the Drop impl is executed in the background.
+        let base_s3_operator = get_s3_operator(false).unwrap().unwrap();
+        let exists = base_s3_operator.exists(&test_root_dir).await.unwrap();
+        assert!(!exists, "Test root directory should not exist anymore as it should have been deleted by the Drop impl");
+    }
 }
@@ -0,0 +1,504 @@
+//! Read-through cache for file contents in front of the storage backend.
+//!
+//! Files are keyed by their blake3 content hash, so an overwritten file simply
+//! gets a new key and the stale copy ages out. A file is only cached after it was
+//! read completely from the backend and its hash matched. The least recently read
+//! files are evicted once the cache exceeds its size.
+//!
+//! Disk copies are written once per hash to a temporary key and renamed into place,
+//! and their hash is checked again when they are read, so a damaged copy is evicted
+//! instead of served.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_util::Stream;
+use lru::LruCache;
+use opendal::Operator;
+use pubky_common::crypto::Hash;
+
+use super::opendal_service::CHUNK_SIZE;
+use crate::observability::Metrics;
+use crate::persistence::files::{FileIoError, FileStream};
+use crate::storage_config::{StorageCacheBackend, StorageCacheToml};
+
+/// Where the cached copies are kept.
+#[derive(Debug)]
+enum CacheStore {
+    /// One file per content hash on the local disk.
+    Disk(Operator),
+    /// In the index itself.
+    Memory,
+}
+
+#[derive(Debug)]
+struct CachedFile {
+    length: u64,
+    /// The content, for the memory store.
+    content: Option<Bytes>,
+}
+
+#[derive(Debug)]
+struct CacheIndex {
+    files: LruCache<Hash, CachedFile>,
+    used_bytes: u64,
+    /// Hashes currently being written to the disk store.
+    writing: HashSet<Hash>,
+}
+
+#[derive(Debug)]
+struct ReadCacheInner {
+    store: CacheStore,
+    size_bytes: u64,
+    max_file_size_bytes: u64,
+    index: Mutex<CacheIndex>,
+    metrics: Metrics,
+}
+
+/// Read cache for files, keyed by content hash. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct ReadCache {
+    inner: Arc<ReadCacheInner>,
+}
+
+impl ReadCache {
+    /// Create the cache. The disk store lives in `data/cache` of the data
+    /// directory and is emptied, because the index is only kept in memory.
+    pub fn new(
+        config: &StorageCacheToml,
+        data_directory: &Path,
+        metrics: Metrics,
+    ) -> Result<Self, FileIoError> {
+        let store = match config.backend {
+            StorageCacheBackend::FileSystem => {
+                let cache_dir = data_directory.join("data/cache");
+                if cache_dir.exists() {
+                    std::fs::remove_dir_all(&cache_dir)?;
+                }
+                std::fs::create_dir_all(&cache_dir)?;
+                let root = cache_dir.to_str().ok_or_else(|| {
+                    FileIoError::OpenDAL(opendal::Error::new(
+                        opendal::ErrorKind::Unexpected,
+                        "Invalid path",
+                    ))
+                })?;
+                let builder = opendal::services::Fs::default().root(root);
+                CacheStore::Disk(Operator::new(builder)?.finish())
+            }
+            StorageCacheBackend::InMemory => CacheStore::Memory,
+        };
+        tracing::info!(
+            "Cache files up to {} MB in a {} MB read cache",
+            config.max_file_size_mb,
+            config.size_mb
+        );
+        Ok(Self {
+            inner: Arc::new(ReadCacheInner {
+                store,
+                size_bytes: config.size_bytes(),
+                max_file_size_bytes: config.max_file_size_bytes(),
+                index: Mutex::new(CacheIndex {
+                    files: LruCache::unbounded(),
+                    used_bytes: 0,
+                    writing: HashSet::new(),
+                }),
+                metrics,
+            }),
+        })
+    }
+
+    /// Whether a file of `length` bytes is cached at all.
+    pub fn accepts(&self, length: u64) -> bool {
+        length <= self.inner.max_file_size_bytes && length <= self.inner.size_bytes
+    }
+
+    /// The cached content of `hash`, recording a hit or a miss.
+    pub async fn get(&self, hash: &Hash) -> Result<Option<FileStream>, FileIoError> {
+        let content = {
+            let mut index = self.lock_index();
+            index.files.get(hash).map(|file| file.content.clone())
+        };
+        let stream = match (content, &self.inner.store) {
+            (None, _) => None,
+            (Some(Some(content)), _) => Some(memory_stream(content)),
+            (Some(None), CacheStore::Memory) => None,
+            (Some(None), CacheStore::Disk(operator)) => {
+                match operator.read(&hash.to_hex()).await {
+                    Ok(content) => {
+                        let content = content.to_bytes();
+                        if pubky_common::crypto::hash(&content) == *hash {
+                            Some(memory_stream(content))
+                        } else {
+                            tracing::warn!("Evicting damaged read cache file {hash}");
+                            self.remove(hash);
+                            Self::delete_from_disk(operator, *hash);
+                            None
+                        }
+                    }
+                    Err(e) if e.kind() == opendal::ErrorKind::NotFound => {
+                        // Evicted in the meantime.
+                        self.remove(hash);
+                        None
+                    }
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        };
+        match stream {
+            Some(_) => self.inner.metrics.record_storage_cache_hit(),
+            None => self.inner.metrics.record_storage_cache_miss(),
+        }
+        Ok(stream)
+    }
+
+    /// Pass `stream`, the content of `hash` read from the backend, through and
+    /// cache it once it was read completely.
+    pub fn fill(&self, hash: Hash, length: u64, stream: FileStream) -> FileStream {
+        Box::new(CacheFillStream {
+            inner: stream,
+            cache: self.clone(),
+            hash,
+            length,
+            buffer: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// Whether the content of `hash` is cached.
+    #[cfg(test)]
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.lock_index().files.contains(hash)
+    }
+
+    fn lock_index(&self) -> std::sync::MutexGuard<'_, CacheIndex> {
+        self.inner
+            .index
+            .lock()
+            .expect("Read cache index lock poisoned")
+    }
+
+    /// Store a verified copy of `hash`.
+    fn insert(&self, hash: Hash, content: Bytes) {
+        {
+            let mut index = self.lock_index();
+            if index.files.contains(&hash) || !index.writing.insert(hash) {
+                // Already cached or written by a concurrent reader.
+                return;
+            }
+        }
+        let length = content.len() as u64;
+        match &self.inner.store {
+            CacheStore::Memory => self.index(
+                hash,
+                CachedFile {
+                    length,
+                    content: Some(content),
+                },
+            ),
+            CacheStore::Disk(operator) => {
+                let operator = operator.clone();
+                let cache = self.clone();
+                tokio::spawn(async move {
+                    let key = hash.to_hex();
+                    let temp_key = format!("{key}.tmp");
+                    let written = async {
+                        operator.write(&temp_key, content).await?;
+                        operator.rename(&temp_key, &key).await
+                    }
+                    .await;
+                    cache.lock_index().writing.remove(&hash);
+                    match written {
+                        Ok(()) => cache.index(
+                            hash,
+                            CachedFile {
+                                length,
+                                content: None,
+                            },
+                        ),
+                        Err(e) => {
+                            tracing::warn!("Failed to write to the read cache: {e}");
+                            let _ = operator.delete(&temp_key).await;
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    /// Add a stored copy to the index and evict the least recently read files
+    /// beyond the cache size.
+    fn index(&self, hash: Hash, file: CachedFile) {
+        let mut evicted = Vec::new();
+        {
+            let mut index = self.lock_index();
+            index.used_bytes += file.length;
+            if let Some(replaced) = index.files.put(hash, file) {
+                index.used_bytes -= replaced.length;
+            }
+            while index.used_bytes > self.inner.size_bytes {
+                let Some((hash, file)) = index.files.pop_lru() else {
+                    break;
+                };
+                index.used_bytes -= file.length;
+                evicted.push(hash);
+            }
+        }
+        if let CacheStore::Disk(operator) = &self.inner.store {
+            for hash in evicted {
+                Self::delete_from_disk(operator, hash);
+            }
+        }
+    }
+
+    fn delete_from_disk(operator: &Operator, hash: Hash) {
+        let operator = operator.clone();
+        tokio::spawn(async move {
+            if let Err(e) = operator.delete(&hash.to_hex()).await {
+                tracing::warn!("Failed to delete from the read cache: {e}");
+            }
+        });
+    }
+
+    fn remove(&self, hash: &Hash) {
+        let mut index = self.lock_index();
+        if let Some(file) = index.files.pop(hash) {
+            index.used_bytes -= file.length;
+        }
+    }
+}
+
+/// Stream `content` in chunks like the backend would.
+fn memory_stream(content: Bytes) -> FileStream {
+    let chunks: Vec<Result<Bytes, std::io::Error>> = (0..content.len())
+        .step_by(CHUNK_SIZE)
+        .map(|start| Ok(content.slice(start..(start + CHUNK_SIZE).min(content.len()))))
+        .collect();
+    Box::new(futures_util::stream::iter(chunks))
+}
+
+/// Passes a backend stream through and caches its content at the end.
+/// Nothing is cached if the stream fails, is dropped early or doesn't match the hash.
+struct CacheFillStream {
+    inner: FileStream,
+    cache: ReadCache,
+    hash: Hash,
+    length: u64,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl Stream for CacheFillStream {
+    type Item = Result<Bytes, std::io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let polled = Pin::new(&mut this.inner).poll_next(cx);
+        if this.done {
+            return polled;
+        }
+        match &polled {
+            Poll::Ready(Some(Ok(chunk))) => {
+                if (this.buffer.len() + chunk.len()) as u64 > this.length {
+                    this.done = true;
+                    this.buffer = Vec::new();
+                } else {
+                    this.buffer.extend_from_slice(chunk);
+                }
+            }
+            Poll::Ready(Some(Err(_))) => {
+                this.done = true;
+                this.buffer = Vec::new();
+            }
+            Poll::Ready(None) => {
+                this.done = true;
+                let content = Bytes::from(std::mem::take(&mut this.buffer));
+                if content.len() as u64 == this.length
+                    && pubky_common::crypto::hash(&content) == this.hash
+                {
+                    this.cache.insert(this.hash, content);
+                }
+            }
+            Poll::Pending => {}
+        }
+        polled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+
+    fn config(backend: StorageCacheBackend) -> StorageCacheToml {
+        StorageCacheToml {
+            backend,
+            size_mb: 1,
+            max_file_size_mb: 1,
+        }
+    }
+
+    fn content(byte: u8, length: usize) -> (Hash, Bytes) {
+        let content = Bytes::from(vec![byte; length]);
+        (pubky_common::crypto::hash(&content), content)
+    }
+
+    fn backend_stream(content: &Bytes) -> FileStream {
+        memory_stream(content.clone())
+    }
+
+    async fn read(stream: FileStream) -> Vec<u8> {
+        let mut stream = stream;
+        let mut collected = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            collected.extend_from_slice(&chunk.unwrap());
+        }
+        collected
+    }
+
+    /// Read `content` through the cache like `OpendalService` does.
+    async fn read_through(cache: &ReadCache, hash: Hash, content: &Bytes) -> Vec<u8> {
+        match cache.get(&hash).await.unwrap() {
+            Some(stream) => read(stream).await,
+            None => read(cache.fill(hash, content.len() as u64, backend_stream(content))).await,
+        }
+    }
+
+    async fn wait_until_cached(cache: &ReadCache, hash: &Hash) {
+        for _ in 0..100 {
+            if cache.contains(hash) {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("File was not cached");
+    }
+
+    #[tokio::test]
+    async fn test_hits_misses_and_metrics() {
+        for backend in [
+            StorageCacheBackend::InMemory,
+            StorageCacheBackend::FileSystem,
+        ] {
+            let data_dir = tempfile::tempdir().unwrap();
+            let metrics = Metrics::default();
+            let cache = ReadCache::new(&config(backend), data_dir.path(), metrics.clone()).unwrap();
+            let (hash, content) = content(1, 3 * CHUNK_SIZE + 1);
+
+            assert_eq!(read_through(&cache, hash, &content).await, content);
+            wait_until_cached(&cache, &hash).await;
+            assert_eq!(read_through(&cache, hash, &content).await, content);
+            assert_eq!(read_through(&cache, hash, &content).await, content);
+
+            let output = metrics.render().unwrap();
+            assert!(
+                output.contains(
+                    "storage_cache_hit_count_total{otel_scope_name=\"pubky_homeserver\"} 2"
+                ),
+                "{backend:?}: {output}"
+            );
+            assert!(
+                output.contains(
+                    "storage_cache_miss_count_total{otel_scope_name=\"pubky_homeserver\"} 1"
+                ),
+                "{backend:?}: {output}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_read() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let cache = ReadCache::new(
+            &config(StorageCacheBackend::FileSystem),
+            data_dir.path(),
+            Metrics::default(),
+        )
+        .unwrap();
+        let half = 512 * 1024;
+        let (a, a_content) = content(1, half);
+        let (b, b_content) = content(2, half);
+        let (c, c_content) = content(3, half);
+
+        read_through(&cache, a, &a_content).await;
+        wait_until_cached(&cache, &a).await;
+        read_through(&cache, b, &b_content).await;
+        wait_until_cached(&cache, &b).await;
+        // `a` is now read more recently than `b`.
+        read_through(&cache, a, &a_content).await;
+        read_through(&cache, c, &c_content).await;
+        wait_until_cached(&cache, &c).await;
+
+        assert!(cache.contains(&a));
+        assert!(!cache.contains(&b));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let cache_dir = data_dir.path().join("data/cache");
+        assert!(!cache_dir.join(b.to_hex().as_str()).exists());
+        assert!(cache_dir.join(a.to_hex().as_str()).exists());
+    }
+
+    #[tokio::test]
+    async fn test_only_caches_complete_matching_content() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let cache = ReadCache::new(
+            &config(StorageCacheBackend::InMemory),
+            data_dir.path(),
+            Metrics::default(),
+        )
+        .unwrap();
+        let (hash, content) = content(1, 10);
+
+        // The backend has other content than the entry.
+        let other = Bytes::from(vec![2; 10]);
+        read(cache.fill(hash, 10, backend_stream(&other))).await;
+        assert!(!cache.contains(&hash));
+
+        // The reader stopped early.
+        let mut stream = cache.fill(hash, 10, backend_stream(&content));
+        drop(stream.next().await);
+        drop(stream);
+        assert!(!cache.contains(&hash));
+
+        read(cache.fill(hash, 10, backend_stream(&content))).await;
+        assert!(cache.contains(&hash));
+
+        assert!(cache.accepts(1024 * 1024));
+        assert!(!cache.accepts(1024 * 1024 + 1));
+    }
+
+    #[tokio::test]
+    async fn test_evicts_damaged_disk_copy() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let cache = ReadCache::new(
+            &config(StorageCacheBackend::FileSystem),
+            data_dir.path(),
+            Metrics::default(),
+        )
+        .unwrap();
+        let (hash, content) = content(1, 10);
+        read_through(&cache, hash, &content).await;
+        wait_until_cached(&cache, &hash).await;
+
+        let file = data_dir
+            .path()
+            .join("data/cache")
+            .join(hash.to_hex().as_str());
+        std::fs::write(&file, vec![2; 10]).unwrap();
+        assert!(cache.get(&hash).await.unwrap().is_none());
+        assert!(!cache.contains(&hash));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!file.exists());
+
+        // The next read caches the backend content again.
+        assert_eq!(read_through(&cache, hash, &content).await, content);
+        wait_until_cached(&cache, &hash).await;
+        assert_eq!(
+            read(cache.get(&hash).await.unwrap().unwrap()).await,
+            content
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::{mem::take, sync::Arc};
 use crate::persistence::files::events::EventType;
 use crate::persistence::sql::{
     entry::{EntryEntity, EntryRepository},
+    path_quota::PathQuotaRepository,
     user::UserEntity,
     UnifiedExecutor,
 };
@@ -12,6 +13,7 @@ use opendal::raw::{oio, OpDelete};
 use opendal::{Error, Result};
 
 use super::layer::{unexpected, Finalizer};
+use super::precondition::check_precondition;
 
 struct StagedDelete {
     user: UserEntity,
@@ -198,7 +200,10 @@ impl Finalizer {
             .await
         {
             Ok(user) => user,
-            Err(sqlx::Error::RowNotFound) => return Ok(None),
+            Err(sqlx::Error::RowNotFound) => {
+                check_precondition(None)?;
+                return Ok(None);
+            }
             Err(error) => {
                 return Err(unexpected(
                     format!("Failed to lock user {}", entry_path.pubkey()),
@@ -209,7 +214,10 @@ impl Finalizer {
 
         let deleted_entry = match EntryRepository::get_by_path(entry_path, executor).await {
             Ok(entry) => entry,
-            Err(sqlx::Error::RowNotFound) => return Ok(None),
+            Err(sqlx::Error::RowNotFound) => {
+                check_precondition(None)?;
+                return Ok(None);
+            }
             Err(error) => {
                 return Err(unexpected(
                     format!("Failed to delete entry {entry_path}"),
@@ -217,6 +225,7 @@ impl Finalizer {
                 ));
             }
         };
+        check_precondition(Some(&deleted_entry.content_hash))?;
         EntryRepository::delete(deleted_entry.id, executor)
             .await
             .map_err(|error| unexpected(format!("Failed to delete entry {entry_path}"), error))?;
@@ -250,6 +259,19 @@ impl Finalizer {
         let bytes_delta = deleted_entry
             .content_length
             .saturating_add(FILE_METADATA_SIZE);
+        PathQuotaRepository::add_used_bytes(
+            user.id,
+            entry_path.path(),
+            -(bytes_delta as i64),
+            executor,
+        )
+        .await
+        .map_err(|error| {
+            unexpected(
+                format!("Failed to update path quotas for {entry_path}"),
+                error,
+            )
+        })?;
         user.used_bytes = user.used_bytes.saturating_sub(bytes_delta);
         self.user_service
             .update_in_tx(&user, executor)
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use crate::persistence::files::{events::EventsService, layer_domain_error::LayerDomainError};
 use crate::persistence::sql::{entry::EntryRepository, SqlDb, UnifiedExecutor};
+use crate::services::content_block_service::ContentBlockService;
 use crate::services::user_service::UserService;
 use crate::shared::webdav::EntryPath;
 use opendal::raw::*;
@@ -14,6 +15,7 @@ use super::{WriteFinalizationDeleter, WriteFinalizationWriter};
 /// The related database changes are committed together in one transaction.
 /// App-facing operators also reject path collisions; admin operators allow them
 /// so they can repair legacy data.
+/// Writes of content on the admin blocklist are rejected on both.
 ///
 /// Blob storage cannot be part of the database transaction. If the database
 /// update after a write fails, the blob may remain without a matching entry.
@@ -47,6 +49,7 @@ impl CollisionPolicy {
 #[derive(Debug)]
 pub(super) struct Finalizer {
     pub(super) user_service: UserService,
+    pub(super) content_block_service: ContentBlockService,
     pub(super) sql_db: SqlDb,
     pub(super) events_service: EventsService,
     pub(super) default_storage_mb: Option<u64>,
@@ -56,6 +59,7 @@ pub(super) struct Finalizer {
 impl WriteFinalizationLayer {
     pub fn new(
         user_service: UserService,
+        content_block_service: ContentBlockService,
         sql_db: SqlDb,
         events_service: EventsService,
         default_storage_mb: Option<u64>,
@@ -64,6 +68,7 @@ impl WriteFinalizationLayer {
         Self {
             finalizer: Arc::new(Finalizer::new(
                 user_service,
+                content_block_service,
                 sql_db,
                 events_service,
                 default_storage_mb,
@@ -198,6 +203,7 @@ impl<A: Access> LayeredAccess for WriteFinalizationAccessor<A> {
 impl Finalizer {
     fn new(
         user_service: UserService,
+        content_block_service: ContentBlockService,
         sql_db: SqlDb,
         events_service: EventsService,
         default_storage_mb: Option<u64>,
@@ -205,6 +211,7 @@ impl Finalizer {
     ) -> Self {
         Self {
             user_service,
+            content_block_service,
             sql_db,
             events_service,
             default_storage_mb,
@@ -243,6 +250,7 @@ pub(super) mod test_support {
     pub(in super::super) fn test_finalizer(db: &SqlDb) -> Finalizer {
         Finalizer::new(
             UserService::new(db.clone()),
+            ContentBlockService::new(),
             db.clone(),
             EventsService::new(100),
             None,
@@ -253,6 +261,7 @@ pub(super) mod test_support {
     pub(in super::super) fn test_operator(db: &SqlDb) -> opendal::Operator {
         get_memory_operator().layer(WriteFinalizationLayer::new(
             UserService::new(db.clone()),
+            ContentBlockService::new(),
             db.clone(),
             EventsService::new(100),
             None,
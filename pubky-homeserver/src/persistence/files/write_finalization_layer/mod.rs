@@ -2,10 +2,14 @@
 
 mod delete;
 mod layer;
+mod precondition;
 mod quota;
 mod write;
 
 pub use delete::WriteFinalizationDeleter;
 pub use layer::WriteFinalizationLayer;
-pub(crate) use quota::{resolve_storage_max_bytes, would_exceed_limit};
+pub use precondition::WritePrecondition;
+pub(crate) use quota::{
+    entries_used_bytes, exceeded_path_quota, resolve_storage_max_bytes, would_exceed_limit,
+};
 pub use write::WriteFinalizationWriter;
@@ -0,0 +1,57 @@
+use std::future::Future;
+
+use pubky_common::crypto::Hash;
+
+use crate::persistence::files::layer_domain_error::LayerDomainError;
+
+tokio::task_local! {
+    static PRECONDITION: WritePrecondition;
+}
+
+/// Version a file must be at for a conditional write or delete (`If-Match`,
+/// `If-None-Match`) to go ahead.
+///
+/// OpenDAL deletes can't carry a condition, so it is scoped to the task doing the
+/// write or delete with [`Self::scope`]. The finalizer checks it against the entry
+/// in the same transaction that changes the entry, so a concurrent writer can't
+/// slip in between the check and the change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePrecondition {
+    /// The file exists with this content hash.
+    Matches(Hash),
+    /// The file exists.
+    Exists,
+    /// The file does not exist.
+    Absent,
+}
+
+impl WritePrecondition {
+    /// Run `operation` with this precondition.
+    pub async fn scope<F: Future>(self, operation: F) -> F::Output {
+        PRECONDITION.scope(self, operation).await
+    }
+
+    /// Whether a file with `current` content hash, or no file, satisfies it.
+    pub fn is_met(&self, current: Option<&Hash>) -> bool {
+        match self {
+            Self::Matches(expected) => current == Some(expected),
+            Self::Exists => current.is_some(),
+            Self::Absent => current.is_none(),
+        }
+    }
+}
+
+/// Check the precondition of the current task, if any, against `current`.
+pub(super) fn check_precondition(current: Option<&Hash>) -> opendal::Result<()> {
+    let met = PRECONDITION
+        .try_with(|precondition| precondition.is_met(current))
+        .unwrap_or(true);
+    if met {
+        return Ok(());
+    }
+    Err(opendal::Error::new(
+        opendal::ErrorKind::ConditionNotMatch,
+        "Write precondition failed",
+    )
+    .set_source(LayerDomainError::PreconditionFailed))
+}
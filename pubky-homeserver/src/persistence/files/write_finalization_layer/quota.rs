@@ -1,4 +1,11 @@
-use crate::persistence::sql::user::UserEntity;
+use crate::persistence::sql::{
+    entry::EntryUsage,
+    path_quota::{PathQuotaEntity, PathQuotaRepository},
+    user::UserEntity,
+    UnifiedExecutor,
+};
+use crate::services::user_service::FILE_METADATA_SIZE;
+use crate::shared::webdav::StoragePath;
 
 /// Check whether adding `bytes_delta` to `current_bytes` would exceed `max_bytes`.
 /// `None` means unlimited storage.
@@ -25,6 +32,36 @@ pub(crate) fn resolve_storage_max_bytes(
         .map(|mb| mb.saturating_mul(1024 * 1024))
 }
 
+/// Storage used by a set of entries, counted like the user's total:
+/// content plus `FILE_METADATA_SIZE` per file.
+pub(crate) fn entries_used_bytes(usage: &EntryUsage) -> u64 {
+    usage
+        .content_bytes
+        .saturating_add(usage.file_count.saturating_mul(FILE_METADATA_SIZE))
+}
+
+/// Find a path quota of the user containing `path` that adding `bytes_delta` would exceed.
+///
+/// Writes that don't grow the storage are always allowed, so a limit set below
+/// the current usage doesn't lock existing files in place.
+pub(crate) async fn exceeded_path_quota(
+    user_id: i32,
+    path: &StoragePath,
+    bytes_delta: i64,
+    executor: &mut UnifiedExecutor<'_>,
+) -> Result<Option<PathQuotaEntity>, sqlx::Error> {
+    if bytes_delta <= 0 {
+        return Ok(None);
+    }
+    for quota in PathQuotaRepository::list_matching(user_id, path, executor).await? {
+        let max_bytes = quota.max_mb.saturating_mul(1024 * 1024);
+        if would_exceed_limit(quota.used_bytes, bytes_delta, Some(max_bytes)) {
+            return Ok(Some(quota));
+        }
+    }
+    Ok(None)
+}
+
 #[cfg(test)]
 mod tests {
     use super::would_exceed_limit;
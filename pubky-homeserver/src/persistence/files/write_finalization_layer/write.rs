@@ -5,6 +5,7 @@ use crate::persistence::files::{
 };
 use crate::persistence::sql::{
     entry::{EntryEntity, EntryRepository},
+    path_quota::PathQuotaRepository,
     user::UserEntity,
     UnifiedExecutor,
 };
@@ -14,7 +15,9 @@ use opendal::raw::oio;
 use opendal::Result;
 
 use super::{
+    exceeded_path_quota,
     layer::{check_no_path_collision, unexpected, Finalizer},
+    precondition::check_precondition,
     resolve_storage_max_bytes, would_exceed_limit,
 };
 
@@ -144,9 +147,23 @@ impl Finalizer {
         file_metadata: &FileMetadata,
         executor: &mut UnifiedExecutor<'_>,
     ) -> Result<opendal::Metadata> {
-        let prepared = self
+        let prepared = match self
             .prepare_write(entry_path, file_metadata, executor)
-            .await?;
+            .await
+        {
+            Ok(prepared) => prepared,
+            Err(error) => {
+                // Drop the staged upload; the stored file stays as it was.
+                if let Err(abort_error) = backend_writer.abort().await {
+                    tracing::warn!(
+                        path = %entry_path,
+                        error = %abort_error,
+                        "Failed to abort rejected write"
+                    );
+                }
+                return Err(error);
+            }
+        };
         let backend_metadata = backend_writer.close().await?;
         self.apply_write_effects(prepared, entry_path, file_metadata, executor)
             .await?;
@@ -174,6 +191,21 @@ impl Finalizer {
             check_no_path_collision(entry_path, executor).await?;
         }
 
+        let block = self
+            .content_block_service
+            .find_match(entry_path, Some(&file_metadata.hash), executor)
+            .await
+            .map_err(|error| {
+                unexpected(format!("Failed to check blocklist for {entry_path}"), error)
+            })?;
+        if let Some(block) = block {
+            return Err(opendal::Error::new(
+                opendal::ErrorKind::PermissionDenied,
+                format!("Content of {entry_path} is blocked by entry {}", block.id),
+            )
+            .set_source(LayerDomainError::ContentBlocked));
+        }
+
         let existing_entry = match EntryRepository::get_by_path(entry_path, executor).await {
             Ok(entry) => Some(entry),
             Err(sqlx::Error::RowNotFound) => None,
@@ -185,7 +217,33 @@ impl Finalizer {
             }
         };
 
-        PreparedWrite::new(user, existing_entry, file_metadata, self.default_storage_mb)
+        check_precondition(existing_entry.as_ref().map(|entry| &entry.content_hash))?;
+
+        let prepared =
+            PreparedWrite::new(user, existing_entry, file_metadata, self.default_storage_mb)?;
+
+        let exceeded = exceeded_path_quota(
+            prepared.user.id,
+            entry_path.path(),
+            prepared.bytes_delta,
+            executor,
+        )
+        .await
+        .map_err(|error| {
+            unexpected(
+                format!("Failed to check path quotas for {}", entry_path),
+                error,
+            )
+        })?;
+        if let Some(quota) = exceeded {
+            return Err(opendal::Error::new(
+                opendal::ErrorKind::RateLimited,
+                format!("Path quota of {} exceeded", quota.path),
+            )
+            .set_source(LayerDomainError::DiskSpaceQuotaExceeded));
+        }
+
+        Ok(prepared)
     }
 
     async fn apply_write_effects(
@@ -246,6 +304,14 @@ impl Finalizer {
                     error,
                 )
             })?;
+        PathQuotaRepository::add_used_bytes(user.id, entry_path.path(), bytes_delta, executor)
+            .await
+            .map_err(|error| {
+                unexpected(
+                    format!("Failed to update path quotas for {}", entry_path),
+                    error,
+                )
+            })?;
         user.used_bytes = user.used_bytes.saturating_add_signed(bytes_delta);
         self.user_service
             .update_in_tx(&user, executor)
@@ -268,7 +334,13 @@ mod tests {
     use tokio::sync::Barrier;
 
     use crate::persistence::files::FileIoError;
-    use crate::persistence::sql::{entry::EntryRepository, SqlDb};
+    use crate::persistence::sql::{
+        content_block::{BlockTarget, ContentBlockRepository},
+        entry::EntryRepository,
+        path_quota::{PathQuotaEntity, PathQuotaRepository},
+        SqlDb,
+    };
+    use crate::quota_config::GlobPattern;
     use crate::services::user_service::FILE_METADATA_SIZE;
     use crate::shared::webdav::{EntryPath, StoragePath};
 
@@ -318,6 +390,104 @@ mod tests {
         assert!(all_events(&db).await.is_empty());
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn path_quota_limits_writes_below_its_prefix() {
+        let db = SqlDb::test().await;
+        let operator = test_operator(&db);
+        let pubkey = create_user(&db).await;
+        PathQuotaRepository::set(
+            &pubkey,
+            &PathQuotaEntity {
+                path: StoragePath::new("/pub/photos/").unwrap(),
+                max_mb: 1,
+                used_bytes: 0,
+            },
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let one_mb = 1024 * 1024;
+        let photo = format!("{}/pub/photos/a.jpg", pubkey.z32());
+
+        operator.write(&photo, vec![1; one_mb / 2]).await.unwrap();
+        let error = operator
+            .write(
+                &format!("{}/pub/photos/b.jpg", pubkey.z32()),
+                vec![2; one_mb / 2],
+            )
+            .await
+            .expect_err("second photo should exceed the path quota");
+        assert!(matches!(
+            FileIoError::from(error),
+            FileIoError::DiskSpaceQuotaExceeded
+        ));
+
+        // Other prefixes and shrinking overwrites are not limited.
+        operator
+            .write(
+                &format!("{}/pub/notes/b.txt", pubkey.z32()),
+                vec![3; one_mb],
+            )
+            .await
+            .unwrap();
+        operator.write(&photo, vec![1; 10]).await.unwrap();
+        assert_eq!(
+            user_usage(&db, &pubkey).await,
+            one_mb as u64 + 10 + 2 * FILE_METADATA_SIZE
+        );
+        let quotas = PathQuotaRepository::list(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(quotas[0].used_bytes, 10 + FILE_METADATA_SIZE);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn blocked_hash_and_path_glob_reject_writes() {
+        let db = SqlDb::test().await;
+        let operator = test_operator(&db);
+        let pubkey = create_user(&db).await;
+        ContentBlockRepository::add(
+            &BlockTarget::Hash(pubky_common::crypto::hash(&[1; 10])),
+            None,
+            "admin",
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        ContentBlockRepository::add(
+            &BlockTarget::PathGlob(GlobPattern::new("*/pub/spam.app/**")),
+            None,
+            "admin",
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+
+        for (path, content) in [("/pub/a.txt", vec![1; 10]), ("/pub/spam.app/b", vec![2])] {
+            let entry_path = EntryPath::new(pubkey.clone(), StoragePath::new(path).unwrap());
+            let error = operator
+                .write(entry_path.as_str(), content)
+                .await
+                .expect_err("blocked content should be rejected");
+            assert!(matches!(
+                FileIoError::from(error),
+                FileIoError::ContentBlocked
+            ));
+            EntryRepository::get_by_path(&entry_path, &mut db.pool().into())
+                .await
+                .expect_err("no entry for blocked content");
+        }
+        assert_eq!(user_usage(&db, &pubkey).await, 0);
+        assert!(all_events(&db).await.is_empty());
+
+        operator
+            .write(&format!("{}/pub/a.txt", pubkey.z32()), vec![3; 10])
+            .await
+            .unwrap();
+    }
+
     #[tokio::test]
     #[pubky_test_utils::test]
     async fn concurrent_colliding_closes_allow_exactly_one_entry() {
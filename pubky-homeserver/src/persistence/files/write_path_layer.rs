@@ -184,6 +184,7 @@ mod tests {
         events::EventsService, write_finalization_layer::WriteFinalizationLayer,
     };
     use crate::persistence::sql::SqlDb;
+    use crate::services::content_block_service::ContentBlockService;
     use crate::services::user_service::UserService;
     use crate::shared::user_quota::UserQuota;
     use crate::shared::webdav::StoragePath;
@@ -229,6 +230,7 @@ mod tests {
         let user_service = UserService::new(db.clone());
         let write_finalization_layer = WriteFinalizationLayer::new(
             user_service.clone(),
+            ContentBlockService::new(),
             db.clone(),
             EventsService::new(100),
             None,
@@ -0,0 +1,171 @@
+use pubky_common::crypto::PublicKey;
+use sea_query::{Expr, Iden, OnConflict, Order, PostgresQueryBuilder, Query, SimpleExpr};
+use sea_query_binder::SqlxBinder;
+use sqlx::{postgres::PgRow, types::chrono::NaiveDateTime, FromRow, Row};
+
+use crate::persistence::sql::UnifiedExecutor;
+
+pub const BANNED_KEY_TABLE: &str = "banned_keys";
+
+/// A public key that is not allowed to sign up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BannedKeyEntity {
+    pub public_key: PublicKey,
+    /// Free-form note of the admin who banned the key.
+    pub reason: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl FromRow<'_, PgRow> for BannedKeyEntity {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let raw_pubkey: String = row.try_get(BannedKeyIden::PublicKey.to_string().as_str())?;
+        let public_key = PublicKey::try_from_z32(raw_pubkey.as_str())
+            .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+        Ok(Self {
+            public_key,
+            reason: row.try_get(BannedKeyIden::Reason.to_string().as_str())?,
+            created_at: row.try_get(BannedKeyIden::CreatedAt.to_string().as_str())?,
+        })
+    }
+}
+
+/// Repository for the signup ban list.
+pub(crate) struct BannedKeyRepository;
+
+impl BannedKeyRepository {
+    /// Ban a public key. Banning an already banned key replaces its reason.
+    pub async fn ban<'a>(
+        public_key: &PublicKey,
+        reason: Option<&str>,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        let statement = Query::insert()
+            .into_table(BANNED_KEY_TABLE)
+            .columns([BannedKeyIden::PublicKey, BannedKeyIden::Reason])
+            .values(vec![
+                SimpleExpr::Value(public_key.z32().into()),
+                SimpleExpr::Value(reason.map(str::to_string).into()),
+            ])
+            .expect("Failed to build insert statement")
+            .on_conflict(
+                OnConflict::column(BannedKeyIden::PublicKey)
+                    .update_column(BannedKeyIden::Reason)
+                    .to_owned(),
+            )
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_with(&query, values).execute(con).await?;
+        Ok(())
+    }
+
+    /// Lift the ban of a public key. Returns whether the key was banned.
+    pub async fn unban<'a>(
+        public_key: &PublicKey,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<bool, sqlx::Error> {
+        let statement = Query::delete()
+            .from_table(BANNED_KEY_TABLE)
+            .and_where(Expr::col(BannedKeyIden::PublicKey).eq(public_key.z32()))
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let result = sqlx::query_with(&query, values).execute(con).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Whether a public key is banned.
+    pub async fn is_banned<'a>(
+        public_key: &PublicKey,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<bool, sqlx::Error> {
+        let statement = Query::select()
+            .from(BANNED_KEY_TABLE)
+            .column(BannedKeyIden::PublicKey)
+            .and_where(Expr::col(BannedKeyIden::PublicKey).eq(public_key.z32()))
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let row = sqlx::query_with(&query, values).fetch_optional(con).await?;
+        Ok(row.is_some())
+    }
+
+    /// All banned keys, most recent first.
+    pub async fn list<'a>(
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<BannedKeyEntity>, sqlx::Error> {
+        let statement = Query::select()
+            .from(BANNED_KEY_TABLE)
+            .columns([
+                BannedKeyIden::PublicKey,
+                BannedKeyIden::Reason,
+                BannedKeyIden::CreatedAt,
+            ])
+            .order_by(BannedKeyIden::CreatedAt, Order::Desc)
+            .order_by(BannedKeyIden::PublicKey, Order::Asc)
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values).fetch_all(con).await
+    }
+}
+
+/// Iden for the banned key table.
+#[derive(Iden)]
+pub enum BannedKeyIden {
+    PublicKey,
+    Reason,
+    CreatedAt,
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::persistence::sql::SqlDb;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_ban_and_unban() {
+        let db = SqlDb::test().await;
+        let pubkey = Keypair::random().public_key();
+
+        assert!(
+            !BannedKeyRepository::is_banned(&pubkey, &mut db.pool().into())
+                .await
+                .unwrap()
+        );
+
+        BannedKeyRepository::ban(&pubkey, Some("spam"), &mut db.pool().into())
+            .await
+            .unwrap();
+        // Banning twice replaces the reason.
+        BannedKeyRepository::ban(&pubkey, Some("abuse"), &mut db.pool().into())
+            .await
+            .unwrap();
+        assert!(
+            BannedKeyRepository::is_banned(&pubkey, &mut db.pool().into())
+                .await
+                .unwrap()
+        );
+        let bans = BannedKeyRepository::list(&mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(bans.len(), 1);
+        assert_eq!(bans[0].public_key, pubkey);
+        assert_eq!(bans[0].reason.as_deref(), Some("abuse"));
+
+        assert!(BannedKeyRepository::unban(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap());
+        assert!(!BannedKeyRepository::unban(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap());
+        assert!(
+            !BannedKeyRepository::is_banned(&pubkey, &mut db.pool().into())
+                .await
+                .unwrap()
+        );
+    }
+}
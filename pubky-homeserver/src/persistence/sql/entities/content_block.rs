@@ -0,0 +1,367 @@
+use pubky_common::crypto::{Hash, PublicKey};
+use sea_query::{Expr, Iden, Order, PostgresQueryBuilder, Query, SimpleExpr};
+use sea_query_binder::SqlxBinder;
+use sqlx::{postgres::PgRow, types::chrono::NaiveDateTime, FromRow, Row};
+
+use crate::persistence::sql::entities::entry::ENTRY_TABLE;
+use crate::persistence::sql::entities::user::USER_TABLE;
+use crate::persistence::sql::UnifiedExecutor;
+use crate::quota_config::GlobPattern;
+use crate::shared::webdav::{EntryPath, StoragePath};
+
+pub const CONTENT_BLOCK_TABLE: &str = "content_blocks";
+
+/// What a blocklist entry matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockTarget {
+    /// Files with this blake3 content hash, at any path.
+    Hash(Hash),
+    /// Files whose entry path (`{pubkey}/{path}`) matches the glob,
+    /// e.g. `*/pub/spam.app/**` for every user.
+    PathGlob(GlobPattern),
+}
+
+impl BlockTarget {
+    /// Whether a file at `entry_path` with `content_hash` is blocked by this target.
+    pub fn matches(&self, entry_path: &EntryPath, content_hash: Option<&Hash>) -> bool {
+        match self {
+            BlockTarget::Hash(hash) => content_hash == Some(hash),
+            BlockTarget::PathGlob(glob) => glob.is_match(entry_path.as_str()),
+        }
+    }
+}
+
+/// An admin-managed blocklist entry.
+///
+/// Entries are never deleted: removing one sets `removed_at` and `removed_by`
+/// so the table doubles as the audit trail of takedowns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentBlockEntity {
+    pub id: i32,
+    pub target: BlockTarget,
+    pub reason: Option<String>,
+    /// Admin who added the entry.
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    /// Admin who lifted the block, `None` while it is active.
+    pub removed_by: Option<String>,
+    pub removed_at: Option<NaiveDateTime>,
+}
+
+impl FromRow<'_, PgRow> for ContentBlockEntity {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let content_hash: Option<Vec<u8>> =
+            row.try_get(ContentBlockIden::ContentHash.to_string().as_str())?;
+        let path_glob: Option<String> =
+            row.try_get(ContentBlockIden::PathGlob.to_string().as_str())?;
+        let target = match (content_hash, path_glob) {
+            (Some(hash), None) => {
+                let hash: [u8; 32] = hash.try_into().map_err(|_| {
+                    sqlx::Error::Decode("content_hash must be 32 bytes".to_string().into())
+                })?;
+                BlockTarget::Hash(Hash::from_bytes(hash))
+            }
+            (None, Some(glob)) => BlockTarget::PathGlob(GlobPattern(glob)),
+            _ => {
+                return Err(sqlx::Error::Decode(
+                    "exactly one of content_hash and path_glob must be set"
+                        .to_string()
+                        .into(),
+                ))
+            }
+        };
+        Ok(Self {
+            id: row.try_get(ContentBlockIden::Id.to_string().as_str())?,
+            target,
+            reason: row.try_get(ContentBlockIden::Reason.to_string().as_str())?,
+            created_by: row.try_get(ContentBlockIden::CreatedBy.to_string().as_str())?,
+            created_at: row.try_get(ContentBlockIden::CreatedAt.to_string().as_str())?,
+            removed_by: row.try_get(ContentBlockIden::RemovedBy.to_string().as_str())?,
+            removed_at: row.try_get(ContentBlockIden::RemovedAt.to_string().as_str())?,
+        })
+    }
+}
+
+const COLUMNS: [ContentBlockIden; 8] = [
+    ContentBlockIden::Id,
+    ContentBlockIden::ContentHash,
+    ContentBlockIden::PathGlob,
+    ContentBlockIden::Reason,
+    ContentBlockIden::CreatedBy,
+    ContentBlockIden::CreatedAt,
+    ContentBlockIden::RemovedBy,
+    ContentBlockIden::RemovedAt,
+];
+
+/// Repository for the content takedown blocklist.
+pub(crate) struct ContentBlockRepository;
+
+impl ContentBlockRepository {
+    /// Add a blocklist entry.
+    pub async fn add<'a>(
+        target: &BlockTarget,
+        reason: Option<&str>,
+        created_by: &str,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<ContentBlockEntity, sqlx::Error> {
+        let (content_hash, path_glob) = match target {
+            BlockTarget::Hash(hash) => (Some(hash.as_bytes().to_vec()), None),
+            BlockTarget::PathGlob(glob) => (None, Some(glob.to_string())),
+        };
+        let statement = Query::insert()
+            .into_table(CONTENT_BLOCK_TABLE)
+            .columns([
+                ContentBlockIden::ContentHash,
+                ContentBlockIden::PathGlob,
+                ContentBlockIden::Reason,
+                ContentBlockIden::CreatedBy,
+            ])
+            .values(vec![
+                SimpleExpr::Value(content_hash.into()),
+                SimpleExpr::Value(path_glob.into()),
+                SimpleExpr::Value(reason.map(str::to_string).into()),
+                SimpleExpr::Value(created_by.into()),
+            ])
+            .expect("Failed to build insert statement")
+            .returning(Query::returning().columns(COLUMNS))
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values).fetch_one(con).await
+    }
+
+    /// Lift an active block. Returns `None` if there is no active entry with this id.
+    pub async fn remove<'a>(
+        id: i32,
+        removed_by: &str,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<ContentBlockEntity>, sqlx::Error> {
+        let statement = Query::update()
+            .table(CONTENT_BLOCK_TABLE)
+            .values([
+                (ContentBlockIden::RemovedBy, removed_by.into()),
+                (
+                    ContentBlockIden::RemovedAt,
+                    Expr::current_timestamp().into(),
+                ),
+            ])
+            .and_where(Expr::col(ContentBlockIden::Id).eq(id))
+            .and_where(Expr::col(ContentBlockIden::RemovedAt).is_null())
+            .returning(Query::returning().columns(COLUMNS))
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values)
+            .fetch_optional(con)
+            .await
+    }
+
+    /// Blocklist entries, most recent first. Lifted entries are only included if `include_removed`.
+    pub async fn list<'a>(
+        include_removed: bool,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<ContentBlockEntity>, sqlx::Error> {
+        let mut statement = Query::select()
+            .from(CONTENT_BLOCK_TABLE)
+            .columns(COLUMNS)
+            .order_by(ContentBlockIden::Id, Order::Desc)
+            .to_owned();
+        if !include_removed {
+            statement.and_where(Expr::col(ContentBlockIden::RemovedAt).is_null());
+        }
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values).fetch_all(con).await
+    }
+
+    /// The active entry with exactly this target, if any.
+    pub async fn get_active<'a>(
+        target: &BlockTarget,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<ContentBlockEntity>, sqlx::Error> {
+        let condition = match target {
+            BlockTarget::Hash(hash) => {
+                Expr::col(ContentBlockIden::ContentHash).eq(hash.as_bytes().to_vec())
+            }
+            BlockTarget::PathGlob(glob) => {
+                Expr::col(ContentBlockIden::PathGlob).eq(glob.to_string())
+            }
+        };
+        let statement = Query::select()
+            .from(CONTENT_BLOCK_TABLE)
+            .columns(COLUMNS)
+            .and_where(condition)
+            .and_where(Expr::col(ContentBlockIden::RemovedAt).is_null())
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values)
+            .fetch_optional(con)
+            .await
+    }
+
+    /// The active entry blocking `content_hash`, if any.
+    pub async fn find_by_hash<'a>(
+        content_hash: &Hash,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<ContentBlockEntity>, sqlx::Error> {
+        let statement = Query::select()
+            .from(CONTENT_BLOCK_TABLE)
+            .columns(COLUMNS)
+            .and_where(
+                Expr::col(ContentBlockIden::ContentHash).eq(content_hash.as_bytes().to_vec()),
+            )
+            .and_where(Expr::col(ContentBlockIden::RemovedAt).is_null())
+            .limit(1)
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values)
+            .fetch_optional(con)
+            .await
+    }
+
+    /// The paths among `paths` of the user `public_key` whose current content hash
+    /// is blocked. Directory paths never match.
+    pub async fn paths_with_blocked_hash<'a>(
+        public_key: &PublicKey,
+        paths: &[&StoragePath],
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query = format!(
+            r#"SELECT e.path FROM {ENTRY_TABLE} e
+            JOIN {USER_TABLE} u ON u.id = e."user"
+            JOIN {CONTENT_BLOCK_TABLE} b ON b.content_hash = e.content_hash AND b.removed_at IS NULL
+            WHERE u.public_key = $1 AND e.path = ANY($2)"#
+        );
+        let paths: Vec<&str> = paths.iter().map(|path| path.as_str()).collect();
+        let con = executor.get_con().await?;
+        sqlx::query_scalar(&query)
+            .bind(public_key.z32())
+            .bind(paths)
+            .fetch_all(con)
+            .await
+    }
+
+    /// All active path glob entries, oldest first.
+    pub async fn list_active_globs<'a>(
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<ContentBlockEntity>, sqlx::Error> {
+        let statement = Query::select()
+            .from(CONTENT_BLOCK_TABLE)
+            .columns(COLUMNS)
+            .and_where(Expr::col(ContentBlockIden::PathGlob).is_not_null())
+            .and_where(Expr::col(ContentBlockIden::RemovedAt).is_null())
+            .order_by(ContentBlockIden::Id, Order::Asc)
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values).fetch_all(con).await
+    }
+}
+
+/// Iden for the content block table.
+#[derive(Iden, Clone, Copy)]
+pub enum ContentBlockIden {
+    Id,
+    ContentHash,
+    PathGlob,
+    Reason,
+    CreatedBy,
+    CreatedAt,
+    RemovedBy,
+    RemovedAt,
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::{hash, Keypair};
+
+    use super::*;
+    use crate::persistence::sql::SqlDb;
+    use crate::shared::webdav::StoragePath;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_find_and_remove() {
+        let db = SqlDb::test().await;
+        let content = hash(b"spam");
+        let path = EntryPath::new(
+            Keypair::random().public_key(),
+            StoragePath::new("/pub/spam.app/post.json").unwrap(),
+        );
+        let other_path = EntryPath::new(
+            Keypair::random().public_key(),
+            StoragePath::new("/pub/ok.app/post.json").unwrap(),
+        );
+
+        let by_hash = ContentBlockRepository::add(
+            &BlockTarget::Hash(content),
+            Some("csam"),
+            "alice",
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        ContentBlockRepository::add(
+            &BlockTarget::PathGlob(GlobPattern::new("*/pub/spam.app/**")),
+            None,
+            "bob",
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+
+        let found = ContentBlockRepository::find_by_hash(&content, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(found.unwrap().id, by_hash.id);
+        let globs = ContentBlockRepository::list_active_globs(&mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(globs.len(), 1);
+        assert_eq!(globs[0].created_by, "bob");
+        assert!(globs[0].target.matches(&path, None));
+        assert!(!globs[0].target.matches(&other_path, None));
+        assert!(
+            ContentBlockRepository::find_by_hash(&hash(b"fine"), &mut db.pool().into())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let removed = ContentBlockRepository::remove(by_hash.id, "carol", &mut db.pool().into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(removed.removed_by.as_deref(), Some("carol"));
+        assert!(removed.removed_at.is_some());
+        assert!(
+            ContentBlockRepository::remove(by_hash.id, "carol", &mut db.pool().into())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(ContentBlockRepository::get_active(
+            &BlockTarget::Hash(content),
+            &mut db.pool().into()
+        )
+        .await
+        .unwrap()
+        .is_none());
+
+        // Lifted entries stay in the audit trail.
+        let active = ContentBlockRepository::list(false, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(active.len(), 1);
+        let all = ContentBlockRepository::list(true, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1], removed);
+    }
+}
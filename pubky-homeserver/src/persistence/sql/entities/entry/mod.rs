@@ -2,4 +2,4 @@ mod entity;
 mod repository;
 
 pub use entity::EntryEntity;
-pub use repository::{EntryIden, EntryRepository};
+pub use repository::{EntryIden, EntryPrefixUsage, EntryRepository, EntryUsage, ENTRY_TABLE};
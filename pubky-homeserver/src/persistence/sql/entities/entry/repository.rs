@@ -87,6 +87,41 @@ impl EntryRepository {
         Ok(entry)
     }
 
+    /// Entries of all users ordered by id, to walk the whole table page by page.
+    /// Cursor is the id of the last entry of the previous page (non-inclusive).
+    pub async fn list_page<'a>(
+        cursor: Option<i64>,
+        limit: u16,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<EntryEntity>, sqlx::Error> {
+        let mut statement = Query::select()
+            .from(ENTRY_TABLE)
+            .columns([
+                (ENTRY_TABLE, EntryIden::Id),
+                (ENTRY_TABLE, EntryIden::User),
+                (ENTRY_TABLE, EntryIden::Path),
+                (ENTRY_TABLE, EntryIden::ContentHash),
+                (ENTRY_TABLE, EntryIden::ContentLength),
+                (ENTRY_TABLE, EntryIden::ContentType),
+                (ENTRY_TABLE, EntryIden::ModifiedAt),
+                (ENTRY_TABLE, EntryIden::CreatedAt),
+            ])
+            .column((USER_TABLE, UserIden::PublicKey))
+            .inner_join(
+                USER_TABLE,
+                Expr::col((ENTRY_TABLE, EntryIden::User)).eq(Expr::col((USER_TABLE, UserIden::Id))),
+            )
+            .order_by((ENTRY_TABLE, EntryIden::Id), Order::Asc)
+            .limit(limit.into())
+            .to_owned();
+        if let Some(cursor) = cursor {
+            statement.and_where(Expr::col((ENTRY_TABLE, EntryIden::Id)).gt(cursor));
+        }
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values).fetch_all(con).await
+    }
+
     pub async fn update<'a>(
         entry: &EntryEntity,
         executor: &mut UnifiedExecutor<'a>,
@@ -162,6 +197,83 @@ impl EntryRepository {
         Ok(())
     }
 
+    /// Total content length and number of files of a user below `prefix`.
+    pub async fn usage_below<'a>(
+        user_id: i32,
+        prefix: &StoragePath,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<EntryUsage, sqlx::Error> {
+        let statement = Query::select()
+            .from(ENTRY_TABLE)
+            .expr_as(
+                Expr::cust("COALESCE(SUM(entries.content_length), 0)::bigint"),
+                Alias::new("content_bytes"),
+            )
+            .expr_as(
+                Expr::col((ENTRY_TABLE, EntryIden::Id)).count(),
+                Alias::new("file_count"),
+            )
+            .and_where(Expr::col((ENTRY_TABLE, EntryIden::User)).eq(user_id))
+            .and_where(Expr::cust_with_values(
+                "starts_with(entries.path, $1)",
+                vec![sea_query::Value::from(prefix.as_str())],
+            ))
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let row: PgRow = sqlx::query_with(&query, values).fetch_one(con).await?;
+        let content_bytes: i64 = row.try_get("content_bytes")?;
+        let file_count: i64 = row.try_get("file_count")?;
+        Ok(EntryUsage {
+            content_bytes: content_bytes as u64,
+            file_count: file_count as u64,
+        })
+    }
+
+    /// Total content length, number of files and last modification of a user's
+    /// entries, grouped by top-level directory and app directory.
+    pub async fn usage_by_prefix<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<EntryPrefixUsage>, sqlx::Error> {
+        let query = format!(
+            r#"SELECT
+                directory,
+                app,
+                COALESCE(SUM(content_length), 0)::bigint AS content_bytes,
+                COUNT(*) AS file_count,
+                MAX(modified_at) AS last_modified
+            FROM (
+                SELECT
+                    substring(path from '^/[^/]+/') AS directory,
+                    substring(path from '^/[^/]+/[^/]+/') AS app,
+                    content_length,
+                    modified_at
+                FROM {ENTRY_TABLE}
+                WHERE "user" = $1
+            ) prefixed
+            GROUP BY directory, app
+            ORDER BY directory COLLATE "C" NULLS FIRST, app COLLATE "C" NULLS FIRST"#
+        );
+        let con = executor.get_con().await?;
+        let rows: Vec<PgRow> = sqlx::query(&query).bind(user_id).fetch_all(con).await?;
+        rows.iter()
+            .map(|row| {
+                let content_bytes: i64 = row.try_get("content_bytes")?;
+                let file_count: i64 = row.try_get("file_count")?;
+                Ok(EntryPrefixUsage {
+                    directory: row.try_get("directory")?,
+                    app: row.try_get("app")?,
+                    usage: EntryUsage {
+                        content_bytes: content_bytes as u64,
+                        file_count: file_count as u64,
+                    },
+                    last_modified: row.try_get("last_modified")?,
+                })
+            })
+            .collect()
+    }
+
     /// Check if a directory exists.
     /// Path is the path to the folder.
     pub async fn contains_directory<'a>(
@@ -435,6 +547,27 @@ impl EntryRepository {
     }
 }
 
+/// Aggregated size of a set of entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntryUsage {
+    /// Sum of the content lengths.
+    pub content_bytes: u64,
+    /// Number of files.
+    pub file_count: u64,
+}
+
+/// Aggregated size of a user's entries in one top-level and app directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryPrefixUsage {
+    /// Top-level directory, e.g. `/pub/`. `None` for files at the root.
+    pub directory: Option<String>,
+    /// App directory, e.g. `/pub/app/`. `None` for files directly in `directory`.
+    pub app: Option<String>,
+    pub usage: EntryUsage,
+    /// Latest modification of the entries.
+    pub last_modified: sqlx::types::chrono::NaiveDateTime,
+}
+
 #[derive(Iden)]
 pub enum EntryIden {
     Id,
@@ -0,0 +1,163 @@
+use pubky_common::crypto::PublicKey;
+use sqlx::Row;
+
+use crate::persistence::sql::migrations::m20261019_create_mirror_cursors::MIRROR_CURSORS_TABLE;
+use crate::persistence::sql::user::{UserEntity, USER_TABLE};
+use crate::persistence::sql::UnifiedExecutor;
+
+/// Repository for the position in the primary's event stream of mirrored users.
+pub(crate) struct MirrorCursorRepository;
+
+impl MirrorCursorRepository {
+    /// Get the cursor of the user in the event stream of `primary`.
+    /// `None` if the user was never mirrored from `primary`.
+    pub async fn get<'a>(
+        user_id: i32,
+        primary: &PublicKey,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let query = format!(
+            r#"SELECT cursor FROM {MIRROR_CURSORS_TABLE}
+            WHERE "user" = $1 AND primary_homeserver = $2"#
+        );
+        let con = executor.get_con().await?;
+        let row = sqlx::query(&query)
+            .bind(user_id)
+            .bind(primary.z32())
+            .fetch_optional(con)
+            .await?;
+        row.map(|row| row.try_get("cursor")).transpose()
+    }
+
+    /// Set the cursor of the user in the event stream of `primary`,
+    /// replacing the cursor of any previous primary.
+    pub async fn set<'a>(
+        user_id: i32,
+        primary: &PublicKey,
+        cursor: &str,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        let query = format!(
+            r#"INSERT INTO {MIRROR_CURSORS_TABLE} ("user", primary_homeserver, cursor)
+            VALUES ($1, $2, $3)
+            ON CONFLICT ("user") DO UPDATE SET
+                primary_homeserver = EXCLUDED.primary_homeserver,
+                cursor = EXCLUDED.cursor"#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query(&query)
+            .bind(user_id)
+            .bind(primary.z32())
+            .bind(cursor)
+            .execute(con)
+            .await?;
+        Ok(())
+    }
+
+    /// List the users with a cursor in id order, starting after the user with id `after`.
+    pub async fn list_users<'a>(
+        after: Option<i32>,
+        limit: u16,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<UserEntity>, sqlx::Error> {
+        let query = format!(
+            r#"SELECT {USER_TABLE}.* FROM {USER_TABLE}
+            JOIN {MIRROR_CURSORS_TABLE} ON {MIRROR_CURSORS_TABLE}."user" = {USER_TABLE}.id
+            WHERE {USER_TABLE}.id > $1
+            ORDER BY {USER_TABLE}.id
+            LIMIT $2"#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query_as(&query)
+            .bind(after.unwrap_or(i32::MIN))
+            .bind(i64::from(limit))
+            .fetch_all(con)
+            .await
+    }
+
+    /// Forget the cursor of the user, so they are mirrored from the start.
+    pub async fn delete<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        let query = format!(r#"DELETE FROM {MIRROR_CURSORS_TABLE} WHERE "user" = $1"#);
+        let con = executor.get_con().await?;
+        sqlx::query(&query).bind(user_id).execute(con).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::persistence::sql::user::UserRepository;
+    use crate::persistence::sql::SqlDb;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_cursor_is_per_primary() {
+        let db = SqlDb::test().await;
+        let user = UserRepository::create(&Keypair::random().public_key(), &mut db.pool().into())
+            .await
+            .unwrap();
+        let primary = Keypair::random().public_key();
+        let other_primary = Keypair::random().public_key();
+
+        let cursor = MirrorCursorRepository::get(user.id, &primary, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(cursor, None);
+
+        MirrorCursorRepository::set(user.id, &primary, "41", &mut db.pool().into())
+            .await
+            .unwrap();
+        MirrorCursorRepository::set(user.id, &primary, "42", &mut db.pool().into())
+            .await
+            .unwrap();
+        let cursor = MirrorCursorRepository::get(user.id, &primary, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(cursor.as_deref(), Some("42"));
+
+        // Moving to another primary replaces the cursor.
+        MirrorCursorRepository::set(user.id, &other_primary, "7", &mut db.pool().into())
+            .await
+            .unwrap();
+        let cursor = MirrorCursorRepository::get(user.id, &primary, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(cursor, None);
+
+        let other = UserRepository::create(&Keypair::random().public_key(), &mut db.pool().into())
+            .await
+            .unwrap();
+        MirrorCursorRepository::set(other.id, &primary, "1", &mut db.pool().into())
+            .await
+            .unwrap();
+        UserRepository::create(&Keypair::random().public_key(), &mut db.pool().into())
+            .await
+            .unwrap();
+        let users = MirrorCursorRepository::list_users(None, 10, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(
+            users.iter().map(|user| user.id).collect::<Vec<_>>(),
+            vec![user.id, other.id]
+        );
+        let users = MirrorCursorRepository::list_users(Some(user.id), 10, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, other.id);
+
+        MirrorCursorRepository::delete(user.id, &mut db.pool().into())
+            .await
+            .unwrap();
+        let cursor = MirrorCursorRepository::get(user.id, &other_primary, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(cursor, None);
+    }
+}
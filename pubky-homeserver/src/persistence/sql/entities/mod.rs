@@ -5,7 +5,19 @@
 //! - [`user`]: User accounts keyed by Ed25519 public key, with quota tracking.
 //! - [`entry`]: File metadata (path, content hash, MIME type, timestamps).
 //! - [`signup_code`]: Token-gated registration codes.
+//! - [`path_quota`]: Per-user storage limits for directory prefixes.
+//! - [`user_usage`]: Per-user, per-day transfer and request counters.
+//! - [`banned_key`]: Public keys that are not allowed to sign up.
+//! - [`content_block`]: Blocklist of content hashes and path globs for takedowns.
+//! - [`mirror_cursor`]: Position in the primary's event stream of mirrored users.
+//! - [`republish_status`]: Per-user result of republishing their pkarr record.
 
+pub mod banned_key;
+pub mod content_block;
 pub mod entry;
+pub mod mirror_cursor;
+pub mod path_quota;
+pub mod republish_status;
 pub mod signup_code;
 pub mod user;
+pub mod user_usage;
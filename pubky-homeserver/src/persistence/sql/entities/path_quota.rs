@@ -0,0 +1,371 @@
+use pubky_common::crypto::PublicKey;
+use sea_query::Iden;
+use sqlx::{postgres::PgRow, FromRow, Row};
+
+use crate::persistence::sql::entities::entry::ENTRY_TABLE;
+use crate::persistence::sql::entities::user::{UserRepository, USER_TABLE};
+use crate::persistence::sql::UnifiedExecutor;
+use crate::services::user_service::FILE_METADATA_SIZE;
+use crate::shared::webdav::StoragePath;
+
+pub const PATH_QUOTA_TABLE: &str = "path_quotas";
+
+/// Storage limit of a user for everything below a directory prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathQuotaEntity {
+    /// Directory prefix, always ending with `/`.
+    pub path: StoragePath,
+    /// Maximum storage below `path` in MB.
+    pub max_mb: u64,
+    /// Storage used below `path`, updated by every write and delete.
+    /// Ignored by [`PathQuotaRepository::set`], which counts it itself.
+    pub used_bytes: u64,
+}
+
+impl FromRow<'_, PgRow> for PathQuotaEntity {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let path: String = row.try_get(PathQuotaIden::Path.to_string().as_str())?;
+        let path = StoragePath::new(&path).map_err(|e| sqlx::Error::Decode(e.into()))?;
+        let max_mb: i32 = row.try_get(PathQuotaIden::MaxMb.to_string().as_str())?;
+        let used_bytes: i64 = row.try_get(PathQuotaIden::UsedBytes.to_string().as_str())?;
+        Ok(Self {
+            path,
+            max_mb: max_mb.max(0) as u64,
+            used_bytes: used_bytes.max(0) as u64,
+        })
+    }
+}
+
+/// Repository for the per-user path prefix quotas.
+pub(crate) struct PathQuotaRepository;
+
+impl PathQuotaRepository {
+    /// Set the quota of `path` for a user, replacing an existing one.
+    /// A new quota starts with the usage of the entries already below `path`.
+    /// Returns `RowNotFound` if the user doesn't exist.
+    pub async fn set<'a>(
+        public_key: &PublicKey,
+        quota: &PathQuotaEntity,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        let query = format!(
+            r#"INSERT INTO {PATH_QUOTA_TABLE} (user_id, path, max_mb, used_bytes)
+            SELECT u.id, $2, $3, (
+                SELECT COALESCE(SUM(e.content_length + {FILE_METADATA_SIZE}), 0)::bigint
+                FROM {ENTRY_TABLE} e WHERE e."user" = u.id AND starts_with(e.path, $2)
+            )
+            FROM {USER_TABLE} u WHERE u.public_key = $1
+            ON CONFLICT (user_id, path) DO UPDATE SET max_mb = EXCLUDED.max_mb"#
+        );
+        let con = executor.get_con().await?;
+        let result = sqlx::query(&query)
+            .bind(public_key.z32())
+            .bind(quota.path.as_str())
+            .bind(i32::try_from(quota.max_mb).unwrap_or(i32::MAX))
+            .execute(con)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound);
+        }
+        Ok(())
+    }
+
+    /// Set the quota of `path` for a user unless that would give them more than
+    /// `max_quotas` quotas. Returns `false` if the limit was reached.
+    ///
+    /// Must be called within a transaction: the user row stays locked until it ends,
+    /// so concurrent calls for the same user can't both pass the count.
+    /// Returns `RowNotFound` if the user doesn't exist.
+    pub async fn set_within_limit<'a>(
+        public_key: &PublicKey,
+        quota: &PathQuotaEntity,
+        max_quotas: u64,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<bool, sqlx::Error> {
+        let user = UserRepository::get_for_update(public_key, executor).await?;
+        let query =
+            format!(r#"SELECT COUNT(*) FROM {PATH_QUOTA_TABLE} WHERE user_id = $1 AND path <> $2"#);
+        let con = executor.get_con().await?;
+        let others: i64 = sqlx::query_scalar(&query)
+            .bind(user.id)
+            .bind(quota.path.as_str())
+            .fetch_one(con)
+            .await?;
+        if others as u64 >= max_quotas {
+            return Ok(false);
+        }
+        Self::set(public_key, quota, executor).await?;
+        Ok(true)
+    }
+
+    /// Remove the quota of `path` for a user. Returns whether a quota existed.
+    pub async fn delete<'a>(
+        public_key: &PublicKey,
+        path: &StoragePath,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<bool, sqlx::Error> {
+        let query = format!(
+            r#"DELETE FROM {PATH_QUOTA_TABLE} q USING {USER_TABLE}
+            WHERE q.user_id = {USER_TABLE}.id AND {USER_TABLE}.public_key = $1 AND q.path = $2"#
+        );
+        let con = executor.get_con().await?;
+        let result = sqlx::query(&query)
+            .bind(public_key.z32())
+            .bind(path.as_str())
+            .execute(con)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// All quotas of a user, ordered by path.
+    pub async fn list<'a>(
+        public_key: &PublicKey,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<PathQuotaEntity>, sqlx::Error> {
+        let query = format!(
+            r#"SELECT q.path, q.max_mb, q.used_bytes FROM {PATH_QUOTA_TABLE} q
+            JOIN {USER_TABLE} ON {USER_TABLE}.id = q.user_id
+            WHERE {USER_TABLE}.public_key = $1
+            ORDER BY q.path COLLATE "C""#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query_as(&query)
+            .bind(public_key.z32())
+            .fetch_all(con)
+            .await
+    }
+
+    /// The quotas of a user whose prefix contains `path`.
+    pub async fn list_matching<'a>(
+        user_id: i32,
+        path: &StoragePath,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<PathQuotaEntity>, sqlx::Error> {
+        let query = format!(
+            r#"SELECT path, max_mb, used_bytes FROM {PATH_QUOTA_TABLE}
+            WHERE user_id = $1 AND starts_with($2, path)"#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query_as(&query)
+            .bind(user_id)
+            .bind(path.as_str())
+            .fetch_all(con)
+            .await
+    }
+
+    /// Add `bytes_delta` to the usage of every quota of a user whose prefix contains `path`.
+    pub async fn add_used_bytes<'a>(
+        user_id: i32,
+        path: &StoragePath,
+        bytes_delta: i64,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        let query = format!(
+            r#"UPDATE {PATH_QUOTA_TABLE} SET used_bytes = GREATEST(used_bytes + $3, 0)
+            WHERE user_id = $1 AND starts_with($2, path)"#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query(&query)
+            .bind(user_id)
+            .bind(path.as_str())
+            .bind(bytes_delta)
+            .execute(con)
+            .await?;
+        Ok(())
+    }
+
+    /// Recount the usage of all quotas of a user from their entries.
+    pub async fn recount_used_bytes<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        let query = format!(
+            r#"UPDATE {PATH_QUOTA_TABLE} q SET used_bytes = (
+                SELECT COALESCE(SUM(e.content_length + {FILE_METADATA_SIZE}), 0)::bigint
+                FROM {ENTRY_TABLE} e WHERE e."user" = q.user_id AND starts_with(e.path, q.path)
+            )
+            WHERE q.user_id = $1"#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query(&query).bind(user_id).execute(con).await?;
+        Ok(())
+    }
+}
+
+/// Iden for the path quota table.
+#[derive(Iden)]
+pub enum PathQuotaIden {
+    #[iden = "user_id"]
+    User,
+    Path,
+    MaxMb,
+    UsedBytes,
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::persistence::sql::{entry::EntryRepository, uexecutor, SqlDb};
+
+    fn quota(path: &str, max_mb: u64) -> PathQuotaEntity {
+        PathQuotaEntity {
+            path: StoragePath::new(path).unwrap(),
+            max_mb,
+            used_bytes: 0,
+        }
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_set_list_and_delete() {
+        let db = SqlDb::test().await;
+        let pubkey = Keypair::random().public_key();
+        let user = UserRepository::create(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+
+        PathQuotaRepository::set(&pubkey, &quota("/pub/b/", 10), &mut db.pool().into())
+            .await
+            .unwrap();
+        PathQuotaRepository::set(&pubkey, &quota("/pub/a/", 10), &mut db.pool().into())
+            .await
+            .unwrap();
+        // Setting an existing path replaces its limit.
+        PathQuotaRepository::set(&pubkey, &quota("/pub/a/", 20), &mut db.pool().into())
+            .await
+            .unwrap();
+
+        let quotas = PathQuotaRepository::list(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(quotas, vec![quota("/pub/a/", 20), quota("/pub/b/", 10)]);
+
+        let file = StoragePath::new("/pub/a/photo.jpg").unwrap();
+        let matching = PathQuotaRepository::list_matching(user.id, &file, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(matching, vec![quota("/pub/a/", 20)]);
+
+        let path = StoragePath::new("/pub/a/").unwrap();
+        assert!(
+            PathQuotaRepository::delete(&pubkey, &path, &mut db.pool().into())
+                .await
+                .unwrap()
+        );
+        assert!(
+            !PathQuotaRepository::delete(&pubkey, &path, &mut db.pool().into())
+                .await
+                .unwrap()
+        );
+        let quotas = PathQuotaRepository::list(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(quotas, vec![quota("/pub/b/", 10)]);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_set_for_unknown_user() {
+        let db = SqlDb::test().await;
+        let pubkey = Keypair::random().public_key();
+        let result =
+            PathQuotaRepository::set(&pubkey, &quota("/pub/a/", 10), &mut db.pool().into()).await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_set_within_limit() {
+        let db = SqlDb::test().await;
+        let pubkey = Keypair::random().public_key();
+        UserRepository::create(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+
+        let set = |path: &'static str, max_mb: u64| {
+            let db = db.clone();
+            let pubkey = pubkey.clone();
+            async move {
+                let mut tx = db.pool().begin().await.unwrap();
+                let set = PathQuotaRepository::set_within_limit(
+                    &pubkey,
+                    &quota(path, max_mb),
+                    2,
+                    uexecutor!(tx),
+                )
+                .await
+                .unwrap();
+                tx.commit().await.unwrap();
+                set
+            }
+        };
+
+        assert!(set("/pub/a/", 10).await);
+        assert!(set("/pub/b/", 10).await);
+        // The limit is reached for new paths, existing ones can still be replaced.
+        assert!(!set("/pub/c/", 10).await);
+        assert!(set("/pub/a/", 20).await);
+
+        let quotas = PathQuotaRepository::list(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(quotas, vec![quota("/pub/a/", 20), quota("/pub/b/", 10)]);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_used_bytes_counter() {
+        let db = SqlDb::test().await;
+        let pubkey = Keypair::random().public_key();
+        let user = UserRepository::create(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        let file = StoragePath::new("/pub/a/photo.jpg").unwrap();
+        EntryRepository::create(
+            user.id,
+            &file,
+            &pubky_common::crypto::hash(&[0; 10]),
+            10,
+            "image/jpeg",
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let used = |bytes: u64| PathQuotaEntity {
+            used_bytes: bytes,
+            ..quota("/pub/a/", 10)
+        };
+
+        // A new quota counts the entries already below it.
+        PathQuotaRepository::set(&pubkey, &quota("/pub/a/", 10), &mut db.pool().into())
+            .await
+            .unwrap();
+        let quotas = PathQuotaRepository::list(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(quotas, vec![used(10 + FILE_METADATA_SIZE)]);
+
+        // Only quotas containing the path are updated, and never below zero.
+        let other = StoragePath::new("/pub/b/note.txt").unwrap();
+        PathQuotaRepository::add_used_bytes(user.id, &other, 100, &mut db.pool().into())
+            .await
+            .unwrap();
+        PathQuotaRepository::add_used_bytes(user.id, &file, -1000, &mut db.pool().into())
+            .await
+            .unwrap();
+        let quotas = PathQuotaRepository::list(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(quotas, vec![used(0)]);
+
+        PathQuotaRepository::recount_used_bytes(user.id, &mut db.pool().into())
+            .await
+            .unwrap();
+        let matching = PathQuotaRepository::list_matching(user.id, &file, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(matching, vec![used(10 + FILE_METADATA_SIZE)]);
+    }
+}
@@ -0,0 +1,610 @@
+use std::time::Duration;
+
+use pubky_common::crypto::PublicKey;
+use pubky_common::republish_status::{RepublishOutcome, RepublishStatus};
+use sea_query::Iden;
+use sqlx::{postgres::PgRow, types::chrono::NaiveDateTime, FromRow, Row};
+
+use crate::constants::{DEFAULT_LIST_LIMIT, DEFAULT_MAX_LIST_LIMIT};
+use crate::persistence::files::events::events_repository::EVENT_TABLE;
+use crate::persistence::sql::{user::USER_TABLE, UnifiedExecutor};
+
+pub const REPUBLISH_STATUS_TABLE: &str = "user_republish_status";
+
+/// Columns selected for a `RepublishStatusEntity`, `s` being this table and `u` the users.
+const SELECT_COLUMNS: &str = r#"s."user", u.public_key, s.last_outcome, s.last_attempt_at,
+    s.last_success_at, s.last_failure_at, s.last_failure_reason, s.record_timestamp"#;
+
+/// When a user's record was last refreshed on the DHT: the newer of the last
+/// successful republish and the record's own timestamp. Records never seen on
+/// the DHT fall back to the last attempt, so missing records are retried
+/// like old ones instead of on every run.
+const REFRESHED_AT: &str =
+    "COALESCE(GREATEST(s.last_success_at, s.record_timestamp), s.last_attempt_at)";
+
+/// Current time in UTC, matching how timestamps are converted to Unix seconds.
+const NOW_UTC: &str = "(CURRENT_TIMESTAMP AT TIME ZONE 'UTC')";
+
+/// Result of the last attempts to republish a user's pkarr record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepublishStatusEntity {
+    pub user_id: i32,
+    pub public_key: PublicKey,
+    pub last_outcome: RepublishOutcome,
+    pub last_attempt_at: NaiveDateTime,
+    pub last_success_at: Option<NaiveDateTime>,
+    pub last_failure_at: Option<NaiveDateTime>,
+    pub last_failure_reason: Option<String>,
+    /// Timestamp of the record last found on the DHT.
+    pub record_timestamp: Option<NaiveDateTime>,
+}
+
+impl FromRow<'_, PgRow> for RepublishStatusEntity {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let raw_pubkey: String = row.try_get("public_key")?;
+        let public_key = PublicKey::try_from_z32(raw_pubkey.as_str())
+            .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+        let raw_outcome: String = row.try_get("last_outcome")?;
+        let last_outcome = raw_outcome
+            .parse()
+            .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+        Ok(Self {
+            user_id: row.try_get("user")?,
+            public_key,
+            last_outcome,
+            last_attempt_at: row.try_get("last_attempt_at")?,
+            last_success_at: row.try_get("last_success_at")?,
+            last_failure_at: row.try_get("last_failure_at")?,
+            last_failure_reason: row.try_get("last_failure_reason")?,
+            record_timestamp: row.try_get("record_timestamp")?,
+        })
+    }
+}
+
+impl From<RepublishStatusEntity> for RepublishStatus {
+    fn from(entity: RepublishStatusEntity) -> Self {
+        let unix = |time: NaiveDateTime| time.and_utc().timestamp().max(0) as u64;
+        Self {
+            last_outcome: Some(entity.last_outcome),
+            last_attempt_at: Some(unix(entity.last_attempt_at)),
+            last_success_at: entity.last_success_at.map(unix),
+            last_failure_at: entity.last_failure_at.map(unix),
+            last_failure_reason: entity.last_failure_reason,
+            record_timestamp: entity.record_timestamp.map(unix),
+        }
+    }
+}
+
+/// Filter and pagination for [`RepublishStatusRepository::list`].
+#[derive(Debug, Clone, Default)]
+pub struct RepublishStatusListQuery {
+    /// Only users whose last attempt had this outcome.
+    pub outcome: Option<RepublishOutcome>,
+    /// Only users whose last attempt needs attention (missing, invalid or failed).
+    pub issues_only: bool,
+    pub limit: Option<u16>,
+    /// Id of the last user of the previous page.
+    pub cursor: Option<i32>,
+}
+
+/// Which users [`RepublishStatusRepository::due`] returns.
+#[derive(Debug, Clone)]
+pub struct RepublishDueQuery {
+    /// Only users whose record was refreshed before this time, or never.
+    pub refreshed_before: NaiveDateTime,
+    /// Users with events since this time come first.
+    pub active_since: Option<NaiveDateTime>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RepublishStatusPage {
+    pub items: Vec<RepublishStatusEntity>,
+    pub next_cursor: Option<i32>,
+}
+
+/// Repository for the per-user republish results of the user keys republisher.
+pub(crate) struct RepublishStatusRepository;
+
+impl RepublishStatusRepository {
+    /// Record an attempt to republish the record of the user with `public_key`.
+    ///
+    /// `failure_reason` marks the attempt as failed. Published attempts update the
+    /// last success. Skipped attempts only update the last outcome.
+    /// `record_timestamp` is the timestamp of the record found on the DHT, if any.
+    /// Unknown users are ignored.
+    pub async fn record<'a>(
+        public_key: &PublicKey,
+        outcome: RepublishOutcome,
+        failure_reason: Option<&str>,
+        record_timestamp: Option<NaiveDateTime>,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        let query = format!(
+            r#"INSERT INTO {REPUBLISH_STATUS_TABLE} AS s ("user", last_outcome, last_attempt_at,
+                last_success_at, last_failure_at, last_failure_reason, record_timestamp)
+            SELECT id, $2, {NOW_UTC},
+                CASE WHEN $3 THEN {NOW_UTC} END,
+                CASE WHEN $4::text IS NOT NULL THEN {NOW_UTC} END,
+                $4::text,
+                $5
+            FROM {USER_TABLE} WHERE public_key = $1
+            ON CONFLICT ("user") DO UPDATE SET
+                last_outcome = EXCLUDED.last_outcome,
+                last_attempt_at = EXCLUDED.last_attempt_at,
+                last_success_at = COALESCE(EXCLUDED.last_success_at, s.last_success_at),
+                last_failure_at = COALESCE(EXCLUDED.last_failure_at, s.last_failure_at),
+                last_failure_reason = COALESCE(EXCLUDED.last_failure_reason, s.last_failure_reason),
+                record_timestamp = COALESCE(EXCLUDED.record_timestamp, s.record_timestamp)"#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query(&query)
+            .bind(public_key.z32())
+            .bind(outcome.as_str())
+            .bind(outcome == RepublishOutcome::Published)
+            .bind(failure_reason)
+            .bind(record_timestamp)
+            .execute(con)
+            .await?;
+        Ok(())
+    }
+
+    /// Claim an attempt for the user with `user_id` by setting their last attempt to now,
+    /// unless the last attempt was less than `min_interval` ago. Returns whether the
+    /// attempt was claimed, so concurrent callers can't both claim it.
+    ///
+    /// Users without a status get one with the `missing` outcome until the attempt
+    /// is recorded.
+    pub async fn claim_attempt<'a>(
+        user_id: i32,
+        min_interval: Duration,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<bool, sqlx::Error> {
+        let query = format!(
+            r#"INSERT INTO {REPUBLISH_STATUS_TABLE} AS s ("user", last_outcome, last_attempt_at)
+            VALUES ($1, $2, {NOW_UTC})
+            ON CONFLICT ("user") DO UPDATE SET last_attempt_at = EXCLUDED.last_attempt_at
+            WHERE s.last_attempt_at <= EXCLUDED.last_attempt_at - make_interval(secs => $3)
+            RETURNING s."user""#
+        );
+        let con = executor.get_con().await?;
+        let claimed: Option<i32> = sqlx::query_scalar(&query)
+            .bind(user_id)
+            .bind(RepublishOutcome::Missing.as_str())
+            .bind(min_interval.as_secs_f64())
+            .fetch_optional(con)
+            .await?;
+        Ok(claimed.is_some())
+    }
+
+    /// Get the republish status of a user. `None` if their record was never republished.
+    pub async fn get<'a>(
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<RepublishStatusEntity>, sqlx::Error> {
+        let query = format!(
+            r#"SELECT {SELECT_COLUMNS} FROM {REPUBLISH_STATUS_TABLE} s
+            JOIN {USER_TABLE} u ON u.id = s."user"
+            WHERE s."user" = $1"#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query_as(&query)
+            .bind(user_id)
+            .fetch_optional(con)
+            .await
+    }
+
+    /// Number of users per outcome of their last attempt. Outcomes without users are omitted.
+    pub async fn count_by_outcome<'a>(
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<(RepublishOutcome, u64)>, sqlx::Error> {
+        let query = format!(
+            "SELECT last_outcome, COUNT(*) AS count FROM {REPUBLISH_STATUS_TABLE} GROUP BY last_outcome"
+        );
+        let con = executor.get_con().await?;
+        let rows = sqlx::query(&query).fetch_all(con).await?;
+        rows.iter()
+            .map(|row| {
+                let raw_outcome: String = row.try_get("last_outcome")?;
+                let outcome = raw_outcome
+                    .parse()
+                    .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+                let count: i64 = row.try_get("count")?;
+                Ok((outcome, count as u64))
+            })
+            .collect()
+    }
+
+    /// Number of republish attempts since `since`, whether by the republisher or
+    /// requested by users.
+    ///
+    /// Only the last attempt of each user is known, so attempts of users
+    /// republished more than once in the period are counted once.
+    pub async fn count_attempts_since<'a>(
+        since: NaiveDateTime,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<u64, sqlx::Error> {
+        let query =
+            format!("SELECT COUNT(*) FROM {REPUBLISH_STATUS_TABLE} WHERE last_attempt_at >= $1");
+        let con = executor.get_con().await?;
+        let count: i64 = sqlx::query_scalar(&query)
+            .bind(since)
+            .fetch_one(con)
+            .await?;
+        Ok(count as u64)
+    }
+
+    /// Users whose record is due for republishing, most urgent first.
+    ///
+    /// Recently active users come first, then users by how long ago their record
+    /// was refreshed, users never attempted before anyone else.
+    pub async fn due<'a>(
+        due_query: RepublishDueQuery,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Vec<PublicKey>, sqlx::Error> {
+        let query = format!(
+            r#"WITH active AS (
+                SELECT DISTINCT "user" FROM {EVENT_TABLE}
+                WHERE $2::timestamp IS NOT NULL AND created_at >= $2
+            )
+            SELECT u.public_key FROM {USER_TABLE} u
+            LEFT JOIN {REPUBLISH_STATUS_TABLE} s ON s."user" = u.id
+            LEFT JOIN active a ON a."user" = u.id
+            WHERE {REFRESHED_AT} IS NULL OR {REFRESHED_AT} < $1
+            ORDER BY a."user" IS NULL, {REFRESHED_AT} ASC NULLS FIRST, u.id ASC
+            LIMIT $3"#
+        );
+        let con = executor.get_con().await?;
+        let rows: Vec<String> = sqlx::query_scalar(&query)
+            .bind(due_query.refreshed_before)
+            .bind(due_query.active_since)
+            .bind(due_query.limit.map(|limit| limit as i64))
+            .fetch_all(con)
+            .await?;
+        rows.iter()
+            .map(|raw| PublicKey::try_from_z32(raw).map_err(|e| sqlx::Error::Decode(Box::new(e))))
+            .collect()
+    }
+
+    /// List republish statuses in user id order, filtered and paginated.
+    pub async fn list<'a>(
+        list_query: RepublishStatusListQuery,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<RepublishStatusPage, sqlx::Error> {
+        let limit = list_query
+            .limit
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .min(DEFAULT_MAX_LIST_LIMIT);
+        let issues: Vec<&str> = RepublishOutcome::ALL
+            .iter()
+            .filter(|outcome| outcome.is_issue())
+            .map(RepublishOutcome::as_str)
+            .collect();
+        let query = format!(
+            r#"SELECT {SELECT_COLUMNS} FROM {REPUBLISH_STATUS_TABLE} s
+            JOIN {USER_TABLE} u ON u.id = s."user"
+            WHERE ($1::text IS NULL OR s.last_outcome = $1)
+                AND (NOT $2 OR s.last_outcome = ANY($3))
+                AND ($4::int IS NULL OR s."user" > $4)
+            ORDER BY s."user" ASC
+            LIMIT $5"#
+        );
+        let con = executor.get_con().await?;
+        let mut items: Vec<RepublishStatusEntity> = sqlx::query_as(&query)
+            .bind(list_query.outcome.map(|outcome| outcome.as_str()))
+            .bind(list_query.issues_only)
+            .bind(issues)
+            .bind(list_query.cursor)
+            .bind(i64::from(limit) + 1)
+            .fetch_all(con)
+            .await?;
+        let next_cursor = if items.len() > limit as usize {
+            items.truncate(limit as usize);
+            items.last().map(|status| status.user_id)
+        } else {
+            None
+        };
+        Ok(RepublishStatusPage { items, next_cursor })
+    }
+}
+
+#[derive(Iden)]
+pub enum RepublishStatusIden {
+    User,
+    LastOutcome,
+    LastAttemptAt,
+    LastSuccessAt,
+    LastFailureAt,
+    LastFailureReason,
+    RecordTimestamp,
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use sqlx::types::chrono::Utc;
+
+    use super::*;
+    use crate::persistence::files::events::{EventRepository, EventType};
+    use crate::persistence::sql::user::UserRepository;
+    use crate::persistence::sql::SqlDb;
+    use crate::shared::webdav::{EntryPath, StoragePath};
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_claim_attempt_once_per_interval() {
+        let db = SqlDb::test().await;
+        let public_key = Keypair::random().public_key();
+        let user = UserRepository::create(&public_key, &mut db.pool().into())
+            .await
+            .unwrap();
+        let interval = Duration::from_secs(60);
+
+        let claim = || async {
+            RepublishStatusRepository::claim_attempt(user.id, interval, &mut db.pool().into())
+                .await
+                .unwrap()
+        };
+        let (first, second) = tokio::join!(claim(), claim());
+        assert!(first ^ second);
+        assert!(!claim().await);
+
+        // Claimable again once the last attempt is older than the interval.
+        sqlx::query(&format!(
+            r#"UPDATE {REPUBLISH_STATUS_TABLE} SET last_attempt_at = last_attempt_at - interval '61 seconds'"#
+        ))
+        .execute(db.pool())
+        .await
+        .unwrap();
+        assert!(claim().await);
+        assert!(RepublishStatusRepository::claim_attempt(
+            user.id,
+            Duration::ZERO,
+            &mut db.pool().into()
+        )
+        .await
+        .unwrap());
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_record_keeps_last_success_and_failure() {
+        let db = SqlDb::test().await;
+        let public_key = Keypair::random().public_key();
+        let user = UserRepository::create(&public_key, &mut db.pool().into())
+            .await
+            .unwrap();
+
+        let status = RepublishStatusRepository::get(user.id, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(status, None);
+
+        RepublishStatusRepository::record(
+            &public_key,
+            RepublishOutcome::Failed,
+            Some("too few nodes"),
+            None,
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        RepublishStatusRepository::record(
+            &public_key,
+            RepublishOutcome::Published,
+            None,
+            None,
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let status = RepublishStatusRepository::get(user.id, &mut db.pool().into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.public_key, public_key);
+        assert_eq!(status.last_outcome, RepublishOutcome::Published);
+        assert!(status.last_success_at.is_some());
+        assert!(status.last_failure_at.is_some());
+        assert_eq!(status.last_failure_reason.as_deref(), Some("too few nodes"));
+
+        // Unknown users are ignored.
+        RepublishStatusRepository::record(
+            &Keypair::random().public_key(),
+            RepublishOutcome::Missing,
+            Some("no packet"),
+            None,
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let counts = RepublishStatusRepository::count_by_outcome(&mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(counts, vec![(RepublishOutcome::Published, 1)]);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_list_filters_and_paginates() {
+        let db = SqlDb::test().await;
+        let outcomes = [
+            RepublishOutcome::Published,
+            RepublishOutcome::Missing,
+            RepublishOutcome::Skipped,
+            RepublishOutcome::Failed,
+        ];
+        for outcome in outcomes {
+            let public_key = Keypair::random().public_key();
+            UserRepository::create(&public_key, &mut db.pool().into())
+                .await
+                .unwrap();
+            let reason = outcome.is_issue().then_some("reason");
+            RepublishStatusRepository::record(
+                &public_key,
+                outcome,
+                reason,
+                None,
+                &mut db.pool().into(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let page = RepublishStatusRepository::list(
+            RepublishStatusListQuery {
+                issues_only: true,
+                limit: Some(1),
+                ..Default::default()
+            },
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].last_outcome, RepublishOutcome::Missing);
+        let page = RepublishStatusRepository::list(
+            RepublishStatusListQuery {
+                issues_only: true,
+                cursor: page.next_cursor,
+                ..Default::default()
+            },
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].last_outcome, RepublishOutcome::Failed);
+        assert_eq!(page.next_cursor, None);
+
+        let page = RepublishStatusRepository::list(
+            RepublishStatusListQuery {
+                outcome: Some(RepublishOutcome::Skipped),
+                ..Default::default()
+            },
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].last_outcome, RepublishOutcome::Skipped);
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_due_orders_active_users_then_oldest_records() {
+        let db = SqlDb::test().await;
+        let now = Utc::now().naive_utc();
+        let hours = |hours: i64| now - chrono::Duration::hours(hours);
+        let mut users = Vec::new();
+        // Never attempted, refreshed 10h, 5h and 1h ago, and an active user refreshed 4h ago.
+        for refreshed_at in [
+            None,
+            Some(hours(10)),
+            Some(hours(5)),
+            Some(hours(1)),
+            Some(hours(4)),
+        ] {
+            let public_key = Keypair::random().public_key();
+            let user = UserRepository::create(&public_key, &mut db.pool().into())
+                .await
+                .unwrap();
+            if let Some(refreshed_at) = refreshed_at {
+                RepublishStatusRepository::record(
+                    &public_key,
+                    RepublishOutcome::Published,
+                    None,
+                    Some(hours(24)),
+                    &mut db.pool().into(),
+                )
+                .await
+                .unwrap();
+                sqlx::query(&format!(
+                    r#"UPDATE {REPUBLISH_STATUS_TABLE}
+                    SET last_success_at = $2, last_attempt_at = $2 WHERE "user" = $1"#
+                ))
+                .bind(user.id)
+                .bind(refreshed_at)
+                .execute(db.pool())
+                .await
+                .unwrap();
+            }
+            users.push((user, public_key));
+        }
+        let path = EntryPath::new(users[4].1.clone(), StoragePath::new("/pub/a").unwrap());
+        EventRepository::create(
+            users[4].0.id,
+            EventType::Delete,
+            &path,
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let keys = |indexes: &[usize]| -> Vec<PublicKey> {
+            indexes.iter().map(|i| users[*i].1.clone()).collect()
+        };
+
+        let query = RepublishDueQuery {
+            refreshed_before: hours(3),
+            active_since: Some(hours(24 * 7)),
+            limit: None,
+        };
+        let due = RepublishStatusRepository::due(query.clone(), &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(due, keys(&[4, 0, 1, 2]));
+
+        let due = RepublishStatusRepository::due(
+            RepublishDueQuery {
+                active_since: None,
+                ..query.clone()
+            },
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(due, keys(&[0, 1, 2, 4]));
+
+        let due = RepublishStatusRepository::due(
+            RepublishDueQuery {
+                limit: Some(2),
+                ..query
+            },
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(due, keys(&[4, 0]));
+
+        // A recently signed record counts as refreshed, even without a recent republish.
+        RepublishStatusRepository::record(
+            &users[1].1,
+            RepublishOutcome::Skipped,
+            None,
+            Some(hours(2)),
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let due = RepublishStatusRepository::due(
+            RepublishDueQuery {
+                refreshed_before: hours(3),
+                active_since: None,
+                limit: None,
+            },
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(due, keys(&[0, 2, 4]));
+
+        let attempts = RepublishStatusRepository::count_attempts_since(
+            hours(2) - chrono::Duration::minutes(1),
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(attempts, 2);
+    }
+}
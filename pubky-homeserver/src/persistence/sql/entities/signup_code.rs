@@ -70,6 +70,11 @@ impl SignupCodeRepository {
                 SignupCodeIden::QuotaRateWrite,
                 SignupCodeIden::QuotaRateReadBurst,
                 SignupCodeIden::QuotaRateWriteBurst,
+                SignupCodeIden::QuotaRequestsPerMinute,
+                SignupCodeIden::QuotaEgressDailyMb,
+                SignupCodeIden::QuotaEgressMonthlyMb,
+                SignupCodeIden::QuotaIngressDailyMb,
+                SignupCodeIden::QuotaIngressMonthlyMb,
                 SignupCodeIden::AllowedWritePaths,
             ])
             .values(vec![
@@ -79,6 +84,11 @@ impl SignupCodeRepository {
                 SimpleExpr::Value(limits.rate_write_str().into()),
                 SimpleExpr::Value(limits.rate_read_burst_i32().into()),
                 SimpleExpr::Value(limits.rate_write_burst_i32().into()),
+                SimpleExpr::Value(limits.requests_per_minute_i32().into()),
+                SimpleExpr::Value(limits.egress_daily_mb_i32().into()),
+                SimpleExpr::Value(limits.egress_monthly_mb_i32().into()),
+                SimpleExpr::Value(limits.ingress_daily_mb_i32().into()),
+                SimpleExpr::Value(limits.ingress_monthly_mb_i32().into()),
                 SimpleExpr::Value(
                     limits
                         .allowed_write_paths_db()
@@ -114,6 +124,11 @@ impl SignupCodeRepository {
                 SignupCodeIden::QuotaRateWrite,
                 SignupCodeIden::QuotaRateReadBurst,
                 SignupCodeIden::QuotaRateWriteBurst,
+                SignupCodeIden::QuotaRequestsPerMinute,
+                SignupCodeIden::QuotaEgressDailyMb,
+                SignupCodeIden::QuotaEgressMonthlyMb,
+                SignupCodeIden::QuotaIngressDailyMb,
+                SignupCodeIden::QuotaIngressMonthlyMb,
                 SignupCodeIden::AllowedWritePaths,
             ])
             .and_where(Expr::col(SignupCodeIden::Id).eq(id.to_string()))
@@ -124,6 +139,41 @@ impl SignupCodeRepository {
         Ok(code)
     }
 
+    /// Get the signup code a user signed up with, if any.
+    /// The executor can either be db.pool() or a transaction.
+    pub async fn get_used_by<'a>(
+        used_by: &PublicKey,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<Option<SignupCodeEntity>, sqlx::Error> {
+        let statement = Query::select()
+            .from(SIGNUP_CODE_TABLE)
+            .columns([
+                SignupCodeIden::Id,
+                SignupCodeIden::CreatedAt,
+                SignupCodeIden::UsedAt,
+                SignupCodeIden::UsedBy,
+                SignupCodeIden::QuotaStorageMb,
+                SignupCodeIden::QuotaRateRead,
+                SignupCodeIden::QuotaRateWrite,
+                SignupCodeIden::QuotaRateReadBurst,
+                SignupCodeIden::QuotaRateWriteBurst,
+                SignupCodeIden::QuotaRequestsPerMinute,
+                SignupCodeIden::QuotaEgressDailyMb,
+                SignupCodeIden::QuotaEgressMonthlyMb,
+                SignupCodeIden::QuotaIngressDailyMb,
+                SignupCodeIden::QuotaIngressMonthlyMb,
+                SignupCodeIden::AllowedWritePaths,
+            ])
+            .and_where(Expr::col(SignupCodeIden::UsedBy).eq(used_by.z32()))
+            .limit(1)
+            .to_owned();
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        sqlx::query_as_with(&query, values)
+            .fetch_optional(con)
+            .await
+    }
+
     /// List signup codes in token order.
     /// The executor can either be db.pool() or a transaction.
     pub async fn list<'a>(
@@ -142,6 +192,11 @@ impl SignupCodeRepository {
                 SignupCodeIden::QuotaRateWrite,
                 SignupCodeIden::QuotaRateReadBurst,
                 SignupCodeIden::QuotaRateWriteBurst,
+                SignupCodeIden::QuotaRequestsPerMinute,
+                SignupCodeIden::QuotaEgressDailyMb,
+                SignupCodeIden::QuotaEgressMonthlyMb,
+                SignupCodeIden::QuotaIngressDailyMb,
+                SignupCodeIden::QuotaIngressMonthlyMb,
                 SignupCodeIden::AllowedWritePaths,
             ])
             .order_by(SignupCodeIden::Id, Order::Asc)
@@ -260,6 +315,11 @@ pub enum SignupCodeIden {
     QuotaRateReadBurst,
     QuotaRateWriteBurst,
     AllowedWritePaths,
+    QuotaRequestsPerMinute,
+    QuotaEgressDailyMb,
+    QuotaEgressMonthlyMb,
+    QuotaIngressDailyMb,
+    QuotaIngressMonthlyMb,
 }
 
 /// Signup code id in the format of "JZY0-D6MY-ZFNG".
@@ -359,6 +419,16 @@ pub struct SignupCodeEntity {
     pub quota_rate_read_burst: Option<i32>,
     /// Per-user write rate burst override. `None` = default (burst = rate).
     pub quota_rate_write_burst: Option<i32>,
+    /// Per-user request-count limit per minute. `None` = Default.
+    pub quota_requests_per_minute: Option<i32>,
+    /// Per-user daily egress cap in MB. `None` = Default.
+    pub quota_egress_daily_mb: Option<i32>,
+    /// Per-user monthly egress cap in MB. `None` = Default.
+    pub quota_egress_monthly_mb: Option<i32>,
+    /// Per-user daily ingress cap in MB. `None` = Default.
+    pub quota_ingress_daily_mb: Option<i32>,
+    /// Per-user monthly ingress cap in MB. `None` = Default.
+    pub quota_ingress_monthly_mb: Option<i32>,
     /// Allowed write paths as JSON array string. `None` = unrestricted.
     pub allowed_write_paths: Option<String>,
 }
@@ -374,6 +444,13 @@ impl SignupCodeEntity {
             self.quota_rate_write_burst,
             self.allowed_write_paths.clone(),
         )
+        .with_usage_columns(
+            self.quota_requests_per_minute,
+            self.quota_egress_daily_mb,
+            self.quota_egress_monthly_mb,
+            self.quota_ingress_daily_mb,
+            self.quota_ingress_monthly_mb,
+        )
     }
 }
 
@@ -408,6 +485,16 @@ impl FromRow<'_, PgRow> for SignupCodeEntity {
             row.try_get(SignupCodeIden::QuotaRateReadBurst.to_string().as_str())?;
         let quota_rate_write_burst: Option<i32> =
             row.try_get(SignupCodeIden::QuotaRateWriteBurst.to_string().as_str())?;
+        let quota_requests_per_minute: Option<i32> =
+            row.try_get(SignupCodeIden::QuotaRequestsPerMinute.to_string().as_str())?;
+        let quota_egress_daily_mb: Option<i32> =
+            row.try_get(SignupCodeIden::QuotaEgressDailyMb.to_string().as_str())?;
+        let quota_egress_monthly_mb: Option<i32> =
+            row.try_get(SignupCodeIden::QuotaEgressMonthlyMb.to_string().as_str())?;
+        let quota_ingress_daily_mb: Option<i32> =
+            row.try_get(SignupCodeIden::QuotaIngressDailyMb.to_string().as_str())?;
+        let quota_ingress_monthly_mb: Option<i32> =
+            row.try_get(SignupCodeIden::QuotaIngressMonthlyMb.to_string().as_str())?;
         let allowed_write_paths: Option<String> =
             row.try_get(SignupCodeIden::AllowedWritePaths.to_string().as_str())?;
 
@@ -421,6 +508,11 @@ impl FromRow<'_, PgRow> for SignupCodeEntity {
             quota_rate_write,
             quota_rate_read_burst,
             quota_rate_write_burst,
+            quota_requests_per_minute,
+            quota_egress_daily_mb,
+            quota_egress_monthly_mb,
+            quota_ingress_daily_mb,
+            quota_ingress_monthly_mb,
             allowed_write_paths,
         })
     }
@@ -533,8 +625,18 @@ mod tests {
             .await
             .unwrap();
         assert_eq!(updated_code.id, signup_code_id);
-        assert_eq!(updated_code.used_by, Some(user_pubkey));
+        assert_eq!(updated_code.used_by, Some(user_pubkey.clone()));
         assert_eq!(updated_code.used_at, Some(used_at));
+
+        let redeemed = SignupCodeRepository::get_used_by(&user_pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(redeemed.map(|code| code.id), Some(signup_code_id));
+        let other = Keypair::random().public_key();
+        let none = SignupCodeRepository::get_used_by(&other, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert!(none.is_none());
     }
 
     #[tokio::test]
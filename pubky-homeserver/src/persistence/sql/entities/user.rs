@@ -1,16 +1,20 @@
 use pubky_common::crypto::PublicKey;
-use sea_query::{Expr, Iden, PostgresQueryBuilder, Query, SimpleExpr};
+use sea_query::{Expr, Iden, Order, PostgresQueryBuilder, Query, SimpleExpr};
 use sea_query_binder::SqlxBinder;
-use sqlx::{postgres::PgRow, FromRow, Row};
+use sqlx::{postgres::PgRow, types::chrono::NaiveDateTime, FromRow, Row};
 
-use crate::persistence::sql::UnifiedExecutor;
+use crate::constants::{DEFAULT_LIST_LIMIT, DEFAULT_MAX_LIST_LIMIT};
+use crate::persistence::sql::{
+    entities::signup_code::{SignupCode, SignupCodeIden, SIGNUP_CODE_TABLE},
+    UnifiedExecutor,
+};
 use crate::shared::user_quota::UserQuota;
 
 pub const USER_TABLE: &str = "users";
 
 /// All columns needed to construct a `UserEntity` from a row.
 /// Single source of truth for queries that construct a `UserEntity`.
-const ALL_USER_COLUMNS: [UserIden; 11] = [
+const ALL_USER_COLUMNS: [UserIden; 16] = [
     UserIden::Id,
     UserIden::PublicKey,
     UserIden::CreatedAt,
@@ -21,9 +25,50 @@ const ALL_USER_COLUMNS: [UserIden; 11] = [
     UserIden::QuotaRateWrite,
     UserIden::QuotaRateReadBurst,
     UserIden::QuotaRateWriteBurst,
+    UserIden::QuotaRequestsPerMinute,
+    UserIden::QuotaEgressDailyMb,
+    UserIden::QuotaEgressMonthlyMb,
+    UserIden::QuotaIngressDailyMb,
+    UserIden::QuotaIngressMonthlyMb,
     UserIden::AllowedWritePaths,
 ];
 
+/// Filters and pagination for [`UserRepository::list`]. All filters are combined with AND.
+#[derive(Debug, Clone, Default)]
+pub struct UserListQuery {
+    /// Only users whose z-base-32 public key starts with this prefix.
+    pub pubkey_prefix: Option<String>,
+    /// Only disabled (`true`) or enabled (`false`) users.
+    pub disabled: Option<bool>,
+    /// Only users created at or after this time.
+    pub created_after: Option<NaiveDateTime>,
+    /// Only users created before this time.
+    pub created_before: Option<NaiveDateTime>,
+    /// Only users using at least this many bytes.
+    pub min_used_bytes: Option<u64>,
+    /// Only users using at most this many bytes.
+    pub max_used_bytes: Option<u64>,
+    /// Only the user who signed up with this signup code.
+    pub signup_code: Option<SignupCode>,
+    pub limit: Option<u16>,
+    /// Id of the last user of the previous page.
+    pub cursor: Option<i32>,
+}
+
+impl UserListQuery {
+    fn effective_limit(&self) -> u16 {
+        self.limit
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .min(DEFAULT_MAX_LIST_LIMIT)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UserListPage {
+    pub items: Vec<UserEntity>,
+    pub next_cursor: Option<i32>,
+}
+
 /// Repository that handles all the queries regarding the UserEntity.
 pub(crate) struct UserRepository;
 
@@ -125,6 +170,7 @@ impl UserRepository {
     }
 
     /// Get all users.
+    #[cfg(test)]
     pub async fn get_all<'a>(
         executor: &mut UnifiedExecutor<'a>,
     ) -> Result<Vec<UserEntity>, sqlx::Error> {
@@ -138,6 +184,71 @@ impl UserRepository {
         Ok(users)
     }
 
+    /// List users in signup order (by id), filtered and paginated.
+    pub async fn list<'a>(
+        list_query: UserListQuery,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<UserListPage, sqlx::Error> {
+        let mut statement = Query::select()
+            .from(USER_TABLE)
+            .columns(ALL_USER_COLUMNS)
+            .order_by(UserIden::Id, Order::Asc)
+            .to_owned();
+
+        if let Some(prefix) = &list_query.pubkey_prefix {
+            statement.and_where(Expr::col(UserIden::PublicKey).like(format!("{prefix}%")));
+        }
+        if let Some(disabled) = list_query.disabled {
+            statement.and_where(Expr::col(UserIden::Disabled).eq(disabled));
+        }
+        if let Some(created_after) = list_query.created_after {
+            statement.and_where(Expr::col(UserIden::CreatedAt).gte(created_after));
+        }
+        if let Some(created_before) = list_query.created_before {
+            statement.and_where(Expr::col(UserIden::CreatedAt).lt(created_before));
+        }
+        if let Some(min_used_bytes) = list_query.min_used_bytes {
+            let min_used_bytes = i64::try_from(min_used_bytes).unwrap_or(i64::MAX);
+            statement.and_where(Expr::col(UserIden::UsedBytes).gte(min_used_bytes));
+        }
+        if let Some(max_used_bytes) = list_query.max_used_bytes {
+            let max_used_bytes = i64::try_from(max_used_bytes).unwrap_or(i64::MAX);
+            statement.and_where(Expr::col(UserIden::UsedBytes).lte(max_used_bytes));
+        }
+        if let Some(code) = &list_query.signup_code {
+            statement.and_where(
+                Expr::col(UserIden::PublicKey).in_subquery(
+                    Query::select()
+                        .from(SIGNUP_CODE_TABLE)
+                        .column(SignupCodeIden::UsedBy)
+                        .and_where(Expr::col(SignupCodeIden::Id).eq(code.to_string()))
+                        .to_owned(),
+                ),
+            );
+        }
+        if let Some(cursor) = list_query.cursor {
+            statement.and_where(Expr::col(UserIden::Id).gt(cursor));
+        }
+
+        let limit = list_query.effective_limit();
+        statement.limit((limit as u64) + 1);
+
+        let (query, values) = statement.build_sqlx(PostgresQueryBuilder);
+        let con = executor.get_con().await?;
+        let mut users: Vec<UserEntity> = sqlx::query_as_with(&query, values).fetch_all(con).await?;
+        let next_cursor = if users.len() > limit as usize {
+            users.truncate(limit as usize);
+            users.last().map(|user| user.id)
+        } else {
+            None
+        };
+
+        Ok(UserListPage {
+            items: users,
+            next_cursor,
+        })
+    }
+
     /// Get the overview of the users.
     pub async fn get_overview<'a>(
         executor: &mut UnifiedExecutor<'a>,
@@ -245,6 +356,26 @@ impl UserRepository {
                     UserIden::QuotaRateWriteBurst,
                     SimpleExpr::Value(config.rate_write_burst_i32().into()),
                 ),
+                (
+                    UserIden::QuotaRequestsPerMinute,
+                    SimpleExpr::Value(config.requests_per_minute_i32().into()),
+                ),
+                (
+                    UserIden::QuotaEgressDailyMb,
+                    SimpleExpr::Value(config.egress_daily_mb_i32().into()),
+                ),
+                (
+                    UserIden::QuotaEgressMonthlyMb,
+                    SimpleExpr::Value(config.egress_monthly_mb_i32().into()),
+                ),
+                (
+                    UserIden::QuotaIngressDailyMb,
+                    SimpleExpr::Value(config.ingress_daily_mb_i32().into()),
+                ),
+                (
+                    UserIden::QuotaIngressMonthlyMb,
+                    SimpleExpr::Value(config.ingress_monthly_mb_i32().into()),
+                ),
                 (
                     UserIden::AllowedWritePaths,
                     SimpleExpr::Value(
@@ -265,9 +396,9 @@ impl UserRepository {
         Ok(user)
     }
 
-    /// Delete a user by their public key.
+    /// Delete a user by their id.
+    /// Sessions, grants, entries, events and usage rows are removed by `ON DELETE CASCADE`.
     /// The executor can either be db.pool() or a transaction.
-    #[cfg(test)]
     pub async fn delete<'a>(
         user_id: i32,
         executor: &mut UnifiedExecutor<'a>,
@@ -299,13 +430,18 @@ pub enum UserIden {
     QuotaRateReadBurst,
     QuotaRateWriteBurst,
     AllowedWritePaths,
+    QuotaRequestsPerMinute,
+    QuotaEgressDailyMb,
+    QuotaEgressMonthlyMb,
+    QuotaIngressDailyMb,
+    QuotaIngressMonthlyMb,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct UserEntity {
     pub id: i32,
     pub public_key: PublicKey,
-    pub created_at: sqlx::types::chrono::NaiveDateTime,
+    pub created_at: NaiveDateTime,
     pub disabled: bool,
     pub used_bytes: u64,
     /// Per-user storage quota in MB. `None` = Default (resolved from system config at enforcement time).
@@ -318,6 +454,16 @@ pub struct UserEntity {
     pub quota_rate_read_burst: Option<i32>,
     /// Per-user write rate burst override. `None` = default (burst = rate).
     pub quota_rate_write_burst: Option<i32>,
+    /// Per-user request-count limit per minute. `None` = Default.
+    pub quota_requests_per_minute: Option<i32>,
+    /// Per-user daily egress cap in MB. `None` = Default.
+    pub quota_egress_daily_mb: Option<i32>,
+    /// Per-user monthly egress cap in MB. `None` = Default.
+    pub quota_egress_monthly_mb: Option<i32>,
+    /// Per-user daily ingress cap in MB. `None` = Default.
+    pub quota_ingress_daily_mb: Option<i32>,
+    /// Per-user monthly ingress cap in MB. `None` = Default.
+    pub quota_ingress_monthly_mb: Option<i32>,
     /// Allowed write paths as JSON array string. `None` = unrestricted.
     pub allowed_write_paths: Option<String>,
 }
@@ -335,6 +481,13 @@ impl UserEntity {
             self.quota_rate_write_burst,
             self.allowed_write_paths.clone(),
         )
+        .with_usage_columns(
+            self.quota_requests_per_minute,
+            self.quota_egress_daily_mb,
+            self.quota_egress_monthly_mb,
+            self.quota_ingress_daily_mb,
+            self.quota_ingress_monthly_mb,
+        )
     }
 }
 
@@ -354,8 +507,7 @@ impl FromRow<'_, PgRow> for UserEntity {
         let disabled: bool = row.try_get(UserIden::Disabled.to_string().as_str())?;
         let raw_used_bytes: i64 = row.try_get(UserIden::UsedBytes.to_string().as_str())?;
         let used_bytes = raw_used_bytes as u64;
-        let created_at: sqlx::types::chrono::NaiveDateTime =
-            row.try_get(UserIden::CreatedAt.to_string().as_str())?;
+        let created_at: NaiveDateTime = row.try_get(UserIden::CreatedAt.to_string().as_str())?;
         let quota_storage_mb: Option<i32> =
             row.try_get(UserIden::QuotaStorageMb.to_string().as_str())?;
         let quota_rate_read: Option<String> =
@@ -366,6 +518,16 @@ impl FromRow<'_, PgRow> for UserEntity {
             row.try_get(UserIden::QuotaRateReadBurst.to_string().as_str())?;
         let quota_rate_write_burst: Option<i32> =
             row.try_get(UserIden::QuotaRateWriteBurst.to_string().as_str())?;
+        let quota_requests_per_minute: Option<i32> =
+            row.try_get(UserIden::QuotaRequestsPerMinute.to_string().as_str())?;
+        let quota_egress_daily_mb: Option<i32> =
+            row.try_get(UserIden::QuotaEgressDailyMb.to_string().as_str())?;
+        let quota_egress_monthly_mb: Option<i32> =
+            row.try_get(UserIden::QuotaEgressMonthlyMb.to_string().as_str())?;
+        let quota_ingress_daily_mb: Option<i32> =
+            row.try_get(UserIden::QuotaIngressDailyMb.to_string().as_str())?;
+        let quota_ingress_monthly_mb: Option<i32> =
+            row.try_get(UserIden::QuotaIngressMonthlyMb.to_string().as_str())?;
         let allowed_write_paths: Option<String> =
             row.try_get(UserIden::AllowedWritePaths.to_string().as_str())?;
         Ok(UserEntity {
@@ -379,6 +541,11 @@ impl FromRow<'_, PgRow> for UserEntity {
             quota_rate_write,
             quota_rate_read_burst,
             quota_rate_write_burst,
+            quota_requests_per_minute,
+            quota_egress_daily_mb,
+            quota_egress_monthly_mb,
+            quota_ingress_daily_mb,
+            quota_ingress_monthly_mb,
             allowed_write_paths,
         })
     }
@@ -597,6 +764,11 @@ mod tests {
             quota_rate_write: None,
             quota_rate_read_burst: None,
             quota_rate_write_burst: None,
+            quota_requests_per_minute: None,
+            quota_egress_daily_mb: None,
+            quota_egress_monthly_mb: None,
+            quota_ingress_daily_mb: None,
+            quota_ingress_monthly_mb: None,
             allowed_write_paths: None,
         };
 
@@ -624,6 +796,11 @@ mod tests {
             quota_rate_write: None,
             quota_rate_read_burst: None,
             quota_rate_write_burst: None,
+            quota_requests_per_minute: None,
+            quota_egress_daily_mb: None,
+            quota_egress_monthly_mb: None,
+            quota_ingress_daily_mb: None,
+            quota_ingress_monthly_mb: None,
             allowed_write_paths: None,
         };
 
@@ -651,6 +828,11 @@ mod tests {
             quota_rate_write: Some("unlimited".to_string()),
             quota_rate_read_burst: None,
             quota_rate_write_burst: None,
+            quota_requests_per_minute: None,
+            quota_egress_daily_mb: None,
+            quota_egress_monthly_mb: None,
+            quota_ingress_daily_mb: None,
+            quota_ingress_monthly_mb: None,
             allowed_write_paths: None,
         };
 
@@ -675,6 +857,11 @@ mod tests {
             quota_rate_write: Some("also_rubbish".to_string()),
             quota_rate_read_burst: None,
             quota_rate_write_burst: None,
+            quota_requests_per_minute: None,
+            quota_egress_daily_mb: None,
+            quota_egress_monthly_mb: None,
+            quota_ingress_daily_mb: None,
+            quota_ingress_monthly_mb: None,
             allowed_write_paths: None,
         };
 
@@ -683,4 +870,102 @@ mod tests {
         assert_eq!(limits.rate_read, QuotaOverride::Default);
         assert_eq!(limits.rate_write, QuotaOverride::Default);
     }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_list_users_filters_and_paginates() {
+        use crate::persistence::sql::signup_code::SignupCodeRepository;
+
+        let db = SqlDb::test().await;
+        let mut users = Vec::new();
+        for _ in 0..3 {
+            let pubkey = Keypair::random().public_key();
+            users.push(
+                UserRepository::create(&pubkey, &mut db.pool().into())
+                    .await
+                    .unwrap(),
+            );
+        }
+        users[1].disabled = true;
+        users[1].used_bytes = 2048;
+        UserRepository::update(&users[1], &mut db.pool().into())
+            .await
+            .unwrap();
+        let code = SignupCode::random();
+        SignupCodeRepository::create(&code, &UserQuota::default(), &mut db.pool().into())
+            .await
+            .unwrap();
+        SignupCodeRepository::mark_as_used(&code, &users[2].public_key, &mut db.pool().into())
+            .await
+            .unwrap();
+
+        let ids = |page: &UserListPage| page.items.iter().map(|u| u.id).collect::<Vec<_>>();
+
+        // Pagination
+        let query = UserListQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = UserRepository::list(query.clone(), &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec![users[0].id, users[1].id]);
+        assert_eq!(page.next_cursor, Some(users[1].id));
+        let query = UserListQuery {
+            cursor: page.next_cursor,
+            ..query
+        };
+        let page = UserRepository::list(query, &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec![users[2].id]);
+        assert_eq!(page.next_cursor, None);
+
+        // Filters
+        let list = |query: UserListQuery| {
+            let db = db.clone();
+            async move {
+                UserRepository::list(query, &mut db.pool().into())
+                    .await
+                    .unwrap()
+            }
+        };
+        let page = list(UserListQuery {
+            disabled: Some(true),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(ids(&page), vec![users[1].id]);
+        let page = list(UserListQuery {
+            min_used_bytes: Some(1),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(ids(&page), vec![users[1].id]);
+        let page = list(UserListQuery {
+            max_used_bytes: Some(1024),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(ids(&page), vec![users[0].id, users[2].id]);
+        let page = list(UserListQuery {
+            signup_code: Some(code),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(ids(&page), vec![users[2].id]);
+        let page = list(UserListQuery {
+            pubkey_prefix: Some(users[0].public_key.z32()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(ids(&page), vec![users[0].id]);
+        let page = list(UserListQuery {
+            created_after: Some(users[0].created_at),
+            created_before: Some(users[0].created_at),
+            ..Default::default()
+        })
+        .await;
+        assert!(page.items.is_empty());
+    }
 }
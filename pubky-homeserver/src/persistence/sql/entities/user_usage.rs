@@ -0,0 +1,225 @@
+use chrono::{Datelike, NaiveDate};
+use pubky_common::crypto::PublicKey;
+use sea_query::Iden;
+use serde::Serialize;
+use sqlx::Row;
+
+use crate::persistence::sql::entities::user::USER_TABLE;
+use crate::persistence::sql::UnifiedExecutor;
+
+pub const USER_USAGE_TABLE: &str = "user_usage";
+
+/// Transfer and request counters of a user over some period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct UsageCounters {
+    /// Bytes sent to the user (response bodies).
+    pub egress_bytes: u64,
+    /// Bytes received from the user (request bodies).
+    pub ingress_bytes: u64,
+    /// Number of requests.
+    pub requests: u64,
+}
+
+impl UsageCounters {
+    /// Whether there is nothing to record.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Add `other` to these counters.
+    pub fn add(&mut self, other: &UsageCounters) {
+        self.egress_bytes = self.egress_bytes.saturating_add(other.egress_bytes);
+        self.ingress_bytes = self.ingress_bytes.saturating_add(other.ingress_bytes);
+        self.requests = self.requests.saturating_add(other.requests);
+    }
+}
+
+/// A user's usage in the current UTC day and calendar month.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct UsagePeriods {
+    pub day: UsageCounters,
+    pub month: UsageCounters,
+}
+
+impl UsagePeriods {
+    /// Add `counters` to both periods.
+    pub fn add(&mut self, counters: &UsageCounters) {
+        self.day.add(counters);
+        self.month.add(counters);
+    }
+}
+
+/// Repository for the per-user, per-day usage counters.
+pub(crate) struct UserUsageRepository;
+
+impl UserUsageRepository {
+    /// Add `counters` to the user's row for `day`.
+    /// Does nothing if the user doesn't exist.
+    pub async fn add<'a>(
+        public_key: &PublicKey,
+        day: NaiveDate,
+        counters: &UsageCounters,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        let query = format!(
+            r#"INSERT INTO {USER_USAGE_TABLE} AS usage (user_id, day, egress_bytes, ingress_bytes, requests)
+            SELECT id, $2, $3, $4, $5 FROM {USER_TABLE} WHERE public_key = $1
+            ON CONFLICT (user_id, day) DO UPDATE SET
+                egress_bytes = usage.egress_bytes + EXCLUDED.egress_bytes,
+                ingress_bytes = usage.ingress_bytes + EXCLUDED.ingress_bytes,
+                requests = usage.requests + EXCLUDED.requests"#
+        );
+        let con = executor.get_con().await?;
+        sqlx::query(&query)
+            .bind(public_key.z32())
+            .bind(day)
+            .bind(counters.egress_bytes as i64)
+            .bind(counters.ingress_bytes as i64)
+            .bind(counters.requests as i64)
+            .execute(con)
+            .await?;
+        Ok(())
+    }
+
+    /// Get the user's usage on `today` and in the calendar month of `today`.
+    pub async fn get<'a>(
+        public_key: &PublicKey,
+        today: NaiveDate,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<UsagePeriods, sqlx::Error> {
+        let month_start = today.with_day(1).expect("day 1 always exists");
+        let query = format!(
+            r#"SELECT
+                COALESCE(SUM(u.egress_bytes) FILTER (WHERE u.day = $2), 0)::bigint AS day_egress,
+                COALESCE(SUM(u.ingress_bytes) FILTER (WHERE u.day = $2), 0)::bigint AS day_ingress,
+                COALESCE(SUM(u.requests) FILTER (WHERE u.day = $2), 0)::bigint AS day_requests,
+                COALESCE(SUM(u.egress_bytes), 0)::bigint AS month_egress,
+                COALESCE(SUM(u.ingress_bytes), 0)::bigint AS month_ingress,
+                COALESCE(SUM(u.requests), 0)::bigint AS month_requests
+            FROM {USER_USAGE_TABLE} u
+            JOIN {USER_TABLE} ON {USER_TABLE}.id = u.user_id
+            WHERE {USER_TABLE}.public_key = $1 AND u.day >= $3 AND u.day <= $2"#
+        );
+        let con = executor.get_con().await?;
+        let row = sqlx::query(&query)
+            .bind(public_key.z32())
+            .bind(today)
+            .bind(month_start)
+            .fetch_one(con)
+            .await?;
+        let get = |column: &str| -> Result<u64, sqlx::Error> {
+            Ok(row.try_get::<i64, _>(column)? as u64)
+        };
+        Ok(UsagePeriods {
+            day: UsageCounters {
+                egress_bytes: get("day_egress")?,
+                ingress_bytes: get("day_ingress")?,
+                requests: get("day_requests")?,
+            },
+            month: UsageCounters {
+                egress_bytes: get("month_egress")?,
+                ingress_bytes: get("month_ingress")?,
+                requests: get("month_requests")?,
+            },
+        })
+    }
+
+    /// Delete all rows of days before `day`. Returns the number of deleted rows.
+    pub async fn delete_before<'a>(
+        day: NaiveDate,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<u64, sqlx::Error> {
+        let query = format!("DELETE FROM {USER_USAGE_TABLE} WHERE day < $1");
+        let con = executor.get_con().await?;
+        let result = sqlx::query(&query).bind(day).execute(con).await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// Iden for the user usage table.
+#[derive(Iden)]
+pub enum UserUsageIden {
+    #[iden = "user_id"]
+    User,
+    Day,
+    EgressBytes,
+    IngressBytes,
+    Requests,
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::persistence::sql::user::UserRepository;
+    use crate::persistence::sql::SqlDb;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_add_and_get_periods() {
+        let db = SqlDb::test().await;
+        let pubkey = Keypair::random().public_key();
+        UserRepository::create(&pubkey, &mut db.pool().into())
+            .await
+            .unwrap();
+        let counters = UsageCounters {
+            egress_bytes: 100,
+            ingress_bytes: 10,
+            requests: 1,
+        };
+
+        // Previous month, earlier this month, and twice today.
+        for day in [date(2026, 9, 30), date(2026, 10, 1), date(2026, 10, 18)] {
+            UserUsageRepository::add(&pubkey, day, &counters, &mut db.pool().into())
+                .await
+                .unwrap();
+        }
+        UserUsageRepository::add(
+            &pubkey,
+            date(2026, 10, 18),
+            &counters,
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+
+        let usage = UserUsageRepository::get(&pubkey, date(2026, 10, 18), &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(
+            usage.day,
+            UsageCounters {
+                egress_bytes: 200,
+                ingress_bytes: 20,
+                requests: 2,
+            }
+        );
+        assert_eq!(usage.month.egress_bytes, 300);
+        assert_eq!(usage.month.requests, 3);
+
+        // Unknown users have no usage and adding to them is a no-op.
+        let unknown = Keypair::random().public_key();
+        UserUsageRepository::add(
+            &unknown,
+            date(2026, 10, 18),
+            &counters,
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let usage = UserUsageRepository::get(&unknown, date(2026, 10, 18), &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(usage, UsagePeriods::default());
+
+        let deleted = UserUsageRepository::delete_before(date(2026, 10, 1), &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+    }
+}
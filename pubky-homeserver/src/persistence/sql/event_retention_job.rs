@@ -0,0 +1,362 @@
+//! Background retention and compaction of the events table.
+//!
+//! Runs next to the [`super::PgEventListener`] and deletes events according to the
+//! `[events]` config section:
+//!
+//! - **Age**: events older than `retention_max_age_days`.
+//! - **Count**: all but the `retention_max_per_user` newest events of each user.
+//! - **Compaction**: events superseded by a newer event for the same user and path.
+//!
+//! Age and count retention drop history a client may not have seen yet, so they raise
+//! a per-user watermark (`event_watermarks.pruned_through`) in the same statement.
+//! `/events-stream` rejects cursors below it with `410 Gone`. Compaction keeps the
+//! latest event per path, so replaying a compacted log still converges to the current
+//! state and the watermark is left untouched.
+//!
+//! Deletes run in batches to keep lock times short. A session-level advisory lock
+//! makes sure only one homeserver instance prunes at a time.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use sqlx::{
+    types::chrono::{DateTime, Utc},
+    PgPool,
+};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::data_directory::EventsToml;
+use crate::persistence::files::events::EventRepository;
+use crate::persistence::sql::UnifiedExecutor;
+
+/// Number of events deleted per statement.
+const BATCH_SIZE: u32 = 10_000;
+
+/// Progress of a single retention run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct EventRetentionRunStats {
+    /// Events deleted because they exceeded `retention_max_age_days`.
+    pub expired: u64,
+    /// Events deleted because their user exceeded `retention_max_per_user`.
+    pub over_limit: u64,
+    /// Events deleted by compaction.
+    pub compacted: u64,
+}
+
+impl EventRetentionRunStats {
+    /// Total number of events deleted in this run.
+    pub fn total(&self) -> u64 {
+        self.expired + self.over_limit + self.compacted
+    }
+}
+
+/// Observable state of the retention job, exposed through the admin API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EventRetentionStatus {
+    /// Whether a run is currently in progress.
+    pub running: bool,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    /// Error of the last run, if it failed.
+    pub last_error: Option<String>,
+    /// Progress of the current run, or the result of the last one.
+    pub run: EventRetentionRunStats,
+    /// Events deleted by this instance since startup.
+    pub total_deleted: u64,
+}
+
+/// Background job that periodically applies the event retention policy.
+///
+/// The task is aborted when the job is dropped.
+pub struct EventRetentionJob {
+    config: EventsToml,
+    handle: Option<JoinHandle<()>>,
+    trigger: Arc<Notify>,
+    status: Arc<Mutex<EventRetentionStatus>>,
+}
+
+impl EventRetentionJob {
+    /// Advisory lock ID used to allow only one retention run across all instances.
+    const RETENTION_LOCK_ID: i64 = 0x6576656e_74730001; // "events" + 1
+
+    /// Start the retention job.
+    ///
+    /// Runs every `retention_interval` seconds, and whenever [`Self::trigger`] is called.
+    /// With an interval of 0 it only runs when triggered.
+    #[must_use = "the job stops when dropped"]
+    pub fn start(pool: &PgPool, config: EventsToml) -> Self {
+        let trigger = Arc::new(Notify::new());
+        let status = Arc::new(Mutex::new(EventRetentionStatus::default()));
+        let handle = {
+            let pool = pool.clone();
+            let config = config.clone();
+            let trigger = trigger.clone();
+            let status = status.clone();
+            tokio::spawn(async move {
+                Self::run_loop(pool, config, trigger, status).await;
+            })
+        };
+        Self {
+            config,
+            handle: Some(handle),
+            trigger,
+            status,
+        }
+    }
+
+    /// The retention policy this job applies.
+    pub fn config(&self) -> &EventsToml {
+        &self.config
+    }
+
+    /// Request a run as soon as possible. A request during a run schedules another one.
+    pub fn trigger(&self) {
+        self.trigger.notify_one();
+    }
+
+    /// Snapshot of the current job state.
+    pub fn status(&self) -> EventRetentionStatus {
+        self.status.lock().expect("status lock poisoned").clone()
+    }
+
+    async fn run_loop(
+        pool: PgPool,
+        config: EventsToml,
+        trigger: Arc<Notify>,
+        status: Arc<Mutex<EventRetentionStatus>>,
+    ) {
+        let interval = Duration::from_secs(config.retention_interval);
+        loop {
+            if interval.is_zero() {
+                trigger.notified().await;
+            } else {
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    _ = trigger.notified() => {}
+                }
+            }
+
+            {
+                let mut status = status.lock().expect("status lock poisoned");
+                status.running = true;
+                status.last_started_at = Some(Utc::now());
+                status.run = EventRetentionRunStats::default();
+            }
+            let result = Self::run_once(&pool, &config, &status).await;
+            let mut status = status.lock().expect("status lock poisoned");
+            status.running = false;
+            status.last_finished_at = Some(Utc::now());
+            match result {
+                Ok(run) => {
+                    if run.total() > 0 {
+                        tracing::info!(
+                            "Event retention deleted {} expired, {} over-limit and {} compacted events",
+                            run.expired,
+                            run.over_limit,
+                            run.compacted
+                        );
+                    }
+                    status.last_error = None;
+                }
+                Err(e) => {
+                    tracing::error!("Event retention run failed: {}", e);
+                    status.last_error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Apply the retention policy once.
+    ///
+    /// Returns without deleting anything if another instance holds the retention lock.
+    /// Progress is published to `status` after every batch.
+    pub(crate) async fn run_once(
+        pool: &PgPool,
+        config: &EventsToml,
+        status: &Mutex<EventRetentionStatus>,
+    ) -> Result<EventRetentionRunStats, sqlx::Error> {
+        if !config.has_policy() {
+            return Ok(EventRetentionRunStats::default());
+        }
+
+        // Session-level lock, held on a dedicated connection for the whole run.
+        let mut lock_con = pool.acquire().await?;
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(Self::RETENTION_LOCK_ID)
+            .fetch_one(&mut *lock_con)
+            .await?;
+        if !locked {
+            tracing::debug!("Event retention is already running on another instance");
+            return Ok(EventRetentionRunStats::default());
+        }
+
+        let result = Self::prune(pool, config, status).await;
+
+        let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(Self::RETENTION_LOCK_ID)
+            .execute(&mut *lock_con)
+            .await;
+        if let Err(e) = unlocked {
+            // Closing the connection releases the lock.
+            tracing::warn!("Failed to release the event retention lock: {}", e);
+            drop(lock_con.detach());
+        }
+        result
+    }
+
+    async fn prune(
+        pool: &PgPool,
+        config: &EventsToml,
+        status: &Mutex<EventRetentionStatus>,
+    ) -> Result<EventRetentionRunStats, sqlx::Error> {
+        let mut run = EventRetentionRunStats::default();
+        let record = |run: &EventRetentionRunStats, deleted: u64| {
+            let mut status = status.lock().expect("status lock poisoned");
+            status.run = *run;
+            status.total_deleted += deleted;
+        };
+
+        if let Some(days) = config.retention_max_age_days {
+            let cutoff = Utc::now() - chrono::Duration::days(days.get() as i64);
+            loop {
+                let deleted = EventRepository::delete_older_than(
+                    &cutoff,
+                    BATCH_SIZE,
+                    &mut UnifiedExecutor::from(pool),
+                )
+                .await?;
+                run.expired += deleted;
+                record(&run, deleted);
+                if deleted < BATCH_SIZE as u64 {
+                    break;
+                }
+            }
+        }
+
+        if let Some(max_per_user) = config.retention_max_per_user {
+            loop {
+                let deleted = EventRepository::delete_exceeding_per_user(
+                    max_per_user.get(),
+                    BATCH_SIZE,
+                    &mut UnifiedExecutor::from(pool),
+                )
+                .await?;
+                run.over_limit += deleted;
+                record(&run, deleted);
+                if deleted < BATCH_SIZE as u64 {
+                    break;
+                }
+            }
+        }
+
+        if config.compaction {
+            loop {
+                let deleted =
+                    EventRepository::compact(BATCH_SIZE, &mut UnifiedExecutor::from(pool)).await?;
+                run.compacted += deleted;
+                record(&run, deleted);
+                if deleted < BATCH_SIZE as u64 {
+                    break;
+                }
+            }
+        }
+
+        Ok(run)
+    }
+}
+
+impl Drop for EventRetentionJob {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use pubky_common::crypto::{Hash, Keypair};
+    use pubky_common::events::EventType;
+
+    use super::*;
+    use crate::persistence::sql::SqlDb;
+    use crate::services::user_service::UserService;
+    use crate::shared::webdav::{EntryPath, StoragePath};
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_run_once_applies_policy() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let user_pubkey = Keypair::random().public_key();
+        let user = user_service.create(&user_pubkey).await.unwrap();
+
+        let old = Utc::now() - chrono::Duration::days(40);
+        for (i, created_at) in [old, old, Utc::now(), Utc::now(), Utc::now()]
+            .iter()
+            .enumerate()
+        {
+            // Two events on the same path so compaction has something to do.
+            let path = format!("/pub/{}", i.min(3));
+            let path = EntryPath::new(user_pubkey.clone(), StoragePath::new(&path).unwrap());
+            EventRepository::create_with_timestamp(
+                user.id,
+                EventType::Put {
+                    content_hash: Hash::from_bytes([0; 32]),
+                },
+                &path,
+                created_at,
+                &mut db.pool().into(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let config = EventsToml {
+            retention_max_age_days: Some(NonZeroU64::new(30).unwrap()),
+            retention_max_per_user: None,
+            compaction: true,
+            retention_interval: 0,
+        };
+        let status = Mutex::new(EventRetentionStatus::default());
+
+        // Another instance holding the lock makes this run a no-op.
+        let mut other = db.pool().acquire().await.unwrap();
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(EventRetentionJob::RETENTION_LOCK_ID)
+            .execute(&mut *other)
+            .await
+            .unwrap();
+        let run = EventRetentionJob::run_once(db.pool(), &config, &status)
+            .await
+            .unwrap();
+        assert_eq!(run, EventRetentionRunStats::default());
+        sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(EventRetentionJob::RETENTION_LOCK_ID)
+            .execute(&mut *other)
+            .await
+            .unwrap();
+
+        let run = EventRetentionJob::run_once(db.pool(), &config, &status)
+            .await
+            .unwrap();
+        assert_eq!(
+            run,
+            EventRetentionRunStats {
+                expired: 2,
+                over_limit: 0,
+                compacted: 1,
+            }
+        );
+        assert_eq!(status.lock().unwrap().total_deleted, 3);
+
+        let overview = EventRepository::get_overview(&mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(overview.num_events, 2);
+    }
+}
@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use sqlx::Transaction;
+
+use crate::persistence::sql::migration::MigrationTrait;
+
+/// Adds the request-count and transfer cap columns to both the `users` and
+/// `signup_codes` tables.
+///
+/// Same encoding as the other quota columns: NULL = Default, -1 = Unlimited.
+pub struct M20261018AddUsageQuotaColumnsMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261018AddUsageQuotaColumnsMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        for table in ["users", "signup_codes"] {
+            for col in [
+                "quota_requests_per_minute",
+                "quota_egress_daily_mb",
+                "quota_egress_monthly_mb",
+                "quota_ingress_daily_mb",
+                "quota_ingress_monthly_mb",
+            ] {
+                sqlx::query(&format!(
+                    "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS {col} INTEGER"
+                ))
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261018_add_usage_quota_columns"
+    }
+}
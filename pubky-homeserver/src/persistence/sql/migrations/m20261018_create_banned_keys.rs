@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use sea_query::{ColumnDef, Expr, PostgresQueryBuilder, Table};
+use sqlx::Transaction;
+
+use crate::persistence::sql::{
+    entities::banned_key::{BannedKeyIden, BANNED_KEY_TABLE},
+    migration::MigrationTrait,
+};
+
+/// Creates the list of public keys that are not allowed to sign up.
+///
+/// Not linked to `users`: a ban outlives the deleted user it was created for.
+pub struct M20261018CreateBannedKeysMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261018CreateBannedKeysMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        let statement = Table::create()
+            .table(BANNED_KEY_TABLE)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(BannedKeyIden::PublicKey)
+                    .string_len(52)
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(BannedKeyIden::Reason).text().null())
+            .col(
+                ColumnDef::new(BannedKeyIden::CreatedAt)
+                    .timestamp()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261018_create_banned_keys"
+    }
+}
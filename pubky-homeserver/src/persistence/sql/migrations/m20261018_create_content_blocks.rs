@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use sea_query::{ColumnDef, Expr, Index, PostgresQueryBuilder, Table};
+use sqlx::Transaction;
+
+use crate::persistence::sql::{
+    entities::content_block::{ContentBlockIden, CONTENT_BLOCK_TABLE},
+    migration::MigrationTrait,
+};
+
+const HASH_INDEX_NAME: &str = "idx_content_blocks_content_hash";
+
+/// Creates the admin-managed blocklist of content hashes and path globs.
+///
+/// Each row sets exactly one of `content_hash` and `path_glob`. Rows are kept
+/// after a block is lifted as the audit trail.
+pub struct M20261018CreateContentBlocksMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261018CreateContentBlocksMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        let statement = Table::create()
+            .table(CONTENT_BLOCK_TABLE)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(ContentBlockIden::Id)
+                    .integer()
+                    .not_null()
+                    .auto_increment()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(ContentBlockIden::ContentHash)
+                    .binary()
+                    .null(),
+            )
+            .col(ColumnDef::new(ContentBlockIden::PathGlob).text().null())
+            .col(ColumnDef::new(ContentBlockIden::Reason).text().null())
+            .col(
+                ColumnDef::new(ContentBlockIden::CreatedBy)
+                    .text()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(ContentBlockIden::CreatedAt)
+                    .timestamp()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(ColumnDef::new(ContentBlockIden::RemovedBy).text().null())
+            .col(
+                ColumnDef::new(ContentBlockIden::RemovedAt)
+                    .timestamp()
+                    .null(),
+            )
+            .check(
+                Expr::col(ContentBlockIden::ContentHash)
+                    .is_null()
+                    .ne(Expr::col(ContentBlockIden::PathGlob).is_null()),
+            )
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        // Uploads and reads look up active blocks by hash.
+        let statement = Index::create()
+            .name(HASH_INDEX_NAME)
+            .table(CONTENT_BLOCK_TABLE)
+            .col(ContentBlockIden::ContentHash)
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261018_create_content_blocks"
+    }
+}
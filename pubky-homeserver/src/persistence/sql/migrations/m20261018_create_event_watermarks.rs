@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use sea_query::{
+    ColumnDef, ForeignKey, ForeignKeyAction, Iden, Index, PostgresQueryBuilder, Table,
+};
+use sqlx::Transaction;
+
+use crate::persistence::{
+    files::events::{events_repository::EVENT_TABLE, EventIden},
+    sql::{
+        entities::user::{UserIden, USER_TABLE},
+        migration::MigrationTrait,
+    },
+};
+
+pub const EVENT_WATERMARKS_TABLE: &str = "event_watermarks";
+
+/// Creates the per-user watermark of events deleted by retention.
+///
+/// `pruned_through` is the highest event id removed from a user's history.
+/// A stream cursor below it points into history that no longer exists.
+///
+/// Also indexes `events.created_at`, which retention deletes by.
+pub struct M20261018CreateEventWatermarksMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261018CreateEventWatermarksMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        let statement = Table::create()
+            .table(EVENT_WATERMARKS_TABLE)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(EventWatermarkIden::User)
+                    .integer()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(EventWatermarkIden::PrunedThrough)
+                    .big_integer()
+                    .not_null(),
+            )
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        let foreign_key = ForeignKey::create()
+            .name("fk_event_watermark_user")
+            .from(EVENT_WATERMARKS_TABLE, EventWatermarkIden::User)
+            .to(USER_TABLE, UserIden::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+        let query = foreign_key.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        let index = Index::create()
+            .name("idx_events_created_at")
+            .table(EVENT_TABLE)
+            .col(EventIden::CreatedAt)
+            .if_not_exists()
+            .to_owned();
+        let query = index.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261018_create_event_watermarks"
+    }
+}
+
+#[derive(Iden)]
+pub enum EventWatermarkIden {
+    User,
+    PrunedThrough,
+}
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use sea_query::{ColumnDef, ForeignKey, ForeignKeyAction, Index, PostgresQueryBuilder, Table};
+use sqlx::Transaction;
+
+use crate::persistence::sql::{
+    entities::path_quota::{PathQuotaIden, PATH_QUOTA_TABLE},
+    entities::user::{UserIden, USER_TABLE},
+    migration::MigrationTrait,
+};
+
+/// Creates the per-user storage limits for directory prefixes.
+///
+/// One row per user and prefix. `max_mb` uses the same unit as `quota_storage_mb`,
+/// `used_bytes` is counted like the user's `used_bytes`.
+pub struct M20261018CreatePathQuotasMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261018CreatePathQuotasMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        let statement = Table::create()
+            .table(PATH_QUOTA_TABLE)
+            .if_not_exists()
+            .col(ColumnDef::new(PathQuotaIden::User).integer().not_null())
+            .col(ColumnDef::new(PathQuotaIden::Path).text().not_null())
+            .col(ColumnDef::new(PathQuotaIden::MaxMb).integer().not_null())
+            .col(
+                ColumnDef::new(PathQuotaIden::UsedBytes)
+                    .big_unsigned()
+                    .not_null()
+                    .default(0),
+            )
+            .primary_key(
+                Index::create()
+                    .col(PathQuotaIden::User)
+                    .col(PathQuotaIden::Path),
+            )
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        let foreign_key = ForeignKey::create()
+            .name("fk_path_quota_user")
+            .from(PATH_QUOTA_TABLE, PathQuotaIden::User)
+            .to(USER_TABLE, UserIden::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+        let query = foreign_key.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261018_create_path_quotas"
+    }
+}
@@ -0,0 +1,31 @@
+use async_trait::async_trait;
+use sqlx::Transaction;
+
+use crate::persistence::sql::migration::MigrationTrait;
+
+pub const RATE_LIMIT_BUCKETS_TABLE: &str = "rate_limit_buckets";
+
+/// Creates the bucket table of the Postgres rate-limiter backend.
+///
+/// The table is `UNLOGGED`: bucket state is short-lived and cheap to lose on a crash,
+/// and skipping the WAL keeps the per-request upserts fast.
+pub struct M20261018CreateRateLimitBucketsMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261018CreateRateLimitBucketsMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        // sea-query can't express UNLOGGED tables.
+        let query = format!(
+            "CREATE UNLOGGED TABLE IF NOT EXISTS {RATE_LIMIT_BUCKETS_TABLE} (
+                key VARCHAR PRIMARY KEY,
+                tat BIGINT NOT NULL
+            )"
+        );
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261018_create_rate_limit_buckets"
+    }
+}
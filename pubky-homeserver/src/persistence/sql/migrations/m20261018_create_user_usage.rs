@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+use sea_query::{ColumnDef, ForeignKey, ForeignKeyAction, Index, PostgresQueryBuilder, Table};
+use sqlx::Transaction;
+
+use crate::persistence::sql::{
+    entities::user::{UserIden, USER_TABLE},
+    entities::user_usage::{UserUsageIden, USER_USAGE_TABLE},
+    migration::MigrationTrait,
+};
+
+/// Creates the per-user, per-day transfer and request counters.
+///
+/// One row per user and UTC day. Monthly usage is the sum of the month's rows.
+pub struct M20261018CreateUserUsageMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261018CreateUserUsageMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        let statement = Table::create()
+            .table(USER_USAGE_TABLE)
+            .if_not_exists()
+            .col(ColumnDef::new(UserUsageIden::User).integer().not_null())
+            .col(ColumnDef::new(UserUsageIden::Day).date().not_null())
+            .col(
+                ColumnDef::new(UserUsageIden::EgressBytes)
+                    .big_integer()
+                    .not_null()
+                    .default(0),
+            )
+            .col(
+                ColumnDef::new(UserUsageIden::IngressBytes)
+                    .big_integer()
+                    .not_null()
+                    .default(0),
+            )
+            .col(
+                ColumnDef::new(UserUsageIden::Requests)
+                    .big_integer()
+                    .not_null()
+                    .default(0),
+            )
+            .primary_key(
+                Index::create()
+                    .col(UserUsageIden::User)
+                    .col(UserUsageIden::Day),
+            )
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        let foreign_key = ForeignKey::create()
+            .name("fk_user_usage_user")
+            .from(USER_USAGE_TABLE, UserUsageIden::User)
+            .to(USER_TABLE, UserIden::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+        let query = foreign_key.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261018_create_user_usage"
+    }
+}
@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use sea_query::{ColumnDef, Index, PostgresQueryBuilder, Table};
+use sqlx::Transaction;
+
+use crate::persistence::{
+    files::events::{events_repository::EVENT_TABLE, EventIden},
+    sql::{
+        entities::republish_status::{RepublishStatusIden, REPUBLISH_STATUS_TABLE},
+        migration::MigrationTrait,
+    },
+};
+
+const EVENTS_CREATED_AT_INDEX: &str = "idx_events_created_at";
+
+/// Adds what the user keys republisher needs to schedule users by record age and activity:
+///
+/// - `user_republish_status.record_timestamp`: timestamp of the record last found on the DHT.
+/// - An index on `events.created_at` to find recently active users.
+pub struct M20261019AddRepublishSchedulingMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261019AddRepublishSchedulingMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        let statement = Table::alter()
+            .table(REPUBLISH_STATUS_TABLE)
+            .add_column_if_not_exists(
+                ColumnDef::new(RepublishStatusIden::RecordTimestamp)
+                    .timestamp()
+                    .null(),
+            )
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        let statement = Index::create()
+            .name(EVENTS_CREATED_AT_INDEX)
+            .table(EVENT_TABLE)
+            .col(EventIden::CreatedAt)
+            .if_not_exists()
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261019_add_republish_scheduling"
+    }
+}
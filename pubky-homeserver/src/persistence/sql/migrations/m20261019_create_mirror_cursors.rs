@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+use sea_query::{ColumnDef, ForeignKey, ForeignKeyAction, Iden, PostgresQueryBuilder, Table};
+use sqlx::Transaction;
+
+use crate::persistence::sql::{
+    entities::user::{UserIden, USER_TABLE},
+    migration::MigrationTrait,
+};
+
+pub const MIRROR_CURSORS_TABLE: &str = "mirror_cursors";
+
+/// Creates the per-user position in the event stream of the primary homeserver
+/// that this homeserver mirrors the user from.
+///
+/// `cursor` is only meaningful for `primary`; a user who moves to another
+/// primary is mirrored from the start again.
+pub struct M20261019CreateMirrorCursorsMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261019CreateMirrorCursorsMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        let statement = Table::create()
+            .table(MIRROR_CURSORS_TABLE)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(MirrorCursorIden::User)
+                    .integer()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(MirrorCursorIden::Primary)
+                    .string()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(MirrorCursorIden::Cursor).string().not_null())
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        let foreign_key = ForeignKey::create()
+            .name("fk_mirror_cursor_user")
+            .from(MIRROR_CURSORS_TABLE, MirrorCursorIden::User)
+            .to(USER_TABLE, UserIden::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+        let query = foreign_key.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261019_create_mirror_cursors"
+    }
+}
+
+#[derive(Iden)]
+pub enum MirrorCursorIden {
+    User,
+    #[iden = "primary_homeserver"]
+    Primary,
+    Cursor,
+}
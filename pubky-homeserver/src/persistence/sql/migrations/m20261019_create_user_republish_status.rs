@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+use sea_query::{ColumnDef, ForeignKey, ForeignKeyAction, PostgresQueryBuilder, Table};
+use sqlx::Transaction;
+
+use crate::persistence::sql::{
+    entities::{
+        republish_status::{RepublishStatusIden, REPUBLISH_STATUS_TABLE},
+        user::{UserIden, USER_TABLE},
+    },
+    migration::MigrationTrait,
+};
+
+/// Creates the per-user result of republishing their pkarr record to the DHT.
+///
+/// One row per user, overwritten by every attempt. Success and failure
+/// timestamps, and the last failure reason, are kept across attempts.
+pub struct M20261019CreateUserRepublishStatusMigration;
+
+#[async_trait]
+impl MigrationTrait for M20261019CreateUserRepublishStatusMigration {
+    async fn up(&self, tx: &mut Transaction<'static, sqlx::Postgres>) -> anyhow::Result<()> {
+        let statement = Table::create()
+            .table(REPUBLISH_STATUS_TABLE)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(RepublishStatusIden::User)
+                    .integer()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(RepublishStatusIden::LastOutcome)
+                    .string()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(RepublishStatusIden::LastAttemptAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(RepublishStatusIden::LastSuccessAt)
+                    .timestamp()
+                    .null(),
+            )
+            .col(
+                ColumnDef::new(RepublishStatusIden::LastFailureAt)
+                    .timestamp()
+                    .null(),
+            )
+            .col(
+                ColumnDef::new(RepublishStatusIden::LastFailureReason)
+                    .text()
+                    .null(),
+            )
+            .to_owned();
+        let query = statement.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        let foreign_key = ForeignKey::create()
+            .name("fk_user_republish_status_user")
+            .from(REPUBLISH_STATUS_TABLE, RepublishStatusIden::User)
+            .to(USER_TABLE, UserIden::Id)
+            .on_delete(ForeignKeyAction::Cascade)
+            .to_owned();
+        let query = foreign_key.build(PostgresQueryBuilder);
+        sqlx::query(query.as_str()).execute(&mut **tx).await?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "m20261019_create_user_republish_status"
+    }
+}
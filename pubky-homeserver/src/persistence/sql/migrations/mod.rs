@@ -9,6 +9,16 @@ mod m20260327_add_quota_columns;
 mod m20260507_add_allowed_write_paths;
 mod m20260609_add_signup_code_used_at;
 mod m20260723_sanitize_capabilities;
+mod m20261018_add_usage_quota_columns;
+mod m20261018_create_banned_keys;
+mod m20261018_create_content_blocks;
+pub(crate) mod m20261018_create_event_watermarks;
+mod m20261018_create_path_quotas;
+pub(crate) mod m20261018_create_rate_limit_buckets;
+mod m20261018_create_user_usage;
+mod m20261019_add_republish_scheduling;
+pub(crate) mod m20261019_create_mirror_cursors;
+mod m20261019_create_user_republish_status;
 
 pub(crate) use m20250806_create_user::M20250806CreateUserMigration;
 pub(crate) use m20250812_create_signup_code::M20250812CreateSignupCodeMigration;
@@ -21,3 +31,13 @@ pub(crate) use m20260327_add_quota_columns::M20260327AddQuotaColumnsMigration;
 pub(crate) use m20260507_add_allowed_write_paths::M20260507AddAllowedWritePathsMigration;
 pub(crate) use m20260609_add_signup_code_used_at::M20260609AddSignupCodeUsedAtMigration;
 pub(crate) use m20260723_sanitize_capabilities::M20260723SanitizeCapabilitiesMigration;
+pub(crate) use m20261018_add_usage_quota_columns::M20261018AddUsageQuotaColumnsMigration;
+pub(crate) use m20261018_create_banned_keys::M20261018CreateBannedKeysMigration;
+pub(crate) use m20261018_create_content_blocks::M20261018CreateContentBlocksMigration;
+pub(crate) use m20261018_create_event_watermarks::M20261018CreateEventWatermarksMigration;
+pub(crate) use m20261018_create_path_quotas::M20261018CreatePathQuotasMigration;
+pub(crate) use m20261018_create_rate_limit_buckets::M20261018CreateRateLimitBucketsMigration;
+pub(crate) use m20261018_create_user_usage::M20261018CreateUserUsageMigration;
+pub(crate) use m20261019_add_republish_scheduling::M20261019AddRepublishSchedulingMigration;
+pub(crate) use m20261019_create_mirror_cursors::M20261019CreateMirrorCursorsMigration;
+pub(crate) use m20261019_create_user_republish_status::M20261019CreateUserRepublishStatusMigration;
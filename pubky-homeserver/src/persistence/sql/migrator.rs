@@ -10,7 +10,12 @@ use crate::persistence::sql::{
         M20250815CreateEntryMigration, M20251014EventsTableIndexAndContentHashMigration,
         M20260325CreateGrantSessionsMigration, M20260327AddQuotaColumnsMigration,
         M20260507AddAllowedWritePathsMigration, M20260609AddSignupCodeUsedAtMigration,
-        M20260723SanitizeCapabilitiesMigration,
+        M20260723SanitizeCapabilitiesMigration, M20261018AddUsageQuotaColumnsMigration,
+        M20261018CreateBannedKeysMigration, M20261018CreateContentBlocksMigration,
+        M20261018CreateEventWatermarksMigration, M20261018CreatePathQuotasMigration,
+        M20261018CreateRateLimitBucketsMigration, M20261018CreateUserUsageMigration,
+        M20261019AddRepublishSchedulingMigration, M20261019CreateMirrorCursorsMigration,
+        M20261019CreateUserRepublishStatusMigration,
     },
     sql_db::SqlDb,
 };
@@ -45,6 +50,16 @@ impl<'a> Migrator<'a> {
             Box::new(M20260507AddAllowedWritePathsMigration),
             Box::new(M20260609AddSignupCodeUsedAtMigration),
             Box::new(M20260723SanitizeCapabilitiesMigration),
+            Box::new(M20261018CreateEventWatermarksMigration),
+            Box::new(M20261018CreateRateLimitBucketsMigration),
+            Box::new(M20261018AddUsageQuotaColumnsMigration),
+            Box::new(M20261018CreateUserUsageMigration),
+            Box::new(M20261018CreatePathQuotasMigration),
+            Box::new(M20261018CreateBannedKeysMigration),
+            Box::new(M20261018CreateContentBlocksMigration),
+            Box::new(M20261019CreateMirrorCursorsMigration),
+            Box::new(M20261019CreateUserRepublishStatusMigration),
+            Box::new(M20261019AddRepublishSchedulingMigration),
         ]
     }
 
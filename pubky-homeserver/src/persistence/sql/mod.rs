@@ -2,11 +2,13 @@
 //!
 //! Manages the connection pool ([`SqlDb`]), schema migrations ([`Migrator`]),
 //! and entity repositories for users, sessions, entries, events, and signup codes.
+//! [`EventRetentionJob`] prunes the events table in the background.
 //! The [`UnifiedExecutor`] abstraction allows repository methods to work with
 //! both pooled connections and explicit transactions.
 
 mod connection_string;
 pub(crate) mod entities;
+mod event_retention_job;
 mod migration;
 pub(crate) mod migrations;
 mod migrator;
@@ -15,9 +17,16 @@ mod sql_db;
 mod unified_executor;
 
 pub use connection_string::ConnectionString;
+pub(crate) use entities::banned_key;
+pub(crate) use entities::content_block;
 pub use entities::entry;
+pub(crate) use entities::mirror_cursor;
+pub(crate) use entities::path_quota;
+pub(crate) use entities::republish_status;
 pub use entities::signup_code;
 pub(crate) use entities::user;
+pub(crate) use entities::user_usage;
+pub(crate) use event_retention_job::{EventRetentionJob, EventRetentionStatus};
 pub use migrator::Migrator;
 pub(crate) use pg_event_listener::PgEventListener;
 pub use sql_db::SqlDb;
@@ -4,18 +4,22 @@
 //!
 //! The task is responsible for:
 //! - Republishing the homeserver's pkarr packet to the DHT every hour.
+//! - During a key transition, also republishing a packet for the previous key
+//!   with a `_successor` record pointing at the current key.
 //! - Stopping the task when the homeserver is stopped.
 
 use std::borrow::Cow;
 use std::net::IpAddr;
+use std::time::SystemTime;
 
 use anyhow::Result;
 use pkarr::dns::Name;
 use pkarr::errors::PublishError;
 use pkarr::{
-    dns::rdata::{SVCParam, SVCB},
-    SignedPacket,
+    dns::rdata::{SVCParam, SVCB, TXT},
+    SignedPacket, SignedPacketBuilder,
 };
+use pubky_common::constants::homeserver_records::SUCCESSOR;
 
 use crate::app_context::AppContext;
 use tokio::task::JoinHandle;
@@ -41,11 +45,39 @@ impl HomeserverKeyRepublisher {
         pubky_tls_port: u16,
     ) -> Result<Self> {
         let signed_packet = create_signed_packet(context, icann_http_port, pubky_tls_port)?;
-        let join_handle =
-            Self::start_periodic_republish(context.pkarr_client.clone(), &signed_packet).await?;
+        let successor_packet = create_successor_packet(context, icann_http_port, pubky_tls_port)?;
+        let join_handle = Self::start_periodic_republish(
+            context.pkarr_client.clone(),
+            &signed_packet,
+            successor_packet,
+        )
+        .await?;
         Ok(Self { join_handle })
     }
 
+    /// Publish the previous key's packet until the transition ends.
+    /// Failures are only logged: the current key is what clients need.
+    async fn publish_successor(
+        client: &pkarr::Client,
+        successor_packet: &Option<(SignedPacket, SystemTime)>,
+    ) {
+        let Some((packet, ends_at)) = successor_packet else {
+            return;
+        };
+        if SystemTime::now() >= *ends_at {
+            return;
+        }
+        match client.publish(packet).await {
+            Ok(_) => tracing::info!(
+                "Published the successor record of the previous homeserver key {} to the DHT.",
+                packet.public_key()
+            ),
+            Err(e) => tracing::warn!(
+                "Failed to publish the successor record of the previous homeserver key: {e}"
+            ),
+        }
+    }
+
     async fn publish_once(
         client: &pkarr::Client,
         signed_packet: &SignedPacket,
@@ -67,11 +99,13 @@ impl HomeserverKeyRepublisher {
     async fn start_periodic_republish(
         client: pkarr::Client,
         signed_packet: &SignedPacket,
+        successor_packet: Option<(SignedPacket, SystemTime)>,
     ) -> anyhow::Result<JoinHandle<()>> {
         // Publish once to make sure the packet is published to the DHT before this
         // function returns.
         // Throws an error if the packet is not published to the DHT.
         Self::publish_once(&client, signed_packet).await?;
+        Self::publish_successor(&client, &successor_packet).await;
 
         // Start the periodic republish task.
         let signed_packet = signed_packet.clone();
@@ -81,6 +115,7 @@ impl HomeserverKeyRepublisher {
             loop {
                 interval.tick().await;
                 let _ = Self::publish_once(&client, &signed_packet).await;
+                Self::publish_successor(&client, &successor_packet).await;
             }
         });
 
@@ -104,6 +139,43 @@ pub fn create_signed_packet(
     local_icann_http_port: u16,
     local_pubky_tls_port: u16,
 ) -> Result<SignedPacket> {
+    let signed_packet_builder =
+        endpoint_records(context, local_icann_http_port, local_pubky_tls_port)?;
+    Ok(signed_packet_builder.build(&context.keypair)?)
+}
+
+/// The packet of the previous key during a key transition, with the same
+/// endpoints as the current key plus a `_successor` record naming it.
+/// Returns the packet and when the transition ends.
+pub fn create_successor_packet(
+    context: &AppContext,
+    local_icann_http_port: u16,
+    local_pubky_tls_port: u16,
+) -> Result<Option<(SignedPacket, SystemTime)>> {
+    let Some(transition) = context
+        .key_transition
+        .as_ref()
+        .filter(|transition| transition.is_active())
+    else {
+        return Ok(None);
+    };
+    let keys = context.homeserver_keys();
+    let previous_keypair = keys.previous_keypair().expect("the transition is active");
+
+    let successor_z32 = keys.current().z32();
+    let successor = TXT::try_from(successor_z32.as_str())?;
+    let signed_packet = endpoint_records(context, local_icann_http_port, local_pubky_tls_port)?
+        .txt(SUCCESSOR.try_into()?, successor, 60 * 60)
+        .build(previous_keypair)?;
+    Ok(Some((signed_packet, transition.ends_at())))
+}
+
+/// The records telling clients how to reach this homeserver.
+fn endpoint_records(
+    context: &AppContext,
+    local_icann_http_port: u16,
+    local_pubky_tls_port: u16,
+) -> Result<SignedPacketBuilder> {
     let root_name: Name = "."
         .try_into()
         .expect(". is the root domain and always valid");
@@ -161,7 +233,7 @@ pub fn create_signed_packet(
     // `A` record to the public IP. This is used for regular browser connections.
     signed_packet_builder = signed_packet_builder.address(root_name.clone(), public_ip, 60 * 60);
 
-    Ok(signed_packet_builder.build(&context.keypair)?)
+    Ok(signed_packet_builder)
 }
 
 #[cfg(test)]
@@ -210,6 +282,42 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_previous_key_points_at_successor() {
+        let (context, _dht) = test_context().await;
+        let previous = pubky_common::crypto::Keypair::random();
+        let transition = crate::shared::KeyTransition::from_rotation(
+            crate::KeyRotation::now(previous.clone()),
+            30,
+        );
+        let context = context.with_key_transition(transition);
+        let _republisher = HomeserverKeyRepublisher::start(&context, 8080, 8080)
+            .await
+            .unwrap();
+
+        let client = context.pkarr_builder.clone().build().unwrap();
+        let packet = client
+            .resolve(&previous.public_key(), ResolvePolicy::CacheFirst)
+            .await
+            .unwrap();
+        let successor = packet
+            .resource_records(SUCCESSOR)
+            .find_map(|record| match &record.rdata {
+                pkarr::dns::rdata::RData::TXT(txt) => String::try_from(txt.clone()).ok(),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(successor, context.keypair.public_key().z32());
+
+        // The previous key still resolves to this homeserver.
+        let endpoint = client
+            .resolve_https_endpoint(previous.public_key().z32().as_str())
+            .await
+            .unwrap();
+        assert_eq!(endpoint.port(), Some(8080));
+    }
+
     #[tokio::test]
     #[pubky_test_utils::test]
     async fn test_endpoints() {
@@ -4,6 +4,9 @@
 //!   DHT every hour.
 //! - [`UserKeysRepublisher`]: Periodically republishes all users' public keys
 //!   to the DHT so they remain discoverable (configurable interval, minimum 30 min).
+//!   Users listing this homeserver as a mirror are republished too. The outcome
+//!   per user is stored in `user_republish_status`; users can also request an
+//!   immediate republish of their own key.
 
 mod key_republisher;
 pub(crate) mod pkarr_republisher;
@@ -11,4 +14,6 @@ mod user_keys_republisher;
 
 pub(crate) use key_republisher::HomeserverKeyRepublisher;
 pub use key_republisher::KeyRepublisherBuildError;
-pub(crate) use user_keys_republisher::UserKeysRepublisherJob;
+pub(crate) use user_keys_republisher::{
+    listed_homeservers, UserKeysRepublisher, UserKeysRepublisherJob,
+};
@@ -75,19 +75,38 @@ impl BatchRepublisher {
             .await
     }
 
+    /// Republish keys one after another with an existing `client` instead of
+    /// building clients from the builder, e.g. for a single key on request.
+    pub async fn run_with_client(
+        &self,
+        client: pkarr::Client,
+        public_keys: Vec<PublicKey>,
+    ) -> RepublishSummary {
+        self.republish_all(client, &Mutex::new(public_keys)).await
+    }
+
     async fn run_worker(
         &self,
         public_keys: &PublicKeyQueue,
     ) -> Result<RepublishSummary, BuildError> {
         let client = self.client_builder.build()?;
+        Ok(self.republish_all(client, public_keys).await)
+    }
+
+    async fn republish_all(
+        &self,
+        client: pkarr::Client,
+        public_keys: &PublicKeyQueue,
+    ) -> RepublishSummary {
         let republisher = Republisher::new(client, self.settings.republisher.clone());
         let republisher = RetryingRepublisher::new(republisher, &self.settings.retry);
 
         let mut summary = RepublishSummary::default();
         while let Some(public_key) = pop(public_keys) {
-            summary.record(republish_key(&republisher, &public_key).await);
+            let result = republish_key(&republisher, &public_key).await;
+            summary.record(public_key, result);
         }
-        Ok(summary)
+        summary
     }
 }
 
@@ -4,7 +4,7 @@ mod republisher;
 mod retrying_republisher;
 
 pub use batch_republisher::{BatchRepublisher, BatchRepublisherSettings};
-pub use republish_summary::RepublishSummary;
+pub use republish_summary::{KeyRepublishResult, RepublishSummary};
 
 #[cfg(test)]
 pub(super) fn test_client_builder(testnet: &mainline::Testnet) -> pkarr::ClientBuilder {
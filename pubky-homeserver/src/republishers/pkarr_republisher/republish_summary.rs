@@ -1,3 +1,6 @@
+use pkarr::{PublicKey, Timestamp};
+use pubky_common::republish_status::RepublishOutcome as KeyOutcome;
+
 use super::{
     republisher::{RepublishError, RepublishOutcome},
     retrying_republisher::RepublishInfo,
@@ -5,7 +8,18 @@ use super::{
 
 pub(super) type RepublishResult = Result<RepublishInfo, RepublishError>;
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Outcome of republishing a single key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRepublishResult {
+    pub public_key: PublicKey,
+    pub outcome: KeyOutcome,
+    /// Why the key needs attention. Only set for missing, invalid and failed keys.
+    pub failure_reason: Option<String>,
+    /// Timestamp of the packet found on the DHT. Only set for published and skipped keys.
+    pub packet_timestamp: Option<Timestamp>,
+}
+
+#[derive(Clone, Default, PartialEq, Eq)]
 pub struct RepublishSummary {
     total_count: usize,
     success_count: usize,
@@ -13,31 +27,56 @@ pub struct RepublishSummary {
     missing_count: usize,
     invalid_signed_packet_count: usize,
     failed_count: usize,
+    keys: Vec<KeyRepublishResult>,
 }
 
 impl RepublishSummary {
-    pub(super) fn record(&mut self, result: RepublishResult) {
+    pub(super) fn record(&mut self, public_key: PublicKey, result: RepublishResult) {
         self.total_count += 1;
 
-        match result {
+        let packet_timestamp = result.as_ref().ok().and_then(|info| info.packet_timestamp);
+        let (outcome, failure_reason) = match result {
             Ok(RepublishInfo {
                 outcome: RepublishOutcome::Published,
                 ..
-            }) => self.success_count += 1,
+            }) => {
+                self.success_count += 1;
+                (KeyOutcome::Published, None)
+            }
             Ok(RepublishInfo {
                 outcome: RepublishOutcome::Skipped,
                 ..
-            }) => self.skipped_count += 1,
+            }) => {
+                self.skipped_count += 1;
+                (KeyOutcome::Skipped, None)
+            }
             Ok(RepublishInfo {
                 outcome: RepublishOutcome::Missing,
                 ..
-            }) => self.missing_count += 1,
+            }) => {
+                self.missing_count += 1;
+                let reason = "no signed packet found on the DHT";
+                (KeyOutcome::Missing, Some(reason.to_string()))
+            }
             Ok(RepublishInfo {
                 outcome: RepublishOutcome::InvalidSignedPacket,
                 ..
-            }) => self.invalid_signed_packet_count += 1,
-            Err(_) => self.failed_count += 1,
-        }
+            }) => {
+                self.invalid_signed_packet_count += 1;
+                let reason = "signed packet found on the DHT is invalid";
+                (KeyOutcome::InvalidPacket, Some(reason.to_string()))
+            }
+            Err(error) => {
+                self.failed_count += 1;
+                (KeyOutcome::Failed, Some(error.to_string()))
+            }
+        };
+        self.keys.push(KeyRepublishResult {
+            public_key,
+            outcome,
+            failure_reason,
+            packet_timestamp,
+        });
     }
 
     pub(crate) fn merge(mut self, other: Self) -> Self {
@@ -47,9 +86,15 @@ impl RepublishSummary {
         self.missing_count += other.missing_count;
         self.invalid_signed_packet_count += other.invalid_signed_packet_count;
         self.failed_count += other.failed_count;
+        self.keys.extend(other.keys);
         self
     }
 
+    /// Outcome of every processed key, in no particular order.
+    pub fn keys(&self) -> &[KeyRepublishResult] {
+        &self.keys
+    }
+
     pub fn is_empty(&self) -> bool {
         self.total_count == 0
     }
@@ -60,6 +105,23 @@ impl RepublishSummary {
     }
 }
 
+/// Logs the counts only; the per-key results can be large.
+impl std::fmt::Debug for RepublishSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RepublishSummary")
+            .field("total_count", &self.total_count)
+            .field("success_count", &self.success_count)
+            .field("skipped_count", &self.skipped_count)
+            .field("missing_count", &self.missing_count)
+            .field(
+                "invalid_signed_packet_count",
+                &self.invalid_signed_packet_count,
+            )
+            .field("failed_count", &self.failed_count)
+            .finish_non_exhaustive()
+    }
+}
+
 #[cfg(test)]
 impl RepublishSummary {
     /// Number of republish attempts.
@@ -95,24 +157,34 @@ impl RepublishSummary {
 
 #[cfg(test)]
 mod tests {
-    use super::{RepublishError, RepublishInfo, RepublishOutcome, RepublishSummary};
+    use pkarr::Keypair;
+
+    use super::{KeyOutcome, RepublishError, RepublishInfo, RepublishOutcome, RepublishSummary};
 
     #[test]
     fn records_and_merges_republish_outcomes() {
+        let key = || Keypair::random().public_key();
         let mut summary = RepublishSummary::default();
-        summary.record(Ok(RepublishInfo::new(RepublishOutcome::Published, 1)));
-        summary.record(Ok(RepublishInfo::new(RepublishOutcome::Skipped, 1)));
+        summary.record(
+            key(),
+            Ok(RepublishInfo::new(RepublishOutcome::Published, 1)),
+        );
+        summary.record(key(), Ok(RepublishInfo::new(RepublishOutcome::Skipped, 1)));
         assert!(!summary.has_issues());
 
         let mut other = RepublishSummary::default();
-        other.record(Ok(RepublishInfo::new(RepublishOutcome::Missing, 1)));
-        other.record(Ok(RepublishInfo::new(
-            RepublishOutcome::InvalidSignedPacket,
-            1,
-        )));
-        other.record(Err(RepublishError::InsufficientlyPublished {
-            published_nodes_count: 1,
-        }));
+        other.record(key(), Ok(RepublishInfo::new(RepublishOutcome::Missing, 1)));
+        other.record(
+            key(),
+            Ok(RepublishInfo::new(RepublishOutcome::InvalidSignedPacket, 1)),
+        );
+        let failed_key = key();
+        other.record(
+            failed_key.clone(),
+            Err(RepublishError::InsufficientlyPublished {
+                published_nodes_count: 1,
+            }),
+        );
 
         let summary = summary.merge(other);
 
@@ -123,5 +195,24 @@ mod tests {
         assert_eq!(summary.invalid_signed_packet_count(), 1);
         assert_eq!(summary.failed_count(), 1);
         assert!(summary.has_issues());
+
+        let outcomes: Vec<KeyOutcome> = summary.keys().iter().map(|key| key.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                KeyOutcome::Published,
+                KeyOutcome::Skipped,
+                KeyOutcome::Missing,
+                KeyOutcome::InvalidPacket,
+                KeyOutcome::Failed,
+            ]
+        );
+        let failed = &summary.keys()[4];
+        assert_eq!(failed.public_key, failed_key);
+        assert_eq!(
+            failed.failure_reason.as_deref(),
+            Some("packet was published to only 1 nodes")
+        );
+        assert!(summary.keys()[0].failure_reason.is_none());
     }
 }
@@ -8,7 +8,7 @@
 //! public key; enforcing that invariant belongs to Pkarr and its cache.
 use pkarr::{
     errors::{PublishError, ResolveError},
-    PublicKey, ResolvePolicy, SignedPacket, StoredNodeCount,
+    PublicKey, ResolvePolicy, SignedPacket, StoredNodeCount, Timestamp,
 };
 use std::{num::NonZeroU8, sync::Arc};
 
@@ -32,6 +32,33 @@ pub(super) enum RepublishOutcome {
     InvalidSignedPacket,
 }
 
+/// Result of a single republish attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct RepublishAttempt {
+    pub(super) outcome: RepublishOutcome,
+    /// Timestamp of the packet found for the key, if any. Tells how old the
+    /// record is, including when it was skipped.
+    pub(super) packet_timestamp: Option<Timestamp>,
+}
+
+impl RepublishAttempt {
+    fn with_packet(outcome: RepublishOutcome, packet: &SignedPacket) -> Self {
+        Self {
+            outcome,
+            packet_timestamp: Some(packet.timestamp()),
+        }
+    }
+}
+
+impl From<RepublishOutcome> for RepublishAttempt {
+    fn from(outcome: RepublishOutcome) -> Self {
+        Self {
+            outcome,
+            packet_timestamp: None,
+        }
+    }
+}
+
 pub(super) type RepublishCondition = dyn Fn(&SignedPacket) -> bool + Send + Sync;
 
 /// Settings for creating a republisher.
@@ -82,12 +109,15 @@ impl Republisher {
     pub(super) async fn republish(
         &self,
         public_key: &PublicKey,
-    ) -> Result<RepublishOutcome, RepublishError> {
+    ) -> Result<RepublishAttempt, RepublishError> {
         let cached_packet = self.resolve_cached(public_key).await;
 
         if let Some(packet) = cached_packet.as_ref() {
             if self.try_publish_cached(packet).await? {
-                return Ok(RepublishOutcome::Published);
+                return Ok(RepublishAttempt::with_packet(
+                    RepublishOutcome::Published,
+                    packet,
+                ));
             }
         }
 
@@ -136,7 +166,7 @@ impl Republisher {
         &self,
         public_key: &PublicKey,
         cached_packet: Option<SignedPacket>,
-    ) -> Result<RepublishOutcome, RepublishError> {
+    ) -> Result<RepublishAttempt, RepublishError> {
         let network_packet = match self
             .client
             .resolve(public_key, ResolvePolicy::NetworkOnly)
@@ -152,7 +182,7 @@ impl Republisher {
                 None
             }
             Err(ResolveError::InvalidSignedPacket { .. }) => {
-                return Ok(RepublishOutcome::InvalidSignedPacket);
+                return Ok(RepublishOutcome::InvalidSignedPacket.into());
             }
             Err(error) => return Err(error.into()),
         };
@@ -161,15 +191,21 @@ impl Republisher {
             (Some(network), Some(cached)) if cached.more_recent_than(&network) => cached,
             (Some(network), _) => network,
             (None, Some(cached)) => cached,
-            (None, None) => return Ok(RepublishOutcome::Missing),
+            (None, None) => return Ok(RepublishOutcome::Missing.into()),
         };
 
         if !self.should_republish(&packet) {
-            return Ok(RepublishOutcome::Skipped);
+            return Ok(RepublishAttempt::with_packet(
+                RepublishOutcome::Skipped,
+                &packet,
+            ));
         }
 
         self.publish(&packet).await?;
-        Ok(RepublishOutcome::Published)
+        Ok(RepublishAttempt::with_packet(
+            RepublishOutcome::Published,
+            &packet,
+        ))
     }
 
     fn should_republish(&self, packet: &SignedPacket) -> bool {
@@ -296,7 +332,7 @@ mod tests {
 
         let settings = test_settings();
         let republisher = Republisher::new(pkarr_client, settings);
-        let outcome = republisher.republish(&public_key).await.unwrap();
+        let outcome = republisher.republish(&public_key).await.unwrap().outcome;
 
         assert_eq!(outcome, RepublishOutcome::Published);
     }
@@ -311,7 +347,7 @@ mod tests {
         let settings = test_settings();
         let republisher = Republisher::new(republishing_client, settings);
 
-        let outcome = republisher.republish(&public_key).await.unwrap();
+        let outcome = republisher.republish(&public_key).await.unwrap().outcome;
 
         assert_eq!(outcome, RepublishOutcome::Published);
     }
@@ -338,7 +374,7 @@ mod tests {
         );
 
         let republisher = Republisher::new(combined_client, test_settings());
-        let outcome = republisher.republish(&public_key).await.unwrap();
+        let outcome = republisher.republish(&public_key).await.unwrap().outcome;
 
         assert_eq!(outcome, RepublishOutcome::Published);
     }
@@ -352,7 +388,7 @@ mod tests {
 
         let settings = test_settings();
         let republisher = Republisher::new(pkarr_client, settings);
-        let outcome = republisher.republish(&public_key).await.unwrap();
+        let outcome = republisher.republish(&public_key).await.unwrap().outcome;
 
         assert_eq!(outcome, RepublishOutcome::Missing);
     }
@@ -375,7 +411,7 @@ mod tests {
         };
 
         let republisher = Republisher::new(pkarr_client, settings);
-        let outcome = republisher.republish(&public_key).await.unwrap();
+        let outcome = republisher.republish(&public_key).await.unwrap().outcome;
 
         assert_eq!(outcome, RepublishOutcome::Skipped);
         assert_eq!(condition_calls.load(Ordering::Relaxed), 2);
@@ -400,7 +436,11 @@ mod tests {
         };
         let republisher = Republisher::new(republishing_client, settings);
 
-        let outcome = republisher.republish(&key.public_key()).await.unwrap();
+        let outcome = republisher
+            .republish(&key.public_key())
+            .await
+            .unwrap()
+            .outcome;
 
         assert_eq!(outcome, RepublishOutcome::Skipped);
         assert_eq!(cache.reads(), 1);
@@ -429,9 +469,10 @@ mod tests {
         };
         let republisher = Republisher::new(republishing_client, settings);
 
-        let outcome = republisher.republish(&key.public_key()).await.unwrap();
+        let attempt = republisher.republish(&key.public_key()).await.unwrap();
 
-        assert_eq!(outcome, RepublishOutcome::Skipped);
+        assert_eq!(attempt.outcome, RepublishOutcome::Skipped);
+        assert_eq!(attempt.packet_timestamp, Some(cached_packet.timestamp()));
     }
 
     #[tokio::test]
@@ -453,7 +494,11 @@ mod tests {
             },
         );
 
-        let outcome = republisher.republish(&key.public_key()).await.unwrap();
+        let outcome = republisher
+            .republish(&key.public_key())
+            .await
+            .unwrap()
+            .outcome;
 
         assert_eq!(outcome, RepublishOutcome::Skipped);
     }
@@ -477,7 +522,11 @@ mod tests {
             },
         );
 
-        let outcome = republisher.republish(&key.public_key()).await.unwrap();
+        let outcome = republisher
+            .republish(&key.public_key())
+            .await
+            .unwrap()
+            .outcome;
 
         assert_eq!(outcome, RepublishOutcome::InvalidSignedPacket);
     }
@@ -502,7 +551,11 @@ mod tests {
         };
         let republisher = Republisher::new(republishing_client, settings);
 
-        let outcome = republisher.republish(&key.public_key()).await.unwrap();
+        let outcome = republisher
+            .republish(&key.public_key())
+            .await
+            .unwrap()
+            .outcome;
 
         assert_eq!(outcome, RepublishOutcome::Published);
     }
@@ -520,7 +573,7 @@ mod tests {
         };
 
         let republisher = Republisher::new(pkarr_client, settings);
-        let outcome = republisher.republish(&public_key).await.unwrap();
+        let outcome = republisher.republish(&public_key).await.unwrap().outcome;
 
         assert_eq!(outcome, RepublishOutcome::Published);
     }
@@ -1,23 +1,27 @@
 use std::{future::Future, num::NonZeroU8, time::Duration};
 
 use backon::{ExponentialBuilder, Retryable};
-use pkarr::PublicKey;
+use pkarr::{PublicKey, Timestamp};
 
-use super::republisher::{RepublishError, RepublishOutcome, Republisher};
+use super::republisher::{RepublishAttempt, RepublishError, RepublishOutcome, Republisher};
 
 #[derive(Debug, Clone)]
 pub(super) struct RepublishInfo {
     /// Result of the republish attempt.
     pub(super) outcome: RepublishOutcome,
+    /// Timestamp of the packet found for the key, if any.
+    pub(super) packet_timestamp: Option<Timestamp>,
     /// Number of republish attempts needed to finish processing the key.
     #[allow(dead_code)]
     pub(super) attempts_needed: usize,
 }
 
 impl RepublishInfo {
-    pub(super) fn new(outcome: RepublishOutcome, attempts_needed: usize) -> Self {
+    pub(super) fn new(attempt: impl Into<RepublishAttempt>, attempts_needed: usize) -> Self {
+        let attempt = attempt.into();
         Self {
-            outcome,
+            outcome: attempt.outcome,
+            packet_timestamp: attempt.packet_timestamp,
             attempts_needed,
         }
     }
@@ -93,7 +97,7 @@ async fn republish_with_retry<F, Fut>(
 ) -> Result<RepublishInfo, RepublishError>
 where
     F: FnMut() -> Fut,
-    Fut: Future<Output = Result<RepublishOutcome, RepublishError>>,
+    Fut: Future<Output = Result<RepublishAttempt, RepublishError>>,
 {
     let mut attempts_count = 1;
 
@@ -112,7 +116,7 @@ where
             attempts_count += 1;
         })
         .await
-        .map(|outcome| RepublishInfo::new(outcome, attempts_count))
+        .map(|attempt| RepublishInfo::new(attempt, attempts_count))
 }
 
 #[cfg(test)]
@@ -190,7 +194,7 @@ mod tests {
                 ready(if attempt < 3 {
                     Err(publish_error())
                 } else {
-                    Ok(RepublishOutcome::Published)
+                    Ok(RepublishOutcome::Published.into())
                 })
             },
         )
@@ -218,7 +222,7 @@ mod tests {
                 ready(if attempt == 1 {
                     Err(resolve_error())
                 } else {
-                    Ok(RepublishOutcome::Published)
+                    Ok(RepublishOutcome::Published.into())
                 })
             },
         )
@@ -242,7 +246,7 @@ mod tests {
             settings.max_retry_delay,
             || {
                 attempts.set(attempts.get() + 1);
-                ready(Ok(RepublishOutcome::Missing))
+                ready(Ok(RepublishOutcome::Missing.into()))
             },
         )
         .await
@@ -1,16 +1,29 @@
+use std::num::NonZeroU64;
 use std::time::Duration;
 
-use pkarr::{dns::rdata::RData, errors::BuildError, SignedPacket};
+use pkarr::{errors::BuildError, SignedPacket};
 use pubky_common::crypto::PublicKey;
+use pubky_common::homeservers::extract_hosts_from_packet;
+use sqlx::types::chrono::{DateTime, Utc};
 use tokio::{
     task::JoinHandle,
     time::{interval, Instant},
 };
 
-use super::pkarr_republisher::{BatchRepublisher, BatchRepublisherSettings, RepublishSummary};
-use crate::services::user_service::UserService;
+use super::pkarr_republisher::{
+    BatchRepublisher, BatchRepublisherSettings, KeyRepublishResult, RepublishSummary,
+};
+use crate::data_directory::ConfigToml;
+use crate::observability::Metrics;
+use crate::persistence::sql::{
+    republish_status::{RepublishDueQuery, RepublishStatusRepository},
+    SqlDb,
+};
+use crate::shared::HomeserverKeys;
+use crate::AppContext;
 
 const MIN_REPUBLISH_INTERVAL: Duration = Duration::from_secs(30 * 60);
+const HOUR: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum UserKeysRepublisherError {
@@ -29,46 +42,38 @@ impl UserKeysRepublisherJob {
     const INITIAL_DELAY_BEFORE_REPUBLISH: Duration = Duration::from_secs(60);
 
     /// Run the user keys republisher with an initial delay.
-    pub fn start(
-        user_service: UserService,
-        pkarr_builder: pkarr::ClientBuilder,
-        homeserver_public_key: PublicKey,
-        mut republish_interval: Duration,
-    ) -> Option<Self> {
-        if republish_interval.is_zero() {
+    pub fn start(mut republisher: UserKeysRepublisher) -> Option<Self> {
+        let schedule = &mut republisher.schedule;
+        if schedule.interval.is_zero() {
             tracing::info!("User keys republisher is disabled.");
             return None;
         }
-        if republish_interval < MIN_REPUBLISH_INTERVAL {
+        if schedule.interval < MIN_REPUBLISH_INTERVAL {
             tracing::warn!(
                 "The configured user keys republisher interval is less than {}s. To avoid spamming the Mainline DHT, the value is set to {}s.",
                 MIN_REPUBLISH_INTERVAL.as_secs(),
                 MIN_REPUBLISH_INTERVAL.as_secs()
             );
-            republish_interval = MIN_REPUBLISH_INTERVAL;
+            schedule.interval = MIN_REPUBLISH_INTERVAL;
         }
         tracing::info!(
-            "Initialize user keys republisher with an interval of {:?} and an initial delay of {:?}",
-            republish_interval,
+            ?schedule,
+            "Initialize user keys republisher with an initial delay of {:?}",
             Self::INITIAL_DELAY_BEFORE_REPUBLISH
         );
 
-        if republish_interval < Duration::from_secs(60 * 60) {
+        if schedule.interval < HOUR {
             tracing::warn!(
                 "User keys republisher interval is less than 60min. This is strongly discouraged "
             );
         }
 
-        let republisher = UserKeysRepublisher {
-            user_service,
-            pkarr_builder,
-            homeserver_public_key,
-        };
         let handle = tokio::spawn(async move {
             tokio::time::sleep(Self::INITIAL_DELAY_BEFORE_REPUBLISH).await;
-            let mut interval = interval(republish_interval);
+            let mut interval = interval(republisher.schedule.interval);
             loop {
                 interval.tick().await;
+                tokio::time::sleep(republisher.schedule.random_jitter()).await;
                 republisher.republish().await;
             }
         });
@@ -82,13 +87,84 @@ impl Drop for UserKeysRepublisherJob {
     }
 }
 
-struct UserKeysRepublisher {
-    user_service: UserService,
+/// Which users the user keys republisher republishes, and when. See `[pkdns]` in the config.
+///
+/// Each run only republishes records older than `min_age`. Recently active users
+/// go first, then the oldest records. The number of keys republished per hour
+/// is capped by `budget_per_hour`, shared across instances through the database.
+#[derive(Debug, Clone)]
+pub(crate) struct RepublishSchedule {
+    /// Time between two runs.
+    interval: Duration,
+    /// Records refreshed on the DHT more recently than this are left alone.
+    min_age: Duration,
+    /// Users with events in this window are republished first.
+    active_window: Option<Duration>,
+    /// Maximum number of keys republished per hour. `None` means unlimited.
+    budget_per_hour: Option<NonZeroU64>,
+    /// Maximum random delay added to each run.
+    jitter: Duration,
+}
+
+impl RepublishSchedule {
+    pub fn from_config(config: &ConfigToml) -> Self {
+        let config = &config.pkdns;
+        let active_days = config.user_keys_republisher_active_days;
+        Self {
+            interval: Duration::from_secs(config.user_keys_republisher_interval),
+            min_age: Duration::from_secs(config.user_keys_republisher_min_age),
+            active_window: (active_days > 0)
+                .then(|| Duration::from_secs(active_days * 24 * 60 * 60)),
+            budget_per_hour: NonZeroU64::new(config.user_keys_republisher_budget_per_hour),
+            jitter: Duration::from_secs(config.user_keys_republisher_jitter),
+        }
+    }
+
+    fn random_jitter(&self) -> Duration {
+        Duration::from_millis(rand::random_range(0..=self.jitter.as_millis() as u64))
+    }
+
+    /// Maximum number of keys a single run may republish, given `attempts_last_hour`.
+    ///
+    /// Spreads the hourly budget over the runs of an hour, so a short interval
+    /// does not spend it all in the first run. `None` means unlimited.
+    fn run_budget(&self, attempts_last_hour: u64) -> Option<u64> {
+        let budget = self.budget_per_hour?.get();
+        let runs_per_hour = HOUR.as_secs_f64() / self.interval.as_secs_f64().max(1.0);
+        let per_run = (budget as f64 / runs_per_hour.max(1.0)).ceil() as u64;
+        Some(per_run.min(budget.saturating_sub(attempts_last_hour)))
+    }
+}
+
+/// Republishes user keys and records the outcome per user in
+/// `user_republish_status` and in the metrics.
+pub(crate) struct UserKeysRepublisher {
+    sql_db: SqlDb,
     pkarr_builder: pkarr::ClientBuilder,
-    homeserver_public_key: PublicKey,
+    /// Users still pointing at the previous key during a key transition are
+    /// republished too.
+    homeserver_keys: HomeserverKeys,
+    metrics: Metrics,
+    schedule: RepublishSchedule,
 }
 
 impl UserKeysRepublisher {
+    /// Advisory lock ID used to allow only one republisher run across all instances.
+    const REPUBLISH_LOCK_ID: i64 = 0x72657075_626c6901; // "republi" + 1
+
+    /// Republisher for the users of the homeserver of `context`.
+    pub fn from_context(context: &AppContext) -> Self {
+        let mut pkarr_builder = context.pkarr_builder.clone();
+        pkarr_builder.no_relays(); // Disable relays to avoid their rate limiting.
+        Self {
+            sql_db: context.sql_db.clone(),
+            pkarr_builder,
+            homeserver_keys: context.homeserver_keys(),
+            metrics: context.metrics.clone(),
+            schedule: RepublishSchedule::from_config(&context.config_toml),
+        }
+    }
+
     async fn republish(&self) {
         let start = Instant::now();
         tracing::debug!("Republishing user keys...");
@@ -108,38 +184,154 @@ impl UserKeysRepublisher {
         }
     }
 
+    /// Republish the keys that are due, within the budget.
+    ///
+    /// Returns an empty summary if another instance is already republishing.
     async fn republish_impl(&self) -> Result<RepublishSummary, UserKeysRepublisherError> {
-        let keys = self.get_all_user_keys().await?;
+        // Session-level lock, held on a dedicated connection for the whole run.
+        let mut lock_con = self.sql_db.pool().acquire().await?;
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(Self::REPUBLISH_LOCK_ID)
+            .fetch_one(&mut *lock_con)
+            .await?;
+        if !locked {
+            tracing::debug!("User keys are already being republished by another instance");
+            return Ok(RepublishSummary::default());
+        }
+
+        let result = self.republish_due_keys().await;
+
+        let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(Self::REPUBLISH_LOCK_ID)
+            .execute(&mut *lock_con)
+            .await;
+        if let Err(e) = unlocked {
+            // Closing the connection releases the lock.
+            tracing::warn!("Failed to release the user keys republisher lock: {}", e);
+            drop(lock_con.detach());
+        }
+        result
+    }
+
+    async fn republish_due_keys(&self) -> Result<RepublishSummary, UserKeysRepublisherError> {
+        let keys = self.get_due_user_keys(Utc::now()).await?;
         if keys.is_empty() {
             tracing::debug!("No user keys to republish.");
             return Ok(RepublishSummary::default());
         }
-        let homeserver_public_key = self.homeserver_public_key.clone();
+        self.republish_keys(keys).await
+    }
+
+    /// Republish the key of a single user right away with `client`, e.g. the shared
+    /// client of the homeserver when requested by the user.
+    pub async fn republish_user(
+        &self,
+        client: &pkarr::Client,
+        user: &PublicKey,
+    ) -> Result<KeyRepublishResult, sqlx::Error> {
+        let summary = self
+            .batch_republisher()
+            .run_with_client(client.clone(), vec![user.clone().into()])
+            .await;
+        self.record(&summary).await?;
+        Ok(summary
+            .keys()
+            .first()
+            .cloned()
+            .expect("a single key yields a single result"))
+    }
+
+    async fn republish_keys(
+        &self,
+        keys: Vec<PublicKey>,
+    ) -> Result<RepublishSummary, UserKeysRepublisherError> {
+        let republisher = self.batch_republisher();
+        let pkarr_keys = keys.into_iter().map(Into::into).collect();
+        let summary = republisher.run(pkarr_keys).await?;
+        self.record(&summary).await?;
+        Ok(summary)
+    }
+
+    /// Republishes the records that list one of the accepted homeserver keys.
+    fn batch_republisher(&self) -> BatchRepublisher {
+        let accepted_keys = self.homeserver_keys.accepted();
         let settings =
             BatchRepublisherSettings::default().with_republish_condition(move |packet| {
-                packet_points_to_homeserver(packet, &homeserver_public_key)
+                accepted_keys
+                    .iter()
+                    .any(|homeserver| packet_points_to_homeserver(packet, homeserver))
             });
-        let republisher = BatchRepublisher::new(settings, self.pkarr_builder.clone());
-        let pkarr_keys = keys.into_iter().map(Into::into).collect();
-        Ok(republisher.run(pkarr_keys).await?)
+        BatchRepublisher::new(settings, self.pkarr_builder.clone())
     }
 
-    async fn get_all_user_keys(&self) -> Result<Vec<PublicKey>, sqlx::Error> {
-        let users = self.user_service.get_all().await?;
-        Ok(users.into_iter().map(|user| user.public_key).collect())
+    /// Persist the outcome of every key of `summary` and count it in the metrics.
+    async fn record(&self, summary: &RepublishSummary) -> Result<(), sqlx::Error> {
+        for key in summary.keys() {
+            self.metrics.record_user_republish(key.outcome.as_str());
+            RepublishStatusRepository::record(
+                &key.public_key.clone().into(),
+                key.outcome,
+                key.failure_reason.as_deref(),
+                key.packet_timestamp.and_then(|timestamp| {
+                    DateTime::from_timestamp_micros(timestamp.as_u64() as i64)
+                        .map(|time| time.naive_utc())
+                }),
+                &mut self.sql_db.pool().into(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Keys due for republishing at `now`, most urgent first, limited to the run budget.
+    async fn get_due_user_keys(&self, now: DateTime<Utc>) -> Result<Vec<PublicKey>, sqlx::Error> {
+        let schedule = &self.schedule;
+        let limit = match schedule.budget_per_hour {
+            Some(_) => {
+                let attempts_last_hour = RepublishStatusRepository::count_attempts_since(
+                    (now - HOUR).naive_utc(),
+                    &mut self.sql_db.pool().into(),
+                )
+                .await?;
+                let limit = schedule.run_budget(attempts_last_hour);
+                if limit == Some(0) {
+                    tracing::info!(
+                        attempts_last_hour,
+                        "User keys republish budget is spent, waiting for the next run"
+                    );
+                    return Ok(Vec::new());
+                }
+                limit
+            }
+            None => None,
+        };
+        RepublishStatusRepository::due(
+            RepublishDueQuery {
+                refreshed_before: (now - schedule.min_age).naive_utc(),
+                active_since: schedule
+                    .active_window
+                    .map(|window| (now - window).naive_utc()),
+                limit,
+            },
+            &mut self.sql_db.pool().into(),
+        )
+        .await
     }
 }
 
+/// Whether the user's `_pubky` record lists this homeserver, as primary or as a mirror.
 fn packet_points_to_homeserver(packet: &SignedPacket, homeserver_public_key: &PublicKey) -> bool {
-    packet
-        .resource_records("_pubky")
-        .find_map(|record| match &record.rdata {
-            RData::SVCB(svcb) => Some(svcb.target.to_string()),
-            RData::HTTPS(https) => Some(https.0.target.to_string()),
-            _ => None,
-        })
-        .and_then(|target| PublicKey::try_from_z32(&target).ok())
-        .is_some_and(|target| target == *homeserver_public_key)
+    listed_homeservers(packet).contains(homeserver_public_key)
+}
+
+/// The homeservers listed in the `_pubky` record of `packet`, primary first.
+///
+/// Records are ordered by priority. Targets that are not public keys are ignored.
+pub(crate) fn listed_homeservers(packet: &SignedPacket) -> Vec<PublicKey> {
+    extract_hosts_from_packet(packet)
+        .iter()
+        .filter_map(|host| PublicKey::try_from_z32(host).ok())
+        .collect()
 }
 
 #[cfg(test)]
@@ -150,6 +342,7 @@ mod tests {
     use crate::services::user_service::UserService;
     use pkarr::dns::rdata::SVCB;
     use pubky_common::crypto::Keypair;
+    use pubky_common::republish_status::RepublishOutcome;
 
     fn packet_with_https_homeserver(user: &Keypair, homeserver: &str) -> SignedPacket {
         SignedPacket::builder()
@@ -173,33 +366,108 @@ mod tests {
             .unwrap()
     }
 
-    async fn init_db_with_users(count: usize) -> (SqlDb, UserService) {
+    async fn init_db_with_users(count: usize) -> SqlDb {
         let db = SqlDb::test().await;
         let user_service = UserService::new(db.clone());
         for _ in 0..count {
             let public_key = Keypair::random().public_key();
             user_service.create(&public_key).await.unwrap();
         }
-        (db, user_service)
+        db
+    }
+
+    fn schedule(budget_per_hour: u64, interval: u64) -> RepublishSchedule {
+        let mut config = ConfigToml::default();
+        config.pkdns.user_keys_republisher_budget_per_hour = budget_per_hour;
+        config.pkdns.user_keys_republisher_interval = interval;
+        RepublishSchedule::from_config(&config)
     }
 
     /// Test that the republisher tries to republish all keys passed.
     #[tokio::test]
     #[pubky_test_utils::test]
     async fn test_republish_keys_once() {
-        let (_db, user_service) = init_db_with_users(10).await;
+        let db = init_db_with_users(10).await;
         let dht = mainline::Testnet::builder(1).build().unwrap();
         let pkarr_builder = test_client_builder(&dht);
         let worker = UserKeysRepublisher {
-            user_service,
+            sql_db: db.clone(),
             pkarr_builder,
-            homeserver_public_key: Keypair::random().public_key(),
+            homeserver_keys: HomeserverKeys::new(Keypair::random().public_key()),
+            metrics: Metrics::default(),
+            schedule: RepublishSchedule::from_config(&ConfigToml::default()),
         };
         let summary = worker.republish_impl().await.unwrap();
         assert_eq!(summary.len(), 10);
         assert_eq!(summary.success_count(), 0);
         assert_eq!(summary.missing_count(), 10);
         assert_eq!(summary.failed_count(), 0);
+
+        // The outcome is persisted per user.
+        let counts = RepublishStatusRepository::count_by_outcome(&mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(counts, vec![(RepublishOutcome::Missing, 10)]);
+
+        // Missing records were just attempted, so they are not due yet.
+        let summary = worker.republish_impl().await.unwrap();
+        assert!(summary.is_empty());
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn republishing_stops_when_the_hourly_budget_is_spent() {
+        let db = init_db_with_users(10).await;
+        let dht = mainline::Testnet::builder(1).build().unwrap();
+        let worker = UserKeysRepublisher {
+            sql_db: db.clone(),
+            pkarr_builder: test_client_builder(&dht),
+            homeserver_keys: HomeserverKeys::new(Keypair::random().public_key()),
+            metrics: Metrics::default(),
+            schedule: RepublishSchedule {
+                min_age: Duration::ZERO,
+                ..schedule(4, 14400)
+            },
+        };
+
+        let summary = worker.republish_impl().await.unwrap();
+        assert_eq!(summary.len(), 4);
+        // Every key is due again, but the budget of the hour is spent.
+        let summary = worker.republish_impl().await.unwrap();
+        assert!(summary.is_empty());
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn only_one_instance_republishes_at_a_time() {
+        let db = init_db_with_users(1).await;
+        let dht = mainline::Testnet::builder(1).build().unwrap();
+        let worker = UserKeysRepublisher {
+            sql_db: db.clone(),
+            pkarr_builder: test_client_builder(&dht),
+            homeserver_keys: HomeserverKeys::new(Keypair::random().public_key()),
+            metrics: Metrics::default(),
+            schedule: RepublishSchedule::from_config(&ConfigToml::default()),
+        };
+        let mut other_instance = db.pool().acquire().await.unwrap();
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(UserKeysRepublisher::REPUBLISH_LOCK_ID)
+            .execute(&mut *other_instance)
+            .await
+            .unwrap();
+
+        let summary = worker.republish_impl().await.unwrap();
+        assert!(summary.is_empty());
+    }
+
+    #[test]
+    fn hourly_budget_is_spread_over_the_runs_of_an_hour() {
+        assert_eq!(schedule(0, 1800).run_budget(0), None);
+        assert_eq!(schedule(100, 1800).run_budget(0), Some(50));
+        assert_eq!(schedule(100, 1800).run_budget(70), Some(30));
+        assert_eq!(schedule(100, 1800).run_budget(120), Some(0));
+        // Runs further apart than an hour get the whole budget.
+        assert_eq!(schedule(100, 14400).run_budget(0), Some(100));
     }
 
     #[tokio::test]
@@ -219,9 +487,11 @@ mod tests {
         pkarr_client.publish(&packet).await.unwrap();
 
         let worker = UserKeysRepublisher {
-            user_service,
+            sql_db: db.clone(),
             pkarr_builder,
-            homeserver_public_key: current_homeserver,
+            homeserver_keys: HomeserverKeys::new(current_homeserver),
+            metrics: Metrics::default(),
+            schedule: RepublishSchedule::from_config(&ConfigToml::default()),
         };
         let summary = worker.republish_impl().await.unwrap();
 
@@ -229,6 +499,45 @@ mod tests {
         assert_eq!(summary.skipped_count(), 1);
         assert_eq!(summary.success_count(), 0);
         assert_eq!(summary.failed_count(), 0);
+
+        // The record was just signed, so it is not due yet.
+        let summary = worker.republish_impl().await.unwrap();
+        assert!(summary.is_empty());
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn user_pointing_to_previous_key_is_republished_during_transition() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let dht = mainline::Testnet::builder(1).build().unwrap();
+        let pkarr_builder = test_client_builder(&dht);
+        let pkarr_client = pkarr_builder.clone().build().unwrap();
+        let user = Keypair::random();
+        user_service.create(&user.public_key()).await.unwrap();
+
+        let previous_homeserver = Keypair::random();
+        let packet = packet_with_https_homeserver(&user, &previous_homeserver.public_key().z32());
+        pkarr_client.publish(&packet).await.unwrap();
+
+        let transition = crate::shared::KeyTransition::from_rotation(
+            crate::KeyRotation::now(previous_homeserver),
+            30,
+        );
+        let worker = UserKeysRepublisher {
+            sql_db: db.clone(),
+            pkarr_builder,
+            homeserver_keys: HomeserverKeys::new(Keypair::random().public_key())
+                .with_transition(transition),
+            metrics: Metrics::default(),
+            schedule: RepublishSchedule::from_config(&ConfigToml::default()),
+        };
+        let summary = worker.republish_impl().await.unwrap();
+
+        // Not skipped: publishing was attempted. Whether it reached enough
+        // nodes depends on the single-node test DHT.
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary.skipped_count(), 0);
     }
 
     #[test]
@@ -249,6 +558,29 @@ mod tests {
         assert!(packet_points_to_homeserver(&packet, &homeserver));
     }
 
+    #[test]
+    fn packet_listing_current_homeserver_as_mirror_is_accepted() {
+        let user = Keypair::random();
+        let primary = Keypair::random().public_key();
+        let mirror = Keypair::random().public_key();
+        let packet = SignedPacket::builder()
+            .https(
+                "_pubky".try_into().unwrap(),
+                SVCB::new(1, mirror.z32().as_str().try_into().unwrap()),
+                3600,
+            )
+            .https(
+                "_pubky".try_into().unwrap(),
+                SVCB::new(0, primary.z32().as_str().try_into().unwrap()),
+                3600,
+            )
+            .sign(&user)
+            .unwrap();
+
+        assert_eq!(listed_homeservers(&packet), vec![primary, mirror.clone()]);
+        assert!(packet_points_to_homeserver(&packet, &mirror));
+    }
+
     #[test]
     fn packet_pointing_to_another_homeserver_is_rejected() {
         let user = Keypair::random();
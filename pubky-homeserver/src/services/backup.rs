@@ -0,0 +1,618 @@
+//! Backup and restore of a whole homeserver.
+//!
+//! A backup is a directory with:
+//!
+//! - `manifest.json`: the [`BackupManifest`]. Written last, so a directory
+//!   without one holds an incomplete backup.
+//! - `db/<table>.jsonl`: the rows of every table but `migrations`, one JSON
+//!   object per line, all dumped from a single `REPEATABLE READ` snapshot.
+//! - `files/<pubkey>/<path>`: the file of every entry in that snapshot.
+//!
+//! Files are copied after the snapshot was taken, so users can overwrite or
+//! delete them in the meantime. Every copied file is hashed and compared to its
+//! entry in the snapshot. If one doesn't match, the backup is retried from a new
+//! snapshot, keeping the files that were already copied and are still current.
+//!
+//! A restore loads the rows into an empty, migrated database and writes the files
+//! straight to the storage backend, checking their hashes. The rows are only
+//! committed once every file is restored.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use futures_util::{StreamExt, TryStreamExt};
+use pubky_common::crypto::Hash;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio_util::io::ReaderStream;
+
+use crate::persistence::files::{FileIoError, FileMetadataBuilder, FileService, WriteStreamError};
+use crate::persistence::sql::{
+    entry::{EntryEntity, EntryRepository},
+    uexecutor, SqlDb,
+};
+
+/// Version of the backup layout. Bumped on incompatible changes.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+/// Number of backup attempts before giving up on files that keep changing.
+const BACKUP_ATTEMPTS: usize = 3;
+/// Number of entries per database page, and rows per insert when restoring.
+const BACKUP_BATCH_SIZE: u16 = 500;
+/// Table of the applied migrations. Compared on restore instead of copied.
+const MIGRATION_TABLE: &str = "migrations";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Description of a backup, stored as `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// [`BACKUP_FORMAT_VERSION`] of the backup.
+    pub version: u32,
+    /// When the database snapshot was taken.
+    pub created_at: DateTime<Utc>,
+    /// Cursor of the last event in the snapshot, `0` without events.
+    /// Clients that synced past it have changes the backup doesn't.
+    pub event_cursor: u64,
+    /// Migrations applied to the database, in order.
+    pub migrations: Vec<String>,
+    /// Backed up tables, in an order that satisfies their foreign keys.
+    pub tables: Vec<BackupTable>,
+    /// Number of files.
+    pub files: u64,
+    /// Total size of the files in bytes.
+    pub file_bytes: u64,
+}
+
+/// A table in a backup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupTable {
+    /// Name of the table.
+    pub name: String,
+    /// Number of rows in `db/<name>.jsonl`.
+    pub rows: u64,
+}
+
+/// Error of a backup or restore.
+#[derive(Debug, thiserror::Error)]
+pub enum BackupError {
+    /// Backups are only written into new directories.
+    #[error("Backup directory {0} is not empty")]
+    DirectoryNotEmpty(PathBuf),
+    /// A file didn't match its entry in any attempt.
+    #[error(
+        "The file of {0} kept changing while it was backed up. \
+         If nothing writes to it, run `storage verify` to check the storage"
+    )]
+    FilesChanged(String),
+    /// The backup is incomplete, corrupted or from another homeserver version.
+    #[error("Invalid backup: {0}")]
+    Invalid(String),
+    /// Restoring would mix the backup with existing data.
+    #[error("Can only restore into an empty database, but table {0} has rows")]
+    DatabaseNotEmpty(String),
+    /// Reading or writing the backup directory failed.
+    #[error("Backup file error: {0}")]
+    Io(#[from] std::io::Error),
+    /// The manifest could not be read or written.
+    #[error("Backup JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The storage backend failed.
+    #[error(transparent)]
+    Storage(#[from] FileIoError),
+    /// The database failed.
+    #[error("DB error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Creates and restores consistent backups of the database and the file storage.
+#[derive(Debug, Clone)]
+pub struct BackupService {
+    sql_db: SqlDb,
+    file_service: FileService,
+}
+
+impl BackupService {
+    pub fn new(sql_db: SqlDb, file_service: FileService) -> Self {
+        Self {
+            sql_db,
+            file_service,
+        }
+    }
+
+    /// Back up the database and the files into `dir`, which must be empty or not exist.
+    pub async fn create(&self, dir: &Path) -> Result<BackupManifest, BackupError> {
+        if dir.exists() && std::fs::read_dir(dir)?.next().is_some() {
+            return Err(BackupError::DirectoryNotEmpty(dir.to_path_buf()));
+        }
+        tokio::fs::create_dir_all(dir.join("db")).await?;
+
+        let mut copied = HashMap::new();
+        let mut changed = String::new();
+        for attempt in 1..=BACKUP_ATTEMPTS {
+            match self.try_create(dir, &mut copied).await? {
+                Ok(manifest) => {
+                    let json = serde_json::to_vec_pretty(&manifest)?;
+                    tokio::fs::write(dir.join(MANIFEST_FILE), json).await?;
+                    return Ok(manifest);
+                }
+                Err(path) => {
+                    tracing::warn!("{path} changed during backup attempt {attempt}");
+                    changed = path;
+                }
+            }
+        }
+        Err(BackupError::FilesChanged(changed))
+    }
+
+    /// One backup attempt. Fails with the path of a file that doesn't match its entry.
+    ///
+    /// `copied` holds the hashes of the files already in `dir`.
+    async fn try_create(
+        &self,
+        dir: &Path,
+        copied: &mut HashMap<String, Hash>,
+    ) -> Result<Result<BackupManifest, String>, BackupError> {
+        let mut tx = self.sql_db.pool().begin().await?;
+        sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+            .execute(&mut *tx)
+            .await?;
+        let created_at = Utc::now();
+        let migrations = applied_migrations(&mut tx).await?;
+        let mut tables = Vec::new();
+        for name in table_order(&mut tx).await? {
+            let path = dir.join("db").join(format!("{name}.jsonl"));
+            let rows = dump_table(&mut tx, &name, &path).await?;
+            tables.push(BackupTable { name, rows });
+        }
+        let event_cursor: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM events")
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let mut in_snapshot = HashSet::new();
+        let mut file_bytes = 0;
+        let mut cursor = None;
+        loop {
+            let entries =
+                EntryRepository::list_page(cursor, BACKUP_BATCH_SIZE, uexecutor!(tx)).await?;
+            let Some(last) = entries.last() else {
+                break;
+            };
+            cursor = Some(last.id);
+            for entry in entries {
+                if !self.copy_file(dir, &entry, copied).await? {
+                    return Ok(Err(entry.path.to_string()));
+                }
+                in_snapshot.insert(entry.path.as_str().to_string());
+                file_bytes += entry.content_length;
+            }
+        }
+        tx.commit().await?;
+
+        // Files of entries deleted since an earlier attempt.
+        let stale: Vec<String> = copied
+            .keys()
+            .filter(|path| !in_snapshot.contains(*path))
+            .cloned()
+            .collect();
+        for path in stale {
+            tokio::fs::remove_file(backup_file_path(dir, &path)).await?;
+            copied.remove(&path);
+        }
+
+        Ok(Ok(BackupManifest {
+            version: BACKUP_FORMAT_VERSION,
+            created_at,
+            event_cursor: event_cursor as u64,
+            migrations,
+            tables,
+            files: in_snapshot.len() as u64,
+            file_bytes,
+        }))
+    }
+
+    /// Copy the file of `entry` into the backup.
+    /// Returns `false` if the file doesn't match the entry, and leaves nothing behind.
+    async fn copy_file(
+        &self,
+        dir: &Path,
+        entry: &EntryEntity,
+        copied: &mut HashMap<String, Hash>,
+    ) -> Result<bool, BackupError> {
+        let key = entry.path.as_str();
+        if copied.get(key) == Some(&entry.content_hash) {
+            return Ok(true);
+        }
+        let mut stream = match self.file_service.opendal.get_stream(&entry.path).await {
+            Ok(stream) => stream,
+            Err(FileIoError::NotFound) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let target = backup_file_path(dir, key);
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut partial = target.clone().into_os_string();
+        partial.push(".partial");
+        let mut file = BufWriter::new(tokio::fs::File::create(&partial).await?);
+        let mut metadata_builder = FileMetadataBuilder::default();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            metadata_builder.update(&chunk);
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        let metadata = metadata_builder.finalize();
+
+        if metadata.hash != entry.content_hash || metadata.length as u64 != entry.content_length {
+            tokio::fs::remove_file(&partial).await?;
+            return Ok(false);
+        }
+        tokio::fs::rename(&partial, &target).await?;
+        copied.insert(key.to_string(), entry.content_hash);
+        Ok(true)
+    }
+
+    /// Restore the backup in `dir`.
+    ///
+    /// The database must be empty and migrated to the same migrations as the backup.
+    /// Files already in the storage at restored paths are overwritten.
+    pub async fn restore(&self, dir: &Path) -> Result<BackupManifest, BackupError> {
+        let manifest: BackupManifest = match tokio::fs::read(dir.join(MANIFEST_FILE)).await {
+            Ok(json) => serde_json::from_slice(&json)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(BackupError::Invalid(format!(
+                    "no {MANIFEST_FILE} in {}, the backup is incomplete",
+                    dir.display()
+                )))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if manifest.version != BACKUP_FORMAT_VERSION {
+            return Err(BackupError::Invalid(format!(
+                "unsupported version {}",
+                manifest.version
+            )));
+        }
+
+        let mut tx = self.sql_db.pool().begin().await?;
+        if applied_migrations(&mut tx).await? != manifest.migrations {
+            return Err(BackupError::Invalid(
+                "the database migrations differ from the backup's. \
+                 Restore with the homeserver version that made the backup"
+                    .to_string(),
+            ));
+        }
+        for table in table_order(&mut tx).await? {
+            let has_rows: bool = sqlx::query_scalar(&format!(
+                "SELECT EXISTS (SELECT 1 FROM {})",
+                quote_ident(&table)
+            ))
+            .fetch_one(&mut *tx)
+            .await?;
+            if has_rows {
+                return Err(BackupError::DatabaseNotEmpty(table));
+            }
+        }
+
+        for table in &manifest.tables {
+            restore_table(&mut tx, dir, table).await?;
+        }
+        reset_sequences(&mut tx).await?;
+
+        let mut cursor = None;
+        loop {
+            let entries =
+                EntryRepository::list_page(cursor, BACKUP_BATCH_SIZE, uexecutor!(tx)).await?;
+            let Some(last) = entries.last() else {
+                break;
+            };
+            cursor = Some(last.id);
+            for entry in entries {
+                self.restore_file(dir, &entry).await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(manifest)
+    }
+
+    async fn restore_file(&self, dir: &Path, entry: &EntryEntity) -> Result<(), BackupError> {
+        let file = match tokio::fs::File::open(backup_file_path(dir, entry.path.as_str())).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(BackupError::Invalid(format!("missing file {}", entry.path)))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let stream = ReaderStream::new(file).map_err(|e| WriteStreamError::Other(e.into()));
+        let metadata = self
+            .file_service
+            .opendal
+            .write_stream_untracked(&entry.path, stream)
+            .await?;
+        if metadata.hash != entry.content_hash || metadata.length as u64 != entry.content_length {
+            return Err(BackupError::Invalid(format!(
+                "file {} does not match its entry",
+                entry.path
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn backup_file_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join("files").join(key)
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+async fn applied_migrations(
+    tx: &mut Transaction<'static, Postgres>,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(&format!(
+        "SELECT name FROM {} ORDER BY id",
+        quote_ident(MIGRATION_TABLE)
+    ))
+    .fetch_all(&mut **tx)
+    .await
+}
+
+/// All tables but `migrations`, each after the tables its foreign keys reference.
+async fn table_order(tx: &mut Transaction<'static, Postgres>) -> Result<Vec<String>, sqlx::Error> {
+    let mut remaining: Vec<String> = sqlx::query_scalar(
+        "SELECT table_name::text FROM information_schema.tables \
+         WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' AND table_name <> $1 \
+         ORDER BY table_name",
+    )
+    .bind(MIGRATION_TABLE)
+    .fetch_all(&mut **tx)
+    .await?;
+    let references: Vec<(String, String)> = sqlx::query_as(
+        "SELECT src.relname::text, dst.relname::text FROM pg_constraint c \
+         JOIN pg_class src ON src.oid = c.conrelid \
+         JOIN pg_class dst ON dst.oid = c.confrelid \
+         WHERE c.contype = 'f' AND src.relnamespace = current_schema()::regnamespace",
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    let mut ordered: Vec<String> = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|table| {
+            references
+                .iter()
+                .all(|(from, to)| from != table || to == table || ordered.contains(to))
+        });
+        if ready.is_empty() {
+            // Cyclic foreign keys, which the schema doesn't have. Inserting will tell.
+            ordered.extend(blocked);
+            break;
+        }
+        ordered.extend(ready);
+        remaining = blocked;
+    }
+    Ok(ordered)
+}
+
+/// Write the rows of `table` to `path` as JSON lines. Returns the number of rows.
+async fn dump_table(
+    tx: &mut Transaction<'static, Postgres>,
+    table: &str,
+    path: &Path,
+) -> Result<u64, BackupError> {
+    let mut file = BufWriter::new(tokio::fs::File::create(path).await?);
+    let query = format!("SELECT row_to_json(t)::text FROM {} t", quote_ident(table));
+    let mut rows = sqlx::query_scalar::<_, String>(&query).fetch(&mut **tx);
+    let mut count = 0;
+    while let Some(row) = rows.try_next().await? {
+        file.write_all(row.as_bytes()).await?;
+        file.write_all(b"\n").await?;
+        count += 1;
+    }
+    file.flush().await?;
+    Ok(count)
+}
+
+async fn restore_table(
+    tx: &mut Transaction<'static, Postgres>,
+    dir: &Path,
+    table: &BackupTable,
+) -> Result<(), BackupError> {
+    let path = dir.join("db").join(format!("{}.jsonl", table.name));
+    let mut lines = BufReader::new(tokio::fs::File::open(&path).await?).lines();
+    let name = quote_ident(&table.name);
+    let query =
+        format!("INSERT INTO {name} SELECT * FROM json_populate_recordset(NULL::{name}, $1::json)");
+    let mut batch = Vec::with_capacity(BACKUP_BATCH_SIZE.into());
+    let mut rows = 0;
+    loop {
+        let line = lines.next_line().await?;
+        let done = line.is_none();
+        batch.extend(line);
+        if batch.len() == usize::from(BACKUP_BATCH_SIZE) || (done && !batch.is_empty()) {
+            sqlx::query(&query)
+                .bind(format!("[{}]", batch.join(",")))
+                .execute(&mut **tx)
+                .await?;
+            rows += batch.len() as u64;
+            batch.clear();
+        }
+        if done {
+            break;
+        }
+    }
+    if rows != table.rows {
+        return Err(BackupError::Invalid(format!(
+            "{} has {rows} rows instead of {}",
+            path.display(),
+            table.rows
+        )));
+    }
+    Ok(())
+}
+
+/// Continue every serial column after its restored values.
+async fn reset_sequences(tx: &mut Transaction<'static, Postgres>) -> Result<(), sqlx::Error> {
+    let columns: Vec<(String, String)> = sqlx::query_as(
+        "SELECT table_name::text, column_name::text FROM information_schema.columns \
+         WHERE table_schema = current_schema() AND column_default LIKE 'nextval(%'",
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    for (table, column) in columns {
+        let table = quote_ident(&table);
+        sqlx::query(&format!(
+            "SELECT setval(pg_get_serial_sequence($1, $2), COALESCE(MAX({}), 0) + 1, false) FROM {table}",
+            quote_ident(&column)
+        ))
+        .bind(&table)
+        .bind(&column)
+        .execute(&mut **tx)
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use opendal::Buffer;
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::shared::webdav::{EntryPath, StoragePath};
+    use crate::storage_config::StorageConfigToml;
+    use crate::AppContext;
+
+    fn test_path(pubkey: &pubky_common::crypto::PublicKey, name: &str) -> EntryPath {
+        EntryPath::new(
+            pubkey.clone(),
+            StoragePath::new(&format!("/pub/test.app/{name}")).unwrap(),
+        )
+    }
+
+    /// Every row of `table` as JSON, sorted.
+    async fn table_rows(context: &AppContext, table: &str) -> Vec<String> {
+        let mut rows: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT row_to_json(t)::text FROM {} t",
+            quote_ident(table)
+        ))
+        .fetch_all(context.sql_db.pool())
+        .await
+        .unwrap();
+        rows.sort();
+        rows
+    }
+
+    async fn assert_round_trip(backend: StorageConfigToml) {
+        let source = AppContext::test_with_config(|c| c.storage.backend = backend.clone()).await;
+        let pubkey = Keypair::random().public_key();
+        source.user_service.create(&pubkey).await.unwrap();
+        let files = [("a.txt", "hello"), ("dir/b.json", "{}")];
+        for (name, content) in files {
+            source
+                .file_service
+                .write(&test_path(&pubkey, name), Buffer::from(content.as_bytes()))
+                .await
+                .unwrap();
+        }
+        let deleted = test_path(&pubkey, "deleted.txt");
+        source
+            .file_service
+            .write(&deleted, Buffer::from(b"gone".to_vec()))
+            .await
+            .unwrap();
+        source.file_service.delete(&deleted).await.unwrap();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path().join("backup");
+        let service = BackupService::new(source.sql_db.clone(), source.file_service.clone());
+        let manifest = service.create(&dir).await.unwrap();
+        assert_eq!((manifest.files, manifest.file_bytes), (2, 7));
+        assert_eq!(manifest.event_cursor, 4);
+        let users = manifest.tables.iter().position(|t| t.name == "users");
+        let entries = manifest.tables.iter().position(|t| t.name == "entries");
+        assert!(users < entries);
+        assert!(matches!(
+            service.create(&dir).await,
+            Err(BackupError::DirectoryNotEmpty(_))
+        ));
+
+        let target = AppContext::test_with_config(|c| c.storage.backend = backend).await;
+        let service = BackupService::new(target.sql_db.clone(), target.file_service.clone());
+        assert_eq!(service.restore(&dir).await.unwrap(), manifest);
+        for table in &manifest.tables {
+            assert_eq!(
+                table_rows(&target, &table.name).await,
+                table_rows(&source, &table.name).await,
+                "{}",
+                table.name
+            );
+        }
+        for (name, content) in files {
+            let restored = target.file_service.get(&test_path(&pubkey, name)).await;
+            assert_eq!(restored.unwrap(), content.as_bytes());
+        }
+        assert!(target.file_service.get(&deleted).await.is_err());
+
+        // Sequences continue after the restored rows.
+        target
+            .file_service
+            .write(
+                &test_path(&pubkey, "new.txt"),
+                Buffer::from(b"new".to_vec()),
+            )
+            .await
+            .unwrap();
+        let last_event: i64 = sqlx::query_scalar("SELECT MAX(id) FROM events")
+            .fetch_one(target.sql_db.pool())
+            .await
+            .unwrap();
+        assert_eq!(last_event as u64, manifest.event_cursor + 1);
+
+        assert!(matches!(
+            service.restore(&dir).await,
+            Err(BackupError::DatabaseNotEmpty(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_backup_round_trip_in_memory() {
+        assert_round_trip(StorageConfigToml::InMemory).await;
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_backup_round_trip_file_system() {
+        assert_round_trip(StorageConfigToml::FileSystem).await;
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_backup_gives_up_on_files_not_matching_their_entry() {
+        let context =
+            AppContext::test_with_config(|c| c.storage.backend = StorageConfigToml::FileSystem)
+                .await;
+        let pubkey = Keypair::random().public_key();
+        context.user_service.create(&pubkey).await.unwrap();
+        let path = test_path(&pubkey, "a.txt");
+        context
+            .file_service
+            .write(&path, Buffer::from(b"hello".to_vec()))
+            .await
+            .unwrap();
+        let files_dir = context.data_dir.path().join("data/files");
+        std::fs::write(files_dir.join(path.as_str()), b"HELLO").unwrap();
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let service = BackupService::new(context.sql_db.clone(), context.file_service.clone());
+        match service.create(temp_dir.path()).await {
+            Err(BackupError::FilesChanged(changed)) => assert_eq!(changed, path.to_string()),
+            other => panic!("unexpected result {other:?}"),
+        }
+        assert!(!temp_dir.path().join(MANIFEST_FILE).exists());
+        assert!(!backup_file_path(temp_dir.path(), path.as_str()).exists());
+    }
+}
@@ -0,0 +1,275 @@
+//! Config service — the subset of the config that can change without a restart.
+//!
+//! `[general] signup_mode`, `[drive] rate_limits`, `[default_quotas]` and
+//! `[logging]` are reloaded from the config file on `SIGHUP` or through the
+//! admin API. Consumers read one [`ReloadableConfig`] snapshot per request from
+//! [`LiveConfig`], so a reload swaps all of them at once and a request never
+//! sees half of a reload.
+//!
+//! Every other setting is only read at startup. A reload reports changes to
+//! those sections as requiring a restart and leaves them alone.
+
+use std::sync::{Arc, RwLock};
+
+use serde::Serialize;
+
+use crate::quota_config::PathLimit;
+use crate::{ConfigToml, DataDir, DefaultQuotasToml, LoggingToml, SignupMode};
+
+/// The settings that can be reloaded without a restart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReloadableConfig {
+    pub signup_mode: SignupMode,
+    pub rate_limits: Vec<PathLimit>,
+    pub default_quotas: DefaultQuotasToml,
+    pub logging: Option<LoggingToml>,
+}
+
+impl From<&ConfigToml> for ReloadableConfig {
+    fn from(config: &ConfigToml) -> Self {
+        Self {
+            signup_mode: config.general.signup_mode.clone(),
+            rate_limits: config.drive.rate_limits.clone(),
+            default_quotas: config.default_quotas.clone(),
+            logging: config.logging.clone(),
+        }
+    }
+}
+
+impl Default for ReloadableConfig {
+    fn default() -> Self {
+        Self::from(&ConfigToml::default())
+    }
+}
+
+impl ReloadableConfig {
+    /// Reject settings the limiters could not be built from.
+    pub(crate) fn validate(&self) -> Result<(), ConfigReloadError> {
+        for limit in &self.rate_limits {
+            limit
+                .validate()
+                .map_err(|e| ConfigReloadError::Invalid(e.to_string()))?;
+            governor::Quota::try_from(limit.clone()).map_err(ConfigReloadError::Invalid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared handle to the current [`ReloadableConfig`].
+#[derive(Debug, Clone)]
+pub struct LiveConfig(Arc<RwLock<Arc<ReloadableConfig>>>);
+
+impl LiveConfig {
+    pub fn new(config: ReloadableConfig) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(config))))
+    }
+
+    /// The current settings. Hold on to the snapshot for the whole request.
+    pub fn get(&self) -> Arc<ReloadableConfig> {
+        Arc::clone(&self.0.read().expect("live config lock poisoned"))
+    }
+}
+
+/// What a reload changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConfigReloadReport {
+    /// Settings that changed and are now in effect, e.g. `general.signup_mode`.
+    pub applied: Vec<&'static str>,
+    /// Settings that differ from the running config but only take effect after a restart.
+    pub requires_restart: Vec<&'static str>,
+}
+
+/// Why a reload was rejected. The running config is left unchanged.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigReloadError {
+    /// The config file could not be read, parsed or merged with the defaults.
+    #[error("Failed to load config: {0}")]
+    Load(anyhow::Error),
+    /// The config parsed but contains settings that cannot be applied.
+    #[error("Invalid config: {0}")]
+    Invalid(String),
+}
+
+/// Reloads the config file and publishes the reloadable settings to [`LiveConfig`].
+#[derive(Debug, Clone)]
+pub struct ConfigService {
+    live: LiveConfig,
+    data_dir: Arc<dyn DataDir>,
+    /// The config the homeserver was started with.
+    startup: Arc<ConfigToml>,
+}
+
+impl ConfigService {
+    pub fn new(config: &ConfigToml, data_dir: Arc<dyn DataDir>) -> Self {
+        Self {
+            live: LiveConfig::new(ReloadableConfig::from(config)),
+            data_dir,
+            startup: Arc::new(config.clone()),
+        }
+    }
+
+    /// Handle to the current reloadable settings.
+    pub fn live(&self) -> &LiveConfig {
+        &self.live
+    }
+
+    /// Re-read the config file, merged with the defaults like at startup, and apply it.
+    pub fn reload(&self) -> Result<ConfigReloadReport, ConfigReloadError> {
+        let config = self
+            .data_dir
+            .read_or_create_config_file()
+            .map_err(ConfigReloadError::Load)?;
+        let report = self.apply(&config)?;
+        tracing::info!(
+            "Config reloaded. Applied: {:?}. Requires restart: {:?}",
+            report.applied,
+            report.requires_restart
+        );
+        Ok(report)
+    }
+
+    /// Swap in the reloadable settings of `config`.
+    pub(crate) fn apply(
+        &self,
+        config: &ConfigToml,
+    ) -> Result<ConfigReloadReport, ConfigReloadError> {
+        let mut next = ReloadableConfig::from(config);
+        next.validate()?;
+
+        // Held until the swap so concurrent reloads report against what they replace.
+        let mut current = self.live.0.write().expect("live config lock poisoned");
+        let mut report = ConfigReloadReport {
+            applied: Vec::new(),
+            requires_restart: restart_sections(&self.startup, config),
+        };
+        if next.signup_mode != current.signup_mode {
+            report.applied.push("general.signup_mode");
+        }
+        if next.rate_limits != current.rate_limits {
+            report.applied.push("drive.rate_limits");
+        }
+        if next.default_quotas != current.default_quotas {
+            report.applied.push("default_quotas");
+        }
+        if next.logging != current.logging {
+            let reloaded = next
+                .logging
+                .as_ref()
+                .is_some_and(crate::tracing::reload_log_filter);
+            if reloaded {
+                report.applied.push("logging");
+            } else {
+                next.logging = current.logging.clone();
+                report.requires_restart.push("logging");
+            }
+        }
+        *current = Arc::new(next);
+        Ok(report)
+    }
+}
+
+/// Sections of `next` that differ from `startup` outside the reloadable settings.
+fn restart_sections(startup: &ConfigToml, next: &ConfigToml) -> Vec<&'static str> {
+    let mut general = next.general.clone();
+    general.signup_mode = startup.general.signup_mode.clone();
+    let mut drive = next.drive.clone();
+    drive.rate_limits = startup.drive.rate_limits.clone();
+
+    [
+        ("general", general == startup.general),
+        ("drive", drive == startup.drive),
+        ("storage", next.storage == startup.storage),
+        ("events", next.events == startup.events),
+        ("admin", next.admin == startup.admin),
+        ("metrics", next.metrics == startup.metrics),
+        ("pkdns", next.pkdns == startup.pkdns),
+    ]
+    .into_iter()
+    .filter(|(_, unchanged)| !unchanged)
+    .map(|(section, _)| section)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::quota_config::BandwidthQuota;
+    use crate::MockDataDir;
+
+    fn service(config: &ConfigToml) -> ConfigService {
+        let data_dir = MockDataDir::new(config.clone(), None).unwrap();
+        ConfigService::new(config, Arc::new(data_dir))
+    }
+
+    #[test]
+    fn test_apply_swaps_reloadable_settings() {
+        let config = ConfigToml::default_test_config();
+        let service = service(&config);
+        let before = service.live().get();
+
+        let mut next = config.clone();
+        next.general.signup_mode = SignupMode::TokenRequired;
+        next.default_quotas.rate_read = Some(BandwidthQuota::from_str("1mb/s").unwrap());
+        next.drive.rate_limits.clear();
+        next.admin.listen_socket.set_port(7000);
+        let report = service.apply(&next).unwrap();
+
+        assert_eq!(
+            report.applied,
+            vec!["general.signup_mode", "drive.rate_limits", "default_quotas"]
+        );
+        assert_eq!(report.requires_restart, vec!["admin"]);
+        let after = service.live().get();
+        assert_eq!(after.signup_mode, SignupMode::TokenRequired);
+        assert!(after.rate_limits.is_empty());
+        // Snapshots taken before the reload are unaffected.
+        assert_eq!(before.signup_mode, config.general.signup_mode);
+
+        // Nothing left to apply, the restart is still pending.
+        let report = service.apply(&next).unwrap();
+        assert!(report.applied.is_empty());
+        assert_eq!(report.requires_restart, vec!["admin"]);
+    }
+
+    #[test]
+    fn test_reload_reads_config_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let data_dir = crate::PersistentDataDir::new(temp_dir.path().to_path_buf());
+        data_dir.ensure_data_dir_exists_and_is_writable().unwrap();
+        let config = data_dir.read_or_create_config_file().unwrap();
+        let config_file_path = data_dir.get_config_file_path();
+        let service = ConfigService::new(&config, Arc::new(data_dir));
+        assert_eq!(service.live().get().signup_mode, SignupMode::TokenRequired);
+
+        std::fs::write(&config_file_path, "[general]\nsignup_mode = \"open\"\n").unwrap();
+        let report = service.reload().unwrap();
+        assert_eq!(report.applied, vec!["general.signup_mode"]);
+        assert_eq!(service.live().get().signup_mode, SignupMode::Open);
+
+        // A broken file keeps the running config.
+        std::fs::write(&config_file_path, "[general\n").unwrap();
+        assert!(matches!(service.reload(), Err(ConfigReloadError::Load(_))));
+        assert_eq!(service.live().get().signup_mode, SignupMode::Open);
+    }
+
+    #[test]
+    fn test_apply_rejects_invalid_rate_limits() {
+        let config = ConfigToml::default_test_config();
+        let service = service(&config);
+
+        let mut next = config.clone();
+        next.general.signup_mode = SignupMode::TokenRequired;
+        let mut limit = config.drive.rate_limits[0].clone();
+        limit.whitelist = vec![crate::quota_config::LimitKey::from_str("127.0.0.1").unwrap()];
+        limit.key = crate::quota_config::LimitKeyType::User;
+        next.drive.rate_limits = vec![limit];
+
+        assert!(matches!(
+            service.apply(&next),
+            Err(ConfigReloadError::Invalid(_))
+        ));
+        assert_eq!(service.live().get().signup_mode, config.general.signup_mode);
+    }
+}
@@ -0,0 +1,213 @@
+//! Content block service — checks reads and writes against the admin blocklist.
+//!
+//! Blocked hashes are looked up with an indexed query. Path globs can only be
+//! matched in Rust, so the active globs are kept in memory instead of being read
+//! on every request. A reload goes through the caller's executor, so a write
+//! holding a transaction never waits for a second pool connection. The cache is
+//! reloaded when this instance adds or lifts a block, and otherwise after
+//! [`CACHE_TTL`], so glob changes made through another instance apply here within
+//! a few seconds.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use pubky_common::crypto::Hash;
+
+use crate::persistence::sql::content_block::{ContentBlockEntity, ContentBlockRepository};
+use crate::persistence::sql::UnifiedExecutor;
+use crate::shared::webdav::{EntryPath, StoragePath};
+
+/// How long the active path globs are reused before being read again.
+const CACHE_TTL: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct CachedGlobs {
+    blocks: Arc<Vec<ContentBlockEntity>>,
+    loaded_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    globs: RwLock<Option<CachedGlobs>>,
+    /// Bumped on every invalidation, so a load that raced with one is not cached.
+    generation: AtomicU64,
+}
+
+/// Matches files against the admin content blocklist.
+#[derive(Debug, Clone, Default)]
+pub struct ContentBlockService {
+    inner: Arc<Inner>,
+}
+
+impl ContentBlockService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The active entry blocking a file at `entry_path`, if any.
+    ///
+    /// Pass `None` as `content_hash` to only check path globs. Queries go through
+    /// `executor`, so writes see blocks added in their transaction.
+    pub async fn find_match(
+        &self,
+        entry_path: &EntryPath,
+        content_hash: Option<&Hash>,
+        executor: &mut UnifiedExecutor<'_>,
+    ) -> Result<Option<ContentBlockEntity>, sqlx::Error> {
+        if let Some(content_hash) = content_hash {
+            if let Some(block) =
+                ContentBlockRepository::find_by_hash(content_hash, executor).await?
+            {
+                return Ok(Some(block));
+            }
+        }
+        let globs = self.active_globs(executor).await?;
+        Ok(globs
+            .iter()
+            .find(|block| block.target.matches(entry_path, None))
+            .cloned())
+    }
+
+    /// Drop the paths of a directory listing that are blocked, so takedowns can't be
+    /// discovered by listing their directory. Directories are dropped when a glob
+    /// matches the directory path itself, e.g. `*/pub/spam.app/**`.
+    pub async fn retain_visible(
+        &self,
+        paths: Vec<EntryPath>,
+        executor: &mut UnifiedExecutor<'_>,
+    ) -> Result<Vec<EntryPath>, sqlx::Error> {
+        let globs = self.active_globs(executor).await?;
+        let mut paths: Vec<EntryPath> = paths
+            .into_iter()
+            .filter(|path| !globs.iter().any(|block| block.target.matches(path, None)))
+            .collect();
+        let Some(first) = paths.first() else {
+            return Ok(paths);
+        };
+        let pubkey = first.pubkey().clone();
+        let files: Vec<&StoragePath> = paths
+            .iter()
+            .map(|path| path.path())
+            .filter(|path| !path.is_directory())
+            .collect();
+        let blocked =
+            ContentBlockRepository::paths_with_blocked_hash(&pubkey, &files, executor).await?;
+        if !blocked.is_empty() {
+            paths.retain(|path| {
+                !blocked
+                    .iter()
+                    .any(|blocked| blocked == path.path().as_str())
+            });
+        }
+        Ok(paths)
+    }
+
+    /// Drop the cached globs. Call after adding or lifting a block.
+    pub fn invalidate(&self) {
+        self.inner.generation.fetch_add(1, Ordering::AcqRel);
+        *self.inner.globs.write().expect("lock poisoned") = None;
+    }
+
+    async fn active_globs(
+        &self,
+        executor: &mut UnifiedExecutor<'_>,
+    ) -> Result<Arc<Vec<ContentBlockEntity>>, sqlx::Error> {
+        if let Some(cached) = self.inner.globs.read().expect("lock poisoned").as_ref() {
+            if cached.loaded_at.elapsed() < CACHE_TTL {
+                return Ok(cached.blocks.clone());
+            }
+        }
+        let generation = self.inner.generation.load(Ordering::Acquire);
+        let blocks = Arc::new(ContentBlockRepository::list_active_globs(executor).await?);
+        let mut globs = self.inner.globs.write().expect("lock poisoned");
+        if self.inner.generation.load(Ordering::Acquire) == generation {
+            *globs = Some(CachedGlobs {
+                blocks: blocks.clone(),
+                loaded_at: Instant::now(),
+            });
+        }
+        Ok(blocks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::{hash, Keypair};
+
+    use super::*;
+    use crate::persistence::sql::content_block::BlockTarget;
+    use crate::persistence::sql::SqlDb;
+    use crate::quota_config::GlobPattern;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_find_match_uses_cached_globs_until_invalidated() {
+        let db = SqlDb::test().await;
+        let service = ContentBlockService::new();
+        let content = hash(b"spam");
+        let path = EntryPath::new(
+            Keypair::random().public_key(),
+            StoragePath::new("/pub/spam.app/post.json").unwrap(),
+        );
+        let other_path = EntryPath::new(
+            Keypair::random().public_key(),
+            StoragePath::new("/pub/ok.app/post.json").unwrap(),
+        );
+        assert!(service
+            .find_match(&path, None, &mut db.pool().into())
+            .await
+            .unwrap()
+            .is_none());
+
+        // Hash blocks apply at once, glob blocks once the cache is invalidated.
+        let by_hash = ContentBlockRepository::add(
+            &BlockTarget::Hash(content),
+            None,
+            "alice",
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let by_glob = ContentBlockRepository::add(
+            &BlockTarget::PathGlob(GlobPattern::new("*/pub/spam.app/**")),
+            None,
+            "bob",
+            &mut db.pool().into(),
+        )
+        .await
+        .unwrap();
+        let found = service
+            .find_match(&other_path, Some(&content), &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(found.unwrap().id, by_hash.id);
+        assert!(service
+            .find_match(&path, None, &mut db.pool().into())
+            .await
+            .unwrap()
+            .is_none());
+
+        service.invalidate();
+        let found = service
+            .find_match(&path, Some(&hash(b"fine")), &mut db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(found.unwrap().id, by_glob.id);
+        assert!(service
+            .find_match(&other_path, Some(&hash(b"fine")), &mut db.pool().into())
+            .await
+            .unwrap()
+            .is_none());
+
+        ContentBlockRepository::remove(by_glob.id, "carol", &mut db.pool().into())
+            .await
+            .unwrap();
+        service.invalidate();
+        assert!(service
+            .find_match(&path, None, &mut db.pool().into())
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,646 @@
+//! Mirroring of users whose primary homeserver is another homeserver.
+//!
+//! A user opts in by having an account on this homeserver and listing it as a
+//! mirror, after their primary homeserver, in their `_pubky` record. Every
+//! `[mirror].interval` seconds, and whenever triggered through the admin API,
+//! [`MirrorJob`] follows the `/events-stream` of each such user on their primary
+//! homeserver from the last stored cursor and replicates their `/pub/` data:
+//!
+//! - `PUT` events copy the file from the primary, unless the local copy already
+//!   has the announced content hash. A copy that matches neither the announced hash
+//!   nor the `ETag` of the primary's response is deleted again and the user is
+//!   retried on the next run.
+//! - `DEL` events delete the local copy.
+//!
+//! Scheduled runs only resolve the `_pubky` records of users with a cursor.
+//! Every `[mirror].discovery_interval` seconds, and on every triggered run, the
+//! records of all local users are resolved to find users who newly opted in.
+//! Records are resolved through the pkarr cache, so a changed list of
+//! homeservers is picked up once the cached record expires.
+//!
+//! The cursor is stored per user in `mirror_cursors` after every event. A user who
+//! moves to another primary is mirrored from the start of its event stream. When the
+//! primary pruned the history behind the cursor (`410 Gone`), the user is mirrored
+//! from the start of what is left.
+//!
+//! A session-level advisory lock makes sure only one homeserver instance mirrors
+//! at a time.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use pkarr::errors::ResolveError;
+use pkarr::ResolvePolicy;
+use pubky_common::crypto::{Hash, PublicKey};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::data_directory::MirrorToml;
+use crate::persistence::files::{FileIoError, FileService, WriteStreamError};
+use crate::persistence::sql::entry::EntryRepository;
+use crate::persistence::sql::mirror_cursor::MirrorCursorRepository;
+use crate::persistence::sql::user::UserListQuery;
+use crate::persistence::sql::SqlDb;
+use crate::republishers::listed_homeservers;
+use crate::services::user_service::UserService;
+use crate::shared::webdav::{EntryPath, EntryPathPub};
+
+/// Number of events requested from the primary per request.
+const BATCH_SIZE: u16 = 100;
+/// Number of local users loaded per page.
+const USER_PAGE_SIZE: u16 = 100;
+
+/// Errors while mirroring a user.
+#[derive(Debug, thiserror::Error)]
+pub enum MirrorError {
+    #[error("DB error: {0}")]
+    SqlDb(#[from] sqlx::Error),
+    #[error("Request to the primary homeserver failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("Primary homeserver responded with {0}")]
+    Status(StatusCode),
+    #[error("Invalid event from the primary homeserver: {0}")]
+    InvalidEvent(String),
+    #[error("File error: {0}")]
+    File(#[from] FileIoError),
+    #[error("Copy of {0} does not match its event or the primary's ETag")]
+    HashMismatch(String),
+}
+
+/// Progress of a single mirror run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MirrorRunStats {
+    /// Whether the run resolved every local user, not only the mirrored ones.
+    pub discovery: bool,
+    /// Local users whose `_pubky` record lists this homeserver as a mirror.
+    pub users: u64,
+    /// Users that could not be mirrored in this run.
+    pub failed_users: u64,
+    /// Files copied from a primary homeserver.
+    pub copied: u64,
+    /// Files deleted because they were deleted on the primary homeserver.
+    pub deleted: u64,
+}
+
+/// Observable state of the mirror job, exposed through the admin API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MirrorStatus {
+    /// Whether a run is currently in progress.
+    pub running: bool,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    /// Error of the last run, if it failed.
+    pub last_error: Option<String>,
+    /// Progress of the current run, or the result of the last one.
+    pub run: MirrorRunStats,
+}
+
+/// An event of the primary's `/events-stream`.
+#[derive(Debug, Clone, PartialEq)]
+struct MirrorEvent {
+    path: EntryPath,
+    cursor: String,
+    /// `Some` for `PUT`, `None` for `DEL`.
+    content_hash: Option<Hash>,
+}
+
+/// Replicates the `/pub/` data of mirrored users from their primary homeserver.
+#[derive(Clone)]
+pub(crate) struct Mirror {
+    sql_db: SqlDb,
+    file_service: FileService,
+    user_service: UserService,
+    /// Public key of this homeserver.
+    homeserver: PublicKey,
+    pkarr: pkarr::Client,
+    /// Client for `https://<pubkey>` URLs, resolved through pkarr.
+    http: reqwest::Client,
+}
+
+impl Mirror {
+    pub fn new(
+        sql_db: SqlDb,
+        file_service: FileService,
+        user_service: UserService,
+        homeserver: PublicKey,
+        pkarr: pkarr::Client,
+    ) -> Result<Self, reqwest::Error> {
+        let http = reqwest::ClientBuilder::from(pkarr.clone())
+            .timeout(Duration::from_secs(60))
+            .build()?;
+        Ok(Self {
+            sql_db,
+            file_service,
+            user_service,
+            homeserver,
+            pkarr,
+            http,
+        })
+    }
+
+    /// Mirror the users that list this homeserver as a mirror, in pages of users.
+    ///
+    /// With `discover`, the `_pubky` record of every local user is resolved to find
+    /// the users who opted in. Otherwise only users with a cursor in `mirror_cursors`
+    /// are resolved; a user who no longer lists this homeserver loses their cursor.
+    ///
+    /// A user that fails is logged and counted, and does not stop the run.
+    pub async fn run(
+        &self,
+        discover: bool,
+        on_progress: impl Fn(&MirrorRunStats),
+    ) -> Result<MirrorRunStats, sqlx::Error> {
+        let mut stats = MirrorRunStats {
+            discovery: discover,
+            ..Default::default()
+        };
+        let mut after = None;
+        loop {
+            let users = if discover {
+                let query = UserListQuery {
+                    disabled: Some(false),
+                    limit: Some(USER_PAGE_SIZE),
+                    cursor: after,
+                    ..Default::default()
+                };
+                self.user_service.list(query).await?.items
+            } else {
+                MirrorCursorRepository::list_users(
+                    after,
+                    USER_PAGE_SIZE,
+                    &mut self.sql_db.pool().into(),
+                )
+                .await?
+            };
+            let Some(last) = users.last() else {
+                return Ok(stats);
+            };
+            after = Some(last.id);
+            let last_page = users.len() < USER_PAGE_SIZE as usize;
+            for user in users {
+                if user.disabled {
+                    continue;
+                }
+                let primary = match self.primary_of(&user.public_key).await {
+                    Ok(Some(primary)) => primary,
+                    Ok(None) => {
+                        if !discover {
+                            MirrorCursorRepository::delete(user.id, &mut self.sql_db.pool().into())
+                                .await?;
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::debug!("Failed to resolve {}: {}", user.public_key, e);
+                        continue;
+                    }
+                };
+                stats.users += 1;
+                if let Err(e) = self
+                    .mirror_user(user.id, &user.public_key, &primary, &mut stats)
+                    .await
+                {
+                    tracing::warn!(
+                        "Failed to mirror {} from {}: {}",
+                        user.public_key,
+                        primary,
+                        e
+                    );
+                    stats.failed_users += 1;
+                }
+                on_progress(&stats);
+            }
+            if last_page {
+                return Ok(stats);
+            }
+        }
+    }
+
+    /// The primary homeserver of `user`, if their `_pubky` record lists this
+    /// homeserver as one of its mirrors.
+    async fn primary_of(&self, user: &PublicKey) -> Result<Option<PublicKey>, ResolveError> {
+        let packet = self.pkarr.resolve(user, ResolvePolicy::CacheFirst).await?;
+        let homeservers = listed_homeservers(&packet);
+        let Some((primary, mirrors)) = homeservers.split_first() else {
+            return Ok(None);
+        };
+        Ok(mirrors.contains(&self.homeserver).then(|| primary.clone()))
+    }
+
+    /// Follow the event stream of `user` on `primary` until it is caught up.
+    async fn mirror_user(
+        &self,
+        user_id: i32,
+        user: &PublicKey,
+        primary: &PublicKey,
+        stats: &mut MirrorRunStats,
+    ) -> Result<(), MirrorError> {
+        let mut cursor =
+            MirrorCursorRepository::get(user_id, primary, &mut self.sql_db.pool().into()).await?;
+        loop {
+            let events = match self.fetch_events(user, primary, cursor.as_deref()).await {
+                Err(MirrorError::Status(StatusCode::GONE)) if cursor.is_some() => {
+                    tracing::info!(
+                        "History of {} on {} was pruned; mirroring from the start",
+                        user,
+                        primary
+                    );
+                    MirrorCursorRepository::delete(user_id, &mut self.sql_db.pool().into()).await?;
+                    cursor = None;
+                    continue;
+                }
+                result => result?,
+            };
+            let caught_up = events.len() < BATCH_SIZE as usize;
+            for event in events {
+                if event.path.pubkey() != user {
+                    return Err(MirrorError::InvalidEvent(format!(
+                        "event of another user: {}",
+                        event.path
+                    )));
+                }
+                match event.content_hash {
+                    Some(hash) => {
+                        if self.copy_file(primary, &event.path, &hash).await? {
+                            stats.copied += 1;
+                        }
+                    }
+                    None => match self.file_service.delete(&event.path).await {
+                        Ok(()) => stats.deleted += 1,
+                        Err(FileIoError::NotFound) => {}
+                        Err(e) => return Err(e.into()),
+                    },
+                }
+                MirrorCursorRepository::set(
+                    user_id,
+                    primary,
+                    &event.cursor,
+                    &mut self.sql_db.pool().into(),
+                )
+                .await?;
+                cursor = Some(event.cursor);
+            }
+            if caught_up {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Fetch the next batch of `/pub/` events of `user` after `cursor` from `primary`.
+    async fn fetch_events(
+        &self,
+        user: &PublicKey,
+        primary: &PublicKey,
+        cursor: Option<&str>,
+    ) -> Result<Vec<MirrorEvent>, MirrorError> {
+        let user_param = match cursor {
+            Some(cursor) => format!("{}:{}", user.z32(), cursor),
+            None => user.z32(),
+        };
+        let mut url = reqwest::Url::parse(&format!("https://{}/events-stream", primary.z32()))
+            .expect("z32 public keys are valid hosts");
+        url.query_pairs_mut()
+            .append_pair("user", &user_param)
+            .append_pair("path", "/pub/")
+            .append_pair("limit", &BATCH_SIZE.to_string());
+        let response = self.http.get(url).send().await?;
+        if !response.status().is_success() {
+            return Err(MirrorError::Status(response.status()));
+        }
+        parse_events(&response.text().await?)
+    }
+
+    /// Copy the file at `path` from `primary`, unless the local copy already has
+    /// `content_hash`. Returns whether the file was copied.
+    ///
+    /// The primary may serve a newer version than the event announced. It is stored
+    /// anyway if it matches the `ETag` of the response; the event of that newer
+    /// version follows later in the stream. A copy matching neither is deleted.
+    async fn copy_file(
+        &self,
+        primary: &PublicKey,
+        path: &EntryPath,
+        content_hash: &Hash,
+    ) -> Result<bool, MirrorError> {
+        match EntryRepository::get_by_path(path, &mut self.sql_db.pool().into()).await {
+            Ok(entry) if entry.content_hash == *content_hash => return Ok(false),
+            Ok(_) | Err(sqlx::Error::RowNotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        let response = self
+            .http
+            .get(format!(
+                "https://{}{}",
+                primary.z32(),
+                path.path().url_encode()
+            ))
+            .header("pubky-host", path.pubkey().z32())
+            .send()
+            .await?;
+        match response.status() {
+            // Deleted since; the `DEL` event follows later in the stream.
+            StatusCode::NOT_FOUND => return Ok(false),
+            status if !status.is_success() => return Err(MirrorError::Status(status)),
+            _ => {}
+        }
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| decode_hash(value.trim().trim_matches('"')));
+        let stream = response
+            .bytes_stream()
+            .map_err(|e| WriteStreamError::Other(e.into()));
+        let entry = self.file_service.write_stream(path, stream).await?;
+        if entry.content_hash == *content_hash {
+            return Ok(true);
+        }
+        if etag == Some(entry.content_hash) {
+            tracing::debug!(
+                "Mirrored {} is newer than its event; expecting a later event",
+                path
+            );
+            return Ok(true);
+        }
+        match self.file_service.delete(path).await {
+            Ok(()) | Err(FileIoError::NotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+        Err(MirrorError::HashMismatch(path.to_string()))
+    }
+}
+
+/// Parse the SSE body of a `/events-stream` batch.
+///
+/// ```text
+/// event: PUT
+/// data: pubky://user_pubkey/pub/example.txt
+/// data: cursor: 42
+/// data: content_hash: r0NJufX5oaagQE3qNtzJSZvLJcmtwRK3zJqTyuQfMmI=
+/// ```
+fn parse_events(body: &str) -> Result<Vec<MirrorEvent>, MirrorError> {
+    let invalid = |message: &str| MirrorError::InvalidEvent(message.to_string());
+    let mut events = Vec::new();
+    for block in body.split("\n\n") {
+        let mut event_type = None;
+        let mut data = Vec::new();
+        for line in block.lines() {
+            if let Some(value) = line.strip_prefix("event:") {
+                event_type = Some(value.trim());
+            } else if let Some(value) = line.strip_prefix("data:") {
+                data.push(value.trim());
+            }
+        }
+        // Keep-alive comments and trailing newlines.
+        let Some(event_type) = event_type else {
+            continue;
+        };
+
+        let uri = data.first().ok_or_else(|| invalid("missing uri"))?;
+        // Only `/pub/` data is mirrored, whatever the primary sends.
+        let path = uri
+            .strip_prefix("pubky://")
+            .ok_or_else(|| invalid(uri))?
+            .parse::<EntryPathPub>()
+            .map_err(|_| invalid(uri))?
+            .0;
+        let field = |name: &str| {
+            data.iter()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .map(str::trim)
+        };
+        let cursor = field("cursor").ok_or_else(|| invalid("missing cursor"))?;
+        let content_hash = match event_type {
+            "PUT" => {
+                let encoded =
+                    field("content_hash").ok_or_else(|| invalid("missing content hash"))?;
+                Some(decode_hash(encoded).ok_or_else(|| invalid(encoded))?)
+            }
+            "DEL" => None,
+            other => return Err(invalid(other)),
+        };
+        events.push(MirrorEvent {
+            path,
+            cursor: cursor.to_string(),
+            content_hash,
+        });
+    }
+    Ok(events)
+}
+
+/// Decode a base64 content hash, as used in events and `ETag`s.
+fn decode_hash(encoded: &str) -> Option<Hash> {
+    let bytes: [u8; 32] = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .ok()?
+        .try_into()
+        .ok()?;
+    Some(Hash::from_bytes(bytes))
+}
+
+/// Background job that periodically mirrors users from their primary homeserver.
+///
+/// Does nothing if mirroring is disabled. The task is aborted when the job is dropped.
+pub struct MirrorJob {
+    handle: Option<JoinHandle<()>>,
+    trigger: Arc<Notify>,
+    status: Arc<Mutex<MirrorStatus>>,
+}
+
+impl MirrorJob {
+    /// Advisory lock ID used to allow only one mirror run across all instances.
+    const MIRROR_LOCK_ID: i64 = 0x6d697272_6f720001; // "mirror" + 1
+
+    /// Start the mirror job.
+    ///
+    /// Runs every `interval` seconds, and whenever [`Self::trigger`] is called.
+    /// With an interval of 0 it only runs when triggered. Triggered runs, and
+    /// scheduled runs at least `discovery_interval` seconds apart, also look for
+    /// users who newly opted in.
+    #[must_use = "the job stops when dropped"]
+    pub(crate) fn start(pool: &PgPool, mirror: Mirror, config: &MirrorToml) -> Self {
+        let trigger = Arc::new(Notify::new());
+        let status = Arc::new(Mutex::new(MirrorStatus::default()));
+        let handle = config.enabled.then(|| {
+            let pool = pool.clone();
+            let interval = Duration::from_secs(config.interval);
+            let discovery_interval = Duration::from_secs(config.discovery_interval);
+            let trigger = trigger.clone();
+            let status = status.clone();
+            tokio::spawn(async move {
+                let mut last_discovery: Option<Instant> = None;
+                loop {
+                    let triggered = if interval.is_zero() {
+                        trigger.notified().await;
+                        true
+                    } else {
+                        tokio::select! {
+                            _ = tokio::time::sleep(interval) => false,
+                            _ = trigger.notified() => true,
+                        }
+                    };
+                    let discover = triggered
+                        || last_discovery.is_none_or(|at| at.elapsed() >= discovery_interval);
+                    if discover {
+                        last_discovery = Some(Instant::now());
+                    }
+                    Self::run(&pool, &mirror, &status, discover).await;
+                }
+            })
+        });
+        Self {
+            handle,
+            trigger,
+            status,
+        }
+    }
+
+    /// Request a run as soon as possible. A request during a run schedules another one.
+    pub fn trigger(&self) {
+        self.trigger.notify_one();
+    }
+
+    /// Snapshot of the current job state.
+    pub fn status(&self) -> MirrorStatus {
+        self.status.lock().expect("status lock poisoned").clone()
+    }
+
+    async fn run(pool: &PgPool, mirror: &Mirror, status: &Mutex<MirrorStatus>, discover: bool) {
+        {
+            let mut status = status.lock().expect("status lock poisoned");
+            status.running = true;
+            status.last_started_at = Some(Utc::now());
+            status.run = MirrorRunStats::default();
+        }
+        let result = Self::run_once(pool, mirror, status, discover).await;
+        let mut status = status.lock().expect("status lock poisoned");
+        status.running = false;
+        status.last_finished_at = Some(Utc::now());
+        match result {
+            Ok(Some(stats)) => {
+                if stats.failed_users > 0 {
+                    tracing::warn!(
+                        "Mirrored {} users, {} failed",
+                        stats.users,
+                        stats.failed_users
+                    );
+                }
+                status.last_error = None;
+                status.run = stats;
+            }
+            Ok(None) => status.last_error = None,
+            Err(e) => {
+                tracing::error!("Mirror run failed: {}", e);
+                status.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Mirror once, publishing progress to `status` after every user.
+    ///
+    /// Returns `None` without mirroring if another instance holds the mirror lock.
+    pub(crate) async fn run_once(
+        pool: &PgPool,
+        mirror: &Mirror,
+        status: &Mutex<MirrorStatus>,
+        discover: bool,
+    ) -> Result<Option<MirrorRunStats>, sqlx::Error> {
+        // Session-level lock, held on a dedicated connection for the whole run.
+        let mut lock_con = pool.acquire().await?;
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(Self::MIRROR_LOCK_ID)
+            .fetch_one(&mut *lock_con)
+            .await?;
+        if !locked {
+            tracing::debug!("Mirroring is already running on another instance");
+            return Ok(None);
+        }
+
+        let result = mirror
+            .run(discover, |stats| {
+                status.lock().expect("status lock poisoned").run = *stats;
+            })
+            .await;
+
+        let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(Self::MIRROR_LOCK_ID)
+            .execute(&mut *lock_con)
+            .await;
+        if let Err(e) = unlocked {
+            // Closing the connection releases the lock.
+            tracing::warn!("Failed to release the mirror lock: {}", e);
+            drop(lock_con.detach());
+        }
+        result.map(Some)
+    }
+}
+
+impl Drop for MirrorJob {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pubky_common::crypto::Keypair;
+
+    #[test]
+    fn test_parse_events() {
+        let user = Keypair::random().public_key();
+        let hash = pubky_common::crypto::hash(b"hello");
+        let encoded = base64::engine::general_purpose::STANDARD.encode(hash.as_bytes());
+        let body = format!(
+            "event: PUT\ndata: pubky://{user}/pub/a b.txt\ndata: cursor: 41\ndata: content_hash: {encoded}\n\n\
+             :\n\n\
+             event: DEL\ndata: pubky://{user}/pub/old.txt\ndata: cursor: 42\n\n",
+            user = user.z32()
+        );
+
+        let events = parse_events(&body).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                MirrorEvent {
+                    path: format!("{}/pub/a b.txt", user.z32()).parse().unwrap(),
+                    cursor: "41".to_string(),
+                    content_hash: Some(hash),
+                },
+                MirrorEvent {
+                    path: format!("{}/pub/old.txt", user.z32()).parse().unwrap(),
+                    cursor: "42".to_string(),
+                    content_hash: None,
+                },
+            ]
+        );
+        assert!(parse_events("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_events_rejects_invalid_events() {
+        let user = Keypair::random().public_key().z32();
+        for body in [
+            format!("event: PUT\ndata: pubky://{user}/pub/a.txt\ndata: cursor: 1\n\n"),
+            format!("event: DEL\ndata: pubky://{user}/pub/a.txt\n\n"),
+            format!("event: MOVE\ndata: pubky://{user}/pub/a.txt\ndata: cursor: 1\n\n"),
+            "event: DEL\ndata: https://example.com/pub/a.txt\ndata: cursor: 1\n\n".to_string(),
+            format!("event: DEL\ndata: pubky://{user}/priv/a.txt\ndata: cursor: 1\n\n"),
+            format!("event: DEL\ndata: pubky://{user}/public.txt\ndata: cursor: 1\n\n"),
+        ] {
+            assert!(
+                matches!(parse_events(&body), Err(MirrorError::InvalidEvent(_))),
+                "{body}"
+            );
+        }
+    }
+}
@@ -1,3 +1,13 @@
 //! Application services — business logic and coordination.
 
+pub mod backup;
+pub mod config_service;
+pub mod content_block_service;
+pub mod mirror_job;
+pub mod moderation_service;
+pub mod storage_integrity;
+pub mod storage_migration;
+pub mod storage_scrub_job;
+pub mod storage_usage;
+pub mod usage_service;
 pub mod user_service;
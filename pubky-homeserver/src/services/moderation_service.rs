@@ -0,0 +1,513 @@
+//! Moderation service — hard-deletes users, revokes sessions and grants on behalf
+//! of operators, and runs bulk moderation actions.
+//!
+//! Revocations queue an [`AuthRevocation`] in their transaction, so private event
+//! streams opened with the revoked credential close on every instance.
+//!
+//! Bulk actions can touch thousands of files, so they run as background jobs.
+//! Jobs are tracked in memory by the instance that started them: their status is
+//! only visible through that instance's admin API and is lost on restart. Every
+//! action is idempotent, so an interrupted job can simply be submitted again.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use pubky_common::auth::jws::{ClientId, GrantId};
+use pubky_common::crypto::PublicKey;
+use serde::Serialize;
+
+use crate::client_server::auth::{
+    cookie::persistence::SessionRepository,
+    grant::persistence::{grant::GrantRepository, grant_session::GrantSessionRepository},
+    revocation::AuthRevocation,
+};
+use crate::persistence::files::{FileIoError, FileService};
+use crate::persistence::sql::{
+    banned_key::BannedKeyRepository, entry::EntryRepository, uexecutor, user::UserRepository,
+    SqlDb, UnifiedExecutor,
+};
+use crate::services::user_service::UserService;
+use crate::shared::webdav::{EntryPath, StoragePath};
+
+/// Number of finished jobs kept for the status endpoints.
+const MAX_FINISHED_JOBS: usize = 100;
+
+/// Number of entries listed per batch while deleting files.
+const DELETE_BATCH_SIZE: u16 = 500;
+
+/// How often a purge deletes files again when entries were written concurrently.
+const PURGE_ATTEMPTS: usize = 3;
+
+/// Error of a moderation action.
+#[derive(Debug, thiserror::Error)]
+pub enum ModerationError {
+    #[error("Files kept being written while the user was purged")]
+    ConcurrentWrites,
+    #[error(transparent)]
+    Storage(#[from] FileIoError),
+    #[error("DB error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// A moderation action executed by a job.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Hard-delete users, optionally banning their keys from signing up again.
+    DeleteUsers {
+        users: Vec<PublicKey>,
+        ban: bool,
+        reason: Option<String>,
+    },
+    /// Disable users.
+    DisableUsers { users: Vec<PublicKey> },
+    /// Delete all entries of a user below a directory prefix.
+    DeleteEntries {
+        user: PublicKey,
+        prefix: StoragePath,
+    },
+}
+
+impl ModerationAction {
+    /// Number of targets the job works through: users, or files for `DeleteEntries`.
+    fn target_count(&self) -> u64 {
+        match self {
+            Self::DeleteUsers { users, .. } | Self::DisableUsers { users } => users.len() as u64,
+            Self::DeleteEntries { .. } => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    /// All targets were processed without error.
+    Succeeded,
+    /// At least one target failed, see [`ModerationJob::failures`].
+    Failed,
+}
+
+/// A target a job could not process.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobFailure {
+    /// Public key or path of the target.
+    pub target: String,
+    pub error: String,
+}
+
+/// Status of a moderation job.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModerationJob {
+    pub id: u64,
+    #[serde(flatten)]
+    pub action: ModerationAction,
+    pub state: JobState,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Targets processed so far.
+    pub processed: u64,
+    /// Number of targets. For `delete_entries` the number of files found when the job started.
+    pub total: u64,
+    /// Files deleted so far.
+    pub entries_deleted: u64,
+    pub failures: Vec<JobFailure>,
+}
+
+#[derive(Debug, Default)]
+struct Jobs {
+    next_id: u64,
+    /// Ordered by id.
+    jobs: VecDeque<ModerationJob>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    sql_db: SqlDb,
+    file_service: FileService,
+    user_service: UserService,
+    jobs: Mutex<Jobs>,
+}
+
+/// Number of credentials revoked by [`ModerationService::revoke_all_sessions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RevokedSessions {
+    pub cookie_sessions: u64,
+    pub grants: u64,
+}
+
+/// Deletes users and their data, revokes credentials and runs bulk moderation jobs.
+#[derive(Debug, Clone)]
+pub struct ModerationService {
+    inner: Arc<Inner>,
+}
+
+impl ModerationService {
+    pub fn new(sql_db: SqlDb, file_service: FileService, user_service: UserService) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                sql_db,
+                file_service,
+                user_service,
+                jobs: Mutex::new(Jobs::default()),
+            }),
+        }
+    }
+
+    // ── Revocations ────────────────────────────────────────────────
+
+    /// Revoke a grant of a user and delete its session.
+    /// Returns `false` if the user has no such grant. Revoking a revoked grant is a no-op.
+    pub async fn revoke_grant(
+        &self,
+        user_id: i32,
+        grant_id: &GrantId,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.inner.sql_db.pool().begin().await?;
+        let grant = match GrantRepository::get_by_id(grant_id, uexecutor!(tx)).await {
+            Ok(grant) if grant.user_id == user_id => grant,
+            Ok(_) | Err(sqlx::Error::RowNotFound) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if grant.revoked_at.is_none() {
+            GrantRepository::revoke(grant_id, uexecutor!(tx)).await?;
+            GrantSessionRepository::delete_all_for_grant(grant_id, uexecutor!(tx)).await?;
+            AuthRevocation::notify_grant_in_transaction(grant_id, uexecutor!(tx)).await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Delete a cookie session of a user. Returns `false` if the user has no such session.
+    pub async fn revoke_cookie_session(
+        &self,
+        user_id: i32,
+        session_id: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.inner.sql_db.pool().begin().await?;
+        if !SessionRepository::delete_by_id(user_id, session_id, uexecutor!(tx)).await? {
+            return Ok(false);
+        }
+        AuthRevocation::notify_cookie_session_in_transaction(session_id, uexecutor!(tx)).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Revoke all grants and delete all cookie sessions of a user, e.g. after a
+    /// device was compromised. The user has to sign in again everywhere.
+    pub async fn revoke_all_sessions(&self, user_id: i32) -> Result<RevokedSessions, sqlx::Error> {
+        let mut tx = self.inner.sql_db.pool().begin().await?;
+        let session_ids = SessionRepository::delete_all_for_user(user_id, uexecutor!(tx)).await?;
+        for id in &session_ids {
+            AuthRevocation::notify_cookie_session_in_transaction(*id, uexecutor!(tx)).await?;
+        }
+        let grant_ids = GrantRepository::revoke_all_for_user(user_id, uexecutor!(tx)).await?;
+        Self::revoke_grant_sessions(&grant_ids, uexecutor!(tx)).await?;
+        tx.commit().await?;
+        Ok(RevokedSessions {
+            cookie_sessions: session_ids.len() as u64,
+            grants: grant_ids.len() as u64,
+        })
+    }
+
+    /// Revoke the grants of all users issued to `client_id`, e.g. after the app's
+    /// key leaked. Returns the number of revoked grants.
+    pub async fn revoke_client_grants(&self, client_id: &ClientId) -> Result<u64, sqlx::Error> {
+        let mut tx = self.inner.sql_db.pool().begin().await?;
+        let grant_ids = GrantRepository::revoke_all_for_client(client_id, uexecutor!(tx)).await?;
+        Self::revoke_grant_sessions(&grant_ids, uexecutor!(tx)).await?;
+        tx.commit().await?;
+        Ok(grant_ids.len() as u64)
+    }
+
+    /// Delete the sessions of revoked grants and notify their streams.
+    async fn revoke_grant_sessions<'a>(
+        grant_ids: &[GrantId],
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        GrantSessionRepository::delete_all_for_grants(grant_ids, executor).await?;
+        for id in grant_ids {
+            AuthRevocation::notify_grant_in_transaction(id, executor).await?;
+        }
+        Ok(())
+    }
+
+    // ── Jobs ───────────────────────────────────────────────────────
+
+    /// Start a job executing `action` in the background and return its initial status.
+    pub fn start_job(&self, action: ModerationAction) -> ModerationJob {
+        let job = {
+            let mut jobs = self.inner.jobs.lock().expect("jobs lock poisoned");
+            jobs.next_id += 1;
+            let job = ModerationJob {
+                id: jobs.next_id,
+                total: action.target_count(),
+                action: action.clone(),
+                state: JobState::Running,
+                created_at: Utc::now(),
+                finished_at: None,
+                processed: 0,
+                entries_deleted: 0,
+                failures: Vec::new(),
+            };
+            jobs.jobs.push_back(job.clone());
+            let finished = jobs
+                .jobs
+                .iter()
+                .filter(|job| job.state != JobState::Running)
+                .count();
+            if finished > MAX_FINISHED_JOBS {
+                if let Some(index) = jobs
+                    .jobs
+                    .iter()
+                    .position(|job| job.state != JobState::Running)
+                {
+                    jobs.jobs.remove(index);
+                }
+            }
+            job
+        };
+
+        let service = self.clone();
+        let id = job.id;
+        tokio::spawn(async move {
+            service.run_job(id, action).await;
+            service.update_job(id, |job| {
+                job.state = if job.failures.is_empty() {
+                    JobState::Succeeded
+                } else {
+                    JobState::Failed
+                };
+                job.finished_at = Some(Utc::now());
+            });
+        });
+        job
+    }
+
+    /// Status of a job, `None` if it is unknown to this instance.
+    pub fn job(&self, id: u64) -> Option<ModerationJob> {
+        let jobs = self.inner.jobs.lock().expect("jobs lock poisoned");
+        jobs.jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    /// Status of all jobs known to this instance, newest first.
+    pub fn jobs(&self) -> Vec<ModerationJob> {
+        let jobs = self.inner.jobs.lock().expect("jobs lock poisoned");
+        jobs.jobs.iter().rev().cloned().collect()
+    }
+
+    fn update_job(&self, id: u64, f: impl FnOnce(&mut ModerationJob)) {
+        let mut jobs = self.inner.jobs.lock().expect("jobs lock poisoned");
+        if let Some(job) = jobs.jobs.iter_mut().find(|job| job.id == id) {
+            f(job);
+        }
+    }
+
+    fn record_failure(&self, id: u64, target: String, error: impl ToString) {
+        let error = error.to_string();
+        tracing::warn!("Moderation job {id} failed for {target}: {error}");
+        self.update_job(id, |job| job.failures.push(JobFailure { target, error }));
+    }
+
+    async fn run_job(&self, id: u64, action: ModerationAction) {
+        match action {
+            ModerationAction::DeleteUsers { users, ban, reason } => {
+                for user in users {
+                    let on_deleted = || self.update_job(id, |job| job.entries_deleted += 1);
+                    if let Err(e) = self
+                        .purge_user(&user, ban, reason.as_deref(), on_deleted)
+                        .await
+                    {
+                        self.record_failure(id, user.z32(), e);
+                    }
+                    self.update_job(id, |job| job.processed += 1);
+                }
+            }
+            ModerationAction::DisableUsers { users } => {
+                for user in users {
+                    match self.disable_user(&user).await {
+                        Ok(true) => {}
+                        Ok(false) => self.record_failure(id, user.z32(), "User not found"),
+                        Err(e) => self.record_failure(id, user.z32(), e),
+                    }
+                    self.update_job(id, |job| job.processed += 1);
+                }
+            }
+            ModerationAction::DeleteEntries { user, prefix } => {
+                let target = EntryPath::new(user.clone(), prefix.clone()).to_string();
+                match self.file_count(&user, &prefix).await {
+                    Ok(total) => self.update_job(id, |job| job.total = total),
+                    Err(e) => return self.record_failure(id, target, e),
+                }
+                let on_deleted = || {
+                    self.update_job(id, |job| {
+                        job.entries_deleted += 1;
+                        job.processed += 1;
+                    })
+                };
+                if let Err(e) = self.delete_entries(&user, &prefix, on_deleted).await {
+                    self.record_failure(id, target, e);
+                }
+            }
+        }
+    }
+
+    // ── Actions ────────────────────────────────────────────────────
+
+    /// Disable a user. Returns `false` if the user does not exist.
+    pub async fn disable_user(&self, pubkey: &PublicKey) -> Result<bool, sqlx::Error> {
+        let mut tx = self.inner.sql_db.pool().begin().await?;
+        let mut user = match UserRepository::get_for_update(pubkey, uexecutor!(tx)).await {
+            Ok(user) => user,
+            Err(sqlx::Error::RowNotFound) => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if !user.disabled {
+            user.disabled = true;
+            UserRepository::update(&user, uexecutor!(tx)).await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Number of files of a user below `prefix`.
+    async fn file_count(
+        &self,
+        pubkey: &PublicKey,
+        prefix: &StoragePath,
+    ) -> Result<u64, sqlx::Error> {
+        let db = &self.inner.sql_db;
+        let user_id = match self.inner.user_service.get_id(pubkey).await {
+            Ok(id) => id,
+            Err(sqlx::Error::RowNotFound) => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let usage = EntryRepository::usage_below(user_id, prefix, &mut db.pool().into()).await?;
+        Ok(usage.file_count)
+    }
+
+    /// Delete all files of a user below the directory `prefix`.
+    ///
+    /// Deletes go through [`FileService`] and the storage layers like a user's own
+    /// deletes, so quota usage is updated and `DEL` events are emitted. Write-path
+    /// restrictions don't apply, as they do for other admin deletes. Calls `on_deleted` per file
+    /// and returns the number of deleted files.
+    pub async fn delete_entries(
+        &self,
+        pubkey: &PublicKey,
+        prefix: &StoragePath,
+        on_deleted: impl Fn(),
+    ) -> Result<u64, FileIoError> {
+        let db = &self.inner.sql_db;
+        let dir = EntryPath::new(pubkey.clone(), prefix.clone());
+        let mut cursor = None;
+        let mut deleted = 0;
+        loop {
+            let batch = EntryRepository::list_deep(
+                &dir,
+                Some(DELETE_BATCH_SIZE),
+                cursor,
+                false,
+                &mut db.pool().into(),
+            )
+            .await?;
+            let Some(last) = batch.last().cloned() else {
+                return Ok(deleted);
+            };
+            for path in batch {
+                match self.inner.file_service.admin_delete(&path).await {
+                    Ok(()) => {
+                        deleted += 1;
+                        on_deleted();
+                    }
+                    // Deleted by the user meanwhile.
+                    Err(FileIoError::NotFound) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            cursor = Some(last);
+        }
+    }
+
+    /// Hard-delete a user: files, events, sessions, grants and the account itself.
+    ///
+    /// The user is disabled first so no new files are written while the files are
+    /// deleted. Open private streams of the user's sessions and grants are closed.
+    /// With `ban` the key is added to the ban list, which also works for keys
+    /// without an account. Calls `on_deleted` per deleted file and returns the
+    /// number of deleted files.
+    pub async fn purge_user(
+        &self,
+        pubkey: &PublicKey,
+        ban: bool,
+        reason: Option<&str>,
+        on_deleted: impl Fn(),
+    ) -> Result<u64, ModerationError> {
+        let root = StoragePath::new("/").expect("root is a valid storage path");
+        let exists = self.disable_user(pubkey).await?;
+        let mut deleted = 0;
+        if exists {
+            for attempt in 1..=PURGE_ATTEMPTS {
+                deleted += self.delete_entries(pubkey, &root, &on_deleted).await?;
+                if self.delete_user_rows(pubkey, ban, reason).await? {
+                    self.inner.user_service.evict_cached_quota(pubkey);
+                    return Ok(deleted);
+                }
+                tracing::debug!(
+                    "Files of {} were written during purge attempt {attempt}",
+                    pubkey.z32()
+                );
+            }
+            return Err(ModerationError::ConcurrentWrites);
+        }
+        if ban {
+            BannedKeyRepository::ban(pubkey, reason, &mut self.inner.sql_db.pool().into()).await?;
+        }
+        Ok(deleted)
+    }
+
+    /// Delete the user row and everything cascading from it, once no files are left.
+    /// Returns `false` if files were written in the meantime.
+    async fn delete_user_rows(
+        &self,
+        pubkey: &PublicKey,
+        ban: bool,
+        reason: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let root = StoragePath::new("/").expect("root is a valid storage path");
+        let mut tx = self.inner.sql_db.pool().begin().await?;
+        let user = match UserRepository::get_for_update(pubkey, uexecutor!(tx)).await {
+            Ok(user) => user,
+            // Deleted concurrently, e.g. by another job.
+            Err(sqlx::Error::RowNotFound) => return Ok(true),
+            Err(e) => return Err(e),
+        };
+        if EntryRepository::usage_below(user.id, &root, uexecutor!(tx))
+            .await?
+            .file_count
+            > 0
+        {
+            return Ok(false);
+        }
+
+        let sessions = SessionRepository::list_for_user(user.id, uexecutor!(tx)).await?;
+        for session in sessions {
+            AuthRevocation::notify_cookie_session_in_transaction(session.id, uexecutor!(tx))
+                .await?;
+        }
+        let grants = GrantRepository::list_active_for_user(user.id, uexecutor!(tx)).await?;
+        for grant in grants {
+            AuthRevocation::notify_grant_in_transaction(&grant.id, uexecutor!(tx)).await?;
+        }
+        self.inner
+            .user_service
+            .delete_in_tx(user.id, uexecutor!(tx))
+            .await?;
+        if ban {
+            BannedKeyRepository::ban(pubkey, reason, uexecutor!(tx)).await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+}
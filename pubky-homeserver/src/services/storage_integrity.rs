@@ -0,0 +1,568 @@
+//! Storage integrity — scrubs the `entries` table against the storage backend.
+//!
+//! A scrub runs in three passes:
+//!
+//! 1. **Entries**: every entry must have a file in the OpenDAL backend with the
+//!    recorded `content_length`. With [`ScrubOptions::rehash`] the file is read
+//!    and its hash compared to `content_hash` as well.
+//! 2. **Orphans**: every file in the backend must belong to an entry.
+//! 3. **Usage**: every user's `used_bytes` must match the sum of their entries.
+//!
+//! With [`ScrubOptions::repair`] the SQL side is fixed to match the files: entries
+//! of missing files are deleted, entries of changed files take the new hash and
+//! length, and `used_bytes` and the path quota usage are recomputed. Repaired
+//! entries get a `DEL` or `PUT` event so clients pick up the change. Each repair
+//! re-checks the entry under the user's row lock and skips it if it was written
+//! since the scan.
+//!
+//! Orphan files are only reported: they may be uploads that are still being
+//! finalized, and deleting files is not something a scrub should do on its own.
+
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::persistence::files::{
+    events::{EventRepository, EventType, EventsService},
+    write_finalization_layer::entries_used_bytes,
+    FileIoError, FileMetadata, FileService,
+};
+use crate::persistence::sql::{
+    entry::{EntryEntity, EntryRepository},
+    path_quota::PathQuotaRepository,
+    uexecutor,
+    user::{UserEntity, UserListQuery},
+    SqlDb,
+};
+use crate::services::user_service::{UserService, FILE_METADATA_SIZE};
+use crate::shared::webdav::{EntryPath, StoragePath};
+
+/// Number of entries and users checked per database page.
+const SCRUB_BATCH_SIZE: u16 = 500;
+
+/// What a scrub does besides checking sizes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrubOptions {
+    /// Read every file and compare its hash, not only its size.
+    pub rehash: bool,
+    /// Fix the database to match the files.
+    pub repair: bool,
+}
+
+/// An entry whose file does not have the recorded size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SizeMismatch {
+    /// Entry path, `{pubkey}/{path}`.
+    pub path: String,
+    /// `content_length` in the `entries` table.
+    pub expected: u64,
+    /// Size of the file in the storage backend.
+    pub actual: u64,
+}
+
+/// An entry whose file has the recorded size but a different hash.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HashMismatch {
+    /// Entry path, `{pubkey}/{path}`.
+    pub path: String,
+    /// Hex encoded `content_hash` in the `entries` table.
+    pub expected: String,
+    /// Hex encoded hash of the file in the storage backend.
+    pub actual: String,
+}
+
+/// A user whose `used_bytes` does not match their entries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UsedBytesMismatch {
+    /// z-base-32 public key of the user.
+    pub public_key: String,
+    /// `used_bytes` in the `users` table.
+    pub recorded: u64,
+    /// Storage used by the user's entries.
+    pub actual: u64,
+}
+
+/// Result of a storage scrub. While a scrub runs, the progress so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StorageScrubReport {
+    /// Number of entries checked.
+    pub checked: u64,
+    /// Entries without a file in the storage backend.
+    pub missing_files: Vec<String>,
+    /// Entries whose file has a different size than recorded.
+    pub size_mismatches: Vec<SizeMismatch>,
+    /// Entries whose file has a different hash than recorded. Only checked with `rehash`.
+    pub hash_mismatches: Vec<HashMismatch>,
+    /// Number of files in the storage backend checked for an entry.
+    pub files_checked: u64,
+    /// Files in the storage backend without an entry.
+    pub orphan_files: Vec<String>,
+    /// Number of users whose `used_bytes` was checked.
+    pub users_checked: u64,
+    /// Users whose `used_bytes` does not match their entries.
+    pub used_bytes_mismatches: Vec<UsedBytesMismatch>,
+    /// Number of entries and users fixed in the database.
+    pub repaired: u64,
+}
+
+impl StorageScrubReport {
+    /// Whether no problem was found.
+    pub fn is_ok(&self) -> bool {
+        self.problem_count() == 0
+    }
+
+    /// Number of problems found, repaired or not.
+    pub fn problem_count(&self) -> usize {
+        self.missing_files.len()
+            + self.size_mismatches.len()
+            + self.hash_mismatches.len()
+            + self.orphan_files.len()
+            + self.used_bytes_mismatches.len()
+    }
+}
+
+/// Problem found with an entry during the entries pass.
+enum EntryProblem {
+    Missing,
+    Size(u64),
+    Hash(FileMetadata),
+}
+
+/// Scrubs the `entries` table against the files in the storage backend.
+#[derive(Debug, Clone)]
+pub struct StorageIntegrityService {
+    sql_db: SqlDb,
+    file_service: FileService,
+    user_service: UserService,
+}
+
+impl StorageIntegrityService {
+    pub fn new(sql_db: SqlDb, file_service: FileService, user_service: UserService) -> Self {
+        Self {
+            sql_db,
+            file_service,
+            user_service,
+        }
+    }
+
+    /// Run all scrub passes. `progress` is called with the report so far after every batch.
+    pub async fn scrub(
+        &self,
+        options: ScrubOptions,
+        progress: impl Fn(&StorageScrubReport),
+    ) -> Result<StorageScrubReport, FileIoError> {
+        let mut report = StorageScrubReport::default();
+        self.scrub_entries(options, &mut report, &progress).await?;
+        self.find_orphans(&mut report, &progress).await?;
+        self.scrub_used_bytes(options, &mut report, &progress)
+            .await?;
+        Ok(report)
+    }
+
+    async fn scrub_entries(
+        &self,
+        options: ScrubOptions,
+        report: &mut StorageScrubReport,
+        progress: &impl Fn(&StorageScrubReport),
+    ) -> Result<(), FileIoError> {
+        let mut cursor = None;
+        loop {
+            let entries = EntryRepository::list_page(
+                cursor,
+                SCRUB_BATCH_SIZE,
+                &mut self.sql_db.pool().into(),
+            )
+            .await?;
+            let Some(last) = entries.last() else {
+                break;
+            };
+            cursor = Some(last.id);
+            for entry in entries {
+                report.checked += 1;
+                let Some(problem) = self.check_entry(&entry, options.rehash).await? else {
+                    continue;
+                };
+                let path = entry.path.to_string();
+                match &problem {
+                    EntryProblem::Missing => report.missing_files.push(path),
+                    EntryProblem::Size(actual) => report.size_mismatches.push(SizeMismatch {
+                        path,
+                        expected: entry.content_length,
+                        actual: *actual,
+                    }),
+                    EntryProblem::Hash(metadata) => report.hash_mismatches.push(HashMismatch {
+                        path,
+                        expected: entry.content_hash.to_hex().to_string(),
+                        actual: metadata.hash.to_hex().to_string(),
+                    }),
+                }
+                if options.repair && self.repair_entry(&entry).await? {
+                    report.repaired += 1;
+                }
+            }
+            progress(report);
+        }
+        Ok(())
+    }
+
+    async fn check_entry(
+        &self,
+        entry: &EntryEntity,
+        rehash: bool,
+    ) -> Result<Option<EntryProblem>, FileIoError> {
+        if !rehash {
+            return Ok(
+                match self
+                    .file_service
+                    .opendal
+                    .content_length(&entry.path)
+                    .await?
+                {
+                    None => Some(EntryProblem::Missing),
+                    Some(actual) if actual != entry.content_length => {
+                        Some(EntryProblem::Size(actual))
+                    }
+                    Some(_) => None,
+                },
+            );
+        }
+        Ok(match self.file_service.opendal.rehash(&entry.path).await? {
+            None => Some(EntryProblem::Missing),
+            Some(metadata) if metadata.length as u64 != entry.content_length => {
+                Some(EntryProblem::Size(metadata.length as u64))
+            }
+            Some(metadata) if metadata.hash != entry.content_hash => {
+                Some(EntryProblem::Hash(metadata))
+            }
+            Some(_) => None,
+        })
+    }
+
+    /// Make the entry match its file. Returns `false` if the entry changed since
+    /// `scanned` was read, or already matches.
+    ///
+    /// The file is read before the user's row is locked, so a large file does not
+    /// hold up the user's writes. Any write in between changes the entry.
+    async fn repair_entry(&self, scanned: &EntryEntity) -> Result<bool, FileIoError> {
+        let path = &scanned.path;
+        let rehashed = self.file_service.opendal.rehash(path).await?;
+        let mut tx = self.sql_db.pool().begin().await?;
+        let mut user = match self
+            .user_service
+            .get_for_no_key_update(path.pubkey(), uexecutor!(tx))
+            .await
+        {
+            Ok(user) => user,
+            Err(sqlx::Error::RowNotFound) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entry = match EntryRepository::get_by_path(path, uexecutor!(tx)).await {
+            Ok(entry) => entry,
+            Err(sqlx::Error::RowNotFound) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        if entry.id != scanned.id
+            || entry.content_hash != scanned.content_hash
+            || entry.content_length != scanned.content_length
+            || entry.modified_at != scanned.modified_at
+        {
+            return Ok(false);
+        }
+
+        let old_bytes = entry.content_length.saturating_add(FILE_METADATA_SIZE);
+        match rehashed {
+            None => {
+                EntryRepository::delete(entry.id, uexecutor!(tx)).await?;
+                EventRepository::create(user.id, EventType::Delete, path, uexecutor!(tx)).await?;
+                PathQuotaRepository::add_used_bytes(
+                    user.id,
+                    path.path(),
+                    -(old_bytes as i64),
+                    uexecutor!(tx),
+                )
+                .await?;
+                user.used_bytes = user.used_bytes.saturating_sub(old_bytes);
+                tracing::warn!("Scrub deleted the entry of missing file {path}");
+            }
+            Some(metadata)
+                if metadata.hash != entry.content_hash
+                    || metadata.length as u64 != entry.content_length =>
+            {
+                entry.content_hash = metadata.hash;
+                entry.content_length = metadata.length as u64;
+                EntryRepository::update(&entry, uexecutor!(tx)).await?;
+                EventRepository::create(
+                    user.id,
+                    EventType::Put {
+                        content_hash: metadata.hash,
+                    },
+                    path,
+                    uexecutor!(tx),
+                )
+                .await?;
+                let new_bytes = entry.content_length.saturating_add(FILE_METADATA_SIZE);
+                PathQuotaRepository::add_used_bytes(
+                    user.id,
+                    path.path(),
+                    new_bytes as i64 - old_bytes as i64,
+                    uexecutor!(tx),
+                )
+                .await?;
+                user.used_bytes = user
+                    .used_bytes
+                    .saturating_sub(old_bytes)
+                    .saturating_add(new_bytes);
+                tracing::warn!("Scrub updated the entry of changed file {path}");
+            }
+            Some(_) => return Ok(false),
+        }
+        self.user_service
+            .update_in_tx(&user, uexecutor!(tx))
+            .await?;
+        tx.commit().await?;
+        EventsService::notify_event(self.sql_db.pool()).await;
+        Ok(true)
+    }
+
+    async fn find_orphans(
+        &self,
+        report: &mut StorageScrubReport,
+        progress: &impl Fn(&StorageScrubReport),
+    ) -> Result<(), FileIoError> {
+        let mut lister = self
+            .file_service
+            .opendal
+            .admin_operator
+            .lister_with("")
+            .recursive(true)
+            .await?;
+        while let Some(file) = lister.try_next().await? {
+            if !file.metadata().is_file() {
+                continue;
+            }
+            report.files_checked += 1;
+            let has_entry = match file.path().parse::<EntryPath>() {
+                Ok(path) => {
+                    match EntryRepository::get_by_path(&path, &mut self.sql_db.pool().into()).await
+                    {
+                        Ok(_) => true,
+                        Err(sqlx::Error::RowNotFound) => false,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                Err(_) => false,
+            };
+            if !has_entry {
+                report.orphan_files.push(file.path().to_string());
+            }
+            if report.files_checked.is_multiple_of(SCRUB_BATCH_SIZE as u64) {
+                progress(report);
+            }
+        }
+        progress(report);
+        Ok(())
+    }
+
+    async fn scrub_used_bytes(
+        &self,
+        options: ScrubOptions,
+        report: &mut StorageScrubReport,
+        progress: &impl Fn(&StorageScrubReport),
+    ) -> Result<(), FileIoError> {
+        let mut cursor = None;
+        loop {
+            let page = self
+                .user_service
+                .list(UserListQuery {
+                    limit: Some(SCRUB_BATCH_SIZE),
+                    cursor,
+                    ..Default::default()
+                })
+                .await?;
+            for user in &page.items {
+                report.users_checked += 1;
+                if let Some(mismatch) = self.check_used_bytes(user, options.repair).await? {
+                    if options.repair {
+                        report.repaired += 1;
+                    }
+                    report.used_bytes_mismatches.push(mismatch);
+                }
+            }
+            progress(report);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Compare `used_bytes` with the user's entries under the user's row lock,
+    /// so writes in flight can't skew the sum. With `repair` the difference is fixed.
+    async fn check_used_bytes(
+        &self,
+        user: &UserEntity,
+        repair: bool,
+    ) -> Result<Option<UsedBytesMismatch>, sqlx::Error> {
+        let root = StoragePath::new("/").expect("root is a valid storage path");
+        let mut tx = self.sql_db.pool().begin().await?;
+        let mut user = match self
+            .user_service
+            .get_for_no_key_update(&user.public_key, uexecutor!(tx))
+            .await
+        {
+            Ok(user) => user,
+            Err(sqlx::Error::RowNotFound) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let usage = EntryRepository::usage_below(user.id, &root, uexecutor!(tx)).await?;
+        let actual = entries_used_bytes(&usage);
+        if actual == user.used_bytes {
+            return Ok(None);
+        }
+        let mismatch = UsedBytesMismatch {
+            public_key: user.public_key.z32(),
+            recorded: user.used_bytes,
+            actual,
+        };
+        if repair {
+            PathQuotaRepository::recount_used_bytes(user.id, uexecutor!(tx)).await?;
+            user.used_bytes = actual;
+            self.user_service
+                .update_in_tx(&user, uexecutor!(tx))
+                .await?;
+            tx.commit().await?;
+            tracing::warn!(
+                "Scrub corrected used bytes of {} from {} to {}",
+                mismatch.public_key,
+                mismatch.recorded,
+                actual
+            );
+        }
+        Ok(Some(mismatch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opendal::Buffer;
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::storage_config::StorageConfigToml;
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_scrub_reports_and_repairs() {
+        let context =
+            AppContext::test_with_config(|c| c.storage.backend = StorageConfigToml::FileSystem)
+                .await;
+        let pubkey = Keypair::random().public_key();
+        let user = context.user_service.create(&pubkey).await.unwrap();
+        let file_service = context.file_service.clone();
+        let service = StorageIntegrityService::new(
+            context.sql_db.clone(),
+            file_service.clone(),
+            context.user_service.clone(),
+        );
+
+        let mut paths = Vec::new();
+        for name in ["ok", "missing", "resized", "changed"] {
+            let path = EntryPath::new(
+                pubkey.clone(),
+                StoragePath::new(&format!("/pub/test.app/{name}.txt")).unwrap(),
+            );
+            file_service
+                .write(&path, Buffer::from(b"hello".to_vec()))
+                .await
+                .unwrap();
+            paths.push(path);
+        }
+        let clean = service
+            .scrub(
+                ScrubOptions {
+                    rehash: true,
+                    repair: false,
+                },
+                |_| {},
+            )
+            .await
+            .unwrap();
+        assert!(clean.is_ok());
+        assert_eq!(
+            (clean.checked, clean.files_checked, clean.users_checked),
+            (4, 4, 1)
+        );
+
+        // Change the files and the usage behind the database's back.
+        let files_dir = context.data_dir.path().join("data/files");
+        std::fs::remove_file(files_dir.join(paths[1].as_str())).unwrap();
+        std::fs::write(files_dir.join(paths[2].as_str()), b"hello world").unwrap();
+        std::fs::write(files_dir.join(paths[3].as_str()), b"HELLO").unwrap();
+        let orphan = format!("{}/pub/test.app/orphan.txt", pubkey.z32());
+        std::fs::write(files_dir.join(&orphan), b"orphan").unwrap();
+        sqlx::query("UPDATE users SET used_bytes = 1 WHERE id = $1")
+            .bind(user.id)
+            .execute(context.sql_db.pool())
+            .await
+            .unwrap();
+
+        let report = service
+            .scrub(ScrubOptions::default(), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(report.missing_files, vec![paths[1].to_string()]);
+        assert_eq!(
+            report.size_mismatches,
+            vec![SizeMismatch {
+                path: paths[2].to_string(),
+                expected: 5,
+                actual: 11,
+            }]
+        );
+        // Same size, so only found by rehashing.
+        assert!(report.hash_mismatches.is_empty());
+        assert_eq!(report.orphan_files, vec![orphan.clone()]);
+        assert_eq!(report.used_bytes_mismatches.len(), 1);
+        assert_eq!(report.used_bytes_mismatches[0].recorded, 1);
+        assert_eq!(report.repaired, 0);
+
+        let progress = std::sync::Mutex::new(0);
+        let report = service
+            .scrub(
+                ScrubOptions {
+                    rehash: true,
+                    repair: true,
+                },
+                |_| *progress.lock().unwrap() += 1,
+            )
+            .await
+            .unwrap();
+        assert!(*progress.lock().unwrap() >= 3);
+        assert_eq!(report.hash_mismatches.len(), 1);
+        assert_eq!(report.hash_mismatches[0].path, paths[3].to_string());
+        // Three entries, then the usage that is still off by the tampered value.
+        assert_eq!(report.repaired, 4);
+
+        let report = service
+            .scrub(
+                ScrubOptions {
+                    rehash: true,
+                    repair: false,
+                },
+                |_| {},
+            )
+            .await
+            .unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.orphan_files, vec![orphan]);
+        assert_eq!(report.problem_count(), 1);
+
+        let user = context.user_service.get(&pubkey).await.unwrap();
+        assert_eq!(user.used_bytes, 5 + 11 + 5 + 3 * FILE_METADATA_SIZE);
+        let entry = EntryRepository::get_by_path(&paths[3], &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+        assert_eq!(entry.content_hash, pubky_common::crypto::hash(b"HELLO"));
+    }
+}
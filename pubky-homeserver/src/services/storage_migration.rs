@@ -0,0 +1,512 @@
+//! Storage migration — copies every file to another storage backend.
+//!
+//! A migration runs in batches so it can be interrupted and resumed from its
+//! [`StorageMigrationState`]:
+//!
+//! 1. **Copy**: the file of every entry is copied from the source to the target
+//!    backend and its hash compared to the entry.
+//! 2. **Replay**: homeservers keep writing to the source during the copy. Every
+//!    path in an event after the cursor taken at the start is synced again: copied
+//!    if it has an entry, deleted from the target otherwise. If event retention
+//!    pruned events the replay has not seen yet, it fails with
+//!    [`StorageMigrationError::EventsPruned`] and the copy has to run again.
+//!
+//! Files are copied to a staging key first and only moved into place under the
+//! user's row lock, if their entry did not change during the copy. Once homeservers
+//! write to the target, this keeps a slow copy from replacing a newer file.
+//!
+//! Files that did not match their entry are retried after every replay. They are
+//! usually files that were written while they were copied; the ones that keep
+//! failing need a `storage verify`.
+
+use std::collections::BTreeSet;
+
+use futures_util::TryStreamExt;
+use opendal::Operator;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::DEFAULT_MAX_LIST_LIMIT;
+use crate::persistence::files::{
+    events::{EventCursor, EventRepository, EventVisibility},
+    FileIoError, FileMetadataBuilder, CHUNK_SIZE,
+};
+use crate::persistence::sql::{
+    entry::{EntryEntity, EntryRepository},
+    uexecutor,
+    user::UserRepository,
+    SqlDb,
+};
+use crate::shared::webdav::EntryPath;
+use crate::storage_config::StorageConfigToml;
+
+/// Number of entries copied per batch.
+const MIGRATION_BATCH_SIZE: u16 = 100;
+
+/// Prefix of the keys files are copied to before being moved into place. Entry
+/// paths start with a public key, so they never fall under it.
+const STAGING_PREFIX: &str = ".storage-migration/";
+
+/// Progress of a storage migration. Saved between batches to resume it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageMigrationState {
+    /// Backend the files are copied from.
+    pub source: StorageConfigToml,
+    /// Backend the files are copied to.
+    pub target: StorageConfigToml,
+    /// Last entry ID whose file was copied.
+    pub entry_cursor: Option<i64>,
+    /// Whether every entry was copied once.
+    pub copied_all: bool,
+    /// Last event whose path was synced. Taken before the copy started.
+    pub event_cursor: u64,
+    /// Paths whose file did not match their entry, to be synced again.
+    pub retry: BTreeSet<String>,
+    /// Whether the config was switched to the target.
+    pub switched: bool,
+    /// Number of files copied.
+    pub files_copied: u64,
+    /// Number of bytes copied.
+    pub bytes_copied: u64,
+}
+
+impl StorageMigrationState {
+    /// A migration that starts with the events after `event_cursor`.
+    pub fn new(source: StorageConfigToml, target: StorageConfigToml, event_cursor: u64) -> Self {
+        Self {
+            source,
+            target,
+            entry_cursor: None,
+            copied_all: false,
+            event_cursor,
+            retry: BTreeSet::new(),
+            switched: false,
+            files_copied: 0,
+            bytes_copied: 0,
+        }
+    }
+
+    /// Copy every file again, replaying the events after `event_cursor` afterwards.
+    pub fn restart_copy(&mut self, event_cursor: u64) {
+        self.entry_cursor = None;
+        self.copied_all = false;
+        self.event_cursor = event_cursor;
+    }
+}
+
+/// Errors of a storage migration.
+#[derive(Debug, thiserror::Error)]
+pub enum StorageMigrationError {
+    #[error(transparent)]
+    FileIo(#[from] FileIoError),
+    /// Event retention deleted events after the replay cursor, so writes the
+    /// target has not seen can no longer be replayed.
+    #[error("Events after {cursor} were pruned by event retention (through {pruned_through}) before the migration replayed them")]
+    EventsPruned { cursor: u64, pruned_through: u64 },
+}
+
+impl From<sqlx::Error> for StorageMigrationError {
+    fn from(e: sqlx::Error) -> Self {
+        Self::FileIo(e.into())
+    }
+}
+
+/// Copies the files of all entries from one storage backend to another.
+#[derive(Debug, Clone)]
+pub struct StorageMigrationService {
+    sql_db: SqlDb,
+    source: Operator,
+    target: Operator,
+}
+
+impl StorageMigrationService {
+    /// `source` and `target` are bare backend operators, so writing the target
+    /// doesn't create entries or events.
+    pub fn new(sql_db: SqlDb, source: Operator, target: Operator) -> Self {
+        Self {
+            sql_db,
+            source,
+            target,
+        }
+    }
+
+    /// Copy the files of the next batch of entries.
+    /// Returns `false` once every entry was copied.
+    pub async fn copy_next_batch(
+        &self,
+        state: &mut StorageMigrationState,
+    ) -> Result<bool, FileIoError> {
+        let entries = EntryRepository::list_page(
+            state.entry_cursor,
+            MIGRATION_BATCH_SIZE,
+            &mut self.sql_db.pool().into(),
+        )
+        .await?;
+        let Some(last) = entries.last() else {
+            state.copied_all = true;
+            return Ok(false);
+        };
+        state.entry_cursor = Some(last.id);
+        for entry in entries {
+            if self.copy_file(&entry).await? {
+                state.files_copied += 1;
+                state.bytes_copied += entry.content_length;
+            } else {
+                state.retry.insert(entry.path.to_string());
+            }
+        }
+        Ok(true)
+    }
+
+    /// Sync the paths of the next batch of events, then retry the files that did
+    /// not match. Returns `false` once there are no new events.
+    pub async fn replay_next_batch(
+        &self,
+        state: &mut StorageMigrationState,
+    ) -> Result<bool, StorageMigrationError> {
+        let events = EventRepository::get_by_cursor(
+            Some(EventCursor::new(state.event_cursor)),
+            Some(DEFAULT_MAX_LIST_LIMIT),
+            EventVisibility::All,
+            &mut self.sql_db.pool().into(),
+        )
+        .await?;
+        // Checked after the fetch: pruning that ran before or during the read has
+        // advanced the watermark by now, so a batch with gaps is never replayed.
+        let pruned_through =
+            EventRepository::get_max_pruned_through(&mut self.sql_db.pool().into()).await?;
+        if let Some(pruned_through) = pruned_through {
+            if state.event_cursor < pruned_through.id() {
+                return Err(StorageMigrationError::EventsPruned {
+                    cursor: state.event_cursor,
+                    pruned_through: pruned_through.id(),
+                });
+            }
+        }
+        let Some(last) = events.last() else {
+            return Ok(false);
+        };
+        let cursor = last.id;
+        let paths: BTreeSet<String> = events.iter().map(|e| e.path.to_string()).collect();
+        state.retry.extend(paths);
+        self.retry(state).await?;
+        state.event_cursor = cursor;
+        Ok(true)
+    }
+
+    /// Sync the paths in `state.retry` again, keeping the ones that still fail.
+    pub async fn retry(&self, state: &mut StorageMigrationState) -> Result<(), FileIoError> {
+        let mut failed = BTreeSet::new();
+        for path in std::mem::take(&mut state.retry) {
+            let entry_path: EntryPath = match path.parse() {
+                Ok(entry_path) => entry_path,
+                Err(e) => {
+                    tracing::warn!("Skipping invalid path {path} in storage migration: {e}");
+                    continue;
+                }
+            };
+            if !self.sync_path(&entry_path, state).await? {
+                failed.insert(path);
+            }
+        }
+        state.retry = failed;
+        Ok(())
+    }
+
+    /// Make the target match the entry of `path`. Returns `false` if neither the
+    /// source nor the target file match it.
+    async fn sync_path(
+        &self,
+        path: &EntryPath,
+        state: &mut StorageMigrationState,
+    ) -> Result<bool, FileIoError> {
+        let entry = match EntryRepository::get_by_path(path, &mut self.sql_db.pool().into()).await {
+            Ok(entry) => entry,
+            Err(sqlx::Error::RowNotFound) => {
+                self.delete_unreferenced(path).await?;
+                return Ok(true);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // Once homeservers use the target, it has newer files than the source.
+        if Self::matches(&self.target, &entry).await? {
+            return Ok(true);
+        }
+        if self.copy_file(&entry).await? {
+            state.files_copied += 1;
+            state.bytes_copied += entry.content_length;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Copy the file of `entry` to the target. Returns `false` if the copy doesn't
+    /// match the entry, or the entry changed while copying.
+    async fn copy_file(&self, entry: &EntryEntity) -> Result<bool, FileIoError> {
+        let path = entry.path.as_str();
+        let reader = match self.source.reader_with(path).chunk(CHUNK_SIZE).await {
+            Ok(reader) => reader,
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let staging = format!("{STAGING_PREFIX}{path}");
+        let mut stream = reader.into_bytes_stream(0..).await?;
+        let mut writer = self.target.writer(&staging).await?;
+        let mut metadata_builder = FileMetadataBuilder::default();
+        let copied: Result<(), FileIoError> = async {
+            while let Some(chunk) = stream.try_next().await? {
+                metadata_builder.update(&chunk);
+                writer.write(chunk).await?;
+            }
+            Ok(())
+        }
+        .await;
+        if let Err(e) = copied {
+            writer.abort().await?;
+            return Err(e);
+        }
+        writer.close().await?;
+
+        let metadata = metadata_builder.finalize();
+        let installed = metadata.hash == entry.content_hash
+            && metadata.length as u64 == entry.content_length
+            && self.install(entry, &staging).await?;
+        self.target.delete(&staging).await?;
+        Ok(installed)
+    }
+
+    /// Move the copy at `staging` to the path of `entry`, if the entry is unchanged.
+    ///
+    /// Writes lock the user row while they store a file, so holding the lock keeps
+    /// a write to the target from landing between the check and the move.
+    async fn install(&self, entry: &EntryEntity, staging: &str) -> Result<bool, FileIoError> {
+        let mut tx = self.sql_db.pool().begin().await?;
+        match UserRepository::get_for_no_key_update(entry.path.pubkey(), uexecutor!(tx)).await {
+            Ok(_) => {}
+            Err(sqlx::Error::RowNotFound) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        let current = match EntryRepository::get_by_path(&entry.path, uexecutor!(tx)).await {
+            Ok(current) => current,
+            Err(sqlx::Error::RowNotFound) => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        if current.content_hash != entry.content_hash || current.modified_at != entry.modified_at {
+            return Ok(false);
+        }
+        self.promote(staging, entry.path.as_str()).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Delete the target file of `path` if no entry refers to it, under the user's
+    /// row lock so a write in flight keeps its file.
+    async fn delete_unreferenced(&self, path: &EntryPath) -> Result<(), FileIoError> {
+        let mut tx = self.sql_db.pool().begin().await?;
+        match UserRepository::get_for_no_key_update(path.pubkey(), uexecutor!(tx)).await {
+            Ok(_) | Err(sqlx::Error::RowNotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+        match EntryRepository::get_by_path(path, uexecutor!(tx)).await {
+            Ok(_) => return Ok(()),
+            Err(sqlx::Error::RowNotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+        self.target.delete(path.as_str()).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Move `from` to `to` on the target, with whatever the backend supports.
+    async fn promote(&self, from: &str, to: &str) -> Result<(), FileIoError> {
+        let capability = self.target.info().full_capability();
+        if capability.rename {
+            self.target.rename(from, to).await?;
+        } else if capability.copy {
+            self.target.copy(from, to).await?;
+        } else {
+            let content = self.target.read(from).await?;
+            self.target.write(to, content).await?;
+        }
+        Ok(())
+    }
+
+    /// Whether `operator` has the file of `entry`.
+    async fn matches(operator: &Operator, entry: &EntryEntity) -> Result<bool, FileIoError> {
+        let path = entry.path.as_str();
+        match operator.stat(path).await {
+            Ok(metadata) if metadata.content_length() == entry.content_length => {}
+            Ok(_) => return Ok(false),
+            Err(e) if e.kind() == opendal::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        let mut stream = operator
+            .reader_with(path)
+            .chunk(CHUNK_SIZE)
+            .await?
+            .into_bytes_stream(0..)
+            .await?;
+        let mut metadata_builder = FileMetadataBuilder::default();
+        while let Some(chunk) = stream.try_next().await? {
+            metadata_builder.update(&chunk);
+        }
+        Ok(metadata_builder.finalize().hash == entry.content_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opendal::Buffer;
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::persistence::files::build_backend_operator;
+    use crate::shared::webdav::StoragePath;
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_copy_and_replay_writes() {
+        let context =
+            AppContext::test_with_config(|c| c.storage.backend = StorageConfigToml::FileSystem)
+                .await;
+        let pubkey = Keypair::random().public_key();
+        context.user_service.create(&pubkey).await.unwrap();
+        let path = |name: &str| {
+            EntryPath::new(
+                pubkey.clone(),
+                StoragePath::new(&format!("/pub/test.app/{name}")).unwrap(),
+            )
+        };
+        let write = |name: &'static str, content: &'static str| {
+            let file_service = context.file_service.clone();
+            let path = path(name);
+            async move {
+                file_service
+                    .write(&path, Buffer::from(content.as_bytes()))
+                    .await
+                    .unwrap();
+            }
+        };
+        for name in ["a.txt", "b.txt", "tampered.txt"] {
+            write(name, "hello").await;
+        }
+        let source =
+            build_backend_operator(&StorageConfigToml::FileSystem, context.data_dir.path())
+                .unwrap();
+        let target = Operator::new(opendal::services::Memory::default())
+            .unwrap()
+            .finish();
+        let service =
+            StorageMigrationService::new(context.sql_db.clone(), source.clone(), target.clone());
+        let mut state = StorageMigrationState::new(
+            StorageConfigToml::FileSystem,
+            StorageConfigToml::InMemory,
+            EventRepository::get_max_id(&mut context.sql_db.pool().into())
+                .await
+                .unwrap(),
+        );
+
+        // Changed behind the database's back.
+        source
+            .write(path("tampered.txt").as_str(), "HELLO")
+            .await
+            .unwrap();
+
+        // Files written while copying are picked up by the replay.
+        assert!(service.copy_next_batch(&mut state).await.unwrap());
+        write("a.txt", "changed").await;
+        context.file_service.delete(&path("b.txt")).await.unwrap();
+        write("c.txt", "new").await;
+        while service.copy_next_batch(&mut state).await.unwrap() {}
+        assert!(state.copied_all);
+        assert_eq!((state.files_copied, state.bytes_copied), (3, 13));
+        assert_eq!(
+            state.retry,
+            BTreeSet::from([path("tampered.txt").to_string()])
+        );
+
+        while service.replay_next_batch(&mut state).await.unwrap() {}
+        assert!(!service.replay_next_batch(&mut state).await.unwrap());
+        let read = |name: &str| {
+            let target = target.clone();
+            let path = path(name);
+            async move { target.read(path.as_str()).await.map(|b| b.to_vec()) }
+        };
+        assert_eq!(read("a.txt").await.unwrap(), b"changed");
+        assert!(read("b.txt").await.is_err());
+        assert_eq!(read("c.txt").await.unwrap(), b"new");
+        assert!(read("tampered.txt").await.is_err());
+        assert_eq!(
+            state.retry,
+            BTreeSet::from([path("tampered.txt").to_string()])
+        );
+
+        // A file already written to the target is not overwritten by the source.
+        target
+            .write(path("tampered.txt").as_str(), "hello")
+            .await
+            .unwrap();
+        service.retry(&mut state).await.unwrap();
+        assert!(state.retry.is_empty());
+        assert_eq!(read("tampered.txt").await.unwrap(), b"hello");
+
+        // Events pruned before they were replayed stop the replay.
+        write("d.txt", "pruned").await;
+        write("e.txt", "kept").await;
+        EventRepository::delete_exceeding_per_user(1, 100, &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+        assert!(matches!(
+            service.replay_next_batch(&mut state).await,
+            Err(StorageMigrationError::EventsPruned { .. })
+        ));
+    }
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_stale_copy_does_not_replace_newer_file() {
+        let context =
+            AppContext::test_with_config(|c| c.storage.backend = StorageConfigToml::FileSystem)
+                .await;
+        let pubkey = Keypair::random().public_key();
+        context.user_service.create(&pubkey).await.unwrap();
+        let path = EntryPath::new(pubkey, StoragePath::new("/pub/test.app/a.txt").unwrap());
+        context
+            .file_service
+            .write(&path, Buffer::from(b"old".to_vec()))
+            .await
+            .unwrap();
+        let source =
+            build_backend_operator(&StorageConfigToml::FileSystem, context.data_dir.path())
+                .unwrap();
+        let target = Operator::new(opendal::services::Memory::default())
+            .unwrap()
+            .finish();
+        let service = StorageMigrationService::new(context.sql_db.clone(), source, target.clone());
+        let stale = EntryRepository::get_by_path(&path, &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+
+        // The entry changes while the old version is being copied.
+        context
+            .file_service
+            .write(&path, Buffer::from(b"new".to_vec()))
+            .await
+            .unwrap();
+        target.write(path.as_str(), "new").await.unwrap();
+        let staging = format!("{STAGING_PREFIX}{path}");
+        target.write(&staging, "old").await.unwrap();
+        assert!(!service.install(&stale, &staging).await.unwrap());
+        assert_eq!(target.read(path.as_str()).await.unwrap().to_vec(), b"new");
+
+        // A file with an entry is never deleted.
+        service.delete_unreferenced(&path).await.unwrap();
+        assert_eq!(target.read(path.as_str()).await.unwrap().to_vec(), b"new");
+
+        let current = EntryRepository::get_by_path(&path, &mut context.sql_db.pool().into())
+            .await
+            .unwrap();
+        target.write(&staging, "new").await.unwrap();
+        assert!(service.install(&current, &staging).await.unwrap());
+    }
+}
@@ -0,0 +1,347 @@
+//! Background storage scrubbing.
+//!
+//! Runs a [`StorageIntegrityService::scrub`] every `[storage].scrub_interval` seconds
+//! with the `scrub_rehash` and `scrub_repair` options of the config, and whenever
+//! triggered through the admin API with options of the caller's choice.
+//!
+//! A session-level advisory lock makes sure only one homeserver instance scrubs
+//! at a time.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::data_directory::storage_config::StorageToml;
+use crate::observability::Metrics;
+use crate::persistence::files::FileIoError;
+use crate::services::storage_integrity::{
+    ScrubOptions, StorageIntegrityService, StorageScrubReport,
+};
+
+/// Observable state of the scrub job, exposed through the admin API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageScrubStatus {
+    /// Whether a scrub is currently in progress.
+    pub running: bool,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    /// Error of the last scrub, if it failed.
+    pub last_error: Option<String>,
+    /// Options of the current or last scrub.
+    pub options: ScrubOptions,
+    /// Counters of the current scrub, or of the last one.
+    pub progress: StorageScrubProgress,
+    /// Result of the last finished scrub.
+    pub run: StorageScrubReport,
+}
+
+/// Counters of a scrub in progress.
+///
+/// Published after every batch instead of the full [`StorageScrubReport`],
+/// whose problem lists are only kept in the final report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct StorageScrubProgress {
+    /// Number of entries checked.
+    pub checked: u64,
+    /// Number of files in the storage backend checked for an entry.
+    pub files_checked: u64,
+    /// Number of users whose `used_bytes` was checked.
+    pub users_checked: u64,
+    /// Number of problems found so far, repaired or not.
+    pub problems: usize,
+    /// Number of entries and users fixed in the database.
+    pub repaired: u64,
+}
+
+impl StorageScrubProgress {
+    fn total_checked(&self) -> u64 {
+        self.checked + self.files_checked + self.users_checked
+    }
+}
+
+impl From<&StorageScrubReport> for StorageScrubProgress {
+    fn from(report: &StorageScrubReport) -> Self {
+        Self {
+            checked: report.checked,
+            files_checked: report.files_checked,
+            users_checked: report.users_checked,
+            problems: report.problem_count(),
+            repaired: report.repaired,
+        }
+    }
+}
+
+/// Background job that periodically scrubs the storage.
+///
+/// The task is aborted when the job is dropped.
+pub struct StorageScrubJob {
+    handle: Option<JoinHandle<()>>,
+    trigger: Arc<Notify>,
+    /// Options of the next triggered scrub.
+    requested: Arc<Mutex<Option<ScrubOptions>>>,
+    status: Arc<Mutex<StorageScrubStatus>>,
+}
+
+impl StorageScrubJob {
+    /// Advisory lock ID used to allow only one scrub across all instances.
+    const SCRUB_LOCK_ID: i64 = 0x73746f72_61676501; // "storage" + 1
+
+    /// Start the scrub job.
+    ///
+    /// Runs every `scrub_interval` seconds, and whenever [`Self::trigger`] is called.
+    /// With an interval of 0 it only runs when triggered.
+    #[must_use = "the job stops when dropped"]
+    pub fn start(
+        pool: &PgPool,
+        service: StorageIntegrityService,
+        config: &StorageToml,
+        metrics: Metrics,
+    ) -> Self {
+        let scheduled_options = ScrubOptions {
+            rehash: config.scrub_rehash,
+            repair: config.scrub_repair,
+        };
+        let trigger = Arc::new(Notify::new());
+        let requested = Arc::new(Mutex::new(None));
+        let status = Arc::new(Mutex::new(StorageScrubStatus::default()));
+        let handle = {
+            let pool = pool.clone();
+            let interval = Duration::from_secs(config.scrub_interval);
+            let trigger = trigger.clone();
+            let requested = requested.clone();
+            let status = status.clone();
+            tokio::spawn(async move {
+                let next_options = || {
+                    requested
+                        .lock()
+                        .expect("requested lock poisoned")
+                        .take()
+                        .unwrap_or(scheduled_options)
+                };
+                loop {
+                    if interval.is_zero() {
+                        trigger.notified().await;
+                    } else {
+                        tokio::select! {
+                            _ = tokio::time::sleep(interval) => {}
+                            _ = trigger.notified() => {}
+                        }
+                    }
+                    Self::run(&pool, &service, next_options(), &status, &metrics).await;
+                }
+            })
+        };
+        Self {
+            handle: Some(handle),
+            trigger,
+            requested,
+            status,
+        }
+    }
+
+    /// Request a scrub with `options` as soon as possible.
+    /// A request during a scrub schedules another one.
+    pub fn trigger(&self, options: ScrubOptions) {
+        *self.requested.lock().expect("requested lock poisoned") = Some(options);
+        self.trigger.notify_one();
+    }
+
+    /// Snapshot of the current job state.
+    pub fn status(&self) -> StorageScrubStatus {
+        self.status.lock().expect("status lock poisoned").clone()
+    }
+
+    async fn run(
+        pool: &PgPool,
+        service: &StorageIntegrityService,
+        options: ScrubOptions,
+        status: &Mutex<StorageScrubStatus>,
+        metrics: &Metrics,
+    ) {
+        {
+            let mut status = status.lock().expect("status lock poisoned");
+            status.running = true;
+            status.last_started_at = Some(Utc::now());
+            status.options = options;
+        }
+        let started = Instant::now();
+        let result = Self::run_once(pool, service, options, status, metrics).await;
+        let mut status = status.lock().expect("status lock poisoned");
+        status.running = false;
+        status.last_finished_at = Some(Utc::now());
+        match result {
+            Ok(Some(report)) => {
+                metrics.record_storage_scrub_run(started.elapsed().as_millis());
+                for (kind, count) in [
+                    ("missing_file", report.missing_files.len()),
+                    ("size_mismatch", report.size_mismatches.len()),
+                    ("hash_mismatch", report.hash_mismatches.len()),
+                    ("orphan_file", report.orphan_files.len()),
+                    ("used_bytes_mismatch", report.used_bytes_mismatches.len()),
+                ] {
+                    if count > 0 {
+                        metrics.record_storage_scrub_problems(kind, count as u64);
+                    }
+                }
+                metrics.record_storage_scrub_repaired(report.repaired);
+                if !report.is_ok() {
+                    tracing::warn!(
+                        "Storage scrub found {} problems and repaired {}",
+                        report.problem_count(),
+                        report.repaired
+                    );
+                }
+                status.last_error = None;
+                status.progress = StorageScrubProgress::from(&report);
+                status.run = report;
+            }
+            Ok(None) => status.last_error = None,
+            Err(e) => {
+                tracing::error!("Storage scrub failed: {}", e);
+                status.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Scrub once, publishing its counters to `status` after every batch.
+    ///
+    /// Returns `None` without scrubbing if another instance holds the scrub lock.
+    pub(crate) async fn run_once(
+        pool: &PgPool,
+        service: &StorageIntegrityService,
+        options: ScrubOptions,
+        status: &Mutex<StorageScrubStatus>,
+        metrics: &Metrics,
+    ) -> Result<Option<StorageScrubReport>, FileIoError> {
+        // Session-level lock, held on a dedicated connection for the whole run.
+        let mut lock_con = pool.acquire().await?;
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(Self::SCRUB_LOCK_ID)
+            .fetch_one(&mut *lock_con)
+            .await?;
+        if !locked {
+            tracing::debug!("Storage scrub is already running on another instance");
+            return Ok(None);
+        }
+
+        status.lock().expect("status lock poisoned").progress = StorageScrubProgress::default();
+        let result = service
+            .scrub(options, |report| {
+                let progress = StorageScrubProgress::from(report);
+                let mut status = status.lock().expect("status lock poisoned");
+                metrics.record_storage_scrub_checked(
+                    progress.total_checked() - status.progress.total_checked(),
+                );
+                status.progress = progress;
+            })
+            .await;
+
+        let unlocked = sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(Self::SCRUB_LOCK_ID)
+            .execute(&mut *lock_con)
+            .await;
+        if let Err(e) = unlocked {
+            // Closing the connection releases the lock.
+            tracing::warn!("Failed to release the storage scrub lock: {}", e);
+            drop(lock_con.detach());
+        }
+        result.map(Some)
+    }
+}
+
+impl Drop for StorageScrubJob {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppContext;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_run_once_skips_when_locked_elsewhere() {
+        let context = AppContext::test().await;
+        let service = StorageIntegrityService::new(
+            context.sql_db.clone(),
+            context.file_service.clone(),
+            context.user_service.clone(),
+        );
+        let pubkey = pubky_common::crypto::Keypair::random().public_key();
+        context.user_service.create(&pubkey).await.unwrap();
+        let last = StorageScrubReport {
+            users_checked: 7,
+            ..Default::default()
+        };
+        let status = Mutex::new(StorageScrubStatus {
+            progress: StorageScrubProgress::from(&last),
+            run: last.clone(),
+            ..Default::default()
+        });
+        let pool = context.sql_db.pool();
+
+        let mut other = pool.acquire().await.unwrap();
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(StorageScrubJob::SCRUB_LOCK_ID)
+            .execute(&mut *other)
+            .await
+            .unwrap();
+        let run = StorageScrubJob::run_once(
+            pool,
+            &service,
+            ScrubOptions::default(),
+            &status,
+            &context.metrics,
+        )
+        .await
+        .unwrap();
+        assert!(run.is_none());
+        // A skipped run keeps the last report.
+        StorageScrubJob::run(
+            pool,
+            &service,
+            ScrubOptions::default(),
+            &status,
+            &context.metrics,
+        )
+        .await;
+        {
+            let status = status.lock().unwrap();
+            assert!(!status.running);
+            assert_eq!(status.run, last);
+            assert_eq!(status.progress, StorageScrubProgress::from(&last));
+        }
+        sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(StorageScrubJob::SCRUB_LOCK_ID)
+            .execute(&mut *other)
+            .await
+            .unwrap();
+
+        let run = StorageScrubJob::run_once(
+            pool,
+            &service,
+            ScrubOptions::default(),
+            &status,
+            &context.metrics,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(run.users_checked, 1);
+        assert_eq!(
+            status.lock().unwrap().progress,
+            StorageScrubProgress::from(&run)
+        );
+        let metrics = context.metrics.render().unwrap();
+        assert!(metrics.contains("storage_scrub_checked_count"), "{metrics}");
+    }
+}
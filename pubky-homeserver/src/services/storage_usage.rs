@@ -0,0 +1,133 @@
+//! Storage usage breakdown — aggregates a user's entries by top-level directory
+//! (`/pub/`, `/priv/`) and app directory (`/pub/photos-app/`).
+//!
+//! Shared by the user-facing `GET /quota/usage` and the admin user detail view.
+
+use std::collections::HashMap;
+
+use pubky_common::storage_usage::{PrefixUsage, StorageUsage};
+
+use crate::persistence::files::write_finalization_layer::{
+    entries_used_bytes, resolve_storage_max_bytes,
+};
+use crate::persistence::sql::{
+    entry::{EntryPrefixUsage, EntryRepository},
+    path_quota::PathQuotaRepository,
+    user::UserEntity,
+    SqlDb,
+};
+
+/// Storage usage of `user` per directory and app, with the applicable limits.
+///
+/// `default_storage_mb` is the system default used when the user has no storage override.
+pub async fn storage_usage(
+    sql_db: &SqlDb,
+    user: &UserEntity,
+    default_storage_mb: Option<u64>,
+) -> Result<StorageUsage, sqlx::Error> {
+    let rows = EntryRepository::usage_by_prefix(user.id, &mut sql_db.pool().into()).await?;
+    let path_limits: HashMap<String, u64> =
+        PathQuotaRepository::list(&user.public_key, &mut sql_db.pool().into())
+            .await?
+            .into_iter()
+            .map(|quota| {
+                let limit_bytes = quota.max_mb.saturating_mul(1024 * 1024);
+                (quota.path.as_str().to_string(), limit_bytes)
+            })
+            .collect();
+
+    Ok(StorageUsage {
+        used_bytes: user.used_bytes,
+        limit_bytes: resolve_storage_max_bytes(user, default_storage_mb),
+        directories: group_by_directory(rows, &path_limits),
+    })
+}
+
+/// Fold the per-app rows into one usage per top-level directory.
+///
+/// Rows must be ordered by directory and app. Files at the root are reported
+/// under `/`.
+fn group_by_directory(
+    rows: Vec<EntryPrefixUsage>,
+    path_limits: &HashMap<String, u64>,
+) -> Vec<PrefixUsage> {
+    let empty = |path: String| PrefixUsage {
+        limit_bytes: path_limits.get(&path).copied(),
+        path,
+        used_bytes: 0,
+        file_count: 0,
+        last_modified: 0,
+        apps: Vec::new(),
+    };
+
+    let mut directories: Vec<PrefixUsage> = Vec::new();
+    for row in rows {
+        let path = row.directory.unwrap_or_else(|| "/".to_string());
+        if directories.last().is_none_or(|last| last.path != path) {
+            directories.push(empty(path));
+        }
+        let directory = directories.last_mut().expect("pushed above");
+
+        let used_bytes = entries_used_bytes(&row.usage);
+        let last_modified = row.last_modified.and_utc().timestamp().max(0) as u64;
+        directory.used_bytes = directory.used_bytes.saturating_add(used_bytes);
+        directory.file_count = directory.file_count.saturating_add(row.usage.file_count);
+        directory.last_modified = directory.last_modified.max(last_modified);
+        if let Some(app) = row.app {
+            directory.apps.push(PrefixUsage {
+                used_bytes,
+                file_count: row.usage.file_count,
+                last_modified,
+                ..empty(app)
+            });
+        }
+    }
+    directories
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+    use crate::persistence::sql::entry::EntryUsage;
+
+    fn row(directory: Option<&str>, app: Option<&str>, bytes: u64, at: i64) -> EntryPrefixUsage {
+        EntryPrefixUsage {
+            directory: directory.map(str::to_string),
+            app: app.map(str::to_string),
+            usage: EntryUsage {
+                content_bytes: bytes,
+                file_count: 1,
+            },
+            last_modified: DateTime::from_timestamp(at, 0).unwrap().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn groups_apps_into_their_directory() {
+        let rows = vec![
+            row(None, None, 1, 10),
+            row(Some("/priv/"), Some("/priv/a/"), 5, 50),
+            row(Some("/pub/"), None, 2, 20),
+            row(Some("/pub/"), Some("/pub/a/"), 3, 40),
+            row(Some("/pub/"), Some("/pub/b/"), 4, 30),
+        ];
+        let limits = HashMap::from([("/pub/a/".to_string(), 1024)]);
+
+        let directories = group_by_directory(rows, &limits);
+        let paths: Vec<&str> = directories.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["/", "/priv/", "/pub/"]);
+
+        let public = &directories[2];
+        let overhead = crate::services::user_service::FILE_METADATA_SIZE;
+        assert_eq!(public.used_bytes, 9 + 3 * overhead);
+        assert_eq!(public.file_count, 3);
+        assert_eq!(public.last_modified, 40);
+        assert_eq!(public.limit_bytes, None);
+        assert_eq!(public.apps.len(), 2);
+        assert_eq!(public.apps[0].path, "/pub/a/");
+        assert_eq!(public.apps[0].limit_bytes, Some(1024));
+        assert!(directories[0].apps.is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! Usage service — accumulates per-user transfer and request counters in SQL.
+//!
+//! Counting happens on the hot path of every authenticated request, so counters
+//! are buffered in memory and flushed to the `user_usage` table in the background.
+//! Reads combine the flushed totals (cached briefly) with this instance's
+//! unflushed counters. Other instances' traffic becomes visible once they flushed
+//! and the cache entry expired, so caps may be overshot by a few seconds of traffic.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use dashmap::DashMap;
+use pubky_common::auth::grant_session_responses::{SessionUsage, TransferUsage};
+use pubky_common::crypto::PublicKey;
+use tokio::sync::RwLock;
+
+use crate::persistence::sql::user_usage::{UsageCounters, UsagePeriods, UserUsageRepository};
+use crate::persistence::sql::SqlDb;
+use crate::shared::user_quota::UserQuota;
+use crate::DefaultQuotasToml;
+
+/// How often buffered counters are written to the database.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long totals read from the database are reused.
+const CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Number of locks the users are spread over.
+const WRITE_LOCK_STRIPES: usize = 64;
+
+/// Flushed totals of a user, as read from the database.
+#[derive(Debug, Clone, Copy)]
+struct CachedUsage {
+    day: NaiveDate,
+    usage: UsagePeriods,
+    fetched_at: Instant,
+}
+
+impl CachedUsage {
+    fn is_fresh(&self, today: NaiveDate) -> bool {
+        self.day == today && self.fetched_at.elapsed() < CACHE_TTL
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    sql_db: SqlDb,
+    /// Counters not yet written to the database.
+    pending: DashMap<PublicKey, UsageCounters>,
+    /// Counters taken out of `pending` by a flush whose write has not landed yet.
+    flushing: DashMap<PublicKey, UsageCounters>,
+    /// Flushed totals per user.
+    cache: DashMap<PublicKey, CachedUsage>,
+    /// Striped by user. Held exclusively by a flush from the start of a user's write
+    /// until its counters left `flushing`, and shared by reads of that user's totals.
+    /// A read therefore never sees a landed write while its counters are still counted
+    /// as in flight, and a slow write only blocks the users sharing its stripe.
+    write_locks: Box<[RwLock<()>]>,
+}
+
+impl Inner {
+    fn write_lock(&self, pubkey: &PublicKey) -> &RwLock<()> {
+        let mut hasher = DefaultHasher::new();
+        pubkey.hash(&mut hasher);
+        &self.write_locks[hasher.finish() as usize % self.write_locks.len()]
+    }
+}
+
+/// Records and reports per-user transfer and request usage.
+#[derive(Debug, Clone)]
+pub struct UsageService {
+    inner: Arc<Inner>,
+}
+
+impl UsageService {
+    /// Create the service and spawn the background flush task.
+    /// The task self-terminates when the last clone is dropped (Weak::upgrade fails).
+    pub fn new(sql_db: SqlDb) -> Self {
+        let inner = Arc::new(Inner {
+            sql_db,
+            pending: DashMap::new(),
+            flushing: DashMap::new(),
+            cache: DashMap::new(),
+            write_locks: (0..WRITE_LOCK_STRIPES).map(|_| RwLock::new(())).collect(),
+        });
+
+        let weak: Weak<Inner> = Arc::downgrade(&inner);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            interval.tick().await; // skip first immediate tick
+            let mut pruned_on = None;
+            loop {
+                interval.tick().await;
+                let Some(inner) = weak.upgrade() else {
+                    break;
+                };
+                let service = Self { inner };
+                service.flush().await;
+                service
+                    .inner
+                    .cache
+                    .retain(|_, cached| cached.fetched_at.elapsed() < CACHE_TTL);
+
+                let today = today();
+                if pruned_on != Some(today) {
+                    pruned_on = Some(today);
+                    if let Err(e) = service.prune(today).await {
+                        tracing::warn!("Failed to prune old usage counters: {e}");
+                    }
+                }
+            }
+        });
+
+        Self { inner }
+    }
+
+    /// Add `counters` to the user's usage of today.
+    pub fn record(&self, pubkey: &PublicKey, counters: UsageCounters) {
+        if counters.is_empty() {
+            return;
+        }
+        self.inner
+            .pending
+            .entry(pubkey.clone())
+            .or_default()
+            .add(&counters);
+    }
+
+    /// The user's usage in the current UTC day and month, including unflushed counters.
+    pub async fn get(&self, pubkey: &PublicKey) -> Result<UsagePeriods, sqlx::Error> {
+        let today = today();
+        let _read = self.inner.write_lock(pubkey).read().await;
+        let cached = self
+            .inner
+            .cache
+            .get(pubkey)
+            .map(|entry| *entry)
+            .filter(|cached| cached.is_fresh(today));
+        let mut usage = match cached {
+            Some(cached) => cached.usage,
+            None => {
+                let usage =
+                    UserUsageRepository::get(pubkey, today, &mut self.inner.sql_db.pool().into())
+                        .await?;
+                self.inner.cache.insert(
+                    pubkey.clone(),
+                    CachedUsage {
+                        day: today,
+                        usage,
+                        fetched_at: Instant::now(),
+                    },
+                );
+                usage
+            }
+        };
+        if let Some(pending) = self.inner.pending.get(pubkey) {
+            usage.add(&pending);
+        }
+        if let Some(flushing) = self.inner.flushing.get(pubkey) {
+            usage.add(&flushing);
+        }
+        Ok(usage)
+    }
+
+    /// The user's usage against the request and transfer caps of `quota`, falling
+    /// back to `defaults` for caps without a per-user override.
+    pub async fn session_usage(
+        &self,
+        pubkey: &PublicKey,
+        quota: &UserQuota,
+        defaults: &DefaultQuotasToml,
+    ) -> Result<SessionUsage, sqlx::Error> {
+        let usage = self.get(pubkey).await?;
+        let transfer = |used_bytes: u64, limit_mb: Option<u64>| TransferUsage {
+            used_bytes,
+            limit_bytes: limit_mb.map(|mb| mb.saturating_mul(1024 * 1024)),
+        };
+        Ok(SessionUsage {
+            requests_per_minute: quota
+                .requests_per_minute
+                .resolve_with_default(defaults.requests_per_minute),
+            egress_daily: transfer(
+                usage.day.egress_bytes,
+                quota
+                    .egress_daily_mb
+                    .resolve_with_default(defaults.egress_daily_mb),
+            ),
+            egress_monthly: transfer(
+                usage.month.egress_bytes,
+                quota
+                    .egress_monthly_mb
+                    .resolve_with_default(defaults.egress_monthly_mb),
+            ),
+            ingress_daily: transfer(
+                usage.day.ingress_bytes,
+                quota
+                    .ingress_daily_mb
+                    .resolve_with_default(defaults.ingress_daily_mb),
+            ),
+            ingress_monthly: transfer(
+                usage.month.ingress_bytes,
+                quota
+                    .ingress_monthly_mb
+                    .resolve_with_default(defaults.ingress_monthly_mb),
+            ),
+        })
+    }
+
+    /// Write all buffered counters to the database.
+    ///
+    /// Counters are attributed to the day of the flush and stay counted by [`Self::get`]
+    /// until their write landed. Each write holds the user's write lock, so a concurrent
+    /// [`Self::get`] counts them either as in flight or as flushed, never both. Counters
+    /// that fail to be written are put back and retried on the next flush.
+    pub async fn flush(&self) {
+        let today = today();
+        let pubkeys: Vec<PublicKey> = self
+            .inner
+            .pending
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect();
+        for pubkey in pubkeys {
+            let _write = self.inner.write_lock(&pubkey).write().await;
+            let Some(counters) = self.take_pending(&pubkey) else {
+                continue;
+            };
+            let result = UserUsageRepository::add(
+                &pubkey,
+                today,
+                &counters,
+                &mut self.inner.sql_db.pool().into(),
+            )
+            .await;
+            match result {
+                Ok(()) => {
+                    self.inner.cache.remove(&pubkey);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to flush usage counters of {}: {e}", pubkey.z32());
+                    self.record(&pubkey, counters);
+                }
+            }
+            self.inner.flushing.remove(&pubkey);
+        }
+    }
+
+    /// Move the user's pending counters to `flushing` and return them.
+    ///
+    /// Both maps are updated while holding the `pending` entry, so [`Self::get`]
+    /// never misses the counters in between.
+    fn take_pending(&self, pubkey: &PublicKey) -> Option<UsageCounters> {
+        let dashmap::Entry::Occupied(entry) = self.inner.pending.entry(pubkey.clone()) else {
+            return None;
+        };
+        let counters = *entry.get();
+        self.inner.flushing.insert(pubkey.clone(), counters);
+        entry.remove();
+        Some(counters)
+    }
+
+    /// Delete counters that are not part of the current or the previous month anymore.
+    async fn prune(&self, today: NaiveDate) -> Result<u64, sqlx::Error> {
+        let keep_from = today
+            .with_day(1)
+            .and_then(|month_start| month_start.checked_sub_months(Months::new(1)))
+            .expect("previous month always exists");
+        UserUsageRepository::delete_before(keep_from, &mut self.inner.sql_db.pool().into()).await
+    }
+}
+
+/// The current UTC day. Transfer caps reset at UTC midnight.
+fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::services::user_service::UserService;
+
+    #[tokio::test]
+    #[pubky_test_utils::test]
+    async fn test_record_flush_and_get() {
+        let db = SqlDb::test().await;
+        let user_service = UserService::new(db.clone());
+        let pubkey = Keypair::random().public_key();
+        user_service.create(&pubkey).await.unwrap();
+        let service = UsageService::new(db.clone());
+        let counters = UsageCounters {
+            egress_bytes: 1000,
+            ingress_bytes: 10,
+            requests: 1,
+        };
+
+        // Unflushed counters are included.
+        service.record(&pubkey, counters);
+        let usage = service.get(&pubkey).await.unwrap();
+        assert_eq!(usage.day, counters);
+        assert_eq!(usage.month, counters);
+
+        // Flushed counters are persisted and still counted exactly once.
+        service.flush().await;
+        service.record(&pubkey, counters);
+        let usage = service.get(&pubkey).await.unwrap();
+        assert_eq!(usage.day.egress_bytes, 2000);
+        assert_eq!(usage.day.requests, 2);
+        service.flush().await;
+
+        // Another instance sees the persisted usage.
+        let other_instance = UsageService::new(db.clone());
+        let usage = other_instance.get(&pubkey).await.unwrap();
+        assert_eq!(usage.month.ingress_bytes, 20);
+    }
+}
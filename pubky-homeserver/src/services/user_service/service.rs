@@ -5,7 +5,9 @@
 
 use pubky_common::crypto::PublicKey;
 
-use crate::persistence::sql::user::{UserEntity, UserOverview, UserRepository};
+use crate::persistence::sql::user::{
+    UserEntity, UserListPage, UserListQuery, UserOverview, UserRepository,
+};
 use crate::persistence::sql::{uexecutor, SqlDb, UnifiedExecutor};
 use crate::shared::user_quota::{UserQuota, UserQuotaPatch};
 use crate::shared::{HttpError, HttpResult};
@@ -76,9 +78,9 @@ impl UserService {
         UserRepository::get_id(pubkey, &mut self.sql_db.pool().into()).await
     }
 
-    /// Get all user entities.
-    pub async fn get_all(&self) -> Result<Vec<UserEntity>, sqlx::Error> {
-        UserRepository::get_all(&mut self.sql_db.pool().into()).await
+    /// List users matching the filters, one page at a time.
+    pub async fn list(&self, query: UserListQuery) -> Result<UserListPage, sqlx::Error> {
+        UserRepository::list(query, &mut self.sql_db.pool().into()).await
     }
 
     /// Get an overview of all users (counts, total disk usage).
@@ -117,6 +119,17 @@ impl UserService {
         UserRepository::create(pubkey, executor).await
     }
 
+    /// Delete a user inside an existing transaction.
+    ///
+    /// Only removes database rows; the caller must delete the user's files first.
+    pub async fn delete_in_tx<'a>(
+        &self,
+        user_id: i32,
+        executor: &mut UnifiedExecutor<'a>,
+    ) -> Result<(), sqlx::Error> {
+        UserRepository::delete(user_id, executor).await
+    }
+
     /// Persist an updated user entity within an existing transaction.
     pub async fn update_in_tx<'a>(
         &self,
@@ -196,6 +209,11 @@ impl UserService {
         }
     }
 
+    /// Drop the cached quota of a user, e.g. after the user was deleted.
+    pub(crate) fn evict_cached_quota(&self, pubkey: &PublicKey) {
+        self.quota_cache.remove(pubkey);
+    }
+
     /// Populate the quota cache after a user has been committed.
     pub(crate) fn cache_user_quota(&self, user: &UserEntity) {
         self.quota_cache
@@ -239,6 +257,11 @@ impl UserService {
 
 #[cfg(test)]
 impl UserService {
+    /// Get all user entities.
+    pub async fn get_all(&self) -> Result<Vec<UserEntity>, sqlx::Error> {
+        UserRepository::get_all(&mut self.sql_db.pool().into()).await
+    }
+
     /// Create a new user using the internal pool.
     ///
     /// Test-only: production signup must go through [`SignupService::create_new_user`]
@@ -0,0 +1,115 @@
+//! The keys a homeserver answers to.
+//!
+//! After `keypair rotate`, the homeserver keeps answering to its previous key
+//! for `[pkdns].key_transition_days` so clients and users have time to follow
+//! the `_successor` record to the new key.
+
+use std::time::{Duration, SystemTime};
+
+use pubky_common::crypto::{Keypair, PublicKey};
+
+use crate::data_directory::KeyRotation;
+
+/// The previous keypair of a homeserver and how long it stays accepted.
+#[derive(Debug, Clone)]
+pub(crate) struct KeyTransition {
+    previous_keypair: Keypair,
+    ends_at: SystemTime,
+}
+
+impl KeyTransition {
+    /// The transition after `rotation`. `None` if it is disabled (`days == 0`).
+    pub fn from_rotation(rotation: KeyRotation, days: u64) -> Option<Self> {
+        if days == 0 {
+            return None;
+        }
+        let ends_at = rotation.transition_ends_at(Duration::from_secs(days * 24 * 60 * 60));
+        Some(Self {
+            previous_keypair: rotation.previous_keypair,
+            ends_at,
+        })
+    }
+
+    /// Whether the previous key is still accepted.
+    pub fn is_active(&self) -> bool {
+        SystemTime::now() < self.ends_at
+    }
+
+    /// When the previous key stops being accepted.
+    pub fn ends_at(&self) -> SystemTime {
+        self.ends_at
+    }
+}
+
+/// The current key of the homeserver and the key it rotated away from, if any.
+#[derive(Debug, Clone)]
+pub(crate) struct HomeserverKeys {
+    current: PublicKey,
+    transition: Option<KeyTransition>,
+}
+
+impl HomeserverKeys {
+    /// Keys of a homeserver that answers to `current` only.
+    pub fn new(current: PublicKey) -> Self {
+        Self {
+            current,
+            transition: None,
+        }
+    }
+
+    /// Also answer to the previous key of `transition` while it is active.
+    pub fn with_transition(mut self, transition: Option<KeyTransition>) -> Self {
+        self.transition = transition;
+        self
+    }
+
+    /// The key the homeserver identifies with.
+    pub fn current(&self) -> &PublicKey {
+        &self.current
+    }
+
+    /// The previous keypair, while the transition is active.
+    pub fn previous_keypair(&self) -> Option<&Keypair> {
+        self.transition
+            .as_ref()
+            .filter(|transition| transition.is_active())
+            .map(|transition| &transition.previous_keypair)
+    }
+
+    /// All keys the homeserver currently answers to, the current one first.
+    pub fn accepted(&self) -> Vec<PublicKey> {
+        let mut keys = vec![self.current.clone()];
+        keys.extend(self.previous_keypair().map(|keypair| keypair.public_key()));
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previous_key_is_accepted_until_the_transition_ends() {
+        let current = Keypair::random().public_key();
+        let previous = Keypair::random();
+
+        let active = KeyTransition::from_rotation(KeyRotation::now(previous.clone()), 30);
+        let keys = HomeserverKeys::new(current.clone()).with_transition(active);
+        assert_eq!(
+            keys.accepted(),
+            vec![current.clone(), previous.public_key()]
+        );
+
+        let rotation = KeyRotation {
+            previous_keypair: previous.clone(),
+            rotated_at: SystemTime::now() - Duration::from_secs(31 * 24 * 60 * 60),
+        };
+        let ended = KeyTransition::from_rotation(rotation, 30);
+        let keys = HomeserverKeys::new(current.clone()).with_transition(ended);
+        assert!(keys.previous_keypair().is_none());
+        assert_eq!(keys.accepted(), vec![current.clone()]);
+
+        let disabled = KeyTransition::from_rotation(KeyRotation::now(previous), 0);
+        assert!(disabled.is_none());
+    }
+}
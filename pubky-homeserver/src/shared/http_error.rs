@@ -55,6 +55,13 @@ impl HttpError {
         )
     }
 
+    pub fn content_blocked() -> HttpError {
+        Self::new_with_message(
+            StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+            "Content blocked by the homeserver operator",
+        )
+    }
+
     pub fn forbidden_with_message(message: impl ToString) -> HttpError {
         Self::new_with_message(StatusCode::FORBIDDEN, message)
     }
@@ -68,6 +75,17 @@ impl HttpError {
     }
 }
 
+impl std::fmt::Display for HttpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.detail {
+            Some(detail) => write!(f, "{}: {}", self.status, detail),
+            None => write!(f, "{}", self.status),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
 impl IntoResponse for HttpError {
     fn into_response(self) -> axum::response::Response {
         match self.detail {
@@ -125,6 +143,11 @@ impl From<FileIoError> for HttpError {
             FileIoError::PathCollision => {
                 Self::new_with_message(StatusCode::CONFLICT, "File/folder path collision")
             }
+            FileIoError::ContentBlocked => Self::content_blocked(),
+            FileIoError::PreconditionFailed => Self::new_with_message(
+                StatusCode::PRECONDITION_FAILED,
+                "The file does not match the given precondition",
+            ),
             FileIoError::StreamBroken(_) => Self::bad_request("Stream broken"),
             e => Self::internal_server_and_log(format!("FileIoError: {}", e)),
         }
@@ -1,3 +1,4 @@
+pub(crate) mod homeserver_keys;
 mod http_error;
 mod pubkey_path_validator;
 pub(crate) mod toml_merge;
@@ -5,6 +6,7 @@ pub(crate) mod user_quota;
 mod utils;
 pub(crate) mod webdav;
 
+pub(crate) use homeserver_keys::{HomeserverKeys, KeyTransition};
 pub(crate) use http_error::{HttpError, HttpResult};
 pub(crate) use pubkey_path_validator::Z32Pubkey;
 pub(crate) use utils::{parse_bool, timestamp_to_sqlx_datetime};
@@ -1,6 +1,7 @@
 //! Per-user quota domain types.
 //!
-//! These types model the per-user overrides for storage and bandwidth limits.
+//! These types model the per-user overrides for storage, bandwidth, request-count
+//! and transfer limits.
 //! They are shared across the codebase: persistence entities use them to
 //! convert raw DB columns into typed values, the service layer uses them for
 //! enforcement and caching, and route handlers use them for API serialization.
@@ -244,6 +245,25 @@ fn validate_burst(
     validate_burst_value(label, burst)
 }
 
+/// Validate that an integer override fits the DB column (`INTEGER`).
+fn validate_int_value(label: &str, field: &QuotaOverride<u64>) -> Result<(), String> {
+    if let QuotaOverride::Value(v) = field {
+        if *v > i32::MAX as u64 {
+            return Err(format!("{label} value {v} exceeds maximum ({})", i32::MAX));
+        }
+    }
+    Ok(())
+}
+
+/// Validate a requests-per-minute override: a limit of zero would block every request,
+/// use `/users/{pubkey}/disable` for that instead.
+fn validate_requests_per_minute(field: &QuotaOverride<u64>) -> Result<(), String> {
+    if matches!(field, QuotaOverride::Value(0)) {
+        return Err("requests_per_minute must be greater than 0".to_string());
+    }
+    validate_int_value("requests_per_minute", field)
+}
+
 /// Validate that allowed_write_paths entries are well-formed path restrictions.
 ///
 /// Each `StoragePath` is already normalized and validated by serde deserialization.
@@ -278,6 +298,11 @@ fn validate_allowed_write_paths(paths: &Option<Vec<StoragePath>>) -> Result<(),
 /// | `rate_read_burst` | burst = rate | `Some(50)` = 50 in rate's unit |
 /// | `rate_write_burst` | burst = rate | `Some(50)` = 50 in rate's unit |
 /// | `allowed_write_paths` | unrestricted | `Some(vec!["/pub/tokens/"])` |
+/// | `requests_per_minute` | use `default_quotas.requests_per_minute` | `Value(600)` |
+/// | `egress_daily_mb` | use `default_quotas.egress_daily_mb` | `Value(1024)` = 1 GB/day |
+/// | `egress_monthly_mb` | use `default_quotas.egress_monthly_mb` | `Value(10240)` |
+/// | `ingress_daily_mb` | use `default_quotas.ingress_daily_mb` | `Value(1024)` |
+/// | `ingress_monthly_mb` | use `default_quotas.ingress_monthly_mb` | `Value(10240)` |
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct UserQuota {
     /// Storage quota in MB.
@@ -303,6 +328,21 @@ pub struct UserQuota {
     /// - `Some(["/pub/tokens/", "/pub/paykit/"])` = only these prefix paths
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub allowed_write_paths: Option<Vec<StoragePath>>,
+    /// Maximum number of requests per minute.
+    #[serde(default, skip_serializing_if = "QuotaOverride::is_default")]
+    pub requests_per_minute: QuotaOverride<u64>,
+    /// Maximum MB downloaded per UTC day.
+    #[serde(default, skip_serializing_if = "QuotaOverride::is_default")]
+    pub egress_daily_mb: QuotaOverride<u64>,
+    /// Maximum MB downloaded per UTC calendar month.
+    #[serde(default, skip_serializing_if = "QuotaOverride::is_default")]
+    pub egress_monthly_mb: QuotaOverride<u64>,
+    /// Maximum MB uploaded per UTC day.
+    #[serde(default, skip_serializing_if = "QuotaOverride::is_default")]
+    pub ingress_daily_mb: QuotaOverride<u64>,
+    /// Maximum MB uploaded per UTC calendar month.
+    #[serde(default, skip_serializing_if = "QuotaOverride::is_default")]
+    pub ingress_monthly_mb: QuotaOverride<u64>,
 }
 
 impl UserQuota {
@@ -338,9 +378,32 @@ impl UserQuota {
             rate_read_burst: rate_read_burst.and_then(|v| u32::try_from(v).ok()?.try_into().ok()),
             rate_write_burst: rate_write_burst.and_then(|v| u32::try_from(v).ok()?.try_into().ok()),
             allowed_write_paths,
+            ..Default::default()
         }
     }
 
+    /// Set the request-count and transfer overrides from their nullable DB columns
+    /// (`INTEGER`: NULL → Default, -1 → Unlimited, positive → Value).
+    pub fn with_usage_columns(
+        mut self,
+        requests_per_minute: Option<i32>,
+        egress_daily_mb: Option<i32>,
+        egress_monthly_mb: Option<i32>,
+        ingress_daily_mb: Option<i32>,
+        ingress_monthly_mb: Option<i32>,
+    ) -> Self {
+        self.requests_per_minute =
+            QuotaOverride::from_db_int("quota_requests_per_minute", requests_per_minute);
+        self.egress_daily_mb = QuotaOverride::from_db_int("quota_egress_daily_mb", egress_daily_mb);
+        self.egress_monthly_mb =
+            QuotaOverride::from_db_int("quota_egress_monthly_mb", egress_monthly_mb);
+        self.ingress_daily_mb =
+            QuotaOverride::from_db_int("quota_ingress_daily_mb", ingress_daily_mb);
+        self.ingress_monthly_mb =
+            QuotaOverride::from_db_int("quota_ingress_monthly_mb", ingress_monthly_mb);
+        self
+    }
+
     /// Storage quota as the DB-column type (`INTEGER`).
     pub fn storage_quota_mb_i32(&self) -> Option<i32> {
         self.storage_quota_mb.to_db_int()
@@ -437,6 +500,20 @@ impl UserQuota {
                 None
             }),
             allowed_write_paths: self.allowed_write_paths.clone(),
+            requests_per_minute: resolve_u64(
+                &self.requests_per_minute,
+                default_quotas.requests_per_minute,
+            ),
+            egress_daily_mb: resolve_u64(&self.egress_daily_mb, default_quotas.egress_daily_mb),
+            egress_monthly_mb: resolve_u64(
+                &self.egress_monthly_mb,
+                default_quotas.egress_monthly_mb,
+            ),
+            ingress_daily_mb: resolve_u64(&self.ingress_daily_mb, default_quotas.ingress_daily_mb),
+            ingress_monthly_mb: resolve_u64(
+                &self.ingress_monthly_mb,
+                default_quotas.ingress_monthly_mb,
+            ),
         }
     }
 
@@ -444,12 +521,18 @@ impl UserQuota {
     /// - Rate values can be persisted (fit in the DB column).
     /// - Burst overrides have a corresponding rate `Value`.
     /// - Burst values are > 0.
+    /// - Request and transfer limits fit in the DB column, requests per minute is > 0.
     pub fn validate(&self) -> Result<(), String> {
         validate_rate_value("rate_read", &self.rate_read)?;
         validate_rate_value("rate_write", &self.rate_write)?;
         validate_burst("rate_read_burst", self.rate_read_burst, &self.rate_read)?;
         validate_burst("rate_write_burst", self.rate_write_burst, &self.rate_write)?;
         validate_allowed_write_paths(&self.allowed_write_paths)?;
+        validate_requests_per_minute(&self.requests_per_minute)?;
+        validate_int_value("egress_daily_mb", &self.egress_daily_mb)?;
+        validate_int_value("egress_monthly_mb", &self.egress_monthly_mb)?;
+        validate_int_value("ingress_daily_mb", &self.ingress_daily_mb)?;
+        validate_int_value("ingress_monthly_mb", &self.ingress_monthly_mb)?;
         Ok(())
     }
 
@@ -473,6 +556,46 @@ impl UserQuota {
         if let Some(ref v) = patch.allowed_write_paths {
             self.allowed_write_paths = v.clone();
         }
+        if let Some(ref v) = patch.requests_per_minute {
+            self.requests_per_minute = v.clone();
+        }
+        if let Some(ref v) = patch.egress_daily_mb {
+            self.egress_daily_mb = v.clone();
+        }
+        if let Some(ref v) = patch.egress_monthly_mb {
+            self.egress_monthly_mb = v.clone();
+        }
+        if let Some(ref v) = patch.ingress_daily_mb {
+            self.ingress_daily_mb = v.clone();
+        }
+        if let Some(ref v) = patch.ingress_monthly_mb {
+            self.ingress_monthly_mb = v.clone();
+        }
+    }
+
+    /// Request-count limit as DB-column type (`INTEGER`).
+    pub fn requests_per_minute_i32(&self) -> Option<i32> {
+        self.requests_per_minute.to_db_int()
+    }
+
+    /// Daily egress cap as DB-column type (`INTEGER`).
+    pub fn egress_daily_mb_i32(&self) -> Option<i32> {
+        self.egress_daily_mb.to_db_int()
+    }
+
+    /// Monthly egress cap as DB-column type (`INTEGER`).
+    pub fn egress_monthly_mb_i32(&self) -> Option<i32> {
+        self.egress_monthly_mb.to_db_int()
+    }
+
+    /// Daily ingress cap as DB-column type (`INTEGER`).
+    pub fn ingress_daily_mb_i32(&self) -> Option<i32> {
+        self.ingress_daily_mb.to_db_int()
+    }
+
+    /// Monthly ingress cap as DB-column type (`INTEGER`).
+    pub fn ingress_monthly_mb_i32(&self) -> Option<i32> {
+        self.ingress_monthly_mb.to_db_int()
     }
 }
 
@@ -547,6 +670,21 @@ pub struct UserQuotaPatch {
     /// Allowed write paths. absent = keep, null = reset to unrestricted, array = set paths.
     #[serde(default, deserialize_with = "deserialize_patch_allowed_write_paths")]
     pub allowed_write_paths: Option<Option<Vec<StoragePath>>>,
+    /// Maximum number of requests per minute.
+    #[serde(default, deserialize_with = "deserialize_patch_override")]
+    pub requests_per_minute: Option<QuotaOverride<u64>>,
+    /// Maximum MB downloaded per UTC day.
+    #[serde(default, deserialize_with = "deserialize_patch_override")]
+    pub egress_daily_mb: Option<QuotaOverride<u64>>,
+    /// Maximum MB downloaded per UTC calendar month.
+    #[serde(default, deserialize_with = "deserialize_patch_override")]
+    pub egress_monthly_mb: Option<QuotaOverride<u64>>,
+    /// Maximum MB uploaded per UTC day.
+    #[serde(default, deserialize_with = "deserialize_patch_override")]
+    pub ingress_daily_mb: Option<QuotaOverride<u64>>,
+    /// Maximum MB uploaded per UTC calendar month.
+    #[serde(default, deserialize_with = "deserialize_patch_override")]
+    pub ingress_monthly_mb: Option<QuotaOverride<u64>>,
 }
 
 impl UserQuotaPatch {
@@ -568,6 +706,19 @@ impl UserQuotaPatch {
         if let Some(ref inner) = self.allowed_write_paths {
             validate_allowed_write_paths(inner)?;
         }
+        if let Some(ref field) = self.requests_per_minute {
+            validate_requests_per_minute(field)?;
+        }
+        for (label, field) in [
+            ("egress_daily_mb", &self.egress_daily_mb),
+            ("egress_monthly_mb", &self.egress_monthly_mb),
+            ("ingress_daily_mb", &self.ingress_daily_mb),
+            ("ingress_monthly_mb", &self.ingress_monthly_mb),
+        ] {
+            if let Some(field) = field {
+                validate_int_value(label, field)?;
+            }
+        }
         Ok(())
     }
 }
@@ -1430,4 +1581,75 @@ mod tests {
         base.merge(&patch);
         assert_eq!(base.allowed_write_paths, None);
     }
+
+    #[test]
+    fn test_usage_columns_roundtrip() {
+        let q = UserQuota::from_nullable_columns(None, None, None, None, None, None)
+            .with_usage_columns(Some(600), Some(-1), None, Some(1024), Some(0));
+        assert_eq!(q.requests_per_minute, QuotaOverride::Value(600));
+        assert_eq!(q.egress_daily_mb, QuotaOverride::Unlimited);
+        assert_eq!(q.egress_monthly_mb, QuotaOverride::Default);
+        assert_eq!(q.ingress_daily_mb, QuotaOverride::Value(1024));
+        assert_eq!(q.ingress_monthly_mb, QuotaOverride::Value(0));
+
+        assert_eq!(q.requests_per_minute_i32(), Some(600));
+        assert_eq!(q.egress_daily_mb_i32(), Some(-1));
+        assert_eq!(q.egress_monthly_mb_i32(), None);
+        assert_eq!(q.ingress_daily_mb_i32(), Some(1024));
+        assert_eq!(q.ingress_monthly_mb_i32(), Some(0));
+    }
+
+    #[test]
+    fn test_resolve_usage_limits_with_defaults() {
+        let defaults = DefaultQuotasToml {
+            requests_per_minute: Some(60),
+            egress_monthly_mb: Some(10_240),
+            ..Default::default()
+        };
+        let q = UserQuota {
+            egress_monthly_mb: QuotaOverride::Unlimited,
+            ingress_daily_mb: QuotaOverride::Value(100),
+            ..Default::default()
+        };
+        let resolved = q.resolve_with_defaults(None, &defaults);
+        assert_eq!(resolved.requests_per_minute, QuotaOverride::Value(60));
+        assert_eq!(resolved.egress_daily_mb, QuotaOverride::Unlimited);
+        assert_eq!(resolved.egress_monthly_mb, QuotaOverride::Unlimited);
+        assert_eq!(resolved.ingress_daily_mb, QuotaOverride::Value(100));
+        assert_eq!(resolved.ingress_monthly_mb, QuotaOverride::Unlimited);
+    }
+
+    #[test]
+    fn test_validate_usage_limits() {
+        let q = UserQuota {
+            requests_per_minute: QuotaOverride::Value(0),
+            ..Default::default()
+        };
+        let err = q.validate().unwrap_err();
+        assert!(err.contains("requests_per_minute"), "error: {err}");
+
+        let q = UserQuota {
+            egress_daily_mb: QuotaOverride::Value(i32::MAX as u64 + 1),
+            ..Default::default()
+        };
+        let err = q.validate().unwrap_err();
+        assert!(err.contains("egress_daily_mb"), "error: {err}");
+
+        let patch: UserQuotaPatch = serde_json::from_str(r#"{"requests_per_minute": 0}"#).unwrap();
+        assert!(patch.validate().is_err());
+
+        let patch: UserQuotaPatch = serde_json::from_str(
+            r#"{"requests_per_minute": 120, "ingress_monthly_mb": "unlimited", "egress_daily_mb": null}"#,
+        )
+        .unwrap();
+        assert!(patch.validate().is_ok());
+        let mut q = UserQuota {
+            egress_daily_mb: QuotaOverride::Value(5),
+            ..Default::default()
+        };
+        q.merge(&patch);
+        assert_eq!(q.requests_per_minute, QuotaOverride::Value(120));
+        assert_eq!(q.ingress_monthly_mb, QuotaOverride::Unlimited);
+        assert_eq!(q.egress_daily_mb, QuotaOverride::Default);
+    }
 }
@@ -8,9 +8,13 @@
 //! This way, we don't miss any logs, for example config file loading errors.
 //!
 
-use crate::{ConfigToml, PersistentDataDir};
+use crate::{ConfigToml, LoggingToml, PersistentDataDir};
 use std::path::Path;
-use tracing_subscriber::EnvFilter;
+use std::sync::OnceLock;
+use tracing_subscriber::{fmt::Formatter, reload, EnvFilter};
+
+/// Set once the subscriber was initialized from the config, so `[logging]` can be reloaded.
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Formatter>> = OnceLock::new();
 
 fn read_config_from_file(data_dir: &Path) -> anyhow::Result<ConfigToml> {
     let data_dir = PersistentDataDir::new(data_dir.to_path_buf());
@@ -26,23 +30,50 @@ pub fn init_tracing_logs_with_config_if_set(config: &ConfigToml) -> anyhow::Resu
         None => return Ok(()),
     };
 
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-        let mut filter = EnvFilter::new("");
-        filter = filter.add_directive(config.level.to_owned().into());
-        // Add any specific filters
-        for filter_str in &config.module_levels {
-            filter = filter.add_directive(filter_str.to_owned().into());
-        }
-        filter
-    });
-    tracing_subscriber::fmt()
+    let is_env_filter = EnvFilter::try_from_default_env().is_ok();
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| config_filter(config));
+    let builder = tracing_subscriber::fmt()
         .with_env_filter(env_filter)
+        .with_filter_reloading();
+    let handle = builder.reload_handle();
+    builder
         .try_init()
         .map_err(|e| anyhow::anyhow!("Failed to initialize tracing: {}", e))?;
+    // Environment variables take precedence over the config, also on reload.
+    if !is_env_filter {
+        let _ = LOG_FILTER.set(handle);
+    }
 
     Ok(())
 }
 
+fn config_filter(config: &LoggingToml) -> EnvFilter {
+    let mut filter = EnvFilter::new("");
+    filter = filter.add_directive(config.level.to_owned().into());
+    // Add any specific filters
+    for filter_str in &config.module_levels {
+        filter = filter.add_directive(filter_str.to_owned().into());
+    }
+    filter
+}
+
+/// Replace the log filter with the levels from `config`.
+///
+/// Returns `false` if logging was not initialized from the config,
+/// or is controlled by environment variables.
+pub(crate) fn reload_log_filter(config: &LoggingToml) -> bool {
+    let Some(handle) = LOG_FILTER.get() else {
+        return false;
+    };
+    match handle.reload(config_filter(config)) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!("Failed to reload log filter: {e}");
+            false
+        }
+    }
+}
+
 /// Initialize tracing logger based on the values defined in the config file.
 /// If the config file is not found, use default values.
 pub fn init_tracing_logs_if_set(data_dir: &Path) -> anyhow::Result<()> {
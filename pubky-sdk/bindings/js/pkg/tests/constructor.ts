@@ -41,3 +41,11 @@ test("new Client() with faulty config", async (t) => {
     "should throw an error",
   );
 });
+
+test("Client.withPersistentCache() without IndexedDB", async (t) => {
+  // Node has no IndexedDB: the cache falls back to memory only.
+  const client = await Client.withPersistentCache({
+    pkarr: { relays: ["http://localhost:15412/relay"] },
+  });
+  t.ok(client, "should create a client");
+});
@@ -0,0 +1,102 @@
+import test from "tape";
+
+import {
+  Keypair,
+  Pubky,
+  PublicKey,
+  SyncEngine,
+  type Path,
+  type SyncConflict,
+  type SyncReport,
+} from "../index.js";
+import {
+  Assert,
+  IsExact,
+  assertPubkyError,
+  createSignupToken,
+} from "./utils.js";
+
+const HOMESERVER_PUBLICKEY = PublicKey.from(
+  "8pinxxgqs41n4aididenw5apqp1urfmzdztr8jt4abrkdn435ewo",
+);
+
+type _SyncReport = Assert<
+  IsExact<ReturnType<SyncEngine["sync"]>, Promise<SyncReport>>
+>;
+type _SyncConflicts = Assert<
+  IsExact<ReturnType<SyncEngine["conflicts"]>, Promise<SyncConflict[]>>
+>;
+type _SyncPut = Assert<
+  IsExact<Parameters<SyncEngine["put"]>, [Path, Uint8Array]>
+>;
+
+async function newSession(label: string) {
+  const sdk = Pubky.testnet();
+  const signer = sdk.signer(Keypair.random());
+  await signer.signup(HOMESERVER_PUBLICKEY, await createSignupToken());
+  return signer.signin(label);
+}
+
+test("SyncEngine: Node runtime rejects without IndexedDB", async (t) => {
+  if (typeof indexedDB !== "undefined") {
+    t.comment("browser runtime has IndexedDB; Node-only check skipped");
+    t.end();
+    return;
+  }
+
+  const session = await newSession("sync.node.test");
+  try {
+    await SyncEngine.open(session, ["/pub/example.com/"]);
+    t.fail("open should reject without IndexedDB");
+  } catch (error) {
+    assertPubkyError(t, error);
+    t.equal(error.name, "ClientStateError", "open maps to ClientStateError");
+    t.ok(/IndexedDB/i.test(error.message), "open message names IndexedDB");
+  }
+  t.end();
+});
+
+test("SyncEngine: queued writes are pushed and remote changes pulled", async (t) => {
+  if (typeof indexedDB === "undefined") {
+    t.comment("IndexedDB unavailable; browser-only sync check skipped");
+    t.end();
+    return;
+  }
+
+  const session = await newSession("sync.browser.test");
+  const engine = await SyncEngine.open(session, ["/pub/example.com/"]);
+  const encoder = new TextEncoder();
+  const decoder = new TextDecoder();
+
+  await engine.put("/pub/example.com/local.txt", encoder.encode("local"));
+  t.deepEqual(await engine.pending(), ["/pub/example.com/local.txt"], "write is queued");
+
+  const pushed = await engine.sync();
+  t.deepEqual(pushed.pushed, ["/pub/example.com/local.txt"], "write is pushed");
+  t.equal(
+    await session.storage.getText("/pub/example.com/local.txt"),
+    "local",
+    "homeserver has the pushed write",
+  );
+
+  await session.storage.putText("/pub/example.com/remote.txt", "remote");
+  const pulled = await engine.pull();
+  t.deepEqual(pulled.pulled, ["/pub/example.com/remote.txt"], "remote write is pulled");
+  const bytes = await engine.get("/pub/example.com/remote.txt");
+  t.equal(bytes && decoder.decode(bytes), "remote", "replica has the remote write");
+  t.ok(await engine.cursor(), "cursor is tracked");
+
+  try {
+    await SyncEngine.open(session, ["/pub/example.com/"]);
+    t.fail("a second engine should not open the same replica");
+  } catch (error) {
+    assertPubkyError(t, error);
+    t.equal(error.name, "ClientStateError", "locked replica maps to ClientStateError");
+  }
+  engine.free();
+  const reopened = await SyncEngine.open(session, ["/pub/example.com/"]);
+  t.deepEqual(await reopened.pending(), [], "replica reopens once freed");
+  reopened.free();
+
+  t.end();
+});
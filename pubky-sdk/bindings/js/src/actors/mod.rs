@@ -12,3 +12,4 @@ pub mod session;
 pub mod session_store;
 pub mod signer;
 pub mod storage;
+pub mod sync;
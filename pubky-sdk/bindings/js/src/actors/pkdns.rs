@@ -3,6 +3,10 @@ use wasm_bindgen::prelude::*;
 use crate::js_error::JsResult;
 use crate::wrappers::keys::{Keypair, PublicKey};
 
+mod records;
+
+pub use records::{DnsRecord, DnsRecordType, RecordChanges};
+
 /// Resolve/publish `_pubky` PKDNS records (homeserver pointers).
 #[wasm_bindgen]
 pub struct Pkdns(pub(crate) pubky::Pkdns);
@@ -40,6 +44,55 @@ impl Pkdns {
             .map(Into::into))
     }
 
+    /// Resolve all homeservers for a given public key (read-only), primary first.
+    ///
+    /// @param {PublicKey} user
+    /// @returns {Promise<PublicKey[]>} The primary homeserver followed by its mirrors; empty
+    ///   when the user has no resolvable homeserver record.
+    /// @throws When Pkarr resolution fails or a resolved `_pubky` target is malformed.
+    #[wasm_bindgen(js_name = "getHomeserversOf")]
+    pub async fn get_homeservers_of(&self, pubky: &PublicKey) -> JsResult<Vec<PublicKey>> {
+        Ok(self
+            .0
+            .get_homeservers_of(pubky.as_inner())
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Resolve the key a homeserver rotated to, from its `_successor` record (read-only).
+    ///
+    /// @param {PublicKey} homeserver
+    /// @returns {Promise<PublicKey|undefined>} The new homeserver key, or `undefined` when the
+    ///   homeserver did not rotate its key.
+    /// @throws When Pkarr resolution fails or the `_successor` record is malformed.
+    #[wasm_bindgen(js_name = "getSuccessorOf")]
+    pub async fn get_successor_of(&self, homeserver: &PublicKey) -> JsResult<Option<PublicKey>> {
+        Ok(self
+            .0
+            .get_successor_of(homeserver.as_inner())
+            .await?
+            .map(Into::into))
+    }
+
+    /// Resolve the DNS records of a given public key (read-only), including `_pubky`.
+    ///
+    /// @param {PublicKey} user
+    /// @returns {Promise<DnsRecord[]>} Records with names relative to the user's key; empty
+    ///   when the user has no Pkarr record.
+    /// @throws When Pkarr resolution fails.
+    #[wasm_bindgen(js_name = "getRecordsOf")]
+    pub async fn get_records_of(&self, user: &PublicKey) -> JsResult<Vec<DnsRecord>> {
+        Ok(self
+            .0
+            .get_records_of(user.as_inner())
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
     /// Resolve the homeserver for **this** user (requires keypair).
     ///
     /// @returns {Promise<PublicKey|undefined>} Homeserver public key or `undefined` if not found.
@@ -65,6 +118,36 @@ impl Pkdns {
         Ok(())
     }
 
+    /// Publish an ordered list of homeservers immediately: the primary first, then mirrors.
+    ///
+    /// Requires keypair or to be signer bound.
+    ///
+    /// @param {PublicKey[]} homeservers Primary homeserver followed by its mirrors.
+    /// @returns {Promise<void>}
+    #[wasm_bindgen(js_name = "publishHomeservers")]
+    pub async fn publish_homeservers(&self, homeservers: Vec<PublicKey>) -> JsResult<()> {
+        let homeservers: Vec<pubky::PublicKey> =
+            homeservers.iter().map(|h| h.as_inner().clone()).collect();
+        self.0.publish_homeservers(&homeservers).await?;
+        Ok(())
+    }
+
+    /// Apply changes to this user's DNS records and republish them, keeping other records
+    /// (including `_pubky`). Concurrent updates are re-read instead of overwritten.
+    ///
+    /// Requires keypair or to be signer bound.
+    ///
+    /// @param {RecordChanges} changes
+    /// @returns {Promise<void>}
+    /// @throws {PubkyError} `InvalidInput` for a malformed address, `RequestError` when changing
+    ///   `_pubky`, `PkarrError` when the record keeps changing concurrently or publishing fails.
+    #[wasm_bindgen(js_name = "updateRecords")]
+    pub async fn update_records(&self, changes: RecordChanges) -> JsResult<()> {
+        let changes = pubky::RecordChanges::try_from(changes)?;
+        self.0.update_records(&changes).await?;
+        Ok(())
+    }
+
     /// Republish homeserver if record is missing/stale.
     ///
     /// Requires keypair or to be signer bound.
@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::js_error::{JsResult, PubkyError, PubkyErrorName};
+
+/// Record types readable and writable through `Pkdns.getRecordsOf()` and `Pkdns.updateRecords()`.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    /// IPv4 address.
+    A,
+    /// IPv6 address.
+    Aaaa,
+    /// Text value.
+    Txt,
+    /// Alias to another name.
+    Cname,
+    /// HTTPS service binding.
+    Https,
+    /// Generic service binding.
+    Svcb,
+}
+
+/// A DNS record of a user, as returned by `Pkdns.getRecordsOf()`.
+///
+/// @typedef {Object} DnsRecord
+/// @property {string} name      Name relative to the user's public key, `@` for the apex.
+/// @property {number=} ttl      Time to live in seconds. Ignored when writing; use `RecordChanges.ttl`.
+/// @property {DnsRecordType} type
+/// @property {string} value     Address (A/AAAA), text (TXT) or target name (CNAME/HTTPS/SVCB).
+/// @property {number=} priority HTTPS/SVCB priority (defaults to 1 when writing).
+/// @property {number=} port     HTTPS/SVCB port.
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsRecord {
+    /// Record name relative to the user's public key.
+    pub name: String,
+    /// Time to live in seconds.
+    #[tsify(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+    /// Record type.
+    #[serde(rename = "type")]
+    pub record_type: DnsRecordType,
+    /// Address, text or target name, depending on the type.
+    pub value: String,
+    /// HTTPS/SVCB priority.
+    #[tsify(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u16>,
+    /// HTTPS/SVCB port.
+    #[tsify(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+/// Changes to a user's DNS records, applied by `Pkdns.updateRecords()`.
+///
+/// @typedef {Object} RecordChanges
+/// @property {DnsRecord[]=} set  Records replacing all records of their names.
+/// @property {DnsRecord[]=} add  Records added next to the existing records of their names.
+/// @property {string[]=} remove  Names whose records are removed.
+/// @property {number=} ttl       TTL in seconds of the written records (defaults to 1 hour).
+///
+/// @example
+/// await signer.pkdns.updateRecords({
+///   set: [{ name: "_profile", type: "TXT", value: "name=alice" }],
+///   remove: ["_old"],
+/// });
+#[derive(Tsify, Serialize, Deserialize, Debug, Clone, Default)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordChanges {
+    /// Records replacing all records of their names.
+    #[tsify(optional)]
+    #[serde(default)]
+    pub set: Vec<DnsRecord>,
+    /// Records added to the existing records of their names.
+    #[tsify(optional)]
+    #[serde(default)]
+    pub add: Vec<DnsRecord>,
+    /// Names whose records are removed.
+    #[tsify(optional)]
+    #[serde(default)]
+    pub remove: Vec<String>,
+    /// TTL in seconds of the written records.
+    #[tsify(optional)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+}
+
+impl From<pubky::DnsRecord> for DnsRecord {
+    fn from(record: pubky::DnsRecord) -> Self {
+        let (record_type, value, binding) = match record.data {
+            pubky::RecordData::A(address) => (DnsRecordType::A, address.to_string(), None),
+            pubky::RecordData::Aaaa(address) => (DnsRecordType::Aaaa, address.to_string(), None),
+            pubky::RecordData::Txt(text) => (DnsRecordType::Txt, text, None),
+            pubky::RecordData::Cname(target) => (DnsRecordType::Cname, target, None),
+            pubky::RecordData::Https(binding) => {
+                (DnsRecordType::Https, binding.target.clone(), Some(binding))
+            }
+            pubky::RecordData::Svcb(binding) => {
+                (DnsRecordType::Svcb, binding.target.clone(), Some(binding))
+            }
+        };
+        Self {
+            name: record.name,
+            ttl: Some(record.ttl),
+            record_type,
+            value,
+            priority: binding.as_ref().map(|binding| binding.priority),
+            port: binding.and_then(|binding| binding.port),
+        }
+    }
+}
+
+impl TryFrom<DnsRecord> for pubky::RecordData {
+    type Error = PubkyError;
+
+    fn try_from(record: DnsRecord) -> JsResult<Self> {
+        let invalid_address = |e: std::net::AddrParseError| {
+            PubkyError::new(
+                PubkyErrorName::InvalidInput,
+                format!("Invalid address for record `{}`: {e}", record.name),
+            )
+        };
+        let binding = || pubky::ServiceBinding {
+            priority: record.priority.unwrap_or(1),
+            target: record.value.clone(),
+            port: record.port,
+        };
+        Ok(match record.record_type {
+            DnsRecordType::A => Self::A(record.value.parse().map_err(invalid_address)?),
+            DnsRecordType::Aaaa => Self::Aaaa(record.value.parse().map_err(invalid_address)?),
+            DnsRecordType::Txt => Self::Txt(record.value),
+            DnsRecordType::Cname => Self::Cname(record.value),
+            DnsRecordType::Https => Self::Https(binding()),
+            DnsRecordType::Svcb => Self::Svcb(binding()),
+        })
+    }
+}
+
+impl TryFrom<RecordChanges> for pubky::RecordChanges {
+    type Error = PubkyError;
+
+    fn try_from(changes: RecordChanges) -> JsResult<Self> {
+        let mut set: Vec<(String, Vec<pubky::RecordData>)> = Vec::new();
+        for record in changes.set {
+            let name = record.name.clone();
+            let data = pubky::RecordData::try_from(record)?;
+            match set.iter_mut().find(|(existing, _)| *existing == name) {
+                Some((_, records)) => records.push(data),
+                None => set.push((name, vec![data])),
+            }
+        }
+
+        let mut result = Self::new();
+        if let Some(ttl) = changes.ttl {
+            result = result.ttl(ttl);
+        }
+        for (name, records) in set {
+            result = result.set(&name, records);
+        }
+        for record in changes.add {
+            let name = record.name.clone();
+            result = result.add(&name, pubky::RecordData::try_from(record)?);
+        }
+        for name in changes.remove {
+            result = result.remove(&name);
+        }
+        Ok(result)
+    }
+}
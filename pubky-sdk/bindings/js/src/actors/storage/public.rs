@@ -59,6 +59,9 @@ impl PublicStorage {
 
     /// Fetch bytes from an addressed path.
     ///
+    /// Falls back to the owner's mirror homeservers when the primary is unreachable,
+    /// and checks the content against its hash.
+    ///
     /// @param {Address} address
     /// @returns {Promise<Uint8Array>}
     #[wasm_bindgen(js_name = "getBytes")]
@@ -66,9 +69,8 @@ impl PublicStorage {
         &self,
         #[wasm_bindgen(unchecked_param_type = "Address")] address: String,
     ) -> JsResult<Uint8Array> {
-        let resp = self.0.get(address).await?;
-        let bytes = resp.bytes().await?;
-        Ok(Uint8Array::from(bytes.as_ref()))
+        let bytes = self.0.get_bytes(address).await?;
+        Ok(Uint8Array::from(bytes.as_slice()))
     }
 
     /// Fetch text from an addressed path as UTF-8 text.
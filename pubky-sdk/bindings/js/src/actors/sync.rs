@@ -0,0 +1,497 @@
+// js/src/actors/sync.rs
+use async_trait::async_trait;
+use pubky_common::crypto::Hash;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use super::session::Session;
+use crate::js_error::{JsResult, PubkyError, PubkyErrorName};
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen(inline_js = r#"
+const PUBKY_SYNC_DB_NAME = "pubky-sync";
+const PUBKY_SYNC_DB_VERSION = 1;
+const PUBKY_SYNC_STATE_STORE_NAME = "replicas";
+const PUBKY_SYNC_CONTENT_STORE_NAME = "content";
+
+/** Assert that IndexedDB is available for the sync replica. */
+function requireIndexedDb() {
+  if (!globalThis.indexedDB) {
+    throw new Error("The Pubky sync engine requires IndexedDB.");
+  }
+}
+
+/** Open the IndexedDB database holding sync replicas: one state record per replica and content by hash. */
+function openSyncDb() {
+  requireIndexedDb();
+  return new Promise((resolve, reject) => {
+    const request = indexedDB.open(PUBKY_SYNC_DB_NAME, PUBKY_SYNC_DB_VERSION);
+    request.onupgradeneeded = () => {
+      const db = request.result;
+      if (!db.objectStoreNames.contains(PUBKY_SYNC_STATE_STORE_NAME)) {
+        db.createObjectStore(PUBKY_SYNC_STATE_STORE_NAME);
+      }
+      if (!db.objectStoreNames.contains(PUBKY_SYNC_CONTENT_STORE_NAME)) {
+        db.createObjectStore(PUBKY_SYNC_CONTENT_STORE_NAME);
+      }
+    };
+    request.onsuccess = () => resolve(request.result);
+    request.onerror = () => reject(request.error ?? new Error("Opening the Pubky sync replica failed."));
+  });
+}
+
+/**
+ * Run an IndexedDB operation against one object store of the sync database,
+ * resolving after the transaction commits.
+ */
+async function withSyncStore(storeName, mode, operation) {
+  const db = await openSyncDb();
+  try {
+    return await new Promise((resolve, reject) => {
+      const tx = db.transaction(storeName, mode);
+      let result;
+      const request = operation(tx.objectStore(storeName));
+      request.onsuccess = () => {
+        result = request.result;
+      };
+      tx.oncomplete = () => resolve(result);
+      tx.onerror = () => reject(tx.error ?? new Error("Pubky sync replica transaction failed."));
+      tx.onabort = () => reject(tx.error ?? new Error("Pubky sync replica transaction aborted."));
+    });
+  } finally {
+    db.close();
+  }
+}
+
+/** Read a record of the sync database, or `undefined` if missing. */
+export async function __pubkySyncRead(storeName, key) {
+  return withSyncStore(storeName, "readonly", (store) => store.get(key));
+}
+
+/** Write a record of the sync database. */
+export async function __pubkySyncWrite(storeName, key, bytes) {
+  await withSyncStore(storeName, "readwrite", (store) => store.put(bytes, key));
+}
+
+/** Delete a record of the sync database. Missing records are ignored. */
+export async function __pubkySyncDelete(storeName, key) {
+  await withSyncStore(storeName, "readwrite", (store) => store.delete(key));
+}
+
+/** Release functions of the replica Web Locks this page holds, by lock name. */
+const PUBKY_SYNC_LOCKS = new Map();
+
+/**
+ * Take the Web Lock `name` without waiting. Resolves to `false` if another engine,
+ * in this or any other tab, holds it.
+ */
+export async function __pubkySyncLock(name) {
+  requireIndexedDb();
+  if (!globalThis.navigator?.locks) {
+    throw new Error("The Pubky sync engine requires the Web Locks API.");
+  }
+  return new Promise((resolve, reject) => {
+    navigator.locks
+      .request(name, { ifAvailable: true }, (lock) => {
+        if (!lock) {
+          resolve(false);
+          return undefined;
+        }
+        // The lock is held until this promise settles.
+        return new Promise((release) => {
+          PUBKY_SYNC_LOCKS.set(name, release);
+          resolve(true);
+        });
+      })
+      .catch(reject);
+  });
+}
+
+/** Release the Web Lock `name` taken with `__pubkySyncLock`, if held. */
+export function __pubkySyncUnlock(name) {
+  PUBKY_SYNC_LOCKS.get(name)?.();
+  PUBKY_SYNC_LOCKS.delete(name);
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(js_name = __pubkySyncRead)]
+    fn js_sync_read(store: &str, key: String) -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = __pubkySyncWrite)]
+    fn js_sync_write(store: &str, key: String, bytes: &[u8]) -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = __pubkySyncDelete)]
+    fn js_sync_delete(store: &str, key: String) -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = __pubkySyncLock)]
+    fn js_sync_lock(name: &str) -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = __pubkySyncUnlock)]
+    fn js_sync_unlock(name: &str);
+}
+
+#[cfg(target_arch = "wasm32")]
+const STATE_STORE: &str = "replicas";
+#[cfg(target_arch = "wasm32")]
+const CONTENT_STORE: &str = "content";
+
+/// [`pubky::ReplicaStore`] backed by IndexedDB, one replica per user.
+///
+/// Content is keyed by `<user>/<hash>` so replicas of different users never share
+/// records. An open engine holds the Web Lock `pubky-sync/<user>`, shared across tabs.
+#[derive(Debug)]
+struct IndexedDbReplicaStore {
+    user: String,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl IndexedDbReplicaStore {
+    fn lock_name(&self) -> String {
+        format!("pubky-sync/{}", self.user)
+    }
+
+    fn content_key(&self, hash: &Hash) -> String {
+        format!("{}/{}", self.user, hash.to_hex())
+    }
+
+    async fn read(store: &str, key: String) -> pubky::Result<Option<Vec<u8>>> {
+        let value = JsFuture::from(js_sync_read(store, key))
+            .await
+            .map_err(store_error)?;
+        Ok((!value.is_undefined()).then(|| js_sys::Uint8Array::new(&value).to_vec()))
+    }
+
+    async fn wait(write: js_sys::Promise) -> pubky::Result<()> {
+        JsFuture::from(write).await.map_err(store_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn store_error(value: JsValue) -> pubky::Error {
+    let message = value
+        .dyn_ref::<js_sys::Error>()
+        .map(|error| String::from(error.message()))
+        .unwrap_or_else(|| format!("{value:?}"));
+    pubky::errors::SyncError::Store { message }.into()
+}
+
+/// Lets the next engine open the replica once this one is freed.
+#[cfg(target_arch = "wasm32")]
+impl Drop for IndexedDbReplicaStore {
+    fn drop(&mut self) {
+        js_sync_unlock(&self.lock_name());
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[async_trait(?Send)]
+impl pubky::ReplicaStore for IndexedDbReplicaStore {
+    async fn lock(&self) -> pubky::Result<()> {
+        let locked = JsFuture::from(js_sync_lock(&self.lock_name()))
+            .await
+            .map_err(store_error)?;
+        if locked.is_truthy() {
+            return Ok(());
+        }
+        Err(pubky::errors::SyncError::Store {
+            message: format!(
+                "the replica of {} is open in another sync engine",
+                self.user
+            ),
+        }
+        .into())
+    }
+
+    async fn load_state(&self) -> pubky::Result<Option<Vec<u8>>> {
+        Self::read(STATE_STORE, self.user.clone()).await
+    }
+
+    async fn save_state(&self, state: &[u8]) -> pubky::Result<()> {
+        Self::wait(js_sync_write(STATE_STORE, self.user.clone(), state)).await
+    }
+
+    async fn read_content(&self, hash: &Hash) -> pubky::Result<Option<Vec<u8>>> {
+        Self::read(CONTENT_STORE, self.content_key(hash)).await
+    }
+
+    async fn write_content(&self, hash: &Hash, content: &[u8]) -> pubky::Result<()> {
+        Self::wait(js_sync_write(
+            CONTENT_STORE,
+            self.content_key(hash),
+            content,
+        ))
+        .await
+    }
+
+    async fn delete_content(&self, hash: &Hash) -> pubky::Result<()> {
+        Self::wait(js_sync_delete(CONTENT_STORE, self.content_key(hash))).await
+    }
+}
+
+/// Native builds only type-check the bindings; the replica needs a browser.
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl pubky::ReplicaStore for IndexedDbReplicaStore {
+    async fn load_state(&self) -> pubky::Result<Option<Vec<u8>>> {
+        Err(self.unsupported())
+    }
+
+    async fn save_state(&self, _state: &[u8]) -> pubky::Result<()> {
+        Err(self.unsupported())
+    }
+
+    async fn read_content(&self, _hash: &Hash) -> pubky::Result<Option<Vec<u8>>> {
+        Err(self.unsupported())
+    }
+
+    async fn write_content(&self, _hash: &Hash, _content: &[u8]) -> pubky::Result<()> {
+        Err(self.unsupported())
+    }
+
+    async fn delete_content(&self, _hash: &Hash) -> pubky::Result<()> {
+        Err(self.unsupported())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl IndexedDbReplicaStore {
+    fn unsupported(&self) -> pubky::Error {
+        pubky::errors::SyncError::Store {
+            message: format!(
+                "The sync replica of {} is only available in wasm browser builds.",
+                self.user
+            ),
+        }
+        .into()
+    }
+}
+
+/// A file changed both locally and on the homeserver since it was last in sync.
+///
+/// @typedef {Object} SyncConflict
+/// @property {string} path    Path of the file.
+/// @property {string=} local  Hex content hash of the local version; absent if deleted locally.
+/// @property {string=} remote Hex content hash of the homeserver version; absent if deleted there.
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    /// Path of the file.
+    pub path: String,
+    /// Hex content hash of the local version.
+    #[tsify(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local: Option<String>,
+    /// Hex content hash of the homeserver version.
+    #[tsify(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+}
+
+impl From<pubky::SyncConflict> for SyncConflict {
+    fn from(conflict: pubky::SyncConflict) -> Self {
+        Self {
+            path: conflict.path,
+            local: conflict.local.map(|hash| hash.to_hex().to_string()),
+            remote: conflict.remote.map(|hash| hash.to_hex().to_string()),
+        }
+    }
+}
+
+/// What a `push()`, `pull()` or `sync()` changed.
+///
+/// @typedef {Object} SyncReport
+/// @property {string[]} pushed    Paths written to or deleted from the homeserver.
+/// @property {string[]} pulled    Paths updated or removed locally from remote changes.
+/// @property {string[]} conflicts Paths that became conflicts.
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+pub struct SyncReport {
+    /// Paths written to or deleted from the homeserver.
+    pub pushed: Vec<String>,
+    /// Paths updated or removed locally from remote changes.
+    pub pulled: Vec<String>,
+    /// Paths that became conflicts.
+    pub conflicts: Vec<String>,
+}
+
+impl From<pubky::SyncReport> for SyncReport {
+    fn from(report: pubky::SyncReport) -> Self {
+        Self {
+            pushed: report.pushed,
+            pulled: report.pulled,
+            conflicts: report.conflicts,
+        }
+    }
+}
+
+/// Offline-first replica of chosen prefixes of the session user's storage, kept in IndexedDB.
+///
+/// Reads and writes go to the replica and work offline; writes are queued until
+/// `push()`. Pushing detects files changed on the homeserver meanwhile and reports
+/// them as conflicts instead of overwriting them. `pull()` applies remote changes
+/// from the user's event feed.
+///
+/// @example
+/// const engine = await SyncEngine.open(session, ["/pub/my-cool-app/"]);
+/// await engine.put("/pub/my-cool-app/notes.txt", new TextEncoder().encode("hello"));
+/// const report = await engine.sync();
+/// for (const conflict of await engine.conflicts()) {
+///   await engine.resolveConflict(conflict.path, "local");
+/// }
+#[wasm_bindgen]
+pub struct SyncEngine(pubky::SyncEngine);
+
+#[wasm_bindgen]
+impl SyncEngine {
+    /// Open the IndexedDB replica of the session user, replicating `prefixes`.
+    ///
+    /// @param {Session} session
+    /// @param {Path[]} prefixes Directory paths ending with `/`.
+    /// @returns {Promise<SyncEngine>}
+    /// Only one engine, across all tabs, may have a user's replica open; call `free()`
+    /// on it before opening it again.
+    ///
+    /// @throws {PubkyError} `InvalidInput` for bad prefixes, `ClientStateError` if IndexedDB
+    /// fails or another engine has the replica open.
+    #[wasm_bindgen]
+    pub async fn open(session: &Session, prefixes: Vec<String>) -> JsResult<SyncEngine> {
+        let store = IndexedDbReplicaStore {
+            user: session.0.public_key().z32(),
+        };
+        let mut builder = pubky::SyncEngine::builder(&session.0, store);
+        for prefix in prefixes {
+            builder = builder.prefix(prefix);
+        }
+        Ok(Self(builder.open().await?))
+    }
+
+    /// Read the local version of a file.
+    ///
+    /// @param {Path} path
+    /// @returns {Promise<Uint8Array|undefined>} `undefined` if the file does not exist locally.
+    #[wasm_bindgen]
+    pub async fn get(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "Path")] path: String,
+    ) -> JsResult<Option<js_sys::Uint8Array>> {
+        let content = self.0.get(path).await?;
+        Ok(content.map(|bytes| js_sys::Uint8Array::from(bytes.as_slice())))
+    }
+
+    /// Paths of all files that exist locally, sorted.
+    ///
+    /// @returns {Promise<string[]>}
+    #[wasm_bindgen]
+    pub async fn list(&self) -> Vec<String> {
+        self.0.list().await
+    }
+
+    /// Write a file locally and queue it for the next push.
+    ///
+    /// @param {Path} path
+    /// @param {Uint8Array} bytes
+    /// @returns {Promise<void>}
+    #[wasm_bindgen]
+    pub async fn put(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "Path")] path: String,
+        bytes: Vec<u8>,
+    ) -> JsResult<()> {
+        Ok(self.0.put(path, &bytes).await?)
+    }
+
+    /// Delete a file locally and queue the deletion for the next push.
+    ///
+    /// @param {Path} path
+    /// @returns {Promise<void>}
+    #[wasm_bindgen]
+    pub async fn delete(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "Path")] path: String,
+    ) -> JsResult<()> {
+        Ok(self.0.delete(path).await?)
+    }
+
+    /// Paths with a local change not pushed yet, oldest first.
+    ///
+    /// @returns {Promise<string[]>}
+    #[wasm_bindgen]
+    pub async fn pending(&self) -> Vec<String> {
+        self.0.pending().await
+    }
+
+    /// Files changed on both sides, waiting for `resolveConflict()`.
+    ///
+    /// @returns {Promise<SyncConflict[]>}
+    #[wasm_bindgen]
+    pub async fn conflicts(&self) -> Vec<SyncConflict> {
+        self.0
+            .conflicts()
+            .await
+            .into_iter()
+            .map(SyncConflict::from)
+            .collect()
+    }
+
+    /// Last applied event cursor, or `undefined` before the first pull.
+    ///
+    /// @returns {Promise<string|undefined>}
+    #[wasm_bindgen]
+    pub async fn cursor(&self) -> Option<String> {
+        self.0.cursor().await.map(|cursor| cursor.to_string())
+    }
+
+    /// Settle a conflict: keep the `"local"` version (pushed next time) or take the
+    /// `"remote"` one.
+    ///
+    /// @param {Path} path
+    /// @param {"local"|"remote"} keep
+    /// @returns {Promise<void>}
+    #[wasm_bindgen(js_name = "resolveConflict")]
+    pub async fn resolve_conflict(
+        &self,
+        #[wasm_bindgen(unchecked_param_type = "Path")] path: String,
+        #[wasm_bindgen(unchecked_param_type = "\"local\" | \"remote\"")] keep: String,
+    ) -> JsResult<()> {
+        let resolution = match keep.as_str() {
+            "local" => pubky::ConflictResolution::KeepLocal,
+            "remote" => pubky::ConflictResolution::KeepRemote,
+            _ => {
+                return Err(PubkyError::new(
+                    PubkyErrorName::InvalidInput,
+                    format!("Unknown conflict resolution {keep:?}; use \"local\" or \"remote\""),
+                ));
+            }
+        };
+        Ok(self.0.resolve_conflict(path, resolution).await?)
+    }
+
+    /// Send queued writes to the homeserver, oldest first.
+    ///
+    /// @returns {Promise<SyncReport>}
+    #[wasm_bindgen]
+    pub async fn push(&self) -> JsResult<SyncReport> {
+        Ok(self.0.push().await?.into())
+    }
+
+    /// Apply remote changes since the last pull.
+    ///
+    /// @returns {Promise<SyncReport>}
+    #[wasm_bindgen]
+    pub async fn pull(&self) -> JsResult<SyncReport> {
+        Ok(self.0.pull().await?.into())
+    }
+
+    /// Push queued writes, then pull remote changes.
+    ///
+    /// @returns {Promise<SyncReport>}
+    #[wasm_bindgen]
+    pub async fn sync(&self) -> JsResult<SyncReport> {
+        Ok(self.0.sync().await?.into())
+    }
+}
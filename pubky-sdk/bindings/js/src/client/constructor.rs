@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
+use super::resolution_cache::IndexedDbResolutionStore;
 use crate::js_error::{JsResult, PubkyError, PubkyErrorName};
 
 // ------------------------------------------------------------------------------------------------
@@ -71,35 +72,36 @@ impl Client {
     /// const pubky = Pubky.withClient(client);
     #[wasm_bindgen(constructor)]
     pub fn new(config_opt: Option<PubkyClientConfig>) -> JsResult<Self> {
-        let mut builder = pubky::PubkyHttpClient::builder();
+        let client = Self::builder(config_opt)?.build()?;
+        log::debug!("Client created: {:?}", client);
 
-        if let Some(config) = config_opt
-            && let Some(pkarr) = config.pkarr
-        {
-            // Relays
-            if let Some(relays) = pkarr.relays {
-                let mut relay_set_error: Option<String> = None;
-                builder.pkarr(|p| {
-                    p.no_relays();
-                    if let Err(e) = p.relays(&relays) {
-                        relay_set_error = Some(e.to_string());
-                    }
-                    p
-                });
-                if let Some(msg) = relay_set_error {
-                    return Err(PubkyError::new(PubkyErrorName::InvalidInput, msg));
-                }
-            }
-            // Timeout
-            if let Some(timeout_ms) = pkarr.request_timeout {
-                builder.pkarr(|p| {
-                    p.request_timeout(Duration::from_millis(timeout_ms));
-                    p
-                });
-            }
-        }
+        Ok(Self(client))
+    }
 
-        let client = builder.build()?;
+    /// Create a Pubky HTTP client that keeps resolved homeservers in IndexedDB.
+    ///
+    /// Records resolved by one page load are reused by the next, so a cold start
+    /// does not resolve every user again. Expired records are still used while they
+    /// are refreshed in the background. Without IndexedDB (e.g. Node.js) the cache
+    /// lives in memory only.
+    ///
+    /// @param {PubkyClientConfig} [config]
+    /// Same transport overrides as `new Client(config)`.
+    ///
+    /// @returns {Promise<Client>}
+    ///
+    /// @throws {InvalidInput}
+    /// If any PKARR relay URL is invalid.
+    ///
+    /// @example
+    /// const client = await Client.withPersistentCache();
+    /// const pubky = Pubky.withClient(client);
+    #[wasm_bindgen(js_name = "withPersistentCache")]
+    pub async fn with_persistent_cache(config_opt: Option<PubkyClientConfig>) -> JsResult<Client> {
+        let store = IndexedDbResolutionStore::open().await;
+        let client = Self::builder(config_opt)?
+            .resolution_cache(pubky::ResolutionCache::new(store))
+            .build()?;
         log::debug!("Client created: {:?}", client);
 
         Ok(Self(client))
@@ -134,3 +136,38 @@ impl Client {
         Ok(Self(client))
     }
 }
+
+impl Client {
+    /// Builder with the transport overrides of a JS config object.
+    fn builder(config_opt: Option<PubkyClientConfig>) -> JsResult<pubky::PubkyHttpClientBuilder> {
+        let mut builder = pubky::PubkyHttpClient::builder();
+
+        if let Some(config) = config_opt
+            && let Some(pkarr) = config.pkarr
+        {
+            // Relays
+            if let Some(relays) = pkarr.relays {
+                let mut relay_set_error: Option<String> = None;
+                builder.pkarr(|p| {
+                    p.no_relays();
+                    if let Err(e) = p.relays(&relays) {
+                        relay_set_error = Some(e.to_string());
+                    }
+                    p
+                });
+                if let Some(msg) = relay_set_error {
+                    return Err(PubkyError::new(PubkyErrorName::InvalidInput, msg));
+                }
+            }
+            // Timeout
+            if let Some(timeout_ms) = pkarr.request_timeout {
+                builder.pkarr(|p| {
+                    p.request_timeout(Duration::from_millis(timeout_ms));
+                    p
+                });
+            }
+        }
+
+        Ok(builder)
+    }
+}
@@ -1,2 +1,3 @@
 pub mod constructor;
 pub mod http;
+mod resolution_cache;
@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use pkarr::SignedPacket;
+use pubky::{PublicKey, ResolutionStore, decode_stored_packet};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen(inline_js = r#"
+const PUBKY_RESOLUTION_DB_NAME = "pubky-resolution-cache";
+const PUBKY_RESOLUTION_DB_VERSION = 1;
+const PUBKY_RESOLUTION_STORE_NAME = "packets";
+
+/** Open the IndexedDB database holding resolved pkarr packets, one per public key. */
+function openResolutionCacheDb() {
+  return new Promise((resolve, reject) => {
+    const request = indexedDB.open(PUBKY_RESOLUTION_DB_NAME, PUBKY_RESOLUTION_DB_VERSION);
+    request.onupgradeneeded = () => {
+      const db = request.result;
+      if (!db.objectStoreNames.contains(PUBKY_RESOLUTION_STORE_NAME)) {
+        db.createObjectStore(PUBKY_RESOLUTION_STORE_NAME, { keyPath: "key" });
+      }
+    };
+    request.onsuccess = () => resolve(request.result);
+    request.onerror = () => reject(request.error ?? new Error("Opening Pubky resolution cache failed."));
+  });
+}
+
+/** The open database, shared by every operation. Reopened after it was closed. */
+let resolutionCacheDb = null;
+
+function getResolutionCacheDb() {
+  if (!resolutionCacheDb) {
+    resolutionCacheDb = openResolutionCacheDb().then(
+      (db) => {
+        // Let other tabs upgrade the database; the next operation reopens it.
+        db.onversionchange = () => {
+          db.close();
+          resolutionCacheDb = null;
+        };
+        db.onclose = () => {
+          resolutionCacheDb = null;
+        };
+        return db;
+      },
+      (error) => {
+        resolutionCacheDb = null;
+        throw error;
+      },
+    );
+  }
+  return resolutionCacheDb;
+}
+
+/**
+ * Run an IndexedDB operation against the packet object store, resolving after the
+ * transaction commits.
+ */
+async function withResolutionCache(mode, operation) {
+  const db = await getResolutionCacheDb();
+  return await new Promise((resolve, reject) => {
+    const tx = db.transaction(PUBKY_RESOLUTION_STORE_NAME, mode);
+    let result;
+    const request = operation(tx.objectStore(PUBKY_RESOLUTION_STORE_NAME));
+    request.onsuccess = () => {
+      result = request.result;
+    };
+    tx.oncomplete = () => resolve(result);
+    tx.onerror = () => reject(tx.error ?? new Error("Pubky resolution cache transaction failed."));
+    tx.onabort = () => reject(tx.error ?? new Error("Pubky resolution cache transaction aborted."));
+  });
+}
+
+/** Load every cached packet, returning an empty list if storage is unavailable. */
+export async function __pubkyResolutionCacheLoad() {
+  if (!globalThis.indexedDB) return [];
+  try {
+    const records = (await withResolutionCache("readonly", (store) => store.getAll())) ?? [];
+    return records.map((record) => record.packet);
+  } catch (_error) {
+    return [];
+  }
+}
+
+/** Persist or replace the cached packet of a public key. No-op without IndexedDB. */
+export async function __pubkyResolutionCachePut(key, packet) {
+  if (!globalThis.indexedDB) return;
+  await withResolutionCache("readwrite", (store) => store.put({ key, packet }));
+}
+
+/** Remove the cached packet of a public key. No-op without IndexedDB. */
+export async function __pubkyResolutionCacheDelete(key) {
+  if (!globalThis.indexedDB) return;
+  await withResolutionCache("readwrite", (store) => store.delete(key));
+}
+"#)]
+extern "C" {
+    #[wasm_bindgen(js_name = __pubkyResolutionCacheLoad)]
+    fn js_cache_load() -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = __pubkyResolutionCachePut)]
+    fn js_cache_put(key: String, packet: Vec<u8>) -> js_sys::Promise;
+
+    #[wasm_bindgen(js_name = __pubkyResolutionCacheDelete)]
+    fn js_cache_delete(key: String) -> js_sys::Promise;
+}
+
+/// [`ResolutionStore`] backed by IndexedDB.
+///
+/// IndexedDB is asynchronous, so packets are read once up front and writes are
+/// queued: a single task applies them in order on one open database, so a removal
+/// never lands before an earlier save of the same key. Without IndexedDB (e.g.
+/// Node.js) the cache is memory only.
+#[derive(Debug, Default)]
+pub(crate) struct IndexedDbResolutionStore {
+    loaded: Mutex<Vec<SignedPacket>>,
+    writes: Arc<Mutex<WriteQueue>>,
+}
+
+/// Writes waiting for the task of an [`IndexedDbResolutionStore`].
+#[derive(Debug, Default)]
+struct WriteQueue {
+    ops: VecDeque<IndexedDbOp>,
+    /// Whether a task is applying the queue.
+    draining: bool,
+}
+
+#[derive(Debug)]
+enum IndexedDbOp {
+    Save(String, Vec<u8>),
+    Remove(String),
+}
+
+impl IndexedDbOp {
+    async fn apply(self) {
+        let (action, write) = match self {
+            Self::Save(key, packet) => ("Writing to", js_cache_put(key, packet)),
+            Self::Remove(key) => ("Removing from", js_cache_delete(key)),
+        };
+        if let Err(error) = JsFuture::from(write).await {
+            log::warn!("{action} the resolution cache failed: {error:?}");
+        }
+    }
+}
+
+impl IndexedDbResolutionStore {
+    /// Open the store and read every cached packet.
+    pub(crate) async fn open() -> Self {
+        let records = match JsFuture::from(js_cache_load()).await {
+            Ok(records) => js_sys::Array::from(&records),
+            Err(error) => {
+                log::warn!("Loading the resolution cache failed: {error:?}");
+                js_sys::Array::new()
+            }
+        };
+        let loaded = records
+            .iter()
+            .filter_map(|record| decode_stored_packet(&js_sys::Uint8Array::new(&record).to_vec()))
+            .collect();
+        Self {
+            loaded: Mutex::new(loaded),
+            writes: Arc::default(),
+        }
+    }
+
+    /// Queue `op`, starting a task to apply the queue unless one is running.
+    fn enqueue(&self, op: IndexedDbOp) {
+        let mut writes = self.writes.lock().unwrap_or_else(PoisonError::into_inner);
+        writes.ops.push_back(op);
+        if writes.draining {
+            return;
+        }
+        writes.draining = true;
+        let queue = Arc::clone(&self.writes);
+        wasm_bindgen_futures::spawn_local(async move {
+            loop {
+                let next = {
+                    let mut writes = queue.lock().unwrap_or_else(PoisonError::into_inner);
+                    let next = writes.ops.pop_front();
+                    writes.draining = next.is_some();
+                    next
+                };
+                let Some(op) = next else {
+                    break;
+                };
+                op.apply().await;
+            }
+        });
+    }
+}
+
+impl ResolutionStore for IndexedDbResolutionStore {
+    fn load(&self) -> Vec<SignedPacket> {
+        std::mem::take(&mut *self.loaded.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn save(&self, packet: &SignedPacket) {
+        let key = packet.public_key().to_string();
+        self.enqueue(IndexedDbOp::Save(key, packet.serialize()));
+    }
+
+    fn remove(&self, public_key: &PublicKey) {
+        self.enqueue(IndexedDbOp::Remove(public_key.z32()));
+    }
+}
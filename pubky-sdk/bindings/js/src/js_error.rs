@@ -10,7 +10,7 @@ use wasm_bindgen::describe::WasmDescribe;
 use wasm_bindgen::prelude::*;
 
 use pkarr::errors::PublicKeyError;
-use pubky::errors::{BuildError, RequestError};
+use pubky::errors::{BuildError, RequestError, SyncError};
 use pubky_common::auth::Error as AuthTokenError;
 use pubky_common::capabilities::{CapabilitiesParseError, CapabilityParseError};
 use pubky_common::recovery_file::Error as RecoveryFileError;
@@ -185,6 +185,8 @@ impl From<pubky::Error> for PubkyError {
             pubky::Error::Authentication(_) => PubkyErrorName::AuthenticationError,
             pubky::Error::Pkarr(_) => PubkyErrorName::PkarrError,
             pubky::Error::Build(_) => PubkyErrorName::InternalError,
+            pubky::Error::Sync(SyncError::Store { .. }) => PubkyErrorName::ClientStateError,
+            pubky::Error::Sync(_) => PubkyErrorName::InvalidInput,
         };
 
         // If this was a server error, attach status_code; else leave it None.
@@ -11,10 +11,14 @@
 //! always returns `Some`.
 
 use base64::{Engine as _, engine::general_purpose::STANDARD};
-use pubky_common::session::CookieSessionRecord;
+use pubky_common::{auth::grant_session_responses::SessionUsage, session::CookieSessionRecord};
+use reqwest::Method;
 
 use super::credential::CookieCredential;
 use crate::actors::session::core::PubkySession;
+use crate::actors::storage::resource::resolve_pubky;
+use crate::errors::{RequestError, Result};
+use crate::util::check_http_status;
 
 /// Cookie-only operations on a [`PubkySession`].
 #[derive(Debug)]
@@ -57,6 +61,34 @@ impl<'a> CookieSessionView<'a> {
         self.credential.cookie_record()
     }
 
+    /// Fetch the user's current usage against their request and transfer caps.
+    ///
+    /// Calls `GET /session/usage`.
+    ///
+    /// # Errors
+    /// - Propagates HTTP errors from the homeserver.
+    pub async fn usage(&self) -> Result<SessionUsage> {
+        let url = format!(
+            "pubky://{}/session/usage",
+            self.session.info().public_key().z32()
+        );
+        let resolved = resolve_pubky(&url)?;
+        let client = self.session.client();
+        let rb = client.cross_request(Method::GET, resolved).await?;
+        let resp = self
+            .session
+            .credential()
+            .attach(rb, client)
+            .await?
+            .send()
+            .await?;
+        let resp = check_http_status(resp).await?;
+        let usage: SessionUsage = resp.json().await.map_err(|e| RequestError::DecodeJson {
+            message: format!("decoding /session/usage response: {e}"),
+        })?;
+        Ok(usage)
+    }
+
     /// Export session metadata for rehydrating after a tab refresh or process restart.
     ///
     /// The returned string contains **no secrets**; it is a base64 encoding of the
@@ -701,6 +701,7 @@ mod tests {
                     token_expires_at: now + 300,
                     grant_expires_at: claims.exp,
                     created_at: now,
+                    usage: None,
                 },
             },
             stored.grant_jws,
@@ -753,7 +754,7 @@ mod tests {
         let mut builder = PubkyHttpClient::builder();
         builder
             .isolated_pkarr_test()
-            .pkarr(|b| b.cache(cache.clone()));
+            .pkarr(|b| b.cache(Arc::<InMemoryCache>::clone(&cache)));
         let client = builder.build().unwrap();
         cache.put(&homeserver_keypair.public_key().into(), &homeserver_packet);
 
@@ -5,11 +5,17 @@
 //! The view borrows the session, so it cannot outlive it; this is what makes
 //! the grant-only API impossible to misuse against a cookie session.
 
-use pubky_common::auth::{grant_session_responses::GrantSessionInfo, jws::GrantId};
+use pubky_common::auth::{
+    grant_session_responses::{GrantSessionInfo, SessionUsage},
+    jws::GrantId,
+};
+use reqwest::Method;
 
 use super::{DelegatedGrantCredentialState, GrantCredential};
 use crate::actors::session::core::PubkySession;
-use crate::errors::Result;
+use crate::actors::storage::resource::resolve_pubky;
+use crate::errors::{RequestError, Result};
+use crate::util::check_http_status;
 
 /// grant-only operations on a [`PubkySession`].
 #[derive(Debug)]
@@ -50,6 +56,35 @@ impl<'a> GrantSessionView<'a> {
         self.credential.state.lock().await.session.clone()
     }
 
+    /// Fetch the user's current usage against their request and transfer caps.
+    ///
+    /// Calls `GET /auth/grant/session`. Returns `None` if the homeserver does
+    /// not report usage.
+    ///
+    /// # Errors
+    /// - Propagates HTTP errors from the homeserver.
+    pub async fn usage(&self) -> Result<Option<SessionUsage>> {
+        let url = format!(
+            "pubky://{}/auth/grant/session",
+            self.session.info().public_key().z32()
+        );
+        let resolved = resolve_pubky(&url)?;
+        let client = self.session.client();
+        let rb = client.cross_request(Method::GET, resolved).await?;
+        let resp = self
+            .session
+            .credential()
+            .attach(rb, client)
+            .await?
+            .send()
+            .await?;
+        let resp = check_http_status(resp).await?;
+        let info: GrantSessionInfo = resp.json().await.map_err(|e| RequestError::DecodeJson {
+            message: format!("decoding /auth/grant/session response: {e}"),
+        })?;
+        Ok(info.usage)
+    }
+
     /// Export the portable local secret material needed to restore this session.
     ///
     /// The returned token contains the grant JWS and `PoP` client secret. Treat
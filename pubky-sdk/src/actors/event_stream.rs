@@ -92,7 +92,7 @@ use base64::Engine;
 use eventsource_stream::Eventsource;
 use futures_util::{Stream, StreamExt};
 use pubky_common::{StoragePath, constants::storage::PRIVATE_ROOT, crypto::Hash};
-use reqwest::Method;
+use reqwest::{Method, StatusCode};
 use url::Url;
 
 pub use pubky_common::events::{EventCursor, EventType};
@@ -487,6 +487,9 @@ impl EventStreamBuilder {
         let sse_stream = response.bytes_stream().eventsource();
         let event_stream = sse_stream.filter_map(|result| async move {
             match result {
+                Ok(sse_event) if sse_event.event == "error" => {
+                    Some(Err(parse_sse_error(&sse_event)))
+                }
                 Ok(sse_event) => match parse_sse_event(&sse_event) {
                     Ok(event) => Some(Ok(event)),
                     Err(e) => {
@@ -620,6 +623,33 @@ fn parse_sse_event(sse: &eventsource_stream::Event) -> Result<Event> {
     })
 }
 
+/// Turn an `error` event, sent when the homeserver ends a stream early, into the
+/// error the request would have failed with.
+///
+/// SSE format:
+/// ```text
+/// event: error
+/// data: 410 <message>
+/// ```
+fn parse_sse_error(sse: &eventsource_stream::Event) -> Error {
+    let (status, message) = sse.data.split_once(' ').unwrap_or((sse.data.as_str(), ""));
+    match status
+        .parse::<u16>()
+        .ok()
+        .and_then(|status| StatusCode::from_u16(status).ok())
+    {
+        Some(status) => RequestError::Server {
+            status,
+            message: message.to_string(),
+        }
+        .into(),
+        None => RequestError::Validation {
+            message: format!("SSE stream error: {}", sse.data),
+        }
+        .into(),
+    }
+}
+
 /// Decode a base64-encoded content hash into a Hash.
 fn decode_content_hash(content_hash_base64: Option<&str>) -> Result<Hash> {
     let b64 = content_hash_base64
@@ -767,6 +797,25 @@ mod tests {
         assert!(err.contains("Unknown event type: PATCH"), "Got: {err}");
     }
 
+    #[test]
+    fn error_event_carries_the_status() {
+        let sse = make_sse(
+            "error",
+            "410 Cursor 42 is older than the retained event history",
+        );
+
+        let err = parse_sse_error(&sse);
+
+        assert!(
+            matches!(
+                &err,
+                Error::Request(RequestError::Server { status, message })
+                    if *status == StatusCode::GONE && message.starts_with("Cursor 42")
+            ),
+            "Got: {err}"
+        );
+    }
+
     #[test]
     fn error_on_missing_path() {
         let hash_b64 = encode_hash([0u8; 32]);
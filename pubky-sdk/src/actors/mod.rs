@@ -4,6 +4,7 @@ pub mod pkdns;
 mod session;
 mod signer;
 pub mod storage;
+pub mod sync;
 
 pub use auth::AuthFlowKind;
 #[allow(deprecated, reason = "Re-exporting deprecated public API")]
@@ -30,3 +31,8 @@ pub use session::SessionInfo;
 pub use session::core::PubkySession;
 pub use signer::PubkySigner;
 pub use storage::core::{PublicStorage, SessionStorage};
+#[cfg(not(target_arch = "wasm32"))]
+pub use sync::FileReplicaStore;
+pub use sync::{
+    ConflictResolution, ReplicaStore, SyncConflict, SyncEngine, SyncEngineBuilder, SyncReport,
+};
@@ -4,20 +4,43 @@
 //! - **Publish (with keys):** `Pkdns::new_with_keypair(..)` or `signer.pkdns()`
 //!
 //! Reads do not require a session or keys. Publishing requires a `Keypair`.
+//!
+//! A `_pubky` record lists one or more homeservers as HTTPS records ordered by
+//! priority: the primary homeserver at priority 0, followed by its mirrors.
+//!
+//! Other records of the user's packet (e.g. a `_profile` TXT record) are read with
+//! [`Pkdns::get_records_of`] and changed with [`Pkdns::update_records`], which keeps
+//! the `_pubky` record intact.
+//!
+//! A homeserver that rotated its key publishes a `_successor` record with its
+//! previous key, naming the new one. Lookups follow it, and republishing a
+//! `_pubky` record moves it to the successor.
 
 use std::time::Duration;
 
 use pkarr::{
-    ResolvePolicy, SignedPacket, Timestamp,
+    DEFAULT_MAXIMUM_TTL, DEFAULT_MINIMUM_TTL, ResolvePolicy, SignedPacket, Timestamp,
     dns::rdata::{RData, SVCB},
-    errors::ResolveError,
+    errors::{PublishError, ResolveError},
 };
+use pubky_common::constants::homeserver_records::SUCCESSOR;
+pub use pubky_common::homeservers::extract_hosts_from_packet;
 
 use crate::{
     Keypair, PubkyHttpClient, PubkySigner, PublicKey, cross_log,
     errors::{AuthError, Error, PkarrError, RequestError, Result},
 };
 
+mod records;
+
+pub use records::{DEFAULT_RECORD_TTL, DnsRecord, RecordChanges, RecordData, ServiceBinding};
+
+/// How many times [`Pkdns::update_records`] re-reads a concurrently updated record.
+const MAX_UPDATE_ATTEMPTS: u32 = 3;
+
+/// How many `_successor` records a lookup follows before giving up.
+const MAX_SUCCESSOR_HOPS: usize = 4;
+
 /// Default staleness window for homeserver `_pubky` Pkarr records (1 hour).
 ///
 /// Used by [`crate::Pkdns::publish_homeserver_if_stale`] to decide when a record
@@ -142,6 +165,9 @@ impl Pkdns {
 
     /// Resolve current homeserver for a user public key via Pkarr (no keypair required).
     ///
+    /// If the homeserver rotated its key, the key it rotated to is returned
+    /// (see [`Self::get_successor_of`]).
+    ///
     /// Returns:
     /// - `Ok(Some(host))` when the `_pubky` record resolves to a valid homeserver public key,
     /// - `Ok(None)` when no Pkarr record exists, or the record carries no `_pubky` target,
@@ -167,7 +193,10 @@ impl Pkdns {
             .pkarr()
             .resolve(user_public_key, ResolvePolicy::CacheFirst)
             .await;
-        let result = Self::homeserver_pubkey_from_resolution(resolution)?;
+        let result = match Self::homeserver_pubkey_from_resolution(resolution)? {
+            Some(homeserver) => Some(self.follow_successors(homeserver).await),
+            None => None,
+        };
         cross_log!(
             debug,
             "Homeserver resolution for {} yielded {:?}",
@@ -190,6 +219,120 @@ impl Pkdns {
         }
     }
 
+    /// Resolve all homeservers of a user public key via Pkarr (no keypair required).
+    ///
+    /// The primary homeserver comes first, followed by its mirrors in the order the user
+    /// published them. The list is empty when no Pkarr record exists or the record carries
+    /// no `_pubky` target. Homeservers that rotated their key are replaced by their successor.
+    ///
+    /// # Errors
+    /// - [`crate::errors::Error::Pkarr`] ([`PkarrError::Resolve`]) if Pkarr resolution fails for
+    ///   any reason other than [`ResolveError::NotFound`].
+    /// - [`crate::errors::Error::Pkarr`] ([`PkarrError::InvalidRecord`]) if any resolved `_pubky`
+    ///   target is not a valid public key.
+    pub async fn get_homeservers_of(&self, user_public_key: &PublicKey) -> Result<Vec<PublicKey>> {
+        cross_log!(
+            info,
+            "Resolving homeservers for public key {} via PKARR",
+            user_public_key
+        );
+        let resolution = self
+            .client
+            .pkarr()
+            .resolve(user_public_key, ResolvePolicy::CacheFirst)
+            .await;
+        let listed = match resolution {
+            Ok(packet) => homeserver_pubkeys_from_packet(&packet)?,
+            Err(ResolveError::NotFound) => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        let mut homeservers = Vec::with_capacity(listed.len());
+        for homeserver in listed {
+            let homeserver = self.follow_successors(homeserver).await;
+            if !homeservers.contains(&homeserver) {
+                homeservers.push(homeserver);
+            }
+        }
+        Ok(homeservers)
+    }
+
+    /// Resolve the key a homeserver rotated to, from the `_successor` record
+    /// published with its previous key.
+    ///
+    /// Returns `Ok(None)` when the homeserver has no Pkarr record or did not rotate its key.
+    /// Answers are remembered by the client for the TTL of the homeserver's packet.
+    ///
+    /// # Errors
+    /// - [`crate::errors::Error::Pkarr`] ([`PkarrError::Resolve`]) if Pkarr resolution fails for
+    ///   any reason other than [`ResolveError::NotFound`].
+    /// - [`crate::errors::Error::Pkarr`] ([`PkarrError::InvalidRecord`]) if the `_successor`
+    ///   record is not a valid public key.
+    pub async fn get_successor_of(&self, homeserver: &PublicKey) -> Result<Option<PublicKey>> {
+        if let Some(successor) = self.client.successors.get(homeserver) {
+            return Ok(successor);
+        }
+        let resolution = self
+            .client
+            .pkarr()
+            .resolve(homeserver, ResolvePolicy::CacheFirst)
+            .await;
+        let (successor, ttl) = match resolution {
+            Ok(packet) => {
+                let expires_in = packet.expires_in(DEFAULT_MINIMUM_TTL, DEFAULT_MAXIMUM_TTL);
+                (
+                    successor_from_packet(&packet)?,
+                    Duration::from_secs(expires_in.into()),
+                )
+            }
+            Err(ResolveError::NotFound) => (None, Duration::from_secs(DEFAULT_MINIMUM_TTL.into())),
+            Err(error) => return Err(error.into()),
+        };
+        self.client
+            .successors
+            .insert(homeserver.clone(), successor.clone(), ttl);
+        Ok(successor)
+    }
+
+    /// Follow `_successor` records from `homeserver` to its current key.
+    ///
+    /// Stops at the last key that resolved, on a loop, or after [`MAX_SUCCESSOR_HOPS`].
+    async fn follow_successors(&self, homeserver: PublicKey) -> PublicKey {
+        let mut current = homeserver;
+        let mut visited = vec![current.clone()];
+        for _ in 0..MAX_SUCCESSOR_HOPS {
+            let successor = match self.get_successor_of(&current).await {
+                Ok(Some(successor)) => successor,
+                Ok(None) => break,
+                Err(error) => {
+                    cross_log!(
+                        warn,
+                        "Failed to resolve the successor of homeserver {}: {}",
+                        current,
+                        error
+                    );
+                    break;
+                }
+            };
+            if visited.contains(&successor) {
+                cross_log!(
+                    warn,
+                    "Ignoring `_successor` loop at homeserver {}",
+                    successor
+                );
+                break;
+            }
+            cross_log!(
+                info,
+                "Homeserver {} rotated its key to {}",
+                current,
+                successor
+            );
+            visited.push(successor.clone());
+            current = successor;
+        }
+        current
+    }
+
     pub(crate) async fn require_homeserver_of(
         &self,
         user_public_key: &PublicKey,
@@ -224,11 +367,34 @@ impl Pkdns {
         self.get_homeserver_of(&kp.public_key()).await
     }
 
+    /// Resolve the DNS records of a user public key via Pkarr (no keypair required).
+    ///
+    /// Returns the records of the types [`RecordData`] models, including the `_pubky`
+    /// homeserver record, with names relative to the user's public key. The list is empty
+    /// when no Pkarr record exists.
+    ///
+    /// # Errors
+    /// - [`crate::errors::Error::Pkarr`] ([`PkarrError::Resolve`]) if Pkarr resolution fails for
+    ///   any reason other than [`ResolveError::NotFound`].
+    pub async fn get_records_of(&self, user_public_key: &PublicKey) -> Result<Vec<DnsRecord>> {
+        match self
+            .client
+            .pkarr()
+            .resolve(user_public_key, ResolvePolicy::CacheFirst)
+            .await
+        {
+            Ok(packet) => Ok(records::records_from_packet(&packet)),
+            Err(ResolveError::NotFound) => Ok(Vec::new()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
     // -------------------- Publishing (requires keypair) --------------------
 
     /// Publish `_pubky` **forcing** a refresh.
     ///
     /// If `host_override` is `None`, reuses the host found in the existing record (if any).
+    /// Homeservers that rotated their key are replaced by their successor.
     ///
     /// # Errors
     /// - [`crate::errors::Error::Authentication`] if called without a keypair or validation fails.
@@ -239,9 +405,10 @@ impl Pkdns {
             .await
     }
 
-    /// Publish `_pubky` **only if stale/missing**.
+    /// Publish `_pubky` **only if stale/missing**, or if a listed homeserver rotated its key.
     ///
     /// If `host_override` is `None`, reuses the host found in the existing record (if any).
+    /// Homeservers that rotated their key are replaced by their successor.
     ///
     /// # Errors
     /// - [`crate::errors::Error::Authentication`] if called without a keypair or validation fails.
@@ -255,6 +422,106 @@ impl Pkdns {
             .await
     }
 
+    /// Publish `_pubky` with an ordered list of homeservers, **forcing** a refresh.
+    ///
+    /// The first homeserver is the primary, the others are mirrors that clients fall back
+    /// to when the primary is unreachable. Replaces any homeservers in the existing record.
+    ///
+    /// # Errors
+    /// - [`crate::errors::Error::Authentication`] if called without a keypair.
+    /// - [`crate::errors::Error::Request`] ([`RequestError::Validation`]) if `homeservers`
+    ///   is empty or contains duplicates.
+    /// - [`crate::errors::Error::Pkarr`] if PKARR/DHT resolution or publish fails.
+    pub async fn publish_homeservers(&self, homeservers: &[PublicKey]) -> Result<()> {
+        let kp = self.keypair_ref()?;
+        let pubky = kp.public_key();
+        if homeservers.is_empty() {
+            return Err(RequestError::Validation {
+                message: "at least one homeserver is required".into(),
+            }
+            .into());
+        }
+        let hosts: Vec<String> = homeservers.iter().map(PublicKey::z32).collect();
+        if hosts
+            .iter()
+            .enumerate()
+            .any(|(i, host)| hosts[..i].contains(host))
+        {
+            return Err(RequestError::Validation {
+                message: "homeservers must not contain duplicates".into(),
+            }
+            .into());
+        }
+
+        let existing = self.resolve_latest(&pubky).await?;
+        self.publish_with_retries(kp, &pubky, &hosts, existing)
+            .await
+    }
+
+    /// Apply `changes` to this user's DNS records and publish them.
+    ///
+    /// Reads the most recent packet, merges the changes into it and republishes it; records
+    /// of other names, including `_pubky`, are kept. The write is a compare-and-swap: when
+    /// the record changed since it was read, the changes are re-applied to the newer record
+    /// instead of overwriting it.
+    ///
+    /// # Errors
+    /// - [`crate::errors::Error::Authentication`] if called without a keypair.
+    /// - [`crate::errors::Error::Request`] ([`RequestError::Validation`]) if `changes` touch a
+    ///   `_pubky` name; use [`Self::publish_homeservers`] for those.
+    /// - [`crate::errors::Error::Pkarr`] ([`PkarrError::ConcurrentUpdate`]) if the record kept
+    ///   changing concurrently.
+    /// - [`crate::errors::Error::Pkarr`] if PKARR/DHT resolution or publish fails, or a record
+    ///   value is not valid DNS data.
+    pub async fn update_records(&self, changes: &RecordChanges) -> Result<()> {
+        let kp = self.keypair_ref()?;
+        let pubky = kp.public_key();
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let mut base = self.resolve_latest(&pubky).await?;
+        for attempt in 1..=MAX_UPDATE_ATTEMPTS {
+            let packet = changes.apply(kp, base.as_ref())?;
+
+            // Compare: only publish over the packet the changes were applied to.
+            let latest = self.resolve_latest(&pubky).await?;
+            if latest.as_ref().map(SignedPacket::timestamp)
+                != base.as_ref().map(SignedPacket::timestamp)
+            {
+                cross_log!(
+                    info,
+                    "Record of {} changed while updating (attempt {attempt}); re-applying",
+                    pubky
+                );
+                base = latest;
+                continue;
+            }
+
+            // Swap: a newer packet seen by the client or the DHT rejects the publish.
+            match self.client.pkarr().publish(&packet).await {
+                Ok(_) => {
+                    cross_log!(info, "Updated DNS records of {}", pubky);
+                    return Ok(());
+                }
+                Err(PublishError::NotMostRecent) => {
+                    cross_log!(
+                        info,
+                        "Newer record of {} found while publishing (attempt {attempt}); re-applying",
+                        pubky
+                    );
+                    base = self.resolve_latest(&pubky).await?;
+                }
+                Err(error) => return Err(PkarrError::from(error).into()),
+            }
+        }
+
+        Err(PkarrError::ConcurrentUpdate {
+            attempts: MAX_UPDATE_ATTEMPTS,
+        }
+        .into())
+    }
+
     // ---- internals ----
 
     async fn publish_homeserver(
@@ -272,51 +539,85 @@ impl Pkdns {
             pubky,
             mode
         );
-        let resolved = self
+        let existing = self.resolve_latest(&pubky).await?;
+
+        // 2) Decide hosts to publish.
+        let Some(hosts) = Self::select_hosts(&pubky, host_override, existing.as_ref())? else {
+            return Ok(());
+        };
+        let (hosts, rotated) = self.replace_rotated_hosts(hosts).await;
+
+        // 3) Age check (for IfStale). A rotated homeserver is always republished.
+        if !rotated && self.should_skip_due_to_age(mode, existing.as_ref(), &pubky) {
+            return Ok(());
+        }
+
+        // 4) Publish with small retry loop on retryable pkarr errors.
+        self.publish_with_retries(kp, &pubky, &hosts, existing)
+            .await
+    }
+
+    /// Replace hosts that rotated their key by their successor.
+    /// Returns the hosts and whether any was replaced.
+    async fn replace_rotated_hosts(&self, hosts: Vec<String>) -> (Vec<String>, bool) {
+        let mut rotated = false;
+        let mut replaced: Vec<String> = Vec::with_capacity(hosts.len());
+        for host in hosts {
+            let host = match PublicKey::try_from_z32(&host) {
+                Ok(homeserver) => {
+                    let successor = self.follow_successors(homeserver.clone()).await;
+                    rotated |= successor != homeserver;
+                    successor.z32()
+                }
+                Err(_) => host,
+            };
+            if !replaced.contains(&host) {
+                replaced.push(host);
+            }
+        }
+        (replaced, rotated)
+    }
+
+    /// Resolve the most recent packet of `pubky` to use as the basis for a write.
+    ///
+    /// Fails when the network cannot be queried: a write based on a missing packet
+    /// would drop the user's other records.
+    async fn resolve_latest(&self, pubky: &PublicKey) -> Result<Option<SignedPacket>> {
+        let resolved = match self
             .client
             .pkarr()
-            .resolve(&pubky, ResolvePolicy::NetworkOnly)
+            .resolve(pubky, ResolvePolicy::NetworkOnly)
             .await
-            .ok();
+        {
+            Ok(packet) => Some(packet),
+            Err(ResolveError::NotFound) => None,
+            Err(error) => return Err(error.into()),
+        };
         // `NetworkOnly` can observe an older packet while a newer packet is still
         // propagating. The client never downgrades its cache, so reconcile with the
         // cache after resolving before using the packet as the basis for a write.
         let cached = self
             .client
             .pkarr()
-            .resolve(&pubky, ResolvePolicy::CacheOnly)
+            .resolve(pubky, ResolvePolicy::CacheOnly)
             .await
             .ok();
-        let existing = most_recent_packet(resolved, cached);
-
-        // 2) Decide host string to publish.
-        let Some(host_str) = Self::select_host(&pubky, host_override, existing.as_ref())? else {
-            return Ok(());
-        };
-
-        // 3) Age check (for IfStale).
-        if self.should_skip_due_to_age(mode, existing.as_ref(), &pubky) {
-            return Ok(());
-        }
-
-        // 4) Publish with small retry loop on retryable pkarr errors.
-        self.publish_with_retries(kp, &pubky, &host_str, existing)
-            .await
+        Ok(most_recent_packet(resolved, cached))
     }
 
     async fn publish_homeserver_inner(
         &self,
         keypair: &Keypair,
-        host: &str,
+        hosts: &[String],
         existing: Option<SignedPacket>,
     ) -> Result<()> {
-        let signed_packet = Self::build_homeserver_packet(keypair, host, existing.as_ref())?;
+        let signed_packet = Self::build_homeserver_packet(keypair, hosts, existing.as_ref())?;
 
         cross_log!(
             debug,
-            "Publishing `_pubky` packet for {} targeting host {}",
+            "Publishing `_pubky` packet for {} targeting hosts {}",
             keypair.public_key(),
-            host
+            hosts.join(", ")
         );
 
         self.client
@@ -341,27 +642,28 @@ impl Pkdns {
         })
     }
 
-    fn select_host(
+    fn select_hosts(
         pubky: &PublicKey,
         host_override: Option<&PublicKey>,
         existing: Option<&SignedPacket>,
-    ) -> Result<Option<String>> {
-        let Some(host) = determine_host(host_override, existing)? else {
+    ) -> Result<Option<Vec<String>>> {
+        let hosts = determine_hosts(host_override, existing)?;
+        if hosts.is_empty() {
             cross_log!(
                 info,
                 "No existing host found for {}; skipping publish",
                 pubky
             );
             return Ok(None);
-        };
+        }
 
         cross_log!(
             info,
-            "Selected host {} for `_pubky` publish of {}",
-            host,
+            "Selected hosts {} for `_pubky` publish of {}",
+            hosts.join(", "),
             pubky
         );
-        Ok(Some(host))
+        Ok(Some(hosts))
     }
 
     fn should_skip_due_to_age(
@@ -397,18 +699,18 @@ impl Pkdns {
         &self,
         keypair: &Keypair,
         pubky: &PublicKey,
-        host: &str,
+        hosts: &[String],
         existing: Option<SignedPacket>,
     ) -> Result<()> {
         for attempt in 1..=3 {
             cross_log!(
                 info,
-                "Publishing homeserver for {} (attempt {attempt}) -> host {}",
+                "Publishing homeserver for {} (attempt {attempt}) -> hosts {}",
                 pubky,
-                host
+                hosts.join(", ")
             );
             match self
-                .publish_homeserver_inner(keypair, host, existing.clone())
+                .publish_homeserver_inner(keypair, hosts, existing.clone())
                 .await
             {
                 Ok(()) => return Ok(()),
@@ -436,10 +738,11 @@ impl Pkdns {
 
     fn build_homeserver_packet(
         keypair: &Keypair,
-        host: &str,
+        hosts: &[String],
         existing: Option<&SignedPacket>,
     ) -> Result<SignedPacket> {
-        // Keep previous records that are *not* `_pubky.*`, then write `_pubky` HTTPS/SVCB.
+        // Keep previous records that are *not* `_pubky.*`, then write one `_pubky` HTTPS
+        // record per host, prioritized in list order (primary at 0).
         let mut builder = SignedPacket::builder();
         if let Some(packet) = existing {
             for record in packet.all_resource_records() {
@@ -449,13 +752,19 @@ impl Pkdns {
             }
         }
 
-        let svcb = SVCB::new(0, host.try_into().map_err(PkarrError::from)?);
-        let pubky_name = "_pubky".try_into().map_err(PkarrError::from)?;
+        for (priority, host) in hosts.iter().enumerate() {
+            let priority = u16::try_from(priority).map_err(|_err| RequestError::Validation {
+                message: "too many homeservers".into(),
+            })?;
+            let svcb = SVCB::new(
+                priority,
+                host.as_str().try_into().map_err(PkarrError::from)?,
+            );
+            let pubky_name = "_pubky".try_into().map_err(PkarrError::from)?;
+            builder = builder.https(pubky_name, svcb, 60 * 60);
+        }
 
-        Ok(builder
-            .https(pubky_name, svcb, 60 * 60)
-            .sign(keypair)
-            .map_err(PkarrError::from)?)
+        Ok(builder.sign(keypair).map_err(PkarrError::from)?)
     }
 }
 
@@ -479,20 +788,37 @@ fn most_recent_packet(
     }
 }
 
-/// Pick a host to publish: explicit override or the one found in the DHT packet.
-fn determine_host(
+/// Pick the hosts to publish: the ones found in the DHT packet, with the primary
+/// replaced by the explicit override if there is one.
+fn determine_hosts(
     override_host: Option<&PublicKey>,
     dht_packet: Option<&SignedPacket>,
-) -> Result<Option<String>> {
+) -> Result<Vec<String>> {
     if let Some(host) = override_host {
         cross_log!(info, "Using override host {} for `_pubky` publish", host);
-        return Ok(Some(host.z32()));
+        // The override replaces the primary; mirrors of the existing record are kept.
+        let mirrors = dht_packet
+            .and_then(|packet| homeserver_pubkeys_from_packet(packet).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .skip(1)
+            .filter(|mirror| mirror != host);
+        return Ok(std::iter::once(host.clone())
+            .chain(mirrors)
+            .map(|pk| pk.z32())
+            .collect());
     }
-    cross_log!(debug, "Deriving publish host from existing `_pubky` record");
+    cross_log!(
+        debug,
+        "Deriving publish hosts from existing `_pubky` record"
+    );
     let Some(packet) = dht_packet else {
-        return Ok(None);
+        return Ok(Vec::new());
     };
-    Ok(homeserver_pubkey_from_packet(packet)?.map(|pk| pk.z32()))
+    Ok(homeserver_pubkeys_from_packet(packet)?
+        .iter()
+        .map(PublicKey::z32)
+        .collect())
 }
 
 /// Resolve the `_pubky` homeserver pointer from a signed Pkarr packet into a [`PublicKey`].
@@ -512,15 +838,47 @@ fn homeserver_pubkey_from_packet(packet: &SignedPacket) -> Result<Option<PublicK
     })
 }
 
-/// Extract `_pubky` SVCB/HTTPS target from a signed Pkarr packet.
-pub fn extract_host_from_packet(packet: &SignedPacket) -> Option<String> {
-    packet
-        .resource_records("_pubky")
+/// Resolve all `_pubky` homeserver pointers from a signed Pkarr packet, primary first.
+///
+/// Returns [`PkarrError::InvalidRecord`] when any target is not a valid public key.
+fn homeserver_pubkeys_from_packet(packet: &SignedPacket) -> Result<Vec<PublicKey>> {
+    extract_hosts_from_packet(packet)
+        .into_iter()
+        .map(|host| {
+            PublicKey::try_from_z32(&host).map_err(|e| {
+                PkarrError::InvalidRecord(format!(
+                    "`_pubky` target `{host}` is not a valid homeserver public key: {e}"
+                ))
+                .into()
+            })
+        })
+        .collect()
+}
+
+/// Read the `_successor` record of a homeserver's packet.
+///
+/// Returns [`PkarrError::InvalidRecord`] when the record is not a valid public key.
+fn successor_from_packet(packet: &SignedPacket) -> Result<Option<PublicKey>> {
+    let Some(successor) = packet
+        .resource_records(SUCCESSOR)
         .find_map(|rr| match &rr.rdata {
-            RData::SVCB(svcb) => Some(svcb.target.to_string()),
-            RData::HTTPS(https) => Some(https.0.target.to_string()),
+            RData::TXT(txt) => String::try_from(txt.clone()).ok(),
             _ => None,
         })
+    else {
+        return Ok(None);
+    };
+    PublicKey::try_from_z32(&successor).map(Some).map_err(|e| {
+        PkarrError::InvalidRecord(format!(
+            "`{SUCCESSOR}` value `{successor}` is not a valid homeserver public key: {e}"
+        ))
+        .into()
+    })
+}
+
+/// Extract the primary `_pubky` SVCB/HTTPS target (lowest priority) from a signed Pkarr packet.
+pub fn extract_host_from_packet(packet: &SignedPacket) -> Option<String> {
+    extract_hosts_from_packet(packet).into_iter().next()
 }
 
 #[cfg(test)]
@@ -570,6 +928,157 @@ mod tests {
         ));
     }
 
+    fn homeserver_packet(homeserver: &Keypair, successor: Option<&PublicKey>) -> SignedPacket {
+        let mut builder = SignedPacket::builder();
+        if let Some(successor) = successor {
+            let successor = successor.z32();
+            builder = builder.txt(
+                SUCCESSOR.try_into().expect("successor name"),
+                TXT::try_from(successor.as_str()).expect("successor TXT"),
+                3600,
+            );
+        }
+        builder
+            .address(
+                ".".try_into().expect("root name"),
+                "127.0.0.1".parse().expect("ip"),
+                3600,
+            )
+            .sign(homeserver)
+            .expect("signed packet")
+    }
+
+    fn pkdns_with_cached(packets: &[&SignedPacket]) -> Pkdns {
+        let cache = Arc::new(InMemoryCache::new(NonZeroUsize::new(16).expect("non-zero")));
+        for packet in packets {
+            let cache_key: pkarr::CacheKey = packet.public_key().into();
+            cache.put(&cache_key, packet);
+        }
+        let mut client_builder = PubkyHttpClient::builder();
+        client_builder
+            .isolated_pkarr_test()
+            .pkarr(|builder| builder.cache(cache));
+        Pkdns::with_client(client_builder.build().expect("client"))
+    }
+
+    #[tokio::test]
+    async fn homeserver_lookup_follows_successor() {
+        let user = Keypair::random();
+        let old_homeserver = Keypair::random();
+        let new_homeserver = Keypair::random();
+        let user_packet = SignedPacket::builder()
+            .https(
+                "_pubky".try_into().expect("_pubky name"),
+                SVCB::new(
+                    0,
+                    old_homeserver
+                        .public_key()
+                        .z32()
+                        .as_str()
+                        .try_into()
+                        .expect("host name"),
+                ),
+                3600,
+            )
+            .sign(&user)
+            .expect("signed packet");
+        let old_packet = homeserver_packet(&old_homeserver, Some(&new_homeserver.public_key()));
+        let new_packet = homeserver_packet(&new_homeserver, None);
+        let pkdns = pkdns_with_cached(&[&user_packet, &old_packet, &new_packet]);
+
+        assert_eq!(
+            pkdns
+                .get_successor_of(&old_homeserver.public_key())
+                .await
+                .expect("successor lookup"),
+            Some(new_homeserver.public_key())
+        );
+        assert_eq!(
+            pkdns
+                .get_homeserver_of(&user.public_key())
+                .await
+                .expect("homeserver lookup"),
+            Some(new_homeserver.public_key())
+        );
+        assert_eq!(
+            pkdns
+                .get_homeservers_of(&user.public_key())
+                .await
+                .expect("homeservers lookup"),
+            vec![new_homeserver.public_key()]
+        );
+    }
+
+    #[tokio::test]
+    async fn successor_lookup_is_remembered() {
+        let old = Keypair::random();
+        let new = Keypair::random();
+        let pkdns = pkdns_with_cached(&[&homeserver_packet(&old, Some(&new.public_key()))]);
+
+        assert_eq!(
+            pkdns
+                .get_successor_of(&old.public_key())
+                .await
+                .expect("lookup"),
+            Some(new.public_key())
+        );
+        assert_eq!(
+            pkdns.client.successors.get(&old.public_key()),
+            Some(Some(new.public_key()))
+        );
+    }
+
+    #[tokio::test]
+    async fn publish_fails_when_existing_record_cannot_be_resolved() {
+        let mut client_builder = PubkyHttpClient::builder();
+        client_builder.isolated_pkarr_test();
+        let pkdns = Pkdns::with_client_and_keypair(
+            client_builder.build().expect("client"),
+            Keypair::random(),
+        );
+
+        // Publishing without the existing record would drop the user's other records.
+        let err = pkdns
+            .publish_homeserver_force(Some(&Keypair::random().public_key()))
+            .await
+            .expect_err("unresolvable record must fail the publish");
+        assert!(matches!(err, Error::Pkarr(PkarrError::Resolve(_))));
+    }
+
+    #[tokio::test]
+    async fn successor_loop_stops_at_last_new_key() {
+        let first = Keypair::random();
+        let second = Keypair::random();
+        let first_packet = homeserver_packet(&first, Some(&second.public_key()));
+        let second_packet = homeserver_packet(&second, Some(&first.public_key()));
+        let pkdns = pkdns_with_cached(&[&first_packet, &second_packet]);
+
+        assert_eq!(
+            pkdns.follow_successors(first.public_key()).await,
+            second.public_key()
+        );
+    }
+
+    #[test]
+    fn successor_from_packet_rejects_malformed_key() {
+        let homeserver = Keypair::random();
+        let packet = SignedPacket::builder()
+            .txt(
+                SUCCESSOR.try_into().expect("successor name"),
+                TXT::try_from("example.com").expect("TXT"),
+                3600,
+            )
+            .sign(&homeserver)
+            .expect("signed packet");
+
+        let err = successor_from_packet(&packet).expect_err("malformed successor");
+        assert!(matches!(err, Error::Pkarr(PkarrError::InvalidRecord(_))));
+        assert_eq!(
+            successor_from_packet(&homeserver_packet(&homeserver, None)).expect("no successor"),
+            None
+        );
+    }
+
     #[test]
     fn homeserver_pubkey_from_resolution_returns_none_when_not_found() {
         let resolved = Pkdns::homeserver_pubkey_from_resolution(Err(ResolveError::NotFound))
@@ -621,9 +1130,12 @@ mod tests {
 
         let new_host = Keypair::random().public_key().z32();
 
-        let republished =
-            Pkdns::build_homeserver_packet(&keypair, &new_host, Some(&existing_packet))
-                .expect("republished packet");
+        let republished = Pkdns::build_homeserver_packet(
+            &keypair,
+            std::slice::from_ref(&new_host),
+            Some(&existing_packet),
+        )
+        .expect("republished packet");
 
         assert_eq!(
             extract_host_from_packet(&republished),
@@ -722,7 +1234,7 @@ mod tests {
             .sign(&user)
             .expect("signed packet");
 
-        let err = determine_host(None, Some(&packet))
+        let err = determine_hosts(None, Some(&packet))
             .expect_err("republishing must reject a malformed existing target");
 
         assert!(
@@ -767,7 +1279,7 @@ mod tests {
 
         let existing = most_recent_packet(Some(network_packet), Some(cached_packet))
             .expect("most recent packet");
-        let republished = Pkdns::build_homeserver_packet(&keypair, &host, Some(&existing))
+        let republished = Pkdns::build_homeserver_packet(&keypair, &[host], Some(&existing))
             .expect("republished packet");
 
         assert!(
@@ -777,4 +1289,64 @@ mod tests {
             "records from the newer cached packet should be preserved"
         );
     }
+
+    #[test]
+    fn build_homeserver_packet_lists_mirrors_after_primary() {
+        let keypair = Keypair::random();
+        let hosts: Vec<String> = (0..3)
+            .map(|_| Keypair::random().public_key().z32())
+            .collect();
+
+        let packet = Pkdns::build_homeserver_packet(&keypair, &hosts, None).expect("signed packet");
+
+        assert_eq!(extract_hosts_from_packet(&packet), hosts);
+        assert_eq!(extract_host_from_packet(&packet), Some(hosts[0].clone()));
+    }
+
+    #[test]
+    fn extract_hosts_from_packet_orders_by_priority() {
+        let user = Keypair::random();
+        let primary = Keypair::random().public_key().z32();
+        let mirror = Keypair::random().public_key().z32();
+
+        let packet = SignedPacket::builder()
+            .https(
+                "_pubky".try_into().expect("_pubky name"),
+                SVCB::new(1, mirror.as_str().try_into().expect("host name")),
+                3600,
+            )
+            .https(
+                "_pubky".try_into().expect("_pubky name"),
+                SVCB::new(0, primary.as_str().try_into().expect("host name")),
+                3600,
+            )
+            .sign(&user)
+            .expect("signed packet");
+
+        assert_eq!(extract_hosts_from_packet(&packet), vec![primary, mirror]);
+    }
+
+    #[test]
+    fn override_host_keeps_existing_mirrors() {
+        let keypair = Keypair::random();
+        let old_primary = Keypair::random().public_key();
+        let mirror = Keypair::random().public_key();
+        let new_primary = Keypair::random().public_key();
+
+        let existing = Pkdns::build_homeserver_packet(
+            &keypair,
+            &[old_primary.z32(), mirror.z32(), new_primary.z32()],
+            None,
+        )
+        .expect("signed packet");
+
+        let hosts = determine_hosts(Some(&new_primary), Some(&existing)).expect("hosts to publish");
+        assert_eq!(hosts, vec![new_primary.z32(), mirror.z32()]);
+
+        let hosts = determine_hosts(None, Some(&existing)).expect("hosts to publish");
+        assert_eq!(
+            hosts,
+            vec![old_primary.z32(), mirror.z32(), new_primary.z32()]
+        );
+    }
 }
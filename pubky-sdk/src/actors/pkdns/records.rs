@@ -0,0 +1,456 @@
+//! User DNS records: a typed view of the records in a user's signed packet, and a set of
+//! changes merged into it on publish.
+//!
+//! The `_pubky` homeserver record is managed by [`super::Pkdns::publish_homeservers`] and
+//! friends; record changes never touch it.
+
+use std::{
+    collections::BTreeMap,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use pkarr::{
+    SignedPacket,
+    dns::{
+        Name,
+        rdata::{A, AAAA, CNAME, HTTPS, RData, SVCB, SVCParam, TXT},
+    },
+};
+
+use crate::{
+    Keypair,
+    errors::{PkarrError, RequestError, Result},
+};
+
+/// Default TTL of records written through [`RecordChanges`] (1 hour).
+pub const DEFAULT_RECORD_TTL: u32 = 60 * 60;
+
+/// Prefix of the names reserved for the homeserver record.
+const RESERVED_PREFIX: &str = "_pubky";
+
+/// A DNS record of a user, with its name relative to the user's public key.
+///
+/// The apex (the public key itself) is named `@`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsRecord {
+    /// Record name relative to the user's public key, e.g. `_profile` or `@`.
+    pub name: String,
+    /// Time to live in seconds.
+    pub ttl: u32,
+    /// Record data.
+    pub data: RecordData,
+}
+
+/// Data of a [`DnsRecord`]. Only the record types listed here can be read or written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    /// IPv4 address.
+    A(Ipv4Addr),
+    /// IPv6 address.
+    Aaaa(Ipv6Addr),
+    /// Text value.
+    Txt(String),
+    /// Alias to another name.
+    Cname(String),
+    /// HTTPS service binding.
+    Https(ServiceBinding),
+    /// Generic service binding.
+    Svcb(ServiceBinding),
+}
+
+/// Target of an HTTPS or SVCB record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceBinding {
+    /// Priority; `0` is alias mode, lower values are preferred otherwise.
+    pub priority: u16,
+    /// Target name, `.` for the record's own name.
+    pub target: String,
+    /// Optional port the service listens on.
+    pub port: Option<u16>,
+}
+
+impl ServiceBinding {
+    /// Service binding to `target` with the given priority and no port.
+    #[must_use]
+    pub fn new(priority: u16, target: impl Into<String>) -> Self {
+        Self {
+            priority,
+            target: target.into(),
+            port: None,
+        }
+    }
+
+    /// Set the port the service listens on (builder-style).
+    #[must_use]
+    pub const fn with_port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    fn to_svcb(&self) -> Result<SVCB<'_>> {
+        let target = Name::try_from(self.target.as_str()).map_err(PkarrError::from)?;
+        let mut svcb = SVCB::new(self.priority, target);
+        if let Some(port) = self.port {
+            svcb.set_port(port);
+        }
+        Ok(svcb)
+    }
+
+    fn from_svcb(svcb: &SVCB<'_>) -> Self {
+        let port = svcb.iter_params().find_map(|param| match param {
+            SVCParam::Port(port) => Some(*port),
+            _ => None,
+        });
+        Self {
+            priority: svcb.priority,
+            target: target_name(&svcb.target),
+            port,
+        }
+    }
+}
+
+impl RecordData {
+    /// Convert a supported [`RData`] into record data; `None` for other record types.
+    fn from_rdata(rdata: &RData<'_>) -> Option<Self> {
+        Some(match rdata {
+            RData::A(a) => Self::A(Ipv4Addr::from(a.address)),
+            RData::AAAA(aaaa) => Self::Aaaa(Ipv6Addr::from(aaaa.address)),
+            RData::TXT(txt) => Self::Txt(String::try_from(txt.clone()).ok()?),
+            RData::CNAME(cname) => Self::Cname(target_name(&cname.0)),
+            RData::HTTPS(https) => Self::Https(ServiceBinding::from_svcb(&https.0)),
+            RData::SVCB(svcb) => Self::Svcb(ServiceBinding::from_svcb(svcb)),
+            _ => return None,
+        })
+    }
+
+    fn to_rdata(&self) -> Result<RData<'_>> {
+        Ok(match self {
+            Self::A(address) => RData::A(A::from(*address)),
+            Self::Aaaa(address) => RData::AAAA(AAAA::from(*address)),
+            Self::Txt(value) => {
+                RData::TXT(TXT::try_from(value.as_str()).map_err(PkarrError::from)?)
+            }
+            Self::Cname(target) => RData::CNAME(CNAME(
+                Name::try_from(target.as_str()).map_err(PkarrError::from)?,
+            )),
+            Self::Https(binding) => RData::HTTPS(HTTPS(binding.to_svcb()?)),
+            Self::Svcb(binding) => RData::SVCB(binding.to_svcb()?),
+        })
+    }
+}
+
+/// Change to the records of one name.
+#[derive(Debug, Clone)]
+enum NameChange {
+    /// Replace all records of the name; an empty list removes them.
+    Set(Vec<RecordData>),
+    /// Keep the existing records of the name and add these.
+    Add(Vec<RecordData>),
+}
+
+/// Changes to a user's DNS records, applied by [`super::Pkdns::update_records`].
+///
+/// Names without changes keep their records, including the `_pubky` homeserver record.
+///
+/// ```
+/// use pubky::{RecordChanges, RecordData};
+///
+/// let changes = RecordChanges::new()
+///     .set("_profile", [RecordData::Txt("name=alice".into())])
+///     .add("@", RecordData::A([192, 0, 2, 1].into()))
+///     .remove("_old");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordChanges {
+    changes: BTreeMap<String, NameChange>,
+    ttl: u32,
+}
+
+impl Default for RecordChanges {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordChanges {
+    /// Empty set of changes, written with [`DEFAULT_RECORD_TTL`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            changes: BTreeMap::new(),
+            ttl: DEFAULT_RECORD_TTL,
+        }
+    }
+
+    /// Set the TTL of the records written by these changes (builder-style).
+    #[must_use]
+    pub const fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Replace all records of `name` with `records`.
+    #[must_use]
+    pub fn set(mut self, name: &str, records: impl IntoIterator<Item = RecordData>) -> Self {
+        self.changes.insert(
+            relative_name(name, None),
+            NameChange::Set(records.into_iter().collect()),
+        );
+        self
+    }
+
+    /// Add `record` to the records of `name`, keeping the existing ones.
+    /// A record identical to an existing one is not duplicated.
+    #[must_use]
+    pub fn add(mut self, name: &str, record: RecordData) -> Self {
+        match self
+            .changes
+            .entry(relative_name(name, None))
+            .or_insert_with(|| NameChange::Add(Vec::new()))
+        {
+            NameChange::Set(records) | NameChange::Add(records) => records.push(record),
+        }
+        self
+    }
+
+    /// Remove all records of `name`.
+    #[must_use]
+    pub fn remove(self, name: &str) -> Self {
+        self.set(name, [])
+    }
+
+    /// Whether there is nothing to change.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Sign a packet holding the records of `base` with these changes merged in.
+    ///
+    /// Records of names without changes are copied from `base` untouched, whatever their type.
+    pub(super) fn apply(
+        &self,
+        keypair: &Keypair,
+        base: Option<&SignedPacket>,
+    ) -> Result<SignedPacket> {
+        if let Some(name) = self
+            .changes
+            .keys()
+            .find(|name| name.starts_with(RESERVED_PREFIX))
+        {
+            return Err(RequestError::Validation {
+                message: format!(
+                    "`{name}` is reserved for the homeserver record; use Pkdns::publish_homeservers"
+                ),
+            }
+            .into());
+        }
+
+        let origin = keypair.public_key().z32();
+        let mut builder = SignedPacket::builder();
+        let mut kept: BTreeMap<&str, Vec<RecordData>> = BTreeMap::new();
+        if let Some(packet) = base {
+            for record in packet.all_resource_records() {
+                let name = relative_name(&record.name.to_string(), Some(&origin));
+                match self.changes.get_key_value(&name) {
+                    None => builder = builder.record(record.to_owned()),
+                    Some((name, NameChange::Add(_))) => {
+                        builder = builder.record(record.to_owned());
+                        if let Some(data) = RecordData::from_rdata(&record.rdata) {
+                            kept.entry(name.as_str()).or_default().push(data);
+                        }
+                    }
+                    Some((_, NameChange::Set(_))) => {}
+                }
+            }
+        }
+
+        for (name, change) in &self.changes {
+            let (NameChange::Set(records) | NameChange::Add(records)) = change;
+            let kept = kept.get(name.as_str());
+            // Pkarr names the apex `.`; `@` is not a valid label.
+            let dns_name = if name == "@" { "." } else { name.as_str() };
+            let dns_name = Name::try_from(dns_name).map_err(PkarrError::from)?;
+            for (i, data) in records.iter().enumerate() {
+                let duplicate = records[..i].contains(data)
+                    || kept.is_some_and(|existing| existing.contains(data));
+                if !duplicate {
+                    builder = builder.rdata(dns_name.clone(), data.to_rdata()?, self.ttl);
+                }
+            }
+        }
+
+        Ok(builder.sign(keypair).map_err(PkarrError::from)?)
+    }
+}
+
+/// Records of `packet` with a type [`RecordData`] models, in packet order.
+pub(super) fn records_from_packet(packet: &SignedPacket) -> Vec<DnsRecord> {
+    let origin = packet.public_key().to_z32();
+    packet
+        .all_resource_records()
+        .filter_map(|record| {
+            Some(DnsRecord {
+                name: relative_name(&record.name.to_string(), Some(&origin)),
+                ttl: record.ttl,
+                data: RecordData::from_rdata(&record.rdata)?,
+            })
+        })
+        .collect()
+}
+
+/// Target name as written, `.` for the root name.
+fn target_name(name: &Name<'_>) -> String {
+    let name = name.to_string();
+    if name.is_empty() {
+        ".".to_string()
+    } else {
+        name
+    }
+}
+
+/// Name relative to the public key `origin`, `@` for the apex.
+///
+/// Without an origin only the trailing dot and the `.`/empty apex forms are normalized.
+fn relative_name(name: &str, origin: Option<&str>) -> String {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let name = match origin {
+        Some(origin) if name == origin => "",
+        Some(origin) => name
+            .strip_suffix(origin)
+            .and_then(|rest| rest.strip_suffix('.'))
+            .unwrap_or(name),
+        None => name,
+    };
+    if name.is_empty() {
+        "@".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_packet(keypair: &Keypair) -> SignedPacket {
+        SignedPacket::builder()
+            .https(
+                "_pubky".try_into().unwrap(),
+                SVCB::new(0, "homeserver".try_into().unwrap()),
+                3600,
+            )
+            .txt(
+                "_profile".try_into().unwrap(),
+                "name=alice".try_into().unwrap(),
+                3600,
+            )
+            .txt(
+                "_keep".try_into().unwrap(),
+                "untouched".try_into().unwrap(),
+                120,
+            )
+            .sign(keypair)
+            .unwrap()
+    }
+
+    fn records_named(packet: &SignedPacket, name: &str) -> Vec<RecordData> {
+        records_from_packet(packet)
+            .into_iter()
+            .filter(|record| record.name == name)
+            .map(|record| record.data)
+            .collect()
+    }
+
+    #[test]
+    fn apply_merges_changes_and_preserves_other_records() {
+        let keypair = Keypair::random();
+        let base = base_packet(&keypair);
+
+        let site = ServiceBinding::new(1, ".").with_port(8443);
+        let changes = RecordChanges::new()
+            .set("_profile", [RecordData::Txt("name=bob".into())])
+            .add("@", RecordData::A(Ipv4Addr::new(192, 0, 2, 1)))
+            .add("@", RecordData::Https(site.clone()));
+        let packet = changes.apply(&keypair, Some(&base)).unwrap();
+
+        assert_eq!(
+            records_named(&packet, "_profile"),
+            vec![RecordData::Txt("name=bob".into())]
+        );
+        assert_eq!(
+            records_named(&packet, "@"),
+            vec![
+                RecordData::A(Ipv4Addr::new(192, 0, 2, 1)),
+                RecordData::Https(site)
+            ]
+        );
+        assert_eq!(
+            records_named(&packet, "_pubky"),
+            vec![RecordData::Https(ServiceBinding::new(0, "homeserver"))]
+        );
+        let keep = records_from_packet(&packet)
+            .into_iter()
+            .find(|record| record.name == "_keep")
+            .unwrap();
+        assert_eq!(keep.ttl, 120);
+    }
+
+    #[test]
+    fn add_keeps_existing_records_without_duplicates() {
+        let keypair = Keypair::random();
+        let base = base_packet(&keypair);
+
+        let changes = RecordChanges::new()
+            .add("_profile", RecordData::Txt("name=alice".into()))
+            .add("_profile", RecordData::Txt("bio=hi".into()));
+        let packet = changes.apply(&keypair, Some(&base)).unwrap();
+
+        assert_eq!(
+            records_named(&packet, "_profile"),
+            vec![
+                RecordData::Txt("name=alice".into()),
+                RecordData::Txt("bio=hi".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_drops_only_the_named_records() {
+        let keypair = Keypair::random();
+        let base = base_packet(&keypair);
+
+        let packet = RecordChanges::new()
+            .remove("_profile.")
+            .apply(&keypair, Some(&base))
+            .unwrap();
+
+        assert!(records_named(&packet, "_profile").is_empty());
+        assert_eq!(records_named(&packet, "_keep").len(), 1);
+        assert_eq!(records_named(&packet, "_pubky").len(), 1);
+    }
+
+    #[test]
+    fn apply_rejects_homeserver_record_changes() {
+        let keypair = Keypair::random();
+        let changes = RecordChanges::new().remove("_pubky");
+
+        let err = changes.apply(&keypair, None).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::errors::Error::Request(RequestError::Validation { .. })
+        ));
+    }
+
+    #[test]
+    fn relative_name_strips_origin() {
+        let origin = "o4dksfbqk85ogzdb5osziw6befigbuxmuxkuxq8434q89uj56uyy";
+        assert_eq!(relative_name(origin, Some(origin)), "@");
+        assert_eq!(
+            relative_name(&format!("_profile.{origin}"), Some(origin)),
+            "_profile"
+        );
+        assert_eq!(relative_name(".", None), "@");
+        assert_eq!(relative_name("www.", None), "www");
+    }
+}
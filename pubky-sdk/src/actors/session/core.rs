@@ -1,11 +1,16 @@
 use std::sync::Arc;
 
 use pubky_common::crypto::PublicKey;
+use pubky_common::republish_status::RepublishStatus;
+use pubky_common::storage_usage::StorageUsage;
+use reqwest::Method;
 
 use super::SessionInfo;
 
 use super::credential::SessionCredential;
-use crate::errors::Error;
+use crate::actors::storage::resource::resolve_pubky;
+use crate::errors::{Error, RequestError};
+use crate::util::check_http_status;
 use crate::{PubkyHttpClient, Result, SessionStorage, cross_log};
 
 /// Your authenticated handle after signing in.
@@ -160,6 +165,80 @@ impl PubkySession {
     pub fn storage(&self) -> SessionStorage {
         SessionStorage::new(self)
     }
+
+    /// Fetch this user's storage usage, broken down by top-level directory and app.
+    ///
+    /// Calls `GET /quota/usage`, which requires a session with root capability.
+    /// The result includes the total storage limit and any path quotas, e.g. to
+    /// show a storage breakdown in a settings screen.
+    ///
+    /// # Errors
+    /// - Propagates transport failures.
+    /// - Returns [`crate::errors::Error::Request`] if the homeserver rejects the request
+    ///   (e.g. `403` without root capability) or the response cannot be decoded.
+    pub async fn storage_usage(&self) -> Result<StorageUsage> {
+        let url = format!("pubky://{}/quota/usage", self.public_key().z32());
+        let resolved = resolve_pubky(&url)?;
+        let rb = self.client.cross_request(Method::GET, resolved).await?;
+        let resp = self
+            .credential
+            .attach(rb, &self.client)
+            .await?
+            .send()
+            .await?;
+        let resp = check_http_status(resp).await?;
+        resp.json().await.map_err(|e| {
+            RequestError::DecodeJson {
+                message: format!("decoding /quota/usage response: {e}"),
+            }
+            .into()
+        })
+    }
+
+    /// Fetch how the homeserver's last attempt to republish this user's pkarr record went.
+    ///
+    /// Calls `GET /pkdns/republish`. All fields are `None` until the homeserver
+    /// attempted to republish the record.
+    ///
+    /// # Errors
+    /// - Propagates transport failures.
+    /// - Returns [`crate::errors::Error::Request`] if the homeserver rejects the request
+    ///   or the response cannot be decoded.
+    pub async fn republish_status(&self) -> Result<RepublishStatus> {
+        self.republish_request(Method::GET).await
+    }
+
+    /// Ask the homeserver to republish this user's pkarr record now, without
+    /// waiting for its next republish run, and return the new status.
+    ///
+    /// Calls `POST /pkdns/republish`.
+    ///
+    /// # Errors
+    /// - Propagates transport failures.
+    /// - Returns [`crate::errors::Error::Request`] if the homeserver rejects the request,
+    ///   e.g. `429` if the record was republished less than a minute ago.
+    pub async fn request_republish(&self) -> Result<RepublishStatus> {
+        self.republish_request(Method::POST).await
+    }
+
+    async fn republish_request(&self, method: Method) -> Result<RepublishStatus> {
+        let url = format!("pubky://{}/pkdns/republish", self.public_key().z32());
+        let resolved = resolve_pubky(&url)?;
+        let rb = self.client.cross_request(method, resolved).await?;
+        let resp = self
+            .credential
+            .attach(rb, &self.client)
+            .await?
+            .send()
+            .await?;
+        let resp = check_http_status(resp).await?;
+        resp.json().await.map_err(|e| {
+            RequestError::DecodeJson {
+                message: format!("decoding /pkdns/republish response: {e}"),
+            }
+            .into()
+        })
+    }
 }
 
 impl std::fmt::Debug for PubkySession {
@@ -3,7 +3,7 @@ use crate::actors::session::credential::SessionCredential;
 use reqwest::{Method, RequestBuilder};
 use std::sync::Arc;
 
-use super::resource::{IntoPubkyResource, IntoResourcePath, PubkyResource, ResourcePath};
+use super::resource::{IntoResourcePath, PubkyResource, ResourcePath};
 use crate::{
     PubkyHttpClient, PubkySession, cross_log,
     errors::{RequestError, Result},
@@ -159,14 +159,15 @@ impl PublicStorage {
         })
     }
 
-    /// Build a request for this public storage (no cookies).
-    pub(crate) async fn request<A: IntoPubkyResource>(
+    /// Build a request for `resource` with an optional `query` (no cookies).
+    pub(crate) async fn request(
         &self,
         method: Method,
-        addr: A,
+        resource: &PubkyResource,
+        query: Option<&str>,
     ) -> Result<RequestBuilder> {
-        let resource: PubkyResource = addr.into_pubky_resource()?;
-        let url = resource.to_transport_url()?;
+        let mut url = resource.to_transport_url()?;
+        url.set_query(query);
         cross_log!(debug, "Public storage {} request {}", method, url);
         let rb = self.client.cross_request(method, url).await?;
         Ok(rb)
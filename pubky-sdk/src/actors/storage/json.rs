@@ -1,8 +1,9 @@
 use reqwest::Response;
 
 use super::core::{PublicStorage, SessionStorage};
-use super::resource::{IntoPubkyResource, IntoResourcePath};
+use super::resource::{IntoPubkyResource, IntoResourcePath, PubkyResource};
 use crate::Result;
+use crate::errors::RequestError;
 use crate::util::check_http_status;
 
 //
@@ -65,23 +66,29 @@ impl SessionStorage {
 impl PublicStorage {
     /// GET and deserialize JSON from an **addressed resource**.
     ///
+    /// Fails over to the owner's mirrors and verifies the content like
+    /// [`PublicStorage::get_bytes`].
+    ///
     /// *Requires the **`json`** crate feature.*
     ///
     /// # Errors
     /// - Returns [`crate::errors::Error::Parse`] if `addr` cannot be converted into a valid addressed resource.
-    /// - Propagates transport failures or JSON deserialization errors from the underlying HTTP request.
+    /// - Propagates transport failures and error statuses like [`PublicStorage::get_bytes`].
+    /// - Returns [`crate::errors::RequestError::DecodeJson`] if the content is not valid JSON for `T`.
     pub async fn get_json<A, T>(&self, addr: A) -> Result<T>
     where
         A: IntoPubkyResource + Send,
         T: serde::de::DeserializeOwned,
     {
-        let resp = self
-            .request(reqwest::Method::GET, addr)
-            .await?
-            .header(reqwest::header::ACCEPT, "application/json")
-            .send()
+        let resource: PubkyResource = addr.into_pubky_resource()?;
+        let body = self
+            .get_verified(&resource, Some("application/json"))
             .await?;
-        let resp = check_http_status(resp).await?;
-        Ok(resp.json::<T>().await?)
+        serde_json::from_slice(&body).map_err(|e| {
+            RequestError::DecodeJson {
+                message: format!("{resource}: {e}"),
+            }
+            .into()
+        })
     }
 }
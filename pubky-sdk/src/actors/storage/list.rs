@@ -1,4 +1,4 @@
-use reqwest::Method;
+use reqwest::{Method, RequestBuilder};
 use url::Url;
 
 use super::core::{PublicStorage, SessionStorage, dir_trailing_slash_error};
@@ -52,7 +52,8 @@ impl PublicStorage {
     /// Requirements:
     /// - Address **must** point to a directory and **must end with `/`**.
     ///
-    /// Returns addressed [`PubkyResource`] entries.
+    /// Returns addressed [`PubkyResource`] entries. Fails over to the owner's mirror
+    /// homeservers like [`PublicStorage::get`].
     ///
     /// # Errors
     /// - Returns [`crate::errors::RequestError::Validation`] if `addr` cannot be converted into an addressed directory ending with `/`.
@@ -170,24 +171,25 @@ impl<'a> ListBuilder<'a> {
             }
         }
 
-        // 2) Build request per scope
-        let rb = match self.scope {
+        // 2) Send per scope; public listings fail over to the owner's mirrors
+        match self.scope {
             ListScope::Public(storage) => {
+                let resource = PubkyResource::from_transport_url(&url)?;
                 storage
-                    .client
-                    .cross_request(Method::GET, url.clone())
-                    .await?
+                    .read_with_failover(Method::GET, &resource, url.query(), |rb, _| {
+                        Self::fetch(rb)
+                    })
+                    .await
             }
             ListScope::Session(storage) => {
-                let rb = storage
-                    .client
-                    .cross_request(Method::GET, url.clone())
-                    .await?;
-                storage.attach_credential(rb).await?
+                let rb = storage.client.cross_request(Method::GET, url).await?;
+                Self::fetch(storage.attach_credential(rb).await?).await
             }
-        };
+        }
+    }
 
-        // 3) Send and parse
+    /// Send a prepared LIST request and parse the returned entries.
+    async fn fetch(rb: RequestBuilder) -> Result<Vec<PubkyResource>> {
         let resp = rb.send().await?;
         cross_log!(
             debug,
@@ -1,9 +1,14 @@
-use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use pubky_common::crypto::Hash;
+use reqwest::{
+    Method, RequestBuilder, Response, StatusCode,
+    header::{ACCEPT, ETAG, IF_MATCH, IF_NONE_MATCH},
+};
 
 use super::core::{PublicStorage, SessionStorage};
-use super::resource::{IntoPubkyResource, IntoResourcePath};
+use super::resource::{IntoPubkyResource, IntoResourcePath, PubkyResource};
 use super::stats::ResourceStats;
-use crate::{Result, cross_log, util::check_http_status};
+use crate::{Error, Pkdns, Result, cross_log, errors::RequestError, util::check_http_status};
 
 /// Interpret the result of a `HEAD` request into a shared outcome used by both
 /// session and public storage clients.
@@ -38,6 +43,57 @@ async fn send_head(rb: RequestBuilder) -> Result<Option<Response>> {
     interpret_head(resp).await
 }
 
+/// Send a prepared `GET` request and check the body against the content hash in its `ETag`.
+///
+/// With `require_etag`, a response without an `ETag` is rejected instead of returned unchecked.
+async fn send_verified(rb: RequestBuilder, require_etag: bool) -> Result<Vec<u8>> {
+    let resp = send_checked(rb).await?;
+    let url = resp.url().clone();
+    let expected = match resp.headers().get(ETAG) {
+        Some(etag) => Some(
+            etag_content_hash(etag.to_str().unwrap_or_default()).ok_or_else(|| {
+                RequestError::ContentHashMismatch {
+                    message: format!("{url} sent an ETag that is not a content hash"),
+                }
+            })?,
+        ),
+        None if require_etag => {
+            return Err(RequestError::ContentHashMismatch {
+                message: format!("{url} sent no ETag to verify the content against"),
+            }
+            .into());
+        }
+        None => None,
+    };
+
+    let body = resp.bytes().await?;
+    if expected.is_some_and(|expected| pubky_common::crypto::hash(&body).as_bytes() != &expected) {
+        return Err(RequestError::ContentHashMismatch {
+            message: format!("content served by {url} does not match its ETag"),
+        }
+        .into());
+    }
+    Ok(body.into())
+}
+
+/// Decode the blake3 content hash a homeserver sends as `ETag` (`"<base64>"`).
+pub(crate) fn etag_content_hash(etag: &str) -> Option<[u8; 32]> {
+    let etag = etag.strip_prefix("W/").unwrap_or(etag).trim_matches('"');
+    STANDARD.decode(etag).ok()?.try_into().ok()
+}
+
+/// Whether a failed read from the primary homeserver is worth retrying on a mirror.
+///
+/// Client errors such as `404` are authoritative and returned as is.
+fn should_fail_over(err: &Error) -> bool {
+    match err {
+        Error::Request(RequestError::Server { status, .. }) => status.is_server_error(),
+        Error::Request(RequestError::Transport(_) | RequestError::ContentHashMismatch { .. })
+        | Error::Pkarr(_) => true,
+        _ => false,
+    }
+}
+
 //
 // SessionStorage (authenticated, as-me)
 //
@@ -116,6 +172,39 @@ impl SessionStorage {
         let rb = self.request(Method::DELETE, path).await?;
         send_checked(rb).await
     }
+
+    /// `PUT` `content`, or `DELETE` if `None`, only while the homeserver version of
+    /// `path` still has the content hash `base` (`None`: while it does not exist).
+    ///
+    /// The homeserver checks this atomically with the write (`If-Match` /
+    /// `If-None-Match`). Returns `false` if the file changed in the meantime.
+    pub(crate) async fn write_if<P: IntoResourcePath>(
+        &self,
+        path: P,
+        base: Option<&Hash>,
+        content: Option<Vec<u8>>,
+    ) -> Result<bool> {
+        let rb = match content {
+            Some(content) => self.request(Method::PUT, path).await?.body(content),
+            None => self.request(Method::DELETE, path).await?,
+        };
+        let rb = match base {
+            Some(hash) => rb.header(
+                IF_MATCH,
+                format!("\"{}\"", STANDARD.encode(hash.as_bytes())),
+            ),
+            None => rb.header(IF_NONE_MATCH, "*"),
+        };
+        match send_checked(rb).await {
+            Ok(_) => Ok(true),
+            Err(Error::Request(RequestError::Server { status, .. }))
+                if status == StatusCode::PRECONDITION_FAILED =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
 }
 
 //
@@ -125,6 +214,11 @@ impl SessionStorage {
 impl PublicStorage {
     /// HTTP `GET` for an **addressed resource** (`pubky://<pk>/<path>`, `pubky<pk>/<path>`, or `(PublicKey, path)` tuple).
     ///
+    /// Tries the owner's primary homeserver first. When it is unreachable or fails with a
+    /// server error, the mirror homeservers listed in the owner's `_pubky` record are tried
+    /// in order. The body is streamed as is; use [`Self::get_bytes`] to verify it against
+    /// its content hash.
+    ///
     /// # Examples
     /// ```no_run
     /// # async fn ex(user: pubky::PublicKey) -> pubky::Result<()> {
@@ -144,28 +238,149 @@ impl PublicStorage {
     /// - [`crate::errors::Error::Parse`] if `addr` cannot be converted into a valid
     ///   addressed resource/URL.
     pub async fn get<A: IntoPubkyResource>(&self, addr: A) -> Result<Response> {
-        let rb = self.request(Method::GET, addr).await?;
-        send_checked(rb).await
+        let resource: PubkyResource = addr.into_pubky_resource()?;
+        self.read_with_failover(Method::GET, &resource, None, |rb, _| send_checked(rb))
+            .await
+    }
+
+    /// HTTP `GET` the content of an **addressed resource**, failing over to the owner's mirrors.
+    ///
+    /// Like [`Self::get`], but also fails over when content does not match its hash.
+    /// Content is checked against the blake3 hash in the response `ETag`. Mirrors that send
+    /// no `ETag` or mismatching content are skipped.
+    ///
+    /// The `ETag` comes from the homeserver that serves the content, so this detects
+    /// corruption in storage or transit, not a mirror that deliberately serves other
+    /// content under a matching `ETag`.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # async fn ex(user: pubky::PublicKey) -> pubky::Result<()> {
+    /// let storage = pubky::PublicStorage::new()?;
+    /// let bytes = storage.get_bytes((&user, "/pub/my-cool-app/file.txt")).await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # Errors
+    /// - The error of the primary homeserver when no mirror serves verified content. Client
+    ///   errors of the primary (e.g. `404 Not Found`) are returned without trying mirrors.
+    /// - [`crate::errors::Error::Parse`] if `addr` cannot be converted into a valid
+    ///   addressed resource/URL.
+    pub async fn get_bytes<A: IntoPubkyResource>(&self, addr: A) -> Result<Vec<u8>> {
+        let resource: PubkyResource = addr.into_pubky_resource()?;
+        self.get_verified(&resource, None).await
+    }
+
+    /// `GET` `resource` with failover, verifying the content against its `ETag`.
+    pub(crate) async fn get_verified(
+        &self,
+        resource: &PubkyResource,
+        accept: Option<&'static str>,
+    ) -> Result<Vec<u8>> {
+        self.read_with_failover(Method::GET, resource, None, |rb, mirror| {
+            let rb = match accept {
+                Some(accept) => rb.header(ACCEPT, accept),
+                None => rb,
+            };
+            send_verified(rb, mirror)
+        })
+        .await
     }
 
-    /// HEAD existence check for an addressed resource.
+    /// Read `resource` from the owner's primary homeserver, then from each mirror listed in
+    /// the owner's `_pubky` record while the read fails with an error worth failing over.
+    ///
+    /// `attempt` sends the prepared request; its flag is `true` for mirrors. Client errors
+    /// of the primary (e.g. `404 Not Found`) are returned without trying mirrors, and the
+    /// primary's error is returned when no mirror succeeds.
+    pub(crate) async fn read_with_failover<T, F, Fut>(
+        &self,
+        method: Method,
+        resource: &PubkyResource,
+        query: Option<&str>,
+        attempt: F,
+    ) -> Result<T>
+    where
+        F: Fn(RequestBuilder, bool) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let rb = self.request(method.clone(), resource, query).await?;
+        let primary_err = match attempt(rb, false).await {
+            Ok(value) => return Ok(value),
+            Err(err) if !should_fail_over(&err) => return Err(err),
+            Err(err) => err,
+        };
+        cross_log!(
+            warn,
+            "Primary homeserver of {} failed: {}; trying mirrors",
+            resource.owner,
+            primary_err
+        );
+
+        let homeservers = match Pkdns::with_client(self.client.clone())
+            .get_homeservers_of(&resource.owner)
+            .await
+        {
+            Ok(homeservers) => homeservers,
+            Err(err) => {
+                cross_log!(
+                    warn,
+                    "Could not resolve mirrors of {}: {}",
+                    resource.owner,
+                    err
+                );
+                return Err(primary_err);
+            }
+        };
+        let target = match query {
+            Some(query) => format!("{}?{query}", resource.path.as_str()),
+            None => resource.path.as_str().to_string(),
+        };
+        for mirror in homeservers.iter().skip(1) {
+            let result = match self
+                .client
+                .cross_request_via_homeserver(method.clone(), mirror, &resource.owner, &target)
+                .await
+            {
+                Ok(rb) => attempt(rb, true).await,
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(value) => {
+                    cross_log!(info, "Served {} from mirror {}", resource, mirror);
+                    return Ok(value);
+                }
+                Err(err) => {
+                    cross_log!(warn, "Mirror {} failed for {}: {}", mirror, resource, err);
+                }
+            }
+        }
+        Err(primary_err)
+    }
+
+    /// HEAD existence check for an addressed resource, failing over to mirrors like [`Self::get`].
     ///
     /// # Errors
     /// - Propagates transport failures while issuing the `HEAD` request.
     /// - Returns [`crate::errors::Error::Parse`] if `addr` cannot be converted into a valid addressed resource.
     pub async fn exists<A: IntoPubkyResource>(&self, addr: A) -> Result<bool> {
-        let rb = self.request(Method::HEAD, addr).await?;
-        Ok(send_head(rb).await?.is_some())
+        let resource: PubkyResource = addr.into_pubky_resource()?;
+        Ok(self
+            .read_with_failover(Method::HEAD, &resource, None, |rb, _| send_head(rb))
+            .await?
+            .is_some())
     }
 
-    /// Metadata via `HEAD` for an addressed resource (no body).
+    /// Metadata via `HEAD` for an addressed resource (no body), failing over to mirrors like
+    /// [`Self::get`].
     ///
     /// # Errors
     /// - Propagates transport failures while issuing the `HEAD` request.
     /// - Returns [`crate::errors::Error::Parse`] if `addr` cannot be converted into a valid addressed resource.
     pub async fn stats<A: IntoPubkyResource>(&self, addr: A) -> Result<Option<ResourceStats>> {
-        let rb = self.request(Method::HEAD, addr).await?;
-        Ok(send_head(rb)
+        let resource: PubkyResource = addr.into_pubky_resource()?;
+        Ok(self
+            .read_with_failover(Method::HEAD, &resource, None, |rb, _| send_head(rb))
             .await?
             .map(|resp| ResourceStats::from_headers(resp.headers())))
     }
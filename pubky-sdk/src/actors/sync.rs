@@ -0,0 +1,868 @@
+//! Sync engine: an offline-first local replica of chosen storage prefixes.
+//!
+//! A [`SyncEngine`] keeps a copy of every file under the chosen prefixes of the
+//! session user's storage in a [`ReplicaStore`]: files on disk natively
+//! ([`FileReplicaStore`]), `IndexedDB` in the browser. Reads and writes go to the
+//! replica and work offline; writes are queued until [`SyncEngine::push`].
+//!
+//! - **Push** sends queued writes oldest first. Each write only applies while the
+//!   homeserver's content hash (`ETag`) is still the version the local change was
+//!   based on; if someone else changed the file in the meantime, the path becomes a
+//!   [`SyncConflict`] instead of being overwritten.
+//! - **Pull** applies remote changes from the user's event feed, resuming from the
+//!   last applied [`EventCursor`]. The first pull lists the prefixes instead, and so
+//!   does a pull whose cursor points into event history the homeserver has pruned.
+//!   Remote changes to a path with a pending local write become conflicts as well.
+//!
+//! Conflicts are settled with [`SyncEngine::resolve_conflict`]. Network failures
+//! leave the queue and cursor where they were, so the next sync picks up from there.
+//!
+//! ```no_run
+//! # async fn example(session: pubky::PubkySession) -> pubky::Result<()> {
+//! use pubky::{FileReplicaStore, SyncEngine};
+//!
+//! let store = FileReplicaStore::new("./replica")?;
+//! let engine = SyncEngine::builder(&session, store)
+//!     .prefix("/pub/my-cool-app/")
+//!     .open()
+//!     .await?;
+//!
+//! // Works offline: only touches the replica.
+//! engine.put("/pub/my-cool-app/notes.txt", b"hello").await?;
+//!
+//! // Once online: push queued writes, then pull remote changes.
+//! let report = engine.sync().await?;
+//! for conflict in engine.conflicts().await {
+//!     println!("{} changed on both sides", conflict.path);
+//! }
+//! # let _ = report;
+//! # Ok(()) }
+//! ```
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use futures_util::TryStreamExt;
+use pubky_common::crypto::{Hash, hash};
+use reqwest::StatusCode;
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::{
+    Event, EventCursor, EventStreamBuilder, EventType, IntoResourcePath, PubkyResource,
+    PubkySession, ResourcePath,
+    actors::storage::verbs::etag_content_hash,
+    cross_log,
+    errors::{Error, RequestError, Result, SyncError},
+};
+
+mod state;
+mod store;
+
+use state::{Entry, ReplicaState, StoredConflict};
+#[cfg(not(target_arch = "wasm32"))]
+pub use store::FileReplicaStore;
+pub use store::ReplicaStore;
+
+/// Events requested per page while pulling.
+const EVENT_PAGE: u16 = 100;
+
+/// Entries requested per page while listing a prefix on the first pull.
+const LIST_PAGE: u16 = 100;
+
+/// A file changed both locally and on the homeserver since it was last in sync.
+///
+/// The local version stays readable through [`SyncEngine::get`] and is not pushed
+/// until the conflict is resolved with [`SyncEngine::resolve_conflict`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncConflict {
+    /// Path of the file.
+    pub path: String,
+    /// Content hash of the local version, `None` if deleted locally.
+    pub local: Option<Hash>,
+    /// Content hash of the homeserver version, `None` if deleted there.
+    pub remote: Option<Hash>,
+}
+
+/// How [`SyncEngine::resolve_conflict`] settles a [`SyncConflict`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Keep the local version; the next push overwrites the homeserver's.
+    KeepLocal,
+    /// Discard the local change and take the homeserver's current version.
+    KeepRemote,
+}
+
+/// What a [`SyncEngine::push`], [`SyncEngine::pull`] or [`SyncEngine::sync`] changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Paths written to or deleted from the homeserver.
+    pub pushed: Vec<String>,
+    /// Paths updated or removed locally from remote changes.
+    pub pulled: Vec<String>,
+    /// Paths that became conflicts.
+    pub conflicts: Vec<String>,
+}
+
+/// Builder for a [`SyncEngine`], created with [`SyncEngine::builder`].
+#[derive(Debug)]
+#[must_use]
+pub struct SyncEngineBuilder {
+    session: PubkySession,
+    store: Arc<dyn ReplicaStore>,
+    prefixes: Vec<String>,
+}
+
+impl SyncEngineBuilder {
+    /// Replicate every file under `prefix` (a directory path ending with `/`).
+    /// Repeatable.
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefixes.push(prefix.into());
+        self
+    }
+
+    /// Load the replica from the store, or start an empty one.
+    ///
+    /// Opening a replica with different prefixes than last time keeps pending
+    /// writes, and the next pull lists the prefixes again.
+    ///
+    /// # Errors
+    /// - Returns [`Error::Request`] if no prefix was given or a prefix is not a
+    ///   valid directory path.
+    /// - Returns [`SyncError::Store`] if the store fails, its state is corrupt, it
+    ///   holds the replica of another user, or another engine has it open.
+    pub async fn open(self) -> Result<SyncEngine> {
+        let mut prefixes = BTreeSet::new();
+        for prefix in &self.prefixes {
+            let prefix = ResourcePath::parse(prefix)?;
+            if !prefix.as_str().ends_with('/') {
+                return Err(RequestError::Validation {
+                    message: format!("sync prefix {prefix} must end with '/'"),
+                }
+                .into());
+            }
+            prefixes.insert(prefix.as_str().to_string());
+        }
+        if prefixes.is_empty() {
+            return Err(RequestError::Validation {
+                message: "at least one sync prefix must be specified".into(),
+            }
+            .into());
+        }
+        let prefixes: Vec<String> = prefixes.into_iter().collect();
+
+        self.store.lock().await?;
+        let user = self.session.public_key().z32();
+        let mut state = match self.store.load_state().await? {
+            Some(bytes) => serde_json::from_slice(&bytes).map_err(|e| SyncError::Store {
+                message: format!("replica state is corrupt: {e}"),
+            })?,
+            None => ReplicaState::new(user.clone()),
+        };
+        state.count_references();
+        if state.user != user {
+            return Err(SyncError::Store {
+                message: format!("the store holds the replica of {}", state.user),
+            }
+            .into());
+        }
+
+        let mut released = Vec::new();
+        if state.prefixes != prefixes {
+            cross_log!(
+                info,
+                "Sync prefixes changed; the next pull lists them again"
+            );
+            state.prefixes = prefixes;
+            state.cursor = None;
+            let uncovered: Vec<String> = state
+                .entries
+                .iter()
+                .filter(|(path, entry)| !entry.is_dirty() && !state.covers(path))
+                .map(|(path, _)| path.clone())
+                .collect();
+            for path in uncovered {
+                released.extend(state.remove_entry(&path));
+            }
+        }
+
+        let engine = SyncEngine {
+            inner: Arc::new(Inner {
+                session: self.session,
+                store: self.store,
+                state: Mutex::new(state),
+                network: Mutex::new(()),
+            }),
+        };
+        let state = engine.inner.state.lock().await;
+        engine.save(&state).await?;
+        for hash in released {
+            engine.release(&state, Some(hash)).await?;
+        }
+        drop(state);
+        Ok(engine)
+    }
+}
+
+/// Offline-first replica of chosen prefixes of the session user's storage.
+///
+/// See the [module docs](self) for how pushes, pulls and conflicts work. Cloning is
+/// cheap and clones share the replica.
+#[derive(Debug, Clone)]
+pub struct SyncEngine {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    session: PubkySession,
+    store: Arc<dyn ReplicaStore>,
+    state: Mutex<ReplicaState>,
+    /// Serializes pushes and pulls; local reads and writes never wait for it.
+    network: Mutex<()>,
+}
+
+/// How a remote version of a path relates to the local entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RemoteChange {
+    /// The replica already knows this version.
+    Known,
+    /// No local change: take the remote version.
+    Apply,
+    /// The remote version equals the pending local one.
+    Converged,
+    /// Both sides changed differently.
+    Conflict,
+}
+
+impl SyncEngine {
+    /// Start building an engine replicating `session`'s user into `store`.
+    pub fn builder<S: ReplicaStore + 'static>(
+        session: &PubkySession,
+        store: S,
+    ) -> SyncEngineBuilder {
+        SyncEngineBuilder {
+            session: session.clone(),
+            store: Arc::new(store),
+            prefixes: Vec::new(),
+        }
+    }
+
+    /// Read the local version of a file, or `None` if it does not exist locally.
+    ///
+    /// # Errors
+    /// - Returns [`SyncError::OutsideReplica`] if `path` is not under a synced prefix.
+    /// - Returns [`SyncError::Store`] if the store fails or lost the content.
+    pub async fn get<P: IntoResourcePath>(&self, path: P) -> Result<Option<Vec<u8>>> {
+        let path = self.replica_path(path).await?;
+        let local = self
+            .lock_state()
+            .await
+            .entries
+            .get(&path)
+            .and_then(|e| e.local);
+        match local {
+            Some(hash) => Ok(Some(self.read_content(&hash).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Paths of all files that exist locally, sorted.
+    pub async fn list(&self) -> Vec<String> {
+        let state = self.lock_state().await;
+        state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.local.is_some())
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    /// Write a file locally and queue it for the next push.
+    ///
+    /// # Errors
+    /// - Returns [`SyncError::OutsideReplica`] if `path` is not under a synced prefix.
+    /// - Returns [`SyncError::Store`] if the store fails.
+    pub async fn put<P: IntoResourcePath>(&self, path: P, content: &[u8]) -> Result<()> {
+        let path = self.replica_path(path).await?;
+        self.set_local(&path, Some(content)).await
+    }
+
+    /// Delete a file locally and queue the deletion for the next push.
+    ///
+    /// # Errors
+    /// - Returns [`SyncError::OutsideReplica`] if `path` is not under a synced prefix.
+    /// - Returns [`SyncError::Store`] if the store fails.
+    pub async fn delete<P: IntoResourcePath>(&self, path: P) -> Result<()> {
+        let path = self.replica_path(path).await?;
+        self.set_local(&path, None).await
+    }
+
+    /// Paths with a local change not pushed yet, oldest first.
+    pub async fn pending(&self) -> Vec<String> {
+        self.lock_state().await.queue.clone()
+    }
+
+    /// Files waiting for [`Self::resolve_conflict`].
+    pub async fn conflicts(&self) -> Vec<SyncConflict> {
+        let state = self.lock_state().await;
+        state
+            .conflicts
+            .keys()
+            .filter_map(|path| state.conflict(path))
+            .collect()
+    }
+
+    /// Last applied event, or `None` before the first pull.
+    pub async fn cursor(&self) -> Option<EventCursor> {
+        self.lock_state().await.cursor.map(EventCursor::new)
+    }
+
+    /// Settle a conflict on `path`.
+    ///
+    /// [`ConflictResolution::KeepRemote`] downloads the homeserver's current version.
+    ///
+    /// # Errors
+    /// - Returns [`SyncError::NoConflict`] if `path` has no conflict.
+    /// - Propagates transport and store failures.
+    pub async fn resolve_conflict<P: IntoResourcePath>(
+        &self,
+        path: P,
+        resolution: ConflictResolution,
+    ) -> Result<()> {
+        let path = path.into_abs_path()?.as_str().to_string();
+        let _network = self.inner.network.lock().await;
+        if !self.lock_state().await.conflicts.contains_key(&path) {
+            return Err(SyncError::NoConflict { path }.into());
+        }
+        let remote_content = match resolution {
+            ConflictResolution::KeepLocal => None,
+            ConflictResolution::KeepRemote => Some(self.fetch(&path).await?),
+        };
+
+        let mut state = self.lock_state().await;
+        let Some(conflict) = state.conflicts.remove(&path) else {
+            return Err(SyncError::NoConflict { path }.into());
+        };
+        let local = state.entries.get(&path).and_then(|entry| entry.local);
+        let resolved = match remote_content {
+            None => Entry {
+                local,
+                base: conflict.remote,
+            },
+            Some(content) => {
+                let remote = match content {
+                    Some(content) => Some(self.write_content(&content).await?),
+                    None => None,
+                };
+                Entry {
+                    local: remote,
+                    base: remote,
+                }
+            }
+        };
+        state.set_local(&path, resolved.local);
+        state.entries.entry(path.clone()).or_default().base = resolved.base;
+        if resolved.is_dirty() {
+            state.enqueue(&path);
+        }
+        state.settle(&path);
+        self.save(&state).await?;
+        self.release(&state, local).await
+    }
+
+    /// Push queued writes, then pull remote changes.
+    ///
+    /// # Errors
+    /// - Propagates the first transport or store failure; progress up to it is kept.
+    pub async fn sync(&self) -> Result<SyncReport> {
+        let _network = self.inner.network.lock().await;
+        let mut report = SyncReport::default();
+        self.push_queue(&mut report).await?;
+        self.pull_changes(&mut report).await?;
+        Ok(report)
+    }
+
+    /// Send queued writes to the homeserver, oldest first.
+    ///
+    /// A path whose homeserver version changed since the local change was made
+    /// becomes a conflict and is skipped.
+    ///
+    /// # Errors
+    /// - Propagates the first transport or store failure; the failed write and
+    ///   everything after it stay queued.
+    pub async fn push(&self) -> Result<SyncReport> {
+        let _network = self.inner.network.lock().await;
+        let mut report = SyncReport::default();
+        self.push_queue(&mut report).await?;
+        Ok(report)
+    }
+
+    /// Apply remote changes since the last pull.
+    ///
+    /// If the homeserver pruned events the replica has not seen yet, the prefixes
+    /// are listed again instead. Pending writes are kept either way.
+    ///
+    /// # Errors
+    /// - Propagates the first transport or store failure; events applied before
+    ///   it are kept.
+    pub async fn pull(&self) -> Result<SyncReport> {
+        let _network = self.inner.network.lock().await;
+        let mut report = SyncReport::default();
+        self.pull_changes(&mut report).await?;
+        Ok(report)
+    }
+
+    // --- Local replica ---
+
+    async fn lock_state(&self) -> MutexGuard<'_, ReplicaState> {
+        self.inner.state.lock().await
+    }
+
+    /// Normalize `path` and check that it lies under a synced prefix.
+    async fn replica_path<P: IntoResourcePath>(&self, path: P) -> Result<String> {
+        let path = path.into_abs_path()?.as_str().to_string();
+        if self.lock_state().await.covers(&path) {
+            Ok(path)
+        } else {
+            Err(SyncError::OutsideReplica { path }.into())
+        }
+    }
+
+    async fn read_content(&self, hash: &Hash) -> Result<Vec<u8>> {
+        self.inner.store.read_content(hash).await?.ok_or_else(|| {
+            SyncError::Store {
+                message: format!("content {} is missing from the store", hash.to_hex()),
+            }
+            .into()
+        })
+    }
+
+    async fn set_local(&self, path: &str, content: Option<&[u8]>) -> Result<()> {
+        let mut state = self.lock_state().await;
+        let local = match content {
+            Some(content) => Some(self.write_content(content).await?),
+            None => None,
+        };
+        let previous = state.set_local(path, local);
+        if state
+            .conflicts
+            .get(path)
+            .is_some_and(|conflict| conflict.remote == local)
+        {
+            // The local edit matches the homeserver again: nothing left to resolve.
+            state.conflicts.remove(path);
+            if let Some(entry) = state.entries.get_mut(path) {
+                entry.base = local;
+            }
+        }
+        if state.entries.get(path).is_some_and(Entry::is_dirty) {
+            state.enqueue(path);
+        }
+        state.settle(path);
+        self.save(&state).await?;
+        self.release(&state, previous).await
+    }
+
+    /// Store `content` and return its hash.
+    ///
+    /// Call with the state locked, so a concurrent [`Self::release`] cannot delete
+    /// the content before an entry refers to it.
+    async fn write_content(&self, content: &[u8]) -> Result<Hash> {
+        let hash = hash(content);
+        self.inner.store.write_content(&hash, content).await?;
+        Ok(hash)
+    }
+
+    async fn save(&self, state: &ReplicaState) -> Result<()> {
+        let bytes = serde_json::to_vec(state).map_err(|e| SyncError::Store {
+            message: format!("encoding the replica state failed: {e}"),
+        })?;
+        self.inner.store.save_state(&bytes).await
+    }
+
+    /// Save the state, then release the content in `released`.
+    async fn commit(&self, released: Vec<Hash>) -> Result<()> {
+        let state = self.lock_state().await;
+        self.save(&state).await?;
+        for hash in released {
+            self.release(&state, Some(hash)).await?;
+        }
+        Ok(())
+    }
+
+    /// Delete content nothing refers to anymore. Runs after the state is saved, so
+    /// a crash leaves unused content behind rather than a missing one.
+    async fn release(&self, state: &ReplicaState, hash: Option<Hash>) -> Result<()> {
+        match hash {
+            Some(hash) if !state.references(&hash) => self.inner.store.delete_content(&hash).await,
+            _ => Ok(()),
+        }
+    }
+
+    // --- Push ---
+
+    async fn push_queue(&self, report: &mut SyncReport) -> Result<()> {
+        let queue = self.lock_state().await.queue.clone();
+        for path in queue {
+            self.push_path(&path, report).await?;
+        }
+        Ok(())
+    }
+
+    async fn push_path(&self, path: &str, report: &mut SyncReport) -> Result<()> {
+        let entry = {
+            let state = self.lock_state().await;
+            if state.conflicts.contains_key(path) {
+                return Ok(());
+            }
+            match state.entries.get(path) {
+                Some(entry) if entry.is_dirty() => entry.clone(),
+                _ => return Ok(()),
+            }
+        };
+
+        loop {
+            let remote = self.remote_hash(path).await?;
+            if remote != entry.base && remote != entry.local {
+                cross_log!(info, "Not pushing {path}: it changed on the homeserver");
+                let mut state = self.lock_state().await;
+                state
+                    .conflicts
+                    .insert(path.to_string(), StoredConflict { remote });
+                report.conflicts.push(path.to_string());
+                return self.save(&state).await;
+            }
+            if remote == entry.local {
+                break;
+            }
+
+            let content = match &entry.local {
+                Some(hash) => Some(self.read_content(hash).await?),
+                None => None,
+            };
+            let storage = self.inner.session.storage();
+            if storage.write_if(path, remote.as_ref(), content).await? {
+                report.pushed.push(path.to_string());
+                break;
+            }
+            // Someone wrote the file after the `HEAD`: look at it again.
+            cross_log!(debug, "{path} changed while pushing it; checking again");
+        }
+
+        // The homeserver now has the pushed version; a newer local edit is based on it.
+        let mut state = self.lock_state().await;
+        state.entries.entry(path.to_string()).or_default().base = entry.local;
+        state.settle(path);
+        self.save(&state).await
+    }
+
+    /// Content hash of the homeserver version of `path`, from a `HEAD` request.
+    async fn remote_hash(&self, path: &str) -> Result<Option<Hash>> {
+        let Some(stats) = self.inner.session.storage().stats(path).await? else {
+            return Ok(None);
+        };
+        stats
+            .etag
+            .as_deref()
+            .and_then(etag_content_hash)
+            .map(|hash| Some(Hash::from_bytes(hash)))
+            .ok_or_else(|| {
+                RequestError::ContentHashMismatch {
+                    message: format!("{path} has no content hash ETag to detect conflicts with"),
+                }
+                .into()
+            })
+    }
+
+    // --- Pull ---
+
+    async fn pull_changes(&self, report: &mut SyncReport) -> Result<()> {
+        let (cursor, prefixes) = {
+            let state = self.lock_state().await;
+            (state.cursor, state.prefixes.clone())
+        };
+        let Some(cursor) = cursor else {
+            return self.snapshot(&prefixes, report).await;
+        };
+        match self.follow_events(cursor, &prefixes, report).await {
+            Err(e) if is_gone(&e) => {
+                cross_log!(
+                    info,
+                    "Events after cursor {cursor} were pruned; listing the prefixes again"
+                );
+                // Pending writes stay queued; the listing reconciles them as usual.
+                let mut state = self.lock_state().await;
+                state.cursor = None;
+                self.save(&state).await?;
+                drop(state);
+                self.snapshot(&prefixes, report).await
+            }
+            result => result,
+        }
+    }
+
+    /// Apply events after `cursor` page by page, saving the state once per page. A
+    /// cursor of 0 starts from the oldest retained event.
+    async fn follow_events(
+        &self,
+        mut cursor: u64,
+        prefixes: &[String],
+        report: &mut SyncReport,
+    ) -> Result<()> {
+        loop {
+            let after = (cursor > 0).then(|| EventCursor::new(cursor));
+            let events = self.events(after, prefixes, false).await?;
+            let mut released = Vec::new();
+            let applied: Result<()> = async {
+                for event in &events {
+                    let remote = match &event.event_type {
+                        EventType::Put { content_hash } => Some(*content_hash),
+                        EventType::Delete => None,
+                    };
+                    cursor = event.cursor.id();
+                    let path = event.resource.path.as_str();
+                    released.extend(
+                        self.apply_remote(path, remote, None, Some(cursor), report)
+                            .await?,
+                    );
+                }
+                Ok(())
+            }
+            .await;
+            self.commit(released).await?;
+            applied?;
+            if events.len() < usize::from(EVENT_PAGE) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// First pull: list the prefixes and download every file, saving the state once
+    /// per listed page.
+    async fn snapshot(&self, prefixes: &[String], report: &mut SyncReport) -> Result<()> {
+        // Events after this cursor may repeat changes seen by the listing; applying
+        // them again is harmless. Without any event under the prefixes, 0 follows the
+        // events from the oldest retained one, as any fixed id could be below the
+        // homeserver's retention watermark.
+        let cursor = self
+            .events(None, prefixes, true)
+            .await?
+            .first()
+            .map_or(0, |event| event.cursor.id());
+
+        let storage = self.inner.session.storage();
+        let mut seen = BTreeSet::new();
+        for prefix in prefixes {
+            let mut page_cursor: Option<String> = None;
+            loop {
+                let mut list = storage.list(prefix.as_str())?.limit(LIST_PAGE);
+                if let Some(page_cursor) = &page_cursor {
+                    list = list.cursor(page_cursor);
+                }
+                let page = match list.send().await {
+                    Err(e) if is_not_found(&e) => Vec::new(),
+                    page => page?,
+                };
+                let mut released = Vec::new();
+                let applied: Result<()> = async {
+                    for resource in &page {
+                        let path = resource.path.as_str();
+                        if path.ends_with('/') {
+                            continue;
+                        }
+                        if let Some(content) = self.fetch(path).await? {
+                            seen.insert(path.to_string());
+                            let remote = hash(&content);
+                            released.extend(
+                                self.apply_remote(path, Some(remote), Some(content), None, report)
+                                    .await?,
+                            );
+                        }
+                    }
+                    Ok(())
+                }
+                .await;
+                self.commit(released).await?;
+                applied?;
+                if page.len() < usize::from(LIST_PAGE) {
+                    break;
+                }
+                page_cursor = page.last().map(PubkyResource::to_pubky_url);
+            }
+        }
+
+        let gone: Vec<String> = {
+            let state = self.lock_state().await;
+            state
+                .entries
+                .iter()
+                .filter(|(path, entry)| {
+                    entry.base.is_some() && state.covers(path) && !seen.contains(*path)
+                })
+                .map(|(path, _)| path.clone())
+                .collect()
+        };
+        let mut released = Vec::new();
+        for path in gone {
+            released.extend(self.apply_remote(&path, None, None, None, report).await?);
+        }
+
+        self.lock_state().await.cursor = Some(cursor);
+        self.commit(released).await
+    }
+
+    /// Events of the user under `prefixes`: the next page after `cursor`, or only
+    /// the newest one.
+    async fn events(
+        &self,
+        cursor: Option<EventCursor>,
+        prefixes: &[String],
+        newest: bool,
+    ) -> Result<Vec<Event>> {
+        let session = &self.inner.session;
+        let mut builder =
+            EventStreamBuilder::for_user(session.client().clone(), &session.public_key(), cursor)
+                .session(session);
+        for prefix in prefixes {
+            builder = builder.path(prefix.as_str());
+        }
+        builder = if newest {
+            builder.reverse().limit(1)
+        } else {
+            builder.limit(EVENT_PAGE)
+        };
+        builder.subscribe().await?.try_collect().await
+    }
+
+    /// Download the homeserver version of `path`, or `None` if it does not exist.
+    async fn fetch(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        match self.inner.session.storage().get(path).await {
+            Ok(response) => Ok(Some(response.bytes().await?.into())),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Reconcile the remote version `remote` of `path` with the replica, and advance
+    /// the cursor to `cursor` if given. Returns the content hash the replica stopped
+    /// using, to [`Self::commit`] with the state.
+    ///
+    /// `content` is the remote content if already downloaded. Otherwise it is
+    /// fetched when needed; if the homeserver no longer serves that version, a later
+    /// event carries the newer one and this one is skipped.
+    async fn apply_remote(
+        &self,
+        path: &str,
+        remote: Option<Hash>,
+        content: Option<Vec<u8>>,
+        cursor: Option<u64>,
+        report: &mut SyncReport,
+    ) -> Result<Option<Hash>> {
+        let mut content = content;
+        loop {
+            {
+                let mut state = self.lock_state().await;
+                let change = classify(&state, path, remote);
+                let covered = state.covers(path);
+                let needs_content = covered
+                    && change == RemoteChange::Apply
+                    && remote.is_some()
+                    && content.is_none();
+                if !needs_content {
+                    let mut released = None;
+                    if covered {
+                        if let (RemoteChange::Apply, Some(content)) = (change, &content) {
+                            self.write_content(content).await?;
+                        }
+                        released = reconcile(&mut state, path, remote, change, report);
+                    }
+                    if let Some(cursor) = cursor {
+                        state.cursor = Some(cursor);
+                    }
+                    return Ok(released);
+                }
+            }
+
+            match (self.fetch(path).await?, remote) {
+                (Some(fetched), Some(remote)) if hash(&fetched) == remote => {
+                    content = Some(fetched);
+                }
+                _ => {
+                    cross_log!(debug, "Skipping superseded remote version of {path}");
+                    let mut state = self.lock_state().await;
+                    if let Some(cursor) = cursor {
+                        state.cursor = Some(cursor);
+                    }
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+fn classify(state: &ReplicaState, path: &str, remote: Option<Hash>) -> RemoteChange {
+    let entry = state.entries.get(path).cloned().unwrap_or_default();
+    if entry.base == remote {
+        RemoteChange::Known
+    } else if !entry.is_dirty() {
+        RemoteChange::Apply
+    } else if entry.local == remote {
+        RemoteChange::Converged
+    } else {
+        RemoteChange::Conflict
+    }
+}
+
+/// Record `change` in the state and return the content hash it stopped using.
+fn reconcile(
+    state: &mut ReplicaState,
+    path: &str,
+    remote: Option<Hash>,
+    change: RemoteChange,
+    report: &mut SyncReport,
+) -> Option<Hash> {
+    match change {
+        RemoteChange::Known => {
+            // The homeserver is back at the version the local change is based on.
+            state.conflicts.remove(path);
+            None
+        }
+        RemoteChange::Apply => {
+            let previous = state.set_local(path, remote);
+            state.entries.entry(path.to_string()).or_default().base = remote;
+            state.settle(path);
+            report.pulled.push(path.to_string());
+            previous
+        }
+        RemoteChange::Converged => {
+            state.entries.entry(path.to_string()).or_default().base = remote;
+            state.conflicts.remove(path);
+            state.settle(path);
+            None
+        }
+        RemoteChange::Conflict => {
+            if state
+                .conflicts
+                .insert(path.to_string(), StoredConflict { remote })
+                .is_none()
+            {
+                report.conflicts.push(path.to_string());
+            }
+            None
+        }
+    }
+}
+
+/// Whether the event stream rejected a cursor older than the retained history.
+fn is_gone(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Request(RequestError::Server { status, .. }) if *status == StatusCode::GONE
+    )
+}
+
+fn is_not_found(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Request(RequestError::Server { status, .. }) if *status == StatusCode::NOT_FOUND
+    )
+}
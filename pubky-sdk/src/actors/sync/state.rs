@@ -0,0 +1,170 @@
+//! Persisted state of a [`super::SyncEngine`] replica.
+
+use std::collections::{BTreeMap, HashMap};
+
+use pubky_common::crypto::Hash;
+use serde::{Deserialize, Serialize};
+
+use super::SyncConflict;
+
+/// Everything a replica knows besides file contents, saved as JSON.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct ReplicaState {
+    /// z32 public key of the replicated user.
+    pub user: String,
+    /// Synced path prefixes, each ending with `/`.
+    pub prefixes: Vec<String>,
+    /// Last applied event, or `None` before the first pull. `Some(0)` when no event
+    /// under the prefixes was retained at the last listing.
+    pub cursor: Option<u64>,
+    /// Known files by path. Change `local` only through [`Self::set_local`] and
+    /// [`Self::remove_entry`], which keep `references` in step.
+    pub entries: BTreeMap<String, Entry>,
+    /// Paths with a local change to push, in the order they were first changed.
+    pub queue: Vec<String>,
+    /// Paths changed both locally and remotely, waiting for a resolution.
+    pub conflicts: BTreeMap<String, StoredConflict>,
+    /// Number of entries whose local version has each content hash; rebuilt on load
+    /// with [`Self::count_references`].
+    #[serde(skip)]
+    references: HashMap<Hash, usize>,
+}
+
+/// Local and last known remote version of one file.
+///
+/// The file has a pending write while `local` differs from `base`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct Entry {
+    /// Content hash of the local version, `None` if deleted locally.
+    #[serde(with = "hex_hash")]
+    pub local: Option<Hash>,
+    /// Content hash of the homeserver version the local one is based on, `None` if
+    /// the file did not exist there.
+    #[serde(with = "hex_hash")]
+    pub base: Option<Hash>,
+}
+
+impl Entry {
+    pub(super) fn is_dirty(&self) -> bool {
+        self.local != self.base
+    }
+}
+
+/// A conflict as persisted; see [`SyncConflict`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct StoredConflict {
+    #[serde(with = "hex_hash")]
+    pub remote: Option<Hash>,
+}
+
+impl ReplicaState {
+    /// An empty replica of `user`.
+    pub(super) fn new(user: String) -> Self {
+        Self {
+            user,
+            ..Self::default()
+        }
+    }
+
+    /// Whether `path` lies under one of the synced prefixes.
+    pub(super) fn covers(&self, path: &str) -> bool {
+        self.prefixes.iter().any(|prefix| path.starts_with(prefix))
+    }
+
+    /// Mark `path` as changed locally, keeping its place in the queue.
+    pub(super) fn enqueue(&mut self, path: &str) {
+        if !self.queue.iter().any(|queued| queued == path) {
+            self.queue.push(path.to_string());
+        }
+    }
+
+    /// Drop `path` from the queue, and its entry once nothing refers to a version.
+    pub(super) fn settle(&mut self, path: &str) {
+        let Some(entry) = self.entries.get(path) else {
+            return;
+        };
+        if !entry.is_dirty() {
+            self.queue.retain(|queued| queued != path);
+            if entry.local.is_none() {
+                self.entries.remove(path);
+            }
+        }
+    }
+
+    /// Set the local version of `path` and return the previous one.
+    pub(super) fn set_local(&mut self, path: &str, local: Option<Hash>) -> Option<Hash> {
+        let entry = self.entries.entry(path.to_string()).or_default();
+        let previous = std::mem::replace(&mut entry.local, local);
+        self.forget(previous);
+        if let Some(hash) = local {
+            *self.references.entry(hash).or_default() += 1;
+        }
+        previous
+    }
+
+    /// Drop the entry of `path` and return its local version.
+    pub(super) fn remove_entry(&mut self, path: &str) -> Option<Hash> {
+        let local = self.entries.remove(path).and_then(|entry| entry.local);
+        self.forget(local);
+        local
+    }
+
+    /// Whether any entry still uses the content with `hash`.
+    pub(super) fn references(&self, hash: &Hash) -> bool {
+        self.references.contains_key(hash)
+    }
+
+    /// Count the references of the entries, after loading the state.
+    pub(super) fn count_references(&mut self) {
+        self.references.clear();
+        for hash in self.entries.values().filter_map(|entry| entry.local) {
+            *self.references.entry(hash).or_default() += 1;
+        }
+    }
+
+    fn forget(&mut self, hash: Option<Hash>) {
+        let Some(hash) = hash else {
+            return;
+        };
+        if let Some(count) = self.references.get_mut(&hash) {
+            *count -= 1;
+            if *count == 0 {
+                self.references.remove(&hash);
+            }
+        }
+    }
+
+    pub(super) fn conflict(&self, path: &str) -> Option<SyncConflict> {
+        let stored = self.conflicts.get(path)?;
+        Some(SyncConflict {
+            path: path.to_string(),
+            local: self.entries.get(path).and_then(|entry| entry.local),
+            remote: stored.remote,
+        })
+    }
+}
+
+/// (De)serializes an optional blake3 hash as a hex string.
+mod hex_hash {
+    use pubky_common::crypto::Hash;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    #[expect(clippy::ref_option, reason = "signature required by #[serde(with)]")]
+    pub(super) fn serialize<S: Serializer>(
+        hash: &Option<Hash>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match hash {
+            Some(hash) => serializer.serialize_some(hash.to_hex().as_str()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Hash>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| Hash::from_hex(hex).map_err(D::Error::custom))
+            .transpose()
+    }
+}
@@ -0,0 +1,164 @@
+//! Local storage of a [`super::SyncEngine`] replica.
+
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use pubky_common::crypto::Hash;
+
+use crate::errors::Result;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::errors::SyncError;
+
+/// Persistent storage of a local replica.
+///
+/// A replica is one state document (synced files, queued writes, conflicts and the
+/// event cursor, encoded by the engine) plus file contents addressed by their blake3
+/// hash. Implement this trait to keep a replica somewhere else than
+/// [`FileReplicaStore`]; use one store per user.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait ReplicaStore: Debug + Send + Sync {
+    /// Claim the replica for one engine until the store is dropped. Called before
+    /// anything else when the engine opens.
+    ///
+    /// Two engines on one replica would overwrite each other's state, so return
+    /// [`SyncError::Store`](crate::errors::SyncError::Store) if another engine, in
+    /// any process, holds it already. The default does not lock.
+    async fn lock(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Read the state document, or `None` for a new replica.
+    async fn load_state(&self) -> Result<Option<Vec<u8>>>;
+
+    /// Replace the state document.
+    async fn save_state(&self, state: &[u8]) -> Result<()>;
+
+    /// Read the content with `hash`, or `None` if it is not stored.
+    async fn read_content(&self, hash: &Hash) -> Result<Option<Vec<u8>>>;
+
+    /// Store `content` under its `hash`. Storing a hash twice is a no-op.
+    async fn write_content(&self, hash: &Hash, content: &[u8]) -> Result<()>;
+
+    /// Remove the content with `hash`, if stored.
+    async fn delete_content(&self, hash: &Hash) -> Result<()>;
+}
+
+/// Keeps a replica in a directory: `state.json` and one file per content hash
+/// under `content/`.
+///
+/// Files are written to a temporary file first and renamed, so a crash never leaves
+/// a truncated state or content file. An open engine holds an exclusive lock on the
+/// `lock` file.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FileReplicaStore {
+    directory: std::path::PathBuf,
+    /// The locked `lock` file, shared by clones and released with the last one.
+    lock: std::sync::Arc<std::sync::OnceLock<std::fs::File>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileReplicaStore {
+    /// Keep the replica in `directory`, creating it if needed.
+    ///
+    /// # Errors
+    /// - Returns [`SyncError::Store`] if the directory cannot be created.
+    pub fn new(directory: impl Into<std::path::PathBuf>) -> Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(directory.join("content"))
+            .map_err(|e| store_error(&directory, &e))?;
+        Ok(Self {
+            directory,
+            lock: std::sync::Arc::default(),
+        })
+    }
+
+    fn state_path(&self) -> std::path::PathBuf {
+        self.directory.join("state.json")
+    }
+
+    fn content_path(&self, hash: &Hash) -> std::path::PathBuf {
+        self.directory.join("content").join(hash.to_hex().as_str())
+    }
+
+    async fn read(path: &std::path::Path) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(path).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(store_error(path, &e).into()),
+        }
+    }
+
+    async fn write(path: &std::path::Path, bytes: &[u8]) -> Result<()> {
+        let temporary = path.with_extension("tmp");
+        tokio::fs::write(&temporary, bytes)
+            .await
+            .map_err(|e| store_error(&temporary, &e))?;
+        tokio::fs::rename(&temporary, path)
+            .await
+            .map_err(|e| store_error(path, &e).into())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl ReplicaStore for FileReplicaStore {
+    async fn lock(&self) -> Result<()> {
+        let path = self.directory.join("lock");
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| store_error(&path, &e))?;
+        match file.try_lock() {
+            Ok(()) if self.lock.set(file).is_ok() => Ok(()),
+            Ok(()) | Err(std::fs::TryLockError::WouldBlock) => Err(SyncError::Store {
+                message: format!(
+                    "the replica in {} is open in another sync engine",
+                    self.directory.display()
+                ),
+            }
+            .into()),
+            Err(std::fs::TryLockError::Error(e)) => Err(store_error(&path, &e).into()),
+        }
+    }
+
+    async fn load_state(&self) -> Result<Option<Vec<u8>>> {
+        Self::read(&self.state_path()).await
+    }
+
+    async fn save_state(&self, state: &[u8]) -> Result<()> {
+        Self::write(&self.state_path(), state).await
+    }
+
+    async fn read_content(&self, hash: &Hash) -> Result<Option<Vec<u8>>> {
+        Self::read(&self.content_path(hash)).await
+    }
+
+    async fn write_content(&self, hash: &Hash, content: &[u8]) -> Result<()> {
+        let path = self.content_path(hash);
+        if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            return Ok(());
+        }
+        Self::write(&path, content).await
+    }
+
+    async fn delete_content(&self, hash: &Hash) -> Result<()> {
+        let path = self.content_path(hash);
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(store_error(&path, &e).into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn store_error(path: &std::path::Path, error: &std::io::Error) -> SyncError {
+    SyncError::Store {
+        message: format!("{}: {error}", path.display()),
+    }
+}
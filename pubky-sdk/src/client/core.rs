@@ -2,6 +2,7 @@ use std::borrow::Cow;
 use std::fmt::Debug;
 use std::time::Duration;
 
+use super::resolution_cache::{ResolutionCache, RevalidationGuard};
 use crate::{cross_log, errors::BuildError};
 
 const DEFAULT_USER_AGENT: &str = concat!("pubky.org", "@", env!("CARGO_PKG_VERSION"),);
@@ -59,6 +60,9 @@ pub struct PubkyHttpClientBuilder {
     /// Optional user-agent segment appended to the default UA for app-level telemetry.
     user_agent_extra: Option<String>,
 
+    /// Persistent pkarr cache, refreshed in the background by the built client.
+    resolution_cache: Option<ResolutionCache>,
+
     #[cfg(not(target_arch = "wasm32"))]
     native_http: NativeHttpConfig,
 
@@ -185,6 +189,27 @@ impl PubkyHttpClientBuilder {
         self
     }
 
+    /// Resolve pkarr records through a persistent [`ResolutionCache`].
+    ///
+    /// Homeserver mappings and endpoints resolved by the client are kept in the
+    /// cache's store and reused by the next client built from it. Expired records are
+    /// still used while the client refreshes them in the background.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+    /// use pubky::{FileResolutionStore, PubkyHttpClient, ResolutionCache};
+    ///
+    /// let cache = ResolutionCache::new(FileResolutionStore::new("./pkarr-cache")?);
+    /// let client = PubkyHttpClient::builder().resolution_cache(cache).build()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn resolution_cache(&mut self, cache: ResolutionCache) -> &mut Self {
+        self.pkarr.cache(std::sync::Arc::new(cache.clone()));
+        self.resolution_cache = Some(cache);
+        self
+    }
+
     /// Append an extra user-agent segment after the default `pubky.org@<version>`.
     /// Enables app-level telemetry
     /// Example: `.user_agent_extra("myapp/1.2.3")`
@@ -259,8 +284,15 @@ impl PubkyHttpClientBuilder {
             icann_http_builder = icann_http_builder.pool_max_idle_per_host(max);
         }
 
+        let revalidation = self
+            .resolution_cache
+            .as_ref()
+            .map(|cache| std::sync::Arc::new(cache.attach(pkarr.clone())));
+
         Ok(PubkyHttpClient {
             pkarr,
+            _revalidation: revalidation,
+            successors: super::successor_cache::SuccessorCache::default(),
             http: http_builder.build()?,
 
             #[cfg(not(target_arch = "wasm32"))]
@@ -419,6 +451,13 @@ pub struct PubkyHttpClient {
     pub(crate) http: reqwest::Client,
     pub(crate) pkarr: pkarr::Client,
 
+    /// Keeps background refreshes of the [`ResolutionCache`] running while any clone
+    /// of this client is alive.
+    _revalidation: Option<std::sync::Arc<RevalidationGuard>>,
+
+    /// Homeserver key rotations seen recently, shared by clones.
+    pub(crate) successors: super::successor_cache::SuccessorCache,
+
     #[cfg(not(target_arch = "wasm32"))]
     pub(crate) icann_http: reqwest::Client,
 
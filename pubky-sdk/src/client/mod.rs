@@ -1,2 +1,4 @@
 pub mod core;
 mod http_targets;
+pub mod resolution_cache;
+pub(crate) mod successor_cache;
@@ -0,0 +1,782 @@
+//! Persistent cache for resolved pkarr records.
+//!
+//! [`ResolutionCache`] replaces the in-memory cache of the pkarr client, so every
+//! resolution made by the SDK (`_pubky` homeserver mappings, endpoints, records read
+//! through [`crate::Pkdns`]) is served from it and written to a [`ResolutionStore`].
+//! A client created from the same store starts warm instead of resolving every key
+//! from the network again.
+//!
+//! Packets are fresh for their TTL, as in pkarr. Once expired, a packet is still
+//! served for up to [`ResolutionCacheBuilder::max_stale`] while it is refreshed in
+//! the background (stale-while-revalidate).
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::Duration;
+
+use pkarr::{Cache, CacheKey, ResolvePolicy, SignedPacket, Timestamp};
+
+use crate::{PublicKey, cross_log};
+
+/// Default maximum number of packets kept by a [`ResolutionCache`].
+pub const DEFAULT_RESOLUTION_CACHE_CAPACITY: usize = 1000;
+
+/// Default time an expired packet is still served while it is refreshed.
+pub const DEFAULT_MAX_STALE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Minimum time between writes of a packet that was only seen again, unchanged.
+const LAST_SEEN_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Persistent storage behind a [`ResolutionCache`].
+///
+/// Stores hold at most one packet per public key and are best effort: failures are
+/// logged by the store and never fail a resolution. Implement this trait to keep
+/// resolved records somewhere else than [`FileResolutionStore`].
+pub trait ResolutionStore: Debug + Send + Sync {
+    /// Return every stored packet. Called once, when the cache is built.
+    fn load(&self) -> Vec<SignedPacket>;
+
+    /// Store `packet`, replacing the previous packet of its public key.
+    ///
+    /// Called for every new or changed packet. A packet that is only seen again is
+    /// saved at most once a minute, to refresh its last seen time.
+    fn save(&self, packet: &SignedPacket);
+
+    /// Remove the packet of `public_key`, if any.
+    fn remove(&self, public_key: &PublicKey);
+}
+
+/// Decode a packet written with [`SignedPacket::serialize`], or `None` if the bytes
+/// are truncated or malformed.
+///
+/// The signature is verified when the cache loads the packet.
+#[must_use]
+pub fn decode_stored_packet(bytes: &[u8]) -> Option<SignedPacket> {
+    if bytes.len() < 8 {
+        return None;
+    }
+    SignedPacket::deserialize(bytes).ok()
+}
+
+/// Configures a [`ResolutionCache`].
+///
+/// # Example
+/// ```no_run
+/// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// use std::time::Duration;
+/// use pubky::{FileResolutionStore, PubkyHttpClient, ResolutionCache};
+///
+/// let cache = ResolutionCache::builder(FileResolutionStore::new("./pkarr-cache")?)
+///     .capacity(5_000)
+///     .max_stale(Duration::from_secs(7 * 24 * 60 * 60))
+///     .build();
+/// let client = PubkyHttpClient::builder().resolution_cache(cache).build()?;
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+#[must_use]
+pub struct ResolutionCacheBuilder {
+    store: Box<dyn ResolutionStore>,
+    capacity: usize,
+    max_stale: Duration,
+    minimum_ttl: u32,
+    maximum_ttl: u32,
+}
+
+impl ResolutionCacheBuilder {
+    /// Maximum number of packets kept in memory and in the store. Least recently
+    /// seen packets are evicted first. Default: [`DEFAULT_RESOLUTION_CACHE_CAPACITY`].
+    ///
+    /// A capacity of `0` disables the cache.
+    pub const fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// How long after expiring a packet is still served while it is refreshed in
+    /// the background. Older packets are only used as a lower bound for a blocking
+    /// network resolution. Default: [`DEFAULT_MAX_STALE`].
+    ///
+    /// `Duration::ZERO` disables stale-while-revalidate.
+    pub const fn max_stale(mut self, max_stale: Duration) -> Self {
+        self.max_stale = max_stale;
+        self
+    }
+
+    /// TTL bounds in seconds used to decide whether a packet has expired.
+    ///
+    /// Set them to the values given to [`pkarr::ClientBuilder::minimum_ttl`] and
+    /// [`pkarr::ClientBuilder::maximum_ttl`] if those were changed.
+    /// Default: [`pkarr::DEFAULT_MINIMUM_TTL`] and [`pkarr::DEFAULT_MAXIMUM_TTL`].
+    pub fn ttl_bounds(mut self, minimum: u32, maximum: u32) -> Self {
+        self.minimum_ttl = minimum.min(maximum);
+        self.maximum_ttl = maximum;
+        self
+    }
+
+    /// Load the stored packets and build the cache.
+    ///
+    /// Packets with an invalid signature, or stale for longer than
+    /// [`Self::max_stale`], are dropped from the store.
+    #[must_use]
+    pub fn build(self) -> ResolutionCache {
+        let inner = Inner {
+            store: self.store,
+            capacity: self.capacity,
+            max_stale: self.max_stale.as_secs(),
+            minimum_ttl: self.minimum_ttl,
+            maximum_ttl: self.maximum_ttl,
+            state: Mutex::default(),
+            refresher: Mutex::default(),
+        };
+
+        let mut packets: HashMap<CacheKey, SignedPacket> = HashMap::new();
+        let mut dropped = Vec::new();
+        for packet in inner.store.load() {
+            let public_key = packet.public_key();
+            let verified =
+                SignedPacket::from_relay_payload(&public_key, &packet.to_relay_payload());
+            let Ok(mut verified) = verified else {
+                cross_log!(
+                    warn,
+                    "Dropping stored record of {public_key}: invalid signature"
+                );
+                dropped.push(public_key);
+                continue;
+            };
+            // A last seen in the future would never expire.
+            verified.set_last_seen(packet.last_seen().min(&Timestamp::now()));
+            if inner.is_too_stale(&verified) {
+                dropped.push(public_key);
+                continue;
+            }
+            let key = CacheKey::from(&public_key);
+            match packets.get(&key) {
+                Some(existing) if !verified.more_recent_than(existing) => {}
+                _ => {
+                    packets.insert(key, verified);
+                }
+            }
+        }
+        cross_log!(debug, "Loaded {} cached pkarr records", packets.len());
+
+        let evicted = {
+            let mut state = inner.lock_state();
+            state.saved = packets
+                .iter()
+                .map(|(key, packet)| (*key, *packet.last_seen()))
+                .collect();
+            state.packets = packets;
+            state.evict_beyond(inner.capacity)
+        };
+        dropped.extend(evicted);
+        for public_key in dropped {
+            inner.store.remove(&public_key.into());
+        }
+
+        ResolutionCache {
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+/// A [`pkarr::Cache`] that persists resolved packets and serves expired ones while
+/// refreshing them in the background.
+///
+/// Attach it with [`crate::PubkyHttpClientBuilder::resolution_cache`]; the client
+/// then resolves homeservers and endpoints from it. Cloning is cheap and shares the
+/// cache.
+///
+/// Expired packets are only refreshed in the background when the cache is attached
+/// this way, and (natively) when resolving inside a Tokio runtime. Otherwise they are
+/// resolved from the network before use, as with pkarr's default cache.
+#[derive(Debug, Clone)]
+pub struct ResolutionCache {
+    inner: Arc<Inner>,
+}
+
+impl ResolutionCache {
+    /// Start configuring a cache persisted in `store`.
+    pub fn builder(store: impl ResolutionStore + 'static) -> ResolutionCacheBuilder {
+        ResolutionCacheBuilder {
+            store: Box::new(store),
+            capacity: DEFAULT_RESOLUTION_CACHE_CAPACITY,
+            max_stale: DEFAULT_MAX_STALE,
+            minimum_ttl: pkarr::DEFAULT_MINIMUM_TTL,
+            maximum_ttl: pkarr::DEFAULT_MAXIMUM_TTL,
+        }
+    }
+
+    /// Cache persisted in `store`, with default settings.
+    #[must_use]
+    pub fn new(store: impl ResolutionStore + 'static) -> Self {
+        Self::builder(store).build()
+    }
+
+    /// Let `client` refresh expired packets in the background, until the returned
+    /// guard is dropped.
+    ///
+    /// The client owns this cache, so the guard breaks the reference cycle.
+    pub(crate) fn attach(&self, client: pkarr::Client) -> RevalidationGuard {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        *self.inner.lock_refresher() = Some((id, client));
+        RevalidationGuard {
+            inner: Arc::downgrade(&self.inner),
+            id,
+        }
+    }
+
+    /// Refresh `public_key` from the network. The client writes the result back
+    /// into this cache.
+    fn spawn_revalidation(client: pkarr::Client, public_key: pkarr::PublicKey) -> bool {
+        let refresh = async move {
+            match client
+                .resolve(&public_key, ResolvePolicy::NetworkOnly)
+                .await
+            {
+                Ok(_) => {
+                    cross_log!(debug, "Refreshed cached record of {public_key}");
+                }
+                Err(error) => {
+                    cross_log!(
+                        debug,
+                        "Refreshing cached record of {public_key} failed: {error}"
+                    );
+                }
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn(refresh)),
+            Err(_) => return false,
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        wasm_bindgen_futures::spawn_local(refresh);
+
+        true
+    }
+}
+
+impl Cache for ResolutionCache {
+    fn capacity(&self) -> usize {
+        self.inner.capacity
+    }
+
+    fn len(&self) -> usize {
+        self.inner.lock_state().packets.len()
+    }
+
+    fn put(&self, key: &CacheKey, signed_packet: &SignedPacket) {
+        let (evicted, to_save) = {
+            let mut guard = self.inner.lock_state();
+            let state = &mut *guard;
+            state.revalidating.remove(key);
+            let to_save = match state.packets.get_mut(key) {
+                Some(existing) if existing.is_same_as(signed_packet) => {
+                    if signed_packet.last_seen() > existing.last_seen() {
+                        existing.set_last_seen(signed_packet.last_seen());
+                    }
+                    // Only the last seen changed; the stored copy is good enough for a while.
+                    let saved = state.saved.get(key).map_or(0, Timestamp::as_u64);
+                    let unsaved = existing.last_seen().as_u64().saturating_sub(saved);
+                    (u128::from(unsaved) >= LAST_SEEN_SAVE_INTERVAL.as_micros())
+                        .then(|| existing.clone())
+                }
+                _ => {
+                    state.packets.insert(*key, signed_packet.clone());
+                    Some(signed_packet.clone())
+                }
+            };
+            if let Some(packet) = &to_save {
+                state.saved.insert(*key, *packet.last_seen());
+            }
+            let evicted = state.evict_beyond(self.inner.capacity);
+            // Do not write back a packet that was evicted right away.
+            (evicted, to_save.filter(|_| state.packets.contains_key(key)))
+        };
+
+        for public_key in evicted {
+            self.inner.store.remove(&public_key.into());
+        }
+        if let Some(packet) = to_save {
+            self.inner.store.save(&packet);
+        }
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<SignedPacket> {
+        let inner = &self.inner;
+        let mut state = inner.lock_state();
+        let packet = state.packets.get(key)?.clone();
+        if !inner.is_expired(&packet) || inner.is_too_stale(&packet) {
+            return Some(packet);
+        }
+
+        // Keep serving the packet as fresh while a refresh is pending. If the refresh
+        // failed, the served copy expires after one TTL and the next read retries.
+        if let Some(served) = state.revalidating.get(key) {
+            let mut served_packet = packet.clone();
+            served_packet.set_last_seen(served);
+            if !inner.is_expired(&served_packet) {
+                return Some(served_packet);
+            }
+        }
+
+        let Some(client) = inner.lock_refresher().as_ref().map(|(_, c)| c.clone()) else {
+            return Some(packet);
+        };
+        if !Self::spawn_revalidation(client, packet.public_key()) {
+            return Some(packet);
+        }
+        cross_log!(
+            debug,
+            "Serving stale record of {} while refreshing it",
+            packet.public_key()
+        );
+        let served = Timestamp::now();
+        state.revalidating.insert(*key, served);
+        let mut served_packet = packet;
+        served_packet.set_last_seen(&served);
+        Some(served_packet)
+    }
+
+    fn get_read_only(&self, key: &CacheKey) -> Option<SignedPacket> {
+        let state = self.inner.lock_state();
+        let mut packet = state.packets.get(key)?.clone();
+        // pkarr writes every resolved packet back; this makes it ignore the copy
+        // served by `get`, while a refreshed packet is seen later and replaces it.
+        if let Some(served) = state.revalidating.get(key) {
+            packet.set_last_seen(served);
+        }
+        Some(packet)
+    }
+}
+
+/// Stops background revalidation through a client when dropped.
+#[derive(Debug)]
+pub(crate) struct RevalidationGuard {
+    inner: Weak<Inner>,
+    id: u64,
+}
+
+impl Drop for RevalidationGuard {
+    fn drop(&mut self) {
+        let Some(inner) = self.inner.upgrade() else {
+            return;
+        };
+        let mut refresher = inner.lock_refresher();
+        if refresher.as_ref().is_some_and(|(id, _)| *id == self.id) {
+            *refresher = None;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    store: Box<dyn ResolutionStore>,
+    capacity: usize,
+    /// Seconds.
+    max_stale: u64,
+    minimum_ttl: u32,
+    maximum_ttl: u32,
+    state: Mutex<State>,
+    /// Client used for background refreshes, with the id of its guard.
+    refresher: Mutex<Option<(u64, pkarr::Client)>>,
+}
+
+impl Inner {
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_refresher(&self) -> std::sync::MutexGuard<'_, Option<(u64, pkarr::Client)>> {
+        self.refresher
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn is_expired(&self, packet: &SignedPacket) -> bool {
+        packet.is_expired(self.minimum_ttl, self.maximum_ttl)
+    }
+
+    fn is_too_stale(&self, packet: &SignedPacket) -> bool {
+        let ttl = packet.ttl(self.minimum_ttl, self.maximum_ttl);
+        u64::from(packet.elapsed().saturating_sub(ttl)) > self.max_stale
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    packets: HashMap<CacheKey, SignedPacket>,
+    /// Keys served stale while being refreshed, with the last seen they were served with.
+    revalidating: HashMap<CacheKey, Timestamp>,
+    /// Last seen of the copy of each packet in the store.
+    saved: HashMap<CacheKey, Timestamp>,
+}
+
+impl State {
+    /// Drop the least recently seen packets until at most `capacity` remain.
+    fn evict_beyond(&mut self, capacity: usize) -> Vec<pkarr::PublicKey> {
+        let mut evicted = Vec::new();
+        while self.packets.len() > capacity {
+            let Some(key) = self
+                .packets
+                .iter()
+                .min_by_key(|(_, packet)| *packet.last_seen())
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            self.revalidating.remove(&key);
+            self.saved.remove(&key);
+            if let Some(packet) = self.packets.remove(&key) {
+                evicted.push(packet.public_key());
+            }
+        }
+        evicted
+    }
+}
+
+/// Stores one file per public key in a directory, named `<z32>.packet`.
+///
+/// Writes and removals run in order on a background thread, so resolutions never
+/// wait on the disk. Each write goes to a uniquely named temporary file first, so a
+/// crash never leaves a truncated packet and concurrent writers never share one.
+/// Cloning is cheap and shares the background thread.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct FileResolutionStore {
+    directory: std::path::PathBuf,
+    writer: std::sync::mpsc::Sender<FileStoreOp>,
+}
+
+/// Work for the background thread of a [`FileResolutionStore`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+enum FileStoreOp {
+    Save(std::path::PathBuf, Vec<u8>),
+    Remove(std::path::PathBuf),
+    /// Acknowledged once every earlier operation is done.
+    Flush(std::sync::mpsc::Sender<()>),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStoreOp {
+    fn apply(self) {
+        match self {
+            Self::Save(path, bytes) => {
+                static TEMPORARY_ID: AtomicU64 = AtomicU64::new(0);
+                let temporary = path.with_extension(format!(
+                    "{}.{}.tmp",
+                    std::process::id(),
+                    TEMPORARY_ID.fetch_add(1, Ordering::Relaxed)
+                ));
+                let written = std::fs::write(&temporary, bytes)
+                    .and_then(|()| std::fs::rename(&temporary, &path));
+                if let Err(error) = written {
+                    let _ = std::fs::remove_file(&temporary);
+                    cross_log!(
+                        warn,
+                        "Writing cached record {} failed: {error}",
+                        path.display()
+                    );
+                }
+            }
+            Self::Remove(path) => match std::fs::remove_file(&path) {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                    cross_log!(
+                        warn,
+                        "Removing cached record {} failed: {error}",
+                        path.display()
+                    );
+                }
+                _ => {}
+            },
+            Self::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileResolutionStore {
+    const EXTENSION: &str = "packet";
+
+    /// Store packets in `directory`, creating it if needed.
+    ///
+    /// # Errors
+    /// - Returns an I/O error if the directory cannot be created or the background
+    ///   thread cannot be started.
+    pub fn new(directory: impl Into<std::path::PathBuf>) -> std::io::Result<Self> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+        let (writer, ops) = std::sync::mpsc::channel::<FileStoreOp>();
+        // Ends once every clone of the store is dropped and the queue is drained.
+        std::thread::Builder::new()
+            .name("pubky-resolution-store".into())
+            .spawn(move || ops.into_iter().for_each(FileStoreOp::apply))?;
+        Ok(Self { directory, writer })
+    }
+
+    fn path(&self, public_key: &PublicKey) -> std::path::PathBuf {
+        self.directory
+            .join(format!("{}.{}", public_key.z32(), Self::EXTENSION))
+    }
+
+    /// Wait until every queued write and removal is done.
+    fn flush(&self) {
+        let (done, flushed) = std::sync::mpsc::channel();
+        if self.writer.send(FileStoreOp::Flush(done)).is_ok() {
+            let _ = flushed.recv();
+        }
+    }
+
+    fn queue(&self, op: FileStoreOp) {
+        if self.writer.send(op).is_err() {
+            cross_log!(warn, "Resolution store writer stopped; dropping a write");
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ResolutionStore for FileResolutionStore {
+    fn load(&self) -> Vec<SignedPacket> {
+        self.flush();
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) => {
+                cross_log!(warn, "Reading {} failed: {error}", self.directory.display());
+                return Vec::new();
+            }
+        };
+        entries
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == Self::EXTENSION))
+            .filter_map(|path| {
+                let packet = std::fs::read(&path)
+                    .ok()
+                    .and_then(|bytes| decode_stored_packet(&bytes));
+                if packet.is_none() {
+                    cross_log!(warn, "Ignoring unreadable cached record {}", path.display());
+                }
+                packet
+            })
+            .collect()
+    }
+
+    fn save(&self, packet: &SignedPacket) {
+        let path = self.path(&packet.public_key().into());
+        self.queue(FileStoreOp::Save(path, packet.serialize()));
+    }
+
+    fn remove(&self, public_key: &PublicKey) {
+        self.queue(FileStoreOp::Remove(self.path(public_key)));
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use httpmock::MockServer;
+    use pubky_common::crypto::Keypair;
+
+    use super::*;
+    use crate::PubkyHttpClient;
+
+    fn packet(keypair: &Keypair, ip: [u8; 4], age: Duration) -> SignedPacket {
+        let mut packet = SignedPacket::builder()
+            .a("_pubky".try_into().unwrap(), ip.into(), 300)
+            .sign(keypair.as_inner())
+            .unwrap();
+        let age = u64::try_from(age.as_micros()).unwrap();
+        packet.set_last_seen(&(Timestamp::now() - age));
+        packet
+    }
+
+    fn temp_store() -> (FileResolutionStore, std::path::PathBuf) {
+        let directory = std::env::temp_dir().join(format!(
+            "pubky-resolution-cache-{}",
+            Keypair::random().public_key().z32()
+        ));
+        (FileResolutionStore::new(&directory).unwrap(), directory)
+    }
+
+    fn key_of(packet: &SignedPacket) -> CacheKey {
+        CacheKey::from(packet.public_key())
+    }
+
+    #[test]
+    fn packets_survive_a_restart() {
+        let (store, directory) = temp_store();
+        let fresh = packet(&Keypair::random(), [1, 1, 1, 1], Duration::from_secs(10));
+        let too_stale = packet(&Keypair::random(), [2, 2, 2, 2], Duration::from_secs(7200));
+
+        let cache = ResolutionCache::builder(store.clone())
+            .max_stale(Duration::from_secs(3600))
+            .build();
+        cache.put(&key_of(&fresh), &fresh);
+        cache.put(&key_of(&too_stale), &too_stale);
+        std::fs::write(directory.join("garbage.packet"), b"garbage").unwrap();
+        drop(cache);
+
+        let restarted = ResolutionCache::builder(store.clone())
+            .max_stale(Duration::from_secs(3600))
+            .build();
+        store.flush();
+        assert_eq!(restarted.len(), 1);
+        let loaded = restarted.get(&key_of(&fresh)).unwrap();
+        assert!(loaded.is_same_as(&fresh));
+        assert_eq!(loaded.last_seen(), fresh.last_seen());
+        assert!(
+            !directory
+                .join(format!("{}.packet", too_stale.public_key()))
+                .exists()
+        );
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[derive(Debug, Default, Clone)]
+    struct CountingStore {
+        saves: Arc<AtomicU64>,
+    }
+
+    impl ResolutionStore for CountingStore {
+        fn load(&self) -> Vec<SignedPacket> {
+            Vec::new()
+        }
+
+        fn save(&self, _packet: &SignedPacket) {
+            self.saves.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn remove(&self, _public_key: &PublicKey) {}
+    }
+
+    #[test]
+    fn packets_seen_again_are_saved_at_most_once_a_minute() {
+        let store = CountingStore::default();
+        let cache = ResolutionCache::new(store.clone());
+        let keypair = Keypair::random();
+        let saves = || store.saves.load(Ordering::Relaxed);
+
+        let seen = packet(&keypair, [1, 1, 1, 1], Duration::from_secs(300));
+        cache.put(&key_of(&seen), &seen);
+        assert_eq!(saves(), 1);
+
+        let mut seen_again = seen.clone();
+        seen_again.set_last_seen(&(*seen.last_seen() + 10_000_000));
+        cache.put(&key_of(&seen_again), &seen_again);
+        assert_eq!(saves(), 1);
+        assert_eq!(
+            cache.get(&key_of(&seen)).unwrap().last_seen(),
+            seen_again.last_seen()
+        );
+
+        seen_again.set_last_seen(&(*seen.last_seen() + 61_000_000));
+        cache.put(&key_of(&seen_again), &seen_again);
+        assert_eq!(saves(), 2);
+
+        let changed = packet(&keypair, [2, 2, 2, 2], Duration::from_secs(1));
+        cache.put(&key_of(&changed), &changed);
+        assert_eq!(saves(), 3);
+    }
+
+    #[test]
+    fn least_recently_seen_packets_are_evicted() {
+        let (store, directory) = temp_store();
+        let cache = ResolutionCache::builder(store.clone()).capacity(2).build();
+        let packets: Vec<_> = [30, 10, 20]
+            .into_iter()
+            .map(|age| packet(&Keypair::random(), [1, 1, 1, 1], Duration::from_secs(age)))
+            .collect();
+        for packet in &packets {
+            cache.put(&key_of(packet), packet);
+        }
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get(&key_of(&packets[0])).is_none());
+        assert_eq!(store.load().len(), 2);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_record_is_served_while_refreshed_in_background() {
+        let keypair = Keypair::random();
+        let stale = packet(&keypair, [1, 1, 1, 1], Duration::from_secs(600));
+        let refreshed = SignedPacket::builder()
+            .a("_pubky".try_into().unwrap(), [2, 2, 2, 2].into(), 300)
+            .timestamp(stale.timestamp() + 1)
+            .sign(keypair.as_inner())
+            .unwrap();
+        let relay = MockServer::start();
+        let mock = relay.mock(|when, then| {
+            when.method("GET")
+                .path(format!("/{}", keypair.public_key().z32()));
+            then.status(200).body(refreshed.to_relay_payload());
+        });
+
+        let (store, directory) = temp_store();
+        let cache = ResolutionCache::new(store.clone());
+        cache.put(&key_of(&stale), &stale);
+        let client = PubkyHttpClient::builder()
+            .pkarr(|builder| {
+                builder
+                    .no_default_network()
+                    .relays(&[relay.base_url()])
+                    .expect("relay url")
+            })
+            .resolution_cache(cache.clone())
+            .build()
+            .unwrap();
+
+        let served = client
+            .pkarr()
+            .resolve(&keypair.public_key().into(), ResolvePolicy::CacheFirst)
+            .await
+            .unwrap();
+        assert!(served.is_same_as(&stale));
+        assert!(!served.is_expired(pkarr::DEFAULT_MINIMUM_TTL, pkarr::DEFAULT_MAXIMUM_TTL));
+
+        for _ in 0..100 {
+            if cache
+                .get(&key_of(&stale))
+                .is_some_and(|packet| packet.is_same_as(&refreshed))
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        mock.assert();
+        assert!(cache.get(&key_of(&stale)).unwrap().is_same_as(&refreshed));
+        assert!(store.load()[0].is_same_as(&refreshed));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn dropping_the_client_stops_revalidation() {
+        let (store, directory) = temp_store();
+        let stale = packet(&Keypair::random(), [1, 1, 1, 1], Duration::from_secs(600));
+        let cache = ResolutionCache::new(store);
+        cache.put(&key_of(&stale), &stale);
+        let client = PubkyHttpClient::builder()
+            .isolated_pkarr_test()
+            .resolution_cache(cache.clone())
+            .build()
+            .unwrap();
+        drop(client);
+
+        // Without a client to refresh it, the expired packet is returned as is.
+        let packet = cache.get(&key_of(&stale)).unwrap();
+        assert_eq!(packet.last_seen(), stale.last_seen());
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+//! Memo of homeserver `_successor` lookups.
+
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::PublicKey;
+
+/// Successor of a homeserver key, and when that answer expires.
+type Entry = (Instant, Option<PublicKey>);
+
+/// Remembers which key each homeserver key rotated to, if any.
+///
+/// Following `_successor` records needs a resolve of the homeserver key on every
+/// user resolution. Answers are kept for the TTL of the packet they came from, so
+/// each homeserver costs one lookup per TTL rather than one per user.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SuccessorCache {
+    entries: Arc<RwLock<HashMap<PublicKey, Entry>>>,
+}
+
+impl SuccessorCache {
+    /// The remembered successor of `homeserver`, or `None` if unknown or expired.
+    #[expect(
+        clippy::option_option,
+        reason = "`Some(None)`: known to have no successor"
+    )]
+    pub(crate) fn get(&self, homeserver: &PublicKey) -> Option<Option<PublicKey>> {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        entries
+            .get(homeserver)
+            .filter(|(expires_at, _)| Instant::now() < *expires_at)
+            .map(|(_, successor)| successor.clone())
+    }
+
+    /// Remember the successor of `homeserver` for `ttl`.
+    pub(crate) fn insert(
+        &self,
+        homeserver: PublicKey,
+        successor: Option<PublicKey>,
+        ttl: Duration,
+    ) {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        entries.retain(|_, (expires_at, _)| now < *expires_at);
+        entries.insert(homeserver, (now + ttl, successor));
+    }
+}
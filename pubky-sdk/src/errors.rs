@@ -31,6 +31,7 @@ pub enum BuildError {
 /// - [`Error::Parse`] — URL parsing failures
 /// - [`Error::Authentication`] — auth/session/token/crypto issues
 /// - [`Error::Build`] — construction of the client failed
+/// - [`Error::Sync`] — the local replica of a [`crate::SyncEngine`] failed
 ///
 /// Most lower-level errors automatically convert into this enum via `From`.
 #[derive(Debug, Error)]
@@ -54,6 +55,10 @@ pub enum Error {
     /// Building the client failed (reqwest or pkarr configuration).
     #[error("Client build failed: {0}")]
     Build(#[from] BuildError),
+
+    /// Local replica operation of a [`crate::SyncEngine`] failed.
+    #[error("Sync failed: {0}")]
+    Sync(#[from] SyncError),
 }
 
 // --- Pkarr Operational Errors ---
@@ -80,6 +85,13 @@ pub enum PkarrError {
     /// Record was present but malformed or missing required fields.
     #[error("Pkarr record is malformed or missing required data: {0}")]
     InvalidRecord(String),
+
+    /// The record kept changing concurrently while trying to update it.
+    #[error("Pkarr record was updated concurrently {attempts} times; giving up")]
+    ConcurrentUpdate {
+        /// Number of update attempts made.
+        attempts: u32,
+    },
 }
 
 impl PkarrError {
@@ -116,6 +128,33 @@ pub enum AuthError {
     RequestExpired,
 }
 
+// --- Sync Engine Errors ---
+
+/// Errors of the local replica kept by a [`crate::SyncEngine`].
+#[derive(Debug, Error)]
+pub enum SyncError {
+    /// Reading or writing the [`crate::ReplicaStore`] failed, or its content is unusable.
+    #[error("Replica store failed: {message}")]
+    Store {
+        /// What failed, with the underlying error.
+        message: String,
+    },
+
+    /// The path lies outside the prefixes the engine replicates.
+    #[error("{path} is outside the replicated prefixes")]
+    OutsideReplica {
+        /// The rejected path.
+        path: String,
+    },
+
+    /// [`crate::SyncEngine::resolve_conflict`] was called for a path without a conflict.
+    #[error("{path} has no conflict to resolve")]
+    NoConflict {
+        /// The path without a conflict.
+        path: String,
+    },
+}
+
 // --- Consolidated Request Error ---
 
 /// Transport and server-side HTTP errors.
@@ -147,6 +186,13 @@ pub enum RequestError {
         /// Error message from the JSON deserializer (with context if available).
         message: String,
     },
+
+    /// The content served by a homeserver does not match its content hash.
+    #[error("Content hash mismatch: {message}")]
+    ContentHashMismatch {
+        /// Which homeserver served the content and why it was rejected.
+        message: String,
+    },
 }
 
 /// A specialized `Result` type for `pubky` operations.
@@ -29,9 +29,20 @@ pub use pubky::Pubky;
 // Transport
 #[doc(inline)]
 pub use client::core::{PubkyHttpClient, PubkyHttpClientBuilder};
+#[cfg(not(target_arch = "wasm32"))]
+#[doc(inline)]
+pub use client::resolution_cache::FileResolutionStore;
+#[doc(inline)]
+pub use client::resolution_cache::{
+    DEFAULT_MAX_STALE, DEFAULT_RESOLUTION_CACHE_CAPACITY, ResolutionCache, ResolutionCacheBuilder,
+    ResolutionStore, decode_stored_packet,
+};
 // High level actors
 #[doc(inline)]
 pub use actors::AuthFlowKind;
+#[cfg(not(target_arch = "wasm32"))]
+#[doc(inline)]
+pub use actors::FileReplicaStore;
 #[doc(inline)]
 pub use actors::Pkdns;
 #[doc(inline)]
@@ -46,6 +57,10 @@ pub use actors::SessionInfo;
 #[doc(inline)]
 pub use actors::deep_links;
 #[doc(inline)]
+pub use actors::{
+    ConflictResolution, ReplicaStore, SyncConflict, SyncEngine, SyncEngineBuilder, SyncReport,
+};
+#[doc(inline)]
 pub use actors::{
     CookieCredential, CookieSessionView, DelegatedGrantCredentialState, GrantCredential,
     GrantManager, GrantSessionView,
@@ -77,6 +92,8 @@ pub use crate::actors::storage::{
 pub use actors::DEFAULT_HTTP_RELAY;
 pub use actors::pkdns::DEFAULT_STALE_AFTER;
 #[doc(inline)]
+pub use actors::pkdns::{DEFAULT_RECORD_TTL, DnsRecord, RecordChanges, RecordData, ServiceBinding};
+#[doc(inline)]
 pub use actors::{DEFAULT_HTTP_RELAY_INBOX, EncryptedHttpRelayInboxChannel, HttpRelayInboxChannel};
 #[doc(hidden)]
 pub use actors::{DelegatedSignFn, delegated_sign_callback};
@@ -97,7 +114,9 @@ pub use pubky_common::{
     capabilities::{Capabilities, Capability},
     crypto::{Keypair, PublicKey},
     recovery_file,
+    republish_status::{RepublishOutcome, RepublishStatus},
     session::CookieSessionRecord,
+    storage_usage::{PrefixUsage, StorageUsage},
 };
 pub use reqwest::{Method, StatusCode};
 
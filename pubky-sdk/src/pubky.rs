@@ -278,6 +278,19 @@ impl Pubky {
             .await
     }
 
+    /// Resolve all homeservers of a user public key via Pkarr, primary first.
+    ///
+    /// Returns an empty list when no record exists or no homeserver is configured.
+    ///
+    /// # Errors
+    /// - [`crate::errors::Error::Pkarr`] if Pkarr resolution fails or a resolved `_pubky`
+    ///   target is malformed (see [`Pkdns::get_homeservers_of`]).
+    pub async fn get_homeservers_of(&self, user_public_key: &PublicKey) -> Result<Vec<PublicKey>> {
+        Pkdns::with_client(self.client.clone())
+            .get_homeservers_of(user_public_key)
+            .await
+    }
+
     /// Create an event stream builder for a single user.
     ///
     /// This is the simplest way to subscribe to events for one user. The homeserver
@@ -458,6 +458,10 @@ impl DataDir for TestnetDataDir {
         }
         self.inner.read_or_create_keypair()
     }
+
+    fn read_key_rotation(&self) -> anyhow::Result<Option<pubky_homeserver::KeyRotation>> {
+        self.inner.read_key_rotation()
+    }
 }
 
 #[cfg(test)]